@@ -0,0 +1,63 @@
+/*
+ * C ABI of naga-wasm, built with `--features c-abi`. See src/ffi.rs.
+ *
+ * Text in is UTF-8 by pointer and length. Results come back in a
+ * MetisBuffer the library allocated, which the caller frees with
+ * metis_buffer_free; on failure it holds a JSON diagnostic,
+ * {"severity": "error", "message": "..."}. Internal errors (panics) never
+ * unwind into the caller; they come back as METIS_ERROR.
+ */
+#ifndef METIS_H
+#define METIS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define METIS_ABI_VERSION 1
+
+#define METIS_OK 0
+#define METIS_ERROR 1
+#define METIS_INVALID_ARGUMENT 2
+
+typedef struct MetisBuffer {
+    uint8_t *data;
+    size_t len;
+} MetisBuffer;
+
+uint32_t metis_abi_version(void);
+
+/* `len` zeroed bytes, for a wasm host to copy input into. */
+MetisBuffer metis_alloc(size_t len);
+void metis_buffer_free(MetisBuffer buffer);
+
+/* On success `out` is empty. */
+int32_t metis_validate(const uint8_t *wgsl, size_t wgsl_len, MetisBuffer *out);
+
+/* `out` gets the JSON reflectWgsl returns. */
+int32_t metis_reflect(const uint8_t *wgsl, size_t wgsl_len, MetisBuffer *out);
+
+/*
+ * `request` is JSON: { "target": "spirv" | "msl" | "hlsl" | "glsl",
+ * "entryPoint"?, "preset"?, "stage"?, "options"? }, `options` being the
+ * target's options object from the JS API. `out` gets SPIR-V as binary,
+ * the rest as UTF-8 text.
+ */
+int32_t metis_compile(const uint8_t *wgsl, size_t wgsl_len,
+                      const uint8_t *request, size_t request_len,
+                      MetisBuffer *out);
+
+/*
+ * `jobs` is a JSON array of compileBatch jobs; `out` gets the JSON of its
+ * result, in job order. Failed jobs are reported there, not as a status.
+ */
+int32_t metis_compile_batch(const uint8_t *jobs, size_t jobs_len, MetisBuffer *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* METIS_H */
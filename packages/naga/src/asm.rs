@@ -0,0 +1,717 @@
+use std::collections::{HashMap, HashSet};
+
+use rspirv::grammar::{
+    CoreInstructionTable, GlslStd450InstructionTable, Instruction, OperandKind, OperandQuantifier,
+};
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::spv;
+
+// ============================================================================
+// SPIR-V Assembler
+// ============================================================================
+//
+// Reads the spirv-dis dialect `spirvBinToText` prints: one instruction per
+// line, `%result = OpName operands`, `;` comments, enumerants and masks by
+// name (`Aligned|Volatile`), `%ids` either numbered or named. Operands are
+// encoded by the instruction's grammar, so `OpConstant` literals follow the
+// width of their result type. Numbered ids keep their number; named ones get
+// the next free ids in order of appearance.
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Id(String),
+    Str(String),
+    Word(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Id(name) => format!("%{name}"),
+            Token::Str(s) => format!("{s:?}"),
+            Token::Word(word) => word.clone(),
+        }
+    }
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => s.push('\n'),
+                        Some('t') => s.push('\t'),
+                        Some('0') => s.push('\0'),
+                        Some(other) => s.push(other),
+                        None => return Err("Unterminated string".to_string()),
+                    },
+                    Some(other) => s.push(other),
+                    None => return Err("Unterminated string".to_string()),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == ';' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            match word.strip_prefix('%') {
+                Some("") => return Err("Empty id name".to_string()),
+                Some(name) => tokens.push(Token::Id(name.to_string())),
+                None => tokens.push(Token::Word(word)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Line {
+    number: usize,
+    grammar: &'static Instruction<'static>,
+    result: Option<String>,
+    operands: Vec<Token>,
+}
+
+fn parse_line(number: usize, text: &str) -> Result<Option<Line>, String> {
+    let mut tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let result = match (&tokens[0], tokens.get(1)) {
+        (Token::Id(name), Some(Token::Word(eq))) if eq == "=" => {
+            let name = name.clone();
+            tokens.drain(..2);
+            Some(name)
+        }
+        _ => None,
+    };
+    let grammar = match tokens.first() {
+        Some(Token::Word(opname)) => lookup_op(opname)?,
+        Some(other) => {
+            return Err(format!(
+                "Expected an instruction, found '{}'",
+                other.describe()
+            ));
+        }
+        None => return Err("Expected an instruction after '='".to_string()),
+    };
+    tokens.remove(0);
+    Ok(Some(Line {
+        number,
+        grammar,
+        result,
+        operands: tokens,
+    }))
+}
+
+fn lookup_op(opname: &str) -> Result<&'static Instruction<'static>, String> {
+    opname
+        .strip_prefix("Op")
+        .and_then(|name| CoreInstructionTable::iter().find(|inst| inst.opname == name))
+        .ok_or_else(|| format!("Unknown instruction '{opname}'"))
+}
+
+/// Numbered ids are reserved first, so named ones never collide with them.
+fn assign_ids(lines: &[Line]) -> HashMap<String, u32> {
+    let names = lines.iter().flat_map(|line| {
+        line.result
+            .iter()
+            .chain(line.operands.iter().filter_map(|token| match token {
+                Token::Id(name) => Some(name),
+                _ => None,
+            }))
+    });
+    let numbered: HashSet<u32> = names
+        .clone()
+        .filter_map(|name| name.parse().ok())
+        .filter(|&id| id != 0)
+        .collect();
+
+    let mut ids = HashMap::new();
+    let mut next = 1;
+    for name in names {
+        if ids.contains_key(name) {
+            continue;
+        }
+        let id = match name.parse::<u32>() {
+            Ok(id) if id != 0 => id,
+            _ => {
+                while numbered.contains(&next) {
+                    next += 1;
+                }
+                next += 1;
+                next - 1
+            }
+        };
+        ids.insert(name.clone(), id);
+    }
+    ids
+}
+
+#[derive(Clone, Copy)]
+enum Numeric {
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+}
+
+fn parse_int(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn literal_word(text: &str) -> Result<u32, String> {
+    match parse_int(text) {
+        Some(value) if (i32::MIN as i128..=u32::MAX as i128).contains(&value) => Ok(value as u32),
+        _ => Err(format!("Expected a 32-bit integer, found '{text}'")),
+    }
+}
+
+/// Words of a literal whose encoding depends on its type. Hex float
+/// literals are taken as raw bits, which is how 16-bit floats are written.
+fn number_words(text: &str, numeric: Numeric) -> Result<Vec<u32>, String> {
+    let wide = |bits: u64| vec![bits as u32, (bits >> 32) as u32];
+    match numeric {
+        Numeric::Int { width, signed } => {
+            let value =
+                parse_int(text).ok_or_else(|| format!("Expected an integer, found '{text}'"))?;
+            // Hex literals spell out the bits, so take the unsigned range.
+            let (min, max) = if signed && !text.starts_with("0x") {
+                (-(1i128 << (width - 1)), (1i128 << (width - 1)) - 1)
+            } else {
+                (0, (1i128 << width) - 1)
+            };
+            if !(min..=max).contains(&value) {
+                return Err(format!("'{text}' does not fit a {width}-bit integer"));
+            }
+            Ok(if width > 32 {
+                wide(value as u64)
+            } else {
+                // Narrower signed values are sign-extended to the word.
+                vec![value as u32]
+            })
+        }
+        Numeric::Float { width } => {
+            if let Some(hex) = text.strip_prefix("0x") {
+                let bits = u64::from_str_radix(hex, 16)
+                    .map_err(|_| format!("Expected float bits, found '{text}'"))?;
+                return Ok(if width > 32 {
+                    wide(bits)
+                } else {
+                    vec![bits as u32]
+                });
+            }
+            match width {
+                32 => text.parse::<f32>().map(|v| vec![v.to_bits()]),
+                64 => text.parse::<f64>().map(|v| wide(v.to_bits())),
+                _ => {
+                    return Err(format!(
+                        "{width}-bit float literals must be written as hex bits"
+                    ));
+                }
+            }
+            .map_err(|_| format!("Expected a float, found '{text}'"))
+        }
+    }
+}
+
+fn same_name(flag: &str, name: &str) -> bool {
+    flag.len() == name.len() + flag.matches('_').count()
+        && flag.replace('_', "").eq_ignore_ascii_case(name)
+}
+
+/// Value of the enumerant or `|`-joined mask `text` of operand kind `kind`.
+/// Raw numbers are accepted for any kind.
+fn enumerant(kind: OperandKind, text: &str) -> Option<u32> {
+    if let Some(value) = parse_int(text) {
+        return u32::try_from(value).ok();
+    }
+    macro_rules! value {
+        ($ty:ty) => {
+            text.parse::<$ty>().ok().map(|v| v as u32)
+        };
+    }
+    macro_rules! mask {
+        ($ty:ty) => {
+            text.split('|').try_fold(0, |bits, name| {
+                if name == "None" {
+                    return Some(bits);
+                }
+                <$ty>::all()
+                    .iter_names()
+                    .find(|(flag, _)| same_name(flag, name))
+                    .map(|(_, flag)| bits | flag.bits())
+            })
+        };
+    }
+    use spirv as s;
+    match kind {
+        OperandKind::ImageOperands => mask!(s::ImageOperands),
+        OperandKind::FPFastMathMode => mask!(s::FPFastMathMode),
+        OperandKind::SelectionControl => mask!(s::SelectionControl),
+        OperandKind::LoopControl => mask!(s::LoopControl),
+        OperandKind::FunctionControl => mask!(s::FunctionControl),
+        OperandKind::MemorySemantics => mask!(s::MemorySemantics),
+        OperandKind::MemoryAccess => mask!(s::MemoryAccess),
+        OperandKind::KernelProfilingInfo => mask!(s::KernelProfilingInfo),
+        OperandKind::RayFlags => mask!(s::RayFlags),
+        OperandKind::FragmentShadingRate => mask!(s::FragmentShadingRate),
+        OperandKind::CooperativeMatrixOperands => mask!(s::CooperativeMatrixOperands),
+        OperandKind::SourceLanguage => value!(s::SourceLanguage),
+        OperandKind::ExecutionModel => value!(s::ExecutionModel),
+        OperandKind::AddressingModel => value!(s::AddressingModel),
+        OperandKind::MemoryModel => value!(s::MemoryModel),
+        OperandKind::ExecutionMode => value!(s::ExecutionMode),
+        OperandKind::StorageClass => value!(s::StorageClass),
+        // spirv-dis writes `2D`, the Rust enum `Dim2D`.
+        OperandKind::Dim => format!("Dim{text}")
+            .parse::<s::Dim>()
+            .ok()
+            .map(|v| v as u32)
+            .or_else(|| value!(s::Dim)),
+        OperandKind::SamplerAddressingMode => value!(s::SamplerAddressingMode),
+        OperandKind::SamplerFilterMode => value!(s::SamplerFilterMode),
+        OperandKind::ImageFormat => value!(s::ImageFormat),
+        OperandKind::ImageChannelOrder => value!(s::ImageChannelOrder),
+        OperandKind::ImageChannelDataType => value!(s::ImageChannelDataType),
+        OperandKind::FPRoundingMode => value!(s::FPRoundingMode),
+        OperandKind::FPDenormMode => value!(s::FPDenormMode),
+        OperandKind::QuantizationModes => value!(s::QuantizationModes),
+        OperandKind::FPOperationMode => value!(s::FPOperationMode),
+        OperandKind::OverflowModes => value!(s::OverflowModes),
+        OperandKind::LinkageType => value!(s::LinkageType),
+        OperandKind::AccessQualifier => value!(s::AccessQualifier),
+        OperandKind::HostAccessQualifier => value!(s::HostAccessQualifier),
+        OperandKind::FunctionParameterAttribute => value!(s::FunctionParameterAttribute),
+        OperandKind::Decoration => value!(s::Decoration),
+        OperandKind::BuiltIn => value!(s::BuiltIn),
+        OperandKind::Scope => value!(s::Scope),
+        OperandKind::GroupOperation => value!(s::GroupOperation),
+        OperandKind::KernelEnqueueFlags => value!(s::KernelEnqueueFlags),
+        OperandKind::Capability => value!(s::Capability),
+        OperandKind::RayQueryIntersection => value!(s::RayQueryIntersection),
+        OperandKind::RayQueryCommittedIntersectionType => {
+            value!(s::RayQueryCommittedIntersectionType)
+        }
+        OperandKind::RayQueryCandidateIntersectionType => {
+            value!(s::RayQueryCandidateIntersectionType)
+        }
+        OperandKind::PackedVectorFormat => value!(s::PackedVectorFormat),
+        OperandKind::CooperativeMatrixLayout => value!(s::CooperativeMatrixLayout),
+        OperandKind::CooperativeMatrixUse => value!(s::CooperativeMatrixUse),
+        OperandKind::InitializationModeQualifier => value!(s::InitializationModeQualifier),
+        OperandKind::LoadCacheControl => value!(s::LoadCacheControl),
+        OperandKind::StoreCacheControl => value!(s::StoreCacheControl),
+        _ => None,
+    }
+}
+
+/// Enumerant kinds some of whose values take extra operands, such as
+/// `Binding 1` or `LocalSize 8 1 1`.
+const PARAMETERIZED: &[OperandKind] = &[
+    OperandKind::Decoration,
+    OperandKind::ExecutionMode,
+    OperandKind::ImageOperands,
+    OperandKind::LoopControl,
+    OperandKind::MemoryAccess,
+];
+
+/// Enumerants that may appear as decoration parameters (`BuiltIn Position`).
+const DECORATION_PARAMETERS: &[OperandKind] = &[
+    OperandKind::BuiltIn,
+    OperandKind::FPRoundingMode,
+    OperandKind::FPFastMathMode,
+    OperandKind::LinkageType,
+    OperandKind::FunctionParameterAttribute,
+];
+
+struct Assembler {
+    ids: HashMap<String, u32>,
+    /// Numeric types by id, for context-dependent literals.
+    numerics: HashMap<u32, Numeric>,
+    /// Result type of every typed result, for `OpSwitch` selectors.
+    value_types: HashMap<u32, u32>,
+}
+
+impl Assembler {
+    fn id(&self, token: Option<&Token>) -> Result<u32, String> {
+        match token {
+            Some(Token::Id(name)) => Ok(self.ids[name]),
+            Some(other) => Err(format!("Expected an id, found '{}'", other.describe())),
+            None => Err("Expected an id".to_string()),
+        }
+    }
+
+    fn numeric(&self, type_id: Option<u32>) -> Result<Numeric, String> {
+        type_id
+            .and_then(|ty| self.numerics.get(&ty).copied())
+            .ok_or_else(|| "Literal's type is not a scalar integer or float type".to_string())
+    }
+
+    fn instruction(&mut self, line: &Line) -> Result<Vec<u32>, String> {
+        let mut tokens = line.operands.iter().peekable();
+        let mut words = Vec::new();
+        let mut result_type = None;
+        let mut result = None;
+
+        for operand in line.grammar.operands {
+            let count = match operand.quantifier {
+                OperandQuantifier::One => 1,
+                OperandQuantifier::ZeroOrOne => 1.min(tokens.len()),
+                OperandQuantifier::ZeroOrMore => usize::MAX,
+            };
+            let mut parsed = 0;
+            while parsed < count {
+                if operand.kind == OperandKind::IdResult {
+                    let name = line
+                        .result
+                        .as_ref()
+                        .ok_or_else(|| format!("Op{} needs a '%result ='", line.grammar.opname))?;
+                    result = Some(self.ids[name]);
+                    words.push(self.ids[name]);
+                    parsed += 1;
+                    continue;
+                }
+                if tokens.peek().is_none() {
+                    if operand.quantifier == OperandQuantifier::One {
+                        return Err(format!(
+                            "Op{} is missing its {:?} operand",
+                            line.grammar.opname, operand.kind
+                        ));
+                    }
+                    break;
+                }
+                match operand.kind {
+                    OperandKind::IdResultType => {
+                        let ty = self.id(tokens.next())?;
+                        result_type = Some(ty);
+                        words.push(ty);
+                    }
+                    OperandKind::IdRef | OperandKind::IdMemorySemantics | OperandKind::IdScope => {
+                        words.push(self.id(tokens.next())?);
+                    }
+                    OperandKind::LiteralString => match tokens.next() {
+                        Some(Token::Str(s)) => words.extend(spv::encode_string(s)),
+                        Some(other) => {
+                            return Err(format!("Expected a string, found '{}'", other.describe()));
+                        }
+                        None => unreachable!(),
+                    },
+                    OperandKind::LiteralContextDependentNumber => {
+                        let text = word(tokens.next())?;
+                        words.extend(number_words(text, self.numeric(result_type)?)?);
+                    }
+                    OperandKind::PairLiteralIntegerIdRef => {
+                        // `OpSwitch` cases follow the selector's width.
+                        let selector = self.value_types.get(&words[0]).copied();
+                        let text = word(tokens.next())?;
+                        words.extend(number_words(text, self.numeric(selector)?)?);
+                        words.push(self.id(tokens.next())?);
+                    }
+                    OperandKind::PairIdRefLiteralInteger => {
+                        words.push(self.id(tokens.next())?);
+                        words.push(literal_word(word(tokens.next())?)?);
+                    }
+                    OperandKind::PairIdRefIdRef => {
+                        words.push(self.id(tokens.next())?);
+                        words.push(self.id(tokens.next())?);
+                    }
+                    OperandKind::LiteralFloat => {
+                        let text = word(tokens.next())?;
+                        words.extend(number_words(text, Numeric::Float { width: 32 })?);
+                    }
+                    OperandKind::LiteralExtInstInteger => {
+                        let text = word(tokens.next())?;
+                        let opcode = GlslStd450InstructionTable::iter()
+                            .find(|inst| inst.opname == text)
+                            .map(|inst| inst.opcode);
+                        words.push(match opcode {
+                            Some(opcode) => opcode,
+                            None => literal_word(text)?,
+                        });
+                    }
+                    OperandKind::LiteralSpecConstantOpInteger => {
+                        let text = word(tokens.next())?;
+                        words.push(match lookup_op(text) {
+                            Ok(inst) => inst.opcode as u32,
+                            Err(_) => literal_word(text)?,
+                        });
+                    }
+                    OperandKind::LiteralInteger => {
+                        words.push(literal_word(word(tokens.next())?)?);
+                    }
+                    kind => {
+                        let text = word(tokens.next())?;
+                        let value = enumerant(kind, text)
+                            .ok_or_else(|| format!("Unknown {kind:?} '{text}'"))?;
+                        words.push(value);
+                        if PARAMETERIZED.contains(&kind) {
+                            self.parameters(kind, &mut tokens, &mut words)?;
+                        }
+                    }
+                }
+                parsed += 1;
+            }
+        }
+        if let Some(extra) = tokens.next() {
+            return Err(format!(
+                "Unexpected operand '{}' for Op{}",
+                extra.describe(),
+                line.grammar.opname
+            ));
+        }
+        if line.result.is_some() && result.is_none() {
+            return Err(format!("Op{} has no result", line.grammar.opname));
+        }
+
+        if let (Some(id), Some(ty)) = (result, result_type) {
+            self.value_types.insert(id, ty);
+        }
+        match (line.grammar.opcode, result) {
+            (Op::TypeInt, Some(id)) => {
+                let (width, signed) = (words[1], words[2] != 0);
+                self.numerics.insert(id, Numeric::Int { width, signed });
+            }
+            (Op::TypeFloat, Some(id)) => {
+                self.numerics.insert(id, Numeric::Float { width: words[1] });
+            }
+            _ => {}
+        }
+        Ok(spv::encode(line.grammar.opcode, &words))
+    }
+
+    /// Operands an enumerant carries: ids, strings and numbers, plus
+    /// enumerants for decorations. They run until the next mask name.
+    fn parameters<'a>(
+        &self,
+        kind: OperandKind,
+        tokens: &mut std::iter::Peekable<std::slice::Iter<'a, Token>>,
+        words: &mut Vec<u32>,
+    ) -> Result<(), String> {
+        while let Some(token) = tokens.peek() {
+            match token {
+                Token::Id(_) => words.push(self.id(tokens.next())?),
+                Token::Str(s) => {
+                    words.extend(spv::encode_string(s));
+                    tokens.next();
+                }
+                Token::Word(text) if parse_int(text).is_some() => {
+                    words.push(literal_word(text)?);
+                    tokens.next();
+                }
+                Token::Word(text) if kind == OperandKind::Decoration => {
+                    let value = DECORATION_PARAMETERS
+                        .iter()
+                        .find_map(|&kind| enumerant(kind, text))
+                        .ok_or_else(|| format!("Unknown decoration parameter '{text}'"))?;
+                    words.push(value);
+                    tokens.next();
+                }
+                Token::Word(_) => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn word(token: Option<&Token>) -> Result<&str, String> {
+    match token {
+        Some(Token::Word(word)) => Ok(word),
+        Some(other) => Err(format!("Expected a literal, found '{}'", other.describe())),
+        None => Err("Expected a literal".to_string()),
+    }
+}
+
+/// `; Version: 1.3`, as spirv-dis and `spirvBinToText` write it.
+fn version_comment(line: &str) -> Option<u32> {
+    let version = line
+        .trim()
+        .strip_prefix(';')?
+        .trim()
+        .strip_prefix("Version:")?;
+    let (major, minor) = version.trim().split_once('.')?;
+    Some((major.parse::<u32>().ok()? << 16) | (minor.parse::<u32>().ok()? << 8))
+}
+
+/// Assemble `text` into a SPIR-V word stream. The version defaults to 1.0.
+pub(crate) fn assemble(text: &str) -> Result<Vec<u32>, Diagnostic> {
+    let at = |number: usize| {
+        move |message: String| Diagnostic::error(format!("Line {number}: {message}"))
+    };
+
+    let mut version = 0x0001_0000;
+    let mut lines = Vec::new();
+    for (index, source) in text.lines().enumerate() {
+        if let Some(v) = version_comment(source) {
+            version = v;
+        }
+        lines.extend(parse_line(index + 1, source).map_err(at(index + 1))?);
+    }
+
+    let ids = assign_ids(&lines);
+    // The bound is one past the largest id, so that id cannot be `u32::MAX`.
+    let bound = match ids.values().max() {
+        Some(&max) => max.checked_add(1).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Id %{max} out of range; ids must be below {}",
+                u32::MAX
+            ))
+        })?,
+        None => 1,
+    };
+    let mut assembler = Assembler {
+        ids,
+        numerics: HashMap::new(),
+        value_types: HashMap::new(),
+    };
+    let mut words = vec![spv::MAGIC, version, 0, bound, 0];
+    for line in &lines {
+        words.extend(assembler.instruction(line).map_err(at(line.number))?);
+    }
+    Ok(words)
+}
+
+fn assemble_checked(text: &str, validate: bool) -> Result<Vec<u8>, Diagnostic> {
+    let words = assemble(text)?;
+    rspirv::dr::load_words(&words)
+        .map_err(|e| Diagnostic::error(format!("SPIR-V parse error: {e}")))?;
+    let bytes = spv::bytes_from_words(&words);
+    if validate {
+        crate::load_spirv(&bytes)?;
+    }
+    Ok(bytes)
+}
+
+/// Textual SPIR-V -> binary, in the format `spirvBinToText` prints:
+/// `%result = OpName operands` lines, `;` comments, enumerants by name, and
+/// `%ids` either numbered or named. A `; Version: 1.x` comment sets the
+/// version (1.0 by default). Unless `validate` is false, the binary must
+/// also load and validate through naga.
+#[wasm_bindgen(js_name = spirvAsmToBin)]
+pub fn spirv_asm_to_bin(asm_text: &str, validate: Option<bool>) -> Result<Vec<u8>, JsValue> {
+    assemble_checked(asm_text, validate.unwrap_or(true)).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPUTE: &str = r#"
+        ; A hand-written fixture.
+        OpCapability Shader
+        OpMemoryModel Logical GLSL450
+        OpEntryPoint GLCompute %main "main"
+        OpExecutionMode %main LocalSize 4 1 1
+        OpDecorate %buffer DescriptorSet 0
+        OpDecorate %buffer Binding 2
+        OpDecorate %Data Block
+        OpMemberDecorate %Data 0 Offset 0
+        %void = OpTypeVoid
+        %uint = OpTypeInt 32 0
+        %float = OpTypeFloat 32
+        %Data = OpTypeStruct %float
+        %ptr = OpTypePointer Uniform %Data
+        %field = OpTypePointer Uniform %float
+        %buffer = OpVariable %ptr Uniform
+        %zero = OpConstant %uint 0
+        %half = OpConstant %float -0.5
+        %fn = OpTypeFunction %void
+        %main = OpFunction %void None %fn
+        %entry = OpLabel
+        %p = OpAccessChain %field %buffer %zero
+        %x = OpLoad %float %p
+        OpReturn
+        OpFunctionEnd
+    "#;
+
+    #[test]
+    fn assembles_named_fixtures() {
+        let bytes = assemble_checked(COMPUTE, true).unwrap();
+        let words = spv::words_from_bytes(&bytes).unwrap();
+        assert_eq!(spv::version(&words), (1, 0));
+
+        let text = spv::disassemble(&words).unwrap();
+        assert!(text.contains("OpExecutionMode %1 LocalSize 4 1 1"));
+        assert!(text.contains("OpDecorate %2 Binding 2"));
+        assert!(text.contains("OpConstant  %6  -0.5"));
+
+        let module = crate::load_spirv(&bytes).unwrap().0;
+        assert_eq!(module.entry_points[0].workgroup_size, [4, 1, 1]);
+
+        // Mask parameters follow the mask.
+        let load = "%f = OpTypeFloat 32\n%p = OpUndef %f\n%x = OpLoad %f %p Volatile|Aligned 4";
+        let words = assemble(load).unwrap();
+        assert_eq!(words[words.len() - 6..], [0x6_003d, 1, 3, 2, 0b11, 4]);
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let source = r#"
+            @group(0) @binding(1) var<storage, read_write> data: array<f32>;
+            @compute @workgroup_size(8)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                var x = 0.5;
+                for (var i = 0u; i < 4u; i++) {
+                    x = sqrt(x) + data[i];
+                    switch i { case 1u: { x = 2.0; } default: {} }
+                }
+                data[id.x] = x * -1.25;
+            }
+        "#;
+        let original = spv::words_from_bytes(&crate::compile_spirv(source, None).unwrap()).unwrap();
+        let text = spv::disassemble(&original).unwrap();
+        let words = spv::words_from_bytes(&assemble_checked(&text, true).unwrap()).unwrap();
+        // The generator word is not part of the text.
+        assert_eq!(words[..2], original[..2]);
+        assert_eq!(words[3..], original[3..]);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let message = |text: &str| assemble_checked(text, true).err().unwrap().message;
+        assert_eq!(
+            message("OpCapability Shader\nOpBogus"),
+            "Line 2: Unknown instruction 'OpBogus'"
+        );
+        assert!(message("OpCapability Wings").contains("Unknown Capability 'Wings'"));
+        assert!(message("%t = OpTypeInt 32").starts_with("Line 1: OpTypeInt is missing"));
+        assert!(message("%x = OpCapability Shader").contains("has no result"));
+        assert!(message("%4294967295 = OpTypeVoid").contains("out of range"));
+
+        // Well-formed, but not a valid shader.
+        let broken = COMPUTE.replace("OpReturn\n", "OpReturnValue %half\n");
+        assert!(assemble_checked(&broken, false).is_ok());
+        assert!(assemble_checked(&broken, true).is_err());
+    }
+}
@@ -0,0 +1,1000 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::entry_points::{
+    EarlyDepth, WorkgroupLimits, set_early_depth_tests, set_position_invariance,
+    set_workgroup_sizes, strip_entry_points,
+};
+use crate::manifest::{Manifest, ManifestEntry, build_manifest, manifest_entry};
+use crate::math::{MathMode, apply_msl, apply_spirv};
+use crate::output;
+use crate::preset;
+use crate::provenance::{Provenance, embed_fingerprint, embed_spirv, embed_spirv_fingerprint};
+use crate::prune::{PrunedBinding, write_spirv_pruned};
+use crate::size::{SizeReport, spirv_report, text_report};
+use crate::spv;
+
+// ============================================================================
+// Batch Types
+// ============================================================================
+
+/// Backend a batch job compiles to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Target {
+    Spirv,
+    Msl,
+}
+
+impl Target {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Target::Spirv => "spirv",
+            Target::Msl => "msl",
+        }
+    }
+
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        match name {
+            "spirv" => Some(Target::Spirv),
+            "msl" => Some(Target::Msl),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that affects a job's output besides its source. Serialized to
+/// derive the options hash of provenance records, so new output-affecting
+/// options belong here.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JobOptions {
+    pub target: Target,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Device preset name; see `listPresets`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Embed a provenance record in the artifact and the manifest.
+    #[serde(default)]
+    pub attest: bool,
+    /// Entry points to compile as if they were not in the source, e.g. debug
+    /// views in release builds. Validation and reflection skip them too.
+    #[serde(default)]
+    pub strip_entry_points: Vec<String>,
+    /// Drop bindings the entry point does not use from SPIR-V output.
+    /// Requires `entry_point`.
+    #[serde(default)]
+    pub prune_bindings: bool,
+    /// Mark every `@builtin(position)` `@invariant` (true) or strip the
+    /// attribute (false). Left as written if unset.
+    #[serde(default)]
+    pub invariant_position: Option<bool>,
+    /// Early depth test per fragment entry point, replacing what the source
+    /// declares.
+    #[serde(default)]
+    pub early_depth_test: BTreeMap<String, EarlyDepth>,
+    /// Workgroup size per compute entry point, replacing the source's.
+    #[serde(default)]
+    pub workgroup_size: BTreeMap<String, Vec<u32>>,
+    /// Floating-point optimizations allowed: `"fast"`, `"precise"`, or the
+    /// backend's own default.
+    #[serde(default)]
+    pub math: MathMode,
+    /// Lead textual output with a comment naming the source hash and entry
+    /// point, or add the same text to SPIR-V as a debug string.
+    #[serde(default)]
+    pub fingerprint: bool,
+    /// Attach a `SizeReport` to the result. Does not change the artifact,
+    /// so it is kept out of the options hash.
+    #[serde(default, skip_serializing)]
+    pub size_report: bool,
+}
+
+impl JobOptions {
+    pub(crate) fn new(target: Target, entry_point: Option<&str>) -> Self {
+        JobOptions {
+            target,
+            entry_point: entry_point.map(str::to_string),
+            preset: None,
+            attest: false,
+            strip_entry_points: Vec::new(),
+            prune_bindings: false,
+            invariant_position: None,
+            early_depth_test: BTreeMap::new(),
+            workgroup_size: BTreeMap::new(),
+            math: MathMode::Default,
+            fingerprint: false,
+            size_report: false,
+        }
+    }
+}
+
+/// One unit of work in a batch, as passed from JS.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BatchJob {
+    /// Stable identifier for the manifest, usually the asset path.
+    /// Defaults to `job-<index>`.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub source: String,
+    /// Write the artifact to this file instead of returning it.
+    #[serde(default)]
+    pub output_path: Option<String>,
+    #[serde(flatten)]
+    pub options: JobOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JobResult {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub target: String,
+    #[wasm_bindgen(readonly)]
+    pub entry_point: Option<String>,
+    /// Set for binary targets (SPIR-V).
+    #[wasm_bindgen(readonly)]
+    pub bytes: Option<Vec<u8>>,
+    /// Set for textual targets (MSL).
+    #[wasm_bindgen(readonly)]
+    pub text: Option<String>,
+    /// Where the artifact was written, for jobs with an `outputPath`; `bytes`
+    /// and `text` are then unset.
+    #[wasm_bindgen(readonly)]
+    pub output_path: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+    /// Other sources read through `#include` to produce this result (project builds only).
+    /// A failed build lists the includes it found, including missing ones.
+    #[wasm_bindgen(readonly)]
+    pub dependencies: Vec<String>,
+    /// Bindings removed by `pruneBindings`.
+    #[wasm_bindgen(readonly)]
+    pub pruned_bindings: Vec<PrunedBinding>,
+    /// Set when the job asked for `sizeReport`.
+    #[wasm_bindgen(readonly)]
+    pub size: Option<SizeReport>,
+}
+
+#[wasm_bindgen]
+impl JobResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BatchResult {
+    #[wasm_bindgen(readonly)]
+    pub results: Vec<JobResult>,
+    #[wasm_bindgen(readonly)]
+    pub failed: u32,
+    /// Manifest of the successful jobs.
+    #[wasm_bindgen(readonly)]
+    pub manifest: Manifest,
+    /// Every job's diagnostics, grouped.
+    #[wasm_bindgen(readonly)]
+    pub report: BatchReport,
+}
+
+#[wasm_bindgen]
+impl BatchResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A batch's diagnostics with duplicates folded together, so an error in a
+/// shared include shows up once with every job it broke rather than once
+/// per job.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BatchReport {
+    /// Error diagnostics across all jobs, duplicates included.
+    #[wasm_bindgen(readonly)]
+    pub errors: u32,
+    #[wasm_bindgen(readonly)]
+    pub warnings: u32,
+    /// In order of first appearance.
+    #[wasm_bindgen(readonly)]
+    pub groups: Vec<DiagnosticGroup>,
+}
+
+#[wasm_bindgen]
+impl BatchReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// One distinct diagnostic and the jobs that reported it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DiagnosticGroup {
+    #[wasm_bindgen(readonly)]
+    pub severity: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// Indices into the batch's `results`, ascending.
+    #[wasm_bindgen(readonly)]
+    pub jobs: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl DiagnosticGroup {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Passed to the progress callback after each job finishes.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JobProgress {
+    #[wasm_bindgen(readonly)]
+    pub index: u32,
+    #[wasm_bindgen(readonly)]
+    pub total: u32,
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl JobProgress {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Batch Implementation
+// ============================================================================
+
+/// Output of a successful job, before it is split into result fields.
+pub(crate) enum Artifact {
+    Binary(Vec<u8>),
+    Text(String),
+}
+
+/// Everything `try_emit` produces besides diagnostics.
+struct Emitted {
+    artifact: Artifact,
+    provenance: Option<Provenance>,
+    pruned_bindings: Vec<PrunedBinding>,
+}
+
+impl Artifact {
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        match self {
+            Artifact::Binary(bytes) => bytes,
+            Artifact::Text(text) => text.as_bytes(),
+        }
+    }
+}
+
+/// Emit one artifact from an already validated module.
+pub(crate) fn emit_artifact(
+    name: &str,
+    source: &str,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    options: &JobOptions,
+) -> (JobResult, Option<ManifestEntry>) {
+    match try_emit(source, module, info, options) {
+        Ok(Emitted {
+            artifact,
+            provenance,
+            pruned_bindings,
+        }) => {
+            let entry = manifest_entry(
+                name,
+                options,
+                source,
+                module,
+                info,
+                artifact.as_bytes(),
+                provenance,
+            );
+            let mut result = job_result(name, options);
+            result.ok = true;
+            result.pruned_bindings = pruned_bindings;
+            if options.size_report {
+                match size_of(&artifact) {
+                    Ok(size) => result.size = Some(size),
+                    Err(diagnostic) => result.diagnostics.push(diagnostic),
+                }
+            }
+            match artifact {
+                Artifact::Binary(bytes) => result.bytes = Some(bytes),
+                Artifact::Text(text) => result.text = Some(text),
+            }
+            (result, Some(entry))
+        }
+        Err(diagnostic) => (failed_job(name, options, diagnostic), None),
+    }
+}
+
+fn size_of(artifact: &Artifact) -> Result<SizeReport, Diagnostic> {
+    match artifact {
+        Artifact::Binary(bytes) => spirv_report(&spv::words_from_bytes(bytes)?),
+        Artifact::Text(text) => Ok(text_report(text)),
+    }
+}
+
+fn try_emit(
+    source: &str,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    options: &JobOptions,
+) -> Result<Emitted, Diagnostic> {
+    let entry_point = options.entry_point.as_deref();
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let provenance = options.attest.then(|| Provenance::new(source, options));
+    let mut pruned_bindings = Vec::new();
+    let artifact = match options.target {
+        Target::Spirv => {
+            let bytes = if options.prune_bindings {
+                let (bytes, pruned) = write_spirv_pruned(module, info, entry_point, preset)?;
+                pruned_bindings = pruned;
+                bytes
+            } else {
+                crate::write_spirv_with(module, info, entry_point, preset)?
+            };
+            if options.math == MathMode::Default && provenance.is_none() && !options.fingerprint {
+                Artifact::Binary(bytes)
+            } else {
+                let mut words = spv::words_from_bytes(&bytes)?;
+                apply_spirv(&mut words, options.math)?;
+                if let Some(provenance) = &provenance {
+                    embed_spirv(&mut words, provenance)?;
+                }
+                if options.fingerprint {
+                    embed_spirv_fingerprint(&mut words, source, entry_point)?;
+                }
+                Artifact::Binary(spv::bytes_from_words(&words))
+            }
+        }
+        Target::Msl => {
+            let mut text = crate::write_msl_with(module, info, entry_point, preset)?;
+            apply_msl(&mut text, options.math);
+            if options.fingerprint {
+                embed_fingerprint(&mut text, source, entry_point);
+            }
+            Artifact::Text(text)
+        }
+    };
+    Ok(Emitted {
+        artifact,
+        provenance,
+        pruned_bindings,
+    })
+}
+
+fn job_result(name: &str, options: &JobOptions) -> JobResult {
+    JobResult {
+        name: name.to_string(),
+        ok: false,
+        target: options.target.as_str().to_string(),
+        entry_point: options.entry_point.clone(),
+        bytes: None,
+        text: None,
+        output_path: None,
+        diagnostics: Vec::new(),
+        dependencies: Vec::new(),
+        pruned_bindings: Vec::new(),
+        size: None,
+    }
+}
+
+/// Result for a job that produced no artifact.
+pub(crate) fn failed_job(name: &str, options: &JobOptions, diagnostic: Diagnostic) -> JobResult {
+    let mut result = job_result(name, options);
+    result.diagnostics.push(diagnostic);
+    result
+}
+
+/// Compile a single job. Never fails; errors land in the result's diagnostics.
+pub(crate) fn compile_job(index: usize, job: &BatchJob) -> (JobResult, Option<ManifestEntry>) {
+    let name = job.name.clone().unwrap_or_else(|| format!("job-{index}"));
+    let (mut result, mut entry) = match parse_job(job) {
+        Ok((module, info)) => emit_artifact(&name, &job.source, &module, &info, &job.options),
+        Err(diagnostic) => (failed_job(&name, &job.options, diagnostic), None),
+    };
+    if let Some(path) = &job.output_path
+        && result.ok
+    {
+        write_output(&mut result, &mut entry, path);
+    }
+    (result, entry)
+}
+
+/// Move a successful result's artifact into the file at `path`. A failed
+/// write fails the job and takes it out of the manifest.
+fn write_output(result: &mut JobResult, entry: &mut Option<ManifestEntry>, path: &str) {
+    let bytes = result.bytes.take();
+    let text = result.text.take();
+    let data = bytes
+        .as_deref()
+        .or(text.as_deref().map(str::as_bytes))
+        .unwrap_or_default();
+    match output::write_file(path, data) {
+        Ok(()) => result.output_path = Some(path.to_string()),
+        Err(diagnostic) => {
+            result.ok = false;
+            result.diagnostics.push(diagnostic);
+            *entry = None;
+        }
+    }
+}
+
+fn parse_job(job: &BatchJob) -> Result<(naga::Module, naga::valid::ModuleInfo), Diagnostic> {
+    let preset = preset::resolve(job.options.preset.as_deref())?;
+    let mut module = crate::parse_wgsl(&job.source)?;
+    strip_entry_points(&mut module, &job.options.strip_entry_points)?;
+    if let Some(invariant) = job.options.invariant_position {
+        set_position_invariance(&mut module, invariant);
+    }
+    set_early_depth_tests(&mut module, &job.options.early_depth_test)?;
+    set_workgroup_sizes(
+        &mut module,
+        &job.options.workgroup_size,
+        preset.map_or(&WorkgroupLimits::WEBGPU_DEFAULT, |p| &p.workgroup_limits),
+    )?;
+    let info = crate::validate_for(&module, preset)?;
+    Ok((module, info))
+}
+
+/// Wrap a JS progress callback for `run_batch`-style drivers.
+pub(crate) fn js_progress(
+    on_progress: &Option<js_sys::Function>,
+) -> impl FnMut(&JobProgress) -> Result<(), JsValue> {
+    move |progress| match on_progress {
+        Some(callback) => callback
+            .call1(&JsValue::NULL, &progress.clone().into())
+            .map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+/// `job(0)` through `job(count - 1)`, yielded in that order. With a thread
+/// pool (see `threads.rs`) they all run across it before the first is
+/// yielded; otherwise each runs when the iterator reaches it, so a consumer
+/// that stops early skips the rest.
+pub(crate) fn in_order<'a, T: Send + 'a>(
+    count: usize,
+    job: impl Fn(usize) -> T + Send + Sync + 'a,
+) -> Box<dyn Iterator<Item = T> + 'a> {
+    #[cfg(feature = "parallel")]
+    if crate::threads::enabled() {
+        use rayon::prelude::*;
+        let done: Vec<T> = (0..count).into_par_iter().map(job).collect();
+        return Box::new(done.into_iter());
+    }
+    Box::new((0..count).map(job))
+}
+
+/// Group `results`' diagnostics by severity and message.
+fn report(results: &[JobResult]) -> BatchReport {
+    let mut report = BatchReport {
+        errors: 0,
+        warnings: 0,
+        groups: Vec::new(),
+    };
+    let mut seen: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for (index, result) in results.iter().enumerate() {
+        for diagnostic in &result.diagnostics {
+            match diagnostic.severity.as_str() {
+                "error" => report.errors += 1,
+                "warning" => report.warnings += 1,
+                _ => {}
+            }
+            let key = (diagnostic.severity.as_str(), diagnostic.message.as_str());
+            let group = *seen.entry(key).or_insert_with(|| {
+                report.groups.push(DiagnosticGroup {
+                    severity: diagnostic.severity.clone(),
+                    message: diagnostic.message.clone(),
+                    jobs: Vec::new(),
+                });
+                report.groups.len() - 1
+            });
+            let jobs = &mut report.groups[group].jobs;
+            if jobs.last() != Some(&(index as u32)) {
+                jobs.push(index as u32);
+            }
+        }
+    }
+    report
+}
+
+/// Assemble a batch's result from its jobs' results, in job order.
+pub(crate) fn batch_result(results: Vec<JobResult>, entries: Vec<ManifestEntry>) -> BatchResult {
+    BatchResult {
+        failed: results.iter().filter(|r| !r.ok).count() as u32,
+        report: report(&results),
+        manifest: build_manifest(entries),
+        results,
+    }
+}
+
+/// Run every job, reporting each one to `on_progress` in order as soon as it
+/// completes. An error from `on_progress` aborts the remaining jobs. With a
+/// thread pool, jobs compile concurrently and progress follows once all are
+/// done, still in order, so aborting saves nothing.
+pub(crate) fn run_batch<E>(
+    jobs: &[BatchJob],
+    mut on_progress: impl FnMut(&JobProgress) -> Result<(), E>,
+) -> Result<BatchResult, E> {
+    let total = jobs.len() as u32;
+    let mut results = Vec::with_capacity(jobs.len());
+    let mut entries = Vec::new();
+
+    let compiled = in_order(jobs.len(), |index| compile_job(index, &jobs[index]));
+    for (index, (result, entry)) in compiled.enumerate() {
+        on_progress(&JobProgress {
+            index: index as u32,
+            total,
+            ok: result.ok,
+            diagnostics: result.diagnostics.clone(),
+        })?;
+        results.push(result);
+        entries.extend(entry);
+    }
+
+    Ok(batch_result(results, entries))
+}
+
+/// Compiles an array of
+/// `{ name?, source, target, entryPoint?, preset?, attest?, stripEntryPoints?, pruneBindings?, invariantPosition?, earlyDepthTest?, workgroupSize?, math?, fingerprint?, sizeReport? }`
+/// jobs in one call. Any of the fields after `source` can also be given in
+/// a nested `options` object, which takes precedence; that way a job can be
+/// `{ source, target, entryPoint, options }` with `options` shared between
+/// many variants.
+/// Failed jobs do not stop the batch; their errors are in each result's diagnostics.
+/// If `on_progress` is given, it is called with a `JobProgress` after every job,
+/// so build UIs can show live diagnostics. Throwing from it aborts the batch.
+/// The result's `manifest` hashes every artifact for cache invalidation, and
+/// its `report` folds identical diagnostics from different jobs together.
+/// With `attest: true`, a provenance record is embedded in SPIR-V output
+/// (see `readProvenance`) and added to the job's manifest entry.
+/// Entry points named in `stripEntryPoints` are treated as removed from the
+/// source, so they are neither validated, emitted nor listed in the manifest.
+/// With `pruneBindings: true`, SPIR-V output drops bindings its entry point
+/// does not use and lists them in `prunedBindings`.
+/// `invariantPosition: true` marks vertex position outputs `@invariant` for
+/// depth-equal multi-pass rendering; `false` strips the attribute.
+/// `earlyDepthTest` maps fragment entry point names to `"force"`,
+/// `"greater_equal"`, `"less_equal"`, `"unchanged"` or `"none"`.
+/// `preset` selects a device preset's capabilities, bounds checks, limits
+/// and backend versions.
+/// `workgroupSize` maps compute entry point names to `[x, y?, z?]`, checked
+/// against the preset's limits, or WebGPU's defaults without one.
+/// `math: "precise"` keeps float arithmetic as written (SPIR-V `NoContraction`,
+/// MSL `fp` pragmas); `math: "fast"` allows reassociation and contraction.
+/// With `fingerprint: true`, MSL output starts with a
+/// `// metis-source: sha256=<source hash> entry=<name>` comment, and SPIR-V
+/// output carries the same text as an `OpModuleProcessed` (1.0:
+/// `OpSourceExtension`) debug string.
+/// With `sizeReport: true`, the job's result carries code-size metrics.
+/// With `outputPath`, the artifact is written to that file, under Node or
+/// natively, and the result carries the path instead of the artifact; for
+/// large packs this skips copying every artifact out to JS.
+#[wasm_bindgen(js_name = compileBatch)]
+pub fn compile_batch(
+    jobs: JsValue,
+    on_progress: Option<js_sys::Function>,
+) -> Result<BatchResult, JsValue> {
+    let jobs: Vec<BatchJob> = serde_wasm_bindgen::from_value(merge_nested_options(jobs)?)
+        .map_err(|e| JsValue::from_str(&format!("Invalid batch jobs: {e}")))?;
+
+    run_batch(&jobs, js_progress(&on_progress))
+}
+
+/// Copy each job's `options` object over the job itself, leaving the caller's
+/// objects untouched. Anything that is not an array is returned as is, for
+/// deserialization to reject.
+fn merge_nested_options(jobs: JsValue) -> Result<JsValue, JsValue> {
+    if !js_sys::Array::is_array(&jobs) {
+        return Ok(jobs);
+    }
+    let merged = js_sys::Array::new();
+    for job in js_sys::Array::from(&jobs).iter() {
+        let options = match job.dyn_ref::<js_sys::Object>() {
+            Some(object) => js_sys::Reflect::get(object, &JsValue::from_str("options"))?,
+            None => JsValue::UNDEFINED,
+        };
+        match options.dyn_into::<js_sys::Object>() {
+            Ok(options) => {
+                let copy = js_sys::Object::assign(&js_sys::Object::new(), job.unchecked_ref());
+                merged.push(&js_sys::Object::assign(&copy, &options));
+            }
+            Err(_) => {
+                merged.push(&job);
+            }
+        }
+    }
+    Ok(merged.into())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPUTE: &str = r#"
+        @compute @workgroup_size(64)
+        fn main() {}
+    "#;
+
+    fn job(source: &str, target: Target) -> BatchJob {
+        BatchJob {
+            name: None,
+            source: source.to_string(),
+            output_path: None,
+            options: JobOptions::new(target, None),
+        }
+    }
+
+    #[test]
+    fn progress_is_reported_per_job_in_order() {
+        let jobs = vec![
+            job(COMPUTE, Target::Spirv),
+            job("not wgsl", Target::Msl),
+            job(COMPUTE, Target::Msl),
+        ];
+        let mut seen = Vec::new();
+        let batch = run_batch(&jobs, |p| {
+            seen.push((p.index, p.total, p.ok, p.diagnostics.len()));
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            seen,
+            vec![(0, 3, true, 0), (1, 3, false, 1), (2, 3, true, 0)]
+        );
+        assert_eq!(batch.failed, 1);
+        assert!(batch.results[0].bytes.is_some());
+        assert!(batch.results[2].text.is_some());
+    }
+
+    #[test]
+    fn manifest_covers_successful_jobs_only() {
+        let mut named = job(COMPUTE, Target::Msl);
+        named.name = Some("shaders/a.wgsl".to_string());
+        let jobs = vec![
+            job(COMPUTE, Target::Spirv),
+            job("not wgsl", Target::Spirv),
+            named,
+        ];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+
+        let names: Vec<_> = batch
+            .manifest
+            .entries
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["job-0", "shaders/a.wgsl"]);
+
+        let spirv = &batch.manifest.entries[0];
+        assert_eq!(
+            spirv.byte_size as usize,
+            batch.results[0].bytes.as_ref().unwrap().len()
+        );
+        assert_eq!(spirv.entry_points, vec!["main"]);
+        assert_eq!(spirv.source_hash, batch.manifest.entries[1].source_hash);
+        assert_ne!(spirv.artifact_hash, batch.manifest.entries[1].artifact_hash);
+    }
+
+    #[test]
+    fn manifest_is_stable_across_builds() {
+        let jobs = vec![job(COMPUTE, Target::Spirv)];
+        let a = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+        let b = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+        assert_eq!(a.manifest.to_json_string(), b.manifest.to_json_string());
+    }
+
+    #[test]
+    fn attested_jobs_carry_provenance() {
+        let mut attested = job(COMPUTE, Target::Spirv);
+        attested.options.attest = true;
+        let jobs = vec![attested, job(COMPUTE, Target::Spirv)];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+
+        let words = spv::words_from_bytes(batch.results[0].bytes.as_ref().unwrap()).unwrap();
+        let embedded = crate::provenance::read_spirv(&words).unwrap().unwrap();
+        assert_eq!(batch.manifest.entries[0].provenance, Some(embedded));
+        assert_eq!(batch.manifest.entries[1].provenance, None);
+    }
+
+    #[test]
+    fn fingerprints_reach_every_target() {
+        let jobs: Vec<_> = [Target::Spirv, Target::Msl]
+            .into_iter()
+            .map(|target| {
+                let mut job = job(COMPUTE, target);
+                job.options.fingerprint = true;
+                job
+            })
+            .collect();
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+        let hash = crate::hash::sha256_hex(COMPUTE.as_bytes());
+        let text = format!("metis-source: sha256={hash} entry=*");
+
+        let words = spv::words_from_bytes(batch.results[0].bytes.as_ref().unwrap()).unwrap();
+        let strings: Vec<_> = spv::instructions(&words)
+            .unwrap()
+            .iter()
+            .map(|i| spv::decode_string(i.operands()).0)
+            .collect();
+        assert!(strings.contains(&text));
+        let msl = batch.results[1].text.as_ref().unwrap();
+        assert!(msl.starts_with(&format!("// {text}\n")));
+    }
+
+    #[test]
+    fn position_invariance_reaches_every_backend() {
+        let source = r#"
+            struct VertexOut {
+                @builtin(position) position: vec4<f32>,
+                @location(0) uv: vec2<f32>,
+            }
+
+            @vertex
+            fn vs() -> VertexOut {
+                return VertexOut(vec4<f32>(0.0), vec2<f32>(0.0));
+            }
+
+            @vertex
+            fn depth_only() -> @invariant @builtin(position) vec4<f32> {
+                return vec4<f32>(1.0);
+            }
+
+            @fragment
+            fn fs(in: VertexOut) -> @location(0) vec4<f32> {
+                return vec4<f32>(in.uv, 0.0, 1.0);
+            }
+        "#;
+        let invariance = |invariant_position| {
+            let mut spirv = job(source, Target::Spirv);
+            spirv.options.invariant_position = invariant_position;
+            let mut msl = job(source, Target::Msl);
+            msl.options.entry_point = Some("vs".to_string());
+            msl.options.invariant_position = invariant_position;
+            let batch = run_batch(&[spirv, msl], |_| Ok::<_, ()>(())).unwrap();
+
+            let words = spv::words_from_bytes(batch.results[0].bytes.as_ref().unwrap()).unwrap();
+            let decorations = spv::instructions(&words)
+                .unwrap()
+                .iter()
+                .filter(|inst| {
+                    matches!(
+                        inst.op(),
+                        Some(spirv::Op::Decorate | spirv::Op::MemberDecorate)
+                    ) && inst
+                        .operands()
+                        .contains(&(spirv::Decoration::Invariant as u32))
+                })
+                .count();
+            let msl = batch.results[1].text.as_ref().unwrap();
+            (decorations, msl.contains("invariant]]"))
+        };
+
+        assert_eq!(invariance(None), (1, false));
+        let (decorations, msl) = invariance(Some(true));
+        assert!(decorations >= 2);
+        assert!(msl);
+        assert_eq!(invariance(Some(false)), (0, false));
+    }
+
+    #[test]
+    fn jobs_deserialize_with_flattened_options() {
+        let job: BatchJob = serde_json::from_str(
+            r#"{ "source": "", "target": "msl", "entryPoint": "main", "attest": true }"#,
+        )
+        .unwrap();
+        assert_eq!(job.options.target, Target::Msl);
+        assert_eq!(job.options.entry_point.as_deref(), Some("main"));
+        assert!(job.options.attest);
+        assert_eq!(job.options.math, MathMode::Default);
+    }
+
+    #[test]
+    fn presets_set_workgroup_limits() {
+        let mut tuned = job(COMPUTE, Target::Spirv);
+        tuned.options.workgroup_size = BTreeMap::from([("main".to_string(), vec![512])]);
+        let mut m1 = tuned.clone();
+        m1.options.preset = Some("apple-m1".to_string());
+        let mut unknown = tuned.clone();
+        unknown.options.preset = Some("nope".to_string());
+        let batch = run_batch(&[tuned, m1, unknown], |_| Ok::<_, ()>(())).unwrap();
+        let ok: Vec<_> = batch.results.iter().map(|r| r.ok).collect();
+        assert_eq!(ok, vec![false, true, false]);
+    }
+
+    #[test]
+    fn math_mode_changes_the_artifact() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+            @compute @workgroup_size(1) fn main() { data[0] = data[1] * data[2]; }
+        "#;
+        let mut precise: BatchJob = serde_json::from_str(
+            r#"{ "source": "", "target": "spirv", "math": "precise", "attest": true }"#,
+        )
+        .unwrap();
+        precise.source = source.to_string();
+        let jobs = vec![precise, job(source, Target::Spirv)];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+
+        let no_contraction = |result: &JobResult| {
+            let words = spv::words_from_bytes(result.bytes.as_ref().unwrap()).unwrap();
+            spv::instructions(&words).unwrap().iter().any(|inst| {
+                inst.op() == Some(spirv::Op::Decorate)
+                    && inst.operands()[1] == spirv::Decoration::NoContraction as u32
+            })
+        };
+        assert!(no_contraction(&batch.results[0]));
+        assert!(!no_contraction(&batch.results[1]));
+        let words = spv::words_from_bytes(batch.results[0].bytes.as_ref().unwrap()).unwrap();
+        assert!(crate::provenance::read_spirv(&words).unwrap().is_some());
+    }
+
+    #[test]
+    fn size_reports_are_opt_in() {
+        let mut spirv = job(COMPUTE, Target::Spirv);
+        spirv.options.size_report = true;
+        let mut msl = job(COMPUTE, Target::Msl);
+        msl.options.size_report = true;
+        let jobs = vec![spirv, msl, job(COMPUTE, Target::Spirv)];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+
+        let spirv = batch.results[0].size.as_ref().unwrap();
+        assert_eq!(
+            spirv.bytes as usize,
+            batch.results[0].bytes.as_ref().unwrap().len()
+        );
+        assert!(spirv.instructions.is_some());
+        let msl = batch.results[1].size.as_ref().unwrap();
+        assert_eq!(
+            msl.lines.unwrap() as usize,
+            batch.results[1].text.as_ref().unwrap().lines().count()
+        );
+        assert!(batch.results[2].size.is_none());
+
+        // Same artifact, same options hash.
+        let a = Provenance::new(COMPUTE, &jobs[0].options);
+        let b = Provenance::new(COMPUTE, &jobs[2].options);
+        assert_eq!(a.options_hash, b.options_hash);
+    }
+
+    #[test]
+    fn pruned_bindings_are_reported() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read_write> a: array<u32>;
+            @group(0) @binding(1) var<storage, read_write> b: array<u32>;
+            @compute @workgroup_size(1) fn first() { a[0] = 1u; }
+            @compute @workgroup_size(1) fn second() { b[0] = 1u; }
+        "#;
+        let mut pruned = job(source, Target::Spirv);
+        pruned.options.entry_point = Some("first".to_string());
+        pruned.options.prune_bindings = true;
+        let mut no_entry_point = job(source, Target::Spirv);
+        no_entry_point.options.prune_bindings = true;
+        let batch = run_batch(&[pruned, no_entry_point], |_| Ok::<_, ()>(())).unwrap();
+
+        let removed: Vec<_> = batch.results[0]
+            .pruned_bindings
+            .iter()
+            .map(|p| p.name.as_deref())
+            .collect();
+        assert_eq!(removed, vec![Some("b")]);
+        assert!(!batch.results[1].ok);
+    }
+
+    #[test]
+    fn stripped_entry_points_leave_the_manifest() {
+        let source = r#"
+            @fragment fn main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }
+            @fragment fn debug_normals() -> @location(0) vec4<f32> { return vec4<f32>(0.5); }
+        "#;
+        let mut release = job(source, Target::Msl);
+        release.options.strip_entry_points = vec!["debug_normals".to_string()];
+        let jobs = vec![release, job(source, Target::Msl)];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+
+        assert!(
+            !batch.results[0]
+                .text
+                .as_ref()
+                .unwrap()
+                .contains("debug_normals")
+        );
+        assert_eq!(batch.manifest.entries[0].entry_points, vec!["main"]);
+        assert_eq!(
+            batch.manifest.entries[1].entry_points,
+            vec!["main", "debug_normals"]
+        );
+    }
+
+    #[test]
+    fn report_folds_identical_diagnostics() {
+        let jobs = vec![
+            job("fn main( {", Target::Spirv),
+            job(COMPUTE, Target::Spirv),
+            job("fn main( {", Target::Msl),
+        ];
+        let batch = run_batch(&jobs, |_| Ok::<_, ()>(())).unwrap();
+        assert_eq!((batch.report.errors, batch.report.warnings), (2, 0));
+        assert_eq!(batch.report.groups.len(), 1);
+        assert_eq!(batch.report.groups[0].severity, "error");
+        assert_eq!(batch.report.groups[0].jobs, vec![0, 2]);
+    }
+
+    #[test]
+    fn output_paths_receive_artifacts() {
+        let dir = std::env::temp_dir().join(format!("naga-wasm-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let spirv = dir.join("main.spv").to_string_lossy().into_owned();
+        let mut written = job(COMPUTE, Target::Spirv);
+        written.output_path = Some(spirv.clone());
+        let mut unwritable = job(COMPUTE, Target::Msl);
+        unwritable.output_path = Some(
+            dir.join("missing/main.metal")
+                .to_string_lossy()
+                .into_owned(),
+        );
+
+        let batch = run_batch(&[written, unwritable], |_| Ok::<_, ()>(())).unwrap();
+        let bytes = std::fs::read(&spirv).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(bytes[..4], 0x0723_0203u32.to_le_bytes());
+        assert_eq!(
+            batch.results[0].output_path.as_deref(),
+            Some(spirv.as_str())
+        );
+        assert!(batch.results[0].bytes.is_none());
+        assert!(!batch.results[1].ok);
+        assert!(
+            batch.results[1].diagnostics[0]
+                .message
+                .starts_with("Could not write")
+        );
+        assert_eq!(batch.manifest.entries.len(), 1);
+    }
+
+    #[test]
+    fn callback_error_aborts_batch() {
+        let jobs = vec![job(COMPUTE, Target::Spirv), job(COMPUTE, Target::Spirv)];
+        let mut calls = 0;
+        let result = run_batch(&jobs, |_| {
+            calls += 1;
+            Err("stop")
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::Write;
+
+use naga::{AddressSpace, ImageClass, TypeInner};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Bind Group Builder Generation
+// ============================================================================
+//
+// Emits a JavaScript module with a table of the bindings the shader's entry
+// points use and a `createBindGroups` that turns a name -> resource map into
+// one `GPUBindGroup` per group, against the pipeline's own layouts. Every
+// resource is checked before anything is created: a missing name or one of
+// the wrong class (a sampler bound where a texture view belongs) is
+// collected, and one error lists them all. Class checks are skipped where
+// the class is not a global, so the module loads outside browsers too.
+// Bindings no listed entry point uses are left out, as `layout: "auto"`
+// leaves them out of the pipeline's layouts.
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BindGroupBuilderOptions {
+    /// Entry points the pipeline is made of; all of them by default.
+    #[serde(default)]
+    pub entry_points: Vec<String>,
+}
+
+/// What a binding takes, as named in the generated table.
+fn kind(module: &naga::Module, var: &naga::GlobalVariable) -> &'static str {
+    if matches!(
+        var.space,
+        AddressSpace::Uniform | AddressSpace::Storage { .. }
+    ) {
+        return "buffer";
+    }
+    match module.types[var.ty].inner {
+        TypeInner::Image {
+            class: ImageClass::External,
+            ..
+        } => "externalTexture",
+        TypeInner::Image { .. } => "textureView",
+        TypeInner::Sampler { .. } => "sampler",
+        TypeInner::AccelerationStructure { .. } => "accelerationStructure",
+        _ => "other",
+    }
+}
+
+/// The part of the module that is the same for every shader.
+const RUNTIME: &str = r#"const CLASSES = {
+    buffer: "GPUBuffer",
+    textureView: "GPUTextureView",
+    sampler: "GPUSampler",
+    externalTexture: "GPUExternalTexture",
+    accelerationStructure: "GPUAccelerationStructure",
+};
+
+function isA(value, className) {
+    const cls = className && globalThis[className];
+    return typeof cls !== "function" || value instanceof cls;
+}
+
+function bindingResource(entry, resource) {
+    if (entry.kind !== "buffer") {
+        return isA(resource, CLASSES[entry.kind]) ? resource : undefined;
+    }
+    if (typeof resource === "object" && "buffer" in resource) {
+        return isA(resource.buffer, "GPUBuffer") ? resource : undefined;
+    }
+    return isA(resource, "GPUBuffer") ? { buffer: resource } : undefined;
+}
+
+/**
+ * Creates the bind groups of `pipeline` from `resources`, which maps each
+ * binding's name to a GPUBuffer or GPUBufferBinding, GPUTextureView,
+ * GPUSampler or GPUExternalTexture. Throws listing every missing or
+ * mistyped resource. The result is indexed by group.
+ * @param {GPUDevice} device
+ * @param {GPURenderPipeline | GPUComputePipeline} pipeline
+ * @param {Record<string, GPUBindingResource | GPUBuffer>} resources
+ * @param {string} [label]
+ * @returns {GPUBindGroup[]}
+ */
+export function createBindGroups(device, pipeline, resources, label) {
+    const problems = [];
+    const groups = new Map();
+    for (const entry of BINDINGS) {
+        const slot = `group ${entry.group}, binding ${entry.binding}`;
+        const resource = resources[entry.name];
+        if (resource === undefined || resource === null) {
+            problems.push(`'${entry.name}' (${slot}) is missing`);
+            continue;
+        }
+        const bound = bindingResource(entry, resource);
+        if (bound === undefined) {
+            problems.push(`'${entry.name}' (${slot}) must be a ${CLASSES[entry.kind]}`);
+            continue;
+        }
+        if (!groups.has(entry.group)) groups.set(entry.group, []);
+        groups.get(entry.group).push({ binding: entry.binding, resource: bound });
+    }
+    if (problems.length > 0) {
+        throw new Error(`Cannot create bind groups: ${problems.join("; ")}`);
+    }
+    const bindGroups = [];
+    for (const [group, entries] of groups) {
+        bindGroups[group] = device.createBindGroup({
+            label: label === undefined ? undefined : `${label} group ${group}`,
+            layout: pipeline.getBindGroupLayout(group),
+            entries,
+        });
+    }
+    return bindGroups;
+}
+
+/**
+ * Sets every bind group `createBindGroups` made on `pass`.
+ * @param {GPURenderPassEncoder | GPUComputePassEncoder | GPURenderBundleEncoder} pass
+ * @param {GPUBindGroup[]} bindGroups
+ */
+export function setBindGroups(pass, bindGroups) {
+    bindGroups.forEach((bindGroup, group) => pass.setBindGroup(group, bindGroup));
+}
+"#;
+
+pub(crate) fn bind_group_builder(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    options: &BindGroupBuilderOptions,
+) -> Result<String, Diagnostic> {
+    let mut entry_points = Vec::new();
+    for name in &options.entry_points {
+        crate::find_entry_point(module, name)?;
+        entry_points.push(name.as_str());
+    }
+    let used = |handle: naga::Handle<naga::GlobalVariable>| {
+        module.entry_points.iter().enumerate().any(|(index, ep)| {
+            (entry_points.is_empty() || entry_points.contains(&ep.name.as_str()))
+                && !info.get_entry_point(index)[handle].is_empty()
+        })
+    };
+
+    let mut bindings = Vec::new();
+    for (handle, var) in module.global_variables.iter() {
+        let Some(binding) = &var.binding else {
+            continue;
+        };
+        if !used(handle) {
+            continue;
+        }
+        let name = var
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("binding_{}_{}", binding.group, binding.binding));
+        bindings.push((binding.group, binding.binding, name, kind(module, var)));
+    }
+    bindings.sort();
+
+    // `write!` into a String cannot fail.
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated by generateBindGroupBuilder.\n");
+    let _ = writeln!(
+        out,
+        "/** Every binding the pipeline uses, in group and binding order. */"
+    );
+    let _ = writeln!(out, "export const BINDINGS = [");
+    for (group, binding, name, kind) in &bindings {
+        let name = serde_json::to_string(name).expect("strings always serialize");
+        let _ = writeln!(
+            out,
+            "    {{ name: {name}, group: {group}, binding: {binding}, kind: \"{kind}\" }},"
+        );
+    }
+    let _ = writeln!(out, "];\n");
+    out.push_str(RUNTIME);
+    Ok(out)
+}
+
+/// A JavaScript module for creating the shader's bind groups: `BINDINGS`,
+/// `createBindGroups(device, pipeline, resources, label?)`, which takes a
+/// map of binding name to GPUBuffer, GPUBufferBinding, GPUTextureView or
+/// GPUSampler and throws listing each missing or mistyped one, and
+/// `setBindGroups(pass, bindGroups)`. `options` is `{ entryPoints?:
+/// string[] }`, the entry points the pipeline uses if not all of them.
+#[wasm_bindgen(js_name = generateBindGroupBuilder)]
+pub fn generate_bind_group_builder(wgsl: &str, options: JsValue) -> Result<String, JsValue> {
+    let options: Option<BindGroupBuilderOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid bind group builder options: {e}")))?;
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    bind_group_builder(&module, &info, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Camera { view_proj: mat4x4<f32> }
+        @group(0) @binding(0) var<uniform> camera: Camera;
+        @group(1) @binding(1) var albedo_sampler: sampler;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+        @group(2) @binding(0) var<storage, read_write> counters: array<atomic<u32>>;
+        @group(3) @binding(0) var<storage> unused: array<f32>;
+
+        @vertex fn vs() -> @builtin(position) vec4<f32> { return camera.view_proj[0]; }
+        @fragment fn fs() -> @location(0) vec4<f32> {
+            return textureSample(albedo, albedo_sampler, vec2<f32>(0.5));
+        }
+        @compute @workgroup_size(1) fn count() { atomicAdd(&counters[0], 1u); }
+    "#;
+
+    fn builder(entry_points: &[&str]) -> Result<String, Diagnostic> {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let options = BindGroupBuilderOptions {
+            entry_points: entry_points.iter().map(|name| name.to_string()).collect(),
+        };
+        bind_group_builder(&module, &info, &options)
+    }
+
+    #[test]
+    fn bindings_follow_reflection() {
+        let js = builder(&[]).unwrap();
+        let table = js.split("];").next().unwrap();
+        let rows: Vec<_> = table.lines().filter(|l| l.contains("kind:")).collect();
+        assert_eq!(
+            rows,
+            [
+                "    { name: \"camera\", group: 0, binding: 0, kind: \"buffer\" },",
+                "    { name: \"albedo\", group: 1, binding: 0, kind: \"textureView\" },",
+                "    { name: \"albedo_sampler\", group: 1, binding: 1, kind: \"sampler\" },",
+                "    { name: \"counters\", group: 2, binding: 0, kind: \"buffer\" },",
+            ]
+        );
+        assert!(
+            js.contains("export function createBindGroups(device, pipeline, resources, label)")
+        );
+        assert!(js.contains("layout: pipeline.getBindGroupLayout(group),"));
+        assert!(js.contains("is missing"));
+    }
+
+    #[test]
+    fn entry_points_narrow_the_table() {
+        let js = builder(&["vs", "fs"]).unwrap();
+        assert!(js.contains("name: \"albedo\""));
+        assert!(!js.contains("name: \"counters\""));
+        let js = builder(&["count"]).unwrap();
+        assert!(!js.contains("name: \"camera\""));
+        assert!(js.contains("name: \"counters\""));
+        assert_eq!(
+            builder(&["main"]).err().unwrap().message,
+            "Entry point 'main' not found"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Blit Types
+// ============================================================================
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlitOptions {
+    /// WebGPU texture format of the source and target, e.g. `"rgba8unorm"`.
+    pub format: String,
+    /// `"blit"` copies a whole view, `"mipmap"` downsamples one level into
+    /// the next.
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    /// `"2d"`, `"2d-array"` or `"cube"`.
+    #[serde(default = "default_dimension")]
+    pub dimension: String,
+}
+
+fn default_kind() -> String {
+    "blit".to_string()
+}
+
+fn default_dimension() -> String {
+    "2d".to_string()
+}
+
+/// A generated blit or mipmap shader and the layout it binds against.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BlitShader {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// `"vs_main"`.
+    #[wasm_bindgen(readonly)]
+    pub vertex_entry_point: String,
+    /// `"fs_main"`.
+    #[wasm_bindgen(readonly)]
+    pub fragment_entry_point: String,
+    /// The texture binding's `sampleType`: `"float"`, `"unfilterable-float"`,
+    /// `"uint"`, `"sint"` or `"depth"`.
+    #[wasm_bindgen(readonly)]
+    pub sample_type: String,
+    /// The source view's dimension, `"2d"` or `"2d-array"` (cubes are
+    /// viewed as arrays of six layers).
+    #[wasm_bindgen(readonly)]
+    pub view_dimension: String,
+    /// Whether binding 0 takes a filtering sampler.
+    #[wasm_bindgen(readonly)]
+    pub uses_sampler: bool,
+    /// Whether binding 2 takes a uniform `u32` selecting the layer.
+    #[wasm_bindgen(readonly)]
+    pub uses_layer: bool,
+    /// A `GPUBindGroupLayoutDescriptor` as JSON, for
+    /// `device.createBindGroupLayout(JSON.parse(...))`.
+    #[wasm_bindgen(readonly)]
+    pub bind_group_layout: String,
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl BlitShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Blit Implementation
+// ============================================================================
+//
+// Both shaders draw the post-process fullscreen triangle into a view of the
+// target and read a single-level view of the source; a mipmap chain is one
+// draw per level, reading level n and rendering level n + 1. What the
+// fragment stage may do depends on the format's sample type: filterable
+// formats sample bilinearly, so one shader serves both kinds. Unfilterable
+// floats are loaded, and averaged over the 2x2 footprint for mipmaps;
+// integer formats are loaded and take the top-left texel, as averaging could
+// overflow. Depth is blitted through `frag_depth` and has no mipmap shader,
+// as hierarchical depth wants a min or max rather than an average. Bindings
+// keep fixed slots whatever is used: sampler 0, source 1, layer 2.
+
+/// How a format can be read: its `sampleType`, or why it cannot be drawn.
+fn sample_type(format: &str) -> Result<&'static str, Diagnostic> {
+    let unrenderable = ["bc", "etc2", "eac", "astc"]
+        .iter()
+        .any(|prefix| format.starts_with(prefix))
+        || format.ends_with("snorm")
+        || format == "rgb9e5ufloat";
+    if unrenderable {
+        return Err(Diagnostic::error(format!(
+            "Texture format '{format}' is not renderable"
+        )));
+    }
+    Ok(match format {
+        "r8unorm" | "rg8unorm" | "rgba8unorm" | "rgba8unorm-srgb" | "bgra8unorm"
+        | "bgra8unorm-srgb" | "r16float" | "rg16float" | "rgba16float" | "rgb10a2unorm"
+        | "rg11b10ufloat" => "float",
+        "r32float" | "rg32float" | "rgba32float" => "unfilterable-float",
+        "r8uint" | "rg8uint" | "rgba8uint" | "r16uint" | "rg16uint" | "rgba16uint" | "r32uint"
+        | "rg32uint" | "rgba32uint" | "rgb10a2uint" => "uint",
+        "r8sint" | "rg8sint" | "rgba8sint" | "r16sint" | "rg16sint" | "rgba16sint" | "r32sint"
+        | "rg32sint" | "rgba32sint" => "sint",
+        "depth16unorm"
+        | "depth24plus"
+        | "depth24plus-stencil8"
+        | "depth32float"
+        | "depth32float-stencil8" => "depth",
+        "stencil8" => {
+            return Err(Diagnostic::error(
+                "Texture format 'stencil8' has no depth or color to blit",
+            ));
+        }
+        _ => {
+            return Err(Diagnostic::error(format!(
+                "Unknown texture format '{format}'"
+            )));
+        }
+    })
+}
+
+fn layout_json(sample_type: &str, view_dimension: &str, sampler: bool, layer: bool) -> String {
+    const FRAGMENT: u32 = 2;
+    let mut entries = Vec::new();
+    if sampler {
+        entries.push(serde_json::json!({
+            "binding": 0,
+            "visibility": FRAGMENT,
+            "sampler": { "type": "filtering" },
+        }));
+    }
+    entries.push(serde_json::json!({
+        "binding": 1,
+        "visibility": FRAGMENT,
+        "texture": {
+            "sampleType": sample_type,
+            "viewDimension": view_dimension,
+            "multisampled": false,
+        },
+    }));
+    if layer {
+        entries.push(serde_json::json!({
+            "binding": 2,
+            "visibility": FRAGMENT,
+            "buffer": { "type": "uniform", "minBindingSize": 4 },
+        }));
+    }
+    serde_json::json!({ "entries": entries }).to_string()
+}
+
+pub(crate) fn blit_source(
+    options: &BlitOptions,
+) -> Result<(String, &'static str, &'static str), Diagnostic> {
+    let sample = sample_type(&options.format)?;
+    let mipmap = match options.kind.as_str() {
+        "blit" => false,
+        "mipmap" => true,
+        kind => {
+            return Err(Diagnostic::error(format!(
+                "Unknown blit kind '{kind}'; expected blit or mipmap"
+            )));
+        }
+    };
+    let array = match options.dimension.as_str() {
+        "2d" => false,
+        "2d-array" | "cube" => true,
+        dimension => {
+            return Err(Diagnostic::error(format!(
+                "Unsupported view dimension '{dimension}'; expected 2d, 2d-array or cube"
+            )));
+        }
+    };
+    if mipmap && sample == "depth" {
+        return Err(Diagnostic::error(format!(
+            "Depth format '{}' has no mipmap shader; reduce it with a min or max instead",
+            options.format
+        )));
+    }
+
+    let suffix = if array { "_array" } else { "" };
+    let (texture, output) = match sample {
+        "uint" => (format!("texture_2d{suffix}<u32>"), "@location(0) vec4<u32>"),
+        "sint" => (format!("texture_2d{suffix}<i32>"), "@location(0) vec4<i32>"),
+        "depth" => (
+            format!("texture_depth_2d{suffix}"),
+            "@builtin(frag_depth) f32",
+        ),
+        _ => (format!("texture_2d{suffix}<f32>"), "@location(0) vec4<f32>"),
+    };
+    let layer = if array { ", params.layer" } else { "" };
+
+    let mut out = String::from(crate::postprocess::VERTEX_STAGE);
+    let _ = writeln!(out);
+    if sample == "float" {
+        let _ = writeln!(out, "@group(0) @binding(0) var source_sampler: sampler;");
+    }
+    let _ = writeln!(out, "@group(0) @binding(1) var source: {texture};");
+    if array {
+        let _ = writeln!(
+            out,
+            "\nstruct Params {{\n    layer: u32,\n}}\n@group(0) @binding(2) var<uniform> params: Params;"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "\n@fragment\nfn fs_main(in: VertexOutput) -> {output} {{"
+    );
+    let body = match (sample, mipmap) {
+        ("float", _) => {
+            format!("    return textureSampleLevel(source, source_sampler, in.uv{layer}, 0.0);")
+        }
+        ("unfilterable-float", true) => format!(
+            "    let last = textureDimensions(source) - vec2<u32>(1u);
+    let base = vec2<u32>(in.position.xy) * 2u;
+    let a = textureLoad(source, min(base, last){layer}, 0);
+    let b = textureLoad(source, min(base + vec2<u32>(1u, 0u), last){layer}, 0);
+    let c = textureLoad(source, min(base + vec2<u32>(0u, 1u), last){layer}, 0);
+    let d = textureLoad(source, min(base + vec2<u32>(1u, 1u), last){layer}, 0);
+    return (a + b + c + d) * 0.25;"
+        ),
+        (_, true) => format!(
+            "    let last = textureDimensions(source) - vec2<u32>(1u);
+    return textureLoad(source, min(vec2<u32>(in.position.xy) * 2u, last){layer}, 0);"
+        ),
+        (_, false) => format!(
+            "    let size = textureDimensions(source);
+    let texel = min(vec2<u32>(in.uv * vec2<f32>(size)), size - vec2<u32>(1u));
+    return textureLoad(source, texel{layer}, 0);"
+        ),
+    };
+    let _ = writeln!(out, "{body}\n}}");
+    Ok((out, sample, if array { "2d-array" } else { "2d" }))
+}
+
+pub(crate) fn blit(options: &BlitOptions) -> Result<BlitShader, Diagnostic> {
+    let (wgsl, sample, view_dimension) = blit_source(options)?;
+    let (module, info) = crate::parse_and_validate(&wgsl)?;
+    let uses_sampler = sample == "float";
+    let uses_layer = view_dimension == "2d-array";
+    Ok(BlitShader {
+        reflection: crate::reflect_module(&module, &info),
+        bind_group_layout: layout_json(sample, view_dimension, uses_sampler, uses_layer),
+        wgsl,
+        vertex_entry_point: "vs_main".to_string(),
+        fragment_entry_point: "fs_main".to_string(),
+        sample_type: sample.to_string(),
+        view_dimension: view_dimension.to_string(),
+        uses_sampler,
+        uses_layer,
+    })
+}
+
+/// A validated blit or mipmap-generation shader for a texture format:
+/// `{ format: GPUTextureFormat, kind?: "blit" | "mipmap", dimension?: "2d" |
+/// "2d-array" | "cube" }`, with the matching bind group layout as JSON. Draw
+/// 3 vertices into a view of the target; for mipmaps, bind a view of level
+/// n and render level n + 1.
+#[wasm_bindgen(js_name = generateBlit)]
+pub fn generate_blit(options: JsValue) -> Result<BlitShader, JsValue> {
+    let options: BlitOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid blit options: {e}")))?;
+    blit(&options).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: &str, kind: &str, dimension: &str) -> BlitOptions {
+        BlitOptions {
+            format: format.to_string(),
+            kind: kind.to_string(),
+            dimension: dimension.to_string(),
+        }
+    }
+
+    #[test]
+    fn every_format_class_validates_and_matches_its_layout() {
+        let formats = ["bgra8unorm-srgb", "rgba32float", "rg16uint", "r32sint"];
+        for format in formats {
+            for kind in ["blit", "mipmap"] {
+                for dimension in ["2d", "2d-array", "cube"] {
+                    let shader = blit(&options(format, kind, dimension)).unwrap();
+                    let layout: serde_json::Value =
+                        serde_json::from_str(&shader.bind_group_layout).unwrap();
+                    let entries = layout["entries"].as_array().unwrap();
+                    let fs = &shader.reflection.entry_points[1];
+                    let bindings: Vec<_> = fs.bindings.iter().map(|b| b.binding).collect();
+                    let declared: Vec<_> = entries
+                        .iter()
+                        .map(|e| e["binding"].as_u64().unwrap() as u32)
+                        .collect();
+                    assert_eq!(bindings, declared, "{format} {kind} {dimension}");
+                    let texture = entries.iter().find(|e| e["binding"] == 1).unwrap();
+                    assert_eq!(texture["texture"]["sampleType"], shader.sample_type);
+                }
+            }
+        }
+        let depth = blit(&options("depth32float", "blit", "2d")).unwrap();
+        assert!(depth.wgsl.contains("@builtin(frag_depth) f32"));
+        assert!(!depth.uses_sampler);
+    }
+
+    #[test]
+    fn unsupported_combinations_are_errors() {
+        let error = |format, kind, dimension| {
+            blit(&options(format, kind, dimension))
+                .err()
+                .unwrap()
+                .message
+        };
+        assert!(error("depth24plus", "mipmap", "2d").contains("min or max"));
+        assert!(error("bc1-rgba-unorm", "blit", "2d").contains("not renderable"));
+        assert!(error("rgba8snorm", "blit", "2d").contains("not renderable"));
+        assert!(error("rgba9unorm", "blit", "2d").contains("Unknown texture format"));
+        assert!(error("rgba8unorm", "blit", "3d").contains("view dimension"));
+        assert!(error("rgba8unorm", "copy", "2d").contains("blit kind"));
+    }
+}
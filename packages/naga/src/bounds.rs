@@ -0,0 +1,331 @@
+use naga::proc::index::{GuardedIndex, access_needs_check};
+use naga::proc::{BoundsCheckPolicies, BoundsCheckPolicy};
+use naga::{AddressSpace, Expression, Handle, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::{SourceSpan, throw};
+use crate::preset;
+
+// ============================================================================
+// Bounds Check Types
+// ============================================================================
+
+/// Options object accepted by `reportBoundsChecks`. The preset's policies
+/// apply, and each policy given replaces the preset's for its access kind.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BoundsCheckOptions {
+    /// Device preset name; see `listPresets`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub index: Option<Policy>,
+    #[serde(default)]
+    pub buffer: Option<Policy>,
+    #[serde(default)]
+    pub image_load: Option<Policy>,
+    #[serde(default)]
+    pub binding_array: Option<Policy>,
+}
+
+/// A `BoundsCheckPolicy`, named as `getPreset` shows it.
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Policy {
+    Restrict,
+    ReadZeroSkipWrite,
+    Unchecked,
+}
+
+impl From<Policy> for BoundsCheckPolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Restrict => BoundsCheckPolicy::Restrict,
+            Policy::ReadZeroSkipWrite => BoundsCheckPolicy::ReadZeroSkipWrite,
+            Policy::Unchecked => BoundsCheckPolicy::Unchecked,
+        }
+    }
+}
+
+/// A check a backend injects under the policies.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct InjectedCheck {
+    #[wasm_bindgen(readonly)]
+    pub function: String,
+    /// The function's `id` in reflection.
+    #[wasm_bindgen(readonly)]
+    pub function_id: String,
+    /// Policy that applies: `"index"`, `"buffer"`, `"image_load"` or
+    /// `"binding_array"`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    /// `"restrict"` (clamped) or `"read_zero_skip_write"` (guarded).
+    #[wasm_bindgen(readonly)]
+    pub policy: String,
+    /// Variable or argument being indexed or loaded from, if named.
+    #[wasm_bindgen(readonly)]
+    pub resource: Option<String>,
+    /// Length the index is checked against; `None` for runtime-sized arrays
+    /// and image loads.
+    #[wasm_bindgen(readonly)]
+    pub length: Option<u32>,
+    /// The checked access.
+    #[wasm_bindgen(readonly)]
+    pub span: Option<SourceSpan>,
+}
+
+#[wasm_bindgen]
+impl InjectedCheck {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Bounds Check Implementation
+// ============================================================================
+//
+// Backends decide per expression: an `Access` or `AccessIndex` into an array,
+// vector or matrix is checked unless its index is a constant known to be in
+// range, and every `ImageLoad` is checked, each under the policy for what it
+// indexes. This asks naga's own `access_needs_check` the same question the
+// backends do, so the report matches what they emit.
+
+fn policy_name(policy: BoundsCheckPolicy) -> &'static str {
+    match policy {
+        BoundsCheckPolicy::Restrict => "restrict",
+        BoundsCheckPolicy::ReadZeroSkipWrite => "read_zero_skip_write",
+        BoundsCheckPolicy::Unchecked => "unchecked",
+    }
+}
+
+/// Name of the variable or argument an access chain starting at `expr`
+/// indexes into.
+fn resource_name(
+    module: &naga::Module,
+    function: &naga::Function,
+    mut expr: Handle<Expression>,
+) -> Option<String> {
+    loop {
+        match function.expressions[expr] {
+            Expression::Access { base, .. } | Expression::AccessIndex { base, .. } => expr = base,
+            Expression::Load { pointer } => expr = pointer,
+            Expression::GlobalVariable(h) => return module.global_variables[h].name.clone(),
+            Expression::LocalVariable(h) => return function.local_variables[h].name.clone(),
+            Expression::FunctionArgument(i) => {
+                return function.arguments[i as usize].name.clone();
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn function_checks(
+    source: &str,
+    module: &naga::Module,
+    name: &str,
+    function: &naga::Function,
+    info: &naga::valid::FunctionInfo,
+    policies: &BoundsCheckPolicies,
+    checks: &mut Vec<InjectedCheck>,
+) {
+    for (h, expr) in function.expressions.iter() {
+        let (kind, policy, resource, length) = match *expr {
+            Expression::Access { base, .. } | Expression::AccessIndex { base, .. } => {
+                let index = match *expr {
+                    Expression::Access { index, .. } => GuardedIndex::Expression(index),
+                    Expression::AccessIndex { index, .. } => GuardedIndex::Known(index),
+                    _ => unreachable!(),
+                };
+                let inner = info[base].ty.inner_with(&module.types);
+                let pointee = match *inner {
+                    TypeInner::Pointer { base, .. } => &module.types[base].inner,
+                    _ => inner,
+                };
+                if matches!(*pointee, TypeInner::Struct { .. }) {
+                    continue;
+                }
+                // Override-sized arrays have no length until specialized.
+                if inner.indexable_length_resolved(module).is_err() {
+                    continue;
+                }
+                let kind = match (pointee, inner.pointer_space()) {
+                    (TypeInner::BindingArray { .. }, _) => "binding_array",
+                    (_, Some(AddressSpace::Storage { .. } | AddressSpace::Uniform)) => "buffer",
+                    _ => "index",
+                };
+                let policy = policies.choose_policy(base, &module.types, info);
+                if policy == BoundsCheckPolicy::Unchecked {
+                    continue;
+                }
+                let Some(length) =
+                    access_needs_check(base, index, module, &function.expressions, info)
+                else {
+                    continue;
+                };
+                let length = match length {
+                    naga::proc::IndexableLength::Known(n) => Some(n),
+                    naga::proc::IndexableLength::Dynamic => None,
+                };
+                (kind, policy, resource_name(module, function, base), length)
+            }
+            Expression::ImageLoad { image, .. } => {
+                if policies.image_load == BoundsCheckPolicy::Unchecked {
+                    continue;
+                }
+                let resource = resource_name(module, function, image);
+                ("image_load", policies.image_load, resource, None)
+            }
+            _ => continue,
+        };
+        checks.push(InjectedCheck {
+            function: name.to_string(),
+            function_id: crate::function_id(module, name),
+            kind: kind.to_string(),
+            policy: policy_name(policy).to_string(),
+            resource,
+            length,
+            span: SourceSpan::new(source, function.expressions.get_span(h)),
+        });
+    }
+}
+
+fn report(wgsl: &str, options: &BoundsCheckOptions) -> Result<Vec<InjectedCheck>, Diagnostic> {
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let mut policies = preset.map(|p| p.bounds_checks).unwrap_or_default();
+    let overrides = [
+        (&mut policies.index, options.index),
+        (&mut policies.buffer, options.buffer),
+        (&mut policies.image_load, options.image_load),
+        (&mut policies.binding_array, options.binding_array),
+    ];
+    for (policy, replacement) in overrides {
+        if let Some(replacement) = replacement {
+            *policy = replacement.into();
+        }
+    }
+
+    let module = crate::parse_wgsl(wgsl)?;
+    let info = crate::validate_for(&module, preset)?;
+    Ok(module_checks(wgsl, &module, &info, &policies))
+}
+
+/// Checks injected into every function of a validated module, in source
+/// order.
+pub(crate) fn module_checks(
+    source: &str,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    policies: &BoundsCheckPolicies,
+) -> Vec<InjectedCheck> {
+    let mut checks = Vec::new();
+    for (handle, function) in module.functions.iter() {
+        let name = function.name.as_deref().unwrap_or("?");
+        let info = &info[handle];
+        function_checks(source, module, name, function, info, policies, &mut checks);
+    }
+    for (index, ep) in module.entry_points.iter().enumerate() {
+        let info = info.get_entry_point(index);
+        function_checks(
+            source,
+            module,
+            &ep.name,
+            &ep.function,
+            info,
+            policies,
+            &mut checks,
+        );
+    }
+    checks.sort_by_key(|c| c.span.as_ref().map_or(u32::MAX, |s| s.start));
+    checks
+}
+
+/// Every bounds check the backends inject into `wgsl` under a preset's
+/// policies, with the access's span and the resource it indexes, so the cost
+/// of the checks can be audited. `options` is `{ preset?, index?, buffer?,
+/// imageLoad?, bindingArray? }`; each policy is `"restrict"`,
+/// `"read_zero_skip_write"` or `"unchecked"` and replaces the preset's. With
+/// neither, nothing is checked and the report is empty.
+#[wasm_bindgen(js_name = reportBoundsChecks)]
+pub fn report_bounds_checks(wgsl: &str, options: JsValue) -> Result<Vec<InjectedCheck>, JsValue> {
+    let options: Option<BoundsCheckOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid bounds check options: {e}")))?;
+    report(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read> lights: array<vec4<f32>>;
+        @group(0) @binding(1) var<uniform> palette: array<vec4<f32>, 8>;
+        @group(0) @binding(2) var albedo: texture_2d<f32>;
+
+        @fragment
+        fn fs(@location(0) @interpolate(flat) i: u32) -> @location(0) vec4<f32> {
+            var weights = array<f32, 4>(1.0, 0.5, 0.25, 0.125);
+            let w = weights[i] + weights[2];
+            let texel = textureLoad(albedo, vec2<u32>(i, 0u), 0);
+            return lights[i] * palette[i] * texel * w;
+        }
+    "#;
+
+    fn kinds(checks: &[InjectedCheck]) -> Vec<(&str, Option<&str>, Option<u32>)> {
+        checks
+            .iter()
+            .map(|c| (c.kind.as_str(), c.resource.as_deref(), c.length))
+            .collect()
+    }
+
+    #[test]
+    fn checks_follow_the_policies() {
+        let checks = report(SHADER, &BoundsCheckOptions::default()).unwrap();
+        assert!(checks.is_empty());
+
+        let options = BoundsCheckOptions {
+            preset: Some("webgpu-default".to_string()),
+            ..Default::default()
+        };
+        let checks = report(SHADER, &options).unwrap();
+        assert_eq!(
+            kinds(&checks),
+            [
+                ("index", Some("weights"), Some(4)),
+                ("image_load", Some("albedo"), None),
+                ("buffer", Some("lights"), None),
+                ("buffer", Some("palette"), Some(8)),
+            ]
+        );
+        assert!(checks.iter().all(|c| c.policy == "restrict"));
+        assert_eq!(checks[0].span.as_ref().unwrap().line, 9);
+    }
+
+    #[test]
+    fn policies_override_the_preset() {
+        let options = BoundsCheckOptions {
+            preset: Some("webgpu-default".to_string()),
+            buffer: Some(Policy::Unchecked),
+            image_load: Some(Policy::ReadZeroSkipWrite),
+            ..Default::default()
+        };
+        let checks = report(SHADER, &options).unwrap();
+        assert_eq!(
+            kinds(&checks),
+            [
+                ("index", Some("weights"), Some(4)),
+                ("image_load", Some("albedo"), None),
+            ]
+        );
+        assert_eq!(checks[1].policy, "read_zero_skip_write");
+    }
+}
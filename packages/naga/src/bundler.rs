@@ -0,0 +1,230 @@
+use std::fmt::Write;
+
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::Target;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Bundler Query
+// ============================================================================
+
+/// Options parsed from a bundler import query, e.g. `?target=spirv,msl&entryPoint=main`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct BundlerQuery {
+    pub targets: Vec<Target>,
+    pub entry_point: Option<String>,
+    pub reflection: bool,
+}
+
+impl Default for BundlerQuery {
+    fn default() -> Self {
+        Self {
+            targets: vec![Target::Spirv],
+            entry_point: None,
+            reflection: true,
+        }
+    }
+}
+
+impl BundlerQuery {
+    /// Parse a query string. Unknown keys are rejected so that typos in an
+    /// import specifier fail the build instead of being silently ignored.
+    pub(crate) fn parse(query: &str) -> Result<Self, Diagnostic> {
+        let mut parsed = Self::default();
+        let query = query.strip_prefix('?').unwrap_or(query);
+
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "target" => {
+                    parsed.targets = value
+                        .split(',')
+                        .map(|t| {
+                            Target::parse(t).ok_or_else(|| {
+                                Diagnostic::error(format!("Unknown bundler target '{}'", t))
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                "entryPoint" => parsed.entry_point = Some(value.to_string()),
+                "reflection" => parsed.reflection = value != "false",
+                _ => {
+                    return Err(Diagnostic::error(format!(
+                        "Unknown bundler query parameter '{}'",
+                        key
+                    )));
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+// ============================================================================
+// ESM Generation
+// ============================================================================
+
+/// Compiles `source` per `query` and returns an ES module exporting the results.
+///
+/// The module has the shape:
+///
+/// ```js
+/// export const reflection = { entryPoints: [...], types: [...] }; // unless reflection=false
+/// export const entryPoints = {
+///     main: { stage: "compute", spirv: Uint8Array, msl: "..." },
+/// };
+/// export default { reflection, entryPoints };
+/// ```
+///
+/// Supported query keys: `target` (comma-separated `spirv`/`msl`, default `spirv`),
+/// `entryPoint` (restrict output to one entry point) and `reflection` (`false` to omit).
+/// This output shape is part of the public API; bundler plugins should only wrap it.
+#[wasm_bindgen(js_name = compileForBundler)]
+pub fn compile_for_bundler(source: &str, query: &str) -> Result<String, JsValue> {
+    bundle(source, query).map_err(throw)
+}
+
+pub(crate) fn bundle(source: &str, query: &str) -> Result<String, Diagnostic> {
+    let query = BundlerQuery::parse(query)?;
+    let (module, info) = crate::parse_and_validate(source)?;
+
+    let entries: Vec<&naga::EntryPoint> = match query.entry_point.as_deref() {
+        Some(name) => vec![crate::find_entry_point(&module, name)?],
+        None => module.entry_points.iter().collect(),
+    };
+
+    let mut out = String::new();
+    out.push_str("// Generated by naga-wasm compileForBundler. Do not edit.\n");
+    out.push_str("const decode = (b64) => Uint8Array.from(atob(b64), (c) => c.charCodeAt(0));\n");
+
+    if query.reflection {
+        let mut reflection = crate::reflect_module(&module, &info);
+        if let Some(name) = query.entry_point.as_deref() {
+            reflection.entry_points.retain(|e| e.name == name);
+        }
+        let json = serde_json::to_string(&reflection).expect("reflection is always serializable");
+        writeln!(out, "export const reflection = {};", json).unwrap();
+    } else {
+        out.push_str("export const reflection = null;\n");
+    }
+
+    out.push_str("export const entryPoints = {\n");
+    for entry in entries {
+        writeln!(
+            out,
+            "    {}: {{\n        stage: {},",
+            js_string(&entry.name),
+            js_string(crate::stage_name(entry.stage))
+        )
+        .unwrap();
+        for target in &query.targets {
+            match target {
+                Target::Spirv => {
+                    let bytes = crate::write_spirv(&module, &info, Some(&entry.name))?;
+                    writeln!(out, "        spirv: decode(\"{}\"),", base64(&bytes)).unwrap();
+                }
+                Target::Msl => {
+                    let msl = crate::write_msl(&module, &info, Some(&entry.name))?;
+                    writeln!(out, "        msl: {},", js_string(&msl)).unwrap();
+                }
+            }
+        }
+        out.push_str("    },\n");
+    }
+    out.push_str("};\n");
+    out.push_str("export default { reflection, entryPoints };\n");
+
+    Ok(out)
+}
+
+/// Quote a string as a JS string literal.
+pub(crate) fn js_string(s: &str) -> String {
+    serde_json::to_string(s).expect("strings are always serializable")
+}
+
+/// Standard (padded) base64, decodable with `atob`.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @vertex
+        fn vs_main() -> @builtin(position) vec4f {
+            return vec4f(0.0);
+        }
+
+        @compute @workgroup_size(1)
+        fn cs_main() {}
+    "#;
+
+    #[test]
+    fn base64_matches_reference() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn query_parsing() {
+        assert_eq!(BundlerQuery::parse("").unwrap(), BundlerQuery::default());
+        let q = BundlerQuery::parse("?target=spirv,msl&entryPoint=main&reflection=false").unwrap();
+        assert_eq!(q.targets, vec![Target::Spirv, Target::Msl]);
+        assert_eq!(q.entry_point.as_deref(), Some("main"));
+        assert!(!q.reflection);
+        assert!(BundlerQuery::parse("targte=msl").is_err());
+        assert!(BundlerQuery::parse("target=dxil").is_err());
+    }
+
+    #[test]
+    fn module_exports_every_entry_point() {
+        let esm = bundle(SHADER, "target=spirv,msl").unwrap();
+        assert!(esm.contains("\"vs_main\": {"));
+        assert!(esm.contains("\"cs_main\": {"));
+        assert!(esm.contains("spirv: decode(\""));
+        assert!(esm.contains("msl: \""));
+        assert!(esm.contains("export default { reflection, entryPoints };"));
+    }
+
+    #[test]
+    fn entry_point_filter_applies_to_reflection() {
+        let esm = bundle(SHADER, "entryPoint=cs_main").unwrap();
+        assert!(!esm.contains("vs_main"));
+        assert!(bundle(SHADER, "entryPoint=missing").is_err());
+    }
+}
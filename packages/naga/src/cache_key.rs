@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::hash::sha256_hex;
+use crate::provenance::NAGA_VERSION;
+
+// ============================================================================
+// Pipeline Cache Key Types
+// ============================================================================
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheKeyShader {
+    /// The WGSL, after any `#include`s are resolved.
+    pub source: String,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    #[serde(default)]
+    pub stage: Option<String>,
+}
+
+/// Input of `pipelineCacheKey`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheKeyInputs {
+    #[serde(default)]
+    pub shaders: Vec<CacheKeyShader>,
+    /// Pipeline constant values by override name or `@id`.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, f64>,
+    /// The target and backend options, whatever their shape.
+    #[serde(default)]
+    pub target_options: Value,
+}
+
+// ============================================================================
+// Pipeline Cache Key Implementation
+// ============================================================================
+//
+// The key is the SHA-256 of one canonical encoding of everything a compile
+// depends on: this crate's and naga's versions, which stand in for preset
+// tables and backend behavior, then every shader's exact source, entry
+// point and stage in the order given, the overrides, and the target
+// options. Sources are hashed byte for byte, so whitespace and comment
+// edits change the key; a key may change when output would not, but never
+// the other way round. The encoding is JSON with object keys sorted and
+// every number written as an `f64`, so `{ a: 1, b: 2 }` and `{ b: 2, a: 1.0 }`
+// give the same key, while string escaping keeps one shader's source from
+// running into the next. Override values are encoded by their bits, so
+// `-0` and `0` differ.
+
+/// Version of the encoding, bumped whenever it changes.
+const SCHEME: &str = "metis-pipeline-cache-key/1";
+
+/// Append `value` to `out` in canonical JSON.
+fn canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&value.to_string()),
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or(f64::NAN);
+            out.push_str(&format!("{:?}", number));
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical(item, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+pub(crate) fn cache_key(inputs: &CacheKeyInputs) -> String {
+    let shaders: Vec<Value> = inputs
+        .shaders
+        .iter()
+        .map(|shader| {
+            serde_json::json!({
+                "source": shader.source,
+                "entryPoint": shader.entry_point,
+                "stage": shader.stage,
+            })
+        })
+        .collect();
+    let overrides: serde_json::Map<String, Value> = inputs
+        .overrides
+        .iter()
+        .map(|(name, value)| (name.clone(), format!("{:016x}", value.to_bits()).into()))
+        .collect();
+    let everything = serde_json::json!({
+        "scheme": SCHEME,
+        "compiler": env!("CARGO_PKG_VERSION"),
+        "naga": NAGA_VERSION,
+        "shaders": shaders,
+        "overrides": overrides,
+        "targetOptions": inputs.target_options,
+    });
+    let mut encoded = String::new();
+    canonical(&everything, &mut encoded);
+    sha256_hex(encoded.as_bytes())
+}
+
+/// A key for caching compiled pipelines, as lowercase SHA-256 hex,
+/// covering everything that affects the output: `{ shaders: [{ source,
+/// entryPoint?, stage? }], overrides?: { [name]: number }, targetOptions? }`,
+/// plus this compiler's and naga's versions. `targetOptions` is whatever
+/// options object the compile is given, with the target. Equal inputs give
+/// equal keys whatever their key order; any change to a source, entry
+/// point, override or option gives a new one.
+#[wasm_bindgen(js_name = pipelineCacheKey)]
+pub fn pipeline_cache_key(inputs: JsValue) -> Result<String, JsValue> {
+    let inputs: CacheKeyInputs = serde_wasm_bindgen::from_value(inputs)
+        .map_err(|e| JsValue::from_str(&format!("Invalid cache key inputs: {e}")))?;
+    Ok(cache_key(&inputs))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shader(source: &str) -> CacheKeyShader {
+        CacheKeyShader {
+            source: source.to_string(),
+            entry_point: None,
+            stage: None,
+        }
+    }
+
+    fn inputs(sources: &[&str], target_options: Value) -> CacheKeyInputs {
+        CacheKeyInputs {
+            shaders: sources.iter().map(|s| shader(s)).collect(),
+            overrides: BTreeMap::new(),
+            target_options,
+        }
+    }
+
+    #[test]
+    fn keys_ignore_option_order_and_number_spelling() {
+        let a = inputs(
+            &["@fragment fn fs() {}"],
+            serde_json::json!({ "target": "msl", "version": 2, "flags": ["a"] }),
+        );
+        let b = inputs(
+            &["@fragment fn fs() {}"],
+            serde_json::json!({ "flags": ["a"], "version": 2.0, "target": "msl" }),
+        );
+        let key = cache_key(&a);
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, cache_key(&b));
+        assert_eq!(key, cache_key(&a));
+    }
+
+    #[test]
+    fn every_input_changes_the_key() {
+        let base = || inputs(&["ab", "c"], serde_json::json!({ "target": "spirv" }));
+        let key = cache_key(&base());
+
+        let mut changed = Vec::new();
+        changed.push(inputs(
+            &["a", "bc"],
+            serde_json::json!({ "target": "spirv" }),
+        ));
+        changed.push(inputs(&["ab", "c"], serde_json::json!({ "target": "msl" })));
+        changed.push(inputs(&["ab", "c"], Value::Null));
+        let mut entry_point = base();
+        entry_point.shaders[0].entry_point = Some("main".to_string());
+        changed.push(entry_point);
+        let mut stage = base();
+        stage.shaders[1].stage = Some("fragment".to_string());
+        changed.push(stage);
+        let mut overrides = base();
+        overrides.overrides.insert("scale".to_string(), 0.0);
+        changed.push(overrides);
+        let mut negative = base();
+        negative.overrides.insert("scale".to_string(), -0.0);
+        changed.push(negative);
+
+        let mut keys: Vec<_> = changed.iter().map(cache_key).collect();
+        keys.push(key);
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+    }
+}
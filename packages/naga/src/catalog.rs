@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use wasm_bindgen::prelude::*;
+
+// ============================================================================
+// Message Catalog
+// ============================================================================
+//
+// A catalog maps English message templates to translations, e.g.
+// `{ "Type '{name}' not found": "Typ '{name}' nicht gefunden" }`. A template
+// is a message with its variable parts replaced by named `{placeholders}`,
+// which match any text; `{{` and `}}` stand for literal braces. Every
+// diagnostic message, label and note goes through the catalog when it is
+// created, so severities, lint rules and spans are never touched, and a
+// message that wraps another is translated around the already translated
+// inner one. A message no template matches as a whole is translated line
+// by line, so the headline of a parse error report can be translated while
+// its source excerpt stays. Without a catalog, messages pass through as is.
+//
+// The catalog is global rather than per thread, so batch jobs running on
+// the thread pool translate the same way.
+
+#[derive(Debug)]
+enum Segment {
+    Text(String),
+    Hole(String),
+}
+
+#[derive(Debug)]
+struct Template {
+    pattern: Vec<Segment>,
+    translation: Vec<Segment>,
+    /// Literal characters in `pattern`; more specific templates try first.
+    weight: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Catalog {
+    /// Templates without placeholders, by message.
+    exact: HashMap<String, String>,
+    /// Templates with placeholders, most specific first.
+    templates: Vec<Template>,
+}
+
+static CATALOG: RwLock<Option<Catalog>> = RwLock::new(None);
+
+fn segments(template: &str) -> Result<Vec<Segment>, String> {
+    let mut out = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let valid =
+                    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(format!("bad placeholder '{{{name}' in \"{template}\""));
+                }
+                if matches!(out.last(), Some(Segment::Hole(_))) && text.is_empty() {
+                    return Err(format!("adjacent placeholders in \"{template}\""));
+                }
+                if !text.is_empty() {
+                    out.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                out.push(Segment::Hole(name));
+            }
+            '}' => return Err(format!("unmatched '}}' in \"{template}\"")),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        out.push(Segment::Text(text));
+    }
+    Ok(out)
+}
+
+/// Bind the holes of `pattern`, in order, to the parts of `message` they
+/// cover.
+fn bind<'a>(pattern: &[Segment], message: &'a str, values: &mut Vec<&'a str>) -> bool {
+    match pattern.split_first() {
+        None => message.is_empty(),
+        Some((Segment::Text(text), rest)) => {
+            message.starts_with(text.as_str()) && bind(rest, &message[text.len()..], values)
+        }
+        Some((Segment::Hole(_), _)) if message.is_empty() => false,
+        Some((Segment::Hole(_), rest)) => {
+            // Holes take as little as lets the rest match, but never nothing.
+            let ends = message.char_indices().map(|(i, _)| i).skip(1);
+            for end in ends.chain([message.len()]) {
+                values.push(&message[..end]);
+                if bind(rest, &message[end..], values) {
+                    return true;
+                }
+                values.pop();
+            }
+            false
+        }
+    }
+}
+
+impl Catalog {
+    pub(crate) fn parse(messages: BTreeMap<String, String>) -> Result<Self, String> {
+        let mut catalog = Catalog::default();
+        for (template, translation) in messages {
+            let pattern = segments(&template)?;
+            let translation = segments(&translation)?;
+            let holes: Vec<&String> = pattern
+                .iter()
+                .filter_map(|s| match s {
+                    Segment::Hole(name) => Some(name),
+                    Segment::Text(_) => None,
+                })
+                .collect();
+            for segment in &translation {
+                if let Segment::Hole(name) = segment
+                    && !holes.contains(&name)
+                {
+                    return Err(format!(
+                        "translation of \"{template}\" uses '{{{name}}}', which it does not have"
+                    ));
+                }
+            }
+            if holes.is_empty() {
+                catalog
+                    .exact
+                    .insert(template_text(&pattern), template_text(&translation));
+                continue;
+            }
+            let weight = pattern
+                .iter()
+                .map(|s| match s {
+                    Segment::Text(text) => text.chars().count(),
+                    Segment::Hole(_) => 0,
+                })
+                .sum();
+            catalog.templates.push(Template {
+                pattern,
+                translation,
+                weight,
+            });
+        }
+        catalog
+            .templates
+            .sort_by_key(|t| std::cmp::Reverse(t.weight));
+        Ok(catalog)
+    }
+
+    fn translate_whole(&self, message: &str) -> Option<String> {
+        if let Some(text) = self.exact.get(message) {
+            return Some(text.clone());
+        }
+        self.templates.iter().find_map(|template| {
+            let mut values = Vec::new();
+            if !bind(&template.pattern, message, &mut values) {
+                return None;
+            }
+            let holes = template.pattern.iter().filter_map(|s| match s {
+                Segment::Hole(name) => Some(name.as_str()),
+                Segment::Text(_) => None,
+            });
+            let values: HashMap<&str, &str> = holes.zip(values).collect();
+            Some(
+                template
+                    .translation
+                    .iter()
+                    .map(|s| match s {
+                        Segment::Text(text) => text.as_str(),
+                        Segment::Hole(name) => values[name.as_str()],
+                    })
+                    .collect(),
+            )
+        })
+    }
+
+    pub(crate) fn translate(&self, message: &str) -> Option<String> {
+        if let Some(text) = self.translate_whole(message) {
+            return Some(text);
+        }
+        if !message.contains('\n') {
+            return None;
+        }
+        let mut changed = false;
+        let lines: Vec<String> = message
+            .split('\n')
+            .map(|line| match self.translate_whole(line) {
+                Some(text) => {
+                    changed = true;
+                    text
+                }
+                None => line.to_string(),
+            })
+            .collect();
+        changed.then(|| lines.join("\n"))
+    }
+}
+
+/// The text of `pattern`, which has no placeholders.
+fn template_text(pattern: &[Segment]) -> String {
+    pattern
+        .iter()
+        .map(|s| match s {
+            Segment::Text(text) => text.as_str(),
+            Segment::Hole(_) => "",
+        })
+        .collect()
+}
+
+/// `message` as the installed catalog translates it.
+pub(crate) fn localize(message: String) -> String {
+    let catalog = CATALOG.read().unwrap_or_else(|e| e.into_inner());
+    match catalog.as_ref().and_then(|c| c.translate(&message)) {
+        Some(text) => text,
+        None => message,
+    }
+}
+
+/// Install a message catalog, an object mapping English message templates
+/// to translations, with `{name}` placeholders for the parts that vary, e.g.
+/// `{ "Type '{name}' not found": "Typ '{name}' nicht gefunden" }`. Applies
+/// to every diagnostic created afterwards, on every thread; `null` removes
+/// the catalog. Returns how many templates were installed.
+#[wasm_bindgen(js_name = setMessageCatalog)]
+pub fn set_message_catalog(catalog: JsValue) -> Result<u32, JsValue> {
+    let messages: Option<BTreeMap<String, String>> = serde_wasm_bindgen::from_value(catalog)
+        .map_err(|e| JsValue::from_str(&format!("Invalid message catalog: {e}")))?;
+    let catalog = match messages {
+        Some(messages) => Some(
+            Catalog::parse(messages)
+                .map_err(|e| JsValue::from_str(&format!("Invalid message catalog: {e}")))?,
+        ),
+        None => None,
+    };
+    let count = catalog
+        .as_ref()
+        .map_or(0, |c| c.exact.len() + c.templates.len());
+    *CATALOG.write().unwrap_or_else(|e| e.into_inner()) = catalog;
+    Ok(count as u32)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(entries: &[(&str, &str)]) -> Catalog {
+        let messages = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Catalog::parse(messages).unwrap()
+    }
+
+    #[test]
+    fn templates_bind_placeholders() {
+        let catalog = catalog(&[
+            ("Type '{name}' not found", "Typ '{name}' nicht gefunden"),
+            (
+                "Pass '{pass}' produced an invalid module: {reason}",
+                "Durchlauf '{pass}' ergab ein ungültiges Modul: {reason}",
+            ),
+            ("Entry point not found", "Einstiegspunkt nicht gefunden"),
+            ("Missing {{braces}}", "Fehlende {{Klammern}}"),
+        ]);
+        assert_eq!(
+            catalog.translate("Type 'Light' not found").unwrap(),
+            "Typ 'Light' nicht gefunden"
+        );
+        assert_eq!(
+            catalog
+                .translate("Pass 'dce' produced an invalid module: Type 'X' not found")
+                .unwrap(),
+            "Durchlauf 'dce' ergab ein ungültiges Modul: Type 'X' not found"
+        );
+        assert_eq!(
+            catalog.translate("Entry point not found").unwrap(),
+            "Einstiegspunkt nicht gefunden"
+        );
+        assert_eq!(
+            catalog.translate("Missing {braces}").unwrap(),
+            "Fehlende {Klammern}"
+        );
+        assert!(catalog.translate("Type 'Light' is not a struct").is_none());
+        // The headline of a report translates; the excerpt stays.
+        assert_eq!(
+            catalog
+                .translate("Type 'A' not found\n  ┌─ wgsl:1:1")
+                .unwrap(),
+            "Typ 'A' nicht gefunden\n  ┌─ wgsl:1:1"
+        );
+    }
+
+    #[test]
+    fn bad_templates_are_rejected() {
+        let parse = |template: &str, translation: &str| {
+            let messages = [(template.to_string(), translation.to_string())];
+            Catalog::parse(messages.into_iter().collect())
+                .err()
+                .unwrap()
+        };
+        assert_eq!(
+            parse("Type '{name}' not found", "Typ '{typ}' fehlt"),
+            "translation of \"Type '{name}' not found\" uses '{typ}', which it does not have"
+        );
+        assert!(parse("Type '{name' not found", "x").starts_with("bad placeholder"));
+        assert!(parse("{a}{b}", "x").starts_with("adjacent placeholders"));
+    }
+}
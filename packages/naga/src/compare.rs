@@ -0,0 +1,499 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::spv::{self, SpirvOptions};
+
+// ============================================================================
+// Comparison Types
+// ============================================================================
+
+/// Cost metrics of one shader, from its compacted SPIR-V and reflection.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ShaderStats {
+    /// SPIR-V instructions inside function bodies.
+    #[wasm_bindgen(readonly)]
+    pub instructions: u32,
+    #[wasm_bindgen(readonly)]
+    pub arithmetic: u32,
+    /// Extended instruction set calls: `sin`, `pow`, `normalize`, ...
+    #[wasm_bindgen(readonly)]
+    pub math_calls: u32,
+    /// Filtered reads: samples and gathers.
+    #[wasm_bindgen(readonly)]
+    pub texture_samples: u32,
+    /// Unfiltered reads and storage texture writes.
+    #[wasm_bindgen(readonly)]
+    pub texture_loads_stores: u32,
+    #[wasm_bindgen(readonly)]
+    pub branches: u32,
+    #[wasm_bindgen(readonly)]
+    pub loops: u32,
+    #[wasm_bindgen(readonly)]
+    pub barriers: u32,
+    #[wasm_bindgen(readonly)]
+    pub bindings: u32,
+    /// User-defined (`@location`) inputs and outputs over the entry points.
+    #[wasm_bindgen(readonly)]
+    pub inputs: u32,
+    #[wasm_bindgen(readonly)]
+    pub outputs: u32,
+    #[wasm_bindgen(readonly)]
+    pub spirv_bytes: u32,
+    /// `instructions` broken down by function, in SPIR-V order.
+    #[wasm_bindgen(readonly)]
+    pub functions: Vec<FunctionStats>,
+}
+
+#[wasm_bindgen]
+impl ShaderStats {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Instructions in one function or entry point that survived compaction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct FunctionStats {
+    /// The function's `id` in reflection, so counts correlate across edits.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub instructions: u32,
+}
+
+#[wasm_bindgen]
+impl FunctionStats {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// One metric side by side.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MetricDelta {
+    /// The `ShaderStats` field, e.g. `"textureSamples"`.
+    #[wasm_bindgen(readonly)]
+    pub metric: String,
+    #[wasm_bindgen(readonly)]
+    pub a: u32,
+    #[wasm_bindgen(readonly)]
+    pub b: u32,
+    /// `b - a`.
+    #[wasm_bindgen(readonly)]
+    pub change: i64,
+    /// The change relative to `a`; unset when `a` is zero.
+    #[wasm_bindgen(readonly)]
+    pub percent: Option<f64>,
+}
+
+#[wasm_bindgen]
+impl MetricDelta {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ShaderComparison {
+    #[wasm_bindgen(readonly)]
+    pub a: ShaderStats,
+    #[wasm_bindgen(readonly)]
+    pub b: ShaderStats,
+    /// Every metric, in `ShaderStats` order.
+    #[wasm_bindgen(readonly)]
+    pub deltas: Vec<MetricDelta>,
+    /// Bindings and `@location`s present in only one of the two, e.g.
+    /// `"fs: input @location(1) uv only in b"`.
+    #[wasm_bindgen(readonly)]
+    pub interface_changes: Vec<String>,
+    /// How `b` compares to `a` on cost: `"better"`, `"worse"`, `"mixed"` or
+    /// `"same"`.
+    #[wasm_bindgen(readonly)]
+    pub verdict: String,
+    /// One line for review comments, e.g. `"b is better: 12 fewer
+    /// instructions (-8.0%), 1 fewer texture sample"`.
+    #[wasm_bindgen(readonly)]
+    pub summary: String,
+}
+
+#[wasm_bindgen]
+impl ShaderComparison {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Comparison Implementation
+// ============================================================================
+//
+// Counts come from SPIR-V compiled with `compact`, so helpers a shader
+// carries but never calls do not count against it, and only instructions in
+// function bodies are counted, leaving out types, constants and decorations.
+// The verdict looks at the cost metrics only, everything but the interface
+// and binary size: `b` is better if none went up and some went down. These
+// are static counts, a proxy for review, not a measurement; a loop counts
+// once however often it runs. The per-function breakdown is read from a
+// second compile with debug names, which only adds `OpName`s, so the
+// bodies and counts match while `spirvBytes` stays that of a plain build.
+
+/// Metrics the verdict weighs, with their singular and plural names.
+const COST_METRICS: &[(&str, &str, &str)] = &[
+    ("instructions", "instruction", "instructions"),
+    ("arithmetic", "arithmetic op", "arithmetic ops"),
+    ("mathCalls", "math call", "math calls"),
+    ("textureSamples", "texture sample", "texture samples"),
+    (
+        "textureLoadsStores",
+        "texture load/store",
+        "texture loads/stores",
+    ),
+    ("branches", "branch", "branches"),
+    ("loops", "loop", "loops"),
+    ("barriers", "barrier", "barriers"),
+    ("bindings", "binding", "bindings"),
+];
+
+impl ShaderStats {
+    fn metrics(&self) -> [(&'static str, u32); 12] {
+        [
+            ("instructions", self.instructions),
+            ("arithmetic", self.arithmetic),
+            ("mathCalls", self.math_calls),
+            ("textureSamples", self.texture_samples),
+            ("textureLoadsStores", self.texture_loads_stores),
+            ("branches", self.branches),
+            ("loops", self.loops),
+            ("barriers", self.barriers),
+            ("bindings", self.bindings),
+            ("inputs", self.inputs),
+            ("outputs", self.outputs),
+            ("spirvBytes", self.spirv_bytes),
+        ]
+    }
+}
+
+/// Bindings and locations of the compared entry points, as sortable lines.
+fn interface(module: &naga::Module, entry_point: Option<&str>) -> BTreeSet<String> {
+    let mut lines = BTreeSet::new();
+    for ep in &module.entry_points {
+        if entry_point.is_some_and(|name| name != ep.name) {
+            continue;
+        }
+        for (result, direction) in [(false, "input"), (true, "output")] {
+            for leaf in crate::varyings::leaves(module, &ep.function, result) {
+                if let naga::Binding::Location { location, .. } = leaf.binding {
+                    lines.insert(format!(
+                        "{}: {direction} @location({location}) {}",
+                        ep.name, leaf.name
+                    ));
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn stats(
+    source: &str,
+    entry_point: Option<&str>,
+) -> Result<(ShaderStats, BTreeSet<String>), Diagnostic> {
+    let (module, info) = crate::parse_and_validate(source)?;
+    if let Some(name) = entry_point {
+        crate::find_entry_point(&module, name)?;
+    }
+    let options = SpirvOptions {
+        compact: true,
+        ..Default::default()
+    };
+    let bytes = crate::write_spirv_configured(&module, &info, entry_point, None, &options)?;
+    let named = SpirvOptions {
+        debug_names: Some(true),
+        ..options
+    };
+    let named = crate::write_spirv_configured(&module, &info, entry_point, None, &named)?;
+    let words = spv::words_from_bytes(&named)?;
+
+    let mut stats = ShaderStats {
+        instructions: 0,
+        arithmetic: 0,
+        math_calls: 0,
+        texture_samples: 0,
+        texture_loads_stores: 0,
+        branches: 0,
+        loops: 0,
+        barriers: 0,
+        bindings: 0,
+        inputs: 0,
+        outputs: 0,
+        spirv_bytes: bytes.len() as u32,
+        functions: Vec::new(),
+    };
+    let instructions = spv::instructions(&words)?;
+    let mut names = HashMap::new();
+    for inst in &instructions {
+        if inst.op() == Some(Op::Name)
+            && let [target, ref text @ ..] = *inst.operands()
+        {
+            names.insert(target, spv::decode_string(text).0);
+        }
+    }
+    let mut function = None;
+    for inst in &instructions {
+        let Some(op) = inst.op() else { continue };
+        match op {
+            Op::Function => {
+                let name = names.get(&inst.operands()[1]);
+                function = Some(name.map(|name| {
+                    stats.functions.push(FunctionStats {
+                        id: crate::function_id(&module, name),
+                        name: name.clone(),
+                        instructions: 0,
+                    });
+                    stats.functions.len() - 1
+                }));
+            }
+            Op::FunctionEnd => function = None,
+            _ => {}
+        }
+        let Some(index) = function else { continue };
+        stats.instructions += 1;
+        if let Some(index) = index {
+            stats.functions[index].instructions += 1;
+        }
+        let name = format!("{op:?}");
+        match op as u32 {
+            126..=152 => stats.arithmetic += 1,
+            _ if op == Op::ExtInst => stats.math_calls += 1,
+            _ if name.starts_with("ImageSample") || name.ends_with("Gather") => {
+                stats.texture_samples += 1
+            }
+            _ if matches!(op, Op::ImageFetch | Op::ImageRead | Op::ImageWrite) => {
+                stats.texture_loads_stores += 1
+            }
+            _ if matches!(op, Op::BranchConditional | Op::Switch) => stats.branches += 1,
+            _ if op == Op::LoopMerge => stats.loops += 1,
+            _ if matches!(op, Op::ControlBarrier | Op::MemoryBarrier) => stats.barriers += 1,
+            _ => {}
+        }
+    }
+
+    let reflection = crate::reflect_module(&module, &info);
+    let mut lines = interface(&module, entry_point);
+    let mut slots = BTreeSet::new();
+    for ep in &reflection.entry_points {
+        if entry_point.is_some_and(|name| name != ep.name) {
+            continue;
+        }
+        for binding in &ep.bindings {
+            slots.insert((binding.group, binding.binding));
+            lines.insert(format!(
+                "@group({}) @binding({}) {}: {}",
+                binding.group, binding.binding, binding.name, binding.resource_type
+            ));
+        }
+    }
+    stats.bindings = slots.len() as u32;
+    stats.inputs = lines.iter().filter(|l| l.contains(": input @")).count() as u32;
+    stats.outputs = lines.iter().filter(|l| l.contains(": output @")).count() as u32;
+    Ok((stats, lines))
+}
+
+fn describe(change: i64, singular: &str, plural: &str, percent: Option<f64>) -> String {
+    let amount = change.unsigned_abs();
+    let noun = if amount == 1 { singular } else { plural };
+    let direction = if change < 0 { "fewer" } else { "more" };
+    match percent {
+        Some(percent) => format!("{amount} {direction} {noun} ({percent:+.1}%)"),
+        None => format!("{amount} {direction} {noun}"),
+    }
+}
+
+pub(crate) fn compare(
+    a: &str,
+    b: &str,
+    entry_point: Option<&str>,
+) -> Result<ShaderComparison, Diagnostic> {
+    let entry_point = entry_point.filter(|name| !name.is_empty());
+    let (a_stats, a_lines) =
+        stats(a, entry_point).map_err(|e| Diagnostic::error(format!("Shader a: {}", e.message)))?;
+    let (b_stats, b_lines) =
+        stats(b, entry_point).map_err(|e| Diagnostic::error(format!("Shader b: {}", e.message)))?;
+
+    let deltas: Vec<_> = a_stats
+        .metrics()
+        .into_iter()
+        .zip(b_stats.metrics())
+        .map(|((metric, a), (_, b))| MetricDelta {
+            metric: metric.to_string(),
+            a,
+            b,
+            change: b as i64 - a as i64,
+            percent: (a > 0).then(|| (b as f64 - a as f64) * 100.0 / a as f64),
+        })
+        .collect();
+
+    let mut interface_changes: Vec<_> = a_lines
+        .difference(&b_lines)
+        .map(|line| format!("{line} only in a"))
+        .collect();
+    interface_changes.extend(
+        b_lines
+            .difference(&a_lines)
+            .map(|line| format!("{line} only in b")),
+    );
+
+    let mut better = false;
+    let mut worse = false;
+    let mut changes = Vec::new();
+    for (metric, singular, plural) in COST_METRICS {
+        let delta = deltas.iter().find(|d| d.metric == *metric).unwrap();
+        if delta.change != 0 {
+            better |= delta.change < 0;
+            worse |= delta.change > 0;
+            changes.push(describe(delta.change, singular, plural, delta.percent));
+        }
+    }
+    let verdict = match (better, worse) {
+        (true, false) => "better",
+        (false, true) => "worse",
+        (true, true) => "mixed",
+        (false, false) => "same",
+    };
+    let mut summary = match verdict {
+        "same" => "b costs the same as a".to_string(),
+        _ => format!("b is {verdict}: {}", changes.join(", ")),
+    };
+    if !interface_changes.is_empty() {
+        summary.push_str(&format!(
+            "; {} interface change{}",
+            interface_changes.len(),
+            if interface_changes.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+    }
+
+    Ok(ShaderComparison {
+        a: a_stats,
+        b: b_stats,
+        deltas,
+        interface_changes,
+        verdict: verdict.to_string(),
+        summary,
+    })
+}
+
+/// Compare two versions of a shader for an "optimize this effect" review:
+/// instruction, texture, branch, loop, barrier and binding counts side by
+/// side, interface differences, and a verdict on whether `b` is cheaper.
+/// If `entryPoint` is given, only that entry point (present in both) and what
+/// it uses is compared.
+#[wasm_bindgen(js_name = compareShaders)]
+pub fn compare_shaders(
+    a: &str,
+    b: &str,
+    entry_point: Option<String>,
+) -> Result<ShaderComparison, JsValue> {
+    compare(a, b, entry_point.as_deref()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLUR: &str = r#"
+        @group(0) @binding(0) var source: texture_2d<f32>;
+        @group(0) @binding(1) var linear: sampler;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            var sum = vec4<f32>(0.0);
+            for (var i = -2; i <= 2; i++) {
+                let offset = vec2<f32>(f32(i) * 0.01, 0.0);
+                sum += textureSample(source, linear, uv + offset);
+            }
+            return sum / 5.0;
+        }
+    "#;
+
+    const UNROLLED: &str = r#"
+        @group(0) @binding(0) var source: texture_2d<f32>;
+        @group(0) @binding(1) var linear: sampler;
+        fn unused(x: f32) -> f32 { return sin(x) * cos(x); }
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>, @location(1) weight: f32) -> @location(0) vec4<f32> {
+            let near = textureSample(source, linear, uv);
+            let far = textureSample(source, linear, uv + vec2<f32>(0.015, 0.0));
+            return mix(near, far, weight);
+        }
+    "#;
+
+    #[test]
+    fn unrolled_blur_is_better() {
+        let comparison = compare(BLUR, UNROLLED, None).unwrap();
+        assert_eq!(comparison.a.loops, 1);
+        assert_eq!(comparison.b.loops, 0);
+        assert_eq!(comparison.b.texture_samples, 2);
+        assert_eq!((comparison.a.inputs, comparison.b.inputs), (1, 2));
+        // `unused` is compacted away rather than counted.
+        assert_eq!(comparison.b.math_calls, 1);
+        let functions: Vec<_> = comparison
+            .b
+            .functions
+            .iter()
+            .map(|f| (f.name.as_str(), f.instructions))
+            .collect();
+        assert_eq!(functions, [("fs", comparison.b.instructions)]);
+        assert_eq!(comparison.a.functions[0].id, comparison.b.functions[0].id);
+        assert_eq!(comparison.verdict, "mixed");
+        assert!(comparison.summary.contains("fewer loop"));
+        assert_eq!(
+            comparison.interface_changes,
+            ["fs: input @location(1) weight only in b"]
+        );
+        let samples = comparison
+            .deltas
+            .iter()
+            .find(|d| d.metric == "textureSamples")
+            .unwrap();
+        assert_eq!((samples.a, samples.b), (1, 2));
+    }
+
+    #[test]
+    fn identical_shaders_are_the_same() {
+        let comparison = compare(BLUR, BLUR, Some("fs")).unwrap();
+        assert_eq!(comparison.verdict, "same");
+        assert!(comparison.interface_changes.is_empty());
+        assert!(comparison.deltas.iter().all(|d| d.change == 0));
+        let error = compare(BLUR, "fn", None).err().unwrap();
+        assert!(error.message.starts_with("Shader b:"));
+        assert!(compare(BLUR, BLUR, Some("vs")).is_err());
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::lexer::{Token, TokenKind, module_declarations, tokenize};
+use crate::minify::non_references;
+use crate::rename::is_identifier;
+
+// ============================================================================
+// Composition Types
+// ============================================================================
+
+/// A shader with its imports resolved.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ComposedShader {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// Modules pulled in, directly or not, dependencies first.
+    #[wasm_bindgen(readonly)]
+    pub modules: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl ComposedShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Named library chunks that shaders compose with `#import`, in the style of
+/// naga_oil. Modules stay registered across calls, like a `Project`'s
+/// sources.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct ShaderComposer {
+    modules: BTreeMap<String, String>,
+}
+
+#[wasm_bindgen]
+impl ShaderComposer {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> ShaderComposer {
+        ShaderComposer::default()
+    }
+
+    /// Register or replace the module `name`, a `::`-separated path such as
+    /// `lighting::pbr`. Its imports are resolved when a shader uses it.
+    #[wasm_bindgen(js_name = registerModule)]
+    pub fn register_module(&mut self, name: &str, wgsl: String) -> Result<(), JsValue> {
+        check_path(name).map_err(throw)?;
+        self.modules.insert(name.to_string(), wgsl);
+        Ok(())
+    }
+
+    /// Unregister `name`. Returns false if it was not registered.
+    #[wasm_bindgen(js_name = removeModule)]
+    pub fn remove_module(&mut self, name: &str) -> bool {
+        self.modules.remove(name).is_some()
+    }
+
+    /// All registered module names, sorted.
+    pub fn modules(&self) -> Vec<String> {
+        self.modules.keys().cloned().collect()
+    }
+
+    /// `wgsl` with every `#import` resolved: the imported modules, and the
+    /// ones they import, ahead of it with their declarations scoped to the
+    /// module, and references rewritten to match. The result is validated.
+    #[wasm_bindgen(js_name = resolveImports)]
+    pub fn resolve_imports(&self, wgsl: &str) -> Result<ComposedShader, JsValue> {
+        compose(&self.modules, wgsl).map_err(throw)
+    }
+}
+
+// ============================================================================
+// Composition Implementation
+// ============================================================================
+//
+// A shader or module imports with lines of the form
+//
+//     #import lighting::pbr
+//     #import lighting::pbr as shading
+//     #import lighting::pbr::{fresnel, ggx as distribution}
+//
+// The first two make the module's declarations reachable as `pbr::fresnel`
+// (or `shading::fresnel`), the last brings the items listed into scope
+// unqualified. Each module's module-scope names are prefixed with its path,
+// `lighting::pbr`'s `fresnel` becoming `lighting_pbr__fresnel`, so modules
+// may reuse each other's names; the shader being resolved keeps its own
+// names, entry points included. Every module is emitted once, after the
+// modules it imports, and import cycles are errors. Renaming follows the
+// rules of a textual rename: member names and attributes are left alone,
+// and a local shadowing a module-scope name is renamed with it.
+
+struct Import {
+    path: String,
+    /// Name the module is reachable by, unless only items are imported.
+    alias: Option<String>,
+    /// Items brought into scope: name in the module, local name.
+    items: Vec<(String, String)>,
+}
+
+fn check_path(path: &str) -> Result<(), Diagnostic> {
+    if path.split("::").all(is_identifier) {
+        Ok(())
+    } else {
+        Err(Diagnostic::error(format!(
+            "'{path}' is not a module path like 'lighting::pbr'"
+        )))
+    }
+}
+
+fn mangle(path: &str, name: &str) -> String {
+    format!("{}__{name}", path.replace("::", "_"))
+}
+
+/// `name` or `name as local`.
+fn parse_item(item: &str) -> Option<(String, String)> {
+    let mut words = item.split_whitespace();
+    let name = words.next()?;
+    let local = match (words.next(), words.next(), words.next()) {
+        (None, _, _) => name,
+        (Some("as"), Some(local), None) => local,
+        _ => return None,
+    };
+    (is_identifier(name) && is_identifier(local)).then(|| (name.to_string(), local.to_string()))
+}
+
+fn parse_import(line: &str) -> Option<Import> {
+    let rest = line.trim().strip_prefix("#import")?.trim();
+    if let Some((path, items)) = rest.split_once("::{") {
+        let items = items.strip_suffix('}')?;
+        let items = items
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(parse_item)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(Import {
+            path: path.trim().to_string(),
+            alias: None,
+            items,
+        });
+    }
+    let (path, alias) = match rest.split_once(" as ") {
+        Some((path, alias)) => (path.trim(), alias.trim()),
+        None => (rest, rest.rsplit("::").next()?),
+    };
+    is_identifier(alias).then(|| Import {
+        path: path.to_string(),
+        alias: Some(alias.to_string()),
+        items: Vec::new(),
+    })
+}
+
+/// The `#import` lines of `source`.
+fn imports(name: &str, source: &str) -> Result<Vec<Import>, Diagnostic> {
+    let mut imports = Vec::new();
+    for (line_no, line) in source.lines().enumerate() {
+        if !line.trim_start().starts_with("#import") {
+            continue;
+        }
+        let import = parse_import(line)
+            .filter(|import| check_path(&import.path).is_ok())
+            .ok_or_else(|| {
+                Diagnostic::error(format!(
+                    "{name}:{}: malformed import '{}'",
+                    line_no + 1,
+                    line.trim()
+                ))
+            })?;
+        imports.push(import);
+    }
+    Ok(imports)
+}
+
+struct Composer<'a> {
+    modules: &'a BTreeMap<String, String>,
+    /// Emission order, dependencies first.
+    order: Vec<&'a str>,
+    visiting: Vec<&'a str>,
+}
+
+impl<'a> Composer<'a> {
+    fn source(&self, path: &str) -> Result<&'a str, Diagnostic> {
+        self.modules
+            .get(path)
+            .map(String::as_str)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown module '{path}'")))
+    }
+
+    fn visit(&mut self, path: &str) -> Result<(), Diagnostic> {
+        let (path, source) = self
+            .modules
+            .get_key_value(path)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown module '{path}'")))?;
+        if self.order.contains(&path.as_str()) {
+            return Ok(());
+        }
+        if self.visiting.contains(&path.as_str()) {
+            let mut cycle = self.visiting.join(" -> ");
+            cycle.push_str(&format!(" -> {path}"));
+            return Err(Diagnostic::error(format!("Import cycle: {cycle}")));
+        }
+        self.visiting.push(path);
+        for import in imports(path, source)? {
+            self.visit(&import.path)?;
+        }
+        self.visiting.pop();
+        self.order.push(path);
+        Ok(())
+    }
+
+    /// Module-scope names of the module `path`.
+    fn declarations(&self, path: &str) -> Result<HashSet<&'a str>, Diagnostic> {
+        let tokens = tokenize(self.source(path)?);
+        Ok(module_declarations(&tokens)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// `source` with its import lines dropped and references rewritten;
+    /// `own` is its module path, if it is a module.
+    fn rewrite(&self, name: &str, source: &str, own: Option<&str>) -> Result<String, Diagnostic> {
+        let tokens = tokenize(source);
+        let skip = non_references(&tokens);
+        let mut locals: HashMap<&str, String> = HashMap::new();
+        let mut qualified: HashMap<&str, &str> = HashMap::new();
+        let imports = imports(name, source)?;
+        if let Some(own) = own {
+            for (declared, _) in module_declarations(&tokens) {
+                locals.insert(declared, mangle(own, declared));
+            }
+        }
+        for import in &imports {
+            let declarations = self.declarations(&import.path)?;
+            for (item, local) in &import.items {
+                if !declarations.contains(item.as_str()) {
+                    return Err(Diagnostic::error(format!(
+                        "{name}: module '{}' has no item '{item}'",
+                        import.path
+                    )));
+                }
+                locals.insert(local, mangle(&import.path, item));
+            }
+            qualified.insert(&import.path, &import.path);
+            if let Some(alias) = &import.alias {
+                qualified.insert(alias, &import.path);
+            }
+        }
+
+        let path_separator = |i: usize| {
+            tokens.get(i).is_some_and(|t| t.is_punct(':'))
+                && tokens.get(i + 1).is_some_and(|t| t.is_punct(':'))
+                && tokens[i].end() == tokens[i + 1].start
+        };
+        let mut edits: Vec<(usize, usize, String)> = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token: Token = tokens[i];
+            if token.kind != TokenKind::Ident || skip.contains(&i) {
+                i += 1;
+                continue;
+            }
+            let mut last = i;
+            while path_separator(last + 1)
+                && tokens
+                    .get(last + 3)
+                    .is_some_and(|t| t.kind == TokenKind::Ident)
+            {
+                last += 3;
+            }
+            if last > i {
+                let segments: Vec<_> = (i..=last).step_by(3).map(|j| tokens[j].text).collect();
+                let (item, prefix) = segments.split_last().unwrap();
+                let prefix = prefix.join("::");
+                let path = qualified.get(prefix.as_str()).ok_or_else(|| {
+                    Diagnostic::error(format!("{name}: '{prefix}' is not imported"))
+                })?;
+                if !self.declarations(path)?.contains(item) {
+                    return Err(Diagnostic::error(format!(
+                        "{name}: module '{path}' has no item '{item}'"
+                    )));
+                }
+                edits.push((token.start, tokens[last].end(), mangle(path, item)));
+            } else if let Some(mangled) = locals.get(token.text) {
+                edits.push((token.start, token.end(), mangled.clone()));
+            }
+            i = last + 1;
+        }
+
+        let mut out = String::with_capacity(source.len());
+        let mut copied = 0;
+        for (start, end, text) in edits {
+            out.push_str(&source[copied..start]);
+            out.push_str(&text);
+            copied = end;
+        }
+        out.push_str(&source[copied..]);
+        Ok(out
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("#import"))
+            .map(|line| format!("{line}\n"))
+            .collect())
+    }
+}
+
+pub(crate) fn compose(
+    modules: &BTreeMap<String, String>,
+    wgsl: &str,
+) -> Result<ComposedShader, Diagnostic> {
+    let mut composer = Composer {
+        modules,
+        order: Vec::new(),
+        visiting: Vec::new(),
+    };
+    for import in imports("shader", wgsl)? {
+        composer.visit(&import.path)?;
+    }
+    let mut out = String::new();
+    for path in &composer.order {
+        out.push_str(&format!("// module {path}\n"));
+        out.push_str(&composer.rewrite(path, composer.source(path)?, Some(path))?);
+        out.push('\n');
+    }
+    out.push_str(&composer.rewrite("shader", wgsl, None)?);
+    crate::parse_and_validate(&out)
+        .map_err(|e| Diagnostic::error(format!("Composed shader is invalid: {}", e.message)))?;
+    Ok(ComposedShader {
+        wgsl: out,
+        modules: composer.order.iter().map(|path| path.to_string()).collect(),
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect()
+    }
+
+    const MATH: &str = "\
+const PI: f32 = 3.14159;
+fn scale(x: f32) -> f32 { return x * PI; }
+";
+
+    const PBR: &str = "\
+#import util::math
+// Shares `scale` with util::math, without clashing.
+struct Surface { roughness: f32, scale: f32 }
+fn scale(s: Surface) -> f32 { return math::scale(s.roughness) * s.scale; }
+fn fresnel(cos_theta: f32) -> f32 { return pow(1.0 - cos_theta, 5.0); }
+";
+
+    #[test]
+    fn imports_are_scoped_and_ordered() {
+        let modules = modules(&[("util::math", MATH), ("lighting::pbr", PBR)]);
+        let shader = "\
+#import lighting::pbr as shading
+#import lighting::pbr::{fresnel as schlick}
+#import util::math::{PI}
+
+@fragment
+fn fs(@location(0) n: f32) -> @location(0) vec4<f32> {
+    let surface = shading::Surface(n, 2.0);
+    return vec4<f32>(shading::scale(surface), schlick(n), PI, 1.0);
+}
+";
+        let composed = compose(&modules, shader).unwrap();
+        assert_eq!(composed.modules, ["util::math", "lighting::pbr"]);
+        assert!(composed.wgsl.contains("fn util_math__scale(x: f32)"));
+        assert!(
+            composed
+                .wgsl
+                .contains("return util_math__scale(s.roughness) * s.scale;")
+        );
+        assert!(
+            composed
+                .wgsl
+                .contains("struct lighting_pbr__Surface { roughness: f32, scale: f32 }")
+        );
+        assert!(
+            composed
+                .wgsl
+                .contains("lighting_pbr__fresnel(n), util_math__PI, 1.0")
+        );
+        assert!(composed.wgsl.contains("fn fs("));
+        assert!(!composed.wgsl.contains("#import"));
+        assert_eq!(composed.wgsl.matches("const util_math__PI").count(), 1);
+    }
+
+    #[test]
+    fn bad_imports_are_errors() {
+        let error = |modules: &BTreeMap<String, String>, shader: &str| {
+            compose(modules, shader).err().unwrap().message
+        };
+        let library = modules(&[("util::math", MATH)]);
+        assert!(error(&library, "#import util::trig").contains("Unknown module 'util::trig'"));
+        assert!(error(&library, "#import util::math::{tau}").contains("no item 'tau'"));
+        assert!(
+            error(&library, "#import util::math\nconst X = math::TAU;").contains("no item 'TAU'")
+        );
+        assert!(error(&library, "const X = trig::TAU;").contains("'trig' is not imported"));
+        assert!(error(&library, "#import util::math::{").contains("malformed import"));
+
+        let cyclic = modules(&[("a", "#import b\nfn f() {}"), ("b", "#import a\nfn g() {}")]);
+        assert_eq!(error(&cyclic, "#import a"), "Import cycle: a -> b -> a");
+        assert!(check_path("lighting::pbr").is_ok());
+        assert!(check_path("lighting::").is_err());
+    }
+}
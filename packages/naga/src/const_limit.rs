@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use crate::lexer::{self, Token, TokenKind};
+
+// ============================================================================
+// Constant Size Limit
+// ============================================================================
+//
+// naga evaluates constant expressions while it lowers WGSL, with no limit and
+// no way to stop it, and indexing or comparing a constructed value expands
+// every component of it: `array<array<f32, 65535>, 65535>()[1][2]` is four
+// billion scalars. Since the evaluation cannot be bounded, the values are,
+// before naga starts: every value constructor (`array<T, N>(...)`, or a
+// struct or alias name followed by `(`) is sized from the source's tokens,
+// and one holding more scalars than the limit fails the parse. Sizes come
+// from the types and `const`s declared at module scope; array counts that
+// are not a literal or such a `const` (overrides, expressions) count as one,
+// so the check never rejects what it cannot size.
+
+/// Scalars one constructed value may hold.
+pub(crate) const MAX_CONST_SCALARS: u64 = 1_000_000;
+
+/// A constructor over the limit: its scalar count and the byte range of its
+/// type.
+pub(crate) struct Oversized {
+    pub scalars: u64,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Oversized {
+    pub(crate) fn message(&self) -> String {
+        format!(
+            "Constant value of {} scalars exceeds the limit of {MAX_CONST_SCALARS}; \
+             use a buffer for data this large",
+            self.scalars
+        )
+    }
+
+    pub(crate) fn span(&self) -> naga::Span {
+        naga::Span::new(self.start as u32, self.end as u32)
+    }
+}
+
+/// The integer a number token spells, with any `u` or `i` suffix.
+fn integer(text: &str) -> Option<u64> {
+    let digits = text.trim_end_matches(['u', 'i']);
+    match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => digits.parse().ok(),
+    }
+}
+
+struct Sizes<'a> {
+    tokens: &'a [Token<'a>],
+    consts: HashMap<&'a str, u64>,
+    /// Scalars in each declared struct and alias.
+    types: HashMap<&'a str, u64>,
+}
+
+impl Sizes<'_> {
+    /// Scalars in the type starting at `at`, and the index past it.
+    fn type_at(&self, at: usize) -> (u64, usize) {
+        let Some(token) = self.tokens.get(at) else {
+            return (1, at);
+        };
+        let end = lexer::skip_template(self.tokens, at + 1);
+        let name = token.text;
+        let scalars = if name == "array" && end > at + 1 {
+            let (element, next) = self.type_at(at + 2);
+            let count = match self.tokens.get(next + 1) {
+                Some(t) if self.tokens[next].is_punct(',') => match t.kind {
+                    TokenKind::Number => integer(t.text),
+                    _ => self.consts.get(t.text).copied(),
+                },
+                _ => None,
+            };
+            element.saturating_mul(count.unwrap_or(1))
+        } else if let Some(&scalars) = self.types.get(name) {
+            scalars
+        } else if let Some(rest) = name.strip_prefix("vec") {
+            rest.get(..1).and_then(|n| n.parse().ok()).unwrap_or(1)
+        } else if let Some(rest) = name.strip_prefix("mat") {
+            let mut dims = rest.split('x');
+            let columns: u64 = dims.next().and_then(|c| c.parse().ok()).unwrap_or(1);
+            let rows: u64 = dims
+                .next()
+                .and_then(|r| r.get(..1))
+                .and_then(|r| r.parse().ok())
+                .unwrap_or(1);
+            columns * rows
+        } else {
+            1
+        };
+        (scalars, end)
+    }
+
+    /// Record the module-scope `const` integers, structs and aliases.
+    fn declare(&mut self) {
+        let tokens = self.tokens;
+        for (name, at) in lexer::module_declarations(tokens) {
+            match tokens[at - 1].text {
+                "const" => {
+                    // `const N = 64u;` or `const N: u32 = 64u;`
+                    let value = tokens[at..]
+                        .iter()
+                        .position(|t| t.is_punct('='))
+                        .and_then(|i| tokens.get(at + i + 1..at + i + 3));
+                    if let Some([value, end]) = value
+                        && value.kind == TokenKind::Number
+                        && end.is_punct(';')
+                        && let Some(value) = integer(value.text)
+                    {
+                        self.consts.insert(name, value);
+                    }
+                }
+                "alias" => {
+                    let (scalars, _) = self.type_at(at + 2);
+                    self.types.insert(name, scalars);
+                }
+                "struct" => {
+                    let mut scalars = 0u64;
+                    let mut i = at + 2;
+                    while i < tokens.len() && !tokens[i].is_punct('}') {
+                        if tokens[i].is_punct(':') {
+                            let (member, next) = self.type_at(i + 1);
+                            scalars = scalars.saturating_add(member);
+                            i = next;
+                        } else {
+                            i += 1;
+                        }
+                    }
+                    self.types.insert(name, scalars);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The first value constructor in `source` holding more than
+/// `MAX_CONST_SCALARS` scalars, if any.
+pub(crate) fn oversized(source: &str) -> Option<Oversized> {
+    let tokens = lexer::tokenize(source);
+    let mut sizes = Sizes {
+        tokens: &tokens,
+        consts: HashMap::new(),
+        types: HashMap::new(),
+    };
+    sizes.declare();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Ident
+            || (token.text != "array" && !sizes.types.contains_key(token.text))
+        {
+            continue;
+        }
+        let (scalars, end) = sizes.type_at(i);
+        if scalars > MAX_CONST_SCALARS && tokens.get(end).is_some_and(|t| t.is_punct('(')) {
+            return Some(Oversized {
+                scalars,
+                start: token.start,
+                end: tokens[end - 1].end(),
+            });
+        }
+    }
+    None
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructors_are_sized_through_declarations() {
+        let source = "const N = 65535u;\n\
+                      alias Row = array<vec4<f32>, N>;\n\
+                      struct Grid { rows: array<Row, 64>, scale: mat4x4f }\n\
+                      fn f() -> f32 { return Grid().scale[0][0]; }";
+        let found = oversized(source).unwrap();
+        assert_eq!(found.scalars, 65535 * 4 * 64 + 16);
+        assert_eq!(&source[found.start..found.end], "Grid");
+
+        // Types alone, and constructors within the limit, are fine.
+        assert!(
+            oversized("@group(0) @binding(0) var<storage> big: array<f32, 4000000>;").is_none()
+        );
+        assert!(oversized("const T = array<vec4f, 0x100>();").is_none());
+        let nested = "const Z = array<array<f32, 65535>, 65535>();";
+        assert_eq!(
+            oversized(nested).map(|o| (o.start, o.end)),
+            Some((10, nested.len() - 3))
+        );
+    }
+
+    #[test]
+    fn non_ascii_type_names_are_sized() {
+        // `vecé` is a user struct, declared after its use.
+        let source = "struct A { b: vecé }
+struct vecé { x: f32 }";
+        assert!(oversized(source).is_none());
+        assert!(crate::parse_wgsl(source).is_ok());
+    }
+}
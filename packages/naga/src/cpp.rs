@@ -0,0 +1,275 @@
+use std::fmt::Write;
+
+use naga::proc::Layouter;
+use naga::{ArraySize, Handle, Scalar, ScalarKind, Type, TypeInner, VectorSize};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::layout::{PaddedField, layouter, padded_struct};
+use crate::typescript::{buffer_structs, struct_name};
+
+// ============================================================================
+// C++ Header Types
+// ============================================================================
+
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Language {
+    #[default]
+    Cpp,
+    /// C11: `typedef struct`s and `_Static_assert`.
+    C,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CppOptions {
+    #[serde(default)]
+    pub language: Language,
+    /// Wraps the structs; C++ only.
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+// ============================================================================
+// C++ Header Implementation
+// ============================================================================
+//
+// The same structs and padded layout as `generateRust`'s bytemuck style:
+// vectors and matrix columns are arrays, vec3 columns and vec3 array
+// elements are widened to four components, gaps are `_padN` byte arrays,
+// f16 values are their `uint16_t` bits, and a runtime-sized array ends the
+// struct at its offset. Every size and member offset is asserted, so a
+// compiler that lays the struct out differently stops the build.
+
+/// Names WGSL allows that C++ reserves, or that would shadow the integer
+/// types the header uses.
+const RESERVED: &[&str] = &[
+    "and", "and_eq", "bitand", "bitor", "char", "char8_t", "char16_t", "char32_t", "compl",
+    "double", "float", "int", "int32_t", "int64_t", "long", "not", "not_eq", "or", "or_eq",
+    "private", "short", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "unsigned", "void",
+    "wchar_t", "xor", "xor_eq",
+];
+
+fn field_name(name: Option<&str>) -> String {
+    match name.unwrap_or("_") {
+        name if RESERVED.contains(&name) => format!("{name}_"),
+        name => name.to_string(),
+    }
+}
+
+fn scalar(scalar: Scalar) -> &'static str {
+    match (scalar.kind, scalar.width) {
+        (ScalarKind::Float, 2) => "uint16_t",
+        (ScalarKind::Float, 8) => "double",
+        (ScalarKind::Float, _) => "float",
+        (ScalarKind::Sint, 8) => "int64_t",
+        (ScalarKind::Sint, _) => "int32_t",
+        (ScalarKind::Uint, 8) => "uint64_t",
+        (_, _) => "uint32_t",
+    }
+}
+
+/// The element type and array dimensions declaring a value of `ty`;
+/// `element` when it is an array element, which widens vec3s.
+fn declarator(module: &naga::Module, ty: Handle<Type>, element: bool) -> (String, String) {
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) => (scalar(s).to_string(), String::new()),
+        TypeInner::Vector { size, scalar: s } => {
+            let size = match (size, element) {
+                (VectorSize::Tri, true) => 4,
+                (size, _) => size as u32,
+            };
+            (scalar(s).to_string(), format!("[{size}]"))
+        }
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar: s,
+        } => {
+            let rows = match rows {
+                VectorSize::Tri => 4,
+                rows => rows as u32,
+            };
+            (
+                scalar(s).to_string(),
+                format!("[{}][{rows}]", columns as u32),
+            )
+        }
+        TypeInner::Array { base, size, .. } => {
+            let (base, dims) = declarator(module, base, true);
+            match size {
+                ArraySize::Constant(count) => (base, format!("[{count}]{dims}")),
+                _ => (base, format!("[]{dims}")),
+            }
+        }
+        _ => (struct_name(module, ty), String::new()),
+    }
+}
+
+fn header_struct(
+    out: &mut String,
+    module: &naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+    options: &CppOptions,
+) {
+    let name = struct_name(module, ty);
+    let padded = padded_struct(module, layouter, ty);
+    let static_assert = match options.language {
+        Language::Cpp => "static_assert",
+        Language::C => "_Static_assert",
+    };
+
+    let _ = writeln!(out, "// `{name}` as a uniform or storage buffer holds it.");
+    if let Some(member) = padded.tail
+        && let TypeInner::Array { base, stride, .. } = module.types[member.ty].inner
+    {
+        let (element, dims) = declarator(module, base, true);
+        let _ = writeln!(
+            out,
+            "// The runtime-sized `{}` follows, one `{element}{dims}` every {stride} bytes.",
+            field_name(member.name.as_deref())
+        );
+    }
+    match options.language {
+        Language::Cpp => {
+            let _ = writeln!(out, "struct {name} {{");
+        }
+        Language::C => {
+            let _ = writeln!(out, "typedef struct {name} {{");
+        }
+    }
+    let mut asserts = String::new();
+    let mut padding = 0;
+    for field in &padded.fields {
+        match *field {
+            PaddedField::Padding(bytes) => {
+                let _ = writeln!(out, "    uint8_t _pad{padding}[{bytes}];");
+                padding += 1;
+            }
+            PaddedField::Member(member) => {
+                let field = field_name(member.name.as_deref());
+                let (element, dims) = declarator(module, member.ty, false);
+                if element == "uint16_t" {
+                    let _ = writeln!(out, "    // f16 bits.");
+                }
+                let _ = writeln!(out, "    {element} {field}{dims};");
+                let _ = writeln!(
+                    asserts,
+                    "{static_assert}(offsetof({name}, {field}) == {offset}, \"{name}.{field} must be at byte {offset}\");",
+                    offset = member.offset
+                );
+            }
+        }
+    }
+    match options.language {
+        Language::Cpp => {
+            let _ = writeln!(out, "}};");
+        }
+        Language::C => {
+            let _ = writeln!(out, "}} {name};");
+        }
+    }
+    let _ = writeln!(
+        out,
+        "{static_assert}(sizeof({name}) == {size}, \"{name} must be {size} bytes\");",
+        size = padded.size
+    );
+    out.push_str(&asserts);
+}
+
+pub(crate) fn generate(module: &naga::Module, options: &CppOptions) -> Result<String, Diagnostic> {
+    let layouter = layouter(module)?;
+    let mut out = String::from("#pragma once\n\n");
+    match options.language {
+        Language::Cpp => out.push_str("#include <cstddef>\n#include <cstdint>\n"),
+        Language::C => out.push_str("#include <stddef.h>\n#include <stdint.h>\n"),
+    }
+    let namespace = match options.language {
+        Language::Cpp => options.namespace.as_deref(),
+        Language::C => None,
+    };
+    if let Some(namespace) = namespace {
+        let _ = write!(out, "\nnamespace {namespace} {{\n");
+    }
+    for ty in buffer_structs(module) {
+        out.push('\n');
+        header_struct(&mut out, module, &layouter, ty, options);
+    }
+    if let Some(namespace) = namespace {
+        let _ = write!(out, "\n}}  // namespace {namespace}\n");
+    }
+    Ok(out)
+}
+
+/// A C++ header mirroring every buffer-storable struct in `wgsl`, with
+/// explicit padding and `static_assert`s on `sizeof` and every `offsetof`,
+/// nested structs and arrays included. `options` is
+/// `{ language?: "cpp" | "c", namespace? }`; `"c"` emits C11 instead.
+#[wasm_bindgen(js_name = generateCppHeader)]
+pub fn generate_cpp_header(wgsl: &str, options: JsValue) -> Result<String, JsValue> {
+    let options: Option<CppOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid C++ header options: {e}")))?;
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    generate(&module, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Scene {
+            view: mat4x4<f32>,
+            lights: array<Light, 4>,
+            offsets: array<vec3<f32>, 2>,
+            int: u32,
+        }
+        struct Particles { count: atomic<u32>, positions: array<vec4<f32>> }
+        @group(0) @binding(0) var<uniform> scene: Scene;
+        @group(0) @binding(1) var<storage, read_write> particles: Particles;
+    "#;
+
+    #[test]
+    fn structs_assert_sizes_and_offsets() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let options = CppOptions {
+            namespace: Some("shaders".into()),
+            ..CppOptions::default()
+        };
+        let header = generate(&module, &options).unwrap();
+        assert!(header.starts_with("#pragma once\n\n#include <cstddef>\n"));
+        assert!(header.contains("namespace shaders {\n"));
+        assert!(header.contains("    Light lights[4];\n"));
+        assert!(header.contains("    float offsets[2][4];\n"));
+        assert!(header.contains("    uint32_t int_;\n    uint8_t _pad0[12];\n};"));
+        assert!(header.contains(
+            "static_assert(offsetof(Scene, int_) == 160, \"Scene.int_ must be at byte 160\");"
+        ));
+        assert!(header.contains("static_assert(sizeof(Scene) == 176, "));
+        assert!(header.contains("one `float[4]` every 16 bytes."));
+        assert!(header.contains("static_assert(sizeof(Particles) == 16, "));
+    }
+
+    #[test]
+    fn c_headers_use_typedefs() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let options = CppOptions {
+            language: Language::C,
+            namespace: Some("ignored".into()),
+        };
+        let header = generate(&module, &options).unwrap();
+        assert!(header.contains("#include <stdint.h>\n"));
+        assert!(!header.contains("namespace"));
+        assert!(header.contains("typedef struct Light {\n"));
+        assert!(header.contains("} Light;\n_Static_assert(sizeof(Light) == 16, "));
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet};
+
+use naga::{Block, Expression, Handle, Statement};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::rewrite::{self, visit_operands, visit_statement_operands};
+
+// ============================================================================
+// CSE Types
+// ============================================================================
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct CseReport {
+    /// Duplicate expressions replaced, over all functions.
+    #[wasm_bindgen(readonly)]
+    pub removed: u32,
+    /// Functions and entry points with at least one duplicate.
+    #[wasm_bindgen(readonly)]
+    pub functions: Vec<FunctionCse>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+}
+
+#[wasm_bindgen]
+impl CseReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct FunctionCse {
+    #[wasm_bindgen(readonly)]
+    pub function: String,
+    #[wasm_bindgen(readonly)]
+    pub removed: u32,
+    /// Size of the expression arena before the pass.
+    #[wasm_bindgen(readonly)]
+    pub expressions_before: u32,
+    /// Size after the pass, once unused expressions are compacted away.
+    #[wasm_bindgen(readonly)]
+    pub expressions_after: u32,
+}
+
+#[wasm_bindgen]
+impl FunctionCse {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// CSE Implementation
+// ============================================================================
+//
+// Value numbering over naga's structured control flow. An expression is
+// replaced by an identical one, operands included, that was emitted earlier
+// in the same block or an enclosing one, and so dominates it. Expressions
+// never emitted (literals, constants, variable pointers and the like) are
+// available throughout their function. Reads of memory that may change, and
+// the results of statements, are never merged. Replaced expressions are left
+// unused for `naga::compact` to drop.
+
+/// Whether two identical copies of `expr` always have the same value.
+fn mergeable(expr: &Expression) -> bool {
+    !matches!(
+        expr,
+        Expression::Load { .. }
+            | Expression::ImageLoad { .. }
+            | Expression::CallResult(_)
+            | Expression::AtomicResult { .. }
+            | Expression::WorkGroupUniformLoadResult { .. }
+            | Expression::RayQueryProceedResult
+            | Expression::RayQueryGetIntersection { .. }
+            | Expression::RayQueryVertexPositions { .. }
+            | Expression::SubgroupBallotResult
+            | Expression::SubgroupOperationResult { .. }
+    )
+}
+
+struct Numbering<'a> {
+    function: &'a naga::Function,
+    replaced: HashMap<Handle<Expression>, Handle<Expression>>,
+    /// Available expressions by key, innermost scope last.
+    scopes: Vec<HashMap<String, Handle<Expression>>>,
+}
+
+impl Numbering<'_> {
+    /// Merge `h` into an available copy, or make it available.
+    fn number(&mut self, h: Handle<Expression>) {
+        let mut expr = self.function.expressions[h].clone();
+        if !mergeable(&expr) {
+            return;
+        }
+        visit_operands(&mut expr, &mut |operand| {
+            if let Some(&to) = self.replaced.get(operand) {
+                *operand = to;
+            }
+        });
+        // Handles print as indices, so equal keys mean equal operands.
+        let key = format!("{:?}", expr);
+        match self.scopes.iter().rev().find_map(|scope| scope.get(&key)) {
+            Some(&earlier) => {
+                self.replaced.insert(h, earlier);
+            }
+            None => {
+                self.scopes.last_mut().unwrap().insert(key, h);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        for statement in block.iter() {
+            match *statement {
+                Statement::Emit(ref range) => range.clone().for_each(|h| self.number(h)),
+                Statement::Block(ref block) => self.block(block),
+                Statement::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    self.block(accept);
+                    self.block(reject);
+                }
+                Statement::Switch { ref cases, .. } => {
+                    for case in cases {
+                        self.block(&case.body);
+                    }
+                }
+                // `continue` can skip the rest of the body, so nothing in it is
+                // assumed available in `continuing`.
+                Statement::Loop {
+                    ref body,
+                    ref continuing,
+                    ..
+                } => {
+                    self.block(body);
+                    self.block(continuing);
+                }
+                _ => {}
+            }
+        }
+        self.scopes.pop();
+    }
+}
+
+/// Merge duplicate expressions in `function`, returning how many emitted
+/// ones were merged. Merging the never-emitted copies naga makes of every
+/// literal and pointer is only a means to that, and is not counted.
+fn eliminate(function: &mut naga::Function) -> u32 {
+    let mut emitted = HashSet::new();
+    rewrite::for_each_statement(&mut function.body, &mut |statement| {
+        if let Statement::Emit(range) = statement {
+            emitted.extend(range.clone());
+        }
+    });
+
+    let mut numbering = Numbering {
+        function,
+        replaced: HashMap::new(),
+        scopes: vec![HashMap::new()],
+    };
+    for (h, _) in function.expressions.iter() {
+        if !emitted.contains(&h) {
+            numbering.number(h);
+        }
+    }
+    numbering.block(&function.body);
+    let replaced = numbering.replaced;
+    if replaced.is_empty() {
+        return 0;
+    }
+
+    let mut remap = |h: &mut Handle<Expression>| {
+        if let Some(&to) = replaced.get(h) {
+            *h = to;
+        }
+    };
+    for (_, expr) in function.expressions.iter_mut() {
+        visit_operands(expr, &mut remap);
+    }
+    rewrite::for_each_statement(&mut function.body, &mut |statement| {
+        visit_statement_operands(statement, &mut remap);
+    });
+    for (_, local) in function.local_variables.iter_mut() {
+        if let Some(init) = &mut local.init {
+            remap(init);
+        }
+    }
+    // A `let` of a duplicate names the original, unless that has a name.
+    for (from, to) in &replaced {
+        if let Some(name) = function.named_expressions.shift_remove(from) {
+            function.named_expressions.entry(*to).or_insert(name);
+        }
+    }
+    replaced.keys().filter(|h| emitted.contains(h)).count() as u32
+}
+
+/// Merge duplicate expressions throughout a validated `module`.
+pub(crate) fn eliminate_common_subexpressions(module: &mut naga::Module) -> Vec<FunctionCse> {
+    let mut stats = Vec::new();
+    let functions = module
+        .functions
+        .iter_mut()
+        .map(|(_, f)| (f.name.clone().unwrap_or_default(), f))
+        .chain(
+            module
+                .entry_points
+                .iter_mut()
+                .map(|ep| (ep.name.clone(), &mut ep.function)),
+        );
+    for (name, function) in functions {
+        let before = function.expressions.len() as u32;
+        let removed = eliminate(function);
+        if removed > 0 {
+            stats.push(FunctionCse {
+                function: name,
+                removed,
+                expressions_before: before,
+                expressions_after: 0,
+            });
+        }
+    }
+
+    naga::compact::compact(module, naga::compact::KeepUnused::Yes);
+    for stat in &mut stats {
+        let function = module
+            .functions
+            .iter()
+            .map(|(_, f)| f)
+            .find(|f| f.name.as_deref() == Some(stat.function.as_str()))
+            .or_else(|| {
+                module
+                    .entry_points
+                    .iter()
+                    .find(|ep| ep.name == stat.function)
+                    .map(|ep| &ep.function)
+            });
+        stat.expressions_after = function.map_or(0, |f| f.expressions.len() as u32);
+    }
+    stats
+}
+
+/// Merges repeated computations of the same value, such as the duplicated
+/// subgraphs of generated material shaders, for downstream compilers that fail
+/// to. An expression is merged into an identical earlier one that dominates it;
+/// loads from memory are never merged. Returns the rewritten WGSL and how many
+/// expressions each function lost.
+#[wasm_bindgen(js_name = eliminateCommonSubexpressions)]
+pub fn eliminate_common_subexpressions_wgsl(wgsl: &str) -> Result<CseReport, JsValue> {
+    let (mut module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    cse_report(&mut module).map_err(throw)
+}
+
+fn cse_report(module: &mut naga::Module) -> Result<CseReport, Diagnostic> {
+    let functions = eliminate_common_subexpressions(module);
+    let info = crate::validate_module(module)?;
+    let wgsl =
+        naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+    Ok(CseReport {
+        removed: functions.iter().map(|f| f.removed).sum(),
+        functions,
+        wgsl,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(source: &str) -> CseReport {
+        let mut module = crate::parse_wgsl(source).unwrap();
+        cse_report(&mut module).unwrap()
+    }
+
+    #[test]
+    fn duplicates_are_merged_into_dominating_copies() {
+        let report = run(r#"
+            @group(0) @binding(0) var t: texture_2d<f32>;
+            @group(0) @binding(1) var s: sampler;
+
+            @fragment
+            fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+                let a = textureSample(t, s, uv * 2.0 + 0.5);
+                let b = textureSample(t, s, uv * 2.0 + 0.5);
+                var c = a;
+                if uv.x > 0.5 {
+                    c = a + b * (uv.x * 2.0);
+                }
+                return c + vec4<f32>(uv * 2.0, 0.0, 1.0);
+            }
+        "#);
+        // `uv * 2.0` twice, the splat of `0.5` and the sum, the second
+        // sample and `uv.x`.
+        assert_eq!(report.removed, 6);
+        let stats = &report.functions[0];
+        assert_eq!(stats.function, "fs");
+        assert!(stats.expressions_after < stats.expressions_before);
+        assert_eq!(report.wgsl.matches("textureSample").count(), 1);
+    }
+
+    #[test]
+    fn loads_and_sibling_branches_are_kept() {
+        let report = run(r#"
+            @group(0) @binding(0) var<storage, read_write> buf: array<f32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let first = buf[0] * 3.0;
+                buf[0] = 1.0;
+                let second = buf[0] * 3.0;
+                if first > 0.0 {
+                    buf[1] = second + 7.0;
+                } else {
+                    buf[1] = second + 7.0;
+                }
+            }
+        "#);
+        // Only the `buf[0]` pointer repeats, for the store and second load.
+        assert_eq!(report.removed, 2);
+        assert_eq!(report.wgsl.matches("+ 7f").count(), 2);
+        assert!(run("fn f() {}").functions.is_empty());
+    }
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Culling Types
+// ============================================================================
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CullingOptions {
+    /// The struct in the instance source that `instances` holds.
+    pub instance_type: String,
+    /// Member holding the world-space bounding sphere: a `vec4<f32>` of
+    /// center and radius.
+    #[serde(default = "default_bounds_field")]
+    pub bounds_field: String,
+    /// Write `drawIndexedIndirect` arguments rather than `drawIndirect` ones.
+    #[serde(default = "default_indexed")]
+    pub indexed: bool,
+    #[serde(default = "default_workgroup_size")]
+    pub workgroup_size: u32,
+}
+
+fn default_bounds_field() -> String {
+    "bounds".to_string()
+}
+
+fn default_indexed() -> bool {
+    true
+}
+
+fn default_workgroup_size() -> u32 {
+    64
+}
+
+/// A generated culling kernel and the layout it binds against.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct CullingKernel {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// `"cull"`.
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    #[wasm_bindgen(readonly)]
+    pub workgroup_size: u32,
+    #[wasm_bindgen(readonly)]
+    pub indexed: bool,
+    /// Bytes of the `draw` buffer: 20 indexed, 16 otherwise. The buffer
+    /// needs `INDIRECT` usage as well as `STORAGE`.
+    #[wasm_bindgen(readonly)]
+    pub draw_args_size: u32,
+    /// Bytes of the `frustum` uniform.
+    #[wasm_bindgen(readonly)]
+    pub frustum_size: u32,
+    /// A `GPUBindGroupLayoutDescriptor` as JSON, for
+    /// `device.createBindGroupLayout(JSON.parse(...))`.
+    #[wasm_bindgen(readonly)]
+    pub bind_group_layout: String,
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl CullingKernel {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Workgroups to dispatch along `x` for `instances` instances.
+    #[wasm_bindgen(js_name = workgroupCount)]
+    pub fn workgroup_count(&self, instances: u32) -> u32 {
+        instances.div_ceil(self.workgroup_size)
+    }
+}
+
+// ============================================================================
+// Culling Implementation
+// ============================================================================
+//
+// One invocation per instance tests its bounding sphere against the six
+// frustum planes, each `vec4(normal, distance)` with the normal pointing
+// inwards, so a point `p` is inside when `dot(normal, p) + distance >= 0`.
+// Survivors bump the draw's instance count atomically and append their
+// index to `visible`, which the vertex stage reads through
+// `@builtin(instance_index)`. The host writes the rest of the draw
+// arguments and resets the instance count to zero before each dispatch.
+// The caller's source is pasted ahead of the kernel, so it may declare
+// helpers too, but not the names the kernel uses.
+
+const COMPUTE: u32 = 4;
+
+/// Checks `instance_type` has a `vec4<f32>` member `bounds_field`.
+fn check_instance(source: &str, options: &CullingOptions) -> Result<(), Diagnostic> {
+    let module = crate::parse_wgsl(source)
+        .map_err(|e| Diagnostic::error(format!("Invalid instance source: {}", e.message)))?;
+    let instance_type = &options.instance_type;
+    let members = module
+        .types
+        .iter()
+        .find_map(|(_, ty)| match ty.inner {
+            naga::TypeInner::Struct { ref members, .. }
+                if ty.name.as_deref() == Some(instance_type) =>
+            {
+                Some(members)
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Diagnostic::error(format!("Instance source has no struct '{instance_type}'"))
+        })?;
+    let field = &options.bounds_field;
+    let member = members
+        .iter()
+        .find(|m| m.name.as_deref() == Some(field))
+        .ok_or_else(|| {
+            Diagnostic::error(format!("Struct '{instance_type}' has no member '{field}'"))
+        })?;
+    match module.types[member.ty].inner {
+        naga::TypeInner::Vector {
+            size: naga::VectorSize::Quad,
+            scalar: naga::Scalar::F32,
+        } => Ok(()),
+        _ => Err(Diagnostic::error(format!(
+            "Bounds member '{field}' must be a vec4<f32> of center and radius"
+        ))),
+    }
+}
+
+fn layout_json() -> String {
+    let buffer = |binding: u32, ty: &str| {
+        serde_json::json!({
+            "binding": binding,
+            "visibility": COMPUTE,
+            "buffer": { "type": ty },
+        })
+    };
+    serde_json::json!({
+        "entries": [
+            buffer(0, "read-only-storage"),
+            buffer(1, "uniform"),
+            buffer(2, "storage"),
+            buffer(3, "storage"),
+        ]
+    })
+    .to_string()
+}
+
+pub(crate) fn culling_source(
+    instance_source: &str,
+    options: &CullingOptions,
+) -> Result<String, Diagnostic> {
+    let size = options.workgroup_size;
+    if size == 0 || size > 256 {
+        return Err(Diagnostic::error(format!(
+            "Workgroup size {size} is not between 1 and 256"
+        )));
+    }
+    check_instance(instance_source, options)?;
+    let draw_args = if options.indexed {
+        "\
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}"
+    } else {
+        "\
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+}"
+    };
+    let instance_type = &options.instance_type;
+    let bounds = &options.bounds_field;
+    Ok(format!(
+        "\
+{instance_source}
+
+// Frustum culling: appends the index of every instance whose bounds are
+// inside `frustum` to `visible`, counting them in `draw.instance_count`.
+struct Frustum {{
+    // Inward-facing planes: xyz normal, w distance.
+    planes: array<vec4<f32>, 6>,
+    instance_count: u32,
+}}
+
+{draw_args}
+
+@group(0) @binding(0) var<storage, read> instances: array<{instance_type}>;
+@group(0) @binding(1) var<uniform> frustum: Frustum;
+@group(0) @binding(2) var<storage, read_write> visible: array<u32>;
+@group(0) @binding(3) var<storage, read_write> draw: DrawArgs;
+
+fn sphere_visible(sphere: vec4<f32>) -> bool {{
+    for (var i = 0u; i < 6u; i++) {{
+        let plane = frustum.planes[i];
+        if dot(plane.xyz, sphere.xyz) + plane.w < -sphere.w {{
+            return false;
+        }}
+    }}
+    return true;
+}}
+
+@compute @workgroup_size({size})
+fn cull(@builtin(global_invocation_id) id: vec3<u32>) {{
+    let index = id.x;
+    if index >= frustum.instance_count {{
+        return;
+    }}
+    if sphere_visible(instances[index].{bounds}) {{
+        let slot = atomicAdd(&draw.instance_count, 1u);
+        visible[slot] = index;
+    }}
+}}
+"
+    ))
+}
+
+pub(crate) fn culling(
+    instance_source: &str,
+    options: &CullingOptions,
+) -> Result<CullingKernel, Diagnostic> {
+    let wgsl = culling_source(instance_source, options)?;
+    let (module, info) = crate::parse_and_validate(&wgsl)
+        .map_err(|e| Diagnostic::error(format!("Invalid culling kernel: {}", e.message)))?;
+    Ok(CullingKernel {
+        reflection: crate::reflect_module(&module, &info),
+        entry_point: "cull".to_string(),
+        workgroup_size: options.workgroup_size,
+        indexed: options.indexed,
+        draw_args_size: crate::layout::struct_layout(&wgsl, &module, "DrawArgs")?.size,
+        frustum_size: crate::layout::struct_layout(&wgsl, &module, "Frustum")?.size,
+        bind_group_layout: layout_json(),
+        wgsl,
+    })
+}
+
+/// A validated GPU frustum-culling kernel for GPU-driven rendering.
+/// `instanceSource` is WGSL declaring the instance struct; `options` is `{
+/// instanceType: string, boundsField?: string, indexed?: boolean,
+/// workgroupSize?: number }`. Group 0 binds the instances, a `frustum`
+/// uniform of six inward planes and the instance count, the `visible` index
+/// list and the `draw` arguments, which the host resets before each
+/// dispatch and then draws with indirectly.
+#[wasm_bindgen(js_name = generateCullingKernel)]
+pub fn generate_culling_kernel(
+    instance_source: &str,
+    options: JsValue,
+) -> Result<CullingKernel, JsValue> {
+    let options: CullingOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid culling options: {e}")))?;
+    culling(instance_source, &options).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTANCE: &str = "\
+struct Instance {
+    model: mat4x4<f32>,
+    sphere: vec4<f32>,
+    material: u32,
+}";
+
+    fn options(instance_type: &str, bounds_field: &str, indexed: bool) -> CullingOptions {
+        CullingOptions {
+            instance_type: instance_type.to_string(),
+            bounds_field: bounds_field.to_string(),
+            indexed,
+            workgroup_size: 64,
+        }
+    }
+
+    #[test]
+    fn kernels_validate_with_matching_layouts() {
+        for indexed in [true, false] {
+            let kernel = culling(INSTANCE, &options("Instance", "sphere", indexed)).unwrap();
+            assert!(kernel.wgsl.contains("array<Instance>"));
+            let ep = &kernel.reflection.entry_points[0];
+            assert_eq!(ep.workgroup_size, Some(vec![64, 1, 1]));
+            let bindings: Vec<_> = ep.bindings.iter().map(|b| b.name.as_str()).collect();
+            assert_eq!(bindings, ["instances", "frustum", "visible", "draw"]);
+            let layout: serde_json::Value =
+                serde_json::from_str(&kernel.bind_group_layout).unwrap();
+            assert_eq!(layout["entries"][0]["buffer"]["type"], "read-only-storage");
+            assert_eq!(kernel.workgroup_count(1000), 16);
+            assert_eq!(kernel.draw_args_size, if indexed { 20 } else { 16 });
+            assert_eq!(kernel.frustum_size, 112);
+        }
+    }
+
+    #[test]
+    fn bad_instance_structs_are_errors() {
+        let error = |source: &str, options: CullingOptions| {
+            culling(source, &options).err().unwrap().message
+        };
+        assert!(error(INSTANCE, options("Mesh", "sphere", true)).contains("no struct 'Mesh'"));
+        assert!(error(INSTANCE, options("Instance", "bounds", true)).contains("no member"));
+        assert!(error(INSTANCE, options("Instance", "material", true)).contains("vec4<f32>"));
+        let clash = format!("{INSTANCE}\nstruct Frustum {{ x: f32 }}");
+        assert!(
+            error(&clash, options("Instance", "sphere", true))
+                .starts_with("Invalid culling kernel")
+        );
+    }
+}
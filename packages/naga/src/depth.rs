@@ -0,0 +1,616 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use naga::{Expression, ImageClass, ImageDimension, ScalarKind, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::rewrite;
+
+// ============================================================================
+// Depth Comparison Types
+// ============================================================================
+
+/// Options object accepted by `emulateDepthComparisons`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DepthCompareOptions {
+    /// Comparison samplers to emulate, by variable name, with the state the
+    /// host creates them with.
+    #[serde(default)]
+    pub samplers: BTreeMap<String, EmulatedSampler>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EmulatedSampler {
+    pub compare: CompareFunction,
+    /// Filtering of the comparison results; `"nearest"` by default.
+    #[serde(default)]
+    pub filter: Filter,
+}
+
+/// A `GPUCompareFunction`.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    /// `depth_ref <op> depth` in WGSL.
+    fn test(self) -> &'static str {
+        match self {
+            CompareFunction::Never => "false",
+            CompareFunction::Less => "depth_ref < depth",
+            CompareFunction::Equal => "depth_ref == depth",
+            CompareFunction::LessEqual => "depth_ref <= depth",
+            CompareFunction::Greater => "depth_ref > depth",
+            CompareFunction::NotEqual => "depth_ref != depth",
+            CompareFunction::GreaterEqual => "depth_ref >= depth",
+            CompareFunction::Always => "true",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CompareFunction::Never => "never",
+            CompareFunction::Less => "less",
+            CompareFunction::Equal => "equal",
+            CompareFunction::LessEqual => "less_equal",
+            CompareFunction::Greater => "greater",
+            CompareFunction::NotEqual => "not_equal",
+            CompareFunction::GreaterEqual => "greater_equal",
+            CompareFunction::Always => "always",
+        }
+    }
+}
+
+/// A `GPUFilterMode`, as the sampler's `magFilter` and `minFilter`.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Filter {
+    #[default]
+    Nearest,
+    Linear,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DepthCompareReport {
+    /// Comparisons replaced by a call to a helper.
+    #[wasm_bindgen(readonly)]
+    pub converted: Vec<EmulatedComparison>,
+    /// Comparisons through one of the samplers left in place, with why.
+    #[wasm_bindgen(readonly)]
+    pub skipped: Vec<EmulatedComparison>,
+    /// Bindings whose declaration or use changed.
+    #[wasm_bindgen(readonly)]
+    pub bindings: Vec<AffectedBinding>,
+    /// Names of the generated helper functions.
+    #[wasm_bindgen(readonly)]
+    pub helpers: Vec<String>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+}
+
+#[wasm_bindgen]
+impl DepthCompareReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Comparisons through one sampler in one function, grouped.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct EmulatedComparison {
+    #[wasm_bindgen(readonly)]
+    pub function: String,
+    #[wasm_bindgen(readonly)]
+    pub sampler: String,
+    /// Number of comparisons.
+    #[wasm_bindgen(readonly)]
+    pub count: u32,
+    /// Why the comparisons were skipped; `None` for converted ones.
+    #[wasm_bindgen(readonly)]
+    pub reason: Option<String>,
+}
+
+#[wasm_bindgen]
+impl EmulatedComparison {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct AffectedBinding {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub binding: Option<u32>,
+    /// `"sampler"` for a comparison sampler now declared as a plain, unused
+    /// `sampler`; `"texture"` for a depth texture now read with `textureLoad`,
+    /// which needs no filtering.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+}
+
+#[wasm_bindgen]
+impl AffectedBinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Depth Comparison Emulation
+// ============================================================================
+//
+// A comparison sample is the sampler's compare function applied to texels
+// fetched around the coordinate: one for nearest filtering, the four a
+// bilinear filter would weigh for linear filtering (percentage-closer
+// filtering). Neither the function nor the filter appears in the shader, so
+// the caller states them per sampler. Each comparison through one of those
+// samplers becomes a call to a helper generated for that state and texture
+// type, which does exactly that with `textureLoad`. Like external textures,
+// the helpers are parsed ahead of the source so they precede their callers.
+// A sampler all of whose comparisons were emulated is no longer used, and is
+// left declared as a plain `sampler` so that bindings do not shift.
+
+/// What a helper is generated for.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Helper {
+    sampler: (CompareFunction, Filter),
+    /// The kind of the array index, for `texture_depth_2d_array`.
+    layer: Option<ScalarKind>,
+}
+
+impl Helper {
+    fn name(&self) -> String {
+        let (compare, filter) = self.sampler;
+        let texture = match self.layer {
+            None => "2d",
+            Some(ScalarKind::Uint) => "2d_array_u32",
+            Some(_) => "2d_array_i32",
+        };
+        let filter = match filter {
+            Filter::Nearest => "nearest",
+            Filter::Linear => "linear",
+        };
+        format!(
+            "metis_depth_compare_{}_{}_{}",
+            compare.name(),
+            filter,
+            texture
+        )
+    }
+
+    fn source(&self) -> String {
+        let (compare, filter) = self.sampler;
+        let (texture, layer, load_layer) = match self.layer {
+            None => ("texture_depth_2d", String::new(), ""),
+            Some(kind) => {
+                let ty = if kind == ScalarKind::Uint {
+                    "u32"
+                } else {
+                    "i32"
+                };
+                (
+                    "texture_depth_2d_array",
+                    format!(", layer: {}", ty),
+                    ", layer",
+                )
+            }
+        };
+        let name = self.name();
+        let test = |coords: &str| {
+            format!(
+                "select(0.0, 1.0, {}_test(depth_ref, textureLoad(t, clamp({}, vec2<i32>(0), last){}, 0)))",
+                name, coords, load_layer
+            )
+        };
+        let body = match filter {
+            Filter::Nearest => format!(
+                "    let texel = vec2<i32>(floor(uv * vec2<f32>(size)));\n    return {};",
+                test("texel")
+            ),
+            Filter::Linear => format!(
+                "    let pos = uv * vec2<f32>(size) - 0.5;\n\
+                 \x20   let base = vec2<i32>(floor(pos));\n\
+                 \x20   let f = fract(pos);\n\
+                 \x20   let c00 = {};\n\
+                 \x20   let c10 = {};\n\
+                 \x20   let c01 = {};\n\
+                 \x20   let c11 = {};\n\
+                 \x20   return mix(mix(c00, c10, f.x), mix(c01, c11, f.x), f.y);",
+                test("base"),
+                test("base + vec2<i32>(1, 0)"),
+                test("base + vec2<i32>(0, 1)"),
+                test("base + vec2<i32>(1, 1)"),
+            ),
+        };
+        format!(
+            "fn {name}_test(depth_ref: f32, depth: f32) -> bool {{\n\
+             \x20   return {test};\n\
+             }}\n\
+             fn {name}(t: {texture}, uv: vec2<f32>{layer}, depth_ref: f32) -> f32 {{\n\
+             \x20   let size = vec2<i32>(textureDimensions(t));\n\
+             \x20   let last = size - 1;\n\
+             {body}\n\
+             }}\n",
+            test = compare.test(),
+        )
+    }
+}
+
+/// The helper for the comparison `expr`, or why it cannot be emulated,
+/// if it goes through one of `samplers`.
+fn classify(
+    module: &naga::Module,
+    function: &naga::Function,
+    info: &naga::valid::FunctionInfo,
+    expr: &Expression,
+    samplers: &BTreeMap<String, EmulatedSampler>,
+) -> Option<(String, Result<Helper, String>)> {
+    let Expression::ImageSample {
+        image,
+        sampler,
+        gather,
+        array_index,
+        offset,
+        depth_ref: Some(_),
+        ..
+    } = *expr
+    else {
+        return None;
+    };
+    let Expression::GlobalVariable(handle) = function.expressions[sampler] else {
+        return None;
+    };
+    let name = module.global_variables[handle].name.clone()?;
+    let state = samplers.get(&name)?;
+    let helper = (|| {
+        if gather.is_some() {
+            return Err("gathers are not emulated".to_string());
+        }
+        if offset.is_some() {
+            return Err("offsets are not supported".to_string());
+        }
+        match *info[image].ty.inner_with(&module.types) {
+            TypeInner::Image {
+                dim: ImageDimension::D2,
+                class: ImageClass::Depth { multi: false },
+                ..
+            } => {}
+            _ => return Err("only 2D depth textures can be fetched".to_string()),
+        }
+        let layer = array_index.map(|h| {
+            info[h]
+                .ty
+                .inner_with(&module.types)
+                .scalar_kind()
+                .unwrap_or(ScalarKind::Sint)
+        });
+        Ok(Helper {
+            sampler: (state.compare, state.filter),
+            layer,
+        })
+    })();
+    Some((name, helper))
+}
+
+type Groups = BTreeMap<(String, String, Option<String>), u32>;
+
+/// Emulate the comparisons through `samplers` in `function`, count every
+/// candidate in `groups` and collect the depth textures now fetched from.
+fn emulate_function(
+    module: &naga::Module,
+    name: &str,
+    function: &mut naga::Function,
+    info: &naga::valid::FunctionInfo,
+    samplers: &BTreeMap<String, EmulatedSampler>,
+    groups: &mut Groups,
+    textures: &mut BTreeSet<String>,
+) {
+    let helper = |helper: &Helper| {
+        let name = helper.name();
+        module
+            .functions
+            .fetch_if(|f| f.name.as_deref() == Some(name.as_str()))
+            .expect("helpers are generated for every comparison")
+    };
+    let mut calls = HashMap::new();
+    for (h, expr) in function.expressions.iter() {
+        let Some((sampler, outcome)) = classify(module, function, info, expr, samplers) else {
+            continue;
+        };
+        if let (
+            Ok(spec),
+            Expression::ImageSample {
+                image,
+                coordinate,
+                array_index,
+                depth_ref: Some(depth_ref),
+                ..
+            },
+        ) = (&outcome, expr)
+        {
+            let mut arguments = vec![*image, *coordinate];
+            arguments.extend(*array_index);
+            arguments.push(*depth_ref);
+            calls.insert(h, (helper(spec), arguments));
+            if let Expression::GlobalVariable(g) = function.expressions[*image] {
+                textures.extend(module.global_variables[g].name.clone());
+            }
+        }
+        *groups
+            .entry((name.to_string(), sampler, outcome.err()))
+            .or_default() += 1;
+    }
+    for (&h, &(callee, _)) in &calls {
+        function.expressions[h] = Expression::CallResult(callee);
+    }
+    rewrite::insert_calls(&mut function.body, &calls);
+}
+
+fn emulate(wgsl: &str, options: &DepthCompareOptions) -> Result<DepthCompareReport, Diagnostic> {
+    let (module, info) = crate::parse_and_validate(wgsl)?;
+    for name in options.samplers.keys() {
+        let declared = module.global_variables.iter().any(|(_, var)| {
+            var.name.as_deref() == Some(name.as_str())
+                && matches!(
+                    module.types[var.ty].inner,
+                    TypeInner::Sampler { comparison: true }
+                )
+        });
+        if !declared {
+            return Err(Diagnostic::error(format!(
+                "Unknown comparison sampler '{}'",
+                name
+            )));
+        }
+    }
+
+    let mut helpers = BTreeSet::new();
+    let functions = module.functions.iter().map(|(h, f)| (f, &info[h])).chain(
+        module
+            .entry_points
+            .iter()
+            .enumerate()
+            .map(|(i, ep)| (&ep.function, info.get_entry_point(i))),
+    );
+    for (function, function_info) in functions {
+        for (_, expr) in function.expressions.iter() {
+            if let Some((_, Ok(helper))) =
+                classify(&module, function, function_info, expr, &options.samplers)
+            {
+                helpers.insert(helper);
+            }
+        }
+    }
+    let source: String = helpers.iter().map(Helper::source).collect();
+    let mut module = crate::parse_wgsl(&format!("{}\n{}", source, wgsl))?;
+    let info = crate::validate_module(&module)?;
+
+    let mut groups = Groups::new();
+    let mut textures = BTreeSet::new();
+    let handles: Vec<_> = module.functions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let mut function = std::mem::take(&mut module.functions[h]);
+        let name = function.name.clone().unwrap_or_default();
+        emulate_function(
+            &module,
+            &name,
+            &mut function,
+            &info[h],
+            &options.samplers,
+            &mut groups,
+            &mut textures,
+        );
+        module.functions[h] = function;
+    }
+    for index in 0..module.entry_points.len() {
+        let mut function = std::mem::take(&mut module.entry_points[index].function);
+        let name = module.entry_points[index].name.clone();
+        emulate_function(
+            &module,
+            &name,
+            &mut function,
+            info.get_entry_point(index),
+            &options.samplers,
+            &mut groups,
+            &mut textures,
+        );
+        module.entry_points[index].function = function;
+    }
+
+    let (mut converted, mut skipped) = (Vec::new(), Vec::new());
+    for ((function, sampler, reason), count) in groups {
+        let list = if reason.is_some() {
+            &mut skipped
+        } else {
+            &mut converted
+        };
+        list.push(EmulatedComparison {
+            function,
+            sampler,
+            count,
+            reason,
+        });
+    }
+
+    let helper_names: BTreeSet<_> = helpers.iter().map(Helper::name).collect();
+    let retyped: BTreeSet<_> = converted
+        .iter()
+        .map(|c| c.sampler.clone())
+        .filter(|s| !skipped.iter().any(|k| &k.sampler == s))
+        .collect();
+    let plain = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: TypeInner::Sampler { comparison: false },
+        },
+        naga::Span::UNDEFINED,
+    );
+    let mut bindings = Vec::new();
+    for (_, var) in module.global_variables.iter_mut() {
+        let Some(name) = var.name.clone() else {
+            continue;
+        };
+        let kind = if retyped.contains(&name) {
+            var.ty = plain;
+            "sampler"
+        } else if textures.contains(&name) {
+            "texture"
+        } else {
+            continue;
+        };
+        bindings.push(AffectedBinding {
+            name,
+            group: var.binding.map(|b| b.group),
+            binding: var.binding.map(|b| b.binding),
+            kind: kind.to_string(),
+        });
+    }
+    naga::compact::compact(&mut module, naga::compact::KeepUnused::Yes);
+
+    let info = crate::validate_module(&module)?;
+    let wgsl =
+        naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+    Ok(DepthCompareReport {
+        converted,
+        skipped,
+        bindings,
+        helpers: helper_names.into_iter().collect(),
+        wgsl,
+    })
+}
+
+/// Replaces depth comparison sampling (`textureSampleCompare` and
+/// `textureSampleCompareLevel`) with `textureLoad`s and a manual compare, for
+/// targets without comparison samplers. Only comparisons through the
+/// samplers in `options.samplers` are emulated, using the compare function
+/// and filter given for each, since the shader does not say. The generated
+/// helpers are part of the returned WGSL; the report lists the samplers and
+/// textures whose bindings are affected, and the comparisons left in place.
+/// `options` is `{ samplers: { [name]: { compare: GPUCompareFunction,
+/// filter?: "nearest" | "linear" } } }`.
+#[wasm_bindgen(js_name = emulateDepthComparisons)]
+pub fn emulate_depth_comparisons(
+    wgsl: &str,
+    options: JsValue,
+) -> Result<DepthCompareReport, JsValue> {
+    let options: Option<DepthCompareOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid depth comparison options: {e}")))?;
+    emulate(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADOWS: &str = r#"
+        @group(0) @binding(0) var shadow_map: texture_depth_2d;
+        @group(0) @binding(1) var cascades: texture_depth_2d_array;
+        @group(0) @binding(2) var shadow_cmp: sampler_comparison;
+        @group(0) @binding(3) var cube: texture_depth_cube;
+        @group(0) @binding(4) var cube_cmp: sampler_comparison;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>, @location(1) depth: f32) -> @location(0) vec4<f32> {
+            let a = textureSampleCompare(shadow_map, shadow_cmp, uv, depth);
+            let b = textureSampleCompareLevel(cascades, shadow_cmp, uv, 2u, depth);
+            let c = textureSampleCompare(cube, cube_cmp, vec3<f32>(uv, 1.0), depth);
+            return vec4<f32>(a, b, c, 1.0);
+        }
+    "#;
+
+    fn sampler(compare: CompareFunction, filter: Filter) -> EmulatedSampler {
+        EmulatedSampler { compare, filter }
+    }
+
+    #[test]
+    fn comparisons_become_fetches() {
+        let options = DepthCompareOptions {
+            samplers: BTreeMap::from([
+                (
+                    "shadow_cmp".to_string(),
+                    sampler(CompareFunction::LessEqual, Filter::Linear),
+                ),
+                (
+                    "cube_cmp".to_string(),
+                    sampler(CompareFunction::Less, Filter::Nearest),
+                ),
+            ]),
+        };
+        let report = emulate(SHADOWS, &options).unwrap();
+        assert_eq!(report.converted.len(), 1);
+        assert_eq!(report.converted[0].count, 2);
+        assert_eq!(
+            report.skipped[0].reason.as_deref(),
+            Some("only 2D depth textures can be fetched")
+        );
+        assert_eq!(
+            report.helpers,
+            [
+                "metis_depth_compare_less_equal_linear_2d",
+                "metis_depth_compare_less_equal_linear_2d_array_u32",
+            ]
+        );
+        let kinds: Vec<_> = report
+            .bindings
+            .iter()
+            .map(|b| (b.name.as_str(), b.kind.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("shadow_map", "texture"),
+                ("cascades", "texture"),
+                ("shadow_cmp", "sampler"),
+            ]
+        );
+        assert!(report.wgsl.contains("var shadow_cmp: sampler;"));
+        assert!(report.wgsl.contains("var cube_cmp: sampler_comparison;"));
+        assert!(report.wgsl.contains("depth_ref <= depth"));
+        assert_eq!(report.wgsl.matches("textureSampleCompare").count(), 1);
+    }
+
+    #[test]
+    fn samplers_must_be_comparison_samplers() {
+        let options = |name: &str| DepthCompareOptions {
+            samplers: BTreeMap::from([(
+                name.to_string(),
+                sampler(CompareFunction::Always, Filter::Nearest),
+            )]),
+        };
+        assert!(emulate(SHADOWS, &options("shadow_map")).is_err());
+        assert!(emulate(SHADOWS, &options("missing")).is_err());
+        let report = emulate(SHADOWS, &DepthCompareOptions::default()).unwrap();
+        assert!(report.converted.is_empty() && report.helpers.is_empty());
+    }
+}
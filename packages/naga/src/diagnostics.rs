@@ -0,0 +1,641 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::catalog::localize;
+
+// ============================================================================
+// Diagnostic Types
+// ============================================================================
+
+/// A single compiler message. Internal code paths return these instead of
+/// building `JsValue`s directly, so the throwing and the result-object APIs
+/// share the same error text.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct Diagnostic {
+    #[wasm_bindgen(readonly)]
+    pub severity: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+}
+
+#[wasm_bindgen]
+impl Diagnostic {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+impl Diagnostic {
+    pub(crate) fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: "error".to_string(),
+            message: localize(message.into()),
+        }
+    }
+
+    pub(crate) fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: "warning".to_string(),
+            ..Self::error(message)
+        }
+    }
+}
+
+/// The throwing API surfaces a diagnostic as a plain JS string, as it always has.
+pub(crate) fn throw(diagnostic: Diagnostic) -> JsValue {
+    JsValue::from_str(&diagnostic.message)
+}
+
+// ============================================================================
+// Detailed Diagnostics
+// ============================================================================
+//
+// Editors need positions, not just text. These carry naga's spans through:
+// byte offsets for slicing the source, and 1-based lines and columns in
+// UTF-16 code units, as JS strings and editor buffers count them.
+
+/// A range of the source.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SourceSpan {
+    /// Byte offset of the first byte.
+    #[wasm_bindgen(readonly)]
+    pub start: u32,
+    /// Byte offset one past the last byte.
+    #[wasm_bindgen(readonly)]
+    pub end: u32,
+    #[wasm_bindgen(readonly)]
+    pub line: u32,
+    #[wasm_bindgen(readonly)]
+    pub column: u32,
+    #[wasm_bindgen(readonly)]
+    pub end_line: u32,
+    #[wasm_bindgen(readonly)]
+    pub end_column: u32,
+}
+
+#[wasm_bindgen]
+impl SourceSpan {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// 1-based line and UTF-16 column of byte `offset`, clamped into `source`.
+fn line_column(source: &str, offset: usize) -> (u32, u32) {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let prefix = &source[..offset];
+    let line_start = prefix.rfind('\n').map_or(0, |i| i + 1);
+    let line = prefix.matches('\n').count() as u32 + 1;
+    let column = prefix[line_start..].encode_utf16().count() as u32 + 1;
+    (line, column)
+}
+
+impl SourceSpan {
+    pub(crate) fn new(source: &str, span: naga::Span) -> Option<Self> {
+        let range = span.to_range()?;
+        let (line, column) = line_column(source, range.start);
+        let (end_line, end_column) = line_column(source, range.end);
+        Some(Self {
+            start: range.start as u32,
+            end: range.end as u32,
+            line,
+            column,
+            end_line,
+            end_column,
+        })
+    }
+}
+
+/// A span with what it points at, e.g. "expected expression".
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DiagnosticLabel {
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    #[wasm_bindgen(readonly)]
+    pub span: SourceSpan,
+}
+
+#[wasm_bindgen]
+impl DiagnosticLabel {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DetailedDiagnostic {
+    #[wasm_bindgen(readonly)]
+    pub severity: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// The primary label's span; `None` if naga recorded none.
+    #[wasm_bindgen(readonly)]
+    pub span: Option<SourceSpan>,
+    /// Primary label first, then related ones.
+    #[wasm_bindgen(readonly)]
+    pub labels: Vec<DiagnosticLabel>,
+    /// Underlying causes, outermost first.
+    #[wasm_bindgen(readonly)]
+    pub notes: Vec<String>,
+    /// With `friendly`, what the error means in plain language.
+    #[wasm_bindgen(readonly)]
+    pub explanation: Option<String>,
+    /// With `friendly`, the declaration or expression rewritten to fix the
+    /// error, where the source shows how; for a GLSL-ism, its WGSL spelling.
+    #[wasm_bindgen(readonly)]
+    pub fix: Option<String>,
+    /// What `fix` replaces.
+    #[wasm_bindgen(readonly)]
+    pub fix_span: Option<SourceSpan>,
+    /// Names `fix` for `applyFix`.
+    #[wasm_bindgen(readonly)]
+    pub fix_id: Option<String>,
+}
+
+#[wasm_bindgen]
+impl DetailedDiagnostic {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+impl DetailedDiagnostic {
+    fn new<'a>(
+        source: &str,
+        message: String,
+        labels: impl Iterator<Item = (naga::Span, &'a str)>,
+        notes: Vec<String>,
+    ) -> Self {
+        let labels: Vec<_> = labels
+            .filter_map(|(span, message)| {
+                Some(DiagnosticLabel {
+                    message: localize(message.to_string()),
+                    span: SourceSpan::new(source, span)?,
+                })
+            })
+            .collect();
+        Self {
+            severity: "error".to_string(),
+            message: localize(message),
+            span: labels.first().map(|label| label.span.clone()),
+            labels,
+            notes: notes.into_iter().map(localize).collect(),
+            explanation: None,
+            fix: None,
+            fix_span: None,
+            fix_id: None,
+        }
+    }
+
+    /// A warning labelled `label` at `span` of `source`.
+    pub(crate) fn warning(source: &str, message: String, span: naga::Span, label: &str) -> Self {
+        Self {
+            severity: "warning".to_string(),
+            ..Self::new(source, message, std::iter::once((span, label)), Vec::new())
+        }
+    }
+
+    pub(crate) fn from_parse_error(source: &str, error: &naga::front::wgsl::ParseError) -> Self {
+        Self::new(
+            source,
+            error.message().to_string(),
+            error.labels(),
+            Vec::new(),
+        )
+    }
+
+    pub(crate) fn from_validation_error<E: std::error::Error>(
+        source: &str,
+        error: &naga::WithSpan<E>,
+    ) -> Self {
+        let mut notes = Vec::new();
+        let mut cause = error.as_inner().source();
+        while let Some(next) = cause {
+            notes.push(next.to_string());
+            cause = next.source();
+        }
+        Self::new(
+            source,
+            error.as_inner().to_string(),
+            error.spans().map(|(span, label)| (*span, label.as_str())),
+            notes,
+        )
+    }
+}
+
+pub(crate) fn detailed_diagnostics(wgsl: &str) -> Vec<DetailedDiagnostic> {
+    detailed_diagnostics_within(
+        wgsl,
+        &Budget::new(None),
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .0
+}
+
+/// `detailed_diagnostics` running the checks in `flags` with
+/// `capabilities`. A limited `budget` validates in steps, stopping at the
+/// first step it has run out before; the flag returned is whether it did.
+pub(crate) fn detailed_diagnostics_within(
+    wgsl: &str,
+    budget: &Budget,
+    flags: naga::valid::ValidationFlags,
+    capabilities: naga::valid::Capabilities,
+) -> (Vec<DetailedDiagnostic>, bool) {
+    if let Some(oversized) = crate::const_limit::oversized(wgsl) {
+        let label = (oversized.span(), "constructed here");
+        let diagnostic = DetailedDiagnostic::new(
+            wgsl,
+            oversized.message(),
+            std::iter::once(label),
+            Vec::new(),
+        );
+        return (vec![diagnostic], false);
+    }
+    let module = match naga::front::wgsl::parse_str(wgsl) {
+        Ok(module) => module,
+        Err(e) => return (vec![DetailedDiagnostic::from_parse_error(wgsl, &e)], false),
+    };
+    if !budget.is_limited() {
+        let diagnostics = crate::run_validator(&module, flags, capabilities)
+            .err()
+            .map(|e| DetailedDiagnostic::from_validation_error(wgsl, &e));
+        return (diagnostics.into_iter().collect(), false);
+    }
+    validate_in_steps(wgsl, module, flags, capabilities, || !budget.exhausted())
+}
+
+// ============================================================================
+// Time Budgets
+// ============================================================================
+//
+// Autosave validation must not jank the editor, so it can be given a
+// wall-clock budget. naga's parser and validator cannot be interrupted, so
+// a budgeted run validates in steps and checks the budget before each: the
+// module without its entry points (declarations and helper functions), then
+// each entry point on its own. Once the budget is spent no further step
+// starts, and what the finished steps found comes back flagged as partial.
+// A step already running finishes, so the budget bounds when work stops
+// being started rather than the total time. Since every step validates the
+// declarations again, a problem there is reported once, by the first; each
+// entry point, though, can add its own, where a single run stops at one.
+
+/// Milliseconds since the epoch, from the JS clock under wasm.
+fn now_millis() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0)
+    }
+}
+
+pub(crate) struct Budget {
+    start: f64,
+    max_millis: Option<f64>,
+}
+
+impl Budget {
+    /// A budget of `max_millis` from now, or an unlimited one.
+    pub(crate) fn new(max_millis: Option<f64>) -> Self {
+        Self {
+            start: now_millis(),
+            max_millis,
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> f64 {
+        (now_millis() - self.start).max(0.0)
+    }
+
+    pub(crate) fn exhausted(&self) -> bool {
+        self.max_millis.is_some_and(|max| self.elapsed() >= max)
+    }
+
+    pub(crate) fn is_limited(&self) -> bool {
+        self.max_millis.is_some()
+    }
+}
+
+/// Validate `module` in the steps described above, asking `proceed` before
+/// each. The flag returned is whether `proceed` stopped it early.
+fn validate_in_steps(
+    wgsl: &str,
+    mut module: naga::Module,
+    flags: naga::valid::ValidationFlags,
+    capabilities: naga::valid::Capabilities,
+    mut proceed: impl FnMut() -> bool,
+) -> (Vec<DetailedDiagnostic>, bool) {
+    let entry_points = std::mem::take(&mut module.entry_points);
+    let mut diagnostics: Vec<DetailedDiagnostic> = Vec::new();
+    for step in 0..=entry_points.len() {
+        if !proceed() {
+            return (diagnostics, true);
+        }
+        let validated = match step {
+            0 => crate::run_validator(&module, flags, capabilities),
+            _ => {
+                let mut piece = module.clone();
+                piece.entry_points.push(entry_points[step - 1].clone());
+                crate::run_validator(&piece, flags, capabilities)
+            }
+        };
+        if let Err(e) = validated {
+            let diagnostic = DetailedDiagnostic::from_validation_error(wgsl, &e);
+            let repeated = diagnostics
+                .iter()
+                .any(|seen| seen.message == diagnostic.message && seen.span == diagnostic.span);
+            if !repeated {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+    (diagnostics, false)
+}
+
+/// Diagnostics found within a time budget.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BudgetedDiagnostics {
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<DetailedDiagnostic>,
+    /// The budget ran out before every check ran, so an empty
+    /// `diagnostics` does not mean the shader is valid.
+    #[wasm_bindgen(readonly)]
+    pub timed_out: bool,
+    #[wasm_bindgen(readonly)]
+    pub elapsed_millis: f64,
+}
+
+#[wasm_bindgen]
+impl BudgetedDiagnostics {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// The diagnostics `options` ask for, within `budget`, flagging whether it
+/// ran out. Fails only for unknown checks or capability profiles.
+fn diagnostics_with(
+    wgsl: &str,
+    options: &DetailedDiagnosticOptions,
+    budget: &Budget,
+) -> Result<(Vec<DetailedDiagnostic>, bool), Diagnostic> {
+    let flags = crate::preset::resolve_checks(options.checks.as_ref())?;
+    let capabilities = crate::preset::resolve_profile(options.profile.as_deref())?;
+    let (mut diagnostics, timed_out) = if options.glsl_isms {
+        crate::glsl_compat::lenient_diagnostics_within(wgsl, budget, flags, capabilities)
+    } else {
+        detailed_diagnostics_within(wgsl, budget, flags, capabilities)
+    };
+    if options.friendly {
+        crate::friendly::befriend(wgsl, &mut diagnostics);
+    }
+    Ok((diagnostics, timed_out))
+}
+
+pub(crate) fn budgeted_diagnostics(
+    wgsl: &str,
+    options: &DetailedDiagnosticOptions,
+) -> Result<BudgetedDiagnostics, Diagnostic> {
+    let budget = Budget::new(options.max_millis);
+    let (diagnostics, timed_out) = diagnostics_with(wgsl, options, &budget)?;
+    Ok(BudgetedDiagnostics {
+        diagnostics,
+        timed_out,
+        elapsed_millis: budget.elapsed(),
+    })
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DetailedDiagnosticOptions {
+    /// Rewrite GLSL spellings (`float(x)`, `vec3 n = ...;`, `1.0F`,
+    /// `inversesqrt`) to WGSL before parsing, reporting each as an `info`.
+    #[serde(default)]
+    pub glsl_isms: bool,
+    /// Time budget of `validateWgslBudgeted`; `validateWgslDetailed`
+    /// rejects it.
+    #[serde(default)]
+    pub max_millis: Option<f64>,
+    /// Explain common errors in plain language, with a suggested fix.
+    #[serde(default)]
+    pub friendly: bool,
+    /// Capability profile to validate against, e.g. `"webgpu-core"`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Validation checks to run: `"full"`, `"fast"` or a list of names.
+    #[serde(default)]
+    pub checks: Option<crate::preset::ValidationChecks>,
+}
+
+/// Parses and validates WGSL, returning its problems with source positions
+/// instead of throwing: an empty array if the shader is valid. Each entry has
+/// a primary `span` plus every `labels` span naga attached, with byte offsets
+/// and 1-based line/column (UTF-16) positions. With `{ glslIsms: true }`,
+/// common GLSL spellings are accepted and reported instead of failing. With
+/// `{ friendly: true }`, common mistakes also get a plain-language
+/// `explanation` and, where the source shows how, a suggested `fix`, which
+/// `applyFix` applies. With `{ profile }`, validation allows only what that
+/// capability profile does (see `listCapabilityProfiles`). `{ checks: "fast"
+/// }` skips uniformity and constant checks for quicker results while
+/// editing; `checks` can also list them, from `expressions`, `blocks`,
+/// `controlFlowUniformity`, `structLayouts`, `constants` and `bindings`.
+/// A time budget needs `validateWgslBudgeted`; `maxMillis` here throws.
+#[wasm_bindgen(js_name = validateWgslDetailed)]
+pub fn validate_wgsl_detailed(
+    wgsl: &str,
+    options: JsValue,
+) -> Result<Vec<DetailedDiagnostic>, JsValue> {
+    let options: Option<DetailedDiagnosticOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid diagnostic options: {e}")))?;
+    detailed_with(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+/// `validateWgslDetailed` after options are parsed. It has no way to say
+/// results are partial, so a `maxMillis` is refused rather than ignored.
+fn detailed_with(
+    wgsl: &str,
+    options: &DetailedDiagnosticOptions,
+) -> Result<Vec<DetailedDiagnostic>, Diagnostic> {
+    if options.max_millis.is_some() {
+        return Err(Diagnostic::error(
+            "maxMillis is only supported by validateWgslBudgeted, which reports whether time ran out",
+        ));
+    }
+    let (diagnostics, _) = diagnostics_with(wgsl, options, &Budget::new(None))?;
+    Ok(diagnostics)
+}
+
+/// `validateWgslDetailed` for background paths such as autosave: with `{
+/// maxMillis }`, checks stop starting once that much time has passed, and the
+/// diagnostics found so far come back with `timedOut` set. Validation runs
+/// per entry point, so it can stop between them and may report a problem
+/// in each. A check already running is not interrupted.
+#[wasm_bindgen(js_name = validateWgslBudgeted)]
+pub fn validate_wgsl_budgeted(
+    wgsl: &str,
+    options: JsValue,
+) -> Result<BudgetedDiagnostics, JsValue> {
+    let options: Option<DetailedDiagnosticOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid diagnostic options: {e}")))?;
+    budgeted_diagnostics(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_errors_carry_positions() {
+        let source = "// héllo\nfn main() {\n    let x = ;\n}\n";
+        let diagnostics = detailed_diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!((span.line, span.column), (3, 13));
+        assert_eq!(&source[span.start as usize..span.end as usize], ";");
+        assert!(!diagnostics[0].labels[0].message.is_empty());
+
+        // Columns count UTF-16 units, bytes do not.
+        assert_eq!(line_column("é = 1", 3), (1, 3));
+        assert!(detailed_diagnostics("fn main() {}").is_empty());
+    }
+
+    #[test]
+    fn validation_errors_carry_spans_and_causes() {
+        let source = "@group(0) @binding(0) var<uniform> u: array<f32>;\n@compute @workgroup_size(1) fn main() { _ = u[0]; }\n";
+        let diagnostics = detailed_diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert!(diagnostic.message.contains("'u' is invalid"));
+        let span = diagnostic.span.clone().unwrap();
+        assert_eq!((span.line, span.column), (1, 23));
+        assert_eq!(diagnostic.labels.len(), 1);
+        // The cause chain explains why.
+        assert!(diagnostic.notes.iter().any(|note| note.contains("stride")));
+    }
+
+    #[test]
+    fn spent_budgets_return_partial_results() {
+        let invalid = "@group(0) @binding(0) var<uniform> u: array<f32>;\n@compute @workgroup_size(1) fn main() { _ = u[0]; }\n";
+        let options = |max_millis| DetailedDiagnosticOptions {
+            max_millis,
+            ..Default::default()
+        };
+        // Parsing spends a zero budget, so validation never starts.
+        let spent = budgeted_diagnostics(invalid, &options(Some(0.0))).unwrap();
+        assert!(spent.timed_out && spent.diagnostics.is_empty());
+        let full = budgeted_diagnostics(invalid, &options(Some(60_000.0))).unwrap();
+        assert!(!full.timed_out);
+        assert_eq!(full.diagnostics.len(), 1);
+
+        // Parse errors are complete results however little time is left.
+        let unparsable = budgeted_diagnostics("fn main( {}", &options(Some(0.0))).unwrap();
+        assert!(!unparsable.timed_out);
+        assert_eq!(unparsable.diagnostics.len(), 1);
+
+        let error = detailed_with(invalid, &options(Some(60_000.0)))
+            .err()
+            .unwrap();
+        assert!(error.message.contains("validateWgslBudgeted"));
+        assert_eq!(detailed_with(invalid, &options(None)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn steps_keep_what_they_found() {
+        let source = "@vertex fn v() -> @location(0) vec4<f32> { return vec4<f32>(); }\n\
+                      @compute @workgroup_size(1) fn c(@builtin(position) p: vec4<f32>) {}\n";
+        let flags = naga::valid::ValidationFlags::all();
+        let capabilities = naga::valid::Capabilities::all();
+        let run = |steps: usize| {
+            let module = crate::parse_wgsl(source).unwrap();
+            let mut taken = 0;
+            let (found, stopped) = validate_in_steps(source, module, flags, capabilities, || {
+                taken += 1;
+                taken <= steps
+            });
+            let messages: Vec<_> = found.into_iter().map(|d| d.message).collect();
+            (messages, stopped)
+        };
+        // Declarations, then `v`, then `c`: stopping before `c` keeps `v`'s.
+        assert_eq!(run(1), (Vec::new(), true));
+        assert_eq!(
+            run(2),
+            (vec!["Entry point v at Vertex is invalid".to_string()], true)
+        );
+        let (all, stopped) = run(3);
+        assert!(!stopped);
+        assert_eq!(all.len(), 2);
+        // An unbudgeted run stops at the first.
+        assert_eq!(detailed_diagnostics(source).len(), 1);
+
+        // A problem in the declarations is reported once.
+        let invalid = "@group(0) @binding(0) var<uniform> u: array<f32>;\n\
+                       @compute @workgroup_size(1) fn a() { _ = u[0]; }\n\
+                       @compute @workgroup_size(1) fn b() { _ = u[0]; }\n";
+        let module = crate::parse_wgsl(invalid).unwrap();
+        let (found, _) = validate_in_steps(invalid, module, flags, capabilities, || true);
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn checks_can_be_chosen() {
+        let source = "struct S { a: f32, b: array<f32, 4> }\n@group(0) @binding(0) var<uniform> u: S;\n@compute @workgroup_size(1) fn main() { _ = u.a; }\n";
+        let run = |checks| {
+            let options = DetailedDiagnosticOptions {
+                checks,
+                ..Default::default()
+            };
+            diagnostics_with(source, &options, &Budget::new(None)).map(|(found, _)| found.len())
+        };
+        let named = |name: &str| Some(crate::preset::ValidationChecks::Named(name.to_string()));
+        let each = |names: &[&str]| {
+            Some(crate::preset::ValidationChecks::Each(
+                names.iter().map(|name| name.to_string()).collect(),
+            ))
+        };
+        // The misaligned uniform array is a layout problem, which `fast`
+        // still checks.
+        assert_eq!(run(None).unwrap(), 1);
+        assert_eq!(run(named("full")).unwrap(), 1);
+        assert_eq!(run(named("fast")).unwrap(), 1);
+        assert_eq!(run(each(&["expressions", "structLayouts"])).unwrap(), 1);
+        assert_eq!(run(each(&["expressions", "blocks"])).unwrap(), 0);
+
+        let flags = crate::preset::resolve_checks(named("fast").as_ref()).unwrap();
+        assert!(!flags.contains(naga::valid::ValidationFlags::CONTROL_FLOW_UNIFORMITY));
+        assert!(!flags.contains(naga::valid::ValidationFlags::CONSTANTS));
+        let error = run(each(&["typing"])).err().unwrap();
+        assert!(
+            error
+                .message
+                .starts_with("Unknown validation check 'typing'; expected one of expressions, ")
+        );
+        assert!(run(named("thorough")).is_err());
+    }
+}
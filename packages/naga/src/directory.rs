@@ -0,0 +1,277 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::{
+    BatchResult, JobOptions, JobProgress, Target, batch_result, emit_artifact, failed_job,
+    in_order, js_progress,
+};
+use crate::include::{expand_includes, scan_includes};
+use crate::preset::Preset;
+
+// ============================================================================
+// Directory Build Types
+// ============================================================================
+
+/// Input to `buildDirectory`: a virtual file tree plus what to build from it.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DirectoryConfig {
+    /// Path -> contents. Only `.wgsl` files are compiled; any file may be `#include`d.
+    pub files: BTreeMap<String, String>,
+    #[serde(default = "default_targets")]
+    pub targets: Vec<Target>,
+    /// Emit one artifact per entry point instead of one per file.
+    #[serde(default)]
+    pub split_entry_points: bool,
+}
+
+fn default_targets() -> Vec<Target> {
+    vec![Target::Spirv]
+}
+
+// ============================================================================
+// Directory Build Implementation
+// ============================================================================
+
+/// Build every shader in `config`.
+pub(crate) fn build_directory_with<E>(
+    config: &DirectoryConfig,
+    on_progress: impl FnMut(&JobProgress) -> Result<(), E>,
+) -> Result<BatchResult, E> {
+    build_files(
+        &config.files,
+        &config.targets,
+        config.split_entry_points,
+        on_progress,
+    )
+}
+
+/// Build every `.wgsl` file in `files`, expanding `#include`s against the same
+/// map. Files are visited in path order so results are deterministic. Files
+/// without entry points (shared libraries) produce no artifacts.
+pub(crate) fn build_files<E>(
+    files: &BTreeMap<String, String>,
+    targets: &[Target],
+    split_entry_points: bool,
+    mut on_progress: impl FnMut(&JobProgress) -> Result<(), E>,
+) -> Result<BatchResult, E> {
+    // Parse everything first so the job count is known before reporting progress.
+    let paths: Vec<&String> = files.keys().filter(|p| p.ends_with(".wgsl")).collect();
+    let parsed: Vec<_> = in_order(paths.len(), |i| (paths[i], parse_file(paths[i], files)))
+        .filter(|(_, r)| {
+            r.as_ref()
+                .map_or(true, |f| !f.module.entry_points.is_empty())
+        })
+        .collect();
+
+    let mut planned: Vec<(usize, JobOptions)> = Vec::new();
+    for (file, (_, parsed)) in parsed.iter().enumerate() {
+        let entry_points: Vec<Option<String>> = match parsed {
+            Ok(f) if split_entry_points => f
+                .module
+                .entry_points
+                .iter()
+                .map(|e| Some(e.name.clone()))
+                .collect(),
+            _ => vec![None],
+        };
+        for entry_point in entry_points {
+            for &target in targets {
+                planned.push((file, JobOptions::new(target, entry_point.as_deref())));
+            }
+        }
+    }
+
+    let total = planned.len() as u32;
+    let mut results = Vec::with_capacity(planned.len());
+    let mut entries = Vec::new();
+
+    let emitted = in_order(planned.len(), |index| {
+        let (file, options) = &planned[index];
+        let (path, parsed) = &parsed[*file];
+        match parsed {
+            Ok(f) => {
+                let (mut result, entry) =
+                    emit_artifact(path, &f.source, &f.module, &f.info, options);
+                result.dependencies = f.dependencies.clone();
+                (result, entry)
+            }
+            Err(diagnostic) => {
+                let mut result = failed_job(path, options, diagnostic.clone());
+                result.dependencies = scan_includes(path, files);
+                (result, None)
+            }
+        }
+    });
+    for (index, (result, entry)) in emitted.enumerate() {
+        on_progress(&JobProgress {
+            index: index as u32,
+            total,
+            ok: result.ok,
+            diagnostics: result.diagnostics.clone(),
+        })?;
+        results.push(result);
+        entries.extend(entry);
+    }
+
+    Ok(batch_result(results, entries))
+}
+
+/// A file with its includes expanded, parsed and validated.
+pub(crate) struct ParsedFile {
+    pub source: String,
+    pub dependencies: Vec<String>,
+    pub module: naga::Module,
+    pub info: naga::valid::ModuleInfo,
+}
+
+pub(crate) fn parse_file(
+    path: &str,
+    files: &BTreeMap<String, String>,
+) -> Result<ParsedFile, Diagnostic> {
+    parse_file_for(path, files, None)
+}
+
+/// `parse_file`, validating with `preset`'s capabilities (every capability
+/// without one).
+pub(crate) fn parse_file_for(
+    path: &str,
+    files: &BTreeMap<String, String>,
+    preset: Option<&Preset>,
+) -> Result<ParsedFile, Diagnostic> {
+    let expanded = expand_includes(path, files)?;
+    let module = crate::parse_wgsl(&expanded.source)?;
+    let info = crate::validate_for(&module, preset)?;
+    Ok(ParsedFile {
+        source: expanded.source,
+        dependencies: expanded.dependencies,
+        module,
+        info,
+    })
+}
+
+/// Builds a whole shader directory in one call.
+/// `manifest_json` is `{ files: { [path]: source }, targets?: ("spirv" | "msl")[], splitEntryPoints?: boolean }`.
+/// Returns the same shape as `compileBatch`, with result names set to the file paths.
+#[wasm_bindgen(js_name = buildDirectory)]
+pub fn build_directory(
+    manifest_json: &str,
+    on_progress: Option<js_sys::Function>,
+) -> Result<BatchResult, JsValue> {
+    let config = parse_config(manifest_json).map_err(crate::diagnostics::throw)?;
+    build_directory_with(&config, js_progress(&on_progress))
+}
+
+pub(crate) fn parse_config(manifest_json: &str) -> Result<DirectoryConfig, Diagnostic> {
+    serde_json::from_str(manifest_json)
+        .map_err(|e| Diagnostic::error(format!("Invalid directory manifest: {e}")))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(json: &str) -> BatchResult {
+        let config = parse_config(json).unwrap();
+        build_directory_with(&config, |_| Ok::<_, ()>(())).unwrap()
+    }
+
+    #[test]
+    fn builds_shaders_and_skips_libraries() {
+        let result = build(
+            r#"{
+                "files": {
+                    "b.wgsl": "@compute @workgroup_size(1) fn main() {}",
+                    "a.wgsl": "@vertex fn vs() -> @builtin(position) vec4f { return vec4f(0.0); }",
+                    "lib.wgsl": "fn helper() -> f32 { return 1.0; }",
+                    "README.md": "not a shader"
+                },
+                "targets": ["spirv", "msl"]
+            }"#,
+        );
+        let names: Vec<_> = result
+            .results
+            .iter()
+            .map(|r| (r.name.as_str(), r.target.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a.wgsl", "spirv"),
+                ("a.wgsl", "msl"),
+                ("b.wgsl", "spirv"),
+                ("b.wgsl", "msl")
+            ]
+        );
+        assert_eq!(result.failed, 0);
+        assert_eq!(result.manifest.entries.len(), 4);
+    }
+
+    #[test]
+    fn splits_entry_points_and_reports_failures() {
+        let result = build(
+            r#"{
+                "files": {
+                    "multi.wgsl": "@compute @workgroup_size(1) fn a() {} @compute @workgroup_size(2) fn b() {}",
+                    "broken.wgsl": "fn ("
+                },
+                "splitEntryPoints": true
+            }"#,
+        );
+        let names: Vec<_> = result
+            .results
+            .iter()
+            .map(|r| (r.name.as_str(), r.entry_point.as_deref(), r.ok))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("broken.wgsl", None, false),
+                ("multi.wgsl", Some("a"), true),
+                ("multi.wgsl", Some("b"), true)
+            ]
+        );
+        assert_eq!(result.failed, 1);
+    }
+
+    #[test]
+    fn includes_are_expanded_and_reported() {
+        let result = build(
+            r##"{
+                "files": {
+                    "main.wgsl": "#include \"lib/consts.wgsl\"\n@compute @workgroup_size(SIZE) fn main() {}",
+                    "lib/consts.wgsl": "const SIZE: u32 = 8;"
+                }
+            }"##,
+        );
+        assert_eq!(result.failed, 0);
+        assert_eq!(result.results.len(), 1);
+        assert_eq!(result.results[0].dependencies, vec!["lib/consts.wgsl"]);
+
+        let result = build(
+            r##"{
+                "files": {
+                    "main.wgsl": "#include \"lib/consts.wgsl\"\n#include \"lib/gone.wgsl\"\n@compute @workgroup_size(SIZE) fn main() {}",
+                    "lib/consts.wgsl": "const SIZE: u32 = 8;"
+                }
+            }"##,
+        );
+        assert_eq!(result.failed, 1);
+        assert_eq!(
+            result.results[0].dependencies,
+            vec!["lib/consts.wgsl", "lib/gone.wgsl"]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_manifest() {
+        assert!(parse_config("{\"files\": 3}").is_err());
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::BTreeMap;
+
+use naga::{Binding, BuiltIn, ConservativeDepth, EarlyDepthTest, Module, ShaderStage, TypeInner};
+use serde::{Deserialize, Serialize};
+
+use crate::Diagnostic;
+
+// ============================================================================
+// Entry Point Transforms
+// ============================================================================
+
+/// Remove the named entry points, as if they had never been written. Run
+/// before validation, so the rest of the module is validated and reflected
+/// without them. Every name must exist.
+pub(crate) fn strip_entry_points(module: &mut Module, names: &[String]) -> Result<(), Diagnostic> {
+    if let Some(missing) = names
+        .iter()
+        .find(|name| !module.entry_points.iter().any(|ep| &ep.name == *name))
+    {
+        return Err(Diagnostic::error(format!(
+            "Cannot strip entry point '{}': not found",
+            missing
+        )));
+    }
+    module.entry_points.retain(|ep| !names.contains(&ep.name));
+    Ok(())
+}
+
+/// Set or clear `@invariant` on every `@builtin(position)`: entry point
+/// results, arguments and struct members. Fragment inputs are included so a
+/// struct shared between stages stays consistent. Run before validation.
+pub(crate) fn set_position_invariance(module: &mut Module, invariant: bool) {
+    fn update(binding: &mut Option<Binding>, invariant: bool) {
+        if let Some(Binding::BuiltIn(BuiltIn::Position { invariant: flag })) = binding {
+            *flag = invariant;
+        }
+    }
+
+    for ep in module.entry_points.iter_mut() {
+        for argument in ep.function.arguments.iter_mut() {
+            update(&mut argument.binding, invariant);
+        }
+        if let Some(result) = ep.function.result.as_mut() {
+            update(&mut result.binding, invariant);
+        }
+    }
+
+    // Types are interned; `replace` keeps each handle valid.
+    let structs: Vec<_> = module
+        .types
+        .iter()
+        .filter(|(_, ty)| match &ty.inner {
+            TypeInner::Struct { members, .. } => members.iter().any(|m| {
+                matches!(m.binding, Some(Binding::BuiltIn(BuiltIn::Position { invariant: flag })) if flag != invariant)
+            }),
+            _ => false,
+        })
+        .map(|(handle, ty)| (handle, ty.clone()))
+        .collect();
+    for (handle, mut ty) in structs {
+        if let TypeInner::Struct { members, .. } = &mut ty.inner {
+            for member in members.iter_mut() {
+                update(&mut member.binding, invariant);
+            }
+        }
+        module.types.replace(handle, ty);
+    }
+}
+
+/// Early depth test mode to emit for a fragment entry point, named as in
+/// WGSL's `@early_depth_test`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EarlyDepth {
+    /// Drop any `@early_depth_test` the source declares.
+    None,
+    Force,
+    GreaterEqual,
+    LessEqual,
+    Unchanged,
+}
+
+impl EarlyDepth {
+    fn to_naga(self) -> Option<EarlyDepthTest> {
+        let conservative = match self {
+            EarlyDepth::None => return None,
+            EarlyDepth::Force => return Some(EarlyDepthTest::Force),
+            EarlyDepth::GreaterEqual => ConservativeDepth::GreaterEqual,
+            EarlyDepth::LessEqual => ConservativeDepth::LessEqual,
+            EarlyDepth::Unchanged => ConservativeDepth::Unchanged,
+        };
+        Some(EarlyDepthTest::Allow { conservative })
+    }
+}
+
+/// Override the early depth test of each named fragment entry point,
+/// whatever the source declares.
+pub(crate) fn set_early_depth_tests(
+    module: &mut Module,
+    modes: &BTreeMap<String, EarlyDepth>,
+) -> Result<(), Diagnostic> {
+    for (name, mode) in modes {
+        let ep = module
+            .entry_points
+            .iter_mut()
+            .find(|ep| &ep.name == name)
+            .ok_or_else(|| {
+                Diagnostic::error(format!(
+                    "Cannot set early depth test of '{}': entry point not found",
+                    name
+                ))
+            })?;
+        if ep.stage != ShaderStage::Fragment {
+            return Err(Diagnostic::error(format!(
+                "Cannot set early depth test of '{}': not a fragment entry point",
+                name
+            )));
+        }
+        ep.early_depth_test = mode.to_naga();
+    }
+    Ok(())
+}
+
+/// Compute workgroup bounds a replacement size is checked against.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WorkgroupLimits {
+    pub max_size: [u32; 3],
+    pub max_invocations: u32,
+}
+
+impl WorkgroupLimits {
+    /// WebGPU's default `maxComputeWorkgroupSize*` and
+    /// `maxComputeInvocationsPerWorkgroup`.
+    pub(crate) const WEBGPU_DEFAULT: WorkgroupLimits = WorkgroupLimits {
+        max_size: [256, 256, 64],
+        max_invocations: 256,
+    };
+}
+
+/// Replace the workgroup size of each named compute entry point, including
+/// sizes the source derives from overrides. Sizes have one to three
+/// dimensions; missing ones are 1.
+pub(crate) fn set_workgroup_sizes(
+    module: &mut Module,
+    sizes: &BTreeMap<String, Vec<u32>>,
+    limits: &WorkgroupLimits,
+) -> Result<(), Diagnostic> {
+    for (name, dims) in sizes {
+        let fail = |reason: String| {
+            Diagnostic::error(format!(
+                "Cannot set workgroup size of '{}': {}",
+                name, reason
+            ))
+        };
+        let ep = module
+            .entry_points
+            .iter_mut()
+            .find(|ep| &ep.name == name)
+            .ok_or_else(|| fail("entry point not found".to_string()))?;
+        if ep.stage != ShaderStage::Compute {
+            return Err(fail("not a compute entry point".to_string()));
+        }
+        if dims.is_empty() || dims.len() > 3 {
+            return Err(fail(format!(
+                "expected 1 to 3 dimensions, got {}",
+                dims.len()
+            )));
+        }
+        let mut size = [1; 3];
+        size[..dims.len()].copy_from_slice(dims);
+        for (axis, (&value, &max)) in size.iter().zip(&limits.max_size).enumerate() {
+            if value == 0 || value > max {
+                return Err(fail(format!(
+                    "{} dimension {} is outside 1..={}",
+                    ["x", "y", "z"][axis],
+                    value,
+                    max
+                )));
+            }
+        }
+        let invocations = size.iter().map(|&d| d as u64).product::<u64>();
+        if invocations > limits.max_invocations as u64 {
+            return Err(fail(format!(
+                "{} invocations exceed the limit of {}",
+                invocations, limits.max_invocations
+            )));
+        }
+        ep.workgroup_size = size;
+        ep.workgroup_size_overrides = None;
+    }
+    Ok(())
+}
+
+/// `module` with `entry_point`, or its only entry point if unset, renamed
+/// to `name` in the output, e.g. `main` for tools that expect it; the
+/// backends are then given `name` to select it by. Entry point names play
+/// no part in validation, so `module`'s info stays good for the copy.
+pub(crate) fn rename_for_output(
+    module: &Module,
+    entry_point: Option<&str>,
+    name: &str,
+) -> Result<Module, Diagnostic> {
+    let index = match entry_point.filter(|ep| !ep.is_empty()) {
+        Some(ep) => module
+            .entry_points
+            .iter()
+            .position(|e| e.name == ep)
+            .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", ep)))?,
+        None if module.entry_points.len() == 1 => 0,
+        None => {
+            return Err(Diagnostic::error(format!(
+                "Renaming the entry point to '{}' needs an entry point picked out of {}",
+                name,
+                module.entry_points.len()
+            )));
+        }
+    };
+    let from = &module.entry_points[index].name;
+    let fail = |reason: &str| {
+        Diagnostic::error(format!(
+            "Cannot rename entry point '{}' to '{}': {}",
+            from, name, reason
+        ))
+    };
+    let mut chars = name.chars();
+    if !chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        || !chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+    {
+        return Err(fail("not an identifier"));
+    }
+    if module
+        .entry_points
+        .iter()
+        .enumerate()
+        .any(|(i, ep)| i != index && ep.name == name)
+    {
+        return Err(fail("another entry point has that name"));
+    }
+    let mut module = module.clone();
+    module.entry_points[index].name = name.to_string();
+    Ok(module)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var tex: texture_2d<f32>;
+        @group(0) @binding(1) var samp: sampler;
+
+        @fragment
+        fn main() -> @location(0) vec4<f32> {
+            return vec4<f32>(1.0);
+        }
+
+        @compute @workgroup_size(1)
+        fn debug_view() {
+            // Implicit derivatives are invalid in compute shaders.
+            _ = textureSample(tex, samp, vec2<f32>(0.5));
+        }
+    "#;
+
+    #[test]
+    fn stripped_entry_points_are_not_validated() {
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        assert!(crate::validate_module(&module).is_err());
+
+        strip_entry_points(&mut module, &["debug_view".to_string()]).unwrap();
+        let info = crate::validate_module(&module).unwrap();
+        let reflection = crate::reflect_module(&module, &info);
+        assert_eq!(reflection.entry_points.len(), 1);
+        assert_eq!(reflection.entry_points[0].name, "main");
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        let err = strip_entry_points(&mut module, &["nope".to_string()]).unwrap_err();
+        assert!(err.message.contains("'nope'"));
+        assert_eq!(module.entry_points.len(), 2);
+    }
+
+    #[test]
+    fn early_depth_tests_are_overridden_per_entry_point() {
+        let source = r#"
+            @fragment @early_depth_test(force)
+            fn forced() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }
+
+            @fragment
+            fn plain() -> @location(0) vec4<f32> { return vec4<f32>(0.0); }
+
+            @compute @workgroup_size(1)
+            fn main() {}
+        "#;
+        let mut module = crate::parse_wgsl(source).unwrap();
+        let modes = BTreeMap::from([
+            ("forced".to_string(), EarlyDepth::None),
+            ("plain".to_string(), EarlyDepth::LessEqual),
+        ]);
+        set_early_depth_tests(&mut module, &modes).unwrap();
+        assert_eq!(module.entry_points[0].early_depth_test, None);
+        assert_eq!(
+            module.entry_points[1].early_depth_test,
+            Some(EarlyDepthTest::Allow {
+                conservative: ConservativeDepth::LessEqual
+            })
+        );
+
+        let info = crate::validate_module(&module).unwrap();
+        let msl = crate::write_msl(&module, &info, None).unwrap();
+        assert!(!msl.contains("early_fragment_tests"));
+
+        for bad in ["main", "nope"] {
+            let modes = BTreeMap::from([(bad.to_string(), EarlyDepth::Force)]);
+            assert!(set_early_depth_tests(&mut module, &modes).is_err());
+        }
+    }
+
+    #[test]
+    fn workgroup_sizes_are_replaced_within_limits() {
+        let source = r#"
+            override BLOCK: u32 = 64u;
+            @compute @workgroup_size(BLOCK) fn tiled() {}
+            @compute @workgroup_size(8, 8) fn image() {}
+            @fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }
+        "#;
+        let limits = WorkgroupLimits::WEBGPU_DEFAULT;
+        let mut module = crate::parse_wgsl(source).unwrap();
+        let sizes = BTreeMap::from([
+            ("tiled".to_string(), vec![128]),
+            ("image".to_string(), vec![16, 4, 2]),
+        ]);
+        set_workgroup_sizes(&mut module, &sizes, &limits).unwrap();
+        assert_eq!(module.entry_points[0].workgroup_size, [128, 1, 1]);
+        assert_eq!(module.entry_points[0].workgroup_size_overrides, None);
+        assert_eq!(module.entry_points[1].workgroup_size, [16, 4, 2]);
+        crate::validate_module(&module).unwrap();
+
+        for (name, dims) in [
+            ("fs", vec![1]),
+            ("image", vec![]),
+            ("image", vec![0]),
+            ("image", vec![1, 1, 65]),
+            ("image", vec![32, 16]),
+        ] {
+            let sizes = BTreeMap::from([(name.to_string(), dims)]);
+            assert!(set_workgroup_sizes(&mut module, &sizes, &limits).is_err());
+        }
+    }
+
+    #[test]
+    fn renamed_entry_points_are_picked_by_their_wgsl_name() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+            @compute @workgroup_size(1) fn clear() { data[0] = 0u; }
+            @compute @workgroup_size(1) fn fill() { data[0] = 1u; }
+        "#;
+        let spirv = crate::spv::SpirvOptions {
+            entry_point_name: Some("main".to_string()),
+            ..Default::default()
+        };
+        let bytes = crate::compile_spirv_with(source, Some("fill"), None, &spirv).unwrap();
+        let words = crate::spv::words_from_bytes(&bytes).unwrap();
+        let text = crate::spv::disassemble(&words).unwrap();
+        assert!(text.contains("OpEntryPoint GLCompute %"));
+        assert!(text.contains("\"main\""));
+        // The function keeps its WGSL name for debuggers.
+        assert!(text.contains("\"fill\""));
+
+        let msl = crate::msl::MslOptions {
+            entry_point_name: Some("fill_kernel".to_string()),
+            source_map: true,
+            ..Default::default()
+        };
+        let output = crate::msl::compile_msl_mapped(source, Some("fill"), &msl).unwrap();
+        assert!(output.source.contains("kernel void fill_kernel("));
+        assert_eq!(output.bindings[0].entry_point, "fill");
+        let mapped = output.source_map.unwrap();
+        assert_eq!(mapped.mappings[0].function.as_deref(), Some("fill"));
+        let hlsl = crate::hlsl::HlslOptions {
+            entry_point_name: Some("CSMain".to_string()),
+            ..Default::default()
+        };
+        let hlsl = crate::hlsl::compile_hlsl(source, Some("clear"), &hlsl).unwrap();
+        assert!(hlsl.contains("void CSMain("));
+
+        let module = crate::parse_wgsl(source).unwrap();
+        for (entry_point, name) in [
+            (None, "main"),
+            (Some("clear"), "fill"),
+            (Some("clear"), "2d"),
+            (Some("nope"), "main"),
+        ] {
+            assert!(rename_for_output(&module, entry_point, name).is_err());
+        }
+    }
+}
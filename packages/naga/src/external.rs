@@ -0,0 +1,578 @@
+use std::collections::{BTreeMap, HashMap};
+
+use naga::{
+    Expression, Handle, ImageClass, ImageDimension, ImageQuery, ScalarKind, Statement, Type,
+    TypeInner,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::Target;
+use crate::diagnostics::throw;
+use crate::preset;
+use crate::rewrite;
+
+// ============================================================================
+// External Texture Types
+// ============================================================================
+
+/// Options object accepted by `lowerExternalTextures`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExternalTextureOptions {
+    /// Device preset whose capabilities the lowered module is validated against.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Bind group for the implicit bindings; each texture's own by default.
+    #[serde(default)]
+    pub group: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ExternalTextureOutput {
+    /// Set for binary targets (SPIR-V).
+    #[wasm_bindgen(readonly)]
+    pub bytes: Option<Vec<u8>>,
+    /// Set for textual targets (MSL, WGSL).
+    #[wasm_bindgen(readonly)]
+    pub text: Option<String>,
+    /// Every binding an external texture now occupies, the one it was
+    /// declared with (`plane0`) included.
+    #[wasm_bindgen(readonly)]
+    pub bindings: Vec<ExternalTextureBinding>,
+}
+
+#[wasm_bindgen]
+impl ExternalTextureOutput {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ExternalTextureBinding {
+    /// The `texture_external` variable this binding belongs to.
+    #[wasm_bindgen(readonly)]
+    pub texture: String,
+    /// `"plane0"`, `"plane1"` or `"plane2"` for a `texture_2d<f32>` plane,
+    /// `"params"` for the uniform buffer describing the planes, laid out like
+    /// wgpu's `ExternalTextureParams`.
+    #[wasm_bindgen(readonly)]
+    pub role: String,
+    /// Name of the variable in the lowered module.
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+}
+
+#[wasm_bindgen]
+impl ExternalTextureBinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// External Texture Lowering
+// ============================================================================
+//
+// naga cannot write `texture_external` to SPIR-V at all, and the MSL and HLSL
+// writers expect bind targets for planes the WebGPU layout never declared. So
+// each external texture is lowered in the IR the way wgpu does it for Metal
+// and D3D12: the variable becomes plane 0, a `texture_2d<f32>`, next to two
+// more planes and a params uniform on fresh bindings, and every
+// `textureSampleBaseClampToEdge`, `textureLoad` and `textureDimensions` of it
+// becomes a call to a generated WGSL helper doing the YUV to RGB and color
+// space conversion. The helpers are parsed together with the source, before
+// it, so they precede their callers in the function arena.
+
+/// Shared by every external texture.
+const COMMON: &str = r#"
+struct MetisExternalTextureTransferFn {
+    a: f32,
+    b: f32,
+    g: f32,
+    k: f32,
+}
+
+struct MetisExternalTextureParams {
+    yuv_conversion_matrix: mat4x4<f32>,
+    gamut_conversion_matrix: mat3x3<f32>,
+    src_tf: MetisExternalTextureTransferFn,
+    dst_tf: MetisExternalTextureTransferFn,
+    sample_transform: mat3x2<f32>,
+    load_transform: mat3x2<f32>,
+    size: vec2<u32>,
+    num_planes: u32,
+}
+
+fn metis_external_to_rgb(y: f32, uv: vec2<f32>, params: MetisExternalTextureParams) -> vec4<f32> {
+    let src_gamma = (params.yuv_conversion_matrix * vec4<f32>(y, uv, 1.0)).rgb;
+    let src_tf = params.src_tf;
+    let src_linear = select(
+        pow((src_gamma + src_tf.a - 1.0) / src_tf.a, vec3<f32>(src_tf.g)),
+        src_gamma / src_tf.k,
+        src_gamma < vec3<f32>(src_tf.k * src_tf.b),
+    );
+    let dst_linear = params.gamut_conversion_matrix * src_linear;
+    let dst_tf = params.dst_tf;
+    let dst_gamma = select(
+        dst_tf.a * pow(dst_linear, vec3<f32>(1.0 / dst_tf.g)) - (dst_tf.a - 1.0),
+        dst_tf.k * dst_linear,
+        dst_linear < vec3<f32>(dst_tf.b),
+    );
+    return vec4<f32>(dst_gamma, 1.0);
+}
+"#;
+
+/// Per external texture, with `TEX` replaced by its name and `GROUP`,
+/// `PLANE1`, `PLANE2` and `PARAMS` by its implicit bindings.
+const PER_TEXTURE: &str = r#"
+@group(GROUP) @binding(PLANE1) var TEX_plane1: texture_2d<f32>;
+@group(GROUP) @binding(PLANE2) var TEX_plane2: texture_2d<f32>;
+@group(GROUP) @binding(PARAMS) var<uniform> TEX_params: MetisExternalTextureParams;
+
+fn TEX_dimensions(plane0: texture_2d<f32>) -> vec2<u32> {
+    let size = TEX_params.size;
+    return select(textureDimensions(plane0), size, any(size != vec2<u32>(0u)));
+}
+
+fn TEX_load(plane0: texture_2d<f32>, coords: vec2<u32>) -> vec4<f32> {
+    let params = TEX_params;
+    let plane0_size = textureDimensions(plane0);
+    let size = select(plane0_size, params.size, any(params.size != vec2<u32>(0u)));
+    let clamped = vec2<f32>(min(coords, size - 1u));
+    let plane0_coords = vec2<u32>(round(params.load_transform * vec3<f32>(clamped, 1.0)));
+    if params.num_planes == 1u {
+        return textureLoad(plane0, plane0_coords, 0);
+    }
+    let scale = vec2<f32>(plane0_coords) / vec2<f32>(plane0_size);
+    let plane1_coords = vec2<u32>(floor(scale * vec2<f32>(textureDimensions(TEX_plane1))));
+    let y = textureLoad(plane0, plane0_coords, 0).x;
+    var uv: vec2<f32>;
+    if params.num_planes == 2u {
+        uv = textureLoad(TEX_plane1, plane1_coords, 0).xy;
+    } else {
+        let plane2_coords = vec2<u32>(floor(scale * vec2<f32>(textureDimensions(TEX_plane2))));
+        uv = vec2<f32>(
+            textureLoad(TEX_plane1, plane1_coords, 0).x,
+            textureLoad(TEX_plane2, plane2_coords, 0).x,
+        );
+    }
+    return metis_external_to_rgb(y, uv, params);
+}
+
+fn TEX_load_signed(plane0: texture_2d<f32>, coords: vec2<i32>) -> vec4<f32> {
+    return TEX_load(plane0, vec2<u32>(max(coords, vec2<i32>(0))));
+}
+
+fn TEX_sample(plane0: texture_2d<f32>, samp: sampler, coords: vec2<f32>) -> vec4<f32> {
+    let params = TEX_params;
+    let transformed = params.sample_transform * vec3<f32>(coords, 1.0);
+    let corner0 = params.sample_transform * vec3<f32>(0.0, 0.0, 1.0);
+    let corner1 = params.sample_transform * vec3<f32>(1.0, 1.0, 1.0);
+    let lo = min(corner0, corner1);
+    let hi = max(corner0, corner1);
+    let plane0_texel = vec2<f32>(0.5) / vec2<f32>(textureDimensions(plane0));
+    let plane0_coords = clamp(transformed, lo + plane0_texel, hi - plane0_texel);
+    if params.num_planes == 1u {
+        return textureSampleLevel(plane0, samp, plane0_coords, 0.0);
+    }
+    let plane1_texel = vec2<f32>(0.5) / vec2<f32>(textureDimensions(TEX_plane1));
+    let plane1_coords = clamp(transformed, lo + plane1_texel, hi - plane1_texel);
+    let y = textureSampleLevel(plane0, samp, plane0_coords, 0.0).x;
+    var uv: vec2<f32>;
+    if params.num_planes == 2u {
+        uv = textureSampleLevel(TEX_plane1, samp, plane1_coords, 0.0).xy;
+    } else {
+        let plane2_texel = vec2<f32>(0.5) / vec2<f32>(textureDimensions(TEX_plane2));
+        let plane2_coords = clamp(transformed, lo + plane2_texel, hi - plane2_texel);
+        uv = vec2<f32>(
+            textureSampleLevel(TEX_plane1, samp, plane1_coords, 0.0).x,
+            textureSampleLevel(TEX_plane2, samp, plane2_coords, 0.0).x,
+        );
+    }
+    return metis_external_to_rgb(y, uv, params);
+}
+"#;
+
+fn is_external(module: &naga::Module, ty: Handle<Type>) -> bool {
+    matches!(
+        module.types[ty].inner,
+        TypeInner::Image {
+            class: ImageClass::External,
+            ..
+        }
+    )
+}
+
+/// The external textures declared in `module`, with their implicit bindings.
+fn plan_bindings(
+    module: &naga::Module,
+    group: Option<u32>,
+) -> Result<Vec<ExternalTextureBinding>, Diagnostic> {
+    let mut next: BTreeMap<u32, u32> = BTreeMap::new();
+    for (_, var) in module.global_variables.iter() {
+        if let Some(b) = var.binding {
+            let slot = next.entry(b.group).or_default();
+            *slot = (*slot).max(b.binding + 1);
+        }
+    }
+    let mut bindings = Vec::new();
+    for (_, var) in module.global_variables.iter() {
+        if !is_external(module, var.ty) {
+            continue;
+        }
+        let (Some(name), Some(binding)) = (var.name.clone(), var.binding) else {
+            return Err(Diagnostic::error(
+                "texture_external variables need a name and a binding",
+            ));
+        };
+        let mut push = |role: &str, name: String, group: u32, binding: u32| {
+            bindings.push(ExternalTextureBinding {
+                texture: var.name.clone().unwrap_or_default(),
+                role: role.to_string(),
+                name,
+                group,
+                binding,
+            });
+        };
+        push("plane0", name.clone(), binding.group, binding.binding);
+        let group = group.unwrap_or(binding.group);
+        let slot = next.entry(group).or_default();
+        for role in ["plane1", "plane2", "params"] {
+            push(role, format!("{}_{}", name, role), group, *slot);
+            *slot += 1;
+        }
+    }
+    Ok(bindings)
+}
+
+/// The generated helpers for `bindings`, to parse ahead of the source.
+fn helpers(bindings: &[ExternalTextureBinding]) -> String {
+    let mut out = COMMON.to_string();
+    for plane0 in bindings.iter().filter(|b| b.role == "plane0") {
+        let slot = |role: &str| {
+            bindings
+                .iter()
+                .find(|b| b.texture == plane0.texture && b.role == role)
+                .expect("every texture has every role")
+        };
+        out += &PER_TEXTURE
+            .replace("GROUP", &slot("params").group.to_string())
+            .replace("PLANE1", &slot("plane1").binding.to_string())
+            .replace("PLANE2", &slot("plane2").binding.to_string())
+            .replace("PARAMS", &slot("params").binding.to_string())
+            .replace("TEX", &plane0.texture);
+    }
+    out
+}
+
+/// Turn each use of an external texture in `function` into a call to its
+/// helper, resolving coordinate types through `info`.
+fn lower_uses(
+    module: &naga::Module,
+    function: &mut naga::Function,
+    info: &naga::valid::FunctionInfo,
+) -> Result<(), Diagnostic> {
+    if let Some(arg) = function
+        .arguments
+        .iter()
+        .find(|arg| is_external(module, arg.ty))
+    {
+        return Err(Diagnostic::error(format!(
+            "texture_external argument '{}' is not supported; use the global directly",
+            arg.name.as_deref().unwrap_or("_")
+        )));
+    }
+    let helper = |texture: &str, suffix: &str| {
+        let name = format!("{}_{}", texture, suffix);
+        module
+            .functions
+            .fetch_if(|f| f.name.as_deref() == Some(name.as_str()))
+            .expect("helpers are declared for every external texture")
+    };
+    let textures: HashMap<_, _> = function
+        .expressions
+        .iter()
+        .filter_map(|(h, expr)| match *expr {
+            Expression::GlobalVariable(g) if is_external(module, module.global_variables[g].ty) => {
+                Some((h, module.global_variables[g].name.clone()?))
+            }
+            _ => None,
+        })
+        .collect();
+    let external = |h: Handle<Expression>| textures.get(&h).cloned();
+
+    let mut calls = HashMap::new();
+    for (h, expr) in function.expressions.iter() {
+        let call = match *expr {
+            Expression::ImageSample {
+                image,
+                sampler,
+                coordinate,
+                ..
+            } => external(image).map(|t| (helper(&t, "sample"), vec![image, sampler, coordinate])),
+            Expression::ImageLoad {
+                image, coordinate, ..
+            } => external(image).map(|t| {
+                let signed = matches!(
+                    *info[coordinate].ty.inner_with(&module.types),
+                    TypeInner::Vector { scalar, .. } if scalar.kind == ScalarKind::Sint
+                );
+                let suffix = if signed { "load_signed" } else { "load" };
+                (helper(&t, suffix), vec![image, coordinate])
+            }),
+            Expression::ImageQuery {
+                image,
+                query: ImageQuery::Size { .. },
+            } => external(image).map(|t| (helper(&t, "dimensions"), vec![image])),
+            _ => None,
+        };
+        if let Some(call) = call {
+            calls.insert(h, call);
+        }
+    }
+    if let Some(arg) = find_external_argument(function, &external) {
+        return Err(Diagnostic::error(format!(
+            "Passing texture_external '{}' to a function is not supported",
+            arg
+        )));
+    }
+    for (&h, &(callee, _)) in &calls {
+        function.expressions[h] = Expression::CallResult(callee);
+    }
+    rewrite::insert_calls(&mut function.body, &calls);
+    Ok(())
+}
+
+fn find_external_argument(
+    function: &mut naga::Function,
+    external: &impl Fn(Handle<Expression>) -> Option<String>,
+) -> Option<String> {
+    let mut found = None;
+    rewrite::for_each_statement(&mut function.body, &mut |statement| {
+        if let Statement::Call { arguments, .. } = statement {
+            found = found
+                .take()
+                .or_else(|| arguments.iter().find_map(|&a| external(a)));
+        }
+    });
+    found
+}
+
+/// Lower every `texture_external` in `wgsl`, returning the unvalidated module
+/// and the bindings the textures now occupy.
+pub(crate) fn lower_external_textures(
+    wgsl: &str,
+    options: &ExternalTextureOptions,
+) -> Result<(naga::Module, Vec<ExternalTextureBinding>), Diagnostic> {
+    let module = crate::parse_wgsl(wgsl)?;
+    crate::validate_module(&module)?;
+    let bindings = plan_bindings(&module, options.group)?;
+    if bindings.is_empty() {
+        return Ok((module, bindings));
+    }
+
+    let mut module = crate::parse_wgsl(&format!("{}\n{}", helpers(&bindings), wgsl))?;
+    let info = crate::validate_module(&module)?;
+    let handles: Vec<_> = module.functions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let mut function = std::mem::take(&mut module.functions[h]);
+        let result = lower_uses(&module, &mut function, &info[h]);
+        module.functions[h] = function;
+        result?;
+    }
+    for index in 0..module.entry_points.len() {
+        let mut function = std::mem::take(&mut module.entry_points[index].function);
+        let result = lower_uses(&module, &mut function, info.get_entry_point(index));
+        module.entry_points[index].function = function;
+        result?;
+    }
+
+    let plane = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Image {
+                dim: ImageDimension::D2,
+                arrayed: false,
+                class: ImageClass::Sampled {
+                    kind: ScalarKind::Float,
+                    multi: false,
+                },
+            },
+        },
+        naga::Span::UNDEFINED,
+    );
+    for (_, var) in module.global_variables.iter_mut() {
+        if matches!(
+            module.types[var.ty].inner,
+            TypeInner::Image {
+                class: ImageClass::External,
+                ..
+            }
+        ) {
+            var.ty = plane;
+        }
+    }
+    naga::compact::compact(&mut module, naga::compact::KeepUnused::Yes);
+    Ok((module, bindings))
+}
+
+fn compile_lowered(
+    wgsl: &str,
+    target: &str,
+    entry_point: Option<&str>,
+    options: &ExternalTextureOptions,
+) -> Result<ExternalTextureOutput, Diagnostic> {
+    let target = match target {
+        "wgsl" => None,
+        _ => Some(
+            Target::parse(target)
+                .ok_or_else(|| Diagnostic::error(format!("Unknown target '{}'", target)))?,
+        ),
+    };
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let (module, bindings) = lower_external_textures(wgsl, options)?;
+    let info = crate::validate_for(&module, preset)?;
+    let mut output = ExternalTextureOutput {
+        bytes: None,
+        text: None,
+        bindings,
+    };
+    match target {
+        Some(Target::Spirv) => {
+            output.bytes = Some(crate::write_spirv_with(
+                &module,
+                &info,
+                entry_point,
+                preset,
+            )?)
+        }
+        Some(Target::Msl) => {
+            output.text = Some(crate::write_msl_with(&module, &info, entry_point, preset)?)
+        }
+        None => {
+            let flags = naga::back::wgsl::WriterFlags::empty();
+            output.text = Some(
+                naga::back::wgsl::write_string(&module, &info, flags)
+                    .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?,
+            )
+        }
+    }
+    Ok(output)
+}
+
+/// Compile WGSL using `texture_external` for a backend without external
+/// textures. Each one is lowered to three `texture_2d<f32>` planes and a
+/// params uniform, like wgpu does for Metal and D3D12, with the conversion to
+/// RGB done in generated helpers. The texture keeps its binding as plane 0;
+/// the rest follow the highest binding of its group, or of `options.group`,
+/// and are all listed in the result's `bindings`. `target` is `"spirv"`,
+/// `"msl"` or `"wgsl"` (the lowered source, for any other backend).
+/// `options` is `{ preset?, group? }`.
+#[wasm_bindgen(js_name = lowerExternalTextures)]
+pub fn lower_external_textures_wgsl(
+    wgsl: &str,
+    target: &str,
+    entry_point: Option<String>,
+    options: JsValue,
+) -> Result<ExternalTextureOutput, JsValue> {
+    let options: Option<ExternalTextureOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid external texture options: {e}")))?;
+    compile_lowered(
+        wgsl,
+        target,
+        entry_point.as_deref(),
+        &options.unwrap_or_default(),
+    )
+    .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO: &str = r#"
+        @group(0) @binding(0) var video: texture_external;
+        @group(0) @binding(1) var linear: sampler;
+        @group(1) @binding(0) var<storage, read_write> out: array<vec4<f32>>;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            let size = vec2<f32>(textureDimensions(video));
+            return textureSampleBaseClampToEdge(video, linear, uv) * size.x;
+        }
+
+        @compute @workgroup_size(8, 8)
+        fn copy(@builtin(global_invocation_id) id: vec3<u32>) {
+            out[id.x] = textureLoad(video, id.xy) + textureLoad(video, vec2<i32>(id.xy));
+        }
+    "#;
+
+    #[test]
+    fn external_textures_lower_to_planes_and_params() {
+        let options = ExternalTextureOptions::default();
+        let output = compile_lowered(VIDEO, "wgsl", None, &options).unwrap();
+        let slots: Vec<_> = output
+            .bindings
+            .iter()
+            .map(|b| (b.role.as_str(), b.name.as_str(), b.group, b.binding))
+            .collect();
+        assert_eq!(
+            slots,
+            [
+                ("plane0", "video", 0, 0),
+                ("plane1", "video_plane1", 0, 2),
+                ("plane2", "video_plane2", 0, 3),
+                ("params", "video_params", 0, 4),
+            ]
+        );
+        let text = output.text.unwrap();
+        assert!(!text.contains("texture_external"));
+        assert!(text.contains("var video: texture_2d<f32>;"));
+        assert!(text.contains("video_sample(video, linear, uv)"));
+        assert!(text.contains("video_load_signed(video"));
+
+        let spirv = compile_lowered(VIDEO, "spirv", Some("copy"), &options).unwrap();
+        assert!(spirv.bytes.is_some_and(|b| !b.is_empty()));
+        let msl = compile_lowered(VIDEO, "msl", Some("fs"), &options).unwrap();
+        assert!(msl.text.unwrap().contains("video_plane1"));
+    }
+
+    #[test]
+    fn group_option_and_unsupported_uses() {
+        let options = ExternalTextureOptions {
+            group: Some(3),
+            ..Default::default()
+        };
+        let (_, bindings) = lower_external_textures(VIDEO, &options).unwrap();
+        assert!(bindings[1..].iter().all(|b| b.group == 3));
+        assert_eq!(bindings[1].binding, 0);
+
+        let plain = "@fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }";
+        let (_, bindings) = lower_external_textures(plain, &options).unwrap();
+        assert!(bindings.is_empty());
+
+        let passed = r#"
+            @group(0) @binding(0) var video: texture_external;
+            fn size(t: texture_external) -> vec2<u32> { return textureDimensions(t); }
+            @compute @workgroup_size(1) fn main() { _ = size(video); }
+        "#;
+        assert!(lower_external_textures(passed, &options).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use std::convert::Infallible;
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use crate::Diagnostic;
+use crate::batch::{BatchJob, run_batch};
+use crate::request::{self, CompileRequest, RequestError};
+
+// ============================================================================
+// C ABI Types
+// ============================================================================
+
+/// Bytes the library allocated; the caller owns them and hands them back to
+/// `metis_buffer_free`.
+#[repr(C)]
+pub struct MetisBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl MetisBuffer {
+    fn from_vec(bytes: Vec<u8>) -> MetisBuffer {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        MetisBuffer { data, len }
+    }
+}
+
+/// Success; the output buffer holds the result.
+pub const METIS_OK: i32 = 0;
+/// The shader failed, or the library hit an internal error; the output
+/// buffer holds a diagnostic as JSON.
+pub const METIS_ERROR: i32 = 1;
+/// A pointer was null, or text was not UTF-8 or JSON; the output buffer, if
+/// there is one, holds a diagnostic as JSON.
+pub const METIS_INVALID_ARGUMENT: i32 = 2;
+
+/// Bumped whenever a signature or the meaning of a status changes.
+pub const METIS_ABI_VERSION: u32 = 1;
+
+// ============================================================================
+// C ABI Implementation
+// ============================================================================
+//
+// For hosts without JS: an engine linking the library natively, or running
+// the wasm module in its own runtime and calling exports directly. Text in
+// is UTF-8 by pointer and length, since a wasm guest cannot see host
+// strings; results come back in a `MetisBuffer` the library allocated, so
+// wasm hosts first get memory for their input from `metis_alloc`. Compile
+// requests (see `request.rs`) and reflection are the same JSON the JS API
+// speaks, which keeps this surface down to a few functions that need not
+// change as options grow. `include/metis.h` declares them. A panic must not
+// unwind into the host, so each call runs under `catch_unwind` and reports
+// one as `METIS_ERROR`.
+
+fn invalid(message: impl Into<String>) -> (i32, Diagnostic) {
+    (METIS_INVALID_ARGUMENT, Diagnostic::error(message))
+}
+
+/// # Safety
+/// `data` must be null or point to `len` readable bytes.
+unsafe fn text<'a>(data: *const u8, len: usize, what: &str) -> Result<&'a str, (i32, Diagnostic)> {
+    if data.is_null() {
+        return match len {
+            0 => Ok(""),
+            _ => Err(invalid(format!("{what} is null"))),
+        };
+    }
+    // SAFETY: the caller guarantees `len` readable bytes at `data`.
+    let bytes = unsafe { slice::from_raw_parts(data, len) };
+    std::str::from_utf8(bytes).map_err(|e| invalid(format!("{what} is not UTF-8: {e}")))
+}
+
+/// Run `call`, turning a panic into an error diagnostic.
+fn guarded(
+    call: impl FnOnce() -> Result<Vec<u8>, (i32, Diagnostic)>,
+) -> Result<Vec<u8>, (i32, Diagnostic)> {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown cause");
+        Err((
+            METIS_ERROR,
+            Diagnostic::error(format!("Internal error: {message}")),
+        ))
+    })
+}
+
+/// Store `result` in `out` and return its status.
+///
+/// # Safety
+/// `out` must be null or valid for writes.
+unsafe fn finish(out: *mut MetisBuffer, result: Result<Vec<u8>, (i32, Diagnostic)>) -> i32 {
+    let (status, bytes) = match result {
+        Ok(bytes) => (METIS_OK, bytes),
+        Err((status, diagnostic)) => (
+            status,
+            serde_json::to_vec(&diagnostic).expect("diagnostics are always serializable"),
+        ),
+    };
+    if out.is_null() {
+        return METIS_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees `out` is valid for writes.
+    unsafe { out.write(MetisBuffer::from_vec(bytes)) };
+    status
+}
+
+fn compile(wgsl: &str, request: &str) -> Result<Vec<u8>, (i32, Diagnostic)> {
+    let request: CompileRequest = serde_json::from_str(request)
+        .map_err(|e| invalid(format!("Invalid compile request: {e}")))?;
+    request::compile(wgsl, request).map_err(|error| match error {
+        RequestError::Invalid(diagnostic) => (METIS_INVALID_ARGUMENT, diagnostic),
+        RequestError::Failed(diagnostic) => (METIS_ERROR, diagnostic),
+    })
+}
+
+/// Copy each job's `options` object over the job, as `compileBatch` does.
+fn merge_nested_options(mut jobs: serde_json::Value) -> serde_json::Value {
+    for job in jobs.as_array_mut().into_iter().flatten() {
+        if let Some(job) = job.as_object_mut()
+            && let Some(serde_json::Value::Object(options)) = job.remove("options")
+        {
+            job.extend(options);
+        }
+    }
+    jobs
+}
+
+fn compile_batch(jobs: &str) -> Result<Vec<u8>, (i32, Diagnostic)> {
+    let jobs: serde_json::Value =
+        serde_json::from_str(jobs).map_err(|e| invalid(format!("Invalid batch jobs: {e}")))?;
+    let jobs: Vec<BatchJob> = serde_json::from_value(merge_nested_options(jobs))
+        .map_err(|e| invalid(format!("Invalid batch jobs: {e}")))?;
+    let batch =
+        run_batch(&jobs, |_| Ok::<_, Infallible>(())).unwrap_or_else(|never| match never {});
+    Ok(serde_json::to_vec(&batch).expect("batch results are always serializable"))
+}
+
+/// The ABI version the library implements, `METIS_ABI_VERSION`.
+#[unsafe(no_mangle)]
+pub extern "C" fn metis_abi_version() -> u32 {
+    METIS_ABI_VERSION
+}
+
+/// `len` zeroed bytes, for a wasm host to copy input into. Free them with
+/// `metis_buffer_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn metis_alloc(len: usize) -> MetisBuffer {
+    MetisBuffer::from_vec(vec![0; len])
+}
+
+/// Frees a buffer from `metis_alloc` or an output. Null buffers are ignored.
+///
+/// # Safety
+/// `buffer` must come from this library, unchanged, and not be freed twice.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metis_buffer_free(buffer: MetisBuffer) {
+    if !buffer.data.is_null() {
+        // SAFETY: the buffer is a boxed slice this library leaked.
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)) });
+    }
+}
+
+/// Parses and validates WGSL. On success `out` is empty.
+///
+/// # Safety
+/// `wgsl` must be null or point to `wgsl_len` readable bytes, and `out`
+/// must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metis_validate(
+    wgsl: *const u8,
+    wgsl_len: usize,
+    out: *mut MetisBuffer,
+) -> i32 {
+    let result = guarded(|| {
+        // SAFETY: forwarded from the caller.
+        unsafe { text(wgsl, wgsl_len, "WGSL") }.and_then(|wgsl| {
+            crate::parse_and_validate(wgsl)
+                .map(|_| Vec::new())
+                .map_err(|d| (METIS_ERROR, d))
+        })
+    });
+    // SAFETY: forwarded from the caller.
+    unsafe { finish(out, result) }
+}
+
+/// Reflects WGSL into `out` as the JSON `reflectWgsl` returns.
+///
+/// # Safety
+/// As for `metis_validate`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metis_reflect(
+    wgsl: *const u8,
+    wgsl_len: usize,
+    out: *mut MetisBuffer,
+) -> i32 {
+    let result = guarded(|| {
+        // SAFETY: forwarded from the caller.
+        unsafe { text(wgsl, wgsl_len, "WGSL") }.and_then(|wgsl| {
+            let reflection = crate::reflect(wgsl).map_err(|d| (METIS_ERROR, d))?;
+            Ok(serde_json::to_vec(&reflection).expect("reflection is always serializable"))
+        })
+    });
+    // SAFETY: forwarded from the caller.
+    unsafe { finish(out, result) }
+}
+
+/// Compiles WGSL per a JSON `request`, `{ target: "spirv" | "msl" | "hlsl" |
+/// "glsl", entryPoint?, preset?, stage?, options? }`, where `options` is
+/// the target's options object from the JS API and `preset` is SPIR-V's
+/// (the others take it in `options`). `out` gets SPIR-V as binary, the rest
+/// as UTF-8 text.
+///
+/// # Safety
+/// `wgsl` and `request` must each be null or point to as many readable
+/// bytes as their length says, and `out` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metis_compile(
+    wgsl: *const u8,
+    wgsl_len: usize,
+    request: *const u8,
+    request_len: usize,
+    out: *mut MetisBuffer,
+) -> i32 {
+    let result = guarded(|| {
+        // SAFETY: forwarded from the caller.
+        unsafe { text(wgsl, wgsl_len, "WGSL") }.and_then(|wgsl| {
+            // SAFETY: forwarded from the caller.
+            let request = unsafe { text(request, request_len, "Compile request") }?;
+            compile(wgsl, request)
+        })
+    });
+    // SAFETY: forwarded from the caller.
+    unsafe { finish(out, result) }
+}
+
+/// Compiles a JSON array of `compileBatch` jobs into `out` as the JSON of
+/// its result. Failed jobs do not fail the call; they are in the result's
+/// diagnostics and `report`. Built with the `parallel` feature, the jobs
+/// compile across threads, with results still in job order.
+///
+/// # Safety
+/// `jobs` must be null or point to `jobs_len` readable bytes, and `out`
+/// must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn metis_compile_batch(
+    jobs: *const u8,
+    jobs_len: usize,
+    out: *mut MetisBuffer,
+) -> i32 {
+    let result = guarded(|| {
+        // SAFETY: forwarded from the caller.
+        unsafe { text(jobs, jobs_len, "Batch jobs") }.and_then(compile_batch)
+    });
+    // SAFETY: forwarded from the caller.
+    unsafe { finish(out, result) }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+        @compute @workgroup_size(1) fn main() { data[0] = 1u; }
+    "#;
+
+    /// Run `call` with an output buffer, returning the status and contents.
+    fn call(call: impl FnOnce(*mut MetisBuffer) -> i32) -> (i32, Vec<u8>) {
+        let mut out = MetisBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        };
+        let status = call(&mut out);
+        // SAFETY: `out` was just filled in by the library.
+        let bytes = unsafe { slice::from_raw_parts(out.data, out.len) }.to_vec();
+        // SAFETY: as above, and freed once.
+        unsafe { metis_buffer_free(out) };
+        (status, bytes)
+    }
+
+    fn request(wgsl: &str, request: &str) -> (i32, Vec<u8>) {
+        call(|out| unsafe {
+            metis_compile(
+                wgsl.as_ptr(),
+                wgsl.len(),
+                request.as_ptr(),
+                request.len(),
+                out,
+            )
+        })
+    }
+
+    #[test]
+    fn compiles_validates_and_reflects_through_buffers() {
+        let (status, spirv) = request(SHADER, r#"{ "target": "spirv", "entryPoint": "main" }"#);
+        assert_eq!(status, METIS_OK);
+        assert_eq!(spirv[..4], 0x0723_0203u32.to_le_bytes());
+        let (status, msl) = request(
+            SHADER,
+            r#"{ "target": "msl", "options": { "entryPointName": "fill" } }"#,
+        );
+        assert_eq!(status, METIS_OK);
+        assert!(
+            String::from_utf8(msl)
+                .unwrap()
+                .contains("kernel void fill(")
+        );
+
+        let (status, empty) =
+            call(|out| unsafe { metis_validate(SHADER.as_ptr(), SHADER.len(), out) });
+        assert_eq!((status, empty.len()), (METIS_OK, 0));
+        let (status, json) =
+            call(|out| unsafe { metis_reflect(SHADER.as_ptr(), SHADER.len(), out) });
+        assert_eq!(status, METIS_OK);
+        let reflection: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(reflection["entryPoints"][0]["name"], "main");
+
+        let input = metis_alloc(16);
+        assert_eq!(input.len, 16);
+        unsafe { metis_buffer_free(input) };
+        assert_eq!(metis_abi_version(), METIS_ABI_VERSION);
+    }
+
+    #[test]
+    fn failures_come_back_as_json_diagnostics() {
+        let broken = "fn main( {";
+        let (status, json) =
+            call(|out| unsafe { metis_validate(broken.as_ptr(), broken.len(), out) });
+        assert_eq!(status, METIS_ERROR);
+        let diagnostic: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(diagnostic["severity"], "error");
+
+        for bad in [
+            r#"{ "target": "dxil" }"#,
+            "not json",
+            r#"{ "target": "glsl" }"#,
+        ] {
+            assert_eq!(request(SHADER, bad).0, METIS_INVALID_ARGUMENT);
+        }
+        let (status, _) = call(|out| unsafe { metis_validate(std::ptr::null(), 4, out) });
+        assert_eq!(status, METIS_INVALID_ARGUMENT);
+        let status = unsafe { metis_validate(SHADER.as_ptr(), SHADER.len(), std::ptr::null_mut()) };
+        assert_eq!(status, METIS_INVALID_ARGUMENT);
+    }
+
+    #[test]
+    fn panics_come_back_as_errors() {
+        let (status, diagnostic) = guarded(|| panic!("bad {}", "state")).unwrap_err();
+        assert_eq!(status, METIS_ERROR);
+        assert_eq!(diagnostic.message, "Internal error: bad state");
+    }
+
+    #[test]
+    fn batches_come_back_in_job_order() {
+        let jobs = serde_json::json!([
+            { "name": "a", "source": SHADER, "target": "spirv" },
+            { "name": "b", "source": "fn main( {", "target": "msl" },
+            { "name": "c", "source": SHADER, "options": { "target": "msl" } },
+        ])
+        .to_string();
+        let (status, json) =
+            call(|out| unsafe { metis_compile_batch(jobs.as_ptr(), jobs.len(), out) });
+        assert_eq!(status, METIS_OK);
+        let batch: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let names: Vec<_> = (0..3)
+            .map(|i| batch["results"][i]["name"].clone())
+            .collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(batch["results"][2]["target"], "msl");
+        assert_eq!(batch["failed"], 1);
+        assert_eq!(batch["report"]["groups"][0]["jobs"], serde_json::json!([1]));
+
+        let (status, _) = call(|out| unsafe { metis_compile_batch(b"{}".as_ptr(), 2, out) });
+        assert_eq!(status, METIS_INVALID_ARGUMENT);
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::{DetailedDiagnostic, detailed_diagnostics};
+
+// ============================================================================
+// Format Types
+// ============================================================================
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct FormatOptions {
+    /// Spaces per level; ignored with `useTabs`.
+    pub indent_width: usize,
+    pub use_tabs: bool,
+    /// Annotate every `let`/`var` with its type.
+    pub explicit_types: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            use_tabs: false,
+            explicit_types: false,
+        }
+    }
+}
+
+/// `{ ok, value, diagnostics }`, like the `try*` results, with spans.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct FormatResult {
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    /// The formatted source, unset if the input is not valid WGSL.
+    #[wasm_bindgen(readonly)]
+    pub value: Option<String>,
+    /// Whether `value` differs from the input, for `--check` style hooks.
+    #[wasm_bindgen(readonly)]
+    pub changed: bool,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<DetailedDiagnostic>,
+}
+
+#[wasm_bindgen]
+impl FormatResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Format Implementation
+// ============================================================================
+//
+// Formatting is a round trip through naga: the module is parsed, validated
+// and written back with the WGSL backend, so spacing, parentheses and
+// declaration order are naga's and do not depend on how the input was laid
+// out. Declarations come out dependencies first, then in source order.
+// Comments do not survive the trip. The backend indents by four spaces,
+// which is rewritten to the configured indent afterwards. Naga names the
+// temporaries it introduces, so the writer is rerun on its own output until
+// it settles, making formatting idempotent.
+
+/// The backend's indent unit.
+const BACKEND_INDENT: usize = 4;
+
+/// Passes after which output that still changes is returned as is.
+const MAX_PASSES: usize = 4;
+
+fn reindent(wgsl: &str, options: &FormatOptions) -> String {
+    let unit = if options.use_tabs {
+        "\t".to_string()
+    } else {
+        " ".repeat(options.indent_width)
+    };
+    if unit == " ".repeat(BACKEND_INDENT) {
+        return wgsl.to_string();
+    }
+    let mut out = String::with_capacity(wgsl.len());
+    for line in wgsl.lines() {
+        let body = line.trim_start_matches(' ');
+        let spaces = line.len() - body.len();
+        out.push_str(&unit.repeat(spaces / BACKEND_INDENT));
+        out.push_str(&" ".repeat(spaces % BACKEND_INDENT));
+        out.push_str(body);
+        out.push('\n');
+    }
+    out
+}
+
+fn write(wgsl: &str, flags: naga::back::wgsl::WriterFlags) -> Option<String> {
+    let (module, info) = crate::parse_and_validate(wgsl).ok()?;
+    naga::back::wgsl::write_string(&module, &info, flags).ok()
+}
+
+pub(crate) fn format(wgsl: &str, options: &FormatOptions) -> FormatResult {
+    let diagnostics = detailed_diagnostics(wgsl);
+    if !diagnostics.is_empty() {
+        return FormatResult {
+            ok: false,
+            value: None,
+            changed: false,
+            diagnostics,
+        };
+    }
+    let mut flags = naga::back::wgsl::WriterFlags::empty();
+    flags.set(
+        naga::back::wgsl::WriterFlags::EXPLICIT_TYPES,
+        options.explicit_types,
+    );
+    let mut current = write(wgsl, flags);
+    for _ in 1..MAX_PASSES {
+        let Some(text) = &current else { break };
+        match write(text, flags) {
+            Some(next) if next != *text => current = Some(next),
+            _ => break,
+        }
+    }
+    match current {
+        Some(text) => {
+            let value = reindent(&text, options);
+            FormatResult {
+                ok: true,
+                changed: value != wgsl,
+                value: Some(value),
+                diagnostics: Vec::new(),
+            }
+        }
+        None => FormatResult {
+            ok: false,
+            value: None,
+            changed: false,
+            diagnostics: vec![DetailedDiagnostic {
+                severity: "error".to_string(),
+                message: crate::catalog::localize(
+                    "Naga could not write the module back as WGSL".to_string(),
+                ),
+                span: None,
+                labels: Vec::new(),
+                notes: Vec::new(),
+                explanation: None,
+                fix: None,
+                fix_span: None,
+                fix_id: None,
+            }],
+        },
+    }
+}
+
+/// Reformats WGSL for consistent style, e.g. in pre-commit hooks. `config` is
+/// `{ indentWidth?: number, useTabs?: boolean, explicitTypes?: boolean }`.
+/// Never throws on bad shaders: if the input does not parse or validate,
+/// `ok` is false and `diagnostics` carry spans, as `validateWgslDetailed`.
+/// Comments are not preserved.
+#[wasm_bindgen(js_name = formatWgsl)]
+pub fn format_wgsl(wgsl: &str, config: JsValue) -> Result<FormatResult, JsValue> {
+    let options: Option<FormatOptions> = serde_wasm_bindgen::from_value(config)
+        .map_err(|e| JsValue::from_str(&format!("Invalid format options: {e}")))?;
+    Ok(format(wgsl, &options.unwrap_or_default()))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = r#"
+fn   helper(x:f32)->f32{ if x>0.0 { return x*2.0; } return  -x; }
+@fragment fn main( @location(0) v:f32 )->@location(0) vec4<f32>{
+let a=helper(v)+helper(v*0.5);
+return vec4<f32>(a,a,a,1.0);}
+"#;
+
+    #[test]
+    fn formatting_is_stable_and_configurable() {
+        let once = format(MESSY, &FormatOptions::default());
+        assert!(once.ok && once.changed);
+        let text = once.value.unwrap();
+        assert!(text.contains("fn helper(x: f32) -> f32 {\n    if (x > 0f) {"));
+        let twice = format(&text, &FormatOptions::default());
+        assert_eq!(twice.value.as_deref(), Some(text.as_str()));
+        assert!(!twice.changed);
+
+        let tabs = FormatOptions {
+            use_tabs: true,
+            ..Default::default()
+        };
+        let tabbed = format(MESSY, &tabs).value.unwrap();
+        assert!(tabbed.contains("{\n\tif (x > 0f) {\n\t\treturn"));
+        let narrow = FormatOptions {
+            indent_width: 2,
+            ..Default::default()
+        };
+        assert!(
+            format(MESSY, &narrow)
+                .value
+                .unwrap()
+                .contains("\n  if (x > 0f) {\n    return")
+        );
+    }
+
+    #[test]
+    fn invalid_input_reports_spans() {
+        let result = format("fn f() {\n    let x = ;\n}\n", &FormatOptions::default());
+        assert!(!result.ok && result.value.is_none());
+        let span = result.diagnostics[0].span.clone().unwrap();
+        assert_eq!((span.line, span.column), (2, 13));
+    }
+}
@@ -0,0 +1,567 @@
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::catalog::localize;
+use crate::diagnostics::{DetailedDiagnostic, SourceSpan, throw};
+
+// ============================================================================
+// Friendly Diagnostics
+// ============================================================================
+//
+// naga's messages are written for people who know its IR: "Function [0]
+// 'f' is invalid" with the real problem two notes down, and types printed
+// as Rust values. With `friendly`, the errors beginners hit most (number
+// types that do not match, variables in the wrong address space or written
+// through a read-only one, and missing `@location`, `@binding` or
+// `@workgroup_size` attributes) get an `explanation` in plain language and,
+// where the source shows what to change, a `fix`: the offending
+// declaration or expression rewritten. naga's own message, notes and spans
+// stay as they were. Fixes are suggestions built from the source text, not
+// checked by compiling them. Explanations go through the message catalog.
+//
+// Each fix also has the span it replaces and an id, the rule and the byte
+// offset it starts at, so `applyFix` can apply it by id alone: it diagnoses
+// the source again, with GLSL-isms on so their rewrites apply the same way,
+// and splices in the fix with that id. An id from an older version of the
+// source fails rather than patching the wrong place.
+
+fn span_text<'a>(source: &'a str, span: &SourceSpan) -> Option<&'a str> {
+    source.get(span.start as usize..span.end as usize)
+}
+
+/// The text labelled with `prefix`, e.g. `naga::ir::Expression`.
+fn labelled<'a>(source: &'a str, diagnostic: &DetailedDiagnostic, prefix: &str) -> Option<&'a str> {
+    let label = diagnostic
+        .labels
+        .iter()
+        .rev()
+        .find(|label| label.message.starts_with(prefix))?;
+    span_text(source, &label.span)
+}
+
+/// The text between `before` and `after` in `text`, e.g. a quoted name.
+fn between<'a>(text: &'a str, before: &str, after: &str) -> Option<&'a str> {
+    let start = text.find(before)? + before.len();
+    let end = text[start..].find(after)? + start;
+    Some(&text[start..end])
+}
+
+/// WGSL for a type naga printed with `Debug`, for scalars and vectors.
+fn wgsl_type(debug: &str) -> Option<String> {
+    let kind = between(debug, "kind: ", ",")?;
+    let width = between(debug, "width: ", " ")?;
+    let scalar = match (kind, width) {
+        ("Float", "2") => "f16",
+        ("Float", "4") => "f32",
+        ("Float", "8") => "f64",
+        ("Sint", "4") => "i32",
+        ("Uint", "4") => "u32",
+        ("Bool", _) => "bool",
+        _ => return None,
+    };
+    if !debug.starts_with("Vector") {
+        return Some(scalar.to_string());
+    }
+    let size = match between(debug, "size: ", ",")? {
+        "Bi" => 2,
+        "Tri" => 3,
+        _ => 4,
+    };
+    Some(format!("vec{size}<{scalar}>"))
+}
+
+/// Byte offsets of `symbol` in `text` outside brackets, as a whole operator.
+fn top_level(text: &str, symbol: &str) -> Vec<usize> {
+    let bytes = text.as_bytes();
+    let operator = |i: usize| bytes.get(i).is_some_and(|b| b"<>=!&|".contains(b));
+    let mut depth = 0i32;
+    let mut found = Vec::new();
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ if depth == 0
+                && text[i..].starts_with(symbol)
+                && (i == 0 || !operator(i - 1))
+                && !operator(i + symbol.len()) =>
+            {
+                found.push(i);
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn binary_symbol(operation: &str) -> Option<&'static str> {
+    Some(match operation {
+        "Add" => "+",
+        "Subtract" => "-",
+        "Multiply" => "*",
+        "Divide" => "/",
+        "Modulo" => "%",
+        "Equal" => "==",
+        "NotEqual" => "!=",
+        "Less" => "<",
+        "LessEqual" => "<=",
+        "Greater" => ">",
+        "GreaterEqual" => ">=",
+        _ => return None,
+    })
+}
+
+/// The `let`, `var` or `const` statement declaring the name at `at`.
+fn declaration(source: &str, at: usize) -> Option<&str> {
+    let line_start = source[..at].rfind('\n').map_or(0, |i| i + 1);
+    let start = ["let ", "var ", "var<", "const "]
+        .iter()
+        .filter_map(|keyword| source[line_start..at].rfind(keyword))
+        .max()?
+        + line_start;
+    let end = source[at..].find(';')? + at;
+    Some(&source[start..end])
+}
+
+/// The declaration of the function `name`, up to its body.
+fn function_header<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let start = source.find(&format!("fn {name}("))?;
+    let end = source[start..].find('{')? + start;
+    Some(source[start..end].trim_end())
+}
+
+/// The source of the global variable `name`, attributes excluded.
+fn global_declaration<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let tokens = crate::lexer::tokenize(source);
+    let at = crate::lexer::module_declarations(&tokens)
+        .into_iter()
+        .find(|&(declared, at)| declared == name && at > 0 && tokens[at - 1].is_punct('>'))
+        .map(|(_, at)| at)?;
+    let start = tokens[..at].iter().rposition(|t| t.is_ident("var"))?;
+    let start = tokens[start].start;
+    let end = source[start..].find(';')? + start;
+    Some(&source[start..end])
+}
+
+/// A suggested edit: `text` in place of `start..end` of the source.
+struct Fix {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// A fix putting `text` in place of `slice` of `source`, and of the `;`
+/// after it if `text` ends with one.
+fn replacing(source: &str, slice: &str, text: String) -> Fix {
+    let start = slice.as_ptr() as usize - source.as_ptr() as usize;
+    let mut end = start + slice.len();
+    if text.ends_with(';') && source[end..].starts_with(';') {
+        end += 1;
+    }
+    Fix { start, end, text }
+}
+
+type Friendly = (String, Option<Fix>);
+
+fn type_mismatch(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let message = &diagnostic.message;
+    let name = between(message, "the type of `", "`")?;
+    let want = between(message, "is expected to be `", "`")?;
+    let got = between(message, "but got `", "`")?;
+    let explanation = format!(
+        "`{name}` is declared as `{want}`, but the value it is given is a `{got}`. WGSL never \
+         converts between number types on its own, so convert the value with `{want}(...)`."
+    );
+    let fix = diagnostic.span.as_ref().and_then(|span| {
+        let statement = declaration(source, span.start as usize)?;
+        let (head, value) = statement.split_once('=')?;
+        let text = format!("{} = {want}({});", head.trim_end(), value.trim());
+        Some(replacing(source, statement, text))
+    });
+    Some((explanation, fix))
+}
+
+fn operand_mismatch(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let note = diagnostic
+        .notes
+        .iter()
+        .find(|note| note.contains("can't work with"))?;
+    let operation = between(note, "Operation ", " ")?;
+    let left = wgsl_type(between(note, "(of type ", ") and")?)?;
+    let right = wgsl_type(note.rsplit_once("(of type ")?.1)?;
+    let explanation = format!(
+        "The two sides of this operation have different types, `{left}` and `{right}`. WGSL \
+         only combines values of the same type, so convert one side to match the other."
+    );
+    let fix = (|| {
+        let symbol = binary_symbol(operation)?;
+        let text = labelled(source, diagnostic, "naga::ir::Expression")?;
+        let [at] = top_level(text, symbol)[..] else {
+            return None;
+        };
+        let (lhs, rhs) = (text[..at].trim(), text[at + symbol.len()..].trim());
+        Some(replacing(
+            source,
+            text,
+            format!("{lhs} {symbol} {left}({rhs})"),
+        ))
+    })();
+    Some((explanation, fix))
+}
+
+fn return_mismatch(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    diagnostic
+        .notes
+        .iter()
+        .find(|note| note.contains("does not match the declared return type"))?;
+    let name = between(&diagnostic.message, "'", "'")?;
+    let declared = function_header(source, name)
+        .and_then(|header| header.rsplit_once("->"))
+        .map(|(_, ty)| ty.trim());
+    let explanation = match declared {
+        Some(ty) => format!(
+            "`{name}` promises to return a `{ty}`, but this `return` gives back a value of \
+             another type."
+        ),
+        None => format!(
+            "`{name}` returns a value of a different type than it declares, or a value when \
+             it declares none."
+        ),
+    };
+    let fix = (|| {
+        let value = labelled(source, diagnostic, "naga::ir::Expression")?;
+        let end = value.as_ptr() as usize - source.as_ptr() as usize + value.len();
+        let statement = &source[source[..end].rfind("return")?..end];
+        Some(replacing(
+            source,
+            statement,
+            format!("return {}({value});", declared?),
+        ))
+    })();
+    Some((explanation, fix))
+}
+
+fn argument_mismatch(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let note = diagnostic
+        .notes
+        .iter()
+        .find(|note| note.starts_with("Argument ") && note.contains("doesn't match the type"))?;
+    let index: usize = between(note, "Argument ", " ")?.parse().ok()?;
+    let call = labelled(source, diagnostic, "invalid function call")?;
+    let callee = call.split('(').next()?.trim();
+    let explanation = format!(
+        "Argument {} of this call to `{callee}` has a different type than the parameter it is \
+         passed to. WGSL never converts between number types on its own.",
+        index + 1
+    );
+    let fix = (|| {
+        let header = function_header(source, callee)?;
+        let params = between(header, "(", ")")?;
+        let (_, ty) = params.split(',').nth(index)?.split_once(':')?;
+        let value = labelled(source, diagnostic, "naga::ir::Expression")?;
+        let ty = ty.trim();
+        let text = call.replacen(value, &format!("{ty}({value})"), 1);
+        Some(replacing(source, call, text))
+    })();
+    Some((explanation, fix))
+}
+
+fn address_space(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let notes = diagnostic.notes.join("\n");
+    let declaration = labelled(source, diagnostic, "naga::ir::GlobalVariable")
+        .map(|text| text.trim_end_matches(';'));
+    let with_private = || {
+        let declaration = declaration?;
+        let (_, rest) = declaration.split_once(['>', ' '])?;
+        let text = format!("var<private> {};", rest.trim_start());
+        Some(replacing(source, declaration, text))
+    };
+    if notes.contains("Type isn't compatible with address space Handle")
+        && declaration.is_some_and(|d| d.starts_with("var "))
+    {
+        let explanation = "A `var` outside any function needs an address space: \
+            `var<private>` for a value each invocation keeps for itself, or `var<uniform>` or \
+            `var<storage>` with `@group` and `@binding` for a buffer the app provides.";
+        return Some((explanation.to_string(), with_private()));
+    }
+    if notes.contains("Usage isn't compatible with address space Function") {
+        let explanation = "`var<function>` is only for variables inside a function. Outside \
+            any function, use `var<private>` for a value each invocation keeps for itself.";
+        return Some((explanation.to_string(), with_private()));
+    }
+    if notes.contains("is not a multiple of the required alignment 16") {
+        let explanation = "Array elements in a uniform buffer must start every 16 bytes, and \
+            these are smaller. Use 16-byte elements such as `vec4<f32>`, or put the array in a \
+            `var<storage>` buffer, which has no such rule.";
+        return Some((explanation.to_string(), None));
+    }
+    None
+}
+
+fn read_only_store(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let target = labelled(
+        source,
+        diagnostic,
+        "writing to this location is not permitted",
+    )?;
+    let name: String = target
+        .trim_start_matches(['*', '(', '&'])
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    let explanation = format!(
+        "`{name}` cannot be written here. Uniform buffers are read-only, and so is \
+         `var<storage>` unless it is declared `var<storage, read_write>`; `let` values and \
+         function parameters cannot be assigned either."
+    );
+    let fix = global_declaration(source, &name).and_then(|declaration| {
+        let (_, rest) = declaration.split_once('>')?;
+        Some(replacing(
+            source,
+            declaration,
+            format!("var<storage, read_write>{rest};"),
+        ))
+    });
+    Some((explanation, fix))
+}
+
+fn missing_attribute(source: &str, diagnostic: &DetailedDiagnostic) -> Option<Friendly> {
+    let notes = diagnostic.notes.join("\n");
+    let message = &diagnostic.message;
+    if notes.contains("Binding decoration is missing") {
+        let explanation = "Buffers, textures and samplers need `@group(n) @binding(m)`, the \
+            slot the app binds them to.";
+        let fix = labelled(source, diagnostic, "naga::ir::GlobalVariable").map(|declaration| {
+            let declaration = declaration.trim();
+            let text = format!("@group(0) @binding(0) {declaration}");
+            replacing(source, declaration, text)
+        });
+        return Some((explanation.to_string(), fix));
+    }
+    if message.contains("needs a 'binding' attribute") || message.contains("needs a 'group'") {
+        let missing = if message.contains("'binding'") {
+            "@binding(0)"
+        } else {
+            "@group(0)"
+        };
+        let explanation = format!(
+            "Resource variables need both `@group` and `@binding`; this one is missing \
+             `{missing}`."
+        );
+        let fix = diagnostic.span.as_ref().and_then(|span| {
+            let start = span.start as usize;
+            let text = &source[start..source[start..].find(';')? + start];
+            let var = text.find("var")?;
+            let fixed = format!("{}{missing} {};", &text[..var], &text[var..]);
+            Some(replacing(source, text, fixed))
+        });
+        return Some((explanation, fix));
+    }
+    if message.starts_with("workgroup size is missing") {
+        let explanation = "Compute entry points must say how many invocations run together, \
+            with `@workgroup_size`. 64 is a common choice.";
+        // naga labels `compute`, without its `@`.
+        let fix = diagnostic.span.as_ref().and_then(|span| {
+            let attribute = source.get(span.start as usize - 1..span.end as usize)?;
+            let text = format!("{attribute} @workgroup_size(64)");
+            (attribute == "@compute").then(|| replacing(source, attribute, text))
+        });
+        return Some((explanation.to_string(), fix));
+    }
+    if let Some(member) = between(&notes, "Struct member ", " is missing a binding") {
+        let explanation = format!(
+            "Member {member} of a struct passed between shader stages has neither \
+             `@location(n)` nor `@builtin(...)`. Every such member needs one; a vertex \
+             shader's position is `@builtin(position)`."
+        );
+        return Some((explanation, None));
+    }
+    if notes.contains("must all have bindings") {
+        let name = between(message, "Entry point ", " at ")?;
+        let stage = message.rsplit_once(" at ")?.1.split(' ').next()?;
+        if let Some(argument) = between(&notes, "Argument ", " varying error") {
+            let explanation = format!(
+                "Parameter {} of entry point `{name}` has neither `@location(n)` nor \
+                 `@builtin(...)`, so nothing says where its value comes from.",
+                argument.parse::<usize>().ok()? + 1
+            );
+            return Some((explanation, None));
+        }
+        let attribute = match stage {
+            "Vertex" => "@builtin(position)",
+            _ => "@location(0)",
+        };
+        let explanation = format!(
+            "The value entry point `{name}` returns has no attribute saying where it goes: a \
+             vertex shader returns `@builtin(position)`, a fragment shader `@location(0)` for \
+             the first color target."
+        );
+        let fix = function_header(source, name).and_then(|header| {
+            let (head, ty) = header.split_once("->")?;
+            let text = format!("{}-> {attribute} {}", head, ty.trim());
+            Some(replacing(source, header, text))
+        });
+        return Some((explanation, fix));
+    }
+    None
+}
+
+type Rule = fn(&str, &DetailedDiagnostic) -> Option<Friendly>;
+
+/// Each rule with the name its fix ids start with.
+const RULES: &[(&str, Rule)] = &[
+    ("convert-value", type_mismatch),
+    ("convert-operand", operand_mismatch),
+    ("convert-return", return_mismatch),
+    ("convert-argument", argument_mismatch),
+    ("address-space", address_space),
+    ("read-write", read_only_store),
+    ("add-attribute", missing_attribute),
+];
+
+/// Add an `explanation` and `fix` to every diagnostic a rule recognizes.
+pub(crate) fn befriend(source: &str, diagnostics: &mut [DetailedDiagnostic]) {
+    for diagnostic in diagnostics {
+        let found = RULES
+            .iter()
+            .find_map(|(name, rule)| Some((name, rule(source, diagnostic)?)));
+        let Some((name, (explanation, fix))) = found else {
+            continue;
+        };
+        diagnostic.explanation = Some(localize(explanation));
+        if let Some(fix) = fix {
+            let span = naga::Span::new(fix.start as u32, fix.end as u32);
+            diagnostic.fix_id = Some(format!("{name}@{}", fix.start));
+            diagnostic.fix_span = SourceSpan::new(source, span);
+            diagnostic.fix = Some(fix.text);
+        }
+    }
+}
+
+/// `source` with the fix `id` applied.
+pub(crate) fn fixed_source(source: &str, id: &str) -> Result<String, Diagnostic> {
+    let mut diagnostics = crate::glsl_compat::lenient_diagnostics(source);
+    befriend(source, &mut diagnostics);
+    diagnostics
+        .into_iter()
+        .find(|diagnostic| diagnostic.fix_id.as_deref() == Some(id))
+        .and_then(|diagnostic| {
+            let span = diagnostic.fix_span?;
+            let (start, end) = (span.start as usize, span.end as usize);
+            Some(format!(
+                "{}{}{}",
+                &source[..start],
+                diagnostic.fix?,
+                &source[end..]
+            ))
+        })
+        .ok_or_else(|| Diagnostic::error(format!("Fix '{id}' does not apply to this source")))
+}
+
+/// Applies the suggested fix with `fixId`, a diagnostic's `fixId` from
+/// `validateWgslDetailed` with `{ friendly: true }` or `{ glslIsms: true }`,
+/// and returns the patched source. Throws if `source` has no such fix, as
+/// when it changed since it was diagnosed.
+#[wasm_bindgen(js_name = applyFix)]
+pub fn apply_fix(source: &str, fix_id: &str) -> Result<String, JsValue> {
+    fixed_source(source, fix_id).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn friendly(source: &str) -> (String, Option<String>) {
+        let mut diagnostics = crate::diagnostics::detailed_diagnostics(source);
+        befriend(source, &mut diagnostics);
+        let diagnostic = diagnostics.pop().unwrap();
+        (diagnostic.explanation.unwrap(), diagnostic.fix)
+    }
+
+    #[test]
+    fn type_mismatches_suggest_conversions() {
+        let (explanation, fix) = friendly("fn f() {\n    let x: f32 = 1u;\n}");
+        assert!(explanation.starts_with("`x` is declared as `f32`, but the value"));
+        assert_eq!(fix.as_deref(), Some("let x: f32 = f32(1u);"));
+
+        let (explanation, fix) = friendly("fn f() { let a = 1.0; let b = 2; let c = a + b; }");
+        assert!(explanation.contains("`f32` and `i32`"));
+        assert_eq!(fix.as_deref(), Some("a + f32(b)"));
+
+        let (_, fix) = friendly("fn f() -> f32 { return 1u; }");
+        assert_eq!(fix.as_deref(), Some("return f32(1u);"));
+
+        let (_, fix) = friendly("fn g(x: f32) {} fn f() { g(1u); }");
+        assert_eq!(fix.as_deref(), Some("g(f32(1u))"));
+    }
+
+    #[test]
+    fn address_spaces_and_attributes_are_explained() {
+        let (explanation, fix) = friendly("var x: f32;");
+        assert!(explanation.starts_with("A `var` outside any function"));
+        assert_eq!(fix.as_deref(), Some("var<private> x: f32;"));
+
+        let source = "@group(0) @binding(0) var<storage> b: array<f32>;\n\
+                      fn f() { b[0] = 1.0; }";
+        let (_, fix) = friendly(source);
+        assert_eq!(
+            fix.as_deref(),
+            Some("var<storage, read_write> b: array<f32>;")
+        );
+
+        let (_, fix) = friendly("var<storage> b: array<f32>;");
+        assert_eq!(
+            fix.as_deref(),
+            Some("@group(0) @binding(0) var<storage> b: array<f32>;")
+        );
+        let (_, fix) = friendly("@group(0) var<storage> b: array<f32>;");
+        assert_eq!(
+            fix.as_deref(),
+            Some("@group(0) @binding(0) var<storage> b: array<f32>;")
+        );
+
+        let (_, fix) = friendly("@fragment fn fs() -> vec4<f32> { return vec4<f32>(1.0); }");
+        assert_eq!(fix.as_deref(), Some("fn fs() -> @location(0) vec4<f32>"));
+        let (_, fix) = friendly("@compute fn cs() {}");
+        assert_eq!(fix.as_deref(), Some("@compute @workgroup_size(64)"));
+    }
+
+    #[test]
+    fn fixes_apply_by_id() {
+        let source = "fn f() {\n    let x: f32 = 1u;\n}";
+        let mut diagnostics = crate::diagnostics::detailed_diagnostics(source);
+        befriend(source, &mut diagnostics);
+        let id = diagnostics[0].fix_id.clone().unwrap();
+        assert_eq!(id, "convert-value@13");
+        let fixed = fixed_source(source, &id).unwrap();
+        assert_eq!(fixed, "fn f() {\n    let x: f32 = f32(1u);\n}");
+        assert!(crate::diagnostics::detailed_diagnostics(&fixed).is_empty());
+        let error = fixed_source(&fixed, &id).err().unwrap();
+        assert_eq!(
+            error.message,
+            "Fix 'convert-value@13' does not apply to this source"
+        );
+
+        // Applied fixes leave shaders that validate.
+        let sources = [
+            "var x: f32;",
+            "@group(0) @binding(0) var<storage> b: array<f32>;\nfn f() { b[0] = 1.0; }",
+            "fn f() -> f32 { return 1u; }",
+            "@compute fn cs() {}",
+            "@fragment fn fs() -> vec4<f32> { return vec4<f32>(1.0); }",
+        ];
+        for source in sources {
+            let mut diagnostics = crate::diagnostics::detailed_diagnostics(source);
+            befriend(source, &mut diagnostics);
+            let fixed = fixed_source(source, diagnostics[0].fix_id.as_ref().unwrap()).unwrap();
+            let left = crate::diagnostics::detailed_diagnostics(&fixed);
+            assert!(left.is_empty(), "{fixed}: {left:?}");
+        }
+
+        let fixed = fixed_source("let n = float(1);", "glsl-ism@8").unwrap();
+        assert_eq!(fixed, "let n = f32(1);");
+    }
+}
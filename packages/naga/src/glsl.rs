@@ -0,0 +1,272 @@
+use naga::ShaderStage;
+use naga::back::glsl::{self, Version};
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::preset;
+use crate::provenance::embed_fingerprint;
+
+// ============================================================================
+// GLSL Backend
+// ============================================================================
+
+/// Options object accepted by `wgslToGlsl`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GlslOptions {
+    /// `"330"` (and later) for desktop core GLSL, `"300es"` (or `"300 es"`)
+    /// through `"320es"` for GLSL ES. Defaults to ES 300 for WebGL2.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Device preset whose capabilities and bounds checks apply.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Lead the output with a source hash and entry point comment.
+    #[serde(default)]
+    pub fingerprint: bool,
+}
+
+pub(crate) fn parse_version(name: &str) -> Result<Version, Diagnostic> {
+    let name = name.trim();
+    let (number, es) = match name.strip_suffix("es") {
+        Some(number) => (number.trim_end(), true),
+        None => (name, false),
+    };
+    let version = number
+        .parse::<u16>()
+        .map_err(|_| Diagnostic::error(format!("Unknown GLSL version '{}'", name)))?;
+    Ok(if es {
+        Version::new_gles(version)
+    } else {
+        Version::Desktop(version)
+    })
+}
+
+pub(crate) fn parse_stage(name: &str) -> Result<ShaderStage, Diagnostic> {
+    [
+        ShaderStage::Vertex,
+        ShaderStage::Fragment,
+        ShaderStage::Compute,
+    ]
+    .into_iter()
+    .find(|stage| crate::stage_name(*stage) == name)
+    .ok_or_else(|| Diagnostic::error(format!("Unknown shader stage '{}'", name)))
+}
+
+/// GLSL holds one entry point per shader. Without a name, the module's only
+/// entry point of `stage` is used.
+pub(crate) fn select_entry_point(
+    module: &naga::Module,
+    entry_point: Option<&str>,
+    stage: ShaderStage,
+) -> Result<String, Diagnostic> {
+    match entry_point.filter(|name| !name.is_empty()) {
+        Some(name) => {
+            let entry = crate::find_entry_point(module, name)?;
+            if entry.stage != stage {
+                return Err(Diagnostic::error(format!(
+                    "Entry point '{}' is a {} shader, not {}",
+                    name,
+                    crate::stage_name(entry.stage),
+                    crate::stage_name(stage)
+                )));
+            }
+            Ok(name.to_string())
+        }
+        None => {
+            let mut candidates = module.entry_points.iter().filter(|ep| ep.stage == stage);
+            match (candidates.next(), candidates.next()) {
+                (Some(entry), None) => Ok(entry.name.clone()),
+                (None, _) => Err(Diagnostic::error(format!(
+                    "No {} entry point found",
+                    crate::stage_name(stage)
+                ))),
+                (Some(_), Some(_)) => Err(Diagnostic::error(format!(
+                    "Several {} entry points found; name one",
+                    crate::stage_name(stage)
+                ))),
+            }
+        }
+    }
+}
+
+/// Run naga's GLSL writer over one entry point of an already validated
+/// module, returning naga's own error so callers can pick it apart.
+pub(crate) fn write_with(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    options: &glsl::Options,
+    entry_point: String,
+    stage: ShaderStage,
+    preset: Option<&preset::Preset>,
+) -> Result<(String, glsl::ReflectionInfo), glsl::Error> {
+    let pipeline_options = glsl::PipelineOptions {
+        shader_stage: stage,
+        entry_point,
+        multiview: None,
+    };
+    let policies = preset.map(|p| p.bounds_checks).unwrap_or_default();
+
+    let mut source = String::new();
+    let reflection = glsl::Writer::new(
+        &mut source,
+        module,
+        info,
+        options,
+        &pipeline_options,
+        policies,
+    )
+    .and_then(|mut writer| writer.write())?;
+    Ok((source, reflection))
+}
+
+/// Emit GLSL source for one entry point of an already validated module,
+/// named as `select_entry_point` resolved it.
+pub(crate) fn write_glsl(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: String,
+    stage: ShaderStage,
+    version: Version,
+    preset: Option<&preset::Preset>,
+) -> Result<String, Diagnostic> {
+    let options = glsl::Options {
+        version,
+        ..Default::default()
+    };
+    write_with(module, info, &options, entry_point, stage, preset)
+        .map(|(source, _)| source)
+        .map_err(|e| Diagnostic::error(format!("GLSL error: {e:?}")))
+}
+
+pub(crate) fn compile_glsl(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    stage: &str,
+    options: &GlslOptions,
+) -> Result<String, Diagnostic> {
+    let stage = parse_stage(stage)?;
+    let version = match options.version.as_deref() {
+        Some(name) => parse_version(name)?,
+        None => Version::new_gles(300),
+    };
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let module = crate::parse_wgsl(wgsl)?;
+    let info = crate::validate_for(&module, preset)?;
+    let entry_point = select_entry_point(&module, entry_point, stage)?;
+    let mut source = write_glsl(&module, &info, entry_point.clone(), stage, version, preset)?;
+    if options.fingerprint {
+        embed_fingerprint(&mut source, wgsl, Some(&entry_point));
+    }
+    Ok(source)
+}
+
+/// WGSL -> GLSL source code for OpenGL and WebGL2.
+/// `stage` is `"vertex"`, `"fragment"` or `"compute"`. GLSL holds a single
+/// entry point, so if entry_point is None or empty string, the module must
+/// have exactly one entry point of that stage.
+/// `options` is `{ version?: "330" | "300es" | ..., preset?, fingerprint? }`;
+/// the version defaults to GLSL ES 3.00.
+#[wasm_bindgen(js_name = wgslToGlsl)]
+pub fn wgsl_to_glsl(
+    wgsl: &str,
+    entry_point: Option<String>,
+    stage: &str,
+    options: JsValue,
+) -> Result<String, JsValue> {
+    let options: Option<GlslOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid GLSL options: {e}")))?;
+    compile_glsl(
+        wgsl,
+        entry_point.as_deref(),
+        stage,
+        &options.unwrap_or_default(),
+    )
+    .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Camera { view_proj: mat4x4<f32> }
+        @group(0) @binding(0) var<uniform> camera: Camera;
+
+        @vertex
+        fn vs(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+            return camera.view_proj * vec4<f32>(position, 1.0);
+        }
+
+        @fragment
+        fn fs() -> @location(0) vec4<f32> {
+            return vec4<f32>(1.0);
+        }
+    "#;
+
+    fn version(name: &str) -> GlslOptions {
+        GlslOptions {
+            version: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn versions_select_the_dialect() {
+        let es = compile_glsl(SHADER, Some("vs"), "vertex", &GlslOptions::default()).unwrap();
+        assert!(es.starts_with("#version 300 es"));
+        let core = compile_glsl(SHADER, Some("vs"), "vertex", &version("330")).unwrap();
+        assert!(core.starts_with("#version 330 core"));
+        assert!(
+            compile_glsl(SHADER, Some("fs"), "fragment", &version("310 es"))
+                .unwrap()
+                .starts_with("#version 310 es")
+        );
+
+        assert!(compile_glsl(SHADER, None, "vertex", &version("120")).is_err());
+        assert!(compile_glsl(SHADER, None, "vertex", &version("gl3")).is_err());
+    }
+
+    #[test]
+    fn entry_points_are_selected_by_stage() {
+        let fs = compile_glsl(SHADER, None, "fragment", &GlslOptions::default()).unwrap();
+        assert!(fs.contains("layout(location = 0) out vec4"));
+        assert!(!fs.contains("gl_Position"));
+
+        for (entry_point, stage) in [
+            (Some("fs"), "vertex"),
+            (None, "compute"),
+            (None, "geometry"),
+            (Some("nope"), "vertex"),
+        ] {
+            assert!(
+                compile_glsl(SHADER, entry_point, stage, &GlslOptions::default()).is_err(),
+                "{entry_point:?} {stage}"
+            );
+        }
+        let twice = format!("{SHADER}\n@fragment fn fs2() {{}}");
+        assert!(compile_glsl(&twice, None, "fragment", &GlslOptions::default()).is_err());
+    }
+
+    #[test]
+    fn fingerprints_name_the_selected_entry_point() {
+        let options = GlslOptions {
+            fingerprint: true,
+            ..Default::default()
+        };
+        let fs = compile_glsl(SHADER, None, "fragment", &options).unwrap();
+        let hash = crate::hash::sha256_hex(SHADER.as_bytes());
+        let (header, rest) = fs.split_once('\n').unwrap();
+        assert_eq!(header, format!("// metis-source: sha256={hash} entry=fs"));
+        assert!(rest.starts_with("#version 300 es"));
+
+        let plain = compile_glsl(SHADER, Some("fs"), "fragment", &GlslOptions::default()).unwrap();
+        assert_eq!(rest, plain);
+    }
+}
@@ -0,0 +1,323 @@
+use naga::valid::{Capabilities, ValidationFlags};
+
+use crate::diagnostics::{Budget, DetailedDiagnostic, SourceSpan};
+use crate::lexer::{Token, TokenKind, tokenize};
+
+// ============================================================================
+// GLSL Compatibility Implementation
+// ============================================================================
+//
+// The lenient mode of `validateWgslDetailed`, for porting sessions: GLSL
+// spellings with an exact WGSL equivalent are rewritten on tokens before
+// naga parses, and each rewrite is reported as an `info` diagnostic. GLSL
+// type names become WGSL ones, both as constructors (`float(x)`,
+// `ivec2(v)`) and in C-style declarations (`vec3 n = ...;`), literal
+// suffixes WGSL spells differently are fixed up, and renamed builtins get
+// their WGSL names. Diagnostics on the rewritten text are mapped back onto
+// the original, so they land where the user typed. Each rewrite is also
+// the diagnostic's `fix`, for `applyFix` to make in the source.
+
+/// One rewrite: byte range of the original text and what replaced it.
+pub(crate) struct Fixup {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+    pub message: String,
+}
+
+/// The WGSL type for GLSL type name `name`.
+fn wgsl_type(name: &str) -> Option<String> {
+    let scalar = |s: &str| match s {
+        "" | "d" => Some("f32"),
+        "i" => Some("i32"),
+        "u" => Some("u32"),
+        "b" => Some("bool"),
+        _ => None,
+    };
+    match name {
+        "float" | "double" => return Some("f32".to_string()),
+        "int" => return Some("i32".to_string()),
+        "uint" => return Some("u32".to_string()),
+        _ => {}
+    }
+    if let Some((prefix, n)) = name.split_once("vec")
+        && matches!(n, "2" | "3" | "4")
+    {
+        return Some(format!("vec{n}<{}>", scalar(prefix)?));
+    }
+    let dims = name
+        .strip_prefix("mat")
+        .or_else(|| name.strip_prefix("dmat"))?;
+    let dim = |d: &str| matches!(d, "2" | "3" | "4").then_some(());
+    let (columns, rows) = dims.split_once('x').unwrap_or((dims, dims));
+    dim(columns)?;
+    dim(rows)?;
+    Some(format!("mat{columns}x{rows}<f32>"))
+}
+
+fn renamed_builtin(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "inversesqrt" => "inverseSqrt",
+        "dFdx" => "dpdx",
+        "dFdy" => "dpdy",
+        "dFdxCoarse" => "dpdxCoarse",
+        "dFdyCoarse" => "dpdyCoarse",
+        "dFdxFine" => "dpdxFine",
+        "dFdyFine" => "dpdyFine",
+        _ => return None,
+    })
+}
+
+/// Number of top-level arguments of the call whose `(` is `tokens[open]`.
+fn argument_count(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0;
+    let mut commas = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.kind {
+            TokenKind::Punct('(' | '[') => depth += 1,
+            TokenKind::Punct(')' | ']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return if i == open + 1 { 0 } else { commas + 1 };
+                }
+            }
+            TokenKind::Punct(',') if depth == 1 => commas += 1,
+            _ => {}
+        }
+    }
+    commas + 1
+}
+
+/// The WGSL spelling of numeric literal `text`, if it differs.
+fn wgsl_literal(text: &str) -> Option<String> {
+    if text.starts_with("0x") || text.starts_with("0X") {
+        return None;
+    }
+    for suffix in ["lf", "LF"] {
+        if let Some(digits) = text.strip_suffix(suffix) {
+            return Some(digits.to_string());
+        }
+    }
+    match text.strip_suffix('F') {
+        Some(digits) => Some(format!("{digits}f")),
+        None => text.strip_suffix('U').map(|digits| format!("{digits}u")),
+    }
+}
+
+pub(crate) fn rewrite(source: &str) -> (String, Vec<Fixup>) {
+    let tokens = tokenize(source);
+    let mut fixups = Vec::new();
+    let mut fixup = |start: usize, end: usize, replacement: String, message: String| {
+        fixups.push(Fixup {
+            start,
+            end,
+            replacement,
+            message,
+        });
+    };
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let prev = i.checked_sub(1).map(|p| tokens[p]);
+        let next = tokens.get(i + 1).copied();
+        match token.kind {
+            TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct('}') => depth = depth.saturating_sub(1),
+            TokenKind::Number => {
+                if let Some(literal) = wgsl_literal(token.text) {
+                    let message = format!("Literal `{}` written as `{literal}`", token.text);
+                    fixup(token.start, token.end(), literal, message);
+                }
+            }
+            TokenKind::Ident if !prev.is_some_and(|p| p.is_punct('.')) => {
+                let called = next.is_some_and(|t| t.is_punct('('));
+                let for_init = prev.is_some_and(|p| p.is_punct('('))
+                    && i.checked_sub(2).is_some_and(|p| tokens[p].is_ident("for"));
+                let statement_start = for_init
+                    || prev.is_none_or(|p| {
+                        p.is_punct(';') || p.is_punct('{') || p.is_punct('}') || p.is_ident("const")
+                    });
+                let declared = tokens
+                    .get(i + 1)
+                    .is_some_and(|t| t.kind == TokenKind::Ident)
+                    && tokens
+                        .get(i + 2)
+                        .is_some_and(|t| t.is_punct('=') || t.is_punct(';'));
+                if let Some(ty) = wgsl_type(token.text) {
+                    if declared && statement_start {
+                        let name = tokens[i + 1];
+                        let constant = prev.is_some_and(|p| p.is_ident("const"));
+                        let (start, keyword) = match (constant, depth) {
+                            (true, _) => (prev.unwrap().start, "const"),
+                            (false, 0) => (token.start, "var<private>"),
+                            (false, _) => (token.start, "var"),
+                        };
+                        let replacement = format!("{keyword} {}: {ty}", name.text);
+                        let original = &source[start..name.end()];
+                        let message =
+                            format!("Declaration `{original}` written as `{replacement}`");
+                        fixup(start, name.end(), replacement, message);
+                        i += 2;
+                        continue;
+                    }
+                    // `vec3(...)` is already WGSL, with an inferred scalar.
+                    let inferred = token.text.starts_with("vec") || token.text.starts_with("mat");
+                    if called && !inferred {
+                        let message = format!("Constructor `{}` written as `{ty}`", token.text);
+                        fixup(token.start, token.end(), ty, message);
+                    }
+                } else if called {
+                    let replacement = match renamed_builtin(token.text) {
+                        Some(name) => Some(name),
+                        None if token.is_ident("atan") && argument_count(&tokens, i + 1) == 2 => {
+                            Some("atan2")
+                        }
+                        None => None,
+                    };
+                    if let Some(name) = replacement {
+                        let message = format!("Builtin `{}` written as `{name}`", token.text);
+                        fixup(token.start, token.end(), name.to_string(), message);
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut wgsl = String::with_capacity(source.len());
+    let mut copied = 0;
+    for fixup in &fixups {
+        wgsl.push_str(&source[copied..fixup.start]);
+        wgsl.push_str(&fixup.replacement);
+        copied = fixup.end;
+    }
+    wgsl.push_str(&source[copied..]);
+    (wgsl, fixups)
+}
+
+/// The original offset of `offset` in the rewritten text. Offsets inside a
+/// replacement map to the start of what it replaced, or its end if `end`.
+fn original_offset(fixups: &[Fixup], offset: usize, end: bool) -> usize {
+    let mut delta = 0isize;
+    for fixup in fixups {
+        let start = fixup.start.saturating_add_signed(delta);
+        if offset < start {
+            break;
+        }
+        if offset < start + fixup.replacement.len() && (offset > start || !end) {
+            return if end { fixup.end } else { fixup.start };
+        }
+        delta += fixup.replacement.len() as isize - (fixup.end - fixup.start) as isize;
+    }
+    offset.saturating_add_signed(-delta)
+}
+
+/// Move `span`, a span of the rewritten text, onto `source`.
+fn remap(source: &str, fixups: &[Fixup], span: &SourceSpan) -> Option<SourceSpan> {
+    let start = original_offset(fixups, span.start as usize, false);
+    let end = original_offset(fixups, span.end as usize, true).max(start);
+    SourceSpan::new(source, naga::Span::new(start as u32, end as u32))
+}
+
+/// `detailed_diagnostics` with GLSL-isms rewritten first: one `info`
+/// diagnostic per fixup, then the problems left, all on `source`'s offsets.
+pub(crate) fn lenient_diagnostics(source: &str) -> Vec<DetailedDiagnostic> {
+    lenient_diagnostics_within(
+        source,
+        &Budget::new(None),
+        ValidationFlags::all(),
+        Capabilities::all(),
+    )
+    .0
+}
+
+/// `lenient_diagnostics` running the checks in `flags` with `capabilities`,
+/// within `budget`, flagging whether it ran out.
+pub(crate) fn lenient_diagnostics_within(
+    source: &str,
+    budget: &Budget,
+    flags: ValidationFlags,
+    capabilities: Capabilities,
+) -> (Vec<DetailedDiagnostic>, bool) {
+    let (wgsl, fixups) = rewrite(source);
+    let mut diagnostics: Vec<_> = fixups
+        .iter()
+        .map(|fixup| {
+            let span = naga::Span::new(fixup.start as u32, fixup.end as u32);
+            DetailedDiagnostic {
+                severity: "info".to_string(),
+                fix: Some(fixup.replacement.clone()),
+                fix_span: SourceSpan::new(source, span),
+                fix_id: Some(format!("glsl-ism@{}", fixup.start)),
+                ..DetailedDiagnostic::warning(source, fixup.message.clone(), span, "GLSL-ism")
+            }
+        })
+        .collect();
+    let (remaining, timed_out) =
+        crate::diagnostics::detailed_diagnostics_within(&wgsl, budget, flags, capabilities);
+    for mut diagnostic in remaining {
+        for label in &mut diagnostic.labels {
+            if let Some(span) = remap(source, &fixups, &label.span) {
+                label.span = span;
+            }
+        }
+        diagnostic.span = diagnostic.labels.first().map(|label| label.span.clone());
+        diagnostics.push(diagnostic);
+    }
+    (diagnostics, timed_out)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glsl_isms_are_rewritten_and_reported() {
+        let source = r#"
+            const float SCALE = 2.0F;
+            @fragment
+            fn main(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+                vec2 d = vec2(dFdx(uv.x), dFdy(uv.y));
+                float a = atan(d.y, d.x) * inversesqrt(SCALE);
+                ivec2 texel = ivec2(uv * 4.0);
+                for (int i = 0; i < 2; i++) { a += 1.0; }
+                return vec4<f32>(a, float(texel.x), 1.0lf, 1.0);
+            }
+        "#;
+        let (wgsl, fixups) = rewrite(source);
+        assert!(wgsl.contains("const SCALE: f32 = 2.0f;"));
+        assert!(wgsl.contains("var d: vec2<f32> = vec2(dpdx(uv.x), dpdy(uv.y));"));
+        assert!(wgsl.contains("var a: f32 = atan2(d.y, d.x) * inverseSqrt(SCALE);"));
+        assert!(wgsl.contains("var texel: vec2<i32> = vec2<i32>(uv * 4.0);"));
+        assert!(wgsl.contains("f32(texel.x), 1.0, 1.0)"));
+        assert!(wgsl.contains("for (var i: i32 = 0; i < 2; i++)"));
+        assert_eq!(fixups.len(), 13);
+
+        let diagnostics = lenient_diagnostics(source);
+        assert_eq!(diagnostics.len(), fixups.len());
+        assert!(diagnostics.iter().all(|d| d.severity == "info"));
+        let span = diagnostics[0].span.as_ref().unwrap();
+        assert_eq!(
+            &source[span.start as usize..span.end as usize],
+            "const float SCALE"
+        );
+    }
+
+    #[test]
+    fn errors_map_back_to_the_original_text() {
+        let source = "fn f() {\n    float x = 1.0;\n    let y: u32 = x;\n}";
+        let diagnostics = lenient_diagnostics(source);
+        let error = diagnostics.iter().find(|d| d.severity == "error").unwrap();
+        let span = error.span.as_ref().unwrap();
+        assert_eq!((span.line, span.column), (3, 9));
+        assert_eq!(&source[span.start as usize..span.end as usize], "y");
+
+        assert!(rewrite("let v = vec3(1.0); let i = 0x1F;").1.is_empty());
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeSet;
+
+use naga::valid::GlobalUse;
+use naga::{Handle, Type, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Binding Graph Types
+// ============================================================================
+
+/// A JSON Graph Format document: one directed graph under `graph`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BindingGraph {
+    #[wasm_bindgen(readonly)]
+    pub graph: Graph,
+}
+
+#[wasm_bindgen]
+impl BindingGraph {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct Graph {
+    /// Always true.
+    #[wasm_bindgen(readonly)]
+    pub directed: bool,
+    /// Entry points, then bindings by group and binding, then struct types.
+    #[wasm_bindgen(readonly)]
+    pub nodes: Vec<GraphNode>,
+    #[wasm_bindgen(readonly)]
+    pub edges: Vec<GraphEdge>,
+}
+
+#[wasm_bindgen]
+impl Graph {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct GraphNode {
+    /// `entry:<name>`, `binding:<group>:<binding>` or `type:<name>`.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
+    #[wasm_bindgen(readonly)]
+    pub label: String,
+    #[wasm_bindgen(readonly)]
+    pub metadata: NodeMetadata,
+}
+
+#[wasm_bindgen]
+impl GraphNode {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct NodeMetadata {
+    /// `"entry_point"`, `"binding"` or `"type"`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    /// Set for entry points.
+    #[wasm_bindgen(readonly)]
+    pub stage: Option<String>,
+    /// Set for bindings.
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    /// Set for bindings.
+    #[wasm_bindgen(readonly)]
+    pub binding: Option<u32>,
+    /// Set for bindings, as in reflection.
+    #[wasm_bindgen(readonly)]
+    pub resource_type: Option<String>,
+}
+
+#[wasm_bindgen]
+impl NodeMetadata {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct GraphEdge {
+    #[wasm_bindgen(readonly)]
+    pub source: String,
+    #[wasm_bindgen(readonly)]
+    pub target: String,
+    /// `"uses"` from an entry point to a binding, `"contains"` from a
+    /// binding or struct to a struct type.
+    #[wasm_bindgen(readonly)]
+    pub relation: String,
+    /// For `"uses"`: any of `"read"`, `"write"`, `"query"` and `"atomic"`.
+    #[wasm_bindgen(readonly)]
+    pub access: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl GraphEdge {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Binding Graph Implementation
+// ============================================================================
+//
+// Uses come from validation, which folds every callee's global uses into its
+// callers, so an entry point uses whatever its helpers touch. Only struct
+// types get nodes; arrays of them are looked through.
+
+const ACCESS: &[(GlobalUse, &str)] = &[
+    (GlobalUse::READ, "read"),
+    (GlobalUse::WRITE, "write"),
+    (GlobalUse::QUERY, "query"),
+    (GlobalUse::ATOMIC, "atomic"),
+];
+
+/// The struct `ty` holds, looking through arrays.
+fn struct_type(module: &naga::Module, mut ty: Handle<Type>) -> Option<Handle<Type>> {
+    loop {
+        match module.types[ty].inner {
+            TypeInner::Struct { .. } => return Some(ty),
+            TypeInner::Array { base, .. } | TypeInner::BindingArray { base, .. } => ty = base,
+            _ => return None,
+        }
+    }
+}
+
+fn type_id(module: &naga::Module, ty: Handle<Type>) -> String {
+    match module.types[ty].name {
+        Some(ref name) => format!("type:{}", name),
+        None => format!("type:{}", ty.index()),
+    }
+}
+
+fn contains(source: String, target: String) -> GraphEdge {
+    GraphEdge {
+        source,
+        target,
+        relation: "contains".to_string(),
+        access: Vec::new(),
+    }
+}
+
+pub(crate) fn binding_graph(module: &naga::Module, info: &naga::valid::ModuleInfo) -> Graph {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+
+    let mut bindings: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter_map(|(h, var)| var.binding.map(|b| (b, h, var)))
+        .collect();
+    bindings.sort_by_key(|(b, ..)| (b.group, b.binding));
+    let binding_id = |b: &naga::ResourceBinding| format!("binding:{}:{}", b.group, b.binding);
+
+    for (index, ep) in module.entry_points.iter().enumerate() {
+        let id = format!("entry:{}", ep.name);
+        let uses = info.get_entry_point(index);
+        for (binding, handle, _) in &bindings {
+            let used = uses[*handle];
+            if used.is_empty() {
+                continue;
+            }
+            edges.push(GraphEdge {
+                source: id.clone(),
+                target: binding_id(binding),
+                relation: "uses".to_string(),
+                access: ACCESS
+                    .iter()
+                    .filter(|(flag, _)| used.contains(*flag))
+                    .map(|(_, name)| name.to_string())
+                    .collect(),
+            });
+        }
+        nodes.push(GraphNode {
+            id,
+            label: ep.name.clone(),
+            metadata: NodeMetadata {
+                kind: "entry_point".to_string(),
+                stage: Some(crate::stage_name(ep.stage).to_string()),
+                ..Default::default()
+            },
+        });
+    }
+
+    let mut structs = BTreeSet::new();
+    for (binding, _, var) in &bindings {
+        let id = binding_id(binding);
+        if let Some(ty) = struct_type(module, var.ty) {
+            edges.push(contains(id.clone(), type_id(module, ty)));
+            structs.insert(ty);
+        }
+        let (resource_type, ..) = crate::classify_binding(module, var);
+        nodes.push(GraphNode {
+            id,
+            label: var
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("binding_{}_{}", binding.group, binding.binding)),
+            metadata: NodeMetadata {
+                kind: "binding".to_string(),
+                group: Some(binding.group),
+                binding: Some(binding.binding),
+                resource_type: Some(resource_type),
+                ..Default::default()
+            },
+        });
+    }
+
+    // Member structs, transitively from the bindings' types.
+    let mut pending: Vec<_> = structs.iter().copied().collect();
+    while let Some(ty) = pending.pop() {
+        let TypeInner::Struct { ref members, .. } = module.types[ty].inner else {
+            continue;
+        };
+        for member in members {
+            if let Some(inner) = struct_type(module, member.ty) {
+                let edge = contains(type_id(module, ty), type_id(module, inner));
+                if !edges
+                    .iter()
+                    .any(|e| e.source == edge.source && e.target == edge.target)
+                {
+                    edges.push(edge);
+                }
+                if structs.insert(inner) {
+                    pending.push(inner);
+                }
+            }
+        }
+    }
+    for ty in structs {
+        nodes.push(GraphNode {
+            id: type_id(module, ty),
+            label: crate::get_type_name(module, ty).unwrap_or_else(|| "unknown".to_string()),
+            metadata: NodeMetadata {
+                kind: "type".to_string(),
+                ..Default::default()
+            },
+        });
+    }
+
+    Graph {
+        directed: true,
+        nodes,
+        edges,
+    }
+}
+
+/// How `wgsl`'s entry points share resources, as a JSON Graph Format
+/// document: entry point, binding and struct type nodes, with `uses` edges
+/// from entry points to the bindings they touch (directly or through the
+/// functions they call) and `contains` edges from bindings and structs to
+/// the struct types inside them.
+#[wasm_bindgen(js_name = bindingModelGraph)]
+pub fn binding_model_graph(wgsl: &str) -> Result<BindingGraph, JsValue> {
+    graph(wgsl).map_err(throw)
+}
+
+fn graph(wgsl: &str) -> Result<BindingGraph, Diagnostic> {
+    let (module, info) = crate::parse_and_validate(wgsl)?;
+    Ok(BindingGraph {
+        graph: binding_graph(&module, &info),
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Lights { count: u32, items: array<Light, 4> }
+
+        @group(0) @binding(0) var<uniform> lights: Lights;
+        @group(0) @binding(1) var<storage, read_write> out_color: array<vec4<f32>>;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+
+        fn shade(i: u32) -> vec3<f32> {
+            return lights.items[i].color;
+        }
+
+        @vertex
+        fn vs() -> @builtin(position) vec4<f32> {
+            return vec4<f32>(shade(0u), 1.0);
+        }
+
+        @compute @workgroup_size(1)
+        fn cs() {
+            out_color[0] = textureLoad(albedo, vec2<i32>(0), 0) * f32(lights.count);
+        }
+    "#;
+
+    fn edges(graph: &Graph, relation: &str) -> Vec<(String, String)> {
+        graph
+            .edges
+            .iter()
+            .filter(|e| e.relation == relation)
+            .map(|e| (e.source.clone(), e.target.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn entry_points_use_bindings_through_helpers() {
+        let graph = graph(SHADER).unwrap().graph;
+        let pairs = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+            list.iter()
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            edges(&graph, "uses"),
+            pairs(&[
+                ("entry:vs", "binding:0:0"),
+                ("entry:cs", "binding:0:0"),
+                ("entry:cs", "binding:0:1"),
+                ("entry:cs", "binding:1:0"),
+            ])
+        );
+        let writes = graph
+            .edges
+            .iter()
+            .find(|e| e.target == "binding:0:1")
+            .unwrap();
+        assert_eq!(writes.access, ["write"]);
+        assert_eq!(
+            edges(&graph, "contains"),
+            pairs(&[
+                ("binding:0:0", "type:Lights"),
+                ("type:Lights", "type:Light"),
+            ])
+        );
+    }
+
+    #[test]
+    fn nodes_carry_their_metadata() {
+        let graph = graph(SHADER).unwrap().graph;
+        let ids: Vec<_> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "entry:vs",
+                "entry:cs",
+                "binding:0:0",
+                "binding:0:1",
+                "binding:1:0",
+                "type:Light",
+                "type:Lights",
+            ]
+        );
+        assert_eq!(graph.nodes[1].metadata.stage.as_deref(), Some("compute"));
+        let albedo = &graph.nodes[4];
+        assert_eq!(albedo.label, "albedo");
+        assert_eq!(albedo.metadata.group, Some(1));
+        assert_eq!(albedo.metadata.resource_type.as_deref(), Some("texture"));
+    }
+}
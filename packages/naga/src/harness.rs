@@ -0,0 +1,323 @@
+use std::fmt::Write;
+
+use naga::proc::Layouter;
+use naga::{AddressSpace, ArraySize, Handle, StorageAccess, Type, TypeInner};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Compute Test Harness Generation
+// ============================================================================
+//
+// Emits a self-contained TypeScript module that embeds the shader, creates
+// one buffer per buffer binding the entry point uses (sized from the layout,
+// runtime-sized arrays holding `elements` items), dispatches the kernel and
+// maps every read-write storage buffer back. Textures and samplers cannot be
+// conjured from reflection, so callers pass them in `resources`.
+
+enum Resource {
+    Uniform,
+    Storage {
+        writable: bool,
+    },
+    /// Texture, sampler or anything else not backed by a buffer.
+    Other,
+}
+
+struct Binding {
+    name: String,
+    group: u32,
+    binding: u32,
+    resource: Resource,
+    /// TypeScript expression for the buffer size in bytes.
+    size: String,
+}
+
+/// Byte size of a buffer holding `ty`, in terms of `elements` if it ends in
+/// a runtime-sized array.
+fn size_expression(module: &naga::Module, layouter: &Layouter, ty: Handle<Type>) -> String {
+    let runtime = |ty: Handle<Type>| match module.types[ty].inner {
+        TypeInner::Array {
+            size: ArraySize::Dynamic,
+            stride,
+            ..
+        } => Some(stride),
+        _ => None,
+    };
+    let dynamic = match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => members
+            .last()
+            .and_then(|last| runtime(last.ty).map(|stride| (last.offset, stride))),
+        _ => runtime(ty).map(|stride| (0, stride)),
+    };
+    match dynamic {
+        Some((0, stride)) => format!("{stride} * elements"),
+        Some((offset, stride)) => format!("{offset} + {stride} * elements"),
+        None => layouter[ty].size.to_string(),
+    }
+}
+
+/// `main_pass` -> `MainPass`.
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+pub(crate) fn compute_test_harness(
+    source: &str,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: &str,
+) -> Result<String, Diagnostic> {
+    let index = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.name == entry_point)
+        .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", entry_point)))?;
+    if module.entry_points[index].stage != naga::ShaderStage::Compute {
+        return Err(Diagnostic::error(format!(
+            "Entry point '{}' is not a compute shader",
+            entry_point
+        )));
+    }
+    let ep_info = info.get_entry_point(index);
+
+    let layouter = crate::layout::layouter(module)?;
+
+    let mut bindings = Vec::new();
+    for (handle, var) in module.global_variables.iter() {
+        let Some(binding) = &var.binding else {
+            continue;
+        };
+        if ep_info[handle].is_empty() {
+            continue;
+        }
+        let resource = match var.space {
+            AddressSpace::Uniform => Resource::Uniform,
+            AddressSpace::Storage { access } => Resource::Storage {
+                writable: access.contains(StorageAccess::STORE),
+            },
+            _ => Resource::Other,
+        };
+        bindings.push(Binding {
+            name: var
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("binding_{}_{}", binding.group, binding.binding)),
+            group: binding.group,
+            binding: binding.binding,
+            size: size_expression(module, &layouter, var.ty),
+            resource,
+        });
+    }
+    bindings.sort_by_key(|b| (b.group, b.binding));
+
+    let code = serde_json::to_string(source).expect("strings always serialize");
+    let entry = serde_json::to_string(entry_point).expect("strings always serialize");
+    let name = pascal_case(entry_point);
+
+    // `write!` into a String cannot fail.
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "// Generated by generateComputeTestHarness for entry point {entry}."
+    );
+    let _ = writeln!(out, "const SHADER = {code};\n");
+    let _ = writeln!(out, "export interface {name}HarnessOptions {{");
+    let _ = writeln!(
+        out,
+        "    /** Items in runtime-sized arrays. Defaults to 64. */\n    elements?: number;"
+    );
+    let _ = writeln!(
+        out,
+        "    /** Workgroup counts to dispatch. Defaults to [1, 1, 1]. */\n    workgroups?: [number, number?, number?];"
+    );
+    let _ = writeln!(
+        out,
+        "    /** Initial buffer contents by binding name. */\n    inputs?: Record<string, ArrayBuffer | ArrayBufferView>;"
+    );
+    let _ = writeln!(
+        out,
+        "    /** Textures, samplers and other non-buffer bindings by name. */\n    resources?: Record<string, GPUBindingResource>;"
+    );
+    let _ = writeln!(out, "}}\n");
+    let _ = writeln!(
+        out,
+        "/** Dispatches {entry} and resolves with the contents of every read-write storage buffer. */"
+    );
+    let _ = writeln!(
+        out,
+        "export async function run{name}(device: GPUDevice, options: {name}HarnessOptions = {{}}): Promise<Record<string, ArrayBuffer>> {{"
+    );
+    let _ = writeln!(out, "    const elements = options.elements ?? 64;");
+    let _ = writeln!(
+        out,
+        "    const [x, y = 1, z = 1] = options.workgroups ?? [1, 1, 1];"
+    );
+    let _ = writeln!(
+        out,
+        "    const module = device.createShaderModule({{ code: SHADER }});"
+    );
+    let _ = writeln!(
+        out,
+        "    const pipeline = device.createComputePipeline({{ layout: \"auto\", compute: {{ module, entryPoint: {entry} }} }});"
+    );
+    if bindings
+        .iter()
+        .any(|b| matches!(b.resource, Resource::Other))
+    {
+        let _ = writeln!(
+            out,
+            "    const resource = (name: string): GPUBindingResource => {{\n        const found = options.resources?.[name];\n        if (!found) throw new Error(`Missing resource '${{name}}'`);\n        return found;\n    }};"
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "\n    const buffers: Record<string, GPUBuffer> = {{}};"
+    );
+    for b in &bindings {
+        let usage = match b.resource {
+            Resource::Uniform => "GPUBufferUsage.UNIFORM | GPUBufferUsage.COPY_DST",
+            Resource::Storage { .. } => {
+                "GPUBufferUsage.STORAGE | GPUBufferUsage.COPY_SRC | GPUBufferUsage.COPY_DST"
+            }
+            Resource::Other => continue,
+        };
+        let _ = writeln!(
+            out,
+            "    buffers[{name:?}] = device.createBuffer({{ size: {size}, usage: {usage} }});",
+            name = b.name,
+            size = b.size,
+        );
+    }
+    let _ = writeln!(
+        out,
+        "    for (const [name, data] of Object.entries(options.inputs ?? {{}})) {{\n        device.queue.writeBuffer(buffers[name], 0, data);\n    }}"
+    );
+
+    let mut groups: Vec<u32> = bindings.iter().map(|b| b.group).collect();
+    groups.dedup();
+    let _ = writeln!(out, "\n    const encoder = device.createCommandEncoder();");
+    let _ = writeln!(out, "    const pass = encoder.beginComputePass();");
+    let _ = writeln!(out, "    pass.setPipeline(pipeline);");
+    for group in &groups {
+        let _ = writeln!(
+            out,
+            "    pass.setBindGroup({group}, device.createBindGroup({{\n        layout: pipeline.getBindGroupLayout({group}),\n        entries: ["
+        );
+        for b in bindings.iter().filter(|b| b.group == *group) {
+            let resource = match b.resource {
+                Resource::Other => format!("resource({:?})", b.name),
+                _ => format!("{{ buffer: buffers[{:?}] }}", b.name),
+            };
+            let _ = writeln!(
+                out,
+                "            {{ binding: {}, resource: {} }},",
+                b.binding, resource
+            );
+        }
+        let _ = writeln!(out, "        ],\n    }}));");
+    }
+    let _ = writeln!(out, "    pass.dispatchWorkgroups(x, y, z);");
+    let _ = writeln!(out, "    pass.end();");
+
+    let outputs: Vec<&Binding> = bindings
+        .iter()
+        .filter(|b| matches!(b.resource, Resource::Storage { writable: true }))
+        .collect();
+    let _ = writeln!(out, "\n    const readbacks: [string, GPUBuffer][] = [];");
+    for b in &outputs {
+        let _ = writeln!(
+            out,
+            "    {{\n        const source = buffers[{name:?}];\n        const staging = device.createBuffer({{ size: source.size, usage: GPUBufferUsage.MAP_READ | GPUBufferUsage.COPY_DST }});\n        encoder.copyBufferToBuffer(source, 0, staging, 0, source.size);\n        readbacks.push([{name:?}, staging]);\n    }}",
+            name = b.name,
+        );
+    }
+    let _ = writeln!(out, "    device.queue.submit([encoder.finish()]);");
+    let _ = writeln!(
+        out,
+        "\n    const results: Record<string, ArrayBuffer> = {{}};\n    for (const [name, staging] of readbacks) {{\n        await staging.mapAsync(GPUMapMode.READ);\n        results[name] = staging.getMappedRange().slice(0);\n        staging.destroy();\n    }}\n    for (const buffer of Object.values(buffers)) buffer.destroy();\n    return results;\n}}"
+    );
+    Ok(out)
+}
+
+/// TypeScript smoke test scaffolding for a compute entry point: an exported
+/// `run<EntryPoint>(device, options?)` that creates a buffer for every buffer
+/// binding the kernel uses, uploads `options.inputs`, dispatches it and
+/// resolves with the contents of its read-write storage buffers by binding
+/// name. Non-buffer bindings are taken from `options.resources`.
+#[wasm_bindgen(js_name = generateComputeTestHarness)]
+pub fn generate_compute_test_harness(wgsl: &str, entry_point: &str) -> Result<String, JsValue> {
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    compute_test_harness(wgsl, &module, &info, entry_point).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Params { scale: f32, count: u32 }
+        struct Output { total: atomic<u32>, values: array<f32> }
+        @group(0) @binding(0) var<uniform> params: Params;
+        @group(0) @binding(1) var<storage, read> input: array<f32>;
+        @group(1) @binding(0) var<storage, read_write> output: Output;
+        @group(1) @binding(1) var<storage, read_write> unused: array<u32>;
+        @group(2) @binding(0) var lut: texture_2d<f32>;
+
+        @compute @workgroup_size(64)
+        fn scale_values(@builtin(global_invocation_id) id: vec3<u32>) {
+            let sample = textureLoad(lut, vec2<u32>(0u), 0).x;
+            output.values[id.x] = input[id.x] * params.scale * sample;
+            atomicAdd(&output.total, 1u);
+        }
+
+        @vertex fn vs() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }
+    "#;
+
+    fn harness(entry_point: &str) -> Result<String, Diagnostic> {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        compute_test_harness(SHADER, &module, &info, entry_point)
+    }
+
+    #[test]
+    fn buffers_follow_reflection() {
+        let ts = harness("scale_values").unwrap();
+        assert!(ts.contains("export async function runScaleValues(device: GPUDevice"));
+        assert!(ts.contains("entryPoint: \"scale_values\""));
+        assert!(ts.contains(
+            "buffers[\"params\"] = device.createBuffer({ size: 8, usage: GPUBufferUsage.UNIFORM"
+        ));
+        assert!(ts.contains("buffers[\"input\"] = device.createBuffer({ size: 4 * elements,"));
+        assert!(ts.contains("buffers[\"output\"] = device.createBuffer({ size: 4 + 4 * elements,"));
+        // Unused bindings are left out, and only writable storage is read back.
+        assert!(!ts.contains("\"unused\""));
+        assert!(ts.contains("readbacks.push([\"output\", staging])"));
+        assert!(!ts.contains("readbacks.push([\"input\""));
+        assert!(ts.contains("{ binding: 0, resource: resource(\"lut\") }"));
+        assert!(ts.contains("pass.setBindGroup(2, device.createBindGroup({"));
+    }
+
+    #[test]
+    fn only_compute_entry_points() {
+        assert!(harness("vs").is_err());
+        assert!(harness("nope").is_err());
+        assert_eq!(pascal_case("main"), "Main");
+        assert_eq!(pascal_case("blur__x_pass"), "BlurXPass");
+    }
+}
@@ -0,0 +1,38 @@
+use sha2::{Digest, Sha256};
+
+/// SHA-256 of `data` as lowercase hex. Used for every content hash we hand out
+/// (sources, artifacts, reflection digests), so hashes compare across APIs.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+/// Short ID for a named item of kind `kind` (`"binding"`, a stage name, ...),
+/// derived from nothing but the two, so it survives edits that move the item
+/// around. Lets successive compiles of an edited file be correlated.
+pub(crate) fn stable_id(kind: &str, name: &str) -> String {
+    let mut hex = sha256_hex(format!("{kind}\0{name}").as_bytes());
+    hex.truncate(16);
+    hex
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_depend_on_kind_and_name() {
+        let id = stable_id("binding", "tint");
+        assert_eq!(id.len(), 16);
+        assert_ne!(id, stable_id("fragment", "tint"));
+        assert_ne!(id, stable_id("binding", "tint2"));
+    }
+}
@@ -0,0 +1,725 @@
+use naga::back::hlsl::{self, ShaderModel};
+use naga::valid::Capabilities;
+use naga::{AddressSpace, ImageClass, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::entry_points;
+use crate::preset;
+use crate::provenance::embed_fingerprint;
+
+// ============================================================================
+// HLSL Types
+// ============================================================================
+
+/// A register for `@group(group) @binding(binding)`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub(crate) struct RegisterBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub register: u32,
+    pub space: u8,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub(crate) struct RegisterTarget {
+    pub register: u32,
+    pub space: u8,
+}
+
+/// Options object accepted by `wgslToHlsl` and `wgslToHlslReflected`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HlslOptions {
+    /// `"5_0"` through `"6_7"`; `"6.0"` is accepted too. Defaults to 5.1.
+    #[serde(default)]
+    pub shader_model: Option<String>,
+    /// Device preset whose capabilities the module is validated against.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Lead the output with a source hash and entry point comment.
+    #[serde(default)]
+    pub fingerprint: bool,
+    /// Name the entry point is given in the output, as for `wgslToSpirvBin`.
+    #[serde(default)]
+    pub entry_point_name: Option<String>,
+    /// Shader models to try, in order, when `shaderModel` lacks a
+    /// capability the module needs.
+    #[serde(default)]
+    pub fallback_shader_models: Vec<String>,
+    /// Registers to use instead of naga's default mapping.
+    #[serde(default)]
+    pub bindings: Vec<RegisterBinding>,
+    /// The push constants' `b` register; see `default_push_constants`.
+    #[serde(default)]
+    pub push_constants: Option<RegisterTarget>,
+}
+
+/// HLSL with the registers every resource was given.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct HlslReflection {
+    #[wasm_bindgen(readonly)]
+    pub hlsl: String,
+    /// The shader model the output targets, e.g. `"6_0"`.
+    #[wasm_bindgen(readonly)]
+    pub shader_model: String,
+    /// Shader models tried before it, with what each lacked.
+    #[wasm_bindgen(readonly)]
+    pub rejected: Vec<RejectedShaderModel>,
+    /// Resources the written entry points use, by group and binding, then
+    /// the push constants.
+    #[wasm_bindgen(readonly)]
+    pub registers: Vec<HlslRegister>,
+    #[wasm_bindgen(readonly)]
+    pub entry_points: Vec<HlslEntryPoint>,
+}
+
+#[wasm_bindgen]
+impl HlslReflection {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RejectedShaderModel {
+    #[wasm_bindgen(readonly)]
+    pub shader_model: String,
+    /// naga capability names the module needs and the model lacks.
+    #[wasm_bindgen(readonly)]
+    pub missing: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl RejectedShaderModel {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct HlslRegister {
+    /// The WGSL variable.
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+    /// Unset for the push constants.
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub binding: Option<u32>,
+    /// `"b"`, `"t"`, `"u"` or `"s"`. A sampler's `register` is its index in
+    /// the group's sampler index buffer rather than an `s` register.
+    #[wasm_bindgen(readonly)]
+    pub class: String,
+    #[wasm_bindgen(readonly)]
+    pub register: u32,
+    #[wasm_bindgen(readonly)]
+    pub space: u32,
+}
+
+#[wasm_bindgen]
+impl HlslRegister {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct HlslEntryPoint {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// The function's name in the HLSL, which differs if `name` is reserved
+    /// there.
+    #[wasm_bindgen(readonly)]
+    pub hlsl_name: String,
+}
+
+#[wasm_bindgen]
+impl HlslEntryPoint {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// HLSL Backend
+// ============================================================================
+//
+// Registers start from naga's default mapping, register `b` of the right
+// class in `space g` for `@group(g) @binding(b)`, with `bindings` taking
+// precedence. naga's writer needs a register for push constants whenever a
+// module has them, so without `pushConstants` they get `b0` in the space
+// after the last group, where the default mapping never reaches.
+//
+// naga writes whatever shader model it is given, even wave intrinsics for
+// 5.1, so the model is checked here instead: the module is validated with
+// the capabilities the model provides, narrowed to the preset's. When that
+// fails, each of `fallbackShaderModels` is tried in turn, and the rejected
+// ones are reported with the capabilities they lacked.
+
+pub(crate) fn parse_shader_model(name: &str) -> Result<ShaderModel, Diagnostic> {
+    Ok(match name.replace('.', "_").as_str() {
+        "5_0" => ShaderModel::V5_0,
+        "5_1" => ShaderModel::V5_1,
+        "6_0" => ShaderModel::V6_0,
+        "6_1" => ShaderModel::V6_1,
+        "6_2" => ShaderModel::V6_2,
+        "6_3" => ShaderModel::V6_3,
+        "6_4" => ShaderModel::V6_4,
+        "6_5" => ShaderModel::V6_5,
+        "6_6" => ShaderModel::V6_6,
+        "6_7" => ShaderModel::V6_7,
+        _ => {
+            return Err(Diagnostic::error(format!(
+                "Unknown shader model '{}'",
+                name
+            )));
+        }
+    })
+}
+
+/// Capabilities naga's HLSL output can use under `shader_model`.
+fn shader_model_capabilities(shader_model: ShaderModel) -> Capabilities {
+    let mut capabilities = Capabilities::PUSH_CONSTANT
+        | Capabilities::FLOAT64
+        | Capabilities::PRIMITIVE_INDEX
+        | Capabilities::CLIP_DISTANCE
+        | Capabilities::CULL_DISTANCE
+        | Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS
+        | Capabilities::EARLY_DEPTH_TEST
+        | Capabilities::MULTISAMPLED_SHADING
+        | Capabilities::DUAL_SOURCE_BLENDING
+        | Capabilities::CUBE_ARRAY_TEXTURES
+        | Capabilities::TEXTURE_ATOMIC
+        | Capabilities::TEXTURE_EXTERNAL
+        | Capabilities::SHADER_FLOAT16_IN_FLOAT32;
+    let steps = [
+        (
+            ShaderModel::V5_1,
+            Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+                | Capabilities::STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING
+                | Capabilities::UNIFORM_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+                | Capabilities::SAMPLER_NON_UNIFORM_INDEXING,
+        ),
+        (
+            ShaderModel::V6_0,
+            Capabilities::SHADER_INT64
+                | Capabilities::SUBGROUP
+                | Capabilities::SUBGROUP_BARRIER
+                | Capabilities::SUBGROUP_VERTEX_STAGE,
+        ),
+        (ShaderModel::V6_1, Capabilities::MULTIVIEW),
+        (ShaderModel::V6_2, Capabilities::SHADER_FLOAT16),
+        (ShaderModel::V6_5, Capabilities::RAY_QUERY),
+        (
+            ShaderModel::V6_6,
+            Capabilities::SHADER_INT64_ATOMIC_MIN_MAX
+                | Capabilities::SHADER_INT64_ATOMIC_ALL_OPS
+                | Capabilities::TEXTURE_INT64_ATOMIC,
+        ),
+    ];
+    for (model, added) in steps {
+        if shader_model >= model {
+            capabilities |= added;
+        }
+    }
+    capabilities
+}
+
+/// Validate `module` for the first of `shader_models` it fits in, within
+/// `allowed`, with those rejected before it and what each lacked.
+fn pick_shader_model(
+    module: &naga::Module,
+    shader_models: &[ShaderModel],
+    allowed: Capabilities,
+) -> Result<
+    (
+        ShaderModel,
+        naga::valid::ModuleInfo,
+        Vec<RejectedShaderModel>,
+    ),
+    Diagnostic,
+> {
+    let mut rejected = Vec::new();
+    for &shader_model in shader_models {
+        let capabilities = allowed & shader_model_capabilities(shader_model);
+        if let Ok(info) = crate::validate_module_with(module, capabilities) {
+            return Ok((shader_model, info, rejected));
+        }
+        // An error with every allowed capability is the module's own.
+        crate::validate_module_with(module, allowed)?;
+        let missing = (allowed - capabilities)
+            .iter_names()
+            .filter(|&(_, capability)| {
+                crate::validate_module_with(module, allowed - capability).is_err()
+            })
+            .map(|(name, _)| name.to_string())
+            .collect();
+        rejected.push(RejectedShaderModel {
+            shader_model: shader_model.to_str().to_string(),
+            missing,
+        });
+    }
+    let tried: Vec<_> = rejected
+        .iter()
+        .map(|r| format!("{} (needs {})", r.shader_model, r.missing.join(", ")))
+        .collect();
+    Err(Diagnostic::error(format!(
+        "No shader model supports this module: {}",
+        tried.join("; ")
+    )))
+}
+
+/// `b0` in the space after the last group the module binds.
+fn default_push_constants(module: &naga::Module) -> hlsl::BindTarget {
+    let space = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, var)| var.binding.as_ref())
+        .map(|binding| binding.group + 1)
+        .max()
+        .unwrap_or(0);
+    target(0, space as u8)
+}
+
+fn target(register: u32, space: u8) -> hlsl::BindTarget {
+    hlsl::BindTarget {
+        space,
+        register,
+        binding_array_size: None,
+        dynamic_storage_buffer_offsets_index: None,
+        restrict_indexing: false,
+    }
+}
+
+/// naga's options for `shader_model`, with `bindings` and `push_constants`
+/// over the default mapping.
+fn naga_options(
+    module: &naga::Module,
+    shader_model: ShaderModel,
+    bindings: &[RegisterBinding],
+    push_constants: Option<RegisterTarget>,
+) -> hlsl::Options {
+    let binding_map = bindings
+        .iter()
+        .map(|b| {
+            let binding = naga::ResourceBinding {
+                group: b.group,
+                binding: b.binding,
+            };
+            (binding, target(b.register, b.space))
+        })
+        .collect();
+    let push_constants_target = match push_constants {
+        Some(t) => target(t.register, t.space),
+        None => default_push_constants(module),
+    };
+    hlsl::Options {
+        shader_model,
+        binding_map,
+        push_constants_target: Some(push_constants_target),
+        ..Default::default()
+    }
+}
+
+fn pipeline_options(
+    module: &naga::Module,
+    entry_point: Option<&str>,
+) -> Result<hlsl::PipelineOptions, Diagnostic> {
+    Ok(match entry_point {
+        Some(ep_name) if !ep_name.is_empty() => {
+            let entry = crate::find_entry_point(module, ep_name)?;
+            hlsl::PipelineOptions {
+                entry_point: Some((entry.stage, ep_name.to_string())),
+            }
+        }
+        // No specific entry point - compile all
+        _ => hlsl::PipelineOptions::default(),
+    })
+}
+
+fn write(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    options: &hlsl::Options,
+) -> Result<(String, hlsl::ReflectionInfo), Diagnostic> {
+    let pipeline_options = pipeline_options(module, entry_point)?;
+    let mut source = String::new();
+    let reflection = hlsl::Writer::new(&mut source, options, &pipeline_options)
+        .write(module, info, None)
+        .map_err(|e| Diagnostic::error(format!("HLSL error: {e:?}")))?;
+    Ok((source, reflection))
+}
+
+/// Emit HLSL source for an already validated module, with naga's default
+/// register mapping (see `wgslToRootSignature`).
+pub(crate) fn write_hlsl(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    shader_model: ShaderModel,
+) -> Result<String, Diagnostic> {
+    let options = naga_options(module, shader_model, &[], None);
+    write(module, info, entry_point, &options).map(|(source, _)| source)
+}
+
+/// `"b"`, `"t"`, `"u"` or `"s"`, the register class of `var`.
+fn register_class(module: &naga::Module, var: &naga::GlobalVariable) -> &'static str {
+    let inner = match module.types[var.ty].inner {
+        TypeInner::BindingArray { base, .. } => &module.types[base].inner,
+        ref inner => inner,
+    };
+    match (var.space, inner) {
+        (AddressSpace::Uniform | AddressSpace::PushConstant, _) => "b",
+        (AddressSpace::Storage { access }, _) if access.contains(naga::StorageAccess::STORE) => "u",
+        (_, TypeInner::Sampler { .. }) => "s",
+        (
+            _,
+            TypeInner::Image {
+                class: ImageClass::Storage { .. },
+                ..
+            },
+        ) => "u",
+        _ => "t",
+    }
+}
+
+/// The registers `options` gives the globals `entry_points` use.
+fn registers(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_points: &[usize],
+    options: &hlsl::Options,
+) -> Vec<HlslRegister> {
+    let mut registers = Vec::new();
+    let mut push_constants = None;
+    for (handle, var) in module.global_variables.iter() {
+        if entry_points
+            .iter()
+            .all(|&i| info.get_entry_point(i)[handle].is_empty())
+        {
+            continue;
+        }
+        let (binding, target) = match (&var.binding, var.space) {
+            (Some(binding), _) => {
+                let target = options.binding_map.get(binding).copied();
+                let target =
+                    target.unwrap_or_else(|| self::target(binding.binding, binding.group as u8));
+                (Some(binding), target)
+            }
+            (None, AddressSpace::PushConstant) => (None, options.push_constants_target.unwrap()),
+            (None, _) => continue,
+        };
+        let register = HlslRegister {
+            name: var.name.clone(),
+            group: binding.map(|b| b.group),
+            binding: binding.map(|b| b.binding),
+            class: register_class(module, var).to_string(),
+            register: target.register,
+            space: target.space as u32,
+        };
+        match binding {
+            Some(_) => registers.push(register),
+            None => push_constants = Some(register),
+        }
+    }
+    registers.sort_by_key(|r| (r.group, r.binding));
+    registers.extend(push_constants);
+    registers
+}
+
+pub(crate) fn compile_hlsl_reflected(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    options: &HlslOptions,
+) -> Result<HlslReflection, Diagnostic> {
+    let first = match options.shader_model.as_deref() {
+        Some(name) => parse_shader_model(name)?,
+        None => hlsl::Options::default().shader_model,
+    };
+    let mut shader_models = vec![first];
+    for name in &options.fallback_shader_models {
+        shader_models.push(parse_shader_model(name)?);
+    }
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let module = crate::parse_wgsl(wgsl)?;
+    let allowed = preset.map_or(Capabilities::all(), |p| p.capabilities);
+    let (shader_model, info, rejected) = pick_shader_model(&module, &shader_models, allowed)?;
+
+    let renamed;
+    let (module, entry_point) = match options.entry_point_name.as_deref() {
+        Some(name) => {
+            renamed = entry_points::rename_for_output(&module, entry_point, name)?;
+            (&renamed, Some(name))
+        }
+        None => (&module, entry_point),
+    };
+    let naga_options = naga_options(
+        module,
+        shader_model,
+        &options.bindings,
+        options.push_constants,
+    );
+    let (mut source, reflection) = write(module, &info, entry_point, &naga_options)?;
+    if options.fingerprint {
+        embed_fingerprint(&mut source, wgsl, entry_point);
+    }
+
+    let written: Vec<usize> = match entry_point.filter(|name| !name.is_empty()) {
+        Some(name) => module
+            .entry_points
+            .iter()
+            .position(|ep| ep.name == name)
+            .into_iter()
+            .collect(),
+        None => (0..module.entry_points.len()).collect(),
+    };
+    let entry_points = written
+        .iter()
+        .zip(reflection.entry_point_names)
+        .map(|(&i, hlsl_name)| {
+            let name = module.entry_points[i].name.clone();
+            let hlsl_name =
+                hlsl_name.map_err(|e| Diagnostic::error(format!("HLSL error in '{name}': {e}")))?;
+            Ok(HlslEntryPoint { name, hlsl_name })
+        })
+        .collect::<Result<_, Diagnostic>>()?;
+    Ok(HlslReflection {
+        hlsl: source,
+        shader_model: shader_model.to_str().to_string(),
+        rejected,
+        registers: registers(module, &info, &written, &naga_options),
+        entry_points,
+    })
+}
+
+pub(crate) fn compile_hlsl(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    options: &HlslOptions,
+) -> Result<String, Diagnostic> {
+    compile_hlsl_reflected(wgsl, entry_point, options).map(|reflected| reflected.hlsl)
+}
+
+/// WGSL -> HLSL source code for Direct3D 12.
+/// If entry_point is provided, only compiles that specific entry point.
+/// If entry_point is None or empty string, compiles all entry points.
+/// `options` is `{ shaderModel?: "5_1" | "6_0" | ..., preset?, fingerprint?,
+/// entryPointName?, fallbackShaderModels?, bindings?, pushConstants? }`; the
+/// shader model defaults to 5.1. Registers follow `wgslToRootSignature`
+/// unless `bindings` (`[{ group, binding, register, space }]`) says
+/// otherwise; `entryPointName` is as for `wgslToSpirvBin`.
+/// `fingerprint: true` leads the output with a
+/// `// metis-source: sha256=<source hash> entry=<name>` comment.
+#[wasm_bindgen(js_name = wgslToHlsl)]
+pub fn wgsl_to_hlsl(
+    wgsl: &str,
+    entry_point: Option<String>,
+    options: JsValue,
+) -> Result<String, JsValue> {
+    let options: Option<HlslOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid HLSL options: {e}")))?;
+    compile_hlsl(wgsl, entry_point.as_deref(), &options.unwrap_or_default()).map_err(throw)
+}
+
+/// `wgslToHlsl`, also returning the shader model used, the register and
+/// space of every resource, and each entry point's HLSL name. A shader
+/// model without a capability the module needs is rejected, and the next
+/// of `fallbackShaderModels` tried; `pushConstants: { register, space }`
+/// places the push constants, `b0` in the space after the last group by
+/// default.
+#[wasm_bindgen(js_name = wgslToHlslReflected)]
+pub fn wgsl_to_hlsl_reflected(
+    wgsl: &str,
+    entry_point: Option<String>,
+    options: JsValue,
+) -> Result<HlslReflection, JsValue> {
+    let options: Option<HlslOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid HLSL options: {e}")))?;
+    compile_hlsl_reflected(wgsl, entry_point.as_deref(), &options.unwrap_or_default())
+        .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = id.x;
+        }
+
+        @fragment
+        fn fs() -> @location(0) vec4<f32> {
+            return vec4<f32>(1.0);
+        }
+    "#;
+
+    #[test]
+    fn emits_selected_entry_point() {
+        let hlsl = compile_hlsl(SHADER, Some("main"), &HlslOptions::default()).unwrap();
+        assert!(hlsl.contains("[numthreads(64, 1, 1)]"));
+        assert!(hlsl.contains("RWByteAddressBuffer data : register(u0);"));
+        assert!(!hlsl.contains("SV_Target0"));
+
+        let all = compile_hlsl(SHADER, None, &HlslOptions::default()).unwrap();
+        assert!(all.contains("SV_Target0"));
+        assert!(compile_hlsl(SHADER, Some("nope"), &HlslOptions::default()).is_err());
+
+        let fingerprinted = HlslOptions {
+            fingerprint: true,
+            ..Default::default()
+        };
+        let hlsl = compile_hlsl(SHADER, Some("main"), &fingerprinted).unwrap();
+        assert!(hlsl.starts_with("// metis-source: sha256="));
+        assert!(hlsl.lines().next().unwrap().ends_with(" entry=main"));
+    }
+
+    #[test]
+    fn shader_models_parse() {
+        assert!(matches!(parse_shader_model("6_0"), Ok(ShaderModel::V6_0)));
+        assert!(matches!(parse_shader_model("6.6"), Ok(ShaderModel::V6_6)));
+        assert!(parse_shader_model("4_0").is_err());
+
+        let wave = "@compute @workgroup_size(64) fn main() { _ = subgroupAdd(1u); }";
+        let sm = |name: &str| HlslOptions {
+            shader_model: Some(name.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            compile_hlsl(wave, None, &sm("6_0"))
+                .unwrap()
+                .contains("WaveActiveSum")
+        );
+        assert!(compile_hlsl(wave, None, &sm("7_0")).is_err());
+    }
+
+    #[test]
+    fn registers_follow_bindings_and_push_constants() {
+        let shader = r#"
+            struct Push { scale: f32 }
+            var<push_constant> push: Push;
+            @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+            @group(0) @binding(1) var albedo: texture_2d<f32>;
+            @group(0) @binding(2) var linear: sampler;
+            @group(1) @binding(0) var<storage, read_write> out: array<vec4<f32>>;
+            @group(1) @binding(1) var<storage> unused: array<f32>;
+
+            @compute @workgroup_size(1)
+            fn main() {
+                let color = textureSampleLevel(albedo, linear, vec2<f32>(0.5), 0.0);
+                out[0] = color * tint * push.scale;
+            }
+        "#;
+        let options = HlslOptions {
+            bindings: vec![RegisterBinding {
+                group: 1,
+                binding: 0,
+                register: 3,
+                space: 0,
+            }],
+            ..Default::default()
+        };
+        let reflected = compile_hlsl_reflected(shader, Some("main"), &options).unwrap();
+        let registers: Vec<_> = reflected
+            .registers
+            .iter()
+            .map(|r| {
+                (
+                    r.name.as_deref().unwrap(),
+                    r.class.as_str(),
+                    r.register,
+                    r.space,
+                )
+            })
+            .collect();
+        assert_eq!(
+            registers,
+            [
+                ("tint", "b", 0, 0),
+                ("albedo", "t", 1, 0),
+                ("linear", "s", 2, 0),
+                ("out", "u", 3, 0),
+                ("push", "b", 0, 2),
+            ]
+        );
+        assert!(
+            reflected.hlsl.contains("out_ : register(u3);"),
+            "{}",
+            reflected.hlsl
+        );
+        assert!(reflected.hlsl.contains("register(b0, space2)"));
+        assert_eq!(reflected.entry_points[0].hlsl_name, "main");
+
+        let options = HlslOptions {
+            push_constants: Some(RegisterTarget {
+                register: 5,
+                space: 9,
+            }),
+            ..Default::default()
+        };
+        let reflected = compile_hlsl_reflected(shader, Some("main"), &options).unwrap();
+        assert!(reflected.hlsl.contains("register(b5, space9)"));
+    }
+
+    #[test]
+    fn shader_models_fall_back_when_capabilities_are_missing() {
+        let shader = r#"
+            enable f16;
+            @group(0) @binding(0) var<storage, read_write> data: array<f16>;
+            @compute @workgroup_size(64)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                data[id.x] = f16(subgroupAdd(id.x));
+            }
+        "#;
+        let options = HlslOptions {
+            shader_model: Some("5_1".to_string()),
+            fallback_shader_models: vec!["6_0".to_string(), "6_2".to_string()],
+            ..Default::default()
+        };
+        let reflected = compile_hlsl_reflected(shader, None, &options).unwrap();
+        assert_eq!(reflected.shader_model, "6_2");
+        let rejected: Vec<_> = reflected
+            .rejected
+            .iter()
+            .map(|r| (r.shader_model.as_str(), r.missing.join(" ")))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                ("5_1", "SUBGROUP SHADER_FLOAT16".to_string()),
+                ("6_0", "SHADER_FLOAT16".to_string())
+            ]
+        );
+
+        let error = compile_hlsl(shader, None, &HlslOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.message,
+            "No shader model supports this module: 5_1 (needs SUBGROUP, SHADER_FLOAT16)"
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Diagnostic;
+
+// ============================================================================
+// Include Preprocessor
+// ============================================================================
+//
+// WGSL has no include mechanism, so multi-file builds accept a line of the form
+//
+//     #include "common/lighting.wgsl"
+//
+// which is replaced by the contents of that file. Paths are relative to the
+// including file unless they start with `/`. Every file is included at most
+// once, which makes diamonds and cycles harmless.
+
+/// A source with its includes expanded.
+pub(crate) struct Expanded {
+    pub source: String,
+    /// Every other file read while expanding, sorted.
+    pub dependencies: Vec<String>,
+}
+
+/// Expand `#include` lines in `path`, reading from `files`.
+pub(crate) fn expand_includes(
+    path: &str,
+    files: &BTreeMap<String, String>,
+) -> Result<Expanded, Diagnostic> {
+    let root = files
+        .get(path)
+        .ok_or_else(|| Diagnostic::error(format!("Source '{}' not found", path)))?;
+
+    let mut seen = BTreeSet::new();
+    seen.insert(path.to_string());
+    let mut source = String::with_capacity(root.len());
+    expand_into(path, root, files, &mut seen, &mut source)?;
+
+    seen.remove(path);
+    Ok(Expanded {
+        source,
+        dependencies: seen.into_iter().collect(),
+    })
+}
+
+fn expand_into(
+    path: &str,
+    text: &str,
+    files: &BTreeMap<String, String>,
+    seen: &mut BTreeSet<String>,
+    out: &mut String,
+) -> Result<(), Diagnostic> {
+    for (line_no, line) in text.lines().enumerate() {
+        let Some(target) = parse_include(line) else {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+        let target = target.map_err(|message| {
+            Diagnostic::error(format!("{}:{}: {}", path, line_no + 1, message))
+        })?;
+
+        let resolved = resolve_path(path, target);
+        if !seen.insert(resolved.clone()) {
+            continue;
+        }
+        let included = files.get(&resolved).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "{}:{}: included file '{}' not found",
+                path,
+                line_no + 1,
+                resolved
+            ))
+        })?;
+        expand_into(&resolved, included, files, seen, out)?;
+    }
+    Ok(())
+}
+
+/// Every file `path` includes, directly or not, sorted, read without failing:
+/// malformed `#include` lines are skipped and missing files are listed but
+/// not followed. This is what changes to `path` may hinge on even while it
+/// does not build, where `expand_includes` stops at the first error.
+pub(crate) fn scan_includes(path: &str, files: &BTreeMap<String, String>) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    seen.insert(path.to_string());
+    let mut pending = vec![path.to_string()];
+    while let Some(current) = pending.pop() {
+        let Some(text) = files.get(&current) else {
+            continue;
+        };
+        for line in text.lines() {
+            if let Some(Ok(target)) = parse_include(line) {
+                let resolved = resolve_path(&current, target);
+                if seen.insert(resolved.clone()) {
+                    pending.push(resolved);
+                }
+            }
+        }
+    }
+    seen.remove(path);
+    seen.into_iter().collect()
+}
+
+/// `None` if the line is not an include; otherwise the quoted path or an error message.
+fn parse_include(line: &str) -> Option<Result<&str, &'static str>> {
+    let rest = line.trim_start().strip_prefix("#include")?;
+    let rest = rest.trim();
+    Some(
+        rest.strip_prefix('"')
+            .and_then(|r| r.strip_suffix('"'))
+            .filter(|p| !p.is_empty())
+            .ok_or("expected #include \"path\""),
+    )
+}
+
+/// Resolve `target` relative to the directory of `from`, normalizing `.` and `..`.
+pub(crate) fn resolve_path(from: &str, target: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    if let Some(absolute) = target.strip_prefix('/') {
+        return normalize(&mut parts, absolute);
+    }
+    if let Some((dir, _)) = from.rsplit_once('/') {
+        parts.extend(dir.split('/').filter(|p| !p.is_empty() && *p != "."));
+    }
+    normalize(&mut parts, target)
+}
+
+fn normalize<'a>(parts: &mut Vec<&'a str>, path: &'a str) -> String {
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(p, s)| (p.to_string(), s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn paths_resolve_relative_to_includer() {
+        assert_eq!(resolve_path("a/b/c.wgsl", "d.wgsl"), "a/b/d.wgsl");
+        assert_eq!(resolve_path("a/b/c.wgsl", "../d.wgsl"), "a/d.wgsl");
+        assert_eq!(resolve_path("a/b/c.wgsl", "/d.wgsl"), "d.wgsl");
+        assert_eq!(resolve_path("c.wgsl", "./x/d.wgsl"), "x/d.wgsl");
+    }
+
+    #[test]
+    fn transitive_dependencies_are_reported_once() {
+        let files = files(&[
+            (
+                "main.wgsl",
+                "#include \"lib/a.wgsl\"\n#include \"lib/b.wgsl\"\nfn main() {}",
+            ),
+            ("lib/a.wgsl", "#include \"common.wgsl\"\nfn a() {}"),
+            ("lib/b.wgsl", "#include \"common.wgsl\"\nfn b() {}"),
+            ("lib/common.wgsl", "fn common() {}"),
+        ]);
+        let expanded = expand_includes("main.wgsl", &files).unwrap();
+        assert_eq!(
+            expanded.dependencies,
+            vec!["lib/a.wgsl", "lib/b.wgsl", "lib/common.wgsl"]
+        );
+        assert_eq!(expanded.source.matches("fn common()").count(), 1);
+    }
+
+    #[test]
+    fn cycles_terminate() {
+        let files = files(&[
+            ("a.wgsl", "#include \"b.wgsl\"\nfn a() {}"),
+            ("b.wgsl", "#include \"a.wgsl\"\nfn b() {}"),
+        ]);
+        let expanded = expand_includes("a.wgsl", &files).unwrap();
+        assert_eq!(expanded.dependencies, vec!["b.wgsl"]);
+    }
+
+    #[test]
+    fn missing_and_malformed_includes_are_errors() {
+        let files = files(&[
+            ("a.wgsl", "\n#include \"nope.wgsl\""),
+            ("b.wgsl", "#include nope.wgsl"),
+        ]);
+        let missing = expand_includes("a.wgsl", &files).err().unwrap();
+        assert!(missing.message.starts_with("a.wgsl:2:"));
+        assert!(expand_includes("b.wgsl", &files).is_err());
+    }
+
+    #[test]
+    fn scanning_lists_includes_past_errors() {
+        let files = files(&[
+            (
+                "main.wgsl",
+                "#include nope\n#include \"gone.wgsl\"\n#include \"lib/a.wgsl\"",
+            ),
+            (
+                "lib/a.wgsl",
+                "#include \"../main.wgsl\"\n#include \"b.wgsl\"",
+            ),
+            ("lib/b.wgsl", "fn b() {}"),
+        ]);
+        assert!(expand_includes("main.wgsl", &files).is_err());
+        assert_eq!(
+            scan_includes("main.wgsl", &files),
+            vec!["gone.wgsl", "lib/a.wgsl", "lib/b.wgsl"]
+        );
+    }
+}
@@ -0,0 +1,653 @@
+use std::collections::{HashMap, HashSet};
+
+use naga::{Block, Expression, Handle, LocalVariable, Span, Statement};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::rewrite::{self, operands, visit_operands, visit_statement_operands};
+
+// ============================================================================
+// Inlining Types
+// ============================================================================
+
+/// Options object accepted by `inlineFunctions`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct InlineOptions {
+    /// Largest callee inlined, in expressions plus statements.
+    pub max_callee_size: u32,
+    /// Stop inlining into a function once it grows past this size.
+    pub max_caller_size: u32,
+    /// Functions inlined whatever their size.
+    pub force: Vec<String>,
+}
+
+impl Default for InlineOptions {
+    fn default() -> Self {
+        Self {
+            max_callee_size: 32,
+            max_caller_size: 1024,
+            force: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct InlineReport {
+    /// Calls replaced by the callee's body.
+    #[wasm_bindgen(readonly)]
+    pub inlined: Vec<InlinedCall>,
+    /// Calls left in place, with why.
+    #[wasm_bindgen(readonly)]
+    pub skipped: Vec<InlinedCall>,
+    /// Functions dropped because no calls to them remain.
+    #[wasm_bindgen(readonly)]
+    pub removed: Vec<String>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+}
+
+#[wasm_bindgen]
+impl InlineReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Calls from one function to another, grouped.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct InlinedCall {
+    #[wasm_bindgen(readonly)]
+    pub caller: String,
+    #[wasm_bindgen(readonly)]
+    pub callee: String,
+    /// Number of call sites.
+    #[wasm_bindgen(readonly)]
+    pub count: u32,
+    /// Why the calls were skipped; `None` for inlined ones.
+    #[wasm_bindgen(readonly)]
+    pub reason: Option<String>,
+}
+
+#[wasm_bindgen]
+impl InlinedCall {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Inlining Implementation
+// ============================================================================
+//
+// Each function is rebuilt into a fresh expression arena, copying the
+// callee's expressions in place of a call as it goes. Functions are visited
+// in arena order, where callees precede callers, so a callee has already had
+// its own calls inlined. Only callees whose sole `return` ends their body are
+// inlined; an early return would need its control flow restructured. A
+// callee's local variables become the caller's, reset at each inlined call
+// so that a call in a loop still starts from zeroed or initialized locals.
+
+/// A call's callee and, if it was not inlined, why.
+type CallSite = (Handle<naga::Function>, Option<String>);
+
+fn size(function: &naga::Function) -> u32 {
+    let mut statements = 0;
+    rewrite::for_each_statement(&mut function.body.clone(), &mut |_| statements += 1);
+    function.expressions.len() as u32 + statements
+}
+
+/// Whether the only `return` in `function` is its last top-level statement.
+fn single_exit(function: &naga::Function) -> bool {
+    let mut returns = 0;
+    rewrite::for_each_statement(&mut function.body.clone(), &mut |statement| {
+        if matches!(statement, Statement::Return { .. }) {
+            returns += 1;
+        }
+    });
+    match function.body.last() {
+        Some(Statement::Return { .. }) => returns == 1,
+        _ => returns == 0,
+    }
+}
+
+/// One function being copied into the output: the caller itself, or a
+/// callee being inlined into it.
+struct Scope<'a> {
+    source: &'a naga::Function,
+    map: HashMap<Handle<Expression>, Handle<Expression>>,
+    locals: HashMap<Handle<LocalVariable>, Handle<LocalVariable>>,
+}
+
+struct Inliner<'a> {
+    module: &'a naga::Module,
+    options: &'a InlineOptions,
+    out: naga::Function,
+    /// Every call site, in order.
+    calls: Vec<CallSite>,
+}
+
+impl Inliner<'_> {
+    /// The output handle of `h`, copying it and what it reads on first use.
+    fn ensure(&mut self, scope: &mut Scope, h: Handle<Expression>) -> Handle<Expression> {
+        if let Some(&mapped) = scope.map.get(&h) {
+            return mapped;
+        }
+        for operand in operands(&scope.source.expressions[h]) {
+            self.ensure(scope, operand);
+        }
+        self.copy(scope, h)
+    }
+
+    fn copy(&mut self, scope: &mut Scope, h: Handle<Expression>) -> Handle<Expression> {
+        let mut expr = scope.source.expressions[h].clone();
+        if let Expression::LocalVariable(local) = &mut expr {
+            *local = scope.locals[local];
+        }
+        visit_operands(&mut expr, &mut |operand| *operand = scope.map[operand]);
+        let span = scope.source.expressions.get_span(h);
+        let mapped = self.out.expressions.append(expr, span);
+        scope.map.insert(h, mapped);
+        mapped
+    }
+
+    fn block(&mut self, scope: &mut Scope, block: &Block) -> Block {
+        let mut out = Block::new();
+        for (statement, span) in block.span_iter() {
+            self.statement(scope, statement, *span, &mut out);
+        }
+        out
+    }
+
+    fn statement(&mut self, scope: &mut Scope, statement: &Statement, span: Span, out: &mut Block) {
+        match *statement {
+            Statement::Emit(ref range) => {
+                let handles: Vec<_> = range.clone().collect();
+                for &h in &handles {
+                    for operand in operands(&scope.source.expressions[h]) {
+                        if !handles.contains(&operand) {
+                            self.ensure(scope, operand);
+                        }
+                    }
+                }
+                let mut copied = Vec::new();
+                for h in handles {
+                    if !scope.map.contains_key(&h) {
+                        copied.push(self.copy(scope, h));
+                    }
+                }
+                if let (Some(&first), Some(&last)) = (copied.first(), copied.last()) {
+                    out.push(
+                        Statement::Emit(naga::Range::new_from_bounds(first, last)),
+                        span,
+                    );
+                }
+            }
+            Statement::Block(ref block) => {
+                let block = self.block(scope, block);
+                out.push(Statement::Block(block), span);
+            }
+            Statement::If {
+                condition,
+                ref accept,
+                ref reject,
+            } => {
+                let condition = self.ensure(scope, condition);
+                let accept = self.block(scope, accept);
+                let reject = self.block(scope, reject);
+                out.push(
+                    Statement::If {
+                        condition,
+                        accept,
+                        reject,
+                    },
+                    span,
+                );
+            }
+            Statement::Switch {
+                selector,
+                ref cases,
+            } => {
+                let selector = self.ensure(scope, selector);
+                let cases = cases
+                    .iter()
+                    .map(|case| naga::SwitchCase {
+                        body: self.block(scope, &case.body),
+                        ..case.clone()
+                    })
+                    .collect();
+                out.push(Statement::Switch { selector, cases }, span);
+            }
+            Statement::Loop {
+                ref body,
+                ref continuing,
+                break_if,
+            } => {
+                let body = self.block(scope, body);
+                let continuing = self.block(scope, continuing);
+                let break_if = break_if.map(|h| self.ensure(scope, h));
+                out.push(
+                    Statement::Loop {
+                        body,
+                        continuing,
+                        break_if,
+                    },
+                    span,
+                );
+            }
+            Statement::Call {
+                function,
+                ref arguments,
+                result,
+            } => match self.skip_reason(function) {
+                None => {
+                    let arguments: Vec<_> =
+                        arguments.iter().map(|&h| self.ensure(scope, h)).collect();
+                    let value = self.inline(function, arguments, out);
+                    if let (Some(result), Some(value)) = (result, value) {
+                        scope.map.insert(result, value);
+                    }
+                    self.calls.push((function, None));
+                }
+                Some(reason) => {
+                    self.calls.push((function, Some(reason)));
+                    self.copy_statement(scope, statement, span, out);
+                }
+            },
+            _ => self.copy_statement(scope, statement, span, out),
+        }
+    }
+
+    /// A statement without nested blocks, with its handles remapped.
+    fn copy_statement(
+        &mut self,
+        scope: &mut Scope,
+        statement: &Statement,
+        span: Span,
+        out: &mut Block,
+    ) {
+        let mut statement = statement.clone();
+        let mut handles = Vec::new();
+        visit_statement_operands(&mut statement, &mut |h| handles.push(*h));
+        for h in handles {
+            self.ensure(scope, h);
+        }
+        visit_statement_operands(&mut statement, &mut |h| *h = scope.map[h]);
+        out.push(statement, span);
+    }
+
+    fn skip_reason(&self, callee: Handle<naga::Function>) -> Option<String> {
+        let function = &self.module.functions[callee];
+        let forced = function
+            .name
+            .as_ref()
+            .is_some_and(|name| self.options.force.contains(name));
+        let callee_size = size(function);
+        if !single_exit(function) {
+            Some("returns early".to_string())
+        } else if forced {
+            None
+        } else if callee_size > self.options.max_callee_size {
+            Some(format!(
+                "size {} exceeds maxCalleeSize {}",
+                callee_size, self.options.max_callee_size
+            ))
+        } else if self.out.expressions.len() as u32 + callee_size > self.options.max_caller_size {
+            Some(format!(
+                "caller would exceed maxCallerSize {}",
+                self.options.max_caller_size
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Copy `callee`'s body into `out`, returning its result.
+    fn inline(
+        &mut self,
+        callee: Handle<naga::Function>,
+        arguments: Vec<Handle<Expression>>,
+        out: &mut Block,
+    ) -> Option<Handle<Expression>> {
+        let source = &self.module.functions[callee];
+        let mut scope = Scope {
+            source,
+            map: HashMap::new(),
+            locals: HashMap::new(),
+        };
+        for (h, expr) in source.expressions.iter() {
+            if let Expression::FunctionArgument(index) = *expr {
+                scope.map.insert(h, arguments[index as usize]);
+            }
+        }
+
+        // Locals move to the caller and are reset on every call.
+        for (handle, local) in source.local_variables.iter() {
+            let init = local.init.map(|h| self.ensure(&mut scope, h));
+            let moved = self.out.local_variables.append(
+                LocalVariable {
+                    init: None,
+                    ..local.clone()
+                },
+                source.local_variables.get_span(handle),
+            );
+            scope.locals.insert(handle, moved);
+            let pointer = self
+                .out
+                .expressions
+                .append(Expression::LocalVariable(moved), Span::UNDEFINED);
+            let value = init.unwrap_or_else(|| {
+                self.out
+                    .expressions
+                    .append(Expression::ZeroValue(local.ty), Span::UNDEFINED)
+            });
+            out.push(Statement::Store { pointer, value }, Span::UNDEFINED);
+        }
+
+        let mut value = None;
+        for (statement, span) in source.body.span_iter() {
+            match *statement {
+                Statement::Return { value: returned } => {
+                    value = returned.map(|h| self.ensure(&mut scope, h));
+                }
+                _ => self.statement(&mut scope, statement, *span, out),
+            }
+        }
+        // Argument names would land on whatever the caller passed.
+        for (h, name) in source.named_expressions.iter() {
+            if matches!(source.expressions[*h], Expression::FunctionArgument(_)) {
+                continue;
+            }
+            if let Some(&mapped) = scope.map.get(h) {
+                self.out
+                    .named_expressions
+                    .entry(mapped)
+                    .or_insert_with(|| name.clone());
+            }
+        }
+        value
+    }
+}
+
+/// Rebuild `function` with calls inlined, returning it with its call sites.
+fn inline_into(
+    module: &naga::Module,
+    options: &InlineOptions,
+    function: &naga::Function,
+) -> (naga::Function, Vec<CallSite>) {
+    let mut inliner = Inliner {
+        module,
+        options,
+        out: naga::Function {
+            name: function.name.clone(),
+            arguments: function.arguments.clone(),
+            result: function.result.clone(),
+            local_variables: function.local_variables.clone(),
+            diagnostic_filter_leaf: function.diagnostic_filter_leaf,
+            ..Default::default()
+        },
+        calls: Vec::new(),
+    };
+    let mut scope = Scope {
+        source: function,
+        map: HashMap::new(),
+        locals: function
+            .local_variables
+            .iter()
+            .map(|(h, _)| (h, h))
+            .collect(),
+    };
+    let inits: Vec<_> = function
+        .local_variables
+        .iter()
+        .filter_map(|(h, local)| Some((h, local.init?)))
+        .collect();
+    for (local, init) in inits {
+        let mapped = inliner.ensure(&mut scope, init);
+        inliner.out.local_variables.get_mut(local).init = Some(mapped);
+    }
+    inliner.out.body = inliner.block(&mut scope, &function.body);
+    for (h, name) in function.named_expressions.iter() {
+        if let Some(&mapped) = scope.map.get(h) {
+            inliner.out.named_expressions.insert(mapped, name.clone());
+        }
+    }
+    (inliner.out, inliner.calls)
+}
+
+fn called_functions(module: &mut naga::Module) -> HashSet<Handle<naga::Function>> {
+    let mut called = HashSet::new();
+    for function in rewrite::functions_mut(module) {
+        rewrite::for_each_statement(&mut function.body, &mut |statement| {
+            if let Statement::Call { function, .. } = statement {
+                called.insert(*function);
+            }
+        });
+    }
+    called
+}
+
+fn function_name(module: &naga::Module, h: Handle<naga::Function>) -> String {
+    module.functions[h]
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("function{}", h.index()))
+}
+
+/// Inline calls throughout a validated `module`, dropping functions whose
+/// every call was inlined. The report's `wgsl` is left empty.
+pub(crate) fn inline_calls(
+    module: &mut naga::Module,
+    options: &InlineOptions,
+) -> Result<InlineReport, Diagnostic> {
+    for name in &options.force {
+        if !module
+            .functions
+            .iter()
+            .any(|(_, f)| f.name.as_deref() == Some(name.as_str()))
+        {
+            return Err(Diagnostic::error(format!("Function '{}' not found", name)));
+        }
+    }
+    let called_before = called_functions(module);
+
+    let mut sites: Vec<(String, Handle<naga::Function>, Option<String>)> = Vec::new();
+    let handles: Vec<_> = module.functions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let caller = function_name(module, h);
+        let (function, calls) = inline_into(module, options, &module.functions[h]);
+        module.functions[h] = function;
+        sites.extend(
+            calls
+                .into_iter()
+                .map(|(f, reason)| (caller.clone(), f, reason)),
+        );
+    }
+    for index in 0..module.entry_points.len() {
+        let ep = &module.entry_points[index];
+        let caller = ep.name.clone();
+        let (function, calls) = inline_into(module, options, &ep.function);
+        module.entry_points[index].function = function;
+        sites.extend(
+            calls
+                .into_iter()
+                .map(|(f, reason)| (caller.clone(), f, reason)),
+        );
+    }
+
+    let mut inlined: Vec<InlinedCall> = Vec::new();
+    let mut skipped: Vec<InlinedCall> = Vec::new();
+    for (caller, callee, reason) in sites {
+        let callee = function_name(module, callee);
+        let list = if reason.is_some() {
+            &mut skipped
+        } else {
+            &mut inlined
+        };
+        match list
+            .iter_mut()
+            .find(|c| c.caller == caller && c.callee == callee && c.reason == reason)
+        {
+            Some(call) => call.count += 1,
+            None => list.push(InlinedCall {
+                caller,
+                callee,
+                count: 1,
+                reason,
+            }),
+        }
+    }
+
+    // Functions nobody called to begin with are left alone.
+    let called_after = called_functions(module);
+    let mut removed = Vec::new();
+    rewrite::retain_functions(module, |h, function| {
+        let keep = called_after.contains(&h) || !called_before.contains(&h);
+        if !keep {
+            removed.push(function.name.clone().unwrap_or_default());
+        }
+        keep
+    });
+
+    Ok(InlineReport {
+        inlined,
+        skipped,
+        removed,
+        wgsl: String::new(),
+    })
+}
+
+/// `inline_calls`, then the result as WGSL.
+pub(crate) fn inline_functions(
+    module: &mut naga::Module,
+    options: &InlineOptions,
+) -> Result<InlineReport, Diagnostic> {
+    let mut report = inline_calls(module, options)?;
+    let info = crate::validate_module(module)?;
+    report.wgsl =
+        naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+    Ok(report)
+}
+
+/// Inlines function calls, for drivers (some MSL and GLES compilers) that
+/// optimize pre-inlined shaders better. A call is inlined if its callee ends
+/// in its only `return`, and is either listed in `force` or no larger than
+/// `maxCalleeSize` (expressions plus statements, default 32) while the
+/// caller stays within `maxCallerSize` (default 1024). Functions left with
+/// no calls are removed. `options` is `{ maxCalleeSize?, maxCallerSize?,
+/// force?: string[] }`. Returns the WGSL and what was inlined or skipped.
+#[wasm_bindgen(js_name = inlineFunctions)]
+pub fn inline_functions_wgsl(wgsl: &str, options: JsValue) -> Result<InlineReport, JsValue> {
+    let options: Option<InlineOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid inline options: {e}")))?;
+    let (mut module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    inline_functions(&mut module, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        fn luma(c: vec3<f32>) -> f32 {
+            return dot(c, vec3<f32>(0.299, 0.587, 0.114));
+        }
+
+        fn tonemap(c: vec3<f32>) -> vec3<f32> {
+            var acc: f32;
+            acc += luma(c);
+            let l = acc;
+            return c / (1.0 + l);
+        }
+
+        fn clamped(x: f32) -> f32 {
+            if x < 0.0 { return 0.0; }
+            return x;
+        }
+
+        @fragment
+        fn fs(@location(0) color: vec3<f32>) -> @location(0) vec4<f32> {
+            var total = vec3<f32>(0.0);
+            for (var i = 0; i < 3; i++) {
+                total += tonemap(color);
+            }
+            return vec4<f32>(total, clamped(luma(color)));
+        }
+    "#;
+
+    fn run(options: InlineOptions) -> InlineReport {
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        inline_functions(&mut module, &options).unwrap()
+    }
+
+    #[test]
+    fn single_exit_callees_are_inlined() {
+        let report = run(InlineOptions::default());
+        let pairs: Vec<_> = report
+            .inlined
+            .iter()
+            .map(|c| (c.caller.as_str(), c.callee.as_str(), c.count))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("tonemap", "luma", 1),
+                ("fs", "tonemap", 1),
+                ("fs", "luma", 1)
+            ]
+        );
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].callee, "clamped");
+        assert_eq!(report.skipped[0].reason.as_deref(), Some("returns early"));
+        assert_eq!(report.removed, ["luma", "tonemap"]);
+
+        assert!(!report.wgsl.contains("fn luma"));
+        assert!(report.wgsl.contains("fn clamped"));
+        // The inlined local is reset inside the loop.
+        let module = crate::parse_wgsl(&report.wgsl).unwrap();
+        assert_eq!(module.entry_points[0].function.local_variables.len(), 3);
+    }
+
+    #[test]
+    fn size_budgets_and_forced_callees() {
+        let report = run(InlineOptions {
+            max_callee_size: 8,
+            ..Default::default()
+        });
+        assert!(report.inlined.iter().all(|c| c.callee == "luma"));
+        let tonemap = report
+            .skipped
+            .iter()
+            .find(|c| c.callee == "tonemap")
+            .unwrap();
+        assert!(tonemap.reason.as_ref().unwrap().contains("maxCalleeSize 8"));
+
+        let forced = run(InlineOptions {
+            max_callee_size: 0,
+            force: vec!["tonemap".to_string()],
+            ..Default::default()
+        });
+        let callees: Vec<_> = forced.inlined.iter().map(|c| c.callee.as_str()).collect();
+        assert_eq!(callees, ["tonemap"]);
+
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        let missing = InlineOptions {
+            force: vec!["nope".to_string()],
+            ..Default::default()
+        };
+        assert!(inline_functions(&mut module, &missing).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::Target;
+use crate::diagnostics::throw;
+use crate::hash::sha256_hex;
+
+// ============================================================================
+// Integrity Types
+// ============================================================================
+
+/// The manifest fields an artifact is checked against; a whole
+/// `ManifestEntry` works too.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExpectedArtifact {
+    pub artifact_hash: String,
+    #[serde(default)]
+    pub byte_size: Option<u32>,
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+/// An artifact hash, or a manifest entry.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum Expected {
+    Hash(String),
+    Entry(ExpectedArtifact),
+}
+
+/// What `verifyArtifact` found.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ArtifactVerification {
+    /// `mismatches` is empty.
+    #[wasm_bindgen(readonly)]
+    pub valid: bool,
+    /// SHA-256 of the bytes, lowercase hex.
+    #[wasm_bindgen(readonly)]
+    pub artifact_hash: String,
+    #[wasm_bindgen(readonly)]
+    pub byte_size: u32,
+    /// `"spirv"` or `"msl"` if the bytes look like one, else unset.
+    #[wasm_bindgen(readonly)]
+    pub target: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub mismatches: Vec<ArtifactMismatch>,
+}
+
+#[wasm_bindgen]
+impl ArtifactVerification {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ArtifactMismatch {
+    /// `"artifactHash"`, `"byteSize"` or `"target"`.
+    #[wasm_bindgen(readonly)]
+    pub field: String,
+    #[wasm_bindgen(readonly)]
+    pub expected: String,
+    /// Unset for a `target` the bytes do not look like any of.
+    #[wasm_bindgen(readonly)]
+    pub actual: Option<String>,
+}
+
+#[wasm_bindgen]
+impl ArtifactMismatch {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Integrity Implementation
+// ============================================================================
+//
+// The hash is the check that matters: any change to the bytes changes it,
+// and it is what the manifest records as `artifactHash`. Size and target
+// are checked too whenever the manifest entry has them, so a report says a
+// truncated download was truncated, or that a Metal library was fetched
+// where SPIR-V was expected, rather than only that the hash differs. The
+// target is recognized from the bytes: SPIR-V by its magic number, MSL as
+// UTF-8 text including `metal_stdlib`.
+
+/// The target `bytes` look like they were compiled for.
+fn detect_target(bytes: &[u8]) -> Option<Target> {
+    if bytes.len().is_multiple_of(4)
+        && bytes.len() >= 4
+        && u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == crate::spv::MAGIC
+    {
+        return Some(Target::Spirv);
+    }
+    std::str::from_utf8(bytes)
+        .ok()
+        .filter(|text| text.contains("#include <metal_stdlib>"))
+        .map(|_| Target::Msl)
+}
+
+/// `hash` as lowercase hex, if it is a SHA-256 hash.
+fn normalize_hash(hash: &str) -> Result<String, Diagnostic> {
+    let hex = hash.trim();
+    let hex = hex.strip_prefix("sha256:").unwrap_or(hex);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Diagnostic::error(format!(
+            "Invalid artifact hash '{hash}'; expected 64 hex digits"
+        )));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+pub(crate) fn verify(
+    bytes: &[u8],
+    expected: &Expected,
+) -> Result<ArtifactVerification, Diagnostic> {
+    let (hash, byte_size, target) = match expected {
+        Expected::Hash(hash) => (hash, None, None),
+        Expected::Entry(entry) => (
+            &entry.artifact_hash,
+            entry.byte_size,
+            entry.target.as_deref(),
+        ),
+    };
+    let expected_hash = normalize_hash(hash)?;
+    let target = target
+        .map(|name| {
+            Target::parse(name)
+                .ok_or_else(|| Diagnostic::error(format!("Unknown artifact target '{name}'")))
+        })
+        .transpose()?;
+
+    let actual_hash = sha256_hex(bytes);
+    let actual_target = detect_target(bytes);
+    let mut mismatches = Vec::new();
+    if actual_hash != expected_hash {
+        mismatches.push(ArtifactMismatch {
+            field: "artifactHash".to_string(),
+            expected: expected_hash,
+            actual: Some(actual_hash.clone()),
+        });
+    }
+    if let Some(size) = byte_size
+        && size as usize != bytes.len()
+    {
+        mismatches.push(ArtifactMismatch {
+            field: "byteSize".to_string(),
+            expected: size.to_string(),
+            actual: Some(bytes.len().to_string()),
+        });
+    }
+    if let Some(target) = target
+        && actual_target != Some(target)
+    {
+        mismatches.push(ArtifactMismatch {
+            field: "target".to_string(),
+            expected: target.as_str().to_string(),
+            actual: actual_target.map(|t| t.as_str().to_string()),
+        });
+    }
+    Ok(ArtifactVerification {
+        valid: mismatches.is_empty(),
+        artifact_hash: actual_hash,
+        byte_size: bytes.len() as u32,
+        target: actual_target.map(|t| t.as_str().to_string()),
+        mismatches,
+    })
+}
+
+/// Checks downloaded artifact `bytes` before they reach the driver, against
+/// either the expected SHA-256 hex (optionally `sha256:`-prefixed) or a
+/// manifest entry from `compileBatch`, whose `artifactHash`, `byteSize` and
+/// `target` are all compared. Mismatches come back in the result, with
+/// `valid` unset; throws only for a malformed hash or unknown target.
+#[wasm_bindgen(js_name = verifyArtifact)]
+pub fn verify_artifact(bytes: &[u8], expected: JsValue) -> Result<ArtifactVerification, JsValue> {
+    let expected: Expected = serde_wasm_bindgen::from_value(expected).map_err(|e| {
+        JsValue::from_str(&format!(
+            "Invalid expected artifact: {e}; pass a hash or a manifest entry"
+        ))
+    })?;
+    verify(bytes, &expected).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "@compute @workgroup_size(1) fn main() {}";
+
+    fn entry(hash: &str, byte_size: usize, target: &str) -> Expected {
+        Expected::Entry(ExpectedArtifact {
+            artifact_hash: hash.to_string(),
+            byte_size: Some(byte_size as u32),
+            target: Some(target.to_string()),
+        })
+    }
+
+    #[test]
+    fn intact_artifacts_verify() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let hash = sha256_hex(&bytes);
+        let result = verify(&bytes, &entry(&hash, bytes.len(), "spirv")).unwrap();
+        assert!(result.valid, "{:?}", result.mismatches);
+        assert_eq!(result.target.as_deref(), Some("spirv"));
+
+        let upper = format!("sha256:{}", hash.to_uppercase());
+        assert!(verify(&bytes, &Expected::Hash(upper)).unwrap().valid);
+
+        let msl = crate::compile_msl_with(SHADER, None, None).unwrap();
+        assert_eq!(detect_target(msl.as_bytes()), Some(Target::Msl));
+
+        let error = verify(&bytes, &Expected::Hash("abc".to_string()))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.message,
+            "Invalid artifact hash 'abc'; expected 64 hex digits"
+        );
+    }
+
+    #[test]
+    fn damaged_artifacts_report_each_mismatch() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let expected = entry(&sha256_hex(&bytes), bytes.len(), "spirv");
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        let result = verify(&flipped, &expected).unwrap();
+        assert!(!result.valid);
+        let fields: Vec<_> = result.mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, ["artifactHash"]);
+
+        let truncated = &bytes[..bytes.len() - 2];
+        let result = verify(truncated, &expected).unwrap();
+        let fields: Vec<_> = result.mismatches.iter().map(|m| m.field.as_str()).collect();
+        assert_eq!(fields, ["artifactHash", "byteSize", "target"]);
+        assert_eq!(result.mismatches[2].actual, None);
+    }
+}
@@ -0,0 +1,708 @@
+use naga::proc::Layouter;
+use naga::{ArraySize, Handle, Type, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::lexer::{self, TokenKind};
+
+// ============================================================================
+// Layout Diagram Types
+// ============================================================================
+
+/// A struct's memory layout as a grid of 16-byte rows, for rendering.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LayoutDiagram {
+    #[wasm_bindgen(readonly)]
+    pub type_name: String,
+    /// Size in bytes, trailing padding included.
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
+    #[wasm_bindgen(readonly)]
+    pub row_bytes: u32,
+    #[wasm_bindgen(readonly)]
+    pub rows: Vec<LayoutRow>,
+}
+
+#[wasm_bindgen]
+impl LayoutDiagram {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LayoutRow {
+    /// Byte offset of the row's first byte.
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    /// Left to right, covering the row exactly; the last row may be shorter.
+    #[wasm_bindgen(readonly)]
+    pub spans: Vec<LayoutSpan>,
+}
+
+#[wasm_bindgen]
+impl LayoutRow {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LayoutSpan {
+    /// `"member"` or `"padding"`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    /// Member path, e.g. `light.color` or `weights[2]`; `None` for padding.
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub type_name: Option<String>,
+    /// Byte offset of the span from the start of the struct.
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    /// `#rrggbb`. A member split across rows keeps its color.
+    #[wasm_bindgen(readonly)]
+    pub color: String,
+    /// Whether the span continues a member from the previous row.
+    #[wasm_bindgen(readonly)]
+    pub continued: bool,
+}
+
+#[wasm_bindgen]
+impl LayoutSpan {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A struct's layout as a tree, from `getStructLayout`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct StructLayout {
+    /// The struct the requested name resolved to, through any aliases.
+    #[wasm_bindgen(readonly)]
+    pub type_name: String,
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
+    /// One node per member.
+    #[wasm_bindgen(readonly)]
+    pub members: Vec<LayoutNode>,
+}
+
+#[wasm_bindgen]
+impl StructLayout {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LayoutNode {
+    /// Member path from the struct, e.g. `light.color` or `weights[2]`.
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    #[wasm_bindgen(readonly)]
+    pub type_name: Option<String>,
+    /// `"struct"`, `"array"` or `"value"`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    /// Byte offset from the start of the outermost struct.
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
+    /// Set for arrays.
+    #[wasm_bindgen(readonly)]
+    pub stride: Option<u32>,
+    /// Element count of arrays; `None` for runtime-sized ones.
+    #[wasm_bindgen(readonly)]
+    pub count: Option<u32>,
+    /// Members of a struct, or elements of an array.
+    #[wasm_bindgen(readonly)]
+    pub children: Vec<LayoutNode>,
+}
+
+#[wasm_bindgen]
+impl LayoutNode {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Layout Diagram Implementation
+// ============================================================================
+//
+// Nested structs are flattened into dotted paths, and arrays into their
+// elements so padding inside strides shows up. Past `MAX_ELEMENTS`, the rest
+// of an array is one span. Runtime-sized arrays show a single element.
+
+/// Sizes, alignments and strides of every type in `module`.
+pub(crate) fn layouter(module: &naga::Module) -> Result<Layouter, Diagnostic> {
+    let mut layouter = Layouter::default();
+    layouter
+        .update(module.to_ctx())
+        .map_err(|e| Diagnostic::error(format!("Layout error: {e}")))?;
+    Ok(layouter)
+}
+
+const ROW_BYTES: u32 = 16;
+const MAX_ELEMENTS: u32 = 16;
+const PADDING_COLOR: &str = "#d0d0d0";
+const PALETTE: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f",
+];
+
+/// One leaf value: `(offset, size, name, type)`.
+type Leaf = (u32, u32, String, Option<String>);
+
+fn leaves(
+    module: &naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+    offset: u32,
+    name: String,
+    out: &mut Vec<Leaf>,
+) {
+    match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => {
+            for member in members {
+                let member_name = member.name.clone().unwrap_or_else(|| "_".to_string());
+                let path = if name.is_empty() {
+                    member_name
+                } else {
+                    format!("{name}.{member_name}")
+                };
+                leaves(
+                    module,
+                    layouter,
+                    member.ty,
+                    offset + member.offset,
+                    path,
+                    out,
+                );
+            }
+        }
+        TypeInner::Array { base, size, stride } => {
+            let count = match size {
+                ArraySize::Constant(count) => count.get(),
+                _ => 1,
+            };
+            for index in 0..count.min(MAX_ELEMENTS) {
+                let at = offset + index * stride;
+                leaves(module, layouter, base, at, format!("{name}[{index}]"), out);
+            }
+            if count > MAX_ELEMENTS {
+                out.push((
+                    offset + MAX_ELEMENTS * stride,
+                    (count - MAX_ELEMENTS) * stride,
+                    format!("{name}[{MAX_ELEMENTS}..{count}]"),
+                    crate::get_type_name(module, base),
+                ));
+            }
+        }
+        _ => out.push((
+            offset,
+            layouter[ty].size,
+            name,
+            crate::get_type_name(module, ty),
+        )),
+    }
+}
+
+pub(crate) fn layout_diagram(
+    module: &naga::Module,
+    type_name: &str,
+) -> Result<LayoutDiagram, Diagnostic> {
+    let (ty, _) = module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(type_name))
+        .ok_or_else(|| Diagnostic::error(format!("Type '{}' not found", type_name)))?;
+    if !matches!(module.types[ty].inner, TypeInner::Struct { .. }) {
+        return Err(Diagnostic::error(format!(
+            "Type '{}' is not a struct",
+            type_name
+        )));
+    }
+    let layouter = layouter(module)?;
+
+    let mut found = Vec::new();
+    leaves(module, &layouter, ty, 0, String::new(), &mut found);
+    found.sort_by_key(|leaf| leaf.0);
+    let size = found
+        .iter()
+        .map(|leaf| leaf.0 + leaf.1)
+        .max()
+        .unwrap_or(0)
+        .max(layouter[ty].size);
+
+    // Whole spans first, padding filling the gaps between them.
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    for (index, (offset, span_size, name, ty)) in found.into_iter().enumerate() {
+        if offset > cursor {
+            spans.push(padding(cursor, offset - cursor));
+        }
+        spans.push(LayoutSpan {
+            kind: "member".to_string(),
+            name: Some(name),
+            type_name: ty,
+            offset,
+            size: span_size,
+            color: PALETTE[index % PALETTE.len()].to_string(),
+            continued: false,
+        });
+        cursor = offset + span_size;
+    }
+    if size > cursor {
+        spans.push(padding(cursor, size - cursor));
+    }
+
+    // Then cut at row boundaries.
+    let mut rows: Vec<LayoutRow> = (0..size.div_ceil(ROW_BYTES))
+        .map(|row| LayoutRow {
+            offset: row * ROW_BYTES,
+            spans: Vec::new(),
+        })
+        .collect();
+    for span in spans {
+        let mut start = span.offset;
+        let end = span.offset + span.size;
+        while start < end {
+            let row_end = (start / ROW_BYTES + 1) * ROW_BYTES;
+            let piece_end = end.min(row_end);
+            rows[(start / ROW_BYTES) as usize].spans.push(LayoutSpan {
+                offset: start,
+                size: piece_end - start,
+                continued: start != span.offset,
+                ..span.clone()
+            });
+            start = piece_end;
+        }
+    }
+
+    Ok(LayoutDiagram {
+        type_name: type_name.to_string(),
+        size,
+        alignment: layouter[ty].alignment.round_up(1),
+        row_bytes: ROW_BYTES,
+        rows,
+    })
+}
+
+fn padding(offset: u32, size: u32) -> LayoutSpan {
+    LayoutSpan {
+        kind: "padding".to_string(),
+        name: None,
+        type_name: None,
+        offset,
+        size,
+        color: PADDING_COLOR.to_string(),
+        continued: false,
+    }
+}
+
+/// Byte-grid model of the struct `typeName`'s storage layout: rows of 16
+/// bytes, each split into colored member and padding spans. Nested structs
+/// and array elements are broken out, so padding inside them is visible.
+#[wasm_bindgen(js_name = structLayoutDiagram)]
+pub fn struct_layout_diagram(wgsl: &str, type_name: &str) -> Result<LayoutDiagram, JsValue> {
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    layout_diagram(&module, type_name).map_err(throw)
+}
+
+// ============================================================================
+// Struct Layout Implementation
+// ============================================================================
+//
+// naga resolves aliases while lowering and keeps only the struct's own name,
+// so the alias chain is followed in the source. Arrays list every element up
+// to `MAX_TREE_ELEMENTS`, and runtime-sized arrays one; `stride` gives the
+// offsets of the rest.
+
+const MAX_TREE_ELEMENTS: u32 = 256;
+
+/// The name `name` is an alias chain for, following `alias A = B;`
+/// declarations.
+fn resolve_alias(source: &str, name: &str) -> Result<String, Diagnostic> {
+    let tokens = lexer::tokenize(source);
+    let mut name = name.to_string();
+    let mut seen = vec![name.clone()];
+    loop {
+        let target = lexer::module_declarations(&tokens)
+            .into_iter()
+            .find(|&(declared, at)| declared == name && at > 0 && tokens[at - 1].is_ident("alias"))
+            .and_then(|(_, at)| match tokens.get(at + 1..at + 4) {
+                Some([eq, target, semi])
+                    if eq.is_punct('=')
+                        && target.kind == TokenKind::Ident
+                        && semi.is_punct(';') =>
+                {
+                    Some(target.text.to_string())
+                }
+                _ => None,
+            });
+        let Some(target) = target else {
+            return Ok(name);
+        };
+        if seen.contains(&target) {
+            return Err(Diagnostic::error(format!(
+                "Alias '{}' refers to itself",
+                seen[0]
+            )));
+        }
+        seen.push(target.clone());
+        name = target;
+    }
+}
+
+fn layout_node(
+    module: &naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+    offset: u32,
+    path: String,
+) -> LayoutNode {
+    let mut node = LayoutNode {
+        path: path.clone(),
+        type_name: crate::get_type_name(module, ty),
+        kind: "value".to_string(),
+        offset,
+        size: layouter[ty].size,
+        alignment: layouter[ty].alignment.round_up(1),
+        stride: None,
+        count: None,
+        children: Vec::new(),
+    };
+    match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => {
+            node.kind = "struct".to_string();
+            node.children = members
+                .iter()
+                .map(|member| {
+                    let name = member.name.clone().unwrap_or_else(|| "_".to_string());
+                    let path = if path.is_empty() {
+                        name
+                    } else {
+                        format!("{path}.{name}")
+                    };
+                    layout_node(module, layouter, member.ty, offset + member.offset, path)
+                })
+                .collect();
+        }
+        TypeInner::Array { base, size, stride } => {
+            let count = match size {
+                ArraySize::Constant(count) => Some(count.get()),
+                _ => None,
+            };
+            node.kind = "array".to_string();
+            node.stride = Some(stride);
+            node.count = count;
+            node.children = (0..count.unwrap_or(1).min(MAX_TREE_ELEMENTS))
+                .map(|index| {
+                    let at = offset + index * stride;
+                    layout_node(module, layouter, base, at, format!("{path}[{index}]"))
+                })
+                .collect();
+        }
+        _ => {}
+    }
+    node
+}
+
+/// The struct `name` in `module` names, through any aliases, with its name.
+pub(crate) fn find_struct(
+    source: &str,
+    module: &naga::Module,
+    name: &str,
+) -> Result<(Handle<Type>, String), Diagnostic> {
+    let resolved = resolve_alias(source, name)?;
+    let (ty, _) = module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(resolved.as_str()))
+        .ok_or_else(|| Diagnostic::error(format!("Type '{}' not found", name)))?;
+    if !matches!(module.types[ty].inner, TypeInner::Struct { .. }) {
+        return Err(Diagnostic::error(format!(
+            "Type '{}' is not a struct",
+            name
+        )));
+    }
+    Ok((ty, resolved))
+}
+
+pub(crate) fn struct_layout(
+    source: &str,
+    module: &naga::Module,
+    name: &str,
+) -> Result<StructLayout, Diagnostic> {
+    let (ty, resolved) = find_struct(source, module, name)?;
+    let layouter = layouter(module)?;
+    let root = layout_node(module, &layouter, ty, 0, String::new());
+    Ok(StructLayout {
+        type_name: resolved,
+        size: root.size,
+        alignment: root.alignment,
+        members: root.children,
+    })
+}
+
+/// Layout of the struct `structName`, or the struct an alias chain starting
+/// there names, as a tree: every member, nested struct member and array
+/// element with its absolute byte offset, size and alignment.
+#[wasm_bindgen(js_name = getStructLayout)]
+pub fn get_struct_layout(wgsl: &str, struct_name: &str) -> Result<StructLayout, JsValue> {
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    struct_layout(wgsl, &module, struct_name).map_err(throw)
+}
+
+// ============================================================================
+// Padded Struct Implementation
+// ============================================================================
+//
+// For generators of C-layout mirrors, where every byte is a named field:
+// members in order with explicit padding wherever the next member's offset
+// or the struct's size leaves a gap. A runtime-sized array cannot be a
+// field, so the struct ends at its offset and the array comes back apart.
+
+pub(crate) enum PaddedField<'a> {
+    Member(&'a naga::StructMember),
+    /// Bytes of padding.
+    Padding(u32),
+}
+
+pub(crate) struct PaddedStruct<'a> {
+    pub fields: Vec<PaddedField<'a>>,
+    /// Size of the fields together, the runtime-sized array left out.
+    pub size: u32,
+    /// The trailing runtime-sized array, if any.
+    pub tail: Option<&'a naga::StructMember>,
+}
+
+pub(crate) fn padded_struct<'a>(
+    module: &'a naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+) -> PaddedStruct<'a> {
+    let mut padded = PaddedStruct {
+        fields: Vec::new(),
+        size: layouter[ty].size,
+        tail: None,
+    };
+    let TypeInner::Struct { ref members, .. } = module.types[ty].inner else {
+        return padded;
+    };
+    let mut cursor = 0;
+    for member in members {
+        if let TypeInner::Array {
+            size: ArraySize::Dynamic,
+            ..
+        } = module.types[member.ty].inner
+        {
+            padded.size = member.offset;
+            padded.tail = Some(member);
+            break;
+        }
+        if member.offset > cursor {
+            padded
+                .fields
+                .push(PaddedField::Padding(member.offset - cursor));
+        }
+        padded.fields.push(PaddedField::Member(member));
+        cursor = member.offset + layouter[member.ty].size;
+    }
+    if padded.size > cursor {
+        padded
+            .fields
+            .push(PaddedField::Padding(padded.size - cursor));
+    }
+    padded
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Scene {
+            time: f32,
+            light: Light,
+            weights: array<vec3<f32>, 2>,
+            count: u32,
+        }
+    "#;
+
+    fn labels(row: &LayoutRow) -> Vec<(Option<&str>, u32)> {
+        row.spans
+            .iter()
+            .map(|s| (s.name.as_deref(), s.size))
+            .collect()
+    }
+
+    #[test]
+    fn rows_split_members_and_padding() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let diagram = layout_diagram(&module, "Scene").unwrap();
+        assert_eq!(diagram.size, 80);
+        assert_eq!(diagram.alignment, 16);
+        assert_eq!(diagram.rows.len(), 5);
+
+        assert_eq!(
+            labels(&diagram.rows[0]),
+            vec![(Some("time"), 4), (None, 12)]
+        );
+        assert_eq!(
+            labels(&diagram.rows[1]),
+            vec![(Some("light.color"), 12), (Some("light.intensity"), 4)]
+        );
+        assert_eq!(
+            labels(&diagram.rows[2]),
+            vec![(Some("weights[0]"), 12), (None, 4)]
+        );
+        assert_eq!(
+            labels(&diagram.rows[4]),
+            vec![(Some("count"), 4), (None, 12)]
+        );
+        assert_eq!(diagram.rows[0].spans[1].kind, "padding");
+        assert_ne!(
+            diagram.rows[1].spans[0].color,
+            diagram.rows[1].spans[1].color
+        );
+    }
+
+    #[test]
+    fn long_members_continue_across_rows() {
+        let source = "struct M { a: f32, m: mat2x2<f32>, big: array<u32, 40> }";
+        let module = crate::parse_wgsl(source).unwrap();
+        let diagram = layout_diagram(&module, "M").unwrap();
+        // `m` is 8-aligned and 16 bytes, so it straddles the first row.
+        let m: Vec<_> = diagram
+            .rows
+            .iter()
+            .flat_map(|row| &row.spans)
+            .filter(|s| s.name.as_deref() == Some("m"))
+            .collect();
+        assert_eq!(m.len(), 2);
+        assert!(!m[0].continued && m[1].continued);
+        assert_eq!(m[0].color, m[1].color);
+
+        let rest = diagram
+            .rows
+            .iter()
+            .flat_map(|row| &row.spans)
+            .find(|s| s.name.as_deref() == Some("big[16..40]"))
+            .unwrap();
+        assert_eq!(rest.offset, 24 + 16 * 4);
+
+        assert!(layout_diagram(&module, "Nope").is_err());
+    }
+
+    #[test]
+    fn reflection_reports_sizes_and_strides() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let reflection = crate::reflect_module(&module, &info);
+        let scene = reflection.types.iter().find(|t| t.name == "Scene").unwrap();
+        assert_eq!((scene.size, scene.alignment), (80, 16));
+        let members: Vec<_> = scene
+            .members
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|m| {
+                (
+                    m.name.as_str(),
+                    m.offset,
+                    m.size,
+                    m.alignment,
+                    m.array_stride,
+                )
+            })
+            .collect();
+        assert_eq!(
+            members,
+            vec![
+                ("time", 0, 4, 4, None),
+                ("light", 16, 16, 16, None),
+                ("weights", 32, 32, 16, Some(16)),
+                ("count", 64, 4, 4, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn padded_structs_fill_gaps_and_stop_at_runtime_arrays() {
+        let source = "struct P { a: f32, b: vec3<f32>, c: u32, rest: array<vec4<f32>> }";
+        let module = crate::parse_wgsl(source).unwrap();
+        let layouter = layouter(&module).unwrap();
+        let (ty, _) = module
+            .types
+            .iter()
+            .find(|(_, t)| t.name.as_deref() == Some("P"))
+            .unwrap();
+        let padded = padded_struct(&module, &layouter, ty);
+        let fields: Vec<_> = padded
+            .fields
+            .iter()
+            .map(|f| match f {
+                PaddedField::Member(m) => m.name.clone().unwrap(),
+                PaddedField::Padding(bytes) => format!("pad {bytes}"),
+            })
+            .collect();
+        assert_eq!(fields, vec!["a", "pad 12", "b", "c"]);
+        assert_eq!(padded.size, 32);
+        assert_eq!(padded.tail.and_then(|m| m.name.as_deref()), Some("rest"));
+    }
+
+    #[test]
+    fn struct_layouts_follow_aliases() {
+        let source = format!("{SHADER}\nalias SceneData = Scene;\nalias Uniforms = SceneData;");
+        let module = crate::parse_wgsl(&source).unwrap();
+        let layout = struct_layout(&source, &module, "Uniforms").unwrap();
+        assert_eq!(layout.type_name, "Scene");
+        assert_eq!((layout.size, layout.alignment), (80, 16));
+
+        let light = &layout.members[1];
+        assert_eq!(light.kind, "struct");
+        assert_eq!(light.children[1].path, "light.intensity");
+        assert_eq!(light.children[1].offset, 28);
+        let weights = &layout.members[2];
+        assert_eq!((weights.stride, weights.count), (Some(16), Some(2)));
+        assert_eq!(weights.children[1].path, "weights[1]");
+        assert_eq!(weights.children[1].offset, 48);
+
+        assert!(struct_layout(&source, &module, "Missing").is_err());
+        let looped = "alias A = B;\nalias B = A;";
+        assert!(resolve_alias(looped, "A").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::DetailedDiagnostic;
+use crate::lexer::{Token, TokenKind, tokenize};
+use crate::rename::{TextEdit, text_edit};
+
+// ============================================================================
+// Legacy Shim Types
+// ============================================================================
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LegacyShimOptions {
+    /// Legacy name to its WGSL text. A key written `name(a, b)` is a macro:
+    /// calls to `name` expand to the text with each parameter replaced by
+    /// the call's argument.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Names starting with one of these are legacy; any without an alias is
+    /// reported, e.g. `["UNITY_", "unity_"]`.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+/// `shimLegacySyntax`'s rewritten source.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LegacyShim {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// One per rewritten use, offsets into the original text.
+    #[wasm_bindgen(readonly)]
+    pub edits: Vec<TextEdit>,
+    /// A warning per use of a legacy name with no alias, left as written.
+    #[wasm_bindgen(readonly)]
+    pub unmapped: Vec<DetailedDiagnostic>,
+}
+
+#[wasm_bindgen]
+impl LegacyShim {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Legacy Shim Implementation
+// ============================================================================
+//
+// Porting from another engine's shading language mostly means renaming its
+// built-in variables and intrinsics, so the shim works on tokens ahead of
+// the parser: an alias replaces a name wherever it is used (never after a
+// `.`, where it would be a member), and a macro alias replaces a whole call.
+// Macro arguments are shimmed first and parenthesized unless they are a
+// single token, so `mul(a + b, m)` cannot bind differently once expanded.
+
+struct Alias {
+    /// `None` for a plain rename.
+    params: Option<Vec<String>>,
+    replacement: String,
+}
+
+fn parse_aliases(aliases: &BTreeMap<String, String>) -> Result<HashMap<&str, Alias>, String> {
+    let is_name = |s: &str| {
+        let tokens = tokenize(s);
+        tokens.len() == 1 && tokens[0].kind == TokenKind::Ident && tokens[0].text == s
+    };
+    let mut parsed = HashMap::new();
+    for (key, replacement) in aliases {
+        let (name, params) = match key.split_once('(') {
+            Some((name, rest)) => {
+                let params = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("Invalid legacy alias '{key}': missing ')'"))?;
+                let params: Vec<_> = params
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                (name.trim(), Some(params))
+            }
+            None => (key.trim(), None),
+        };
+        if !is_name(name) || params.iter().flatten().any(|p| !is_name(p)) {
+            return Err(format!("Invalid legacy alias '{key}'"));
+        }
+        parsed.insert(
+            name,
+            Alias {
+                params,
+                replacement: replacement.clone(),
+            },
+        );
+    }
+    Ok(parsed)
+}
+
+struct Shim<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    aliases: HashMap<&'a str, Alias>,
+    prefixes: &'a [String],
+    unmapped: Vec<DetailedDiagnostic>,
+}
+
+impl Shim<'_> {
+    /// Index of the `)` closing the `(` at `open`, and the token ranges of
+    /// the arguments between them.
+    fn arguments(&self, open: usize) -> (usize, Vec<(usize, usize)>) {
+        let mut depth = 0;
+        let mut args = Vec::new();
+        let mut arg_start = open + 1;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.kind {
+                TokenKind::Punct('(' | '[') => depth += 1,
+                TokenKind::Punct(')' | ']') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if arg_start < i {
+                            args.push((arg_start, i));
+                        }
+                        return (i, args);
+                    }
+                }
+                TokenKind::Punct(',') if depth == 1 => {
+                    args.push((arg_start, i));
+                    arg_start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        (self.tokens.len() - 1, args)
+    }
+
+    /// The edits shimming tokens `lo..hi`, as `(start, end, text)` byte
+    /// ranges in order.
+    fn rewrite(&mut self, lo: usize, hi: usize) -> Vec<(usize, usize, String)> {
+        let mut edits = Vec::new();
+        let mut i = lo;
+        while i < hi {
+            let token = self.tokens[i];
+            let member = i > 0 && self.tokens[i - 1].is_punct('.');
+            if token.kind != TokenKind::Ident || member {
+                i += 1;
+                continue;
+            }
+            let called = self.tokens.get(i + 1).is_some_and(|t| t.is_punct('('));
+            let alias = self
+                .aliases
+                .get(token.text)
+                .map(|alias| (alias.params.clone(), alias.replacement.clone()));
+            match alias {
+                Some((None, replacement)) => {
+                    edits.push((token.start, token.end(), replacement));
+                }
+                Some((Some(params), replacement)) if called => {
+                    let (close, args) = self.arguments(i + 1);
+                    if args.len() != params.len() {
+                        let message = format!(
+                            "Legacy macro '{}' takes {} arguments but is given {}",
+                            token.text,
+                            params.len(),
+                            args.len()
+                        );
+                        self.warn(message, token, "left as written");
+                    } else {
+                        let args: Vec<_> = args
+                            .into_iter()
+                            .map(|(start, end)| self.argument(start, end))
+                            .collect();
+                        let expanded = expand(&replacement, &params, &args);
+                        edits.push((token.start, self.tokens[close].end(), expanded));
+                        i = close + 1;
+                        continue;
+                    }
+                }
+                Some((Some(_), _)) => {
+                    let message =
+                        format!("Legacy macro '{}' is used without arguments", token.text);
+                    self.warn(message, token, "left as written");
+                }
+                None if self
+                    .prefixes
+                    .iter()
+                    .any(|p| token.text.starts_with(p.as_str())) =>
+                {
+                    let message = format!("No WGSL equivalent for legacy name '{}'", token.text);
+                    self.warn(message, token, "unmapped");
+                }
+                None => {}
+            }
+            i += 1;
+        }
+        edits
+    }
+
+    /// The shimmed text of tokens `lo..hi`, parenthesized unless it is one
+    /// token.
+    fn argument(&mut self, lo: usize, hi: usize) -> String {
+        let (start, end) = (self.tokens[lo].start, self.tokens[hi - 1].end());
+        let edits = self.rewrite(lo, hi);
+        let mut text = String::new();
+        let mut copied = start;
+        for (edit_start, edit_end, replacement) in edits {
+            text.push_str(&self.source[copied..edit_start]);
+            text.push_str(&replacement);
+            copied = edit_end;
+        }
+        text.push_str(&self.source[copied..end]);
+        if hi - lo == 1 {
+            text
+        } else {
+            format!("({text})")
+        }
+    }
+
+    fn warn(&mut self, message: String, token: Token, label: &str) {
+        let span = naga::Span::new(token.start as u32, token.end() as u32);
+        self.unmapped.push(DetailedDiagnostic::warning(
+            self.source,
+            message,
+            span,
+            label,
+        ));
+    }
+}
+
+/// `replacement` with each parameter name replaced by its argument.
+fn expand(replacement: &str, params: &[String], args: &[String]) -> String {
+    let mut out = String::with_capacity(replacement.len());
+    let mut copied = 0;
+    for token in tokenize(replacement) {
+        if token.kind != TokenKind::Ident {
+            continue;
+        }
+        if let Some(index) = params.iter().position(|p| p == token.text) {
+            out.push_str(&replacement[copied..token.start]);
+            out.push_str(&args[index]);
+            copied = token.end();
+        }
+    }
+    out.push_str(&replacement[copied..]);
+    out
+}
+
+pub(crate) fn shim(source: &str, options: &LegacyShimOptions) -> Result<LegacyShim, String> {
+    let tokens = tokenize(source);
+    let mut shim = Shim {
+        source,
+        aliases: parse_aliases(&options.aliases)?,
+        prefixes: &options.prefixes,
+        unmapped: Vec::new(),
+        tokens,
+    };
+    let edits = shim.rewrite(0, shim.tokens.len());
+
+    let mut wgsl = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (start, end, replacement) in &edits {
+        wgsl.push_str(&source[copied..*start]);
+        wgsl.push_str(replacement);
+        copied = *end;
+    }
+    wgsl.push_str(&source[copied..]);
+
+    Ok(LegacyShim {
+        wgsl,
+        edits: edits
+            .iter()
+            .map(|(start, end, replacement)| text_edit(source, *start, *end, replacement))
+            .collect(),
+        unmapped: shim.unmapped,
+    })
+}
+
+/// Rewrite another engine's shader names to WGSL ahead of parsing, from the
+/// caller's `aliases` table (`{ "saturate(x)": "clamp(x, 0.0, 1.0)",
+/// "UNITY_PI": "3.14159265" }`). Legacy names matching `prefixes` that have
+/// no alias are left in place and reported as warnings.
+#[wasm_bindgen(js_name = shimLegacySyntax)]
+pub fn shim_legacy_syntax(source: &str, options: JsValue) -> Result<LegacyShim, JsValue> {
+    let options: Option<LegacyShimOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid legacy shim options: {e}")))?;
+    shim(source, &options.unwrap_or_default()).map_err(|e| JsValue::from_str(&e))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(aliases: &[(&str, &str)], prefixes: &[&str]) -> LegacyShimOptions {
+        LegacyShimOptions {
+            aliases: aliases
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            prefixes: prefixes.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn aliases_and_macros_rewrite_to_valid_wgsl() {
+        let source = r#"
+            @group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+            @vertex
+            fn vs(@location(0) p: vec3<f32>) -> @builtin(position) vec4<f32> {
+                let t = saturate(p.x * UNITY_PI);
+                return mul(UNITY_MATRIX_VP, float4(p, lerp(0.0, 1.0, t)));
+            }
+        "#;
+        let aliases = [
+            ("UNITY_PI", "3.14159265"),
+            ("UNITY_MATRIX_VP", "camera"),
+            ("float4", "vec4<f32>"),
+            ("lerp", "mix"),
+            ("saturate(x)", "clamp(x, 0.0, 1.0)"),
+            ("mul(m, v)", "(m * v)"),
+        ];
+        let result = shim(source, &options(&aliases, &["UNITY_"])).unwrap();
+        assert!(result.unmapped.is_empty());
+        assert!(result.wgsl.contains("clamp((p.x * 3.14159265), 0.0, 1.0)"));
+        assert!(
+            result
+                .wgsl
+                .contains("(camera * (vec4<f32>(p, mix(0.0, 1.0, t))))")
+        );
+        assert_eq!(result.edits.len(), 2);
+        assert!(crate::parse_and_validate(&result.wgsl).is_ok());
+    }
+
+    #[test]
+    fn unmapped_legacy_names_are_reported() {
+        let source = "fn f() -> f32 {\n    return UNITY_TIME.y + v.UNITY_X + mul(1.0);\n}";
+        let result = shim(source, &options(&[("mul(a, b)", "a * b")], &["UNITY_"])).unwrap();
+        let messages: Vec<_> = result.unmapped.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "No WGSL equivalent for legacy name 'UNITY_TIME'",
+                "Legacy macro 'mul' takes 2 arguments but is given 1",
+            ]
+        );
+        let span = result.unmapped[0].span.as_ref().unwrap();
+        assert_eq!((span.line, span.column), (2, 12));
+        assert_eq!(result.unmapped[0].severity, "warning");
+        assert_eq!(result.wgsl, source);
+
+        assert!(shim("", &options(&[("bad(", "x")], &[])).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+// ============================================================================
+// WGSL Token Scanner
+// ============================================================================
+//
+// Just enough lexing for source-level tools that must not touch comments or
+// our `#include` lines: identifiers, numbers and single-character punctuation,
+// with byte offsets into the original text. Not a validating lexer; naga's
+// front end remains the authority on what is valid WGSL.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Ident,
+    Number,
+    Punct(char),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte offset in the source.
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub(crate) fn is_ident(&self, text: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text == text
+    }
+
+    pub(crate) fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct(c)
+    }
+
+    pub(crate) fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+/// Split `source` into tokens, skipping whitespace, comments and
+/// preprocessor lines (a `#` as the first non-blank character of a line).
+pub(crate) fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    let mut line_start = true;
+
+    while let Some((start, c)) = chars.next() {
+        if c == '\n' {
+            line_start = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let at_line_start = std::mem::replace(&mut line_start, false);
+
+        if c == '#' && at_line_start {
+            while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            continue;
+        }
+        if c == '/' && chars.peek().is_some_and(|(_, c)| *c == '/') {
+            while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            continue;
+        }
+        if c == '/' && chars.peek().is_some_and(|(_, c)| *c == '*') {
+            chars.next();
+            // Block comments nest in WGSL.
+            let mut depth = 1;
+            while depth > 0 {
+                match chars.next() {
+                    Some((_, '/')) if chars.next_if(|(_, c)| *c == '*').is_some() => depth += 1,
+                    Some((_, '*')) if chars.next_if(|(_, c)| *c == '/').is_some() => depth -= 1,
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            continue;
+        }
+
+        let kind = if is_ident_start(c) {
+            while chars.next_if(|(_, c)| is_ident_continue(*c)).is_some() {}
+            TokenKind::Ident
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit()))
+        {
+            // Decimal and hex literals, exponents with signs, and suffixes.
+            let mut prev = c;
+            let mut hex = false;
+            while let Some((_, next)) = chars.next_if(|(_, n)| {
+                let exponent = match prev {
+                    'e' | 'E' => !hex,
+                    'p' | 'P' => hex,
+                    _ => false,
+                };
+                is_ident_continue(*n) || *n == '.' || ((*n == '+' || *n == '-') && exponent)
+            }) {
+                hex |= prev == '0' && matches!(next, 'x' | 'X');
+                prev = next;
+            }
+            TokenKind::Number
+        } else {
+            TokenKind::Punct(c)
+        };
+
+        let end = chars.peek().map_or(source.len(), |(i, _)| *i);
+        tokens.push(Token {
+            kind,
+            text: &source[start..end],
+            start,
+        });
+    }
+    tokens
+}
+
+/// `//` comments outside block comments, as the byte offset of the text after
+/// the slashes and that text, up to the end of the line.
+pub(crate) fn line_comments(source: &str) -> Vec<(usize, &str)> {
+    let mut comments = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    let mut depth = 0;
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '/' if chars.next_if(|(_, c)| *c == '*').is_some() => depth += 1,
+            '*' if depth > 0 && chars.next_if(|(_, c)| *c == '/').is_some() => depth -= 1,
+            '/' if depth == 0 && chars.next_if(|(_, c)| *c == '/').is_some() => {
+                let end = source[start..]
+                    .find('\n')
+                    .map_or(source.len(), |i| start + i);
+                comments.push((start + 2, &source[start + 2..end]));
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+            }
+            _ => {}
+        }
+    }
+    comments
+}
+
+/// Names declared at module scope, with the index of their name token.
+pub(crate) fn module_declarations<'a>(tokens: &[Token<'a>]) -> Vec<(&'a str, usize)> {
+    let mut declarations = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        match token.kind {
+            TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct('}') => depth = depth.saturating_sub(1),
+            TokenKind::Ident if depth == 0 => {
+                let name_at = match token.text {
+                    "fn" | "struct" | "alias" | "const" | "override" => Some(i + 1),
+                    "var" => Some(skip_template(tokens, i + 1)),
+                    _ => None,
+                };
+                if let Some(at) = name_at
+                    && let Some(name) = tokens.get(at).filter(|t| t.kind == TokenKind::Ident)
+                {
+                    declarations.push((name.text, at));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    declarations
+}
+
+/// The attributes right before the token at `at`, nearest first, each with
+/// the indices of its `(` and `)` if it has arguments.
+pub(crate) fn attributes_before<'a>(
+    tokens: &[Token<'a>],
+    at: usize,
+) -> Vec<(&'a str, Option<(usize, usize)>)> {
+    let mut attributes = Vec::new();
+    let mut i = at;
+    loop {
+        let mut j = i;
+        let mut args = None;
+        if j > 0 && tokens[j - 1].is_punct(')') {
+            let close = j - 1;
+            let mut depth = 0;
+            while j > 0 {
+                j -= 1;
+                if tokens[j].is_punct(')') {
+                    depth += 1;
+                } else if tokens[j].is_punct('(') {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            args = Some((j, close));
+        }
+        if j >= 2 && tokens[j - 1].kind == TokenKind::Ident && tokens[j - 2].is_punct('@') {
+            attributes.push((tokens[j - 1].text, args));
+            i = j - 2;
+        } else {
+            return attributes;
+        }
+    }
+}
+
+/// Index past a `<...>` template list starting at `at`, or `at` if there is none.
+pub(crate) fn skip_template(tokens: &[Token], at: usize) -> usize {
+    if !tokens.get(at).is_some_and(|t| t.is_punct('<')) {
+        return at;
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(at) {
+        if token.is_punct('<') {
+            depth += 1;
+        } else if token.is_punct('>') {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+    }
+    tokens.len()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<&str> {
+        tokenize(source).iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn skips_comments_and_preprocessor_lines() {
+        let source =
+            "#include \"a.wgsl\"\nfn f() /* x /* nested */ y */ { // z\n return 1.5e-3f; }";
+        assert_eq!(
+            texts(source),
+            vec!["fn", "f", "(", ")", "{", "return", "1.5e-3f", ";", "}"]
+        );
+        let comments = line_comments("/* // no */ a // one\n// two /* three */");
+        assert_eq!(comments, vec![(16, " one"), (23, " two /* three */")]);
+    }
+
+    #[test]
+    fn offsets_point_into_the_source() {
+        let source = "let  déjà = a.b;";
+        for token in tokenize(source) {
+            assert_eq!(&source[token.start..token.end()], token.text);
+        }
+        assert_eq!(texts(source)[1], "déjà");
+        assert_eq!(texts("0x1e-3"), vec!["0x1e", "-", "3"]);
+        assert_eq!(texts("0x1p-3"), vec!["0x1p-3"]);
+    }
+
+    #[test]
+    fn finds_module_declarations() {
+        let source = "struct S { a: f32 }\n@group(0) @binding(0) var<uniform> u: S;\nfn f() { let x = 1; }\nconst C = 2;";
+        let names: Vec<_> = module_declarations(&tokenize(source))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["S", "u", "f", "C"]);
+    }
+}
@@ -1,127 +1,547 @@
+mod asm;
+mod batch;
+mod bind_groups;
+mod blit;
+mod bounds;
+mod bundler;
+mod cache_key;
+mod catalog;
+mod compare;
+mod compose;
+mod const_limit;
+mod cpp;
+mod cse;
+mod culling;
+mod depth;
+mod diagnostics;
+mod directory;
+mod entry_points;
+mod external;
+#[cfg(feature = "c-abi")]
+mod ffi;
+mod format;
+mod friendly;
+mod glsl;
+mod glsl_compat;
+mod graph;
+mod harness;
+mod hash;
+mod hlsl;
+mod include;
+mod inline;
+mod integrity;
+mod layout;
+mod legacy;
+mod lexer;
+mod link;
+mod lint;
+mod manifest;
+mod math;
+mod merge;
+mod minify;
+mod mock;
+mod modernize;
+mod msl;
+mod output;
+mod packing;
+mod pipeline;
+mod postprocess;
+mod precision;
+mod preset;
+mod project;
+mod provenance;
+#[cfg(feature = "python")]
+mod python;
+mod reduction;
+mod prune;
+mod remap;
+mod rename;
+#[cfg(any(feature = "c-abi", feature = "python"))]
+mod request;
+mod resources;
+mod results;
+mod rewrite;
+mod rust;
+mod safety;
+mod session;
+mod root_signature;
+mod shader_module;
+mod size;
+mod sourcemap;
+mod specialize;
+mod spirv_validation;
+mod spelling;
+mod spv;
+mod strip;
+mod symbols;
+mod sweep;
+mod texel;
+mod threads;
+mod typescript;
+mod usage;
+mod varyings;
+mod vertex;
+mod webgl;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
 use naga::Module;
 use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
 use naga::{back, front};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+pub use diagnostics::Diagnostic;
+use diagnostics::throw;
+use preset::Preset;
+
 /// WGSL -> Naga IR + validation.
-fn parse_and_validate(wgsl: &str) -> Result<(Module, ModuleInfo), JsValue> {
-    // WGSL -> IR
-    let module =
-        front::wgsl::parse_str(wgsl).map_err(|e| JsValue::from_str(&e.emit_to_string(wgsl)))?;
-    // Validation
-    let mut v = Validator::new(ValidationFlags::all(), Capabilities::all());
-    let info = v
-        .validate(&module)
-        .map_err(|e| JsValue::from_str(&format!("{e:?}")))?;
+fn parse_and_validate(wgsl: &str) -> Result<(Module, ModuleInfo), Diagnostic> {
+    let module = parse_wgsl(wgsl)?;
+    let info = validate_module(&module)?;
+    Ok((module, info))
+}
+
+/// WGSL -> Naga IR, for callers that transform the module before validating.
+/// Refuses constant values too large for naga to evaluate in bounded time.
+fn parse_wgsl(wgsl: &str) -> Result<Module, Diagnostic> {
+    if let Some(oversized) = const_limit::oversized(wgsl) {
+        let location = oversized.span().location(wgsl);
+        return Err(Diagnostic::error(format!(
+            "{} (at {}:{})",
+            oversized.message(),
+            location.line_number,
+            location.line_position
+        )));
+    }
+    front::wgsl::parse_str(wgsl).map_err(|e| Diagnostic::error(e.emit_to_string(wgsl)))
+}
+
+/// Validate a module with every check and capability enabled.
+fn validate_module(module: &Module) -> Result<ModuleInfo, Diagnostic> {
+    validate_module_with(module, Capabilities::all())
+}
+
+thread_local! {
+    /// Validators by flags and capabilities. `validate` resets one before
+    /// use, so keeping them only saves rebuilding their tables and buffers.
+    static VALIDATORS: RefCell<HashMap<(u8, u32), Validator>> = RefCell::default();
+}
+
+/// Validate `module` with the cached validator for `flags` and
+/// `capabilities`.
+fn run_validator(
+    module: &Module,
+    flags: ValidationFlags,
+    capabilities: Capabilities,
+) -> Result<ModuleInfo, Box<naga::WithSpan<naga::valid::ValidationError>>> {
+    VALIDATORS.with_borrow_mut(|validators| {
+        validators
+            .entry((flags.bits(), capabilities.bits()))
+            .or_insert_with(|| Validator::new(flags, capabilities))
+            .validate(module)
+            .map_err(Box::new)
+    })
+}
+
+/// Validate a module with every check, allowing only `capabilities`.
+fn validate_module_with(
+    module: &Module,
+    capabilities: Capabilities,
+) -> Result<ModuleInfo, Diagnostic> {
+    run_validator(module, ValidationFlags::all(), capabilities)
+        .map_err(|e| Diagnostic::error(format!("{e:?}")))
+}
+
+/// The `ModuleInfo` of a module known to be valid, e.g. one derived from a
+/// validated module by compaction, or WGSL we wrote from one. Skips every
+/// check, so an invalid module yields unusable info rather than an error.
+fn revalidate_trusted(module: &Module) -> Result<ModuleInfo, Diagnostic> {
+    run_validator(module, ValidationFlags::empty(), Capabilities::all())
+        .map_err(|e| Diagnostic::error(format!("{e:?}")))
+}
+
+/// Validate for `preset`, or with every capability if there is none.
+fn validate_for(module: &Module, preset: Option<&Preset>) -> Result<ModuleInfo, Diagnostic> {
+    validate_module_with(module, preset.map_or(Capabilities::all(), |p| p.capabilities))
+}
+
+/// `validate_for`, or with `assume_valid`, `revalidate_trusted`.
+fn validate_or_trust(
+    module: &Module,
+    preset: Option<&Preset>,
+    assume_valid: bool,
+) -> Result<ModuleInfo, Diagnostic> {
+    if assume_valid {
+        revalidate_trusted(module)
+    } else {
+        validate_for(module, preset)
+    }
+}
+
+/// Look up an entry point by name.
+fn find_entry_point<'a>(module: &'a Module, name: &str) -> Result<&'a naga::EntryPoint, Diagnostic> {
+    module
+        .entry_points
+        .iter()
+        .find(|ep| ep.name == name)
+        .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", name)))
+}
+
+/// WGSL -> Naga IR + validation with the capabilities of `profile`, or
+/// every capability without one.
+fn parse_and_validate_as(
+    wgsl: &str,
+    profile: Option<&str>,
+) -> Result<(Module, ModuleInfo), Diagnostic> {
+    let capabilities = preset::resolve_profile(profile)?;
+    let module = parse_wgsl(wgsl)?;
+    let info = validate_module_with(&module, capabilities)?;
     Ok((module, info))
 }
 
 /// Validates WGSL and returns true if valid, false otherwise.
+/// `profile` names a capability profile (see `listCapabilityProfiles`).
 #[wasm_bindgen(js_name = isWgslValid)]
-pub fn is_wgsl_valid(wgsl: &str) -> bool {
-    parse_and_validate(wgsl).is_ok()
+pub fn is_wgsl_valid(wgsl: &str, profile: Option<String>) -> bool {
+    parse_and_validate_as(wgsl, profile.as_deref()).is_ok()
 }
 
 /// Only validates WGSL (throws JS error if invalid).
+/// `profile` names a capability profile (see `listCapabilityProfiles`), so
+/// that e.g. `"webgpu-core"` rejects `f64` as the browser would.
 #[wasm_bindgen(js_name = validateWgsl)]
-pub fn validate_wgsl(wgsl: &str) -> Result<(), JsValue> {
-    let _ = parse_and_validate(wgsl)?;
+pub fn validate_wgsl(wgsl: &str, profile: Option<String>) -> Result<(), JsValue> {
+    let _ = parse_and_validate_as(wgsl, profile.as_deref()).map_err(throw)?;
     Ok(())
 }
 
 /// WGSL -> SPIR-V (binary words -> LE bytes) for Vulkan.
 /// If entry_point is provided, only compiles that specific entry point.
 /// If entry_point is None or empty string, compiles all entry points.
+/// `preset` names a device preset (see `listPresets`).
+/// `options` is `{ version?: "1.0" | ... | "1.6", debugNames?: boolean,
+/// flags?: string[], overrides?: { [nameOrId]: number }, compact?: boolean,
+/// sourceMap?: boolean, fileName?: string, assumeValid?: boolean,
+/// entryPointName?: string }`.
+/// `version` overrides the preset's; `flags` replaces naga's default writer
+/// flags (`adjustCoordinateSpace`, `labelVaryings`, `clampFragDepth`, plus
+/// `debug` in debug builds) with the ones named, out of those and
+/// `forcePointSize`; `debugNames` then sets or clears `debug`. Overrides are
+/// baked in, `overrides` giving values by name or `@id` and the rest taking
+/// their defaults. `compact` strips the functions, globals, types and
+/// constants no emitted entry point uses, e.g. the rest of a shared header.
+/// `sourceMap` embeds the WGSL as `fileName` (default `shader.wgsl`) with an
+/// `OpLine` per statement, for RenderDoc and friends;
+/// `wgslToSpirvWithSourceMap` also returns the lines as a table.
+/// `assumeValid` skips validation, for WGSL already validated or written
+/// from a validated module. `entryPointName` renames the entry point in
+/// the output, e.g. to `main` for tools that insist on it, and needs
+/// `entry_point` unless the module has only one.
 #[wasm_bindgen(js_name = wgslToSpirvBin)]
-pub fn wgsl_to_spirv_bin(wgsl: &str, entry_point: Option<String>) -> Result<Box<[u8]>, JsValue> {
-    let (module, info) = parse_and_validate(wgsl)?;
-    let spv_opts = back::spv::Options::default();
+pub fn wgsl_to_spirv_bin(
+    wgsl: &str,
+    entry_point: Option<String>,
+    preset: Option<String>,
+    options: JsValue,
+) -> Result<Box<[u8]>, JsValue> {
+    let options = spirv_options(options)?;
+    let preset = preset::resolve(preset.as_deref()).map_err(throw)?;
+    compile_spirv_with(wgsl, entry_point.as_deref(), preset, &options)
+        .map(Vec::into_boxed_slice)
+        .map_err(throw)
+}
 
-    // Determine pipeline options based on entry point
-    let pipeline_opts = if let Some(ep_name) = entry_point {
-        if ep_name.is_empty() {
-            None
-        } else {
-            // Find the entry point in the module
-            let entry = module
-                .entry_points
-                .iter()
-                .find(|ep| ep.name == ep_name)
-                .ok_or_else(|| {
-                    JsValue::from_str(&format!("Entry point '{}' not found", ep_name))
-                })?;
+/// Deserialize a `wgslToSpirvBin` options argument.
+fn spirv_options(options: JsValue) -> Result<spv::SpirvOptions, JsValue> {
+    parse_spirv_options(options).map_err(|e| JsValue::from_str(&e.message))
+}
+
+/// `spirv_options`, failing with a diagnostic for the `try*` variants.
+fn parse_spirv_options(options: JsValue) -> Result<spv::SpirvOptions, Diagnostic> {
+    let options: Option<spv::SpirvOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| Diagnostic::error(format!("Invalid SPIR-V options: {e}")))?;
+    Ok(options.unwrap_or_default())
+}
 
+/// `compile_spirv_with` without a preset or options, for tests.
+#[cfg(test)]
+fn compile_spirv(wgsl: &str, entry_point: Option<&str>) -> Result<Vec<u8>, Diagnostic> {
+    compile_spirv_with(wgsl, entry_point, None, &spv::SpirvOptions::default())
+}
+
+fn compile_spirv_with(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+    options: &spv::SpirvOptions,
+) -> Result<Vec<u8>, Diagnostic> {
+    let module = parse_wgsl(wgsl)?;
+    let info = validate_or_trust(&module, preset, options.assume_valid)?;
+    let source = options.source_map.then_some(wgsl);
+    write_spirv_sourced(&module, &info, entry_point, preset, options, source)
+}
+
+/// Emit SPIR-V bytes for an already validated module.
+fn write_spirv(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+) -> Result<Vec<u8>, Diagnostic> {
+    write_spirv_with(module, info, entry_point, None)
+}
+
+/// Emit SPIR-V bytes with `preset`'s SPIR-V version and bounds checks.
+fn write_spirv_with(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+) -> Result<Vec<u8>, Diagnostic> {
+    write_spirv_configured(
+        module,
+        info,
+        entry_point,
+        preset,
+        &spv::SpirvOptions::default(),
+    )
+}
+
+/// `write_spirv_with`, then `options` on top of the preset.
+fn write_spirv_configured(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+    options: &spv::SpirvOptions,
+) -> Result<Vec<u8>, Diagnostic> {
+    write_spirv_sourced(module, info, entry_point, preset, options, None)
+}
+
+/// `write_spirv_configured`, with `OpSource` and `OpLine`s pointing into
+/// `source`, the WGSL `module` was parsed from, if given.
+fn write_spirv_sourced(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+    options: &spv::SpirvOptions,
+    source: Option<&str>,
+) -> Result<Vec<u8>, Diagnostic> {
+    let renamed = options
+        .entry_point_name
+        .as_deref()
+        .map(|name| entry_points::rename_for_output(module, entry_point, name))
+        .transpose()?;
+    let (module, entry_point) = match &renamed {
+        Some(renamed) => (renamed, options.entry_point_name.as_deref()),
+        None => (module, entry_point),
+    };
+    let (module, info) = specialize::bake(module, info, entry_point, &options.overrides)?;
+    let compacted = options
+        .compact
+        .then(|| pipeline::compact_for(&module, entry_point))
+        .transpose()?;
+    let (module, info) = match &compacted {
+        Some((module, info)) => (module, info),
+        None => (module.as_ref(), info.as_ref()),
+    };
+
+    let mut spv_opts = back::spv::Options::default();
+    if let Some(preset) = preset {
+        spv_opts.lang_version = preset.spirv_version;
+        spv_opts.bounds_check_policies = preset.bounds_checks;
+    }
+    options.apply(&mut spv_opts)?;
+    if let Some(source) = source {
+        spv_opts.flags |= back::spv::WriterFlags::DEBUG;
+        spv_opts.debug_info = Some(back::spv::DebugInfo {
+            source_code: source,
+            file_name: sourcemap::file_name(options.file_name.as_deref()),
+            language: back::spv::SourceLanguage::WGSL,
+        });
+    }
+
+    // Determine pipeline options based on entry point
+    let pipeline_opts = match entry_point {
+        Some(ep_name) if !ep_name.is_empty() => {
+            let entry = find_entry_point(module, ep_name)?;
             Some(back::spv::PipelineOptions {
                 shader_stage: entry.stage,
-                entry_point: ep_name,
+                entry_point: ep_name.to_string(),
             })
         }
-    } else {
-        None
+        _ => None,
     };
 
-    let words: Vec<u32> = back::spv::write_vec(&module, &info, &spv_opts, pipeline_opts.as_ref())
-        .map_err(|e| JsValue::from_str(&format!("SPIR-V error: {e:?}")))?;
+    let words: Vec<u32> = back::spv::write_vec(module, info, &spv_opts, pipeline_opts.as_ref())
+        .map_err(|e| Diagnostic::error(format!("SPIR-V error: {e:?}")))?;
 
     // u32 words -> little-endian bytes
-    let mut bytes = Vec::with_capacity(words.len() * 4);
-    for w in words {
-        bytes.extend_from_slice(&w.to_le_bytes());
-    }
-    Ok(bytes.into_boxed_slice())
+    Ok(spv::bytes_from_words(&words))
 }
 
 /// WGSL -> MSL (Metal Shading Language) source code for Metal/macOS/iOS.
 /// If entry_point is provided, only compiles that specific entry point.
 /// If entry_point is None or empty string, compiles all entry points.
+/// `preset` names a device preset (see `listPresets`).
 #[wasm_bindgen(js_name = wgslToMsl)]
-pub fn wgsl_to_msl(wgsl: &str, entry_point: Option<String>) -> Result<String, JsValue> {
-    let (module, info) = parse_and_validate(wgsl)?;
+pub fn wgsl_to_msl(
+    wgsl: &str,
+    entry_point: Option<String>,
+    preset: Option<String>,
+) -> Result<String, JsValue> {
+    let preset = preset::resolve(preset.as_deref()).map_err(throw)?;
+    compile_msl_with(wgsl, entry_point.as_deref(), preset).map_err(throw)
+}
+
+fn compile_msl_with(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+) -> Result<String, Diagnostic> {
+    let module = parse_wgsl(wgsl)?;
+    let info = validate_for(&module, preset)?;
+    write_msl_with(&module, &info, entry_point, preset)
+}
+
+/// Emit MSL source for an already validated module.
+fn write_msl(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+) -> Result<String, Diagnostic> {
+    write_msl_with(module, info, entry_point, None)
+}
+
+/// Emit MSL source with `preset`'s Metal version and bounds checks.
+fn write_msl_with(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+) -> Result<String, Diagnostic> {
+    write_msl_configured(
+        module,
+        info,
+        entry_point,
+        msl_options(preset),
+        &Default::default(),
+        false,
+    )
+}
+
+/// naga's MSL options, with `preset`'s Metal version and bounds checks.
+fn msl_options(preset: Option<&Preset>) -> back::msl::Options {
+    let mut msl_opts = back::msl::Options::default();
+    if let Some(preset) = preset {
+        msl_opts.lang_version = preset.msl_version;
+        msl_opts.bounds_check_policies = preset.bounds_checks;
+    }
+    msl_opts
+}
+
+/// Emit MSL source with `msl_opts` and `overrides` baked in, and unused
+/// code stripped if `compact`, raising the Metal version if the module needs
+/// it.
+fn write_msl_configured(
+    module: &Module,
+    info: &ModuleInfo,
+    entry_point: Option<&str>,
+    mut msl_opts: back::msl::Options,
+    overrides: &BTreeMap<String, f64>,
+    compact: bool,
+) -> Result<String, Diagnostic> {
+    let (module, info) = specialize::bake(module, info, entry_point, overrides)?;
+    let compacted = compact
+        .then(|| pipeline::compact_for(&module, entry_point))
+        .transpose()?;
+    let (module, info) = match &compacted {
+        Some((module, info)) => (module, info),
+        None => (module.as_ref(), info.as_ref()),
+    };
+
+    // `[[invariant]]` needs Metal 2.1.
+    if uses_invariance(module) {
+        msl_opts.lang_version = msl_opts.lang_version.max((2, 1));
+    }
 
     // Build pipeline options based on entry point
-    let msl_opts = back::msl::Options::default();
-
-    if let Some(ep_name) = entry_point {
-        if !ep_name.is_empty() {
-            // Find the entry point in the module
-            let entry = module
-                .entry_points
-                .iter()
-                .find(|ep| ep.name == ep_name)
-                .ok_or_else(|| {
-                    JsValue::from_str(&format!("Entry point '{}' not found", ep_name))
-                })?;
 
+    let pipeline_opts = match entry_point {
+        Some(ep_name) if !ep_name.is_empty() => {
+            let entry = find_entry_point(module, ep_name)?;
             // For MSL, we need to create PipelineOptions with the entry point info
-            let pipeline_opts = back::msl::PipelineOptions {
-                entry_point: Some((entry.stage, ep_name)),
+            back::msl::PipelineOptions {
+                entry_point: Some((entry.stage, ep_name.to_string())),
                 ..Default::default()
-            };
-
-            let (msl_source, _) =
-                back::msl::write_string(&module, &info, &msl_opts, &pipeline_opts)
-                    .map_err(|e| JsValue::from_str(&format!("MSL error: {e:?}")))?;
-
-            return Ok(msl_source);
+            }
         }
-    }
+        // No specific entry point - compile all
+        _ => back::msl::PipelineOptions::default(),
+    };
 
-    // No specific entry point - compile all
-    let pipeline_opts = back::msl::PipelineOptions::default();
-    let (msl_source, _) = back::msl::write_string(&module, &info, &msl_opts, &pipeline_opts)
-        .map_err(|e| JsValue::from_str(&format!("MSL error: {e:?}")))?;
+    let (msl_source, _) = back::msl::write_string(module, info, &msl_opts, &pipeline_opts)
+        .map_err(|e| Diagnostic::error(format!("MSL error: {e:?}")))?;
 
     Ok(msl_source)
 }
 
+/// Whether any `@builtin(position)` is marked `@invariant`.
+fn uses_invariance(module: &Module) -> bool {
+    let invariant = |binding: &Option<naga::Binding>| {
+        matches!(
+            binding,
+            Some(naga::Binding::BuiltIn(naga::BuiltIn::Position { invariant: true }))
+        )
+    };
+    let in_structs = module.types.iter().any(|(_, ty)| match &ty.inner {
+        naga::TypeInner::Struct { members, .. } => members.iter().any(|m| invariant(&m.binding)),
+        _ => false,
+    });
+    in_structs
+        || module.entry_points.iter().any(|ep| {
+            ep.function.arguments.iter().any(|a| invariant(&a.binding))
+                || ep.function.result.as_ref().is_some_and(|r| invariant(&r.binding))
+        })
+}
+
 /// SPIR-V binary -> disassembled text for debugging.
-/// Takes SPIR-V bytes (little-endian) and returns human-readable assembly.
+/// Takes SPIR-V bytes (little-endian) and returns spirv-dis style assembly,
+/// decorations and capabilities included. Use `spirvToWgsl` for WGSL.
 #[wasm_bindgen(js_name = spirvBinToText)]
 pub fn spirv_bin_to_text(spirv_bytes: &[u8]) -> Result<String, JsValue> {
+    spirv_to_text(spirv_bytes).map_err(throw)
+}
+
+fn spirv_to_text(spirv_bytes: &[u8]) -> Result<String, Diagnostic> {
+    spv::disassemble(&spv::words_from_bytes(spirv_bytes)?)
+}
+
+/// SPIR-V binary -> WGSL, through naga's SPIR-V frontend.
+/// Fails for modules naga cannot load or validate.
+#[wasm_bindgen(js_name = spirvToWgsl)]
+pub fn spirv_to_wgsl_text(spirv_bytes: &[u8]) -> Result<String, JsValue> {
+    spirv_to_wgsl(spirv_bytes).map_err(throw)
+}
+
+fn spirv_to_wgsl(spirv_bytes: &[u8]) -> Result<String, Diagnostic> {
+    let (module, info) = load_spirv(spirv_bytes)?;
+
+    // Convert to WGSL for human-readable output
+    let wgsl_opts = back::wgsl::WriterFlags::all();
+    let wgsl_text = back::wgsl::write_string(&module, &info, wgsl_opts)
+        .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+
+    Ok(wgsl_text)
+}
+
+/// Load a SPIR-V binary through naga's frontend and validate it.
+fn load_spirv(spirv_bytes: &[u8]) -> Result<(Module, ModuleInfo), Diagnostic> {
     // Validate length
-    if spirv_bytes.len() % 4 != 0 {
-        return Err(JsValue::from_str(
+    if !spirv_bytes.len().is_multiple_of(4) {
+        return Err(Diagnostic::error(
             "SPIR-V binary length must be multiple of 4",
         ));
     }
@@ -129,27 +549,20 @@ pub fn spirv_bin_to_text(spirv_bytes: &[u8]) -> Result<String, JsValue> {
     // Parse SPIR-V binary directly from bytes
     let spv_opts = front::spv::Options::default();
     let module = front::spv::parse_u8_slice(spirv_bytes, &spv_opts)
-        .map_err(|e| JsValue::from_str(&format!("SPIR-V parse error: {e:?}")))?;
+        .map_err(|e| Diagnostic::error(format!("SPIR-V parse error: {e:?}")))?;
 
     // Validate
-    let mut validator = Validator::new(ValidationFlags::all(), Capabilities::all());
-    let info = validator
-        .validate(&module)
-        .map_err(|e| JsValue::from_str(&format!("SPIR-V validation error: {e:?}")))?;
-
-    // Convert back to WGSL for human-readable output
-    let wgsl_opts = back::wgsl::WriterFlags::all();
-    let wgsl_text = back::wgsl::write_string(&module, &info, wgsl_opts)
-        .map_err(|e| JsValue::from_str(&format!("WGSL write error: {e:?}")))?;
+    let info = run_validator(&module, ValidationFlags::all(), Capabilities::all())
+        .map_err(|e| Diagnostic::error(format!("SPIR-V validation error: {e:?}")))?;
 
-    Ok(wgsl_text)
+    Ok((module, info))
 }
 
 // ============================================================================
 // Reflection Types
 // ============================================================================
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[wasm_bindgen(getter_with_clone)]
 pub struct ReflectionData {
@@ -157,6 +570,12 @@ pub struct ReflectionData {
     pub entry_points: Vec<EntryPointInfo>,
     #[wasm_bindgen(readonly)]
     pub types: Vec<TypeInfo>,
+    /// In declaration order.
+    #[wasm_bindgen(readonly)]
+    pub overrides: Vec<OverrideInfo>,
+    /// Helper functions, in declaration order.
+    #[wasm_bindgen(readonly)]
+    pub functions: Vec<FunctionInfo>,
 }
 
 #[wasm_bindgen]
@@ -167,10 +586,38 @@ impl ReflectionData {
     }
 }
 
+/// Name and stage of an entry point, as listed by `listEntryPoints`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct EntryPointSummary {
+    /// The `id` reflection gives the entry point.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub stage: String,
+    /// Set for compute entry points.
+    #[wasm_bindgen(readonly)]
+    pub workgroup_size: Option<Vec<u32>>,
+}
+
+#[wasm_bindgen]
+impl EntryPointSummary {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[wasm_bindgen(getter_with_clone)]
 pub struct EntryPointInfo {
+    /// Hash of the stage and name, stable across edits that keep both.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
     #[wasm_bindgen(readonly)]
     pub name: String,
     #[wasm_bindgen(readonly)]
@@ -193,10 +640,33 @@ impl EntryPointInfo {
     }
 }
 
+/// A function that is not an entry point.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct FunctionInfo {
+    /// Hash of the function's name, stable across edits that keep it.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+}
+
+#[wasm_bindgen]
+impl FunctionInfo {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[wasm_bindgen(getter_with_clone)]
 pub struct BindingInfo {
+    /// Hash of the variable's name, stable across edits that keep it.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
     #[wasm_bindgen(readonly)]
     pub name: String,
     #[wasm_bindgen(readonly)]
@@ -209,6 +679,9 @@ pub struct BindingInfo {
     pub type_name: Option<String>,
     #[wasm_bindgen(readonly)]
     pub is_readonly: bool,
+    /// Render graph resource the binding is annotated with, if any.
+    #[wasm_bindgen(readonly)]
+    pub resource_name: Option<String>,
 }
 
 #[wasm_bindgen]
@@ -219,6 +692,39 @@ impl BindingInfo {
     }
 }
 
+/// A pipeline-overridable constant.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct OverrideInfo {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// The explicit `@id`, if any.
+    #[wasm_bindgen(readonly)]
+    pub id: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub type_name: String,
+    /// The default as a pipeline constant value (`bool`s as 0 or 1). Unset
+    /// if there is none or it is computed from other overrides.
+    #[wasm_bindgen(readonly)]
+    pub default_value: Option<f64>,
+    #[wasm_bindgen(readonly)]
+    pub has_default: bool,
+    /// Entry points whose pipelines it affects, directly, through called
+    /// functions, workgroup sizes, array lengths or other overrides'
+    /// defaults.
+    #[wasm_bindgen(readonly)]
+    pub entry_points: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl OverrideInfo {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 #[wasm_bindgen(getter_with_clone)]
@@ -269,6 +775,11 @@ pub struct TypeInfo {
     pub kind: String,
     #[wasm_bindgen(readonly)]
     pub members: Option<Vec<StructMemberInfo>>,
+    /// Size in bytes under WGSL layout rules, trailing padding included.
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
 }
 
 #[wasm_bindgen]
@@ -289,6 +800,15 @@ pub struct StructMemberInfo {
     pub type_name: String,
     #[wasm_bindgen(readonly)]
     pub offset: u32,
+    /// Size in bytes, without padding to the next member. Runtime-sized
+    /// arrays count one element.
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
+    /// Bytes between elements, for array members.
+    #[wasm_bindgen(readonly)]
+    pub array_stride: Option<u32>,
 }
 
 #[wasm_bindgen]
@@ -304,21 +824,61 @@ impl StructMemberInfo {
 // ============================================================================
 
 /// Reflects WGSL shader and returns detailed information about entry points,
-/// bindings, inputs/outputs, and type definitions.
+/// bindings, inputs/outputs, and type definitions. `resourceNames` maps
+/// variable names or `"group:binding"` slots to render graph resource names,
+/// reported as each binding's `resourceName`.
 #[wasm_bindgen(js_name = reflectWgsl)]
-pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
-    let (module, _info) = parse_and_validate(wgsl)?;
+pub fn reflect_wgsl(wgsl: &str, resource_names: JsValue) -> Result<ReflectionData, JsValue> {
+    let names = resources::resource_names(resource_names)?;
+    reflect_named(wgsl, &names).map_err(throw)
+}
+
+fn reflect(wgsl: &str) -> Result<ReflectionData, Diagnostic> {
+    let (module, info) = parse_and_validate(wgsl)?;
+    Ok(reflect_module(&module, &info))
+}
+
+/// `reflect`, with bindings annotated from `names`.
+fn reflect_named(
+    wgsl: &str,
+    names: &resources::ResourceNames,
+) -> Result<ReflectionData, Diagnostic> {
+    if names.is_empty() {
+        return reflect(wgsl);
+    }
+    let (module, info) = parse_and_validate(wgsl)?;
+    names.check(&module)?;
+    let mut reflection = reflect_module(&module, &info);
+    names.annotate_reflection(&mut reflection);
+    Ok(reflection)
+}
+
+/// Names, stages and workgroup sizes of `wgsl`'s entry points, in
+/// declaration order. Only parses: the module is not validated and nothing
+/// else is reflected, so this is cheap enough to run on every edit.
+#[wasm_bindgen(js_name = listEntryPoints)]
+pub fn list_entry_points(wgsl: &str) -> Result<Vec<EntryPointSummary>, JsValue> {
+    let module = parse_wgsl(wgsl).map_err(throw)?;
+    Ok(module
+        .entry_points
+        .iter()
+        .map(|entry| EntryPointSummary {
+            id: function_id(&module, &entry.name),
+            name: entry.name.clone(),
+            stage: stage_name(entry.stage).to_string(),
+            workgroup_size: (entry.stage == naga::ShaderStage::Compute)
+                .then(|| entry.workgroup_size.to_vec()),
+        })
+        .collect())
+}
+
+/// Build reflection data for an already validated module.
+fn reflect_module(module: &Module, info: &ModuleInfo) -> ReflectionData {
 
     let mut entry_points = Vec::new();
 
-    for entry in &module.entry_points {
-        let stage = match entry.stage {
-            naga::ShaderStage::Vertex => "vertex",
-            naga::ShaderStage::Fragment => "fragment",
-            naga::ShaderStage::Compute => "compute",
-            naga::ShaderStage::Task => "task",
-            naga::ShaderStage::Mesh => "mesh",
-        };
+    for (index, entry) in module.entry_points.iter().enumerate() {
+        let stage = stage_name(entry.stage);
 
         let workgroup_size = if entry.stage == naga::ShaderStage::Compute {
             Some(vec![
@@ -334,21 +894,23 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
         let mut bindings = Vec::new();
         for (handle, var) in module.global_variables.iter() {
             if let Some(binding) = &var.binding {
-                // Check if this entry point uses this global
-                if entry.function.expressions.iter().any(
-                    |(_, expr)| matches!(expr, naga::Expression::GlobalVariable(h) if *h == handle),
-                ) {
-                    let (resource_type, type_name, is_readonly) = classify_binding(&module, var);
+                // Check if this entry point uses this global, directly or
+                // through the functions it calls
+                if !info.get_entry_point(index)[handle].is_empty() {
+                    let (resource_type, type_name, is_readonly) = classify_binding(module, var);
 
+                    let name = var.name.clone().unwrap_or_else(|| {
+                        format!("binding_{}_{}", binding.group, binding.binding)
+                    });
                     bindings.push(BindingInfo {
-                        name: var.name.clone().unwrap_or_else(|| {
-                            format!("binding_{}_{}", binding.group, binding.binding)
-                        }),
+                        id: hash::stable_id("binding", &name),
+                        name,
                         group: binding.group,
                         binding: binding.binding,
                         resource_type,
                         type_name,
                         is_readonly,
+                        resource_name: None,
                     });
                 }
             }
@@ -359,7 +921,7 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
         if entry.stage == naga::ShaderStage::Vertex {
             for arg in &entry.function.arguments {
                 if let Some(naga::Binding::Location { location, .. }) = arg.binding {
-                    let type_name = get_type_name(&module, arg.ty);
+                    let type_name = get_type_name(module, arg.ty);
                     vertex_inputs.push(VertexInputInfo {
                         name: arg
                             .name
@@ -374,37 +936,37 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
 
         // Collect fragment outputs
         let mut fragment_outputs = Vec::new();
-        if entry.stage == naga::ShaderStage::Fragment {
-            if let Some(ref result) = entry.function.result {
-                match &result.binding {
-                    Some(naga::Binding::Location { location, .. }) => {
-                        let type_name = get_type_name(&module, result.ty);
-                        fragment_outputs.push(FragmentOutputInfo {
-                            name: "output".to_string(),
-                            location: *location,
-                            type_name: type_name.unwrap_or_else(|| "unknown".to_string()),
-                        });
-                    }
-                    _ => {
-                        // Check if return type is a struct with location bindings
-                        if let naga::TypeInner::Struct { ref members, .. } =
-                            module.types[result.ty].inner
-                        {
-                            for member in members {
-                                if let Some(naga::Binding::Location { location, .. }) =
-                                    member.binding
-                                {
-                                    let type_name = get_type_name(&module, member.ty);
-                                    fragment_outputs.push(FragmentOutputInfo {
-                                        name: member
-                                            .name
-                                            .clone()
-                                            .unwrap_or_else(|| format!("output_{}", location)),
-                                        location,
-                                        type_name: type_name
-                                            .unwrap_or_else(|| "unknown".to_string()),
-                                    });
-                                }
+        if entry.stage == naga::ShaderStage::Fragment
+            && let Some(ref result) = entry.function.result
+        {
+            match &result.binding {
+                Some(naga::Binding::Location { location, .. }) => {
+                    let type_name = get_type_name(module, result.ty);
+                    fragment_outputs.push(FragmentOutputInfo {
+                        name: "output".to_string(),
+                        location: *location,
+                        type_name: type_name.unwrap_or_else(|| "unknown".to_string()),
+                    });
+                }
+                _ => {
+                    // Check if return type is a struct with location bindings
+                    if let naga::TypeInner::Struct { ref members, .. } =
+                        module.types[result.ty].inner
+                    {
+                        for member in members {
+                            if let Some(naga::Binding::Location { location, .. }) =
+                                member.binding
+                            {
+                                let type_name = get_type_name(module, member.ty);
+                                fragment_outputs.push(FragmentOutputInfo {
+                                    name: member
+                                        .name
+                                        .clone()
+                                        .unwrap_or_else(|| format!("output_{}", location)),
+                                    location,
+                                    type_name: type_name
+                                        .unwrap_or_else(|| "unknown".to_string()),
+                                });
                             }
                         }
                     }
@@ -413,6 +975,7 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
         }
 
         entry_points.push(EntryPointInfo {
+            id: function_id(module, &entry.name),
             name: entry.name.clone(),
             stage: stage.to_string(),
             workgroup_size,
@@ -422,17 +985,32 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
         });
     }
 
-    // Collect type information (structs mainly)
+    // Collect type information (structs mainly), with sizes and alignments;
+    // validated modules always lay out.
+    let layouter = layout::layouter(module).ok();
+    let layout_of = |ty: naga::Handle<naga::Type>| {
+        layouter
+            .as_ref()
+            .map_or((0, 1), |l| (l[ty].size, l[ty].alignment.round_up(1)))
+    };
     let mut types = Vec::new();
     for (handle, ty) in module.types.iter() {
-        if let naga::TypeInner::Struct { ref members, .. } = ty.inner {
+        if let naga::TypeInner::Struct { ref members, span } = ty.inner {
             let mut struct_members = Vec::new();
             for member in members {
-                let type_name = get_type_name(&module, member.ty);
+                let type_name = get_type_name(module, member.ty);
+                let (size, alignment) = layout_of(member.ty);
+                let array_stride = match module.types[member.ty].inner {
+                    naga::TypeInner::Array { stride, .. } => Some(stride),
+                    _ => None,
+                };
                 struct_members.push(StructMemberInfo {
                     name: member.name.clone().unwrap_or_else(|| "unnamed".to_string()),
                     type_name: type_name.unwrap_or_else(|| "unknown".to_string()),
                     offset: member.offset,
+                    size,
+                    alignment,
+                    array_stride,
                 });
             }
 
@@ -443,14 +1021,51 @@ pub fn reflect_wgsl(wgsl: &str) -> Result<ReflectionData, JsValue> {
                     .unwrap_or_else(|| format!("type_{:?}", handle)),
                 kind: "struct".to_string(),
                 members: Some(struct_members),
+                size: span,
+                alignment: layout_of(handle).1,
             });
         }
     }
 
-    Ok(ReflectionData {
+    let functions = module
+        .functions
+        .iter()
+        .filter_map(|(_, function)| function.name.as_deref())
+        .map(|name| FunctionInfo {
+            id: function_id(module, name),
+            name: name.to_string(),
+        })
+        .collect();
+
+    ReflectionData {
         entry_points,
         types,
-    })
+        overrides: specialize::reflect_overrides(module, info),
+        functions,
+    }
+}
+
+/// WGSL-style name of a shader stage
+fn stage_name(stage: naga::ShaderStage) -> &'static str {
+    match stage {
+        naga::ShaderStage::Vertex => "vertex",
+        naga::ShaderStage::Fragment => "fragment",
+        naga::ShaderStage::Compute => "compute",
+        naga::ShaderStage::Task => "task",
+        naga::ShaderStage::Mesh => "mesh",
+    }
+}
+
+/// Stable ID of the entry point or helper function `name`, the same in
+/// reflection and every report naming functions. Entry points hash with
+/// their stage, helpers with `"function"`; WGSL gives both one namespace.
+pub(crate) fn function_id(module: &Module, name: &str) -> String {
+    let kind = module
+        .entry_points
+        .iter()
+        .find(|ep| ep.name == name)
+        .map_or("function", |ep| stage_name(ep.stage));
+    hash::stable_id(kind, name)
 }
 
 /// Classify a binding's resource type, get its type name, and determine if it's readonly
@@ -703,3 +1318,59 @@ fn format_scalar(scalar: naga::Scalar) -> String {
         _ => format!("{:?}", scalar),
     }
 }
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflection_follows_helper_calls() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read> data: array<f32>;
+            @group(0) @binding(1) var<storage, read_write> out: array<f32>;
+            @group(0) @binding(2) var<uniform> unused: vec4<f32>;
+
+            fn load(i: u32) -> f32 { return data[i]; }
+            fn store(i: u32, v: f32) { out[i] = load(i) * v; }
+
+            @compute @workgroup_size(64)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                store(id.x, 2.0);
+            }
+        "#;
+        let reflection = reflect(source).unwrap();
+        let names: Vec<_> = reflection.entry_points[0]
+            .bindings
+            .iter()
+            .map(|b| b.name.as_str())
+            .collect();
+        assert_eq!(names, ["data", "out"]);
+    }
+
+    #[test]
+    fn reflection_ids_survive_edits() {
+        let before = "@group(0) @binding(0) var<uniform> tint: vec4<f32>;\n\
+                      fn shade() -> vec4<f32> { return tint; }\n\
+                      @fragment fn fs() -> @location(0) vec4<f32> { return shade(); }";
+        let after = format!(
+            "// moved down\n\n{}",
+            before.replace("binding(0)", "binding(3)")
+        );
+        let ids = |source: &str| {
+            let mut reflection = reflect(source).unwrap();
+            let ep = reflection.entry_points.remove(0);
+            let function = reflection.functions.remove(0);
+            (ep.id, ep.bindings[0].id.clone(), function.id)
+        };
+        assert_eq!(ids(before), ids(&after));
+
+        let (entry_point, _, function) = ids(before);
+        assert_ne!(entry_point, function);
+        let listed = list_entry_points(before).ok().unwrap();
+        assert_eq!(listed[0].id, entry_point);
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+
+use naga::valid::ModuleInfo;
+use naga::{
+    AddressSpace, ArraySize, Binding, GlobalVariable, ImageClass, ImageDimension, Interpolation,
+    Module, Sampling, ScalarKind, ShaderStage, StorageAccess, TypeInner,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::varyings::{self, Leaf};
+
+// ============================================================================
+// Stage Linking Types
+// ============================================================================
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LinkReport {
+    /// True if no issue is an error, so a pipeline of the two would link.
+    #[wasm_bindgen(readonly)]
+    pub linked: bool,
+    /// Varying issues by location, then binding issues by slot.
+    #[wasm_bindgen(readonly)]
+    pub issues: Vec<LinkIssue>,
+}
+
+#[wasm_bindgen]
+impl LinkReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LinkIssue {
+    /// `error` or `warning`.
+    #[wasm_bindgen(readonly)]
+    pub severity: String,
+    /// `missing-output`, `type-mismatch`, `interpolation-mismatch`,
+    /// `unused-output` or `binding-conflict`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// The varying's `@location`, for varying issues.
+    #[wasm_bindgen(readonly)]
+    pub location: Option<u32>,
+    /// The slot, for binding issues.
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub binding: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl LinkIssue {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Stage Linking Implementation
+// ============================================================================
+//
+// The checks WebGPU makes at pipeline creation, made on the WGSL: every
+// fragment input location must be written by the vertex stage with the
+// same type, interpolation and sampling (defaults filled in, so
+// `@location(0) uv: vec2<f32>` compares as perspective, center), and a
+// slot both stages use must agree on what its bind group layout entry
+// records, since the pipeline layout has one entry for it. That entry holds
+// the buffer kind, the texture's sample type, view dimension and
+// multisampling, or whether a sampler compares, never the WGSL type, so
+// `Camera` and an identically laid out `CameraData` share a slot. Outputs
+// nothing reads are legal but reported as warnings, as they cost
+// interpolators.
+
+struct Stage {
+    module: Module,
+    info: ModuleInfo,
+    index: usize,
+}
+
+fn load(wgsl: &str, entry: &str, stage: ShaderStage) -> Result<Stage, Diagnostic> {
+    let label = match stage {
+        ShaderStage::Vertex => "Vertex",
+        _ => "Fragment",
+    };
+    let (module, info) = crate::parse_and_validate(wgsl)
+        .map_err(|e| Diagnostic::error(format!("{label} shader: {}", e.message)))?;
+    let index = varyings::find(&module, entry, stage)?;
+    Ok(Stage {
+        module,
+        info,
+        index,
+    })
+}
+
+impl Stage {
+    fn name(&self) -> &str {
+        &self.module.entry_points[self.index].name
+    }
+
+    /// The `@location` leaves of the entry point's inputs or its result.
+    fn locations(&self, result: bool) -> BTreeMap<u32, Leaf> {
+        let function = &self.module.entry_points[self.index].function;
+        varyings::leaves(&self.module, function, result)
+            .into_iter()
+            .filter_map(|leaf| match leaf.binding {
+                Binding::Location { location, .. } => Some((location, leaf)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn type_name(&self, leaf: &Leaf) -> String {
+        crate::get_type_name(&self.module, leaf.ty).unwrap_or_default()
+    }
+
+    /// Bound globals the entry point uses, by slot, with their
+    /// `layout_entry`.
+    fn bindings(&self) -> BTreeMap<(u32, u32), (String, String)> {
+        let used = self.info.get_entry_point(self.index);
+        self.module
+            .global_variables
+            .iter()
+            .filter(|(handle, _)| !used[*handle].is_empty())
+            .filter_map(|(_, var)| {
+                let binding = var.binding.as_ref()?;
+                let name = var.name.clone().unwrap_or_default();
+                let entry = layout_entry(&self.module, var);
+                Some(((binding.group, binding.binding), (name, entry)))
+            })
+            .collect()
+    }
+}
+
+/// What the bind group layout entry for `var` records, in WebGPU's terms,
+/// e.g. `read-only-storage buffer` or `float texture 2d-array multisampled`.
+fn layout_entry(module: &Module, var: &GlobalVariable) -> String {
+    let (ty, count) = match module.types[var.ty].inner {
+        TypeInner::BindingArray { base, size } => match size {
+            ArraySize::Constant(n) => (base, format!(" x{n}")),
+            _ => (base, " x?".to_string()),
+        },
+        _ => (var.ty, String::new()),
+    };
+    let entry = match (var.space, &module.types[ty].inner) {
+        (AddressSpace::Uniform, _) => "uniform buffer".to_string(),
+        (AddressSpace::Storage { access }, _) if access.contains(StorageAccess::STORE) => {
+            "storage buffer".to_string()
+        }
+        (AddressSpace::Storage { .. }, _) => "read-only-storage buffer".to_string(),
+        (
+            _,
+            &TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+        ) => {
+            let dimension = match (dim, arrayed) {
+                (ImageDimension::D1, _) => "1d",
+                (ImageDimension::D2, false) => "2d",
+                (ImageDimension::D2, true) => "2d-array",
+                (ImageDimension::D3, _) => "3d",
+                (ImageDimension::Cube, false) => "cube",
+                (ImageDimension::Cube, true) => "cube-array",
+            };
+            let multisampled = |multi| if multi { " multisampled" } else { "" };
+            match class {
+                ImageClass::Sampled { kind, multi } => {
+                    let sample = match kind {
+                        ScalarKind::Sint => "sint",
+                        ScalarKind::Uint => "uint",
+                        _ => "float",
+                    };
+                    format!("{sample} texture {dimension}{}", multisampled(multi))
+                }
+                ImageClass::Depth { multi } => {
+                    format!("depth texture {dimension}{}", multisampled(multi))
+                }
+                ImageClass::Storage { format, access } => {
+                    let access = match (
+                        access.contains(StorageAccess::LOAD),
+                        access.contains(StorageAccess::STORE),
+                    ) {
+                        (true, true) => "read-write",
+                        (true, false) => "read-only",
+                        _ => "write-only",
+                    };
+                    let format = format!("{format:?}").to_lowercase();
+                    format!("{access} {format} storage texture {dimension}")
+                }
+                ImageClass::External => "external texture".to_string(),
+            }
+        }
+        (_, TypeInner::Sampler { comparison: true }) => "comparison sampler".to_string(),
+        (_, TypeInner::Sampler { comparison: false }) => "sampler".to_string(),
+        (_, TypeInner::AccelerationStructure { .. }) => "acceleration structure".to_string(),
+        _ => "resource".to_string(),
+    };
+    format!("{entry}{count}")
+}
+
+/// `binding`'s interpolation and sampling, with WGSL's defaults for the
+/// sampling.
+fn interpolation(binding: &Binding) -> String {
+    let Binding::Location {
+        interpolation,
+        sampling,
+        ..
+    } = binding
+    else {
+        return String::new();
+    };
+    let sampling = sampling.or(match interpolation {
+        Some(Interpolation::Flat) => Some(Sampling::First),
+        Some(_) => Some(Sampling::Center),
+        None => None,
+    });
+    match (interpolation, sampling) {
+        (Some(interpolation), Some(sampling)) => format!("{interpolation:?}, {sampling:?}"),
+        (Some(interpolation), None) => format!("{interpolation:?}"),
+        (None, _) => "none".to_string(),
+    }
+    .to_lowercase()
+}
+
+fn varying_issue(severity: &str, kind: &str, message: String, location: u32) -> LinkIssue {
+    LinkIssue {
+        severity: severity.to_string(),
+        kind: kind.to_string(),
+        message,
+        location: Some(location),
+        group: None,
+        binding: None,
+    }
+}
+
+fn link(vertex: &Stage, fragment: &Stage) -> LinkReport {
+    let mut issues = Vec::new();
+    let outputs = vertex.locations(true);
+    let inputs = fragment.locations(false);
+    let (vs, fs) = (vertex.name(), fragment.name());
+    for (&location, input) in &inputs {
+        let Some(output) = outputs.get(&location) else {
+            let message = format!(
+                "Fragment input '{}' reads @location({location}), which '{vs}' does not write",
+                input.name
+            );
+            issues.push(varying_issue("error", "missing-output", message, location));
+            continue;
+        };
+        let (wrote, read) = (vertex.type_name(output), fragment.type_name(input));
+        if wrote != read {
+            let message = format!(
+                "Fragment input '{}' at @location({location}) is {read}, but '{vs}' writes {wrote}",
+                input.name
+            );
+            issues.push(varying_issue("error", "type-mismatch", message, location));
+        }
+        let (wrote, read) = (
+            interpolation(&output.binding),
+            interpolation(&input.binding),
+        );
+        if wrote != read {
+            let message = format!(
+                "Fragment input '{}' at @location({location}) is interpolated as {read}, but '{vs}' writes it as {wrote}",
+                input.name
+            );
+            issues.push(varying_issue(
+                "error",
+                "interpolation-mismatch",
+                message,
+                location,
+            ));
+        }
+    }
+    for (&location, output) in &outputs {
+        if !inputs.contains_key(&location) {
+            let message = format!(
+                "Vertex output '{}' at @location({location}) is never read by '{fs}'",
+                output.name
+            );
+            issues.push(varying_issue("warning", "unused-output", message, location));
+        }
+    }
+    issues.sort_by_key(|issue| issue.location);
+
+    let fragment_bindings = fragment.bindings();
+    for (&(group, binding), (name, kind)) in &vertex.bindings() {
+        let Some((other, other_kind)) = fragment_bindings.get(&(group, binding)) else {
+            continue;
+        };
+        if kind != other_kind {
+            issues.push(LinkIssue {
+                severity: "error".to_string(),
+                kind: "binding-conflict".to_string(),
+                message: format!(
+                    "@group({group}) @binding({binding}) is '{name}' ({kind}) in '{vs}' but '{other}' ({other_kind}) in '{fs}'"
+                ),
+                location: None,
+                group: Some(group),
+                binding: Some(binding),
+            });
+        }
+    }
+
+    LinkReport {
+        linked: issues.iter().all(|issue| issue.severity != "error"),
+        issues,
+    }
+}
+
+pub(crate) fn link_stages_wgsl(
+    vertex_wgsl: &str,
+    vs_entry: &str,
+    fragment_wgsl: &str,
+    fs_entry: &str,
+) -> Result<LinkReport, Diagnostic> {
+    let vertex = load(vertex_wgsl, vs_entry, ShaderStage::Vertex)?;
+    let fragment = load(fragment_wgsl, fs_entry, ShaderStage::Fragment)?;
+    Ok(link(&vertex, &fragment))
+}
+
+/// Checks that `vsEntry` in `vertexWgsl` and `fsEntry` in `fragmentWgsl`
+/// can share a render pipeline, before any GPU sees them: fragment inputs
+/// against vertex outputs by location, type, interpolation and sampling,
+/// and the resources both stages bind at a slot. Mismatches come back as
+/// `issues`; `linked` is false if any is an error. The two sources may be
+/// the same. Throws if either fails to validate or lacks its entry point.
+#[wasm_bindgen(js_name = linkStages)]
+pub fn link_stages(
+    vertex_wgsl: &str,
+    vs_entry: &str,
+    fragment_wgsl: &str,
+    fs_entry: &str,
+) -> Result<LinkReport, JsValue> {
+    link_stages_wgsl(vertex_wgsl, vs_entry, fragment_wgsl, fs_entry).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX: &str = r#"
+        struct VsOut {
+            @builtin(position) position: vec4<f32>,
+            @location(0) uv: vec2<f32>,
+            @location(1) @interpolate(flat) material: u32,
+            @location(2) debug: vec4<f32>,
+        }
+        @group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+
+        @vertex
+        fn vs(@location(0) p: vec3<f32>) -> VsOut {
+            return VsOut(camera * vec4<f32>(p, 1.0), p.xy, 0u, vec4<f32>());
+        }
+    "#;
+
+    #[test]
+    fn matching_stages_link_with_warnings_only() {
+        let fragment = r#"
+            @group(0) @binding(0) var<uniform> view: mat4x4<f32>;
+            @fragment
+            fn fs(@location(0) uv: vec2<f32>, @location(1) @interpolate(flat) m: u32)
+                -> @location(0) vec4<f32> {
+                return view[0] * uv.x + f32(m);
+            }
+        "#;
+        let report = link_stages_wgsl(VERTEX, "vs", fragment, "fs").unwrap();
+        assert!(report.linked, "{:?}", report.issues);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, "unused-output");
+        assert_eq!(report.issues[0].location, Some(2));
+    }
+
+    #[test]
+    fn mismatches_are_reported_by_kind() {
+        let fragment = r#"
+            @group(0) @binding(0) var<storage> camera: array<f32>;
+            @fragment
+            fn fs(@location(0) uv: vec3<f32>,
+                  @location(1) @interpolate(linear) m: f32,
+                  @location(2) d: vec4<f32>,
+                  @location(5) extra: f32) -> @location(0) vec4<f32> {
+                return vec4<f32>(uv, m + extra + camera[0]) + d;
+            }
+        "#;
+        let report = link_stages_wgsl(VERTEX, "vs", fragment, "fs").unwrap();
+        assert!(!report.linked);
+        let kinds: Vec<_> = report
+            .issues
+            .iter()
+            .map(|i| (i.kind.as_str(), i.location))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("type-mismatch", Some(0)),
+                ("type-mismatch", Some(1)),
+                ("interpolation-mismatch", Some(1)),
+                ("missing-output", Some(5)),
+                ("binding-conflict", None),
+            ]
+        );
+        assert!(
+            report.issues[2].message.contains("linear, center"),
+            "{}",
+            report.issues[2].message
+        );
+        assert_eq!(
+            report.issues[4].message,
+            "@group(0) @binding(0) is 'camera' (uniform buffer) in 'vs' but 'camera' (read-only-storage buffer) in 'fs'"
+        );
+
+        assert!(link_stages_wgsl(VERTEX, "fs", fragment, "fs").is_err());
+        let error = link_stages_wgsl(VERTEX, "vs", "fn", "fs").err().unwrap();
+        assert!(error.message.starts_with("Fragment shader: "));
+    }
+
+    #[test]
+    fn bindings_compare_by_layout_entry() {
+        let vertex = r#"
+            struct Camera { view: mat4x4<f32> }
+            @group(0) @binding(0) var<uniform> camera: Camera;
+            @group(0) @binding(1) var tex: texture_2d<f32>;
+            @group(0) @binding(2) var samp: sampler;
+            @vertex
+            fn vs() -> @builtin(position) vec4<f32> {
+                let t = textureSampleLevel(tex, samp, vec2<f32>(), 0.0);
+                return camera.view[0] + t;
+            }
+        "#;
+        let conflicts = |bindings: &str, color: &str| -> Vec<String> {
+            let fragment = format!(
+                "struct CameraData {{ matrix: mat4x4<f32> }}\n\
+                 @group(0) @binding(0) var<uniform> camera: CameraData;\n\
+                 {bindings}\n\
+                 @fragment fn fs() -> @location(0) vec4<f32> {{\n\
+                     return camera.matrix[0] + {color};\n\
+                 }}"
+            );
+            link_stages_wgsl(vertex, "vs", &fragment, "fs")
+                .unwrap()
+                .issues
+                .into_iter()
+                .filter(|issue| issue.kind == "binding-conflict")
+                .map(|issue| issue.message)
+                .collect()
+        };
+        let same = conflicts(
+            "@group(0) @binding(1) var tex: texture_2d<f32>;\n\
+             @group(0) @binding(2) var samp: sampler;",
+            "textureSample(tex, samp, vec2<f32>())",
+        );
+        assert!(same.is_empty(), "{same:?}");
+        assert_eq!(
+            conflicts(
+                "@group(0) @binding(1) var tex: texture_2d<u32>;\n\
+                 @group(0) @binding(2) var samp: sampler_comparison;\n\
+                 @group(0) @binding(3) var depth: texture_depth_2d;",
+                "vec4<f32>(textureLoad(tex, vec2<i32>(), 0)) + \
+                 textureSampleCompare(depth, samp, vec2<f32>(), 0.5)",
+            ),
+            [
+                "@group(0) @binding(1) is 'tex' (float texture 2d) in 'vs' but 'tex' (uint texture 2d) in 'fs'",
+                "@group(0) @binding(2) is 'samp' (sampler) in 'vs' but 'samp' (comparison sampler) in 'fs'",
+            ]
+        );
+        assert_eq!(
+            conflicts(
+                "@group(0) @binding(1) var tex: texture_multisampled_2d<f32>;",
+                "textureLoad(tex, vec2<i32>(), 0)",
+            ),
+            [
+                "@group(0) @binding(1) is 'tex' (float texture 2d) in 'vs' but 'tex' (float texture 2d multisampled) in 'fs'"
+            ]
+        );
+    }
+}
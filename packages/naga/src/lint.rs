@@ -0,0 +1,472 @@
+use std::collections::HashSet;
+
+use naga::{Block, Expression, Handle, Span, Statement};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::{SourceSpan, throw};
+use crate::lexer::{self, TokenKind};
+use crate::rewrite::{self, operands, visit_statement_operands};
+use crate::spelling::{self, IGNORE_DIRECTIVE};
+
+// ============================================================================
+// Lint Types
+// ============================================================================
+
+/// Rule names, as accepted by the `allow` option and `metis-ignore`
+/// comments. The `misspelled-*` ones need the `spellcheck` option.
+pub(crate) const RULES: &[&str] = &[
+    "unused-global",
+    "unused-function",
+    "unreachable-code",
+    "shadowed-variable",
+    "unused-argument",
+    "misspelled-attribute",
+    "misspelled-diagnostic",
+    "misspelled-directive",
+];
+
+/// Options object accepted by `lintWgsl`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LintOptions {
+    /// Rules not to report.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Also report likely typos; see `spellcheckWgsl`.
+    #[serde(default)]
+    pub spellcheck: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct LintWarning {
+    /// e.g. `"unused-global"`.
+    #[wasm_bindgen(readonly)]
+    pub rule: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    #[wasm_bindgen(readonly)]
+    pub span: Option<SourceSpan>,
+}
+
+#[wasm_bindgen]
+impl LintWarning {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Lint Implementation
+// ============================================================================
+//
+// Most rules read the validated IR, whose spans point back into the source.
+// Shadowing cannot: naga resolves names while lowering and keeps no scopes,
+// so that rule walks the source's tokens instead, tracking the names each
+// brace-delimited scope declares. A `// metis-ignore: rule, ...` comment
+// silences the rules named, or all of them if none are, on its own line, or
+// on the next one when nothing precedes it.
+
+struct Linter<'a> {
+    source: &'a str,
+    warnings: Vec<LintWarning>,
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, rule: &str, message: String, span: Span) {
+        self.warnings.push(LintWarning {
+            rule: rule.to_string(),
+            message: crate::catalog::localize(message),
+            span: SourceSpan::new(self.source, span),
+        });
+    }
+
+    fn unused_globals(&mut self, module: &mut naga::Module) {
+        let mut used = HashSet::new();
+        for function in rewrite::functions_mut(module) {
+            for (_, expr) in function.expressions.iter() {
+                if let Expression::GlobalVariable(global) = *expr {
+                    used.insert(global);
+                }
+            }
+        }
+        for (handle, global) in module.global_variables.iter() {
+            if !used.contains(&handle) {
+                let name = global.name.as_deref().unwrap_or("?");
+                let span = module.global_variables.get_span(handle);
+                self.warn(
+                    "unused-global",
+                    format!("Global '{}' is never used", name),
+                    span,
+                );
+            }
+        }
+    }
+
+    fn unused_functions(&mut self, module: &naga::Module) {
+        let mut reached: HashSet<Handle<naga::Function>> = HashSet::new();
+        let mut pending: Vec<&naga::Function> =
+            module.entry_points.iter().map(|ep| &ep.function).collect();
+        while let Some(function) = pending.pop() {
+            rewrite::for_each_statement(&mut function.body.clone(), &mut |statement| {
+                if let Statement::Call { function, .. } = *statement
+                    && reached.insert(function)
+                {
+                    pending.push(&module.functions[function]);
+                }
+            });
+        }
+        for (handle, function) in module.functions.iter() {
+            if !reached.contains(&handle) {
+                let name = function.name.as_deref().unwrap_or("?");
+                let message = format!("Function '{}' is not called from any entry point", name);
+                let span = module.functions.get_span(handle);
+                self.warn("unused-function", message, span);
+            }
+        }
+    }
+
+    /// Statements following a `return`, `discard`, `break` or `continue` in
+    /// the same block; one warning per block.
+    fn unreachable(&mut self, function: &naga::Function, block: &Block) {
+        let mut terminated = false;
+        for (statement, span) in block.span_iter() {
+            if terminated {
+                self.warn(
+                    "unreachable-code",
+                    "Unreachable code".to_string(),
+                    statement_span(function, statement, *span),
+                );
+                return;
+            }
+            terminated = matches!(
+                statement,
+                Statement::Return { .. } | Statement::Kill | Statement::Break | Statement::Continue
+            );
+            for child in rewrite::child_blocks(&mut statement.clone()) {
+                self.unreachable(function, child);
+            }
+        }
+    }
+
+    fn unused_arguments(&mut self, ep: &naga::EntryPoint) {
+        let function = &ep.function;
+        let mut used = HashSet::new();
+        for (_, expr) in function.expressions.iter() {
+            used.extend(operands(expr));
+        }
+        rewrite::for_each_statement(&mut function.body.clone(), &mut |statement| {
+            visit_statement_operands(statement, &mut |h| {
+                used.insert(*h);
+            });
+        });
+        for (index, argument) in function.arguments.iter().enumerate() {
+            let expr = function.expressions.iter().find(|(_, expr)| {
+                matches!(**expr, Expression::FunctionArgument(i) if i as usize == index)
+            });
+            if expr.is_some_and(|(h, _)| used.contains(&h)) {
+                continue;
+            }
+            let name = argument.name.as_deref().unwrap_or("?");
+            let message = format!(
+                "Argument '{}' of entry point '{}' is never used",
+                name, ep.name
+            );
+            let span = expr.map_or(Span::UNDEFINED, |(h, _)| function.expressions.get_span(h));
+            self.warn("unused-argument", message, span);
+        }
+    }
+
+    fn shadowed(&mut self) {
+        let tokens = lexer::tokenize(self.source);
+        let globals: Vec<&str> = lexer::module_declarations(&tokens)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let token_span = |i: usize| Span::new(tokens[i].start as u32, tokens[i].end() as u32);
+
+        // Names per open scope inside the current function, and those
+        // declared ahead of the brace that opens their scope: parameters and
+        // `for` initializers.
+        let mut scopes: Vec<Vec<(&str, usize)>> = Vec::new();
+        let mut pending: Vec<(&str, usize)> = Vec::new();
+        let mut parens = 0usize;
+        let mut params = false;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = tokens[i];
+            match token.kind {
+                TokenKind::Punct('{') if !scopes.is_empty() || params => {
+                    scopes.push(std::mem::take(&mut pending));
+                    params = false;
+                }
+                TokenKind::Punct('}') => {
+                    scopes.pop();
+                }
+                TokenKind::Punct('(') => parens += 1,
+                TokenKind::Punct(')') => parens = parens.saturating_sub(1),
+                TokenKind::Ident if token.text == "fn" && scopes.is_empty() => {
+                    params = true;
+                    parens = 0;
+                    i += 1;
+                }
+                // A parameter name is followed by its `:` type.
+                TokenKind::Ident
+                    if params
+                        && parens == 1
+                        && tokens.get(i + 1).is_some_and(|t| t.is_punct(':')) =>
+                {
+                    pending.push((token.text, i));
+                }
+                TokenKind::Ident
+                    if !scopes.is_empty() && matches!(token.text, "let" | "var" | "const") =>
+                {
+                    let at = lexer::skip_template(&tokens, i + 1);
+                    if let Some(name) = tokens.get(at).filter(|t| t.kind == TokenKind::Ident) {
+                        let outer = scopes
+                            .iter()
+                            .flatten()
+                            .chain(&pending)
+                            .find(|(declared, _)| *declared == name.text);
+                        let message = match outer {
+                            Some(&(_, at)) => Some(format!(
+                                "'{}' shadows a declaration on line {}",
+                                name.text,
+                                self.source[..tokens[at].start].matches('\n').count() + 1
+                            )),
+                            None if globals.contains(&name.text) => Some(format!(
+                                "'{}' shadows a module-scope declaration",
+                                name.text
+                            )),
+                            None => None,
+                        };
+                        if let Some(message) = message {
+                            self.warn("shadowed-variable", message, token_span(at));
+                        }
+                        // `for (var i ...)` declares into the loop's scope.
+                        let into = if parens > 0 {
+                            &mut pending
+                        } else {
+                            scopes.last_mut().unwrap()
+                        };
+                        into.push((name.text, at));
+                        i = at;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Lines `metis-ignore` comments silence, with the rules they name; an
+/// empty list silences every rule.
+fn suppressions(source: &str) -> Vec<(u32, Vec<&str>)> {
+    lexer::line_comments(source)
+        .into_iter()
+        .filter_map(|(at, text)| {
+            let rest = text.trim_start().strip_prefix(IGNORE_DIRECTIVE)?;
+            let rules = match rest.trim_start().strip_prefix(':') {
+                Some(list) => list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty())
+                    .collect(),
+                None if rest.trim().is_empty() => Vec::new(),
+                None => return None,
+            };
+            let line_start = source[..at].rfind('\n').map_or(0, |i| i + 1);
+            let line = source[..at].matches('\n').count() as u32 + 1;
+            // `at` is past the slashes; anything before them is code.
+            let alone = source[line_start..at - 2].trim().is_empty();
+            Some((if alone { line + 1 } else { line }, rules))
+        })
+        .collect()
+}
+
+/// `span`, or for an `Emit` without one, the span of what it evaluates.
+fn statement_span(function: &naga::Function, statement: &Statement, span: Span) -> Span {
+    let mut span = span;
+    if let Statement::Emit(range) = statement {
+        for h in range.clone() {
+            span.subsume(function.expressions.get_span(h));
+        }
+    }
+    span
+}
+
+pub(crate) fn lint(wgsl: &str, options: &LintOptions) -> Result<Vec<LintWarning>, Diagnostic> {
+    if let Some(rule) = options.allow.iter().find(|r| !RULES.contains(&r.as_str())) {
+        return Err(Diagnostic::error(format!(
+            "Unknown lint rule '{}'; expected one of {}",
+            rule,
+            RULES.join(", ")
+        )));
+    }
+    let (mut module, _info) = crate::parse_and_validate(wgsl)?;
+    let mut linter = Linter {
+        source: wgsl,
+        warnings: Vec::new(),
+    };
+    linter.unused_globals(&mut module);
+    linter.unused_functions(&module);
+    for (_, function) in module.functions.iter() {
+        linter.unreachable(function, &function.body);
+    }
+    for ep in &module.entry_points {
+        linter.unreachable(&ep.function, &ep.function.body);
+        linter.unused_arguments(ep);
+    }
+    linter.shadowed();
+    if options.spellcheck {
+        linter.warnings.extend(spelling::spellcheck(wgsl));
+    }
+
+    let suppressed = suppressions(wgsl);
+    let mut warnings = linter.warnings;
+    warnings.retain(|w| !options.allow.contains(&w.rule));
+    warnings.retain(|w| {
+        let Some(line) = w.span.as_ref().map(|s| s.line) else {
+            return true;
+        };
+        !suppressed
+            .iter()
+            .any(|(l, rules)| *l == line && (rules.is_empty() || rules.contains(&w.rule.as_str())))
+    });
+    warnings.sort_by_key(|w| w.span.as_ref().map_or(u32::MAX, |s| s.start));
+    Ok(warnings)
+}
+
+/// Reports shader hygiene problems that validation accepts, as warnings in
+/// source order: `unused-global`, `unused-function` (unreachable from every
+/// entry point), `unreachable-code` (after `return`, `discard`, `break` or
+/// `continue`), `shadowed-variable` and `unused-argument` (of entry points).
+/// `options` is `{ allow?: string[], spellcheck?: boolean }`: `allow`
+/// suppresses rules by name, and `spellcheck` adds the `misspelled-*` rules
+/// of `spellcheckWgsl`. A `// metis-ignore: rule, ...` comment suppresses
+/// the rules named, or every rule, on its line, or on the next line if the
+/// comment stands alone. Throws if the shader does not validate.
+#[wasm_bindgen(js_name = lintWgsl)]
+pub fn lint_wgsl(wgsl: &str, options: JsValue) -> Result<Vec<LintWarning>, JsValue> {
+    let options: Option<LintOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid lint options: {e}")))?;
+    lint(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+@group(0) @binding(0) var<uniform> scale: f32;
+@group(0) @binding(1) var<uniform> unused: f32;
+
+fn helper(x: f32) -> f32 {
+    let scale = 2.0;
+    return x * scale;
+}
+
+fn orphan() {}
+
+@fragment
+fn fs(@location(0) uv: vec2<f32>, @location(1) tint: vec4<f32>) -> @location(0) vec4<f32> {
+    var acc = 0.0;
+    for (var i = 0; i < 4; i++) {
+        let acc = helper(f32(i));
+        if acc > 1.0 {
+            discard;
+            let after = acc * 2.0;
+        }
+    }
+    return vec4<f32>(uv, acc * scale, 1.0);
+}
+"#;
+
+    fn rules(warnings: &[LintWarning]) -> Vec<(&str, u32)> {
+        warnings
+            .iter()
+            .map(|w| (w.rule.as_str(), w.span.as_ref().unwrap().line))
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_rule_in_source_order() {
+        let warnings = lint(SHADER, &LintOptions::default()).unwrap();
+        assert_eq!(
+            rules(&warnings),
+            [
+                ("unused-global", 3),
+                ("shadowed-variable", 6),
+                ("unused-function", 10),
+                ("unused-argument", 13),
+                ("shadowed-variable", 16),
+                ("unreachable-code", 19),
+            ]
+        );
+        assert_eq!(
+            warnings[4].message,
+            "'acc' shadows a declaration on line 14"
+        );
+        assert!(warnings[3].message.contains("'tint'"));
+    }
+
+    #[test]
+    fn allowed_rules_are_suppressed() {
+        let options = LintOptions {
+            allow: vec!["shadowed-variable".to_string(), "unused-global".to_string()],
+            ..Default::default()
+        };
+        let warnings = lint(SHADER, &options).unwrap();
+        assert!(warnings.iter().all(|w| !options.allow.contains(&w.rule)));
+        assert_eq!(warnings.len(), 3);
+
+        let unknown = LintOptions {
+            allow: vec!["nope".to_string()],
+            ..Default::default()
+        };
+        assert!(lint(SHADER, &unknown).is_err());
+        assert!(lint("fn main() {}", &LintOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn ignore_comments_suppress_rules() {
+        let source = "\
+// metis-ignore: unused-global
+@group(0) @binding(1) var<uniform> unused: f32;
+@group(0) @binding(2) var<uniform> spare: f32; // metis-ignore
+@group(0) @binding(3) var<uniform> other: f32; // metis-ignore: unused-function
+// metis-ignor: unused-function
+fn orphan() {}
+@compute @workgroup_size(1) fn main() {}
+";
+        let rules = |options: &LintOptions| -> Vec<(String, u32)> {
+            lint(source, options)
+                .unwrap()
+                .into_iter()
+                .map(|w| (w.rule, w.span.unwrap().line))
+                .collect()
+        };
+        let plain = rules(&LintOptions::default());
+        assert_eq!(
+            plain,
+            [
+                ("unused-global".to_string(), 4),
+                ("unused-function".to_string(), 6)
+            ]
+        );
+        let options = LintOptions {
+            spellcheck: true,
+            ..Default::default()
+        };
+        assert_eq!(rules(&options)[1], ("misspelled-directive".to_string(), 5));
+    }
+}
@@ -0,0 +1,132 @@
+use naga::Module;
+use naga::valid::ModuleInfo;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::batch::JobOptions;
+use crate::hash::sha256_hex;
+use crate::provenance::Provenance;
+
+/// Bumped whenever the manifest shape changes, so diff tools can refuse to
+/// compare manifests of different formats.
+pub(crate) const MANIFEST_VERSION: u32 = 1;
+
+// ============================================================================
+// Manifest Types
+// ============================================================================
+
+/// Build manifest for a set of compiled artifacts. Entries are sorted by
+/// `(name, target, entryPoint)` so two builds of the same project produce
+/// line-diffable JSON.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct Manifest {
+    #[wasm_bindgen(readonly)]
+    pub version: u32,
+    #[wasm_bindgen(readonly)]
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[wasm_bindgen]
+impl Manifest {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Pretty-printed JSON with a stable key order, ready to be written next to the artifacts.
+    #[wasm_bindgen(js_name = toJsonString)]
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest is always serializable")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ManifestEntry {
+    /// Caller-provided job name (usually the asset path).
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub target: String,
+    /// The entry point requested for this artifact, if any.
+    #[wasm_bindgen(readonly)]
+    pub entry_point: Option<String>,
+    /// Every entry point contained in the artifact.
+    #[wasm_bindgen(readonly)]
+    pub entry_points: Vec<String>,
+    #[wasm_bindgen(readonly)]
+    pub source_hash: String,
+    #[wasm_bindgen(readonly)]
+    pub artifact_hash: String,
+    #[wasm_bindgen(readonly)]
+    pub byte_size: u32,
+    /// Hash of the reflection data for the contained entry points. Changes
+    /// whenever bindings or interfaces change, even if the artifact hash
+    /// changes for unrelated reasons.
+    #[wasm_bindgen(readonly)]
+    pub reflection_digest: String,
+    /// Present when the job was compiled with `attest: true`.
+    #[wasm_bindgen(readonly)]
+    pub provenance: Option<Provenance>,
+}
+
+#[wasm_bindgen]
+impl ManifestEntry {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Manifest Implementation
+// ============================================================================
+
+/// Describe one compiled artifact.
+pub(crate) fn manifest_entry(
+    name: &str,
+    options: &JobOptions,
+    source: &str,
+    module: &Module,
+    info: &ModuleInfo,
+    artifact: &[u8],
+    provenance: Option<Provenance>,
+) -> ManifestEntry {
+    let entry_point = options.entry_point.as_deref();
+    let mut reflection = crate::reflect_module(module, info);
+    if let Some(ep) = entry_point.filter(|ep| !ep.is_empty()) {
+        reflection.entry_points.retain(|e| e.name == ep);
+    }
+    let reflection_json =
+        serde_json::to_vec(&reflection).expect("reflection is always serializable");
+
+    ManifestEntry {
+        name: name.to_string(),
+        target: options.target.as_str().to_string(),
+        entry_point: options.entry_point.clone(),
+        entry_points: reflection
+            .entry_points
+            .iter()
+            .map(|e| e.name.clone())
+            .collect(),
+        source_hash: sha256_hex(source.as_bytes()),
+        artifact_hash: sha256_hex(artifact),
+        byte_size: artifact.len() as u32,
+        reflection_digest: sha256_hex(&reflection_json),
+        provenance,
+    }
+}
+
+/// Collect entries into a manifest with a deterministic order.
+pub(crate) fn build_manifest(mut entries: Vec<ManifestEntry>) -> Manifest {
+    entries.sort_by(|a, b| {
+        (&a.name, &a.target, &a.entry_point).cmp(&(&b.name, &b.target, &b.entry_point))
+    });
+    Manifest {
+        version: MANIFEST_VERSION,
+        entries,
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use spirv::{Decoration, Op};
+
+use crate::Diagnostic;
+use crate::spv;
+
+// ============================================================================
+// Math Mode
+// ============================================================================
+//
+// Floating-point optimization freedom per compile, mapped onto what each
+// backend can express:
+// - SPIR-V: `precise` decorates every float arithmetic result with
+//   `NoContraction`, like GLSL's `precise`. Vulkan already allows contraction
+//   otherwise, and `FPFastMathMode` needs the Kernel capability, so `fast`
+//   leaves the binary as naga writes it.
+// - MSL: `#pragma METAL fp math_mode(...)` and `fp contract(...)` after the
+//   header. Compilers predating the pragmas ignore them, so hosts targeting
+//   those should also set `MTLCompileOptions.mathMode` (or `fastMathEnabled`).
+
+/// Floating-point optimizations a job allows.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MathMode {
+    /// Whatever the backend and driver default to.
+    #[default]
+    Default,
+    /// Allow reassociation and FMA contraction.
+    Fast,
+    /// Evaluate float arithmetic as written.
+    Precise,
+}
+
+/// Float arithmetic whose results `NoContraction` applies to.
+const FLOAT_ARITHMETIC: &[Op] = &[
+    Op::FNegate,
+    Op::FAdd,
+    Op::FSub,
+    Op::FMul,
+    Op::FDiv,
+    Op::FRem,
+    Op::FMod,
+    Op::VectorTimesScalar,
+    Op::MatrixTimesScalar,
+    Op::VectorTimesMatrix,
+    Op::MatrixTimesVector,
+    Op::MatrixTimesMatrix,
+    Op::OuterProduct,
+    Op::Dot,
+];
+
+/// Apply `mode` to a SPIR-V module in place.
+pub(crate) fn apply_spirv(words: &mut Vec<u32>, mode: MathMode) -> Result<(), Diagnostic> {
+    if mode != MathMode::Precise {
+        return Ok(());
+    }
+    let decorations: Vec<u32> = spv::instructions(words)?
+        .iter()
+        .filter(|inst| inst.op().is_some_and(|op| FLOAT_ARITHMETIC.contains(&op)))
+        .flat_map(|inst| {
+            spv::encode(
+                Op::Decorate,
+                &[inst.operands()[1], Decoration::NoContraction as u32],
+            )
+        })
+        .collect();
+    let after = [
+        spv::PREAMBLE,
+        spv::DEBUG_SOURCE,
+        spv::DEBUG_NAMES,
+        spv::ANNOTATIONS,
+    ]
+    .concat();
+    let at = spv::insertion_point(words, &after)?;
+    words.splice(at..at, decorations);
+    Ok(())
+}
+
+/// Apply `mode` to MSL source in place.
+pub(crate) fn apply_msl(source: &mut String, mode: MathMode) {
+    let pragmas = match mode {
+        MathMode::Default => return,
+        MathMode::Fast => "#pragma METAL fp math_mode(fast)\n#pragma METAL fp contract(fast)\n",
+        MathMode::Precise => "#pragma METAL fp math_mode(safe)\n#pragma METAL fp contract(off)\n",
+    };
+    let at = crate::specialize::header_end(source);
+    source.insert_str(at, &format!("\n{pragmas}"));
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            data[0] = data[1] * data[2] + data[3];
+        }
+    "#;
+
+    fn no_contraction_count(words: &[u32]) -> usize {
+        spv::instructions(words)
+            .unwrap()
+            .iter()
+            .filter(|inst| {
+                inst.op() == Some(Op::Decorate)
+                    && inst.operands()[1] == Decoration::NoContraction as u32
+            })
+            .count()
+    }
+
+    #[test]
+    fn precise_spirv_decorates_float_arithmetic() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let original = spv::words_from_bytes(&bytes).unwrap();
+
+        let mut fast = original.clone();
+        apply_spirv(&mut fast, MathMode::Fast).unwrap();
+        assert_eq!(fast, original);
+
+        let mut precise = original.clone();
+        apply_spirv(&mut precise, MathMode::Precise).unwrap();
+        // The multiply and the add.
+        assert_eq!(no_contraction_count(&precise), 2);
+        naga::front::spv::parse_u8_slice(&spv::bytes_from_words(&precise), &Default::default())
+            .unwrap();
+    }
+
+    #[test]
+    fn msl_pragmas_follow_the_header() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let original = crate::write_msl(&module, &info, None).unwrap();
+
+        let mut precise = original.clone();
+        apply_msl(&mut precise, MathMode::Precise);
+        let pragma = precise.find("#pragma METAL fp contract(off)").unwrap();
+        assert!(precise.find("#include").unwrap() < pragma);
+        assert!(pragma < precise.find("struct").unwrap());
+
+        let mut default = original.clone();
+        apply_msl(&mut default, MathMode::Default);
+        assert_eq!(default, original);
+    }
+}
@@ -0,0 +1,534 @@
+use naga::{
+    AddressSpace, Binding, Block, Expression, Handle, ResourceBinding, Scalar, ShaderStage, Span,
+    Statement, SwitchCase, SwitchValue, Type, TypeInner,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::varyings::{Body, demote, find, leaves};
+
+// ============================================================================
+// Entry Point Merging Types
+// ============================================================================
+
+/// Options object accepted by `mergeComputeEntryPoints`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MergeOptions {
+    /// Name of the merged entry point; `<first>_<second>` by default.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Read the mode from a push constant instead of a uniform buffer.
+    /// Push constants are a native-only extension, not core WebGPU.
+    #[serde(default)]
+    pub push_constant: bool,
+    #[serde(default)]
+    pub mode_group: Option<u32>,
+    #[serde(default)]
+    pub mode_binding: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MergedEntryPoint {
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    /// Original entry point run for each mode value: `modes[0]` for 0.
+    #[wasm_bindgen(readonly)]
+    pub modes: Vec<String>,
+    /// Where the `u32` mode uniform is bound; `None` for a push constant.
+    #[wasm_bindgen(readonly)]
+    pub mode_group: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub mode_binding: Option<u32>,
+    /// Bindings both entry points declared, now served by one variable.
+    #[wasm_bindgen(readonly)]
+    pub unified: Vec<UnifiedBinding>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+}
+
+#[wasm_bindgen]
+impl MergedEntryPoint {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct UnifiedBinding {
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+    /// The first entry point's variable, which both now use.
+    #[wasm_bindgen(readonly)]
+    pub kept: Option<String>,
+    /// The second entry point's variable, which was removed.
+    #[wasm_bindgen(readonly)]
+    pub removed: Option<String>,
+}
+
+#[wasm_bindgen]
+impl UnifiedBinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Entry Point Merging Implementation
+// ============================================================================
+//
+// Both entry points become plain functions of their own names, called from
+// one `switch` in the merged entry point. Its arguments are the union of the
+// builtins the two read. A resource slot both bind must hold the same type in
+// the same address space; the second entry point's variable is then replaced
+// by the first's.
+
+fn globals_used(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    index: usize,
+) -> Vec<Handle<naga::GlobalVariable>> {
+    let usage = info.get_entry_point(index);
+    module
+        .global_variables
+        .iter()
+        .filter(|(handle, _)| !usage[*handle].is_empty())
+        .map(|(handle, _)| handle)
+        .collect()
+}
+
+fn replace_global(
+    module: &mut naga::Module,
+    from: Handle<naga::GlobalVariable>,
+    to: Handle<naga::GlobalVariable>,
+) {
+    let functions = module
+        .functions
+        .iter_mut()
+        .map(|(_, f)| f)
+        .chain(module.entry_points.iter_mut().map(|ep| &mut ep.function));
+    for function in functions {
+        for (_, expr) in function.expressions.iter_mut() {
+            if *expr == Expression::GlobalVariable(from) {
+                *expr = Expression::GlobalVariable(to);
+            }
+        }
+    }
+}
+
+fn unify_bindings(
+    module: &mut naga::Module,
+    first: &[Handle<naga::GlobalVariable>],
+    second: &[Handle<naga::GlobalVariable>],
+) -> Result<Vec<UnifiedBinding>, Diagnostic> {
+    let mut unified = Vec::new();
+    for &b in second {
+        let Some(binding) = module.global_variables[b].binding else {
+            continue;
+        };
+        let Some(&a) = first
+            .iter()
+            .find(|&&a| a != b && module.global_variables[a].binding == Some(binding))
+        else {
+            continue;
+        };
+        let (kept, removed) = (&module.global_variables[a], &module.global_variables[b]);
+        if kept.ty != removed.ty || kept.space != removed.space {
+            return Err(Diagnostic::error(format!(
+                "@group({}) @binding({}) holds '{}' in one entry point and '{}' in the other",
+                binding.group,
+                binding.binding,
+                kept.name.as_deref().unwrap_or("_"),
+                removed.name.as_deref().unwrap_or("_"),
+            )));
+        }
+        unified.push(UnifiedBinding {
+            group: binding.group,
+            binding: binding.binding,
+            kept: kept.name.clone(),
+            removed: removed.name.clone(),
+        });
+        replace_global(module, b, a);
+    }
+    Ok(unified)
+}
+
+/// One past the highest binding of the highest group in use, so existing
+/// bind group layouts only grow.
+fn free_slot(module: &naga::Module, used: &[Handle<naga::GlobalVariable>]) -> ResourceBinding {
+    let bindings = used
+        .iter()
+        .filter_map(|&h| module.global_variables[h].binding);
+    match bindings.clone().map(|b| b.group).max() {
+        Some(group) => ResourceBinding {
+            group,
+            binding: bindings
+                .filter(|b| b.group == group)
+                .map(|b| b.binding + 1)
+                .max()
+                .unwrap_or(0),
+        },
+        None => ResourceBinding {
+            group: 0,
+            binding: 0,
+        },
+    }
+}
+
+/// Merge compute entry points `first` (mode 0) and `second` (mode 1) of a
+/// validated module into one, dropping the originals.
+pub(crate) fn merge_compute(
+    module: &mut naga::Module,
+    info: &naga::valid::ModuleInfo,
+    first: &str,
+    second: &str,
+    options: &MergeOptions,
+) -> Result<MergedEntryPoint, Diagnostic> {
+    let a = find(module, first, ShaderStage::Compute)?;
+    let b = find(module, second, ShaderStage::Compute)?;
+    if a == b {
+        return Err(Diagnostic::error("Cannot merge an entry point with itself"));
+    }
+    let (ep_a, ep_b) = (&module.entry_points[a], &module.entry_points[b]);
+    if ep_a.workgroup_size != ep_b.workgroup_size
+        || ep_a.workgroup_size_overrides.is_some()
+        || ep_b.workgroup_size_overrides.is_some()
+    {
+        return Err(Diagnostic::error(format!(
+            "'{}' and '{}' must have the same constant workgroup size",
+            first, second
+        )));
+    }
+    let workgroup_size = ep_a.workgroup_size;
+    let name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{first}_{second}"));
+    if module.entry_points.iter().any(|ep| ep.name == name) {
+        return Err(Diagnostic::error(format!(
+            "An entry point named '{}' already exists",
+            name
+        )));
+    }
+
+    let used_a = globals_used(module, info, a);
+    let used_b = globals_used(module, info, b);
+    let unified = unify_bindings(module, &used_a, &used_b)?;
+    let used: Vec<_> = used_a.iter().chain(&used_b).copied().collect();
+
+    // The mode variable.
+    let u32_ty = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Scalar(Scalar::U32),
+        },
+        Span::UNDEFINED,
+    );
+    let (space, binding) = if options.push_constant {
+        if used
+            .iter()
+            .any(|&h| module.global_variables[h].space == AddressSpace::PushConstant)
+        {
+            return Err(Diagnostic::error(
+                "The entry points already use push constants; bind the mode as a uniform",
+            ));
+        }
+        (AddressSpace::PushConstant, None)
+    } else {
+        let free = free_slot(module, &used);
+        let binding = ResourceBinding {
+            group: options.mode_group.unwrap_or(free.group),
+            binding: options.mode_binding.unwrap_or(match options.mode_group {
+                Some(group) if group != free.group => 0,
+                _ => free.binding,
+            }),
+        };
+        if let Some(&taken) = used
+            .iter()
+            .find(|&&h| module.global_variables[h].binding == Some(binding))
+        {
+            return Err(Diagnostic::error(format!(
+                "@group({}) @binding({}) is already taken by '{}'",
+                binding.group,
+                binding.binding,
+                module.global_variables[taken]
+                    .name
+                    .as_deref()
+                    .unwrap_or("_")
+            )));
+        }
+        (AddressSpace::Uniform, Some(binding))
+    };
+    let mode = module.global_variables.append(
+        naga::GlobalVariable {
+            name: Some("mode".to_string()),
+            space,
+            binding,
+            ty: u32_ty,
+            init: None,
+        },
+        Span::UNDEFINED,
+    );
+
+    // Builtins in order of first use, shared between the two.
+    let inputs = [a, b].map(|index| leaves(module, &module.entry_points[index].function, false));
+    let mut arguments: Vec<naga::FunctionArgument> = Vec::new();
+    for leaf in inputs.iter().flatten() {
+        if !arguments
+            .iter()
+            .any(|arg| arg.binding.as_ref() == Some(&leaf.binding))
+        {
+            arguments.push(naga::FunctionArgument {
+                name: Some(leaf.name.clone()),
+                ty: leaf.ty,
+                binding: Some(leaf.binding.clone()),
+            });
+        }
+    }
+
+    let mut function = naga::Function {
+        name: Some(name.clone()),
+        arguments: arguments.clone(),
+        ..Default::default()
+    };
+    let mut body = Body {
+        function: &mut function,
+    };
+    let values: Vec<_> = (0..arguments.len())
+        .map(|i| body.add(Expression::FunctionArgument(i as u32)))
+        .collect();
+    let value_of = |binding: &Binding| {
+        let index = arguments
+            .iter()
+            .position(|arg| arg.binding.as_ref() == Some(binding))
+            .expect("every builtin has an argument");
+        values[index]
+    };
+
+    let mut cases = Vec::new();
+    for (mode_value, (index, leaves)) in [a, b].into_iter().zip(&inputs).enumerate() {
+        let original = &module.entry_points[index].function;
+        let mut call_arguments = Vec::with_capacity(original.arguments.len());
+        for (arg_index, arg) in original.arguments.iter().enumerate() {
+            let parts: Vec<_> = leaves
+                .iter()
+                .filter(|leaf| leaf.argument == arg_index)
+                .map(|leaf| (leaf.member, value_of(&leaf.binding)))
+                .collect();
+            call_arguments.push(match parts.as_slice() {
+                [(None, value)] => *value,
+                _ => body.add(Expression::Compose {
+                    ty: arg.ty,
+                    components: parts.iter().map(|(_, value)| *value).collect(),
+                }),
+            });
+        }
+        cases.push((mode_value as u32, index, call_arguments));
+    }
+    let pointer = body.add(Expression::GlobalVariable(mode));
+    let selector = body.add(Expression::Load { pointer });
+
+    let names = [a, b].map(|index| module.entry_points[index].name.clone());
+    let mut switch_cases = Vec::new();
+    for (mode_value, index, call_arguments) in cases {
+        let (helper, _) = demote(module, index, module.entry_points[index].name.clone());
+        switch_cases.push(SwitchCase {
+            value: SwitchValue::U32(mode_value),
+            body: Block::from_vec(vec![Statement::Call {
+                function: helper,
+                arguments: call_arguments,
+                result: None,
+            }]),
+            fall_through: false,
+        });
+    }
+    switch_cases.push(SwitchCase {
+        value: SwitchValue::Default,
+        body: Block::new(),
+        fall_through: false,
+    });
+    function.body.push(
+        Statement::Switch {
+            selector,
+            cases: switch_cases,
+        },
+        Span::UNDEFINED,
+    );
+    function
+        .body
+        .push(Statement::Return { value: None }, Span::UNDEFINED);
+
+    module.entry_points[a] = naga::EntryPoint {
+        name: name.clone(),
+        stage: ShaderStage::Compute,
+        early_depth_test: None,
+        workgroup_size,
+        workgroup_size_overrides: None,
+        function,
+    };
+    module.entry_points.remove(b);
+
+    // Drop the replaced variables and anything else only the originals used.
+    crate::validate_module(module)?;
+    naga::compact::compact(module, naga::compact::KeepUnused::No);
+    let info = crate::validate_module(module)?;
+    let wgsl =
+        naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+
+    Ok(MergedEntryPoint {
+        entry_point: name,
+        modes: names.to_vec(),
+        mode_group: binding.map(|b| b.group),
+        mode_binding: binding.map(|b| b.binding),
+        unified,
+        wgsl,
+    })
+}
+
+/// Merges compute entry points `first` and `second` into one uber entry
+/// point that runs `first` when a `u32` mode value is 0 and `second` when it
+/// is 1, so one pipeline serves both. Resources both bind at the same slot
+/// are unified and must agree in type. The two must share a workgroup size.
+/// `options` is `{ name?, pushConstant?, modeGroup?, modeBinding? }`; by
+/// default the mode is a uniform one past the highest binding in use.
+#[wasm_bindgen(js_name = mergeComputeEntryPoints)]
+pub fn merge_compute_entry_points(
+    wgsl: &str,
+    first: &str,
+    second: &str,
+    options: JsValue,
+) -> Result<MergedEntryPoint, JsValue> {
+    let options: Option<MergeOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid merge options: {e}")))?;
+    let (mut module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    merge_compute(
+        &mut module,
+        &info,
+        first,
+        second,
+        &options.unwrap_or_default(),
+    )
+    .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+        @group(0) @binding(1) var<uniform> scale: f32;
+        @group(0) @binding(0) var<storage, read_write> values: array<f32>;
+
+        @compute @workgroup_size(64)
+        fn double(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = data[id.x] * 2.0 * scale;
+        }
+
+        struct Ids {
+            @builtin(local_invocation_index) local: u32,
+            @builtin(global_invocation_id) global: vec3<u32>,
+        }
+
+        @compute @workgroup_size(64)
+        fn clear(ids: Ids) {
+            values[ids.global.x] = f32(ids.local) * 0.0;
+        }
+    "#;
+
+    fn merge(source: &str, options: MergeOptions) -> Result<MergedEntryPoint, Diagnostic> {
+        let (mut module, info) = crate::parse_and_validate(source).unwrap();
+        merge_compute(&mut module, &info, "double", "clear", &options)
+    }
+
+    #[test]
+    fn switches_on_a_mode_uniform() {
+        let merged = merge(SHADER, MergeOptions::default()).unwrap();
+        assert_eq!(merged.entry_point, "double_clear");
+        assert_eq!(merged.modes, ["double", "clear"]);
+        assert_eq!((merged.mode_group, merged.mode_binding), (Some(0), Some(2)));
+        assert_eq!(
+            merged.unified,
+            [UnifiedBinding {
+                group: 0,
+                binding: 0,
+                kept: Some("data".to_string()),
+                removed: Some("values".to_string()),
+            }]
+        );
+
+        let module = crate::parse_wgsl(&merged.wgsl).unwrap();
+        assert_eq!(module.entry_points.len(), 1);
+        assert_eq!(module.entry_points[0].workgroup_size, [64, 1, 1]);
+        // Both builtins, the shared one once.
+        assert_eq!(module.entry_points[0].function.arguments.len(), 2);
+        assert!(merged.wgsl.contains("var<uniform> mode: u32;"));
+        assert!(merged.wgsl.contains("switch"));
+        assert!(!merged.wgsl.contains("values"));
+        assert!(merged.wgsl.contains("fn clear("));
+    }
+
+    #[test]
+    fn push_constant_and_explicit_slots() {
+        let pushed = merge(
+            SHADER,
+            MergeOptions {
+                push_constant: true,
+                name: Some("uber".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(pushed.entry_point, "uber");
+        assert_eq!(pushed.mode_group, None);
+        assert!(pushed.wgsl.contains("var<push_constant> mode: u32;"));
+
+        let placed = merge(
+            SHADER,
+            MergeOptions {
+                mode_group: Some(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!((placed.mode_group, placed.mode_binding), (Some(3), Some(0)));
+
+        let taken = MergeOptions {
+            mode_binding: Some(1),
+            ..Default::default()
+        };
+        assert!(merge(SHADER, taken).is_err());
+    }
+
+    #[test]
+    fn incompatible_entry_points_are_rejected() {
+        let sizes = SHADER.replacen("@workgroup_size(64)", "@workgroup_size(32)", 1);
+        assert!(merge(&sizes, MergeOptions::default()).is_err());
+
+        let types = SHADER.replace(
+            "var<storage, read_write> values: array<f32>",
+            "var<storage, read_write> values: array<u32>",
+        );
+        let types = types.replace("f32(ids.local) * 0.0", "ids.local");
+        let message = merge(&types, MergeOptions::default())
+            .err()
+            .unwrap()
+            .message;
+        assert!(message.contains("@group(0) @binding(0)"), "{message}");
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::lexer::{Token, TokenKind, skip_template, tokenize};
+
+// ============================================================================
+// Minify Types
+// ============================================================================
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MinifyOptions {
+    /// Shorten declared names other than entry points and overrides.
+    #[serde(default)]
+    pub rename: bool,
+    /// Further names to keep when renaming, e.g. ones host code looks up.
+    #[serde(default)]
+    pub keep: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MinifiedWgsl {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// Old and new name of everything renamed, most used first.
+    #[wasm_bindgen(readonly)]
+    pub renamed: Vec<RenamedSymbol>,
+    /// Bytes before minifying.
+    #[wasm_bindgen(readonly)]
+    pub original_size: u32,
+    #[wasm_bindgen(readonly)]
+    pub minified_size: u32,
+}
+
+#[wasm_bindgen]
+impl MinifiedWgsl {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RenamedSymbol {
+    #[wasm_bindgen(readonly)]
+    pub from: String,
+    #[wasm_bindgen(readonly)]
+    pub to: String,
+}
+
+#[wasm_bindgen]
+impl RenamedSymbol {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Minify Implementation
+// ============================================================================
+//
+// The source is re-emitted from its tokens, which drops comments and all
+// layout. A space is kept only between two word tokens, and between two
+// punctuation characters that were apart in the source and would otherwise
+// read as one operator (`a - -b`, `x < <y`).
+//
+// Renaming is textual, so it is only done for names the source declares, and
+// never where a token is not a reference: member names (after `.` and in
+// struct bodies), attribute names and the enumerants of `@builtin`,
+// `@interpolate` and `@diagnostic`. Every occurrence of a name changes
+// together, shadowed or not, so the mapping stays consistent. Entry points
+// and overrides keep their names, since pipelines look them up, and
+// `@group`/`@binding` attributes are untouched. New names avoid every
+// identifier already in the source and WGSL's short keywords.
+
+/// Pairs of characters that lex as one token when adjacent.
+const JOINING: &[&str] = &[
+    "--", "++", "&&", "||", "<<", ">>", "<=", ">=", "==", "!=", "->", "//", "/*", "*/", "+=", "-=",
+    "*=", "/=", "%=", "&=", "|=", "^=",
+];
+
+/// Keywords and reserved words short enough to be generated.
+const SHORT_RESERVED: &[&str] = &[
+    "as", "do", "fn", "if", "in", "is", "of", "asm", "box", "end", "f16", "f32", "for", "get",
+    "i32", "let", "mat", "mod", "mut", "new", "nil", "out", "pub", "ref", "set", "try", "u32",
+    "use", "var", "vec",
+];
+
+/// Attributes whose arguments are enumerants, not references.
+const ENUMERANT_ATTRIBUTES: &[&str] = &["builtin", "interpolate", "diagnostic"];
+
+fn word(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Ident | TokenKind::Number)
+}
+
+fn join(source: &str, tokens: &[Token]) -> String {
+    let mut out = String::with_capacity(source.len() / 2);
+    for (i, token) in tokens.iter().enumerate() {
+        if let Some(prev) = i.checked_sub(1).map(|p| &tokens[p]) {
+            let apart = prev.end() < token.start;
+            let pair = format!("{}{}", prev.text, token.text);
+            let joins = match (prev.kind, token.kind) {
+                (TokenKind::Punct(_), TokenKind::Punct(_)) => JOINING.contains(&pair.as_str()),
+                _ => word(prev) && word(token),
+            };
+            if apart && joins {
+                out.push(' ');
+            }
+        }
+        out.push_str(token.text);
+    }
+    out
+}
+
+/// Indices of tokens that are not references to a declaration.
+pub(crate) fn non_references(tokens: &[Token]) -> HashSet<usize> {
+    let mut skip = HashSet::new();
+    let mut depth = 0usize;
+    let mut struct_depth = None;
+    let mut struct_next = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        match token.kind {
+            TokenKind::Punct('{') => {
+                depth += 1;
+                if std::mem::take(&mut struct_next) {
+                    struct_depth = Some(depth);
+                }
+            }
+            TokenKind::Punct('}') => {
+                if struct_depth == Some(depth) {
+                    struct_depth = None;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            TokenKind::Punct('.') | TokenKind::Punct('@') => {
+                skip.insert(i + 1);
+                let attribute = tokens.get(i + 1).map(|t| t.text);
+                if token.is_punct('@')
+                    && attribute.is_some_and(|a| ENUMERANT_ATTRIBUTES.contains(&a))
+                    && tokens.get(i + 2).is_some_and(|t| t.is_punct('('))
+                {
+                    let mut j = i + 3;
+                    while j < tokens.len() && !tokens[j].is_punct(')') {
+                        skip.insert(j);
+                        j += 1;
+                    }
+                    i = j;
+                    continue;
+                }
+            }
+            TokenKind::Ident if token.is_ident("struct") && depth == 0 => struct_next = true,
+            TokenKind::Ident if token.is_ident("enable") || token.is_ident("requires") => {
+                while i < tokens.len() && !tokens[i].is_punct(';') {
+                    skip.insert(i);
+                    i += 1;
+                }
+                continue;
+            }
+            TokenKind::Ident
+                if struct_depth == Some(depth)
+                    && tokens.get(i + 1).is_some_and(|t| t.is_punct(':')) =>
+            {
+                skip.insert(i);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    skip
+}
+
+/// Every name the source declares: module scope, locals and parameters.
+fn declared_names<'a>(tokens: &[Token<'a>]) -> HashSet<&'a str> {
+    let mut names = HashSet::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let at = match token.text {
+            _ if token.kind != TokenKind::Ident => continue,
+            "fn" | "struct" | "alias" | "const" | "let" | "override" => i + 1,
+            "var" => skip_template(tokens, i + 1),
+            _ => continue,
+        };
+        if let Some(name) = tokens.get(at).filter(|t| t.kind == TokenKind::Ident) {
+            names.insert(name.text);
+        }
+        // Parameters: `name:` at the top level of the list after `fn name`.
+        if token.is_ident("fn") && tokens.get(at + 1).is_some_and(|t| t.is_punct('(')) {
+            let mut depth = 0;
+            for (j, t) in tokens.iter().enumerate().skip(at + 1) {
+                match t.kind {
+                    TokenKind::Punct('(') => depth += 1,
+                    TokenKind::Punct(')') => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    TokenKind::Ident
+                        if depth == 1
+                            && tokens.get(j + 1).is_some_and(|t| t.is_punct(':'))
+                            && !tokens[j - 1].is_punct('@') =>
+                    {
+                        names.insert(t.text);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Short names in order: `a`..`Z`, then two characters, skipping `taken`.
+fn short_names(taken: &HashSet<&str>) -> impl Iterator<Item = String> {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let taken: HashSet<String> = taken.iter().map(|s| s.to_string()).collect();
+    (1usize..)
+        .flat_map(|len| {
+            let count = FIRST.len() * REST.len().pow(len as u32 - 1);
+            (0..count).map(move |mut n| {
+                let mut name = vec![FIRST[n % FIRST.len()]];
+                n /= FIRST.len();
+                for _ in 1..len {
+                    name.push(REST[n % REST.len()]);
+                    n /= REST.len();
+                }
+                String::from_utf8(name).unwrap()
+            })
+        })
+        .filter(move |name| !taken.contains(name) && !SHORT_RESERVED.contains(&name.as_str()))
+}
+
+pub(crate) fn minify(source: &str, options: &MinifyOptions) -> Result<MinifiedWgsl, Diagnostic> {
+    let (module, _info) = crate::parse_and_validate(source)?;
+    let mut tokens = tokenize(source);
+    let mut renamed = Vec::new();
+    let mut names: HashMap<&str, String> = HashMap::new();
+
+    if options.rename {
+        let skip = non_references(&tokens);
+        let mut kept: HashSet<&str> = module
+            .entry_points
+            .iter()
+            .map(|ep| ep.name.as_str())
+            .collect();
+        kept.extend(
+            module
+                .overrides
+                .iter()
+                .filter_map(|(_, o)| o.name.as_deref()),
+        );
+        kept.extend(options.keep.iter().map(String::as_str));
+
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if token.kind == TokenKind::Ident && !skip.contains(&i) {
+                *uses.entry(token.text).or_default() += 1;
+            }
+        }
+        let mut candidates: Vec<_> = declared_names(&tokens)
+            .into_iter()
+            .filter(|name| !kept.contains(name) && *name != "_")
+            .collect();
+        candidates.sort_by_key(|name| (std::cmp::Reverse(uses.get(name).copied()), *name));
+
+        let taken: HashSet<&str> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Ident)
+            .map(|t| t.text)
+            .collect();
+        for (name, short) in candidates.into_iter().zip(short_names(&taken)) {
+            if short.len() < name.len() {
+                names.insert(name, short);
+            }
+        }
+        for (i, token) in tokens.iter_mut().enumerate() {
+            if let Some(short) = names.get(token.text).filter(|_| !skip.contains(&i)) {
+                token.text = short.as_str();
+            }
+        }
+        let mut order: Vec<_> = names.iter().collect();
+        order.sort_by_key(|(name, short)| (short.len(), short.as_str(), **name));
+        renamed = order
+            .into_iter()
+            .map(|(name, short)| RenamedSymbol {
+                from: name.to_string(),
+                to: short.clone(),
+            })
+            .collect();
+    }
+
+    let wgsl = join(source, &tokens);
+    crate::parse_and_validate(&wgsl).map_err(|e| {
+        Diagnostic::error(format!("Minified WGSL failed to validate: {}", e.message))
+    })?;
+    Ok(MinifiedWgsl {
+        original_size: source.len() as u32,
+        minified_size: wgsl.len() as u32,
+        wgsl,
+        renamed,
+    })
+}
+
+/// Smaller WGSL for shipping: comments and whitespace removed and, with
+/// `{ rename: true }`, declared names shortened. Entry points, overrides,
+/// `keep` names and `@group`/`@binding` assignments are preserved, and the
+/// result is validated. The source must be valid WGSL, includes resolved.
+#[wasm_bindgen(js_name = minifyWgsl)]
+pub fn minify_wgsl(source: &str, options: JsValue) -> Result<MinifiedWgsl, JsValue> {
+    let options: Option<MinifyOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid minify options: {e}")))?;
+    minify(source, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        // Blurs along x.
+        struct BlurParams {
+            radius: i32,  /* taps each side */
+            position: f32,
+        }
+        override strength: f32 = 1.0;
+        @group(0) @binding(0) var<uniform> params: BlurParams;
+        @group(0) @binding(1) var<storage, read_write> values: array<f32>;
+
+        fn weight_for(offset: i32) -> f32 {
+            let distance = f32(abs(offset)) - -1.0;
+            return strength / distance;
+        }
+
+        @compute @workgroup_size(64)
+        fn blur_main(@builtin(global_invocation_id) position: vec3<u32>) {
+            var total = 0.0;
+            for (var offset = -params.radius; offset <= params.radius; offset++) {
+                total += values[position.x] * weight_for(offset) * params.position;
+            }
+            values[position.x] = total;
+        }
+    "#;
+
+    #[test]
+    fn whitespace_and_comments_are_stripped() {
+        let result = minify(SHADER, &MinifyOptions::default()).unwrap();
+        assert!(!result.wgsl.contains("//") && !result.wgsl.contains("/*"));
+        assert!(result.wgsl.contains("f32(abs(offset))- -1.0;"));
+        assert!(
+            result
+                .wgsl
+                .contains("@compute@workgroup_size(64)fn blur_main(")
+        );
+        assert!(result.renamed.is_empty());
+        assert!(result.minified_size < result.original_size);
+    }
+
+    #[test]
+    fn renaming_keeps_entry_points_bindings_and_members() {
+        let options = MinifyOptions {
+            rename: true,
+            keep: vec!["params".to_string()],
+        };
+        let result = minify(SHADER, &options).unwrap();
+        let (module, _) = crate::parse_and_validate(&result.wgsl).unwrap();
+        assert_eq!(module.entry_points[0].name, "blur_main");
+        assert_eq!(
+            module.overrides.iter().next().unwrap().1.name.as_deref(),
+            Some("strength")
+        );
+        assert!(
+            result
+                .wgsl
+                .contains("@group(0)@binding(0)var<uniform>params:")
+        );
+        assert!(result.wgsl.contains("@builtin(global_invocation_id)"));
+        assert!(result.wgsl.contains("params.position"));
+        assert!(!result.wgsl.contains("weight_for"));
+        let from: Vec<_> = result.renamed.iter().map(|r| r.from.as_str()).collect();
+        assert!(from.contains(&"offset") && from.contains(&"BlurParams"));
+        assert!(!from.contains(&"radius"));
+    }
+}
@@ -0,0 +1,310 @@
+use naga::{ArraySize, Handle, Scalar, ScalarKind, Type, TypeInner};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Mock Data Generation
+// ============================================================================
+//
+// Fills one value of a host-shareable type with seeded random contents, laid
+// out as a storage buffer would hold it: floats in [-1, 1), signed integers
+// in [-100, 100], unsigned ones in [0, 100], padding zeroed. A runtime-sized
+// trailing array gets one element.
+
+/// splitmix64: tiny, seedable, and good enough for test inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [-1, 1).
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// f16 bits of `value`, flushing subnormals to zero. Only used for values
+/// in [-1, 1), so the exponent never overflows.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.abs() < 6.103_515_6e-5 {
+        return sign;
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = ((bits >> 13) & 0x3ff) as u16;
+    sign | ((exponent as u16) << 10) | mantissa
+}
+
+struct Mock<'a> {
+    module: &'a naga::Module,
+    rng: Rng,
+    unit_normals: bool,
+    bytes: Vec<u8>,
+}
+
+impl Mock<'_> {
+    fn put(&mut self, offset: usize, data: &[u8]) {
+        if self.bytes.len() < offset + data.len() {
+            self.bytes.resize(offset + data.len(), 0);
+        }
+        self.bytes[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn scalar(
+        &mut self,
+        offset: usize,
+        scalar: Scalar,
+        value: Option<f64>,
+    ) -> Result<(), Diagnostic> {
+        match (scalar.kind, scalar.width) {
+            (ScalarKind::Float, 2) => {
+                let value = value.unwrap_or_else(|| self.rng.unit());
+                self.put(offset, &f16_bits(value as f32).to_le_bytes());
+            }
+            (ScalarKind::Float, 4) => {
+                let value = value.unwrap_or_else(|| self.rng.unit());
+                self.put(offset, &(value as f32).to_le_bytes());
+            }
+            (ScalarKind::Float, 8) => {
+                let value = value.unwrap_or_else(|| self.rng.unit());
+                self.put(offset, &value.to_le_bytes());
+            }
+            (ScalarKind::Sint, 4) => {
+                let value = self.rng.below(201) as i32 - 100;
+                self.put(offset, &value.to_le_bytes());
+            }
+            (ScalarKind::Sint, 8) => {
+                let value = self.rng.below(201) as i64 - 100;
+                self.put(offset, &value.to_le_bytes());
+            }
+            (ScalarKind::Uint, 4) => {
+                let value = self.rng.below(101) as u32;
+                self.put(offset, &value.to_le_bytes());
+            }
+            (ScalarKind::Uint, 8) => {
+                let value = self.rng.below(101);
+                self.put(offset, &value.to_le_bytes());
+            }
+            _ => {
+                return Err(Diagnostic::error(format!(
+                    "{:?} values cannot be stored in a buffer",
+                    scalar
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// A random direction, with trailing components past xyz zeroed.
+    fn direction(&mut self, components: usize) -> Vec<f64> {
+        let (v, length) = loop {
+            let v = [self.rng.unit(), self.rng.unit(), self.rng.unit()];
+            let length = v.iter().map(|c| c * c).sum::<f64>().sqrt();
+            if length > 1e-3 {
+                break (v, length);
+            }
+        };
+        (0..components)
+            .map(|i| v.get(i).map_or(0.0, |c| c / length))
+            .collect()
+    }
+
+    fn value(
+        &mut self,
+        offset: usize,
+        ty: Handle<Type>,
+        name: Option<&str>,
+    ) -> Result<(), Diagnostic> {
+        match self.module.types[ty].inner {
+            TypeInner::Scalar(scalar) | TypeInner::Atomic(scalar) => {
+                self.scalar(offset, scalar, None)?;
+            }
+            TypeInner::Vector { size, scalar } => {
+                let components = size as usize;
+                let normal = self.unit_normals
+                    && scalar.kind == ScalarKind::Float
+                    && components >= 3
+                    && name.is_some_and(|n| n.to_ascii_lowercase().contains("normal"));
+                let values = if normal {
+                    self.direction(components).into_iter().map(Some).collect()
+                } else {
+                    vec![None; components]
+                };
+                for (i, value) in values.into_iter().enumerate() {
+                    self.scalar(offset + i * scalar.width as usize, scalar, value)?;
+                }
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                scalar,
+            } => {
+                let stride = naga::proc::Alignment::from(rows) * scalar.width as u32;
+                for column in 0..columns as usize {
+                    for row in 0..rows as usize {
+                        let at = offset + column * stride as usize + row * scalar.width as usize;
+                        self.scalar(at, scalar, None)?;
+                    }
+                }
+            }
+            TypeInner::Array { base, size, stride } => {
+                let count = match size {
+                    ArraySize::Constant(count) => count.get(),
+                    ArraySize::Dynamic => 1,
+                    ArraySize::Pending(_) => {
+                        return Err(Diagnostic::error(
+                            "Arrays sized by an override have no fixed layout",
+                        ));
+                    }
+                };
+                for index in 0..count as usize {
+                    self.value(offset + index * stride as usize, base, name)?;
+                }
+            }
+            TypeInner::Struct { ref members, span } => {
+                // Zero the padding up to the span.
+                self.put(offset, &vec![0; span as usize]);
+                for member in members {
+                    let at = offset + member.offset as usize;
+                    self.value(at, member.ty, member.name.as_deref())?;
+                }
+            }
+            _ => {
+                return Err(Diagnostic::error(format!(
+                    "Type '{}' cannot be stored in a buffer",
+                    crate::get_type_name(self.module, ty).unwrap_or_default()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes of one randomized value of the type named `type_name`.
+pub(crate) fn mock_data(
+    module: &naga::Module,
+    type_name: &str,
+    seed: u64,
+    unit_normals: bool,
+) -> Result<Vec<u8>, Diagnostic> {
+    let (ty, _) = module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(type_name))
+        .ok_or_else(|| Diagnostic::error(format!("Type '{}' not found", type_name)))?;
+    let layouter = crate::layout::layouter(module)?;
+    let size = layouter[ty].size as usize;
+
+    let mut mock = Mock {
+        module,
+        rng: Rng(seed),
+        unit_normals,
+        bytes: Vec::with_capacity(size),
+    };
+    mock.value(0, ty, None)?;
+    let mut bytes = mock.bytes;
+    bytes.resize(bytes.len().max(size), 0);
+    Ok(bytes)
+}
+
+/// Randomized contents for one value of the struct (or other named,
+/// host-shareable type) `typeName`, laid out as a storage buffer holds it.
+/// The same `seed` always gives the same bytes. With `unitNormals: true`,
+/// float vec3/vec4 members whose name contains "normal" get unit-length xyz.
+/// A runtime-sized trailing array is given one element.
+#[wasm_bindgen(js_name = generateMockData)]
+pub fn generate_mock_data(
+    wgsl: &str,
+    type_name: &str,
+    seed: u32,
+    unit_normals: Option<bool>,
+) -> Result<js_sys::ArrayBuffer, JsValue> {
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    let bytes = mock_data(
+        &module,
+        type_name,
+        seed as u64,
+        unit_normals.unwrap_or(false),
+    )
+    .map_err(throw)?;
+    Ok(js_sys::Uint8Array::from(bytes.as_slice()).buffer())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Vertex {
+            position: vec3<f32>,
+            normal: vec3<f32>,
+            id: u32,
+            offset: i32,
+            transform: mat3x3<f32>,
+        }
+        struct Particles {
+            count: u32,
+            items: array<Vertex>,
+        }
+        @group(0) @binding(0) var<storage> particles: Particles;
+        @compute @workgroup_size(1) fn main() { _ = particles.count; }
+    "#;
+
+    fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn respects_layout_and_ranges() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let bytes = mock_data(&module, "Vertex", 7, false).unwrap();
+        // vec3 members are 16-aligned, as are the matrix columns.
+        assert_eq!(bytes.len(), 96);
+        for offset in [0, 4, 8, 16, 20, 24, 48, 64, 80] {
+            assert!((-1.0..1.0).contains(&f32_at(&bytes, offset)), "{offset}");
+        }
+        // Padding after position and after each matrix column.
+        for offset in [12, 60, 76, 92] {
+            assert_eq!(f32_at(&bytes, offset), 0.0, "{offset}");
+        }
+        let id = u32::from_le_bytes(bytes[28..32].try_into().unwrap());
+        assert!(id <= 100);
+        let signed = i32::from_le_bytes(bytes[32..36].try_into().unwrap());
+        assert!((-100..=100).contains(&signed));
+
+        // Runtime arrays get one element at the member's offset.
+        let particles = mock_data(&module, "Particles", 7, false).unwrap();
+        assert_eq!(particles.len(), 16 + 96);
+    }
+
+    #[test]
+    fn seeds_are_deterministic_and_normals_unit_length() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let a = mock_data(&module, "Vertex", 1, true).unwrap();
+        assert_eq!(a, mock_data(&module, "Vertex", 1, true).unwrap());
+        assert_ne!(a, mock_data(&module, "Vertex", 2, true).unwrap());
+
+        let normal: Vec<f32> = (0..3).map(|i| f32_at(&a, 16 + i * 4)).collect();
+        let length = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+        assert!((length - 1.0).abs() < 1e-5);
+
+        assert!(mock_data(&module, "Nope", 1, false).is_err());
+    }
+}
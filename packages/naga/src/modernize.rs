@@ -0,0 +1,466 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::lexer::{Token, TokenKind, skip_template, tokenize};
+use crate::rename::{TextEdit, text_edit};
+
+// ============================================================================
+// Modernize Types
+// ============================================================================
+
+/// `modernizeWgsl`'s rewritten source, with what changed and why.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ModernizeResult {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// In source order, offsets into the original text.
+    #[wasm_bindgen(readonly)]
+    pub edits: Vec<TextEdit>,
+    /// One per rewrite, and one per old construct that needs a hand edit.
+    #[wasm_bindgen(readonly)]
+    pub notes: Vec<ModernizeNote>,
+    /// Why the rewritten source still fails to validate, if it does.
+    #[wasm_bindgen(readonly)]
+    pub diagnostic: Option<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl ModernizeResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ModernizeNote {
+    /// e.g. `"attribute-syntax"`.
+    #[wasm_bindgen(readonly)]
+    pub rule: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// 1-based line in the original text.
+    #[wasm_bindgen(readonly)]
+    pub line: u32,
+    /// Whether the construct was rewritten, rather than only reported.
+    #[wasm_bindgen(readonly)]
+    pub fixed: bool,
+}
+
+#[wasm_bindgen]
+impl ModernizeNote {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Modernize Implementation
+// ============================================================================
+//
+// Pre-1.0 WGSL differs from today's mostly in spelling, so the rewrites are
+// token-level, like renames, and leave comments and formatting alone:
+// `[[...]]` attribute lists become `@` attributes, `@stage(x)` becomes `@x`,
+// `type` aliases, module-scope `let`s, `;`-separated struct members,
+// `elseif`, the read/write-only storage texture types and renamed builtins
+// and functions get their current names. Attributes that no longer exist
+// (`block`, `stride`, `offset`, `access`) are dropped with a note, as are
+// constructs with no mechanical replacement.
+
+/// Builtin values renamed since, old name first.
+const RENAMED_BUILTINS: &[(&str, &str)] = &[
+    ("frag_coord", "position"),
+    ("vertex_idx", "vertex_index"),
+    ("instance_idx", "instance_index"),
+    ("local_invocation_idx", "local_invocation_index"),
+    ("sample_mask_in", "sample_mask"),
+    ("sample_mask_out", "sample_mask"),
+];
+
+const RENAMED_FUNCTIONS: &[(&str, &str)] = &[("smoothStep", "smoothstep")];
+
+const REMOVED_FUNCTIONS: &[&str] = &["isNan", "isInf", "isFinite", "isNormal"];
+
+struct Modernizer<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    /// `(start, end, replacement)`, byte offsets.
+    edits: Vec<(usize, usize, String)>,
+    notes: Vec<ModernizeNote>,
+}
+
+impl<'a> Modernizer<'a> {
+    fn line(&self, offset: usize) -> u32 {
+        self.source[..offset].matches('\n').count() as u32 + 1
+    }
+
+    fn note(&mut self, rule: &str, message: String, offset: usize, fixed: bool) {
+        self.notes.push(ModernizeNote {
+            rule: rule.to_string(),
+            message,
+            line: self.line(offset),
+            fixed,
+        });
+    }
+
+    fn edit(&mut self, start: usize, end: usize, replacement: impl Into<String>) {
+        self.edits.push((start, end, replacement.into()));
+    }
+
+    /// Index of the `)` closing the `(` at `open`.
+    fn close_paren(&self, open: usize) -> usize {
+        let mut depth = 0;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            if token.is_punct('(') {
+                depth += 1;
+            } else if token.is_punct(')') {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    /// The attribute named at `at`: its arguments' text and the index of its
+    /// last token.
+    fn attribute(&self, at: usize) -> (Option<&'a str>, usize) {
+        if !self.tokens.get(at + 1).is_some_and(|t| t.is_punct('(')) {
+            return (None, at);
+        }
+        let close = self.close_paren(at + 1);
+        let args = &self.source[self.tokens[at + 1].end()..self.tokens[close].start];
+        (Some(args.trim()), close)
+    }
+
+    /// The current form of attribute `name(args)`, or `None` if it is gone.
+    /// Sets `override_next` for `override`, whose declaration changes too.
+    fn convert(
+        &mut self,
+        name: &str,
+        args: Option<&str>,
+        offset: usize,
+        override_next: &mut bool,
+    ) -> Option<String> {
+        let gone = |this: &mut Self, message: &str| {
+            this.note("removed-attribute", message.to_string(), offset, false);
+            None
+        };
+        match (name, args) {
+            ("stage", Some(stage)) => {
+                let message = format!("`stage({stage})` is now `@{stage}`");
+                self.note("stage-attribute", message, offset, true);
+                Some(format!("@{stage}"))
+            }
+            ("block", _) => gone(
+                self,
+                "`block` was removed; buffer structs need no attribute",
+            ),
+            ("stride", _) => gone(
+                self,
+                "`stride` was removed; array strides follow the layout rules, so check the \
+                 host-side buffer layout",
+            ),
+            ("offset", _) => gone(
+                self,
+                "`offset` was removed; use `@align` or `@size` to place members",
+            ),
+            ("access", _) => gone(
+                self,
+                "`access` was removed; put the access mode in `var<storage, ...>` or the \
+                 storage texture type",
+            ),
+            ("override", id) => {
+                *override_next = true;
+                let message = "`override` on a `let` is now an `override` declaration";
+                self.note("override-declaration", message.to_string(), offset, true);
+                Some(id.map(|id| format!("@id({id})")).unwrap_or_default())
+            }
+            ("builtin", Some(builtin)) => {
+                let current = RENAMED_BUILTINS
+                    .iter()
+                    .find(|(old, _)| *old == builtin)
+                    .map_or(builtin, |(_, new)| *new);
+                if current != builtin {
+                    let message = format!("builtin `{builtin}` is now `{current}`");
+                    self.note("builtin-name", message, offset, true);
+                }
+                Some(format!("@builtin({current})"))
+            }
+            (name, Some(args)) => Some(format!("@{name}({args})")),
+            (name, None) => Some(format!("@{name}")),
+        }
+    }
+
+    fn run(&mut self) {
+        let mut depth = 0usize;
+        // Brace depth of the struct body being read, if any.
+        let mut struct_body = None;
+        let mut struct_next = false;
+        let mut override_next = false;
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let token = self.tokens[i];
+            let next = self.tokens.get(i + 1).copied();
+            match token.kind {
+                TokenKind::Punct('{') => {
+                    depth += 1;
+                    if std::mem::take(&mut struct_next) {
+                        struct_body = Some(depth);
+                    }
+                }
+                TokenKind::Punct('}') => {
+                    if struct_body == Some(depth) {
+                        struct_body = None;
+                    }
+                    depth = depth.saturating_sub(1);
+                }
+                TokenKind::Punct(';') if struct_body == Some(depth) => {
+                    self.edit(token.start, token.end(), ",");
+                    let message = "struct members are now separated by `,`".to_string();
+                    self.note("struct-separator", message, token.start, true);
+                }
+                TokenKind::Punct('[') if next.is_some_and(|t| t.is_punct('[')) => {
+                    i = self.attribute_list(i, &mut override_next);
+                    continue;
+                }
+                TokenKind::Punct('@') if next.is_some_and(|t| t.kind == TokenKind::Ident) => {
+                    let name = self.tokens[i + 1].text;
+                    let (args, last) = self.attribute(i + 1);
+                    let start = token.start;
+                    let end = self.tokens[last].end();
+                    let converted = self.convert(name, args, start, &mut override_next);
+                    let original = &self.source[start..end];
+                    let replacement = converted.unwrap_or_default();
+                    if replacement != original {
+                        self.edit(start, end, replacement);
+                    }
+                    i = last + 1;
+                    continue;
+                }
+                TokenKind::Ident => self.identifier(i, depth, &mut struct_next, &mut override_next),
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Rewrite the `[[...]]` list opening at `at`; returns the index past it.
+    fn attribute_list(&mut self, at: usize, override_next: &mut bool) -> usize {
+        let start = self.tokens[at].start;
+        let mut converted = Vec::new();
+        let mut i = at + 2;
+        while i < self.tokens.len() {
+            let token = self.tokens[i];
+            if token.is_punct(']') {
+                break;
+            }
+            if token.kind == TokenKind::Ident {
+                let (args, last) = self.attribute(i);
+                converted.extend(self.convert(token.text, args, token.start, override_next));
+                i = last;
+            }
+            i += 1;
+        }
+        // Past both closing brackets.
+        let last = (i + 1).min(self.tokens.len() - 1);
+        let end = self.tokens[last].end();
+        self.edit(start, end, converted.join(" "));
+        let message = "`[[...]]` attribute lists are now written as `@` attributes".to_string();
+        self.note("attribute-syntax", message, start, true);
+        last + 1
+    }
+
+    fn identifier(
+        &mut self,
+        i: usize,
+        depth: usize,
+        struct_next: &mut bool,
+        override_next: &mut bool,
+    ) {
+        let token = self.tokens[i];
+        let prev = i.checked_sub(1).map(|p| self.tokens[p]);
+        let next = self.tokens.get(i + 1).copied();
+        let member = prev.is_some_and(|p| p.is_punct('.'));
+        let called = next.is_some_and(|t| t.is_punct('(')) && !member;
+        match token.text {
+            "struct" if depth == 0 => *struct_next = true,
+            "type" if depth == 0 && next.is_some_and(|t| t.kind == TokenKind::Ident) => {
+                self.edit(token.start, token.end(), "alias");
+                let message = "type aliases are now declared with `alias`".to_string();
+                self.note("type-alias", message, token.start, true);
+            }
+            "let" if depth == 0 => {
+                let (keyword, message) = if std::mem::take(override_next) {
+                    (
+                        "override",
+                        "pipeline-overridable constants are now `override`",
+                    )
+                } else {
+                    ("const", "module-scope constants are now `const`")
+                };
+                self.edit(token.start, token.end(), keyword);
+                self.note("module-let", message.to_string(), token.start, true);
+            }
+            "elseif" => {
+                self.edit(token.start, token.end(), "else if");
+                let message = "`elseif` is now `else if`".to_string();
+                self.note("elseif", message, token.start, true);
+            }
+            "ignore" if called => {
+                self.edit(token.start, token.end(), "_ =");
+                let message = "`ignore(e)` is now the phony assignment `_ = e`".to_string();
+                self.note("ignore", message, token.start, true);
+            }
+            text if text.starts_with("texture_read_only_")
+                || text.starts_with("texture_write_only_") =>
+            {
+                let (access, dim) = match text.strip_prefix("texture_read_only_") {
+                    Some(dim) => ("read", dim),
+                    None => ("write", &text["texture_write_only_".len()..]),
+                };
+                let close = skip_template(&self.tokens, i + 1);
+                if close == i + 1 {
+                    return;
+                }
+                let gt = self.tokens[close - 1].start;
+                self.edit(token.start, token.end(), format!("texture_storage_{dim}"));
+                self.edit(gt, gt, format!(", {access}"));
+                let message =
+                    format!("`{text}` is now `texture_storage_{dim}` with `{access}` access");
+                self.note("storage-texture", message, token.start, true);
+            }
+            text if called => {
+                if let Some((_, new)) = RENAMED_FUNCTIONS.iter().find(|(old, _)| *old == text) {
+                    self.edit(token.start, token.end(), *new);
+                    let message = format!("`{text}` is now `{new}`");
+                    self.note("renamed-function", message, token.start, true);
+                } else if REMOVED_FUNCTIONS.contains(&text) {
+                    let message = format!("`{text}` was removed and has no direct replacement");
+                    self.note("removed-function", message, token.start, false);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn modernize(source: &str) -> ModernizeResult {
+    let mut modernizer = Modernizer {
+        source,
+        tokens: tokenize(source),
+        edits: Vec::new(),
+        notes: Vec::new(),
+    };
+    modernizer.run();
+    let Modernizer {
+        mut edits, notes, ..
+    } = modernizer;
+    edits.sort_by_key(|&(start, end, _)| (start, end));
+
+    let mut wgsl = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (start, end, replacement) in &edits {
+        wgsl.push_str(&source[copied..*start]);
+        wgsl.push_str(replacement);
+        copied = *end;
+    }
+    wgsl.push_str(&source[copied..]);
+
+    let diagnostic = crate::parse_and_validate(&wgsl).err();
+    ModernizeResult {
+        wgsl,
+        edits: edits
+            .iter()
+            .map(|(start, end, replacement)| text_edit(source, *start, *end, replacement))
+            .collect(),
+        notes,
+        diagnostic,
+    }
+}
+
+/// Rewrite pre-1.0 WGSL syntax to its current form: `[[...]]` attributes,
+/// `@stage`, `type` aliases, module-scope `let`, `;` between struct
+/// members, `elseif`, old storage texture types and renamed builtins and
+/// functions. Returns the new source, the edits made, notes on every change
+/// and on old constructs that need a hand edit, and the diagnostic if the
+/// result still does not validate.
+#[wasm_bindgen(js_name = modernizeWgsl)]
+pub fn modernize_wgsl(source: &str) -> ModernizeResult {
+    modernize(source)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_shaders_validate_after_rewriting() {
+        let source = r#"
+            [[block]] struct Uniforms {
+                tint: vec4<f32>;
+                scale: f32;
+            };
+            type Color = vec4<f32>;
+            let HALF: f32 = 0.5;
+            [[group(0), binding(0)]] var<uniform> u: Uniforms;
+            [[group(0), binding(1)]] var output: texture_write_only_2d<rgba8unorm>;
+
+            [[stage(fragment)]]
+            fn main([[builtin(frag_coord)]] coord: vec4<f32>) -> [[location(0)]] Color {
+                var c = u.tint * HALF;
+                if (coord.x > 1.0) {
+                    c = vec4<f32>(0.0);
+                } elseif (coord.y > 1.0) {
+                    c = vec4<f32>(smoothStep(0.0, 1.0, u.scale));
+                }
+                return c;
+            }
+        "#;
+        let result = modernize(source);
+        assert!(result.diagnostic.is_none(), "{:?}", result.diagnostic);
+        assert!(result.wgsl.contains("@group(0) @binding(0) var<uniform>"));
+        assert!(
+            result
+                .wgsl
+                .contains("texture_storage_2d<rgba8unorm, write>")
+        );
+        assert!(result.wgsl.contains("@fragment\n"));
+        assert!(result.wgsl.contains("@builtin(position) coord"));
+        assert!(result.wgsl.contains("const HALF"));
+        let block = result
+            .notes
+            .iter()
+            .find(|n| n.rule == "removed-attribute")
+            .unwrap();
+        assert_eq!((block.line, block.fixed), (2, false));
+        assert_eq!(result.edits[0].line, 2);
+    }
+
+    #[test]
+    fn overrides_and_current_syntax() {
+        let result = modernize("[[override(3)]] let gain: f32 = 1.0;");
+        assert_eq!(result.wgsl, "@id(3) override gain: f32 = 1.0;");
+
+        let current = "@vertex fn vs(@builtin(vertex_index) i: u32) -> @builtin(position) \
+                       vec4<f32> { return vec4<f32>(f32(i)); }";
+        let result = modernize(current);
+        assert_eq!(result.wgsl, current);
+        assert!(result.edits.is_empty() && result.notes.is_empty());
+
+        let removed = modernize("fn f(x: f32) -> bool { return isNan(x); }");
+        assert_eq!(removed.notes[0].rule, "removed-function");
+        assert!(removed.diagnostic.is_some());
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+
+use naga::back::msl::{BindSamplerTarget, BindTarget, EntryPointResources};
+use naga::{AddressSpace, ResourceBinding, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::entry_points;
+use crate::preset;
+use crate::sourcemap::{self, SourceMap};
+
+// ============================================================================
+// MSL Backend Types
+// ============================================================================
+
+/// Options object accepted by `wgslToMslWithBindings`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MslOptions {
+    /// `"1.0"` through `"3.1"`; overrides the preset's.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Device preset whose capabilities the module is validated against.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// First slot of each class per bind group.
+    #[serde(default)]
+    pub bind_groups: Vec<MslBindGroup>,
+    /// Pipeline constant values by override name or `@id`, baked in before
+    /// emitting.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, f64>,
+    /// Strip what the compiled entry points never use before emitting.
+    #[serde(default)]
+    pub compact: bool,
+    /// Put a `#line` directive before each function pointing at its WGSL
+    /// declaration, and return the mapping as `sourceMap`.
+    #[serde(default)]
+    pub source_map: bool,
+    /// The file name `#line` directives and the source map give the WGSL.
+    #[serde(default)]
+    pub file_name: Option<String>,
+    /// Skip validation, as for `wgslToSpirvBin`.
+    #[serde(default)]
+    pub assume_valid: bool,
+    /// Name the entry point is given in the output, as for `wgslToSpirvBin`.
+    #[serde(default)]
+    pub entry_point_name: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MslBindGroup {
+    pub group: u32,
+    #[serde(default)]
+    pub buffer: Option<u32>,
+    #[serde(default)]
+    pub texture: Option<u32>,
+    #[serde(default)]
+    pub sampler: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MslOutput {
+    #[wasm_bindgen(readonly)]
+    pub source: String,
+    /// Every Metal argument index assigned, per entry point.
+    #[wasm_bindgen(readonly)]
+    pub bindings: Vec<MslBinding>,
+    /// Where each function's `#line` points, with the `sourceMap` option.
+    #[wasm_bindgen(readonly)]
+    pub source_map: Option<SourceMap>,
+}
+
+#[wasm_bindgen]
+impl MslOutput {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MslBinding {
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    /// `"buffer"`, `"texture"` or `"sampler"` for a resource; `"sizes"` and
+    /// `"pushConstants"` are the buffers naga adds for runtime array lengths
+    /// and push constants, and have no group or binding.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    /// The `[[buffer(n)]]`, `[[texture(n)]]` or `[[sampler(n)]]` index.
+    #[wasm_bindgen(readonly)]
+    pub index: u32,
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub binding: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+}
+
+#[wasm_bindgen]
+impl MslBinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// MSL Resource Mapping
+// ============================================================================
+//
+// Without a resource map naga numbers Metal arguments in declaration order,
+// so an unrelated binding shifts every index after it. Here each entry point
+// gets its own map: the resources it uses, in (group, binding) order, take
+// consecutive slots of their class, starting from the group's configured base
+// or else right after the previous group. The sizes and push constant buffers
+// naga may add come after every resource buffer.
+
+fn parse_version(name: &str) -> Result<(u8, u8), Diagnostic> {
+    const VERSIONS: &[(u8, u8)] = &[
+        (1, 0),
+        (1, 1),
+        (1, 2),
+        (2, 0),
+        (2, 1),
+        (2, 2),
+        (2, 3),
+        (2, 4),
+        (3, 0),
+        (3, 1),
+    ];
+    VERSIONS
+        .iter()
+        .copied()
+        .find(|(major, minor)| name == format!("{}.{}", major, minor))
+        .ok_or_else(|| Diagnostic::error(format!("Unknown Metal version '{}'", name)))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Buffer,
+    Texture,
+    Sampler,
+}
+
+fn class_of(module: &naga::Module, var: &naga::GlobalVariable) -> Option<Class> {
+    match var.space {
+        AddressSpace::Uniform | AddressSpace::Storage { .. } => return Some(Class::Buffer),
+        AddressSpace::Handle => {}
+        _ => return None,
+    }
+    let mut ty = &module.types[var.ty].inner;
+    if let TypeInner::BindingArray { base, .. } = *ty {
+        ty = &module.types[base].inner;
+    }
+    match *ty {
+        TypeInner::Image { .. } => Some(Class::Texture),
+        TypeInner::Sampler { .. } => Some(Class::Sampler),
+        TypeInner::AccelerationStructure { .. } => Some(Class::Buffer),
+        _ => None,
+    }
+}
+
+/// Slots a binding takes: a binding array of constant size takes one each.
+fn slot_count(module: &naga::Module, var: &naga::GlobalVariable) -> u32 {
+    match module.types[var.ty].inner {
+        TypeInner::BindingArray {
+            size: naga::ArraySize::Constant(size),
+            ..
+        } => size.get(),
+        _ => 1,
+    }
+}
+
+/// Next free slot per class.
+#[derive(Default, Clone, Copy)]
+struct Cursor([u32; 3]);
+
+impl Cursor {
+    fn take(&mut self, class: Class, count: u32) -> Result<u8, Diagnostic> {
+        let next = &mut self.0[class as usize];
+        let slot = u8::try_from(*next)
+            .map_err(|_| Diagnostic::error(format!("Metal argument index {} exceeds 255", next)))?;
+        *next += count;
+        Ok(slot)
+    }
+}
+
+fn class_name(class: Class) -> &'static str {
+    match class {
+        Class::Buffer => "buffer",
+        Class::Texture => "texture",
+        Class::Sampler => "sampler",
+    }
+}
+
+/// Resource map for the entry point at `index`, and what it assigned.
+fn map_entry_point(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    index: usize,
+    groups: &[MslBindGroup],
+) -> Result<(EntryPointResources, Vec<MslBinding>), Diagnostic> {
+    let ep = &module.entry_points[index];
+    let ep_info = info.get_entry_point(index);
+    let mut used: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter(|(handle, _)| !ep_info[*handle].is_empty())
+        .filter_map(|(_, var)| Some((var.binding?, var)))
+        .collect();
+    used.sort_by_key(|(binding, _)| (binding.group, binding.binding));
+
+    let mut resources = EntryPointResources::default();
+    let mut bindings = Vec::new();
+    let mut cursor = Cursor::default();
+    let mut group = None;
+    for (binding, var) in used {
+        let Some(class) = class_of(module, var) else {
+            continue;
+        };
+        if group != Some(binding.group) {
+            group = Some(binding.group);
+            if let Some(bases) = groups.iter().find(|g| g.group == binding.group) {
+                let base = [bases.buffer, bases.texture, bases.sampler];
+                for (next, base) in cursor.0.iter_mut().zip(base) {
+                    *next = base.unwrap_or(*next);
+                }
+            }
+        }
+        let slot = cursor.take(class, slot_count(module, var))?;
+        let mut target = BindTarget::default();
+        match class {
+            Class::Buffer => target.buffer = Some(slot),
+            Class::Texture => target.texture = Some(slot),
+            Class::Sampler => target.sampler = Some(BindSamplerTarget::Resource(slot)),
+        }
+        resources.resources.insert(
+            ResourceBinding {
+                group: binding.group,
+                binding: binding.binding,
+            },
+            target,
+        );
+        bindings.push(MslBinding {
+            entry_point: ep.name.clone(),
+            kind: class_name(class).to_string(),
+            index: slot as u32,
+            group: Some(binding.group),
+            binding: Some(binding.binding),
+            name: var.name.clone(),
+        });
+    }
+
+    // Past every resource buffer, whatever the group bases.
+    let buffers = bindings.iter().filter(|b| b.kind == "buffer");
+    cursor.0[Class::Buffer as usize] = buffers.map(|b| b.index + 1).max().unwrap_or(0);
+    let mut extra = |kind: &str| -> Result<u8, Diagnostic> {
+        let slot = cursor.take(Class::Buffer, 1)?;
+        bindings.push(MslBinding {
+            entry_point: ep.name.clone(),
+            kind: kind.to_string(),
+            index: slot as u32,
+            group: None,
+            binding: None,
+            name: None,
+        });
+        Ok(slot)
+    };
+    let uses = |pred: &dyn Fn(&naga::GlobalVariable) -> bool| {
+        module
+            .global_variables
+            .iter()
+            .any(|(handle, var)| !ep_info[handle].is_empty() && pred(var))
+    };
+    if uses(&|var| needs_array_length(module, var.ty)) {
+        resources.sizes_buffer = Some(extra("sizes")?);
+    }
+    if uses(&|var| var.space == AddressSpace::PushConstant) {
+        resources.push_constant_buffer = Some(extra("pushConstants")?);
+    }
+    Ok((resources, bindings))
+}
+
+/// Whether naga passes `ty`'s runtime length in the sizes buffer.
+fn needs_array_length(module: &naga::Module, ty: naga::Handle<naga::Type>) -> bool {
+    let dynamic = |ty: naga::Handle<naga::Type>| {
+        matches!(
+            module.types[ty].inner,
+            TypeInner::Array {
+                size: naga::ArraySize::Dynamic,
+                ..
+            }
+        )
+    };
+    match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => members.last().is_some_and(|m| dynamic(m.ty)),
+        _ => dynamic(ty),
+    }
+}
+
+pub(crate) fn compile_msl_mapped(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    options: &MslOptions,
+) -> Result<MslOutput, Diagnostic> {
+    let version = options.version.as_deref().map(parse_version).transpose()?;
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let module = crate::parse_wgsl(wgsl)?;
+    let info = crate::validate_or_trust(&module, preset, options.assume_valid)?;
+
+    let entry_point = entry_point.filter(|name| !name.is_empty());
+    if let Some(name) = entry_point {
+        crate::find_entry_point(&module, name)?;
+    }
+    // Renamed after the bindings are mapped, which keep the WGSL name.
+    let renamed = options
+        .entry_point_name
+        .as_deref()
+        .map(|name| entry_points::rename_for_output(&module, entry_point, name))
+        .transpose()?;
+    let mut msl_opts = crate::msl_options(preset);
+    if let Some(version) = version {
+        msl_opts.lang_version = version;
+    }
+    let mut bindings = Vec::new();
+    for (index, ep) in module.entry_points.iter().enumerate() {
+        if entry_point.is_some_and(|name| name != ep.name) {
+            continue;
+        }
+        let (resources, assigned) = map_entry_point(&module, &info, index, &options.bind_groups)?;
+        let name = options.entry_point_name.as_ref().unwrap_or(&ep.name);
+        msl_opts.per_entry_point_map.insert(name.clone(), resources);
+        bindings.extend(assigned);
+    }
+    let (emitted, emitted_entry_point) = match &renamed {
+        Some(renamed) => (renamed, options.entry_point_name.as_deref()),
+        None => (&module, entry_point),
+    };
+    let source = crate::write_msl_configured(
+        emitted,
+        &info,
+        emitted_entry_point,
+        msl_opts,
+        &options.overrides,
+        options.compact,
+    )?;
+    let (source, source_map) = if options.source_map {
+        let file = sourcemap::file_name(options.file_name.as_deref());
+        let (source, map) = sourcemap::annotate_msl(&source, wgsl, &module, emitted, file);
+        (source, Some(map))
+    } else {
+        (source, None)
+    };
+    Ok(MslOutput {
+        source,
+        bindings,
+        source_map,
+    })
+}
+
+/// WGSL -> MSL with predictable Metal argument indices, returned alongside the
+/// source. Each entry point's resources take consecutive `[[buffer]]`,
+/// `[[texture]]` and `[[sampler]]` indices in (group, binding) order;
+/// `bindGroups` sets where a group's indices start, and groups without a base
+/// continue from the previous one. `options` is `{ version?: "2.1" | ...,
+/// preset?, bindGroups?: [{ group, buffer?, texture?, sampler? }],
+/// overrides?: { [nameOrId]: number }, compact?: boolean, sourceMap?:
+/// boolean, fileName?: string, assumeValid?: boolean, entryPointName?:
+/// string }`; `compact` strips functions, globals and types no emitted
+/// entry point uses. `sourceMap` puts a `#line` before each
+/// function so Xcode reports WGSL lines in `fileName` (default
+/// `shader.wgsl`), and returns them as `sourceMap`. `assumeValid` skips
+/// validation for WGSL written from a validated module.
+#[wasm_bindgen(js_name = wgslToMslWithBindings)]
+pub fn wgsl_to_msl_with_bindings(
+    wgsl: &str,
+    entry_point: Option<String>,
+    options: JsValue,
+) -> Result<MslOutput, JsValue> {
+    let options: Option<MslOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid MSL options: {e}")))?;
+    compile_msl_mapped(wgsl, entry_point.as_deref(), &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<uniform> camera: mat4x4<f32>;
+        @group(0) @binding(1) var<uniform> unused: vec4<f32>;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+        @group(1) @binding(1) var linear: sampler;
+        @group(1) @binding(2) var<uniform> material: vec4<f32>;
+        @group(2) @binding(0) var<storage, read> lights: array<vec4<f32>>;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            let n = f32(arrayLength(&lights));
+            return camera[0] * textureSample(albedo, linear, uv) * material + lights[0] * n;
+        }
+    "#;
+
+    fn indices(output: &MslOutput) -> Vec<(&str, u32, Option<String>)> {
+        output
+            .bindings
+            .iter()
+            .map(|b| (b.kind.as_str(), b.index, b.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn used_resources_take_dense_indices() {
+        let output = compile_msl_mapped(SHADER, Some("fs"), &MslOptions::default()).unwrap();
+        let name = |n: &str| Some(n.to_string());
+        assert_eq!(
+            indices(&output),
+            [
+                ("buffer", 0, name("camera")),
+                ("texture", 0, name("albedo")),
+                ("sampler", 0, name("linear")),
+                ("buffer", 1, name("material")),
+                ("buffer", 2, name("lights")),
+                ("sizes", 3, None),
+            ]
+        );
+        assert!(output.source.contains("[[buffer(1)]]"));
+        assert!(output.source.contains("[[buffer(3)]]"));
+        assert!(!output.source.contains("fake"));
+    }
+
+    #[test]
+    fn group_bases_and_version() {
+        let options = MslOptions {
+            version: Some("2.1".to_string()),
+            bind_groups: vec![MslBindGroup {
+                group: 1,
+                buffer: Some(8),
+                texture: Some(4),
+                sampler: None,
+            }],
+            ..Default::default()
+        };
+        let output = compile_msl_mapped(SHADER, None, &options).unwrap();
+        let assigned: Vec<_> = output
+            .bindings
+            .iter()
+            .map(|b| (b.kind.as_str(), b.index))
+            .collect();
+        assert_eq!(
+            assigned,
+            [
+                ("buffer", 0),
+                ("texture", 4),
+                ("sampler", 0),
+                ("buffer", 8),
+                ("buffer", 9),
+                ("sizes", 10),
+            ]
+        );
+        assert!(output.source.contains("[[texture(4)]]"));
+        assert!(output.source.contains("#include <metal_stdlib>"));
+
+        let bad = MslOptions {
+            version: Some("4.0".to_string()),
+            ..Default::default()
+        };
+        assert!(compile_msl_mapped(SHADER, None, &bad).is_err());
+        assert!(compile_msl_mapped(SHADER, Some("nope"), &MslOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::Diagnostic;
+
+// ============================================================================
+// Artifact Output
+// ============================================================================
+//
+// Batch jobs with an `outputPath` write their artifact there instead of
+// returning it. Natively that is `std::fs`. Under Node the module has no
+// file system of its own, so it asks Node for `fs` at call time (through
+// `process.getBuiltinModule`, Node 20.16 and later) rather than importing
+// it, which keeps the same build loadable in browsers, where writing fails
+// with a diagnostic instead. The data goes to `writeFileSync` as a view of
+// the module's memory, never copied into a JS buffer.
+
+fn write_error(path: &str, reason: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::error(format!("Could not write '{path}': {reason}"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn write_file(path: &str, data: &[u8]) -> Result<(), Diagnostic> {
+    std::fs::write(path, data).map_err(|e| write_error(path, e))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn write_file(path: &str, data: &[u8]) -> Result<(), Diagnostic> {
+    use js_sys::{Function, Reflect};
+    use wasm_bindgen::{JsCast, JsValue};
+
+    let reason = |error: JsValue| match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{error:?}"),
+    };
+    let no_fs = || {
+        write_error(
+            path,
+            "no file system (outputPath needs Node 20.16 or later)",
+        )
+    };
+    let process = Reflect::get(&js_sys::global(), &"process".into()).map_err(|_| no_fs())?;
+    let get_builtin = Reflect::get(&process, &"getBuiltinModule".into())
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .ok_or_else(no_fs)?;
+    let fs = get_builtin
+        .call1(&process, &"fs".into())
+        .map_err(|e| write_error(path, reason(e)))?;
+    let write = Reflect::get(&fs, &"writeFileSync".into())
+        .ok()
+        .and_then(|f| f.dyn_into::<Function>().ok())
+        .ok_or_else(no_fs)?;
+    let target = JsValue::from_str(path);
+    // SAFETY: nothing allocates while the view is alive, so the memory it
+    // points into cannot move, and `writeFileSync` is done with it on return.
+    let view = unsafe { js_sys::Uint8Array::view(data) };
+    write
+        .call2(&fs, &target, &view)
+        .map(|_| ())
+        .map_err(|e| write_error(path, reason(e)))
+}
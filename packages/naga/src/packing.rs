@@ -0,0 +1,504 @@
+use std::fmt::Write;
+
+use naga::proc::Layouter;
+use naga::{ArraySize, Handle, Scalar, ScalarKind, Type, TypeInner, VectorSize};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::layout::{find_struct, layouter};
+use crate::typescript::host_shareable;
+
+// ============================================================================
+// Packing Plan Types
+// ============================================================================
+
+/// How to pack a struct into a buffer, from `getPackingPlan`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PackingPlan {
+    /// The struct the requested name resolved to, through any aliases.
+    #[wasm_bindgen(readonly)]
+    pub type_name: String,
+    /// Size in bytes, trailing padding included; with a runtime-sized array,
+    /// for one element of it.
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    #[wasm_bindgen(readonly)]
+    pub alignment: u32,
+    /// One entry per scalar, vector or matrix member, in offset order.
+    #[wasm_bindgen(readonly)]
+    pub entries: Vec<PackEntry>,
+    /// The runtime-sized array ending the struct, if any.
+    #[wasm_bindgen(readonly)]
+    pub tail: Option<PackTail>,
+    /// With `writer: true`, the body of a JS function of `value` that returns
+    /// it packed into a new, zeroed `Uint8Array`.
+    #[wasm_bindgen(readonly)]
+    pub writer: Option<String>,
+}
+
+#[wasm_bindgen]
+impl PackingPlan {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PackEntry {
+    /// Member path from the struct, with `[]` for each array on the way,
+    /// e.g. `lights[].color`.
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    /// Byte offset with every array index at 0.
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    #[wasm_bindgen(readonly)]
+    pub size: u32,
+    /// Scalar type each component is written as: `f32`, `f16` (as its
+    /// bits), `f64`, `i32`, `u32`, or `i64` and `u64` (from bigints).
+    #[wasm_bindgen(readonly)]
+    pub conversion: String,
+    /// Components of a vector or matrix column; 1 for scalars.
+    #[wasm_bindgen(readonly)]
+    pub components: u32,
+    /// Columns of a matrix, whose value is a flat column-major array; 1
+    /// otherwise.
+    #[wasm_bindgen(readonly)]
+    pub columns: u32,
+    /// Bytes between matrix columns; 0 otherwise.
+    #[wasm_bindgen(readonly)]
+    pub column_stride: u32,
+    /// The arrays the path's `[]`s stand for, outermost first. Element
+    /// `i, j, ...` sits at `offset + i * arrays[0].stride + j * ...`.
+    #[wasm_bindgen(readonly)]
+    pub arrays: Vec<PackArray>,
+}
+
+#[wasm_bindgen]
+impl PackEntry {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PackArray {
+    /// `None` for a runtime-sized array, which takes its count from the value.
+    #[wasm_bindgen(readonly)]
+    pub count: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub stride: u32,
+}
+
+#[wasm_bindgen]
+impl PackArray {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A runtime-sized array: the struct takes
+/// `ceil((offset + stride * length) / alignment) * alignment` bytes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PackTail {
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    #[wasm_bindgen(readonly)]
+    pub stride: u32,
+}
+
+#[wasm_bindgen]
+impl PackTail {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PackingOptions {
+    #[serde(default)]
+    pub writer: bool,
+}
+
+// ============================================================================
+// Packing Plan Implementation
+// ============================================================================
+//
+// The plan is `getStructLayout` cut down to what a packer needs: leaves
+// only, arrays kept as strides rather than listed element by element, and
+// the conversion each component goes through. The writer is generated from
+// the plan alone, one loop per array and one `DataView` call per
+// component, so anything packing by hand from the plan writes the same
+// bytes. Padding is never written and stays zero.
+
+fn conversion(scalar: Scalar) -> &'static str {
+    match (scalar.kind, scalar.width) {
+        (ScalarKind::Float, 2) => "f16",
+        (ScalarKind::Float, 8) => "f64",
+        (ScalarKind::Float, _) => "f32",
+        (ScalarKind::Sint, 8) => "i64",
+        (ScalarKind::Sint, _) => "i32",
+        (ScalarKind::Uint, 8) => "u64",
+        (_, _) => "u32",
+    }
+}
+
+fn walk(
+    module: &naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+    offset: u32,
+    path: String,
+    arrays: &mut Vec<PackArray>,
+    out: &mut Vec<PackEntry>,
+) {
+    let entry = |scalar: Scalar, components: u32, columns: u32, column_stride: u32| PackEntry {
+        path: path.clone(),
+        offset,
+        size: layouter[ty].size,
+        conversion: conversion(scalar).to_string(),
+        components,
+        columns,
+        column_stride,
+        arrays: arrays.clone(),
+    };
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) => out.push(entry(s, 1, 1, 0)),
+        TypeInner::Vector { size, scalar } => out.push(entry(scalar, size as u32, 1, 0)),
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => {
+            // Columns align like vectors, so vec3 columns take four slots.
+            let slots = match rows {
+                VectorSize::Tri => 4,
+                rows => rows as u32,
+            };
+            let stride = slots * scalar.width as u32;
+            out.push(entry(scalar, rows as u32, columns as u32, stride));
+        }
+        TypeInner::Array { base, size, stride } => {
+            let count = match size {
+                ArraySize::Constant(count) => Some(count.get()),
+                _ => None,
+            };
+            arrays.push(PackArray { count, stride });
+            let path = format!("{path}[]");
+            walk(module, layouter, base, offset, path, arrays, out);
+            arrays.pop();
+        }
+        TypeInner::Struct { ref members, .. } => {
+            for member in members {
+                let name = member.name.as_deref().unwrap_or("_");
+                let path = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{path}.{name}")
+                };
+                let at = offset + member.offset;
+                walk(module, layouter, member.ty, at, path, arrays, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `value.<path>` with the `[]`s before char `end` indexed by `i0`, `i1`...
+fn accessor(path: &str, end: usize) -> String {
+    let mut out = String::from("value.");
+    for (depth, part) in path[..end].split("[]").enumerate() {
+        if depth > 0 {
+            let _ = write!(out, "[i{}]", depth - 1);
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+fn setter(conversion: &str) -> &'static str {
+    match conversion {
+        "f16" => "setUint16",
+        "f64" => "setFloat64",
+        "f32" => "setFloat32",
+        "i64" => "setBigInt64",
+        "i32" => "setInt32",
+        "u64" => "setBigUint64",
+        _ => "setUint32",
+    }
+}
+
+/// Bytes each component of a `conversion` takes.
+fn width(conversion: &str) -> u32 {
+    match conversion {
+        "f16" => 2,
+        "f64" | "i64" | "u64" => 8,
+        _ => 4,
+    }
+}
+
+/// Body of `f16Bits(x)`: the f16 bits of `x`, rounding toward zero and
+/// flushing subnormals. Also valid TypeScript, so the generated bindings
+/// wrap the same statements.
+pub(crate) const F16_BITS_BODY: &str = "\
+  const f32 = new DataView(new ArrayBuffer(4));
+  f32.setFloat32(0, x);
+  const bits = f32.getUint32(0);
+  const sign = (bits >>> 16) & 0x8000;
+  const exponent = ((bits >>> 23) & 0xff) - 127 + 15;
+  if (exponent <= 0) return sign;
+  if (exponent >= 31) return sign | 0x7c00;
+  return sign | (exponent << 10) | ((bits >>> 13) & 0x3ff);
+";
+
+fn writer(plan: &PackingPlan) -> String {
+    let mut out = String::new();
+    match &plan.tail {
+        Some(tail) => {
+            let _ = writeln!(
+                out,
+                "const end = {} + {} * {}.length;",
+                tail.offset,
+                tail.stride,
+                accessor(&tail.path, tail.path.len())
+            );
+            let _ = writeln!(
+                out,
+                "const bytes = new Uint8Array(Math.ceil(end / {0}) * {0});",
+                plan.alignment
+            );
+        }
+        None => {
+            let _ = writeln!(out, "const bytes = new Uint8Array({});", plan.size);
+        }
+    }
+    let _ = writeln!(out, "const view = new DataView(bytes.buffer);");
+    if plan.entries.iter().any(|e| e.conversion == "f16") {
+        let _ = writeln!(
+            out,
+            "// f16 bits of `x`, rounding toward zero and flushing subnormals.\n\
+             const f16Bits = (x) => {{\n{F16_BITS_BODY}}};"
+        );
+    }
+
+    for entry in &plan.entries {
+        let mut indent = String::new();
+        let mut terms = Vec::new();
+        let dims = entry.path.match_indices("[]").map(|(i, _)| i);
+        for (depth, (start, array)) in dims.zip(&entry.arrays).enumerate() {
+            let count = match array.count {
+                Some(count) => count.to_string(),
+                None => format!("{}.length", accessor(&entry.path, start)),
+            };
+            let _ = writeln!(
+                out,
+                "{indent}for (let i{depth} = 0; i{depth} < {count}; i{depth}++) {{"
+            );
+            terms.push(format!("{} * i{depth}", array.stride));
+            indent.push_str("  ");
+        }
+        let value = accessor(&entry.path, entry.path.len());
+        let setter = setter(&entry.conversion);
+        for column in 0..entry.columns {
+            for row in 0..entry.components {
+                let component = match (entry.columns, entry.components) {
+                    (1, 1) => value.clone(),
+                    (1, _) => format!("{value}[{row}]"),
+                    _ => format!("{value}[{}]", column * entry.components + row),
+                };
+                let component = match entry.conversion.as_str() {
+                    "f16" => format!("f16Bits({component})"),
+                    _ => component,
+                };
+                let bytes =
+                    entry.offset + column * entry.column_stride + row * width(&entry.conversion);
+                let at = match (bytes, terms.is_empty()) {
+                    (0, false) => terms.join(" + "),
+                    (bytes, true) => bytes.to_string(),
+                    (bytes, false) => format!("{bytes} + {}", terms.join(" + ")),
+                };
+                let _ = writeln!(out, "{indent}view.{setter}({at}, {component}, true);");
+            }
+        }
+        for depth in (0..entry.arrays.len()).rev() {
+            let _ = writeln!(out, "{}}}", "  ".repeat(depth));
+        }
+    }
+    let _ = writeln!(out, "return bytes;");
+    out
+}
+
+pub(crate) fn packing_plan(
+    source: &str,
+    module: &naga::Module,
+    name: &str,
+    options: &PackingOptions,
+) -> Result<PackingPlan, Diagnostic> {
+    let (ty, type_name) = find_struct(source, module, name)?;
+    if !host_shareable(module, ty) {
+        return Err(Diagnostic::error(format!(
+            "Struct '{name}' cannot be stored in a buffer"
+        )));
+    }
+    let layouter = layouter(module)?;
+    let mut entries = Vec::new();
+    walk(
+        module,
+        &layouter,
+        ty,
+        0,
+        String::new(),
+        &mut Vec::new(),
+        &mut entries,
+    );
+    entries.sort_by_key(|e| e.offset);
+
+    let tail = match module.types[ty].inner {
+        TypeInner::Struct { ref members, .. } => {
+            members
+                .last()
+                .and_then(|member| match module.types[member.ty].inner {
+                    TypeInner::Array {
+                        size: ArraySize::Dynamic,
+                        stride,
+                        ..
+                    } => Some(PackTail {
+                        path: member.name.clone().unwrap_or_else(|| "_".to_string()),
+                        offset: member.offset,
+                        stride,
+                    }),
+                    _ => None,
+                })
+        }
+        _ => None,
+    };
+    let mut plan = PackingPlan {
+        type_name,
+        size: layouter[ty].size,
+        alignment: layouter[ty].alignment.round_up(1),
+        entries,
+        tail,
+        writer: None,
+    };
+    if options.writer {
+        plan.writer = Some(writer(&plan));
+    }
+    Ok(plan)
+}
+
+/// Packing plan for the struct `structName`: every scalar, vector and
+/// matrix in it with its byte offset, size and conversion, arrays as
+/// strides, and the runtime-sized tail if there is one. `options` is
+/// `{ writer?: boolean }`; with `writer`, the plan also carries a JS
+/// function body, for `new Function("value", plan.writer)`, that packs a
+/// plain object into a `Uint8Array` with the padding the GPU expects.
+#[wasm_bindgen(js_name = getPackingPlan)]
+pub fn get_packing_plan(
+    wgsl: &str,
+    struct_name: &str,
+    options: JsValue,
+) -> Result<PackingPlan, JsValue> {
+    let options: Option<PackingOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid packing options: {e}")))?;
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    packing_plan(wgsl, &module, struct_name, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Uniforms {
+            normal: mat3x3<f32>,
+            lights: array<Light, 2>,
+            frame: u32,
+        }
+        struct Particles { count: atomic<u32>, positions: array<vec4<f32>> }
+        @group(0) @binding(0) var<uniform> uniforms: Uniforms;
+        @group(0) @binding(1) var<storage, read_write> particles: Particles;
+    "#;
+
+    fn plan(name: &str) -> PackingPlan {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let options = PackingOptions { writer: true };
+        packing_plan(SHADER, &module, name, &options).unwrap()
+    }
+
+    #[test]
+    fn plans_keep_arrays_as_strides() {
+        let plan = plan("Uniforms");
+        assert_eq!(plan.size, 96);
+        let paths: Vec<_> = plan
+            .entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.offset))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("normal", 0),
+                ("lights[].color", 48),
+                ("lights[].intensity", 60),
+                ("frame", 80)
+            ]
+        );
+        assert_eq!(plan.entries[0].column_stride, 16);
+        assert_eq!(
+            plan.entries[1].arrays,
+            [PackArray {
+                count: Some(2),
+                stride: 16
+            }]
+        );
+        assert!(plan.tail.is_none());
+
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let error = packing_plan(SHADER, &module, "Missing", &PackingOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(error.message, "Type 'Missing' not found");
+    }
+
+    #[test]
+    fn writers_skip_padding() {
+        let writer = plan("Uniforms").writer.unwrap();
+        assert!(writer.starts_with("const bytes = new Uint8Array(96);\n"));
+        // The third column starts at 32, past the first two's padding.
+        assert!(writer.contains("view.setFloat32(32, value.normal[6], true);\n"));
+        assert!(writer.contains(
+            "for (let i0 = 0; i0 < 2; i0++) {\n  \
+             view.setFloat32(48 + 16 * i0, value.lights[i0].color[0], true);\n"
+        ));
+        assert!(writer.ends_with("view.setUint32(80, value.frame, true);\nreturn bytes;\n"));
+
+        let writer = plan("Particles").writer.unwrap();
+        assert!(writer.starts_with(
+            "const end = 16 + 16 * value.positions.length;\n\
+             const bytes = new Uint8Array(Math.ceil(end / 16) * 16);\n"
+        ));
+        assert!(writer.contains("i0 < value.positions.length;"));
+    }
+}
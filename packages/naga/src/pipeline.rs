@@ -0,0 +1,631 @@
+use std::collections::HashSet;
+
+use naga::proc::{ConstantEvaluator, Emitter, ExpressionKindTracker};
+use naga::{Block, Expression, Statement};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::rewrite::{self, operands, visit_statement_operands};
+
+// ============================================================================
+// Pipeline Types
+// ============================================================================
+
+/// Pass names, in no particular order.
+const PASSES: &[&str] = &["constant-fold", "dce", "cse", "inline"];
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct OptimizeReport {
+    /// One entry per pass run, in order.
+    #[wasm_bindgen(readonly)]
+    pub passes: Vec<PassMetrics>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// Warnings from passes that stopped short, such as a `constant-fold`
+    /// that reached its step limit.
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl OptimizeReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// What one pass did. Counts are over every function and entry point.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PassMetrics {
+    #[wasm_bindgen(readonly)]
+    pub pass: String,
+    /// Expressions folded, merged or calls inlined; for `dce`, statements,
+    /// `let`s, functions and globals removed.
+    #[wasm_bindgen(readonly)]
+    pub changes: u32,
+    #[wasm_bindgen(readonly)]
+    pub expressions_before: u32,
+    #[wasm_bindgen(readonly)]
+    pub expressions_after: u32,
+    #[wasm_bindgen(readonly)]
+    pub statements_before: u32,
+    #[wasm_bindgen(readonly)]
+    pub statements_after: u32,
+}
+
+#[wasm_bindgen]
+impl PassMetrics {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Constant Folding
+// ============================================================================
+//
+// naga folds constant expressions while lowering WGSL, but inlining and
+// merging leave new ones behind, such as arithmetic on a literal argument.
+// Each is evaluated with naga's own evaluator, on a scratch copy of the
+// arena since evaluation appends its results; a scalar or zero result then
+// replaces the expression in place. Anything the evaluator rejects, such as
+// an integer division by zero, is left to run as written.
+//
+// The evaluator has no limits of its own, and indexing or comparing a large
+// constant expands every component of it, so a pass runs on a step budget.
+// Evaluating an expression costs one step plus one per scalar its operands
+// hold, charged before the evaluator starts; a pass that would overrun the
+// budget stops there with a warning, keeping what it folded so far. naga's
+// own folding while parsing is bounded separately, by `const_limit`.
+
+/// Steps one `constant-fold` pass may take unless the options say otherwise.
+const DEFAULT_MAX_CONST_STEPS: u64 = 1_000_000;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OptimizeOptions {
+    /// Evaluation steps each `constant-fold` pass may take.
+    #[serde(default = "default_max_const_steps")]
+    pub max_const_steps: u64,
+}
+
+fn default_max_const_steps() -> u64 {
+    DEFAULT_MAX_CONST_STEPS
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        Self {
+            max_const_steps: DEFAULT_MAX_CONST_STEPS,
+        }
+    }
+}
+
+/// Scalars in a value of type `ty`.
+fn scalars(module: &naga::Module, ty: naga::Handle<naga::Type>) -> u64 {
+    use naga::{ArraySize, TypeInner};
+    match module.types[ty].inner {
+        TypeInner::Vector { size, .. } => size as u64,
+        TypeInner::Matrix { columns, rows, .. } => columns as u64 * rows as u64,
+        TypeInner::Array {
+            base,
+            size: ArraySize::Constant(count),
+            ..
+        } => scalars(module, base).saturating_mul(count.get() as u64),
+        TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .fold(0, |sum: u64, m| sum.saturating_add(scalars(module, m.ty))),
+        _ => 1,
+    }
+}
+
+/// Scalars the value of `h` holds once expanded. Composites share their
+/// components, so each handle's count is kept in `memo`.
+fn weight(
+    module: &naga::Module,
+    scratch: &naga::Arena<Expression>,
+    memo: &mut Vec<Option<u64>>,
+    h: naga::Handle<Expression>,
+) -> u64 {
+    if let Some(Some(known)) = memo.get(h.index()) {
+        return *known;
+    }
+    let value = match scratch[h] {
+        Expression::ZeroValue(ty) => scalars(module, ty),
+        Expression::Constant(c) => scalars(module, module.constants[c].ty),
+        Expression::Splat { size, .. } => size as u64,
+        Expression::Compose { ref components, .. } => {
+            components.clone().into_iter().fold(0, |sum: u64, c| {
+                sum.saturating_add(weight(module, scratch, memo, c))
+            })
+        }
+        _ => 1,
+    };
+    if memo.len() <= h.index() {
+        memo.resize(h.index() + 1, None);
+    }
+    memo[h.index()] = Some(value);
+    value
+}
+
+/// Fold `function`'s constant expressions, stopping with a warning if that
+/// would take `steps` past `max_steps`.
+fn fold_function(
+    module: &mut naga::Module,
+    function: &mut naga::Function,
+    steps: &mut u64,
+    max_steps: u64,
+) -> Result<(u32, Option<Diagnostic>), Diagnostic> {
+    let mut layouter = crate::layout::layouter(module)?;
+    let mut scratch = function.expressions.clone();
+    let mut tracker = ExpressionKindTracker::from_arena(&scratch);
+    let (mut emitter, mut block) = (Emitter::default(), Block::new());
+    let mut memo = Vec::new();
+
+    let (mut folded, mut stopped) = (0, None);
+    let handles: Vec<_> = function.expressions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let expr = &function.expressions[h];
+        let leaf = matches!(
+            expr,
+            Expression::Literal(_)
+                | Expression::Constant(_)
+                | Expression::ZeroValue(_)
+                | Expression::Compose { .. }
+        );
+        if leaf || !tracker.is_const(h) {
+            continue;
+        }
+        let cost = operands(expr).into_iter().fold(1, |sum: u64, op| {
+            sum.saturating_add(weight(module, &scratch, &mut memo, op))
+        });
+        *steps = steps.saturating_add(cost);
+        if *steps > max_steps {
+            stopped = Some(Diagnostic::warning(format!(
+                "Constant folding stopped at its limit of {max_steps} evaluation steps{}; \
+                 raise maxConstSteps or simplify the constant expressions",
+                match function.name.as_deref() {
+                    Some(name) => format!(" in '{name}'"),
+                    None => String::new(),
+                }
+            )));
+            break;
+        }
+        let result = ConstantEvaluator::for_wgsl_function(
+            module,
+            &mut scratch,
+            &mut tracker,
+            &mut layouter,
+            &mut emitter,
+            &mut block,
+            false,
+        )
+        .try_eval_and_append(expr.clone(), function.expressions.get_span(h));
+        let Ok(result) = result else {
+            continue;
+        };
+        // Later expressions evaluate against the value, whatever its form.
+        let value = scratch[result].clone();
+        *scratch.get_mut(h) = value.clone();
+        if let Some(known) = memo.get_mut(h.index()) {
+            *known = None;
+        }
+        if matches!(value, Expression::Literal(_) | Expression::ZeroValue(_)) {
+            *function.expressions.get_mut(h) = value;
+            folded += 1;
+        }
+    }
+    if folded > 0 {
+        rewrite::split_emits(&mut function.body, &function.expressions);
+    }
+    Ok((folded, stopped))
+}
+
+/// Fold constant expressions throughout `module`, within `max_steps`; any
+/// warning goes to `diagnostics` and ends the pass.
+fn constant_fold(
+    module: &mut naga::Module,
+    max_steps: u64,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<u32, Diagnostic> {
+    let (mut folded, mut steps) = (0, 0);
+    let handles: Vec<_> = module.functions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let mut function = std::mem::take(&mut module.functions[h]);
+        let result = fold_function(module, &mut function, &mut steps, max_steps);
+        module.functions[h] = function;
+        let (count, stopped) = result?;
+        folded += count;
+        if let Some(warning) = stopped {
+            diagnostics.push(warning);
+            return Ok(folded);
+        }
+    }
+    for index in 0..module.entry_points.len() {
+        let mut function = std::mem::take(&mut module.entry_points[index].function);
+        let result = fold_function(module, &mut function, &mut steps, max_steps);
+        module.entry_points[index].function = function;
+        let (count, stopped) = result?;
+        folded += count;
+        if let Some(warning) = stopped {
+            diagnostics.push(warning);
+            return Ok(folded);
+        }
+    }
+    Ok(folded)
+}
+
+// ============================================================================
+// Dead Code Elimination
+// ============================================================================
+//
+// Drops statements after a `return`, `discard`, `break` or `continue`, and
+// `let`s whose value nothing uses, then compacts the module. With entry
+// points present, compaction also removes the functions and globals none of
+// them reach; a module without any is a library and keeps them.
+
+/// Remove what follows a terminator in `block` and its children, returning
+/// how many statements went.
+fn cut_unreachable(block: &mut Block) -> u32 {
+    let end = block.iter().position(|statement| {
+        matches!(
+            statement,
+            Statement::Return { .. } | Statement::Kill | Statement::Break | Statement::Continue
+        )
+    });
+    let mut removed = 0;
+    if let Some(end) = end {
+        removed += (block.len() - end - 1) as u32;
+        block.cull(end + 1..);
+    }
+    for statement in block.iter_mut() {
+        for child in rewrite::child_blocks(statement) {
+            removed += cut_unreachable(child);
+        }
+    }
+    removed
+}
+
+/// Forget the names of expressions nothing uses, so compaction drops them,
+/// returning how many were forgotten.
+fn unname_dead(function: &mut naga::Function) -> u32 {
+    let mut pending: Vec<_> = function
+        .local_variables
+        .iter()
+        .filter_map(|(_, local)| local.init)
+        .collect();
+    rewrite::for_each_statement(&mut function.body, &mut |statement| {
+        visit_statement_operands(statement, &mut |h| pending.push(*h));
+    });
+    let mut live = HashSet::new();
+    while let Some(h) = pending.pop() {
+        if live.insert(h) {
+            pending.extend(operands(&function.expressions[h]));
+        }
+    }
+    let before = function.named_expressions.len();
+    function.named_expressions.retain(|h, _| live.contains(h));
+    (before - function.named_expressions.len()) as u32
+}
+
+fn dce(module: &mut naga::Module) -> u32 {
+    let mut removed = 0;
+    for function in rewrite::functions_mut(module) {
+        removed += cut_unreachable(&mut function.body);
+        removed += unname_dead(function);
+    }
+    let before = module.functions.len() + module.global_variables.len();
+    let keep = if module.entry_points.is_empty() {
+        naga::compact::KeepUnused::Yes
+    } else {
+        naga::compact::KeepUnused::No
+    };
+    naga::compact::compact(module, keep);
+    removed + (before - module.functions.len() - module.global_variables.len()) as u32
+}
+
+/// `module` cut down to what `entry_point`, or every entry point, uses, for
+/// the backends: other entry points go, then everything none left reaches.
+pub(crate) fn compact_for(
+    module: &naga::Module,
+    entry_point: Option<&str>,
+) -> Result<(naga::Module, naga::valid::ModuleInfo), Diagnostic> {
+    let mut module = module.clone();
+    if let Some(name) = entry_point.filter(|name| !name.is_empty()) {
+        module.entry_points.retain(|ep| ep.name == name);
+    }
+    if !module.entry_points.is_empty() {
+        naga::compact::compact(&mut module, naga::compact::KeepUnused::No);
+    }
+    // Compaction only drops what nothing uses, so the checks would pass.
+    let info = crate::revalidate_trusted(&module)?;
+    Ok((module, info))
+}
+
+// ============================================================================
+// Pipeline
+// ============================================================================
+
+/// Total expressions and statements over every function body.
+fn sizes(module: &mut naga::Module) -> (u32, u32) {
+    let (mut expressions, mut statements) = (0, 0);
+    for function in rewrite::functions_mut(module) {
+        expressions += function.expressions.len() as u32;
+        rewrite::for_each_statement(&mut function.body, &mut |_| statements += 1);
+    }
+    (expressions, statements)
+}
+
+/// Check every name in `passes` before anything runs.
+fn check_passes(passes: &[String]) -> Result<(), Diagnostic> {
+    match passes.iter().find(|p| !PASSES.contains(&p.as_str())) {
+        Some(pass) => Err(Diagnostic::error(format!(
+            "Unknown pass '{}'; expected one of {}",
+            pass,
+            PASSES.join(", ")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Run `passes` in order over a validated `module`, revalidating after each.
+/// Warnings from passes that stopped short go to `diagnostics`.
+pub(crate) fn run_passes(
+    module: &mut naga::Module,
+    passes: &[String],
+    options: &OptimizeOptions,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<Vec<PassMetrics>, Diagnostic> {
+    check_passes(passes)?;
+    let mut metrics = Vec::with_capacity(passes.len());
+    for pass in passes {
+        let (expressions_before, statements_before) = sizes(module);
+        let changes = match pass.as_str() {
+            "constant-fold" => constant_fold(module, options.max_const_steps, diagnostics)?,
+            "dce" => dce(module),
+            "cse" => crate::cse::eliminate_common_subexpressions(module)
+                .iter()
+                .map(|f| f.removed)
+                .sum(),
+            "inline" => {
+                let report = crate::inline::inline_calls(module, &Default::default())?;
+                report.inlined.iter().map(|c| c.count).sum()
+            }
+            _ => unreachable!("pass names are checked up front"),
+        };
+        crate::validate_module(module).map_err(|e| {
+            Diagnostic::error(format!(
+                "Pass '{}' produced an invalid module: {}",
+                pass, e.message
+            ))
+        })?;
+        let (expressions_after, statements_after) = sizes(module);
+        metrics.push(PassMetrics {
+            pass: pass.clone(),
+            changes,
+            expressions_before,
+            expressions_after,
+            statements_before,
+            statements_after,
+        });
+    }
+    Ok(metrics)
+}
+
+fn optimize(
+    wgsl: &str,
+    passes: &[String],
+    options: &OptimizeOptions,
+) -> Result<OptimizeReport, Diagnostic> {
+    check_passes(passes)?;
+    let (mut module, _info) = crate::parse_and_validate(wgsl)?;
+    let mut diagnostics = Vec::new();
+    let passes = run_passes(&mut module, passes, options, &mut diagnostics)?;
+    let info = crate::validate_module(&module)?;
+    let wgsl =
+        naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+    Ok(OptimizeReport {
+        passes,
+        wgsl,
+        diagnostics,
+    })
+}
+
+/// Runs an ordered list of IR passes over the shader and returns the result
+/// as WGSL, with what each pass changed and the expression and statement
+/// counts around it. Passes may repeat. `"constant-fold"` evaluates constant
+/// expressions left by other passes, `"dce"` removes unreachable statements,
+/// unused `let`s and anything no entry point reaches, `"cse"` is
+/// `eliminateCommonSubexpressions` and `"inline"` is `inlineFunctions` with
+/// its default budgets. Unknown pass names throw before anything runs.
+/// `options` is `{ maxConstSteps? }`: each `"constant-fold"` stops rather
+/// than take more evaluation steps than that, one per expression plus one
+/// per scalar it reads, a million by default, and says so in `diagnostics`.
+#[wasm_bindgen(js_name = optimizeWgsl)]
+pub fn optimize_wgsl(
+    wgsl: &str,
+    passes: Vec<String>,
+    options: JsValue,
+) -> Result<OptimizeReport, JsValue> {
+    let options: Option<OptimizeOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid optimize options: {e}")))?;
+    optimize(wgsl, &passes, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<uniform> unused: vec4<f32>;
+
+        fn scale(x: f32, k: f32) -> f32 {
+            return x * (k * 2.0 + 1.0);
+        }
+
+        @fragment
+        fn fs(@location(0) v: f32) -> @location(0) vec4<f32> {
+            let dead = v * 4.0;
+            let a = scale(v, 3.0);
+            let b = scale(v, 3.0);
+            return vec4<f32>(a, b, 0.0, 1.0);
+            discard;
+        }
+    "#;
+
+    fn pipeline(passes: &[&str]) -> OptimizeReport {
+        let passes: Vec<String> = passes.iter().map(|p| p.to_string()).collect();
+        optimize(SHADER, &passes, &OptimizeOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn passes_run_in_order_with_metrics() {
+        let report = pipeline(&["inline", "constant-fold", "cse", "dce"]);
+        let changes: Vec<_> = report
+            .passes
+            .iter()
+            .map(|m| (m.pass.as_str(), m.changes))
+            .collect();
+        // Two inlined calls; `3.0 * 2.0 + 1.0` folds in each copy, then the
+        // copies of `v * 7.0` merge; dce drops `discard`, `dead` and `unused`.
+        assert_eq!(
+            changes,
+            [("inline", 2), ("constant-fold", 4), ("cse", 1), ("dce", 3)]
+        );
+        for metrics in &report.passes[1..] {
+            assert!(metrics.expressions_after <= metrics.expressions_before);
+        }
+        assert!(report.wgsl.contains("7f"));
+        assert!(!report.wgsl.contains("unused"));
+        assert!(!report.wgsl.contains("discard"));
+    }
+
+    #[test]
+    fn pass_names_are_checked_up_front() {
+        let passes = vec!["dce".to_string(), "unroll".to_string()];
+        let error = optimize(SHADER, &passes, &OptimizeOptions::default())
+            .err()
+            .unwrap();
+        assert!(error.message.contains("Unknown pass 'unroll'"));
+        // Folding alone finds nothing the front end left.
+        assert_eq!(pipeline(&["constant-fold"]).passes[0].changes, 0);
+        assert!(pipeline(&[]).passes.is_empty());
+    }
+
+    #[test]
+    fn folding_stops_at_its_step_limit() {
+        let source = r#"
+            const TABLE = array<f32, 4>(1.0, 2.0, 3.0, 4.0);
+            fn pick(i: u32) -> f32 { return TABLE[i]; }
+            @fragment
+            fn fs() -> @location(0) vec4<f32> { return vec4<f32>(pick(2u)); }
+        "#;
+        let passes = vec!["inline".to_string(), "constant-fold".to_string()];
+        let report = optimize(source, &passes, &OptimizeOptions::default()).unwrap();
+        assert!(report.wgsl.contains("3f"));
+
+        assert!(report.diagnostics.is_empty());
+
+        // Reading the table and the index takes six steps, the splat two.
+        let options = OptimizeOptions { max_const_steps: 7 };
+        let report = optimize(source, &passes, &options).unwrap();
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].severity, "warning");
+        assert_eq!(
+            report.diagnostics[0].message,
+            "Constant folding stopped at its limit of 7 evaluation steps in 'fs'; \
+             raise maxConstSteps or simplify the constant expressions"
+        );
+        // What was folded before the limit stays folded.
+        assert_eq!(report.passes[1].changes, 1);
+        let options = OptimizeOptions { max_const_steps: 8 };
+        assert!(
+            optimize(source, &passes, &options)
+                .unwrap()
+                .diagnostics
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn oversized_constants_are_refused_before_naga_evaluates_them() {
+        let source = "const Z = array<array<f32, 65535>, 65535>();\n\
+                      @fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(Z[1][2]); }";
+        let passes = vec!["constant-fold".to_string()];
+        let error = optimize(source, &passes, &OptimizeOptions::default())
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .message
+                .starts_with("Constant value of 4294836225 scalars")
+        );
+        assert!(error.message.ends_with("(at 1:11)"));
+
+        let diagnostics = crate::diagnostics::detailed_diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        let span = diagnostics[0].span.clone().unwrap();
+        assert_eq!((span.line, span.column), (1, 11));
+    }
+
+    #[test]
+    fn backends_can_compact_first() {
+        let source = r#"
+            struct Unused { x: f32 }
+            const UNUSED_SCALE: f32 = 4.0;
+            fn unused_helper(x: f32) -> f32 { return x * UNUSED_SCALE; }
+            fn vertex_helper(x: f32) -> f32 { return x + 1.0; }
+            @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+
+            @vertex
+            fn vs() -> @builtin(position) vec4<f32> {
+                return vec4<f32>(vertex_helper(0.0));
+            }
+            @fragment
+            fn fs() -> @location(0) vec4<f32> { return tint; }
+        "#;
+        let (module, _) = crate::parse_and_validate(source).unwrap();
+        let (compacted, _) = compact_for(&module, Some("fs")).unwrap();
+        assert_eq!(compacted.entry_points.len(), 1);
+        assert!(compacted.functions.is_empty());
+        assert_eq!(compacted.global_variables.len(), 1);
+        assert!(
+            compacted
+                .types
+                .iter()
+                .all(|(_, ty)| ty.name.as_deref() != Some("Unused"))
+        );
+
+        let (all, _) = compact_for(&module, None).unwrap();
+        let names: Vec<_> = all
+            .functions
+            .iter()
+            .filter_map(|(_, f)| f.name.as_deref())
+            .collect();
+        assert_eq!(names, ["vertex_helper"]);
+
+        let spirv = |compact| {
+            let options = crate::spv::SpirvOptions {
+                compact,
+                ..Default::default()
+            };
+            crate::compile_spirv_with(source, Some("vs"), None, &options)
+                .unwrap()
+                .len()
+        };
+        assert!(spirv(true) < spirv(false));
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::lexer::{TokenKind, tokenize};
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Post-Process Types
+// ============================================================================
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PostProcessInputs {
+    /// Names of the `texture_2d<f32>` inputs, bound in order after the
+    /// sampler.
+    #[serde(default)]
+    pub textures: Vec<String>,
+    /// Members of the `params` uniform, name to WGSL type, bound last.
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+}
+
+/// A generated post-processing shader and its reflection.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PostProcessShader {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// `"vs_main"`.
+    #[wasm_bindgen(readonly)]
+    pub vertex_entry_point: String,
+    /// `"fs_main"`.
+    #[wasm_bindgen(readonly)]
+    pub fragment_entry_point: String,
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl PostProcessShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Post-Process Implementation
+// ============================================================================
+//
+// Effects are written against a fixed frame: one triangle covering the
+// viewport, drawn with three vertices and no buffers, hands the fragment
+// stage `uv` (0,0 at the top left) and `position`. Inputs live in group 0:
+// a filtering `input_sampler` at binding 0, each texture after it, then the
+// `params` uniform if there is one. The body is pasted into the fragment
+// function as written and must return the output color.
+
+pub(crate) const VERTEX_STAGE: &str = "\
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+";
+
+/// Names the generated code declares itself.
+const RESERVED: &[&str] = &["input_sampler", "params", "Params", "uv", "position", "in"];
+
+fn check_identifier(name: &str) -> Result<(), Diagnostic> {
+    let tokens = tokenize(name);
+    if tokens.len() == 1 && tokens[0].kind == TokenKind::Ident && tokens[0].text == name {
+        Ok(())
+    } else {
+        Err(Diagnostic::error(format!(
+            "Post-process input '{name}' is not an identifier"
+        )))
+    }
+}
+
+pub(crate) fn post_process_source(
+    body: &str,
+    inputs: &PostProcessInputs,
+) -> Result<String, Diagnostic> {
+    let mut out = String::from(VERTEX_STAGE);
+    let _ = writeln!(out, "\n@group(0) @binding(0) var input_sampler: sampler;");
+    for (binding, name) in (1..).zip(&inputs.textures) {
+        check_identifier(name)?;
+        if RESERVED.contains(&name.as_str()) {
+            return Err(Diagnostic::error(format!(
+                "Post-process input '{name}' is reserved"
+            )));
+        }
+        if inputs.textures[..binding - 1].contains(name) {
+            return Err(Diagnostic::error(format!(
+                "Post-process input '{name}' is declared twice"
+            )));
+        }
+        let _ = writeln!(
+            out,
+            "@group(0) @binding({binding}) var {name}: texture_2d<f32>;"
+        );
+    }
+    if !inputs.params.is_empty() {
+        let _ = writeln!(out, "\nstruct Params {{");
+        for (name, ty) in &inputs.params {
+            check_identifier(name)?;
+            let _ = writeln!(out, "    {name}: {ty},");
+        }
+        let _ = writeln!(out, "}}");
+        let binding = inputs.textures.len() + 1;
+        let _ = writeln!(
+            out,
+            "@group(0) @binding({binding}) var<uniform> params: Params;"
+        );
+    }
+    let _ = writeln!(
+        out,
+        "\n@fragment\nfn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {{\n    let uv = in.uv;\n    let position = in.position;"
+    );
+    for line in body.trim_matches('\n').lines() {
+        let _ = writeln!(out, "    {line}");
+    }
+    let _ = writeln!(out, "}}");
+    Ok(out)
+}
+
+pub(crate) fn post_process(
+    body: &str,
+    inputs: &PostProcessInputs,
+) -> Result<PostProcessShader, Diagnostic> {
+    let wgsl = post_process_source(body, inputs)?;
+    let (module, info) = crate::parse_and_validate(&wgsl)
+        .map_err(|e| Diagnostic::error(format!("Invalid post-process body: {}", e.message)))?;
+    Ok(PostProcessShader {
+        reflection: crate::reflect_module(&module, &info),
+        wgsl,
+        vertex_entry_point: "vs_main".to_string(),
+        fragment_entry_point: "fs_main".to_string(),
+    })
+}
+
+/// Wrap a fragment body into a complete, validated post-processing shader:
+/// a fullscreen-triangle vertex stage (draw 3 vertices, no buffers) and a
+/// fragment stage running `fragmentBody` with `uv`, `position`,
+/// `input_sampler`, each of `inputs.textures` and `params` in scope. The body
+/// must return the output color. `inputs` is `{ textures?: string[],
+/// params?: { [name]: wgslType } }`.
+#[wasm_bindgen(js_name = generatePostProcess)]
+pub fn generate_post_process(
+    fragment_body: &str,
+    inputs: JsValue,
+) -> Result<PostProcessShader, JsValue> {
+    let inputs: Option<PostProcessInputs> = serde_wasm_bindgen::from_value(inputs)
+        .map_err(|e| JsValue::from_str(&format!("Invalid post-process inputs: {e}")))?;
+    post_process(fragment_body, &inputs.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(textures: &[&str], params: &[(&str, &str)]) -> PostProcessInputs {
+        PostProcessInputs {
+            textures: textures.iter().map(|t| t.to_string()).collect(),
+            params: params
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn bodies_become_fullscreen_shaders() {
+        let body = r#"
+let scene_color = textureSample(scene, input_sampler, uv);
+let glow = textureSample(bloom, input_sampler, uv).rgb * params.strength;
+return vec4<f32>(scene_color.rgb + glow * params.tint, scene_color.a);
+"#;
+        let inputs = inputs(
+            &["scene", "bloom"],
+            &[("strength", "f32"), ("tint", "vec3<f32>")],
+        );
+        let shader = post_process(body, &inputs).unwrap();
+        assert!(shader.wgsl.contains("    let glow = textureSample(bloom"));
+        let fs = shader
+            .reflection
+            .entry_points
+            .iter()
+            .find(|ep| ep.name == shader.fragment_entry_point)
+            .unwrap();
+        let bindings: Vec<_> = fs
+            .bindings
+            .iter()
+            .map(|b| (b.name.as_str(), b.binding))
+            .collect();
+        assert_eq!(
+            bindings,
+            [
+                ("input_sampler", 0),
+                ("scene", 1),
+                ("bloom", 2),
+                ("params", 3)
+            ]
+        );
+        assert_eq!(shader.reflection.entry_points.len(), 2);
+    }
+
+    #[test]
+    fn bad_bodies_and_inputs_are_errors() {
+        let err = post_process("return 1.0;", &inputs(&[], &[]))
+            .err()
+            .unwrap();
+        assert!(err.message.starts_with("Invalid post-process body"));
+        let err = post_process("return vec4<f32>();", &inputs(&["uv"], &[]))
+            .err()
+            .unwrap();
+        assert!(err.message.contains("'uv'"));
+        assert!(post_process("return vec4<f32>();", &inputs(&["a b"], &[])).is_err());
+        assert!(post_process("return vec4<f32>();", &inputs(&["a", "a"], &[])).is_err());
+        assert!(post_process("return vec4<f32>(uv, 0.0, 1.0);", &inputs(&[], &[])).is_ok());
+    }
+}
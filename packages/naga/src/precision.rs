@@ -0,0 +1,350 @@
+use naga::{BinaryOperator, Expression, Literal, ScalarKind};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Precision Audit Types
+// ============================================================================
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PrecisionReport {
+    /// Sorted by source position.
+    #[wasm_bindgen(readonly)]
+    pub findings: Vec<PrecisionFinding>,
+}
+
+#[wasm_bindgen]
+impl PrecisionReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PrecisionFinding {
+    /// `"mixed-precision"`, `"literal-narrowing"` or `"division-by-zero"`.
+    #[wasm_bindgen(readonly)]
+    pub rule: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// Function or entry point containing the expression; `None` at module scope.
+    #[wasm_bindgen(readonly)]
+    pub function: Option<String>,
+    /// The function's `id` in reflection.
+    #[wasm_bindgen(readonly)]
+    pub function_id: Option<String>,
+    /// 1-based, if naga kept a span for the expression.
+    #[wasm_bindgen(readonly)]
+    pub line: Option<u32>,
+    #[wasm_bindgen(readonly)]
+    pub column: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl PrecisionFinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Precision Audit Implementation
+// ============================================================================
+//
+// Three checks over the validated IR:
+// - conversions between f16 and f32, where mixed-precision math happens;
+// - literals without a suffix whose value f32 cannot hold exactly (beyond
+//   2^24, or out of range). naga has already concretized them, so the source
+//   text behind the literal's span is compared with the stored value;
+// - integer `/` and `%` whose divisor is not a nonzero constant. WGSL defines
+//   the result instead of trapping, so a zero divisor silently yields the
+//   dividend (or zero for `%`).
+
+/// Largest magnitude below which every integer is exact in an f32.
+const F32_EXACT_INTEGERS: f64 = 16_777_216.0;
+
+struct Audit<'a> {
+    module: &'a naga::Module,
+    source: &'a str,
+    findings: Vec<PrecisionFinding>,
+}
+
+impl Audit<'_> {
+    fn push(&mut self, rule: &str, message: String, function: Option<&str>, span: naga::Span) {
+        let location = span.is_defined().then(|| span.location(self.source));
+        self.findings.push(PrecisionFinding {
+            rule: rule.to_string(),
+            message,
+            function: function.map(str::to_string),
+            function_id: function.map(|name| crate::function_id(self.module, name)),
+            line: location.map(|l| l.line_number),
+            column: location.map(|l| l.line_position),
+        });
+    }
+
+    fn literal(&mut self, literal: &Literal, function: Option<&str>, span: naga::Span) {
+        let Literal::F32(value) = *literal else {
+            return;
+        };
+        let Some(text) = span.to_range().and_then(|r| self.source.get(r)) else {
+            return;
+        };
+        // Suffixed, hex and folded literals are not implicit narrowings.
+        let Ok(written) = text.trim().parse::<f64>() else {
+            return;
+        };
+        let stored = value as f64;
+        let narrowed = if !value.is_finite() {
+            Some("overflows f32")
+        } else if written != 0.0 && stored == 0.0 {
+            Some("underflows to zero in f32")
+        } else if written.abs() > F32_EXACT_INTEGERS && written.fract() == 0.0 && stored != written
+        {
+            Some("is not exactly representable in f32")
+        } else {
+            None
+        };
+        if let Some(problem) = narrowed {
+            self.push(
+                "literal-narrowing",
+                format!("Literal {text} {problem}; it becomes {value:e}"),
+                function,
+                span,
+            );
+        }
+    }
+
+    fn function(
+        &mut self,
+        function: &naga::Function,
+        info: &naga::valid::FunctionInfo,
+        name: Option<&str>,
+    ) {
+        let types = &self.module.types;
+        for (handle, expr) in function.expressions.iter() {
+            let span = function.expressions.get_span(handle);
+            match *expr {
+                Expression::Literal(ref literal) => self.literal(literal, name, span),
+                Expression::As {
+                    expr: operand,
+                    kind: ScalarKind::Float,
+                    convert: Some(width),
+                } => {
+                    let from = info[operand].ty.inner_with(types).scalar();
+                    if let Some(from) = from
+                        && from.kind == ScalarKind::Float
+                        && from.width != width
+                    {
+                        let what = if width < from.width {
+                            "narrowed"
+                        } else {
+                            "widened"
+                        };
+                        self.push(
+                            "mixed-precision",
+                            format!("f{} value {} to f{}", from.width * 8, what, width * 8),
+                            name,
+                            span,
+                        );
+                    }
+                }
+                Expression::Binary {
+                    op: op @ (BinaryOperator::Divide | BinaryOperator::Modulo),
+                    right,
+                    ..
+                } => {
+                    let integer = info[handle]
+                        .ty
+                        .inner_with(types)
+                        .scalar_kind()
+                        .is_some_and(|k| matches!(k, ScalarKind::Sint | ScalarKind::Uint));
+                    if !integer {
+                        continue;
+                    }
+                    let symbol = if op == BinaryOperator::Divide {
+                        '/'
+                    } else {
+                        '%'
+                    };
+                    match self.divisor(&function.expressions, right) {
+                        Divisor::Nonzero => {}
+                        Divisor::Zero => self.push(
+                            "division-by-zero",
+                            format!("Integer `{symbol}` by a constant zero"),
+                            name,
+                            span,
+                        ),
+                        Divisor::Unknown => self.push(
+                            "division-by-zero",
+                            format!("Integer `{symbol}` by a value that may be zero"),
+                            name,
+                            span,
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn divisor(
+        &self,
+        arena: &naga::Arena<Expression>,
+        handle: naga::Handle<Expression>,
+    ) -> Divisor {
+        match arena[handle] {
+            Expression::Literal(literal) => Divisor::of(literal),
+            Expression::Constant(c) => {
+                let init = self.module.constants[c].init;
+                self.divisor(&self.module.global_expressions, init)
+            }
+            Expression::ZeroValue(_) => Divisor::Zero,
+            Expression::Splat { value, .. } => self.divisor(arena, value),
+            Expression::Compose { ref components, .. } => components
+                .iter()
+                .map(|c| self.divisor(arena, *c))
+                .fold(Divisor::Nonzero, Divisor::combine),
+            _ => Divisor::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Divisor {
+    Nonzero,
+    Zero,
+    Unknown,
+}
+
+impl Divisor {
+    fn of(literal: Literal) -> Self {
+        let zero = match literal {
+            Literal::I32(v) => v == 0,
+            Literal::U32(v) => v == 0,
+            Literal::I64(v) => v == 0,
+            Literal::U64(v) => v == 0,
+            Literal::AbstractInt(v) => v == 0,
+            _ => return Divisor::Unknown,
+        };
+        if zero {
+            Divisor::Zero
+        } else {
+            Divisor::Nonzero
+        }
+    }
+
+    fn combine(self, other: Self) -> Self {
+        match (self, other) {
+            (Divisor::Zero, _) | (_, Divisor::Zero) => Divisor::Zero,
+            (Divisor::Unknown, _) | (_, Divisor::Unknown) => Divisor::Unknown,
+            _ => Divisor::Nonzero,
+        }
+    }
+}
+
+/// Run every precision check over `module`, parsed from `source`.
+pub(crate) fn audit_precision(
+    source: &str,
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> PrecisionReport {
+    let mut audit = Audit {
+        module,
+        source,
+        findings: Vec::new(),
+    };
+    for (handle, expr) in module.global_expressions.iter() {
+        if let Expression::Literal(literal) = expr {
+            audit.literal(literal, None, module.global_expressions.get_span(handle));
+        }
+    }
+    for (handle, function) in module.functions.iter() {
+        audit.function(function, &info[handle], function.name.as_deref());
+    }
+    for (index, ep) in module.entry_points.iter().enumerate() {
+        audit.function(&ep.function, info.get_entry_point(index), Some(&ep.name));
+    }
+
+    let mut findings = audit.findings;
+    findings.sort_by_key(|f| (f.line.is_none(), f.line, f.column));
+    PrecisionReport { findings }
+}
+
+/// Precision and robustness lints for simulation-style shaders: f16/f32
+/// conversions, unsuffixed literals that lose value as f32, and integer
+/// division or modulo by values that may be zero. Throws if `wgsl` does not
+/// validate.
+#[wasm_bindgen(js_name = auditPrecision)]
+pub fn audit_precision_wgsl(wgsl: &str) -> Result<PrecisionReport, JsValue> {
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    Ok(audit_precision(wgsl, &module, &info))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audit(source: &str) -> Vec<PrecisionFinding> {
+        let (module, info) = crate::parse_and_validate(source).unwrap();
+        audit_precision(source, &module, &info).findings
+    }
+
+    fn rules(findings: &[PrecisionFinding]) -> Vec<&str> {
+        findings.iter().map(|f| f.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn flags_mixed_precision_conversions() {
+        let findings =
+            audit("enable f16;\nfn mix(a: f32, b: f16) -> f16 {\n    return f16(a) * b;\n}");
+        assert_eq!(rules(&findings), vec!["mixed-precision"]);
+        assert_eq!(findings[0].message, "f32 value narrowed to f16");
+        assert_eq!(findings[0].function.as_deref(), Some("mix"));
+        assert_eq!(findings[0].line, Some(3));
+    }
+
+    #[test]
+    fn flags_only_lossy_literals() {
+        let findings = audit(
+            "const BIG: f32 = 16777217.0;\nconst OK: f32 = 16777216.0;\nconst SMALL: f32 = 0.1;\nconst SUFFIXED = 16777217f;\nfn f() -> f32 { return BIG + OK + SMALL + SUFFIXED; }",
+        );
+        let lines: Vec<_> = findings
+            .iter()
+            .filter(|f| f.rule == "literal-narrowing")
+            .map(|f| f.line)
+            .collect();
+        assert_eq!(lines, vec![Some(1)]);
+        assert!(findings[0].message.contains("16777217.0"));
+    }
+
+    #[test]
+    fn flags_possibly_zero_integer_divisors() {
+        let findings = audit(
+            "const TWO = 2u;\nfn f(a: u32, b: u32, v: vec2<i32>) -> u32 {\n    let x = a / TWO + a % 3u;\n    let y = v / vec2<i32>(1, 2);\n    let z = v % vec2<i32>(b32(b), 1);\n    return a / b + u32(x > 1u) + u32(y.x + z.x) + u32(f32(a) / 0.0);\n}\nfn b32(b: u32) -> i32 { return i32(b); }",
+        );
+        let divisions: Vec<_> = findings
+            .iter()
+            .filter(|f| f.rule == "division-by-zero")
+            .map(|f| (f.line, f.message.as_str()))
+            .collect();
+        assert_eq!(
+            divisions,
+            vec![
+                (Some(5), "Integer `%` by a value that may be zero"),
+                (Some(6), "Integer `/` by a value that may be zero"),
+            ]
+        );
+    }
+}
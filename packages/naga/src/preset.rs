@@ -0,0 +1,439 @@
+use naga::proc::{BoundsCheckPolicies, BoundsCheckPolicy};
+use naga::valid::{Capabilities, ValidationFlags};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::entry_points::WorkgroupLimits;
+
+// ============================================================================
+// Device Presets
+// ============================================================================
+//
+// A preset bundles what a device family supports and how we compile for it:
+// validator capabilities, bounds-check policies, workgroup limits and hints,
+// and backend language versions. Without a preset, compiles validate with
+// every capability and use naga's backend defaults, as they always have.
+
+pub(crate) struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub capabilities: Capabilities,
+    pub bounds_checks: BoundsCheckPolicies,
+    pub workgroup_limits: WorkgroupLimits,
+    /// Workgroup size to start tuning from.
+    pub preferred_workgroup_size: [u32; 3],
+    pub subgroup_size: Option<u32>,
+    pub spirv_version: (u8, u8),
+    pub msl_version: (u8, u8),
+}
+
+const fn bounds(
+    index: BoundsCheckPolicy,
+    buffer: BoundsCheckPolicy,
+    image_load: BoundsCheckPolicy,
+) -> BoundsCheckPolicies {
+    BoundsCheckPolicies {
+        index,
+        buffer,
+        image_load,
+        binding_array: BoundsCheckPolicy::Unchecked,
+    }
+}
+
+pub(crate) const PRESETS: &[Preset] = &[
+    Preset {
+        name: "webgpu-default",
+        description: "Core WebGPU without optional features, at default limits",
+        capabilities: WEBGPU_CORE,
+        bounds_checks: bounds(
+            BoundsCheckPolicy::Restrict,
+            BoundsCheckPolicy::Restrict,
+            BoundsCheckPolicy::Restrict,
+        ),
+        workgroup_limits: WorkgroupLimits::WEBGPU_DEFAULT,
+        preferred_workgroup_size: [64, 1, 1],
+        subgroup_size: None,
+        spirv_version: (1, 0),
+        msl_version: (2, 0),
+    },
+    Preset {
+        name: "apple-m1",
+        description: "Apple M1-class GPUs through Metal 2.4",
+        capabilities: Capabilities::MULTISAMPLED_SHADING
+            .union(Capabilities::CUBE_ARRAY_TEXTURES)
+            .union(Capabilities::PUSH_CONSTANT)
+            .union(Capabilities::DUAL_SOURCE_BLENDING)
+            .union(Capabilities::EARLY_DEPTH_TEST)
+            .union(Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS)
+            .union(Capabilities::SHADER_FLOAT16)
+            .union(Capabilities::SHADER_INT64)
+            .union(Capabilities::SUBGROUP)
+            .union(Capabilities::SUBGROUP_BARRIER),
+        // Metal has no robust buffer access.
+        bounds_checks: bounds(
+            BoundsCheckPolicy::Restrict,
+            BoundsCheckPolicy::Restrict,
+            BoundsCheckPolicy::ReadZeroSkipWrite,
+        ),
+        workgroup_limits: WorkgroupLimits {
+            max_size: [1024, 1024, 1024],
+            max_invocations: 1024,
+        },
+        preferred_workgroup_size: [32, 1, 1],
+        subgroup_size: Some(32),
+        spirv_version: (1, 3),
+        msl_version: (2, 4),
+    },
+    Preset {
+        name: "adreno-6xx",
+        description: "Qualcomm Adreno 6xx through Vulkan 1.1",
+        capabilities: Capabilities::MULTISAMPLED_SHADING
+            .union(Capabilities::CUBE_ARRAY_TEXTURES)
+            .union(Capabilities::PUSH_CONSTANT)
+            .union(Capabilities::MULTIVIEW)
+            .union(Capabilities::SHADER_FLOAT16)
+            .union(Capabilities::SUBGROUP)
+            .union(Capabilities::SUBGROUP_BARRIER),
+        // Buffers are covered by robustBufferAccess.
+        bounds_checks: bounds(
+            BoundsCheckPolicy::Restrict,
+            BoundsCheckPolicy::Unchecked,
+            BoundsCheckPolicy::ReadZeroSkipWrite,
+        ),
+        workgroup_limits: WorkgroupLimits {
+            max_size: [1024, 1024, 64],
+            max_invocations: 1024,
+        },
+        preferred_workgroup_size: [64, 1, 1],
+        subgroup_size: Some(64),
+        spirv_version: (1, 3),
+        msl_version: (2, 0),
+    },
+];
+
+/// Look up a preset by name. `None` and `""` select no preset.
+pub(crate) fn resolve(name: Option<&str>) -> Result<Option<&'static Preset>, Diagnostic> {
+    match name.filter(|name| !name.is_empty()) {
+        None => Ok(None),
+        Some(name) => PRESETS
+            .iter()
+            .find(|p| p.name == name)
+            .map(Some)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown preset '{}'", name))),
+    }
+}
+
+// ============================================================================
+// Capability Profiles
+// ============================================================================
+//
+// A profile is only the capability half of a preset, for validation that
+// should match where a shader will be deployed without choosing a device:
+// the core profile rejects `f64`, ray queries or push constants here, as
+// `createShaderModule` would, instead of letting them through. Without a
+// profile, validation keeps allowing every capability.
+
+pub(crate) struct Profile {
+    pub name: &'static str,
+    pub capabilities: Capabilities,
+}
+
+const WEBGPU_CORE: Capabilities =
+    Capabilities::MULTISAMPLED_SHADING.union(Capabilities::CUBE_ARRAY_TEXTURES);
+
+pub(crate) const PROFILES: &[Profile] = &[
+    // Core WebGPU without optional features.
+    Profile {
+        name: "webgpu-core",
+        capabilities: WEBGPU_CORE,
+    },
+    // Core WebGPU with the `shader-f16` feature.
+    Profile {
+        name: "webgpu+f16",
+        capabilities: WEBGPU_CORE.union(Capabilities::SHADER_FLOAT16),
+    },
+    // Everything naga validates, as native Vulkan with every extension.
+    Profile {
+        name: "vulkan-all",
+        capabilities: Capabilities::all(),
+    },
+];
+
+/// The capabilities of a profile by name. `None` and `""` allow every
+/// capability.
+pub(crate) fn resolve_profile(name: Option<&str>) -> Result<Capabilities, Diagnostic> {
+    match name.filter(|name| !name.is_empty()) {
+        None => Ok(Capabilities::all()),
+        Some(name) => PROFILES
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.capabilities)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown capability profile '{}'", name))),
+    }
+}
+
+/// Names of the capability profiles accepted by validation calls.
+#[wasm_bindgen(js_name = listCapabilityProfiles)]
+pub fn list_capability_profiles() -> Vec<String> {
+    PROFILES.iter().map(|p| p.name.to_string()).collect()
+}
+
+// ============================================================================
+// Validation Checks
+// ============================================================================
+//
+// Which of naga's validation passes run, for callers such as a live editor
+// that revalidate on every keystroke and would rather skip the slow ones:
+// `"fast"` leaves out uniformity analysis and constant evaluation checks,
+// the two that only catch problems rare while typing. A module validated
+// that way may still be rejected by `"full"`, which every compile uses.
+
+/// `checks` names, camel-cased from naga's.
+const VALIDATION_CHECKS: &[(&str, ValidationFlags)] = &[
+    ("expressions", ValidationFlags::EXPRESSIONS),
+    ("blocks", ValidationFlags::BLOCKS),
+    (
+        "controlFlowUniformity",
+        ValidationFlags::CONTROL_FLOW_UNIFORMITY,
+    ),
+    ("structLayouts", ValidationFlags::STRUCT_LAYOUTS),
+    ("constants", ValidationFlags::CONSTANTS),
+    ("bindings", ValidationFlags::BINDINGS),
+];
+
+/// A named set of checks, or the checks to run by name.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub(crate) enum ValidationChecks {
+    Named(String),
+    Each(Vec<String>),
+}
+
+/// The validation flags of `checks`. `None` runs every check.
+pub(crate) fn resolve_checks(
+    checks: Option<&ValidationChecks>,
+) -> Result<ValidationFlags, Diagnostic> {
+    match checks {
+        None => Ok(ValidationFlags::all()),
+        Some(ValidationChecks::Named(name)) => match name.as_str() {
+            "full" => Ok(ValidationFlags::all()),
+            "fast" => Ok(ValidationFlags::all()
+                - ValidationFlags::CONTROL_FLOW_UNIFORMITY
+                - ValidationFlags::CONSTANTS),
+            _ => Err(Diagnostic::error(format!(
+                "Unknown validation checks '{}'; expected fast, full or a list of checks",
+                name
+            ))),
+        },
+        Some(ValidationChecks::Each(names)) => {
+            let mut flags = ValidationFlags::empty();
+            for name in names {
+                let (_, flag) = VALIDATION_CHECKS
+                    .iter()
+                    .find(|(known, _)| known == name)
+                    .ok_or_else(|| {
+                        let known: Vec<_> = VALIDATION_CHECKS.iter().map(|(n, _)| *n).collect();
+                        Diagnostic::error(format!(
+                            "Unknown validation check '{}'; expected one of {}",
+                            name,
+                            known.join(", ")
+                        ))
+                    })?;
+                flags |= *flag;
+            }
+            Ok(flags)
+        }
+    }
+}
+
+// ============================================================================
+// Preset Inspection
+// ============================================================================
+
+/// What a preset selects, as shown by `getPreset`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PresetInfo {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub description: String,
+    /// naga validator capability names, e.g. `SHADER_FLOAT16`.
+    #[wasm_bindgen(readonly)]
+    pub capabilities: Vec<String>,
+    #[wasm_bindgen(readonly)]
+    pub bounds_checks: BoundsCheckInfo,
+    #[wasm_bindgen(readonly)]
+    pub max_workgroup_size: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub max_workgroup_invocations: u32,
+    #[wasm_bindgen(readonly)]
+    pub preferred_workgroup_size: Vec<u32>,
+    #[wasm_bindgen(readonly)]
+    pub subgroup_size: Option<u32>,
+    /// `"major.minor"`.
+    #[wasm_bindgen(readonly)]
+    pub spirv_version: String,
+    /// `"major.minor"`.
+    #[wasm_bindgen(readonly)]
+    pub msl_version: String,
+}
+
+#[wasm_bindgen]
+impl PresetInfo {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Bounds-check policy per access kind: `"restrict"`,
+/// `"read_zero_skip_write"` or `"unchecked"`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BoundsCheckInfo {
+    #[wasm_bindgen(readonly)]
+    pub index: String,
+    #[wasm_bindgen(readonly)]
+    pub buffer: String,
+    #[wasm_bindgen(readonly)]
+    pub image_load: String,
+}
+
+#[wasm_bindgen]
+impl BoundsCheckInfo {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+fn policy_name(policy: BoundsCheckPolicy) -> String {
+    match policy {
+        BoundsCheckPolicy::Restrict => "restrict",
+        BoundsCheckPolicy::ReadZeroSkipWrite => "read_zero_skip_write",
+        BoundsCheckPolicy::Unchecked => "unchecked",
+    }
+    .to_string()
+}
+
+impl Preset {
+    fn info(&self) -> PresetInfo {
+        let version = |(major, minor): (u8, u8)| format!("{major}.{minor}");
+        PresetInfo {
+            name: self.name.to_string(),
+            description: self.description.to_string(),
+            capabilities: self
+                .capabilities
+                .iter_names()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            bounds_checks: BoundsCheckInfo {
+                index: policy_name(self.bounds_checks.index),
+                buffer: policy_name(self.bounds_checks.buffer),
+                image_load: policy_name(self.bounds_checks.image_load),
+            },
+            max_workgroup_size: self.workgroup_limits.max_size.to_vec(),
+            max_workgroup_invocations: self.workgroup_limits.max_invocations,
+            preferred_workgroup_size: self.preferred_workgroup_size.to_vec(),
+            subgroup_size: self.subgroup_size,
+            spirv_version: version(self.spirv_version),
+            msl_version: version(self.msl_version),
+        }
+    }
+}
+
+/// Names of the built-in device presets accepted by compile calls.
+#[wasm_bindgen(js_name = listPresets)]
+pub fn list_presets() -> Vec<String> {
+    PRESETS.iter().map(|p| p.name.to_string()).collect()
+}
+
+/// Everything a preset selects. Throws for unknown names.
+#[wasm_bindgen(js_name = getPreset)]
+pub fn get_preset(name: &str) -> Result<PresetInfo, JsValue> {
+    match resolve(Some(name)).map_err(throw)? {
+        Some(preset) => Ok(preset.info()),
+        None => Err(throw(Diagnostic::error("Preset name is empty"))),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF: &str =
+        "enable f16;\n@compute @workgroup_size(1) fn main() { _ = vec2<f16>(1.0h); }";
+
+    #[test]
+    fn presets_gate_capabilities() {
+        let module = crate::parse_wgsl(HALF).unwrap();
+        for (name, valid) in [
+            ("webgpu-default", false),
+            ("apple-m1", true),
+            ("adreno-6xx", true),
+        ] {
+            let preset = resolve(Some(name)).unwrap().unwrap();
+            assert_eq!(
+                crate::validate_module_with(&module, preset.capabilities).is_ok(),
+                valid,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn presets_select_backend_options() {
+        let preset = resolve(Some("apple-m1")).unwrap();
+        let msl = crate::compile_msl_with("@fragment fn fs() {}", None, preset).unwrap();
+        assert!(msl.starts_with("// language: metal2.4"));
+
+        let info = preset.unwrap().info();
+        assert!(info.capabilities.contains(&"SHADER_FLOAT16".to_string()));
+        assert_eq!(info.bounds_checks.image_load, "read_zero_skip_write");
+        assert_eq!(info.max_workgroup_size, vec![1024, 1024, 1024]);
+    }
+
+    #[test]
+    fn profiles_match_deployment_targets() {
+        let double = "@compute @workgroup_size(1) fn main() { let x: f64 = 1.0lf; }";
+        for (source, profile, valid) in [
+            (HALF, "webgpu-core", false),
+            (HALF, "webgpu+f16", true),
+            (double, "webgpu+f16", false),
+            (double, "vulkan-all", true),
+        ] {
+            let result = crate::parse_and_validate_as(source, Some(profile));
+            assert_eq!(result.is_ok(), valid, "{profile}");
+        }
+        // Without a profile, everything is allowed, as before.
+        assert!(crate::is_wgsl_valid(double, None));
+        let error = crate::parse_and_validate_as(double, Some("metal"))
+            .err()
+            .unwrap();
+        assert_eq!(error.message, "Unknown capability profile 'metal'");
+        assert_eq!(
+            list_capability_profiles(),
+            vec!["webgpu-core", "webgpu+f16", "vulkan-all"]
+        );
+    }
+
+    #[test]
+    fn names_resolve() {
+        assert_eq!(
+            list_presets(),
+            vec!["webgpu-default", "apple-m1", "adreno-6xx"]
+        );
+        assert!(resolve(None).unwrap().is_none());
+        assert!(resolve(Some("")).unwrap().is_none());
+        assert!(resolve(Some("voodoo-3")).is_err());
+    }
+}
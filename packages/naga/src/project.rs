@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::{
+    BatchResult, JobOptions, JobResult, Target, emit_artifact, failed_job, js_progress,
+};
+use crate::diagnostics::throw;
+use crate::directory::{build_files, parse_file_for};
+use crate::include::{expand_includes, scan_includes};
+use crate::preset;
+use crate::rename::{RenameResult, rename_across};
+use crate::resources;
+use crate::symbols::{SymbolIndex, SymbolMatch};
+use crate::usage::{BindingUsageReport, binding_usage};
+
+// ============================================================================
+// Project
+// ============================================================================
+
+/// A set of registered sources that can `#include` each other.
+/// Sources stay registered across compiles, so a watch loop only has to
+/// `setSource` the file that changed and rebuild its `dependents`.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Project {
+    files: BTreeMap<String, String>,
+    symbols: SymbolIndex,
+}
+
+#[wasm_bindgen]
+impl Project {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Project {
+        Project::default()
+    }
+
+    /// Register or replace the source at `path`.
+    #[wasm_bindgen(js_name = setSource)]
+    pub fn set_source(&mut self, path: String, source: String) {
+        self.symbols.set(&path, &source);
+        self.files.insert(path, source);
+    }
+
+    /// Unregister `path`. Returns false if it was not registered.
+    #[wasm_bindgen(js_name = removeSource)]
+    pub fn remove_source(&mut self, path: &str) -> bool {
+        self.symbols.remove(path);
+        self.files.remove(path).is_some()
+    }
+
+    /// All registered paths, sorted.
+    pub fn paths(&self) -> Vec<String> {
+        self.files.keys().cloned().collect()
+    }
+
+    /// Compile one registered file. The result's `dependencies` lists every
+    /// other source it read through `#include`. `preset` names a device
+    /// preset (see `listPresets`).
+    pub fn compile(
+        &self,
+        path: &str,
+        target: &str,
+        entry_point: Option<String>,
+        preset: Option<String>,
+    ) -> Result<JobResult, JsValue> {
+        let target = Target::parse(target)
+            .ok_or_else(|| throw(Diagnostic::error(format!("Unknown target '{}'", target))))?;
+        let mut options = JobOptions::new(target, entry_point.as_deref());
+        options.preset = preset;
+        Ok(self.compile_file(path, &options))
+    }
+
+    /// Build every registered `.wgsl` file with entry points, like `buildDirectory`.
+    pub fn build(
+        &self,
+        targets: Vec<String>,
+        split_entry_points: bool,
+        on_progress: Option<js_sys::Function>,
+    ) -> Result<BatchResult, JsValue> {
+        let targets = targets
+            .iter()
+            .map(|t| {
+                Target::parse(t)
+                    .ok_or_else(|| throw(Diagnostic::error(format!("Unknown target '{}'", t))))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        build_files(
+            &self.files,
+            &targets,
+            split_entry_points,
+            js_progress(&on_progress),
+        )
+    }
+
+    /// Transitive dependencies of `path`, sorted.
+    pub fn dependencies(&self, path: &str) -> Result<Vec<String>, JsValue> {
+        expand_includes(path, &self.files)
+            .map(|e| e.dependencies)
+            .map_err(throw)
+    }
+
+    /// Every registered file whose output depends on `path`, i.e. what a watch
+    /// loop must rebuild when `path` changes. Includes `path` itself.
+    pub fn dependents(&self, path: &str) -> Vec<String> {
+        self.dependents_of(path)
+    }
+
+    /// Which group/binding slots are used by which files, entry points and
+    /// stages across the project, with slots whose users disagree on the
+    /// resource flagged as conflicts. `resourceNames`, as for `reflectWgsl`,
+    /// sets each use's `resourceName`; entries no file uses are reported in
+    /// `diagnostics`.
+    #[wasm_bindgen(js_name = bindingUsageReport)]
+    pub fn binding_usage_report(
+        &self,
+        resource_names: JsValue,
+    ) -> Result<BindingUsageReport, JsValue> {
+        let names = resources::resource_names(resource_names)?;
+        let mut report = binding_usage(&self.files);
+        if !names.is_empty() {
+            names.annotate_usage(&mut report);
+        }
+        Ok(report)
+    }
+
+    /// Rename the module-scope struct, function, constant or variable
+    /// `symbol` in the one file declaring it and in every file including that
+    /// file. The new sources replace the registered ones; the result lists
+    /// each changed file's edits. Throws, changing nothing, if the symbol is
+    /// missing or declared in several files, `newName` is invalid or already
+    /// used, or the rename would break a file that built before.
+    #[wasm_bindgen(js_name = renameAcrossProject)]
+    pub fn rename_across_project(
+        &mut self,
+        symbol: &str,
+        new_name: &str,
+    ) -> Result<RenameResult, JsValue> {
+        self.rename_files(symbol, new_name).map_err(throw)
+    }
+
+    /// Module-scope functions, entry points, structs, aliases, constants,
+    /// overrides and variables across every registered file whose names
+    /// match `query`, best first, at most `limit` (default 50) of them.
+    /// Exact names rank above prefixes, then prefixes of a name's words
+    /// (`light` finds `PointLight`), substrings and scattered letters.
+    #[wasm_bindgen(js_name = searchSymbols)]
+    pub fn search_symbols(&self, query: &str, limit: Option<u32>) -> Vec<SymbolMatch> {
+        self.symbols.search(query, limit.unwrap_or(50) as usize)
+    }
+}
+
+impl Project {
+    pub(crate) fn compile_file(&self, path: &str, options: &JobOptions) -> JobResult {
+        let parsed = preset::resolve(options.preset.as_deref())
+            .and_then(|preset| parse_file_for(path, &self.files, preset));
+        match parsed {
+            Ok(f) => {
+                let (mut result, _) = emit_artifact(path, &f.source, &f.module, &f.info, options);
+                result.dependencies = f.dependencies;
+                result
+            }
+            Err(diagnostic) => {
+                let mut result = failed_job(path, options, diagnostic);
+                result.dependencies = scan_includes(path, &self.files);
+                result
+            }
+        }
+    }
+
+    pub(crate) fn rename_files(
+        &mut self,
+        symbol: &str,
+        new_name: &str,
+    ) -> Result<RenameResult, Diagnostic> {
+        let result = rename_across(&self.files, |p| self.dependents_of(p), symbol, new_name)?;
+        for file in &result.files {
+            self.symbols.set(&file.path, &file.source);
+            self.files.insert(file.path.clone(), file.source.clone());
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn dependents_of(&self, path: &str) -> Vec<String> {
+        self.files
+            .keys()
+            .filter(|candidate| {
+                candidate.as_str() == path
+                    || scan_includes(candidate, &self.files)
+                        .iter()
+                        .any(|d| d == path)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        let mut project = Project::new();
+        project.set_source(
+            "common/consts.wgsl".to_string(),
+            "const SIZE: u32 = 4;".to_string(),
+        );
+        project.set_source(
+            "common/math.wgsl".to_string(),
+            "#include \"consts.wgsl\"\nfn twice(x: u32) -> u32 { return x * 2u; }".to_string(),
+        );
+        project.set_source(
+            "a.wgsl".to_string(),
+            "#include \"common/math.wgsl\"\n@compute @workgroup_size(SIZE) fn main() { _ = twice(1u); }"
+                .to_string(),
+        );
+        project.set_source(
+            "b.wgsl".to_string(),
+            "@compute @workgroup_size(1) fn main() {}".to_string(),
+        );
+        project
+    }
+
+    #[test]
+    fn compile_reports_transitive_dependencies() {
+        let result = project().compile_file("a.wgsl", &JobOptions::new(Target::Spirv, None));
+        assert!(result.ok);
+        assert_eq!(
+            result.dependencies,
+            vec!["common/consts.wgsl", "common/math.wgsl"]
+        );
+    }
+
+    #[test]
+    fn dependents_cover_transitive_includers() {
+        let project = project();
+        assert_eq!(
+            project.dependents_of("common/consts.wgsl"),
+            vec!["a.wgsl", "common/consts.wgsl", "common/math.wgsl"]
+        );
+        assert_eq!(project.dependents_of("b.wgsl"), vec!["b.wgsl"]);
+    }
+
+    #[test]
+    fn removed_include_fails_dependents() {
+        let mut project = project();
+        assert!(project.remove_source("common/consts.wgsl"));
+        let result = project.compile_file("a.wgsl", &JobOptions::new(Target::Msl, None));
+        assert!(!result.ok);
+        assert_eq!(
+            result.dependencies,
+            vec!["common/consts.wgsl", "common/math.wgsl"]
+        );
+        // Restoring the header must rebuild the files that failed without it.
+        assert_eq!(
+            project.dependents_of("common/consts.wgsl"),
+            vec!["a.wgsl", "common/math.wgsl"]
+        );
+    }
+
+    #[test]
+    fn rename_replaces_registered_sources() {
+        let mut project = project();
+        let before = project.files.clone();
+        assert!(project.rename_files("twice", "main").is_err());
+        assert_eq!(project.files, before);
+
+        let result = project.rename_files("twice", "double").unwrap();
+        let paths: Vec<_> = result.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.wgsl", "common/math.wgsl"]);
+        assert!(project.files["a.wgsl"].contains("_ = double(1u);"));
+        assert!(project.search_symbols("twice", None).is_empty());
+        let matches = project.search_symbols("double", None);
+        assert_eq!(matches[0].path, "common/math.wgsl");
+        assert!(
+            project
+                .compile_file("a.wgsl", &JobOptions::new(Target::Spirv, None))
+                .ok
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::hash::sha256_hex;
+use crate::spv;
+
+/// Prefix of the debug string carrying a provenance record.
+pub(crate) const PROVENANCE_PREFIX: &str = "metis-provenance:";
+
+/// Prefix of the comment fingerprinting textual output.
+pub(crate) const FINGERPRINT_PREFIX: &str = "metis-source:";
+
+/// Major version of the naga dependency. Keep in sync with Cargo.toml.
+pub(crate) const NAGA_VERSION: &str = "27";
+
+// ============================================================================
+// Provenance Types
+// ============================================================================
+
+/// Which toolchain produced an artifact, and from what.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct Provenance {
+    /// `naga-wasm/<version>`
+    #[wasm_bindgen(readonly)]
+    pub compiler: String,
+    #[wasm_bindgen(readonly)]
+    pub naga: String,
+    /// SHA-256 of the WGSL source that was compiled.
+    #[wasm_bindgen(readonly)]
+    pub input_hash: String,
+    /// SHA-256 of the compile options (target, entry point, backend options).
+    #[wasm_bindgen(readonly)]
+    pub options_hash: String,
+}
+
+#[wasm_bindgen]
+impl Provenance {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+impl Provenance {
+    pub(crate) fn new(source: &str, options: &impl Serialize) -> Self {
+        let options = serde_json::to_vec(options).expect("options are always serializable");
+        Provenance {
+            compiler: format!("naga-wasm/{}", env!("CARGO_PKG_VERSION")),
+            naga: NAGA_VERSION.to_string(),
+            input_hash: sha256_hex(source.as_bytes()),
+            options_hash: sha256_hex(&options),
+        }
+    }
+
+    fn encode(&self) -> String {
+        format!(
+            "{}{}",
+            PROVENANCE_PREFIX,
+            serde_json::to_string(self).expect("provenance is always serializable")
+        )
+    }
+}
+
+// ============================================================================
+// SPIR-V Embedding
+// ============================================================================
+
+/// Add the record as a debug instruction. SPIR-V 1.1+ gets an
+/// `OpModuleProcessed`; 1.0 has no such instruction, so an
+/// `OpSourceExtension` carries it instead. Both are non-semantic and
+/// removed by any debug-info stripper.
+pub(crate) fn embed_spirv(words: &mut Vec<u32>, provenance: &Provenance) -> Result<(), Diagnostic> {
+    embed_spirv_string(words, &provenance.encode())
+}
+
+/// Add `text` as a debug string, where `embed_spirv` puts records.
+fn embed_spirv_string(words: &mut Vec<u32>, text: &str) -> Result<(), Diagnostic> {
+    let text = spv::encode_string(text);
+    let (op, at) = if spv::version(words) >= (1, 1) {
+        let after = [spv::PREAMBLE, spv::DEBUG_SOURCE, spv::DEBUG_NAMES].concat();
+        (Op::ModuleProcessed, spv::insertion_point(words, &after)?)
+    } else {
+        let after = [spv::PREAMBLE, spv::DEBUG_SOURCE].concat();
+        (Op::SourceExtension, spv::insertion_point(words, &after)?)
+    };
+    words.splice(at..at, spv::encode(op, &text));
+    Ok(())
+}
+
+/// Find the first provenance record in a module.
+pub(crate) fn read_spirv(words: &[u32]) -> Result<Option<Provenance>, Diagnostic> {
+    for inst in spv::instructions(words)? {
+        if !matches!(inst.op(), Some(Op::ModuleProcessed | Op::SourceExtension)) {
+            continue;
+        }
+        let (text, _) = spv::decode_string(inst.operands());
+        if let Some(json) = text.strip_prefix(PROVENANCE_PREFIX) {
+            let provenance = serde_json::from_str(json)
+                .map_err(|e| Diagnostic::error(format!("Malformed provenance record: {e}")))?;
+            return Ok(Some(provenance));
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the provenance record embedded by an attested compile, or
+/// `undefined` if the binary carries none.
+#[wasm_bindgen(js_name = readProvenance)]
+pub fn read_provenance(spirv_bytes: &[u8]) -> Result<Option<Provenance>, JsValue> {
+    let words = spv::words_from_bytes(spirv_bytes).map_err(throw)?;
+    read_spirv(&words).map_err(throw)
+}
+
+// ============================================================================
+// Source Fingerprints
+// ============================================================================
+
+/// `metis-source: sha256=<hash> entry=<name>`, with `entry=*` when every
+/// entry point was emitted.
+fn fingerprint(source: &str, entry_point: Option<&str>) -> String {
+    let entry = entry_point.filter(|name| !name.is_empty()).unwrap_or("*");
+    format!(
+        "{} sha256={} entry={}",
+        FINGERPRINT_PREFIX,
+        sha256_hex(source.as_bytes()),
+        entry
+    )
+}
+
+/// Prepend `// metis-source: sha256=<hash> entry=<name>` to MSL, HLSL or
+/// GLSL output, so generated-source snippets in vendor crash logs trace back
+/// to the WGSL asset.
+pub(crate) fn embed_fingerprint(text: &mut String, source: &str, entry_point: Option<&str>) {
+    text.insert_str(0, &format!("// {}\n", fingerprint(source, entry_point)));
+}
+
+/// The SPIR-V counterpart of `embed_fingerprint`: the same text as a debug
+/// string, beside any provenance record.
+pub(crate) fn embed_spirv_fingerprint(
+    words: &mut Vec<u32>,
+    source: &str,
+    entry_point: Option<&str>,
+) -> Result<(), Diagnostic> {
+    embed_spirv_string(words, &fingerprint(source, entry_point))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "@compute @workgroup_size(1) fn main() {}";
+
+    #[test]
+    fn embedded_record_reads_back() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let mut words = spv::words_from_bytes(&bytes).unwrap();
+        assert_eq!(read_spirv(&words).unwrap(), None);
+
+        let provenance = Provenance::new(SHADER, &"spirv");
+        embed_spirv(&mut words, &provenance).unwrap();
+        assert_eq!(read_spirv(&words).unwrap(), Some(provenance));
+
+        // Still a well-formed module naga can load.
+        let bytes = spv::bytes_from_words(&words);
+        naga::front::spv::parse_u8_slice(&bytes, &Default::default()).unwrap();
+    }
+
+    #[test]
+    fn record_lands_in_the_debug_section() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let mut words = spv::words_from_bytes(&bytes).unwrap();
+        embed_spirv(&mut words, &Provenance::new(SHADER, &"spirv")).unwrap();
+
+        let ops: Vec<_> = spv::instructions(&words)
+            .unwrap()
+            .iter()
+            .filter_map(|i| i.op())
+            .collect();
+        let record = ops
+            .iter()
+            .position(|op| *op == Op::SourceExtension)
+            .unwrap();
+        let last_preamble = ops
+            .iter()
+            .rposition(|op| spv::PREAMBLE.contains(op))
+            .unwrap();
+        assert!(record > last_preamble);
+        assert!(!ops[..record].contains(&Op::Name));
+    }
+
+    #[test]
+    fn fingerprint_leads_textual_output() {
+        let mut msl = crate::compile_msl_with(SHADER, Some("main"), None).unwrap();
+        embed_fingerprint(&mut msl, SHADER, Some("main"));
+        let first = msl.lines().next().unwrap();
+        assert_eq!(
+            first,
+            format!(
+                "// metis-source: sha256={} entry=main",
+                sha256_hex(SHADER.as_bytes())
+            )
+        );
+        assert!(
+            msl.lines()
+                .nth(1)
+                .unwrap()
+                .starts_with("// language: metal")
+        );
+
+        let mut all = String::new();
+        embed_fingerprint(&mut all, SHADER, None);
+        assert!(all.ends_with(" entry=*\n"));
+
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let mut words = spv::words_from_bytes(&bytes).unwrap();
+        embed_spirv_fingerprint(&mut words, SHADER, None).unwrap();
+        let strings: Vec<_> = spv::instructions(&words)
+            .unwrap()
+            .iter()
+            .filter(|i| i.op() == Some(Op::SourceExtension))
+            .map(|i| spv::decode_string(i.operands()).0)
+            .collect();
+        assert_eq!(strings, [fingerprint(SHADER, None)]);
+    }
+
+    #[test]
+    fn options_change_the_options_hash() {
+        let a = Provenance::new(SHADER, &"spirv");
+        let b = Provenance::new(SHADER, &"msl");
+        assert_eq!(a.input_hash, b.input_hash);
+        assert_ne!(a.options_hash, b.options_hash);
+    }
+}
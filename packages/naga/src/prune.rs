@@ -0,0 +1,334 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use spirv::{Decoration, Op};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::preset::Preset;
+use crate::spv;
+
+// ============================================================================
+// Pruning Types
+// ============================================================================
+
+/// A resource binding left out of a single-entry-point binary.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PrunedBinding {
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+}
+
+#[wasm_bindgen]
+impl PrunedBinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct PrunedSpirv {
+    #[wasm_bindgen(readonly)]
+    pub bytes: Vec<u8>,
+    /// Bindings of the module the entry point does not use, sorted.
+    #[wasm_bindgen(readonly)]
+    pub pruned: Vec<PrunedBinding>,
+}
+
+#[wasm_bindgen]
+impl PrunedSpirv {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Binding Pruning
+// ============================================================================
+//
+// With a single entry point naga already replaces unused globals by dummies,
+// but their pointer and block types, `Block` decorations and member names are
+// still emitted. This pass removes any descriptor variable no instruction
+// refers to, then every type declaration left without a user, along with the
+// annotations and debug names of everything it removed.
+
+/// Emit SPIR-V for `entry_point` with unused bindings pruned.
+pub(crate) fn write_spirv_pruned(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    preset: Option<&Preset>,
+) -> Result<(Vec<u8>, Vec<PrunedBinding>), Diagnostic> {
+    let name = entry_point
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Diagnostic::error("Binding pruning requires an entry point"))?;
+    let index = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.name == name)
+        .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", name)))?;
+
+    // What naga leaves out.
+    let ep_info = info.get_entry_point(index);
+    let mut pruned: BTreeSet<PrunedBinding> = module
+        .global_variables
+        .iter()
+        .filter(|(handle, _)| ep_info[*handle].is_empty())
+        .filter_map(|(_, var)| {
+            var.binding.as_ref().map(|b| PrunedBinding {
+                group: b.group,
+                binding: b.binding,
+                name: var.name.clone(),
+            })
+        })
+        .collect();
+
+    // What remains of it, plus anything else unreferenced.
+    let bytes = crate::write_spirv_with(module, info, Some(name), preset)?;
+    let (words, removed) = prune_words(&spv::words_from_bytes(&bytes)?)?;
+    pruned.extend(removed);
+    Ok((spv::bytes_from_words(&words), pruned.into_iter().collect()))
+}
+
+/// Instructions that only describe another id and never keep it alive.
+fn is_annotation(op: Op) -> bool {
+    matches!(
+        op,
+        Op::Name
+            | Op::MemberName
+            | Op::Decorate
+            | Op::MemberDecorate
+            | Op::DecorateId
+            | Op::DecorateString
+            | Op::MemberDecorateString
+    )
+}
+
+/// Remove unreferenced descriptor variables and orphaned types.
+pub(crate) fn prune_words(words: &[u32]) -> Result<(Vec<u32>, Vec<PrunedBinding>), Diagnostic> {
+    let insts = spv::instructions(words)?;
+
+    let mut names = HashMap::new();
+    let mut sets = HashMap::new();
+    let mut bindings = HashMap::new();
+    for inst in &insts {
+        let ops = inst.operands();
+        match inst.op() {
+            Some(Op::Name) => {
+                names.insert(inst.operand(0)?, spv::decode_string(&ops[1..]).0);
+            }
+            Some(Op::Decorate) if ops.get(1) == Some(&(Decoration::DescriptorSet as u32)) => {
+                sets.insert(inst.operand(0)?, inst.operand(2)?);
+            }
+            Some(Op::Decorate) if ops.get(1) == Some(&(Decoration::Binding as u32)) => {
+                bindings.insert(inst.operand(0)?, inst.operand(2)?);
+            }
+            // The passes below index these operands: the target of an
+            // annotation, and an entry point's function and name.
+            Some(op) if is_annotation(op) => {
+                inst.operand(0)?;
+            }
+            Some(Op::EntryPoint) => {
+                inst.operand(2)?;
+            }
+            _ => {}
+        }
+    }
+
+    let mut removed = HashSet::new();
+    loop {
+        // Every word is counted as a potential id use. Literals that happen to
+        // collide with an id only make the pass keep more than it could.
+        let mut uses = HashMap::<u32, u32>::new();
+        for inst in &insts {
+            let Some(op) = inst.op() else { continue };
+            if is_annotation(op) || defined_id(inst).is_some_and(|id| removed.contains(&id)) {
+                continue;
+            }
+            let ops = match op {
+                // The interface list does not keep variables alive.
+                Op::EntryPoint => &inst.operands()[1..2],
+                _ => inst.operands(),
+            };
+            for word in ops {
+                *uses.entry(*word).or_default() += 1;
+            }
+        }
+
+        let before = removed.len();
+        for inst in &insts {
+            let Some(id) = defined_id(inst) else { continue };
+            let candidate = match inst.op() {
+                Some(Op::Variable) => bindings.contains_key(&id),
+                Some(op) => format!("{op:?}").starts_with("Type"),
+                None => false,
+            };
+            // One use is the definition itself.
+            if candidate && !removed.contains(&id) && uses.get(&id).copied().unwrap_or(0) <= 1 {
+                removed.insert(id);
+            }
+        }
+        if removed.len() == before {
+            break;
+        }
+    }
+
+    let mut out = words[..spv::HEADER_WORDS].to_vec();
+    for inst in &insts {
+        let ops = inst.operands();
+        match inst.op() {
+            Some(op) if is_annotation(op) && removed.contains(&ops[0]) => {}
+            Some(Op::EntryPoint) => {
+                let (_, name_words) = spv::decode_string(&ops[2..]);
+                let split = 2 + name_words;
+                let mut kept = ops[..split].to_vec();
+                kept.extend(ops[split..].iter().filter(|id| !removed.contains(id)));
+                out.extend(spv::encode(Op::EntryPoint, &kept));
+            }
+            _ if defined_id(inst).is_some_and(|id| removed.contains(&id)) => {}
+            _ => out.extend_from_slice(inst.words),
+        }
+    }
+
+    let mut pruned: Vec<_> = removed
+        .iter()
+        .filter_map(|id| {
+            Some(PrunedBinding {
+                group: *sets.get(id)?,
+                binding: *bindings.get(id)?,
+                name: names.get(id).cloned(),
+            })
+        })
+        .collect();
+    pruned.sort();
+    Ok((out, pruned))
+}
+
+/// Result id of a variable or type declaration.
+fn defined_id(inst: &spv::Instruction) -> Option<u32> {
+    match inst.op()? {
+        Op::Variable => inst.operands().get(1).copied(),
+        op if format!("{op:?}").starts_with("Type") => inst.operands().first().copied(),
+        _ => None,
+    }
+}
+
+/// WGSL -> SPIR-V for one entry point, leaving out every binding that entry
+/// point does not use along with the types and decorations only they needed.
+/// Some Vulkan validation layers warn about unused descriptor interfaces;
+/// `pruned` lists what was removed.
+#[wasm_bindgen(js_name = wgslToSpirvPruned)]
+pub fn wgsl_to_spirv_pruned(wgsl: &str, entry_point: &str) -> Result<PrunedSpirv, JsValue> {
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    let (bytes, pruned) =
+        write_spirv_pruned(&module, &info, Some(entry_point), None).map_err(throw)?;
+    Ok(PrunedSpirv { bytes, pruned })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec4<f32> }
+        struct Params { scale: f32 }
+
+        @group(0) @binding(0) var<uniform> params: Params;
+        @group(0) @binding(1) var<storage, read_write> out: array<f32>;
+        @group(1) @binding(0) var<uniform> light: Light;
+
+        @compute @workgroup_size(1)
+        fn scale() {
+            out[0] = params.scale;
+        }
+
+        @fragment
+        fn shade() -> @location(0) vec4<f32> {
+            return light.color;
+        }
+    "#;
+
+    fn ops(words: &[u32]) -> Vec<Op> {
+        spv::instructions(words)
+            .unwrap()
+            .iter()
+            .filter_map(|i| i.op())
+            .collect()
+    }
+
+    #[test]
+    fn reports_bindings_unused_by_the_entry_point() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let (_, pruned) = write_spirv_pruned(&module, &info, Some("scale"), None).unwrap();
+        assert_eq!(
+            pruned,
+            vec![PrunedBinding {
+                group: 1,
+                binding: 0,
+                name: Some("light".to_string()),
+            }]
+        );
+
+        let (_, pruned) = write_spirv_pruned(&module, &info, Some("shade"), None).unwrap();
+        let slots: Vec<_> = pruned.iter().map(|p| (p.group, p.binding)).collect();
+        assert_eq!(slots, vec![(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn output_is_smaller_and_still_loads() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let plain = crate::write_spirv(&module, &info, Some("shade")).unwrap();
+        let (bytes, _) = write_spirv_pruned(&module, &info, Some("shade"), None).unwrap();
+        assert!(bytes.len() < plain.len());
+
+        let words = spv::words_from_bytes(&bytes).unwrap();
+        let variables = ops(&words).iter().filter(|op| **op == Op::Variable).count();
+        // The `light` uniform and the fragment output.
+        assert_eq!(variables, 2);
+
+        let loaded = naga::front::spv::parse_u8_slice(&bytes, &Default::default()).unwrap();
+        assert_eq!(loaded.entry_points[0].name, "shade");
+    }
+
+    #[test]
+    fn modules_without_dead_bindings_are_untouched() {
+        let words = spv::words_from_bytes(&crate::compile_spirv(SHADER, None).unwrap()).unwrap();
+        let (pruned_words, pruned) = prune_words(&words).unwrap();
+        assert!(pruned.is_empty());
+        assert_eq!(pruned_words, words);
+    }
+
+    #[test]
+    fn requires_an_entry_point() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        assert!(write_spirv_pruned(&module, &info, None, None).is_err());
+        assert!(write_spirv_pruned(&module, &info, Some("nope"), None).is_err());
+    }
+
+    #[test]
+    fn truncated_instructions_are_errors() {
+        let words = spv::words_from_bytes(&crate::compile_spirv(SHADER, None).unwrap()).unwrap();
+        for op in [Op::Name, Op::Decorate, Op::EntryPoint] {
+            let mut words = words.clone();
+            let at = spv::insertion_point(&words, &[Op::Capability, Op::Extension]).unwrap();
+            words.splice(at..at, spv::encode(op, &[]));
+            let error = prune_words(&words).unwrap_err();
+            assert!(error.message.contains("missing operand"), "{op:?}");
+        }
+    }
+}
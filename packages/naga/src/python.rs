@@ -0,0 +1,185 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+
+use crate::preset;
+use crate::request::{self, CompileRequest, RequestError};
+
+// ============================================================================
+// Python Bindings
+// ============================================================================
+//
+// A native extension module for Python content pipelines, built with
+// maturin (see pyproject.toml): `import naga_wasm`. It covers the calls a
+// pipeline makes, validate, compile and reflect, with options given as the
+// same dicts the JS API takes, passed through JSON. Compiles release the
+// GIL, so a thread pool can run several at once.
+
+create_exception!(
+    naga_wasm,
+    ShaderError,
+    PyException,
+    "A shader failed to validate or compile."
+);
+
+fn raise(error: RequestError) -> PyErr {
+    match error {
+        RequestError::Invalid(diagnostic) => PyValueError::new_err(diagnostic.message),
+        RequestError::Failed(diagnostic) => ShaderError::new_err(diagnostic.message),
+    }
+}
+
+/// `value`, a dict or anything else `json.dumps` takes, as JSON.
+fn json_value(py: Python<'_>, value: Option<&Bound<'_, PyAny>>) -> PyResult<serde_json::Value> {
+    let Some(value) = value.filter(|v| !v.is_none()) else {
+        return Ok(serde_json::Value::Null);
+    };
+    let text: String = py
+        .import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()?;
+    serde_json::from_str(&text).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Parse and validate `wgsl`, against `preset` if given. Raises
+/// `ShaderError` if it is invalid.
+#[pyfunction]
+#[pyo3(signature = (wgsl, preset=None))]
+fn validate(py: Python<'_>, wgsl: &str, preset: Option<&str>) -> PyResult<()> {
+    let preset = preset::resolve(preset).map_err(|d| PyValueError::new_err(d.message))?;
+    py.detach(|| {
+        let module = crate::parse_wgsl(wgsl)?;
+        crate::validate_for(&module, preset)
+    })
+    .map(|_| ())
+    .map_err(|d| ShaderError::new_err(d.message))
+}
+
+/// Compile `wgsl` for `target`, `"spirv"`, `"msl"`, `"hlsl"` or `"glsl"`.
+/// `options` is the target's options dict from the JS API; GLSL needs a
+/// `stage`. Returns `bytes` for SPIR-V and `str` otherwise.
+#[pyfunction]
+#[pyo3(signature = (wgsl, target, entry_point=None, *, preset=None, stage=None, options=None))]
+fn compile<'py>(
+    py: Python<'py>,
+    wgsl: &str,
+    target: String,
+    entry_point: Option<String>,
+    preset: Option<String>,
+    stage: Option<String>,
+    options: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let binary = target == "spirv";
+    let request = CompileRequest {
+        target,
+        entry_point,
+        preset,
+        stage,
+        options: json_value(py, options)?,
+    };
+    let output = py
+        .detach(|| request::compile(wgsl, request))
+        .map_err(raise)?;
+    if binary {
+        return Ok(PyBytes::new(py, &output).into_any());
+    }
+    let text = String::from_utf8(output).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(PyString::new(py, &text).into_any())
+}
+
+/// `reflectWgsl`'s result as a dict.
+#[pyfunction]
+fn reflect<'py>(py: Python<'py>, wgsl: &str) -> PyResult<Bound<'py, PyAny>> {
+    let reflection = py
+        .detach(|| crate::reflect(wgsl))
+        .map_err(|d| ShaderError::new_err(d.message))?;
+    let json = serde_json::to_string(&reflection).expect("reflection is always serializable");
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+#[pymodule]
+fn naga_wasm(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ShaderError", m.py().get_type::<ShaderError>())?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(compile, m)?)?;
+    m.add_function(wrap_pyfunction!(reflect, m)?)?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<u32>;
+        @compute @workgroup_size(1) fn main() { data[0] = 1u; }
+    "#;
+
+    #[test]
+    fn compiles_and_reflects_from_python_values() {
+        Python::initialize();
+        Python::attach(|py| {
+            validate(py, SHADER, None).unwrap();
+            let spirv = compile(py, SHADER, "spirv".into(), None, None, None, None).unwrap();
+            let bytes: Vec<u8> = spirv.extract().unwrap();
+            assert_eq!(bytes[..4], 0x0723_0203u32.to_le_bytes());
+
+            let options = PyDict::new(py);
+            options.set_item("entryPointName", "fill").unwrap();
+            let msl = compile(
+                py,
+                SHADER,
+                "msl".into(),
+                Some("main".into()),
+                None,
+                None,
+                Some(options.as_any()),
+            )
+            .unwrap();
+            assert!(
+                msl.extract::<String>()
+                    .unwrap()
+                    .contains("kernel void fill(")
+            );
+
+            let reflection = reflect(py, SHADER).unwrap();
+            let name = reflection
+                .get_item("entryPoints")
+                .and_then(|eps| eps.get_item(0))
+                .and_then(|ep| ep.get_item("name"))
+                .unwrap();
+            assert_eq!(name.extract::<String>().unwrap(), "main");
+        });
+    }
+
+    #[test]
+    fn failures_raise_by_cause() {
+        Python::initialize();
+        Python::attach(|py| {
+            let error = validate(py, "fn main( {", None).unwrap_err();
+            assert!(error.is_instance_of::<ShaderError>(py));
+            let error = validate(py, SHADER, Some("no-such-preset")).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+            let error = compile(py, SHADER, "dxil".into(), None, None, None, None).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+            let error = compile(
+                py,
+                SHADER,
+                "hlsl".into(),
+                Some("nope".into()),
+                None,
+                None,
+                None,
+            )
+            .unwrap_err();
+            assert!(error.is_instance_of::<ShaderError>(py));
+        });
+    }
+}
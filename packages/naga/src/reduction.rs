@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Reduction Types
+// ============================================================================
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReductionOptions {
+    /// `"sum"`, `"min"` or `"max"`.
+    pub operation: String,
+    /// Element type: `"f32"`, `"i32"` or `"u32"`.
+    #[serde(default = "default_element_type", rename = "type")]
+    pub element_type: String,
+    /// A power of two up to 256, WebGPU's default invocation limit.
+    #[serde(default = "default_workgroup_size")]
+    pub workgroup_size: u32,
+}
+
+fn default_element_type() -> String {
+    "f32".to_string()
+}
+
+fn default_workgroup_size() -> u32 {
+    256
+}
+
+/// A generated reduction kernel and how to dispatch it.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ReductionKernel {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// `"reduce"`.
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    #[wasm_bindgen(readonly)]
+    pub workgroup_size: u32,
+    /// Input elements each workgroup folds into one output element.
+    #[wasm_bindgen(readonly)]
+    pub elements_per_workgroup: u32,
+    /// WGSL for the operation's identity, which the kernel pads with.
+    #[wasm_bindgen(readonly)]
+    pub identity: String,
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl ReductionKernel {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// The passes reducing `count` elements to one, in order.
+    #[wasm_bindgen(js_name = planDispatches)]
+    pub fn plan_dispatches(&self, count: u32) -> Vec<ReductionPass> {
+        plan(count, self.elements_per_workgroup)
+    }
+}
+
+/// One dispatch of a reduction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ReductionPass {
+    /// Elements read: `params.count` for this pass.
+    #[wasm_bindgen(readonly)]
+    pub count: u32,
+    /// Elements written, one per workgroup.
+    #[wasm_bindgen(readonly)]
+    pub outputs: u32,
+    #[wasm_bindgen(readonly)]
+    pub workgroups_x: u32,
+    #[wasm_bindgen(readonly)]
+    pub workgroups_y: u32,
+}
+
+#[wasm_bindgen]
+impl ReductionPass {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Reduction Implementation
+// ============================================================================
+//
+// Each workgroup folds two elements per invocation, padding past
+// `params.count` with the identity, then halves the partials in workgroup
+// memory with a barrier after every step, outside the divergent `if`. One
+// pass leaves a partial per workgroup in `output`; the next pass reduces
+// those, with the buffers swapped, until one element remains. Workgroups
+// are numbered across `x` and `y` so a pass may exceed the 65535-workgroup
+// limit of one dimension.
+
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// Identity and the combining expression of `a` and `b`, per operation.
+fn operation(name: &str, ty: &str) -> Result<(&'static str, &'static str), Diagnostic> {
+    let identity = match (name, ty) {
+        ("sum", "f32") => "0.0",
+        ("sum", "i32") => "0i",
+        ("sum", "u32") => "0u",
+        ("min", "f32") => "3.40282347e38",
+        ("min", "i32") => "2147483647i",
+        ("min", "u32") => "4294967295u",
+        ("max", "f32") => "-3.40282347e38",
+        ("max", "i32") => "i32(-2147483648)",
+        ("max", "u32") => "0u",
+        ("sum" | "min" | "max", _) => {
+            return Err(Diagnostic::error(format!(
+                "Unsupported reduction type '{ty}'; expected f32, i32 or u32"
+            )));
+        }
+        _ => {
+            return Err(Diagnostic::error(format!(
+                "Unknown reduction '{name}'; expected sum, min or max"
+            )));
+        }
+    };
+    let combine = match name {
+        "sum" => "a + b",
+        "min" => "min(a, b)",
+        _ => "max(a, b)",
+    };
+    Ok((identity, combine))
+}
+
+fn plan(count: u32, per_workgroup: u32) -> Vec<ReductionPass> {
+    let mut passes = Vec::new();
+    let mut count = count;
+    loop {
+        let outputs = count.div_ceil(per_workgroup).max(1);
+        let workgroups_x = outputs.min(MAX_WORKGROUPS);
+        passes.push(ReductionPass {
+            count,
+            outputs,
+            workgroups_x,
+            workgroups_y: outputs.div_ceil(workgroups_x),
+        });
+        if outputs == 1 {
+            return passes;
+        }
+        count = outputs;
+    }
+}
+
+pub(crate) fn reduction_source(options: &ReductionOptions) -> Result<String, Diagnostic> {
+    let size = options.workgroup_size;
+    if !size.is_power_of_two() || size > 256 {
+        return Err(Diagnostic::error(format!(
+            "Workgroup size {size} is not a power of two up to 256"
+        )));
+    }
+    let ty = options.element_type.as_str();
+    let (identity, combine) = operation(&options.operation, ty)?;
+    let op = &options.operation;
+    Ok(format!(
+        "\
+// {op} over array<{ty}>: each workgroup reduces {per} elements to one,
+// written to output[workgroup index].
+struct Params {{
+    count: u32,
+}}
+
+@group(0) @binding(0) var<storage, read> input: array<{ty}>;
+@group(0) @binding(1) var<storage, read_write> output: array<{ty}>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const WORKGROUP_SIZE: u32 = {size}u;
+var<workgroup> partials: array<{ty}, WORKGROUP_SIZE>;
+
+fn combine(a: {ty}, b: {ty}) -> {ty} {{
+    return {combine};
+}}
+
+fn load(i: u32) -> {ty} {{
+    if i < params.count {{
+        return input[i];
+    }}
+    return {identity};
+}}
+
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn reduce(
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) workgroup: vec3<u32>,
+    @builtin(num_workgroups) workgroups: vec3<u32>,
+) {{
+    let index = workgroup.x + workgroup.y * workgroups.x;
+    let base = index * WORKGROUP_SIZE * 2u;
+    partials[local] = combine(load(base + local), load(base + local + WORKGROUP_SIZE));
+    workgroupBarrier();
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {{
+        if local < stride {{
+            partials[local] = combine(partials[local], partials[local + stride]);
+        }}
+        workgroupBarrier();
+    }}
+    // Workgroups past the end, from rounding up the y dimension, write
+    // nothing.
+    if local == 0u && (index == 0u || base < params.count) {{
+        output[index] = partials[0];
+    }}
+}}
+",
+        per = size * 2,
+    ))
+}
+
+pub(crate) fn reduction(options: &ReductionOptions) -> Result<ReductionKernel, Diagnostic> {
+    let wgsl = reduction_source(options)?;
+    let (module, info) = crate::parse_and_validate(&wgsl)?;
+    let (identity, _) = operation(&options.operation, &options.element_type)?;
+    Ok(ReductionKernel {
+        reflection: crate::reflect_module(&module, &info),
+        wgsl,
+        entry_point: "reduce".to_string(),
+        workgroup_size: options.workgroup_size,
+        elements_per_workgroup: options.workgroup_size * 2,
+        identity: identity.to_string(),
+    })
+}
+
+/// A validated parallel reduction kernel: `{ operation: "sum" | "min" |
+/// "max", type?: "f32" | "i32" | "u32", workgroupSize?: number }`. Bind
+/// `input` and `output` storage buffers and a `params` uniform holding the
+/// element count in group 0; `planDispatches(count)` gives each pass's
+/// count and workgroups, swapping `input` and `output` between passes.
+#[wasm_bindgen(js_name = generateReduction)]
+pub fn generate_reduction(options: JsValue) -> Result<ReductionKernel, JsValue> {
+    let options: ReductionOptions = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid reduction options: {e}")))?;
+    reduction(&options).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(operation: &str, ty: &str, workgroup_size: u32) -> ReductionOptions {
+        ReductionOptions {
+            operation: operation.to_string(),
+            element_type: ty.to_string(),
+            workgroup_size,
+        }
+    }
+
+    #[test]
+    fn every_operation_and_type_validates() {
+        for operation in ["sum", "min", "max"] {
+            for ty in ["f32", "i32", "u32"] {
+                let kernel = reduction(&options(operation, ty, 64)).unwrap();
+                assert_eq!(kernel.elements_per_workgroup, 128);
+                let ep = &kernel.reflection.entry_points[0];
+                assert_eq!(ep.workgroup_size, Some(vec![64, 1, 1]));
+                let bindings: Vec<_> = ep.bindings.iter().map(|b| b.name.as_str()).collect();
+                assert_eq!(bindings, ["input", "output", "params"]);
+            }
+        }
+        assert!(reduction(&options("mean", "f32", 64)).is_err());
+        assert!(reduction(&options("sum", "f16", 64)).is_err());
+        assert!(reduction(&options("sum", "f32", 96)).is_err());
+        assert!(reduction(&options("sum", "f32", 512)).is_err());
+    }
+
+    #[test]
+    fn dispatch_plans_reduce_to_one_element() {
+        let passes: Vec<_> = plan(1_000_000, 512)
+            .iter()
+            .map(|p| (p.count, p.outputs, p.workgroups_x, p.workgroups_y))
+            .collect();
+        assert_eq!(
+            passes,
+            [(1_000_000, 1954, 1954, 1), (1954, 4, 4, 1), (4, 1, 1, 1)]
+        );
+
+        let large = plan(100_000_000, 2);
+        assert_eq!((large[0].workgroups_x, large[0].workgroups_y), (65535, 763));
+        assert_eq!(
+            plan(0, 512),
+            [ReductionPass {
+                count: 0,
+                outputs: 1,
+                workgroups_x: 1,
+                workgroups_y: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{BTreeMap, HashMap};
+
+use naga::{GlobalVariable, Handle, ResourceBinding};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::lexer::{attributes_before, module_declarations, tokenize};
+use crate::rename::{TextEdit, text_edit};
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Binding Remap Types
+// ============================================================================
+
+/// Map object accepted by `remapBindings`. A variable's entry in `bindings`
+/// wins over its group's in `groups`, which wins over `groupOffset`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BindingMap {
+    /// Added to every group `groups` and `bindings` leave alone.
+    #[serde(default)]
+    pub group_offset: u32,
+    /// New group by old group, e.g. `{ "0": 1 }`.
+    #[serde(default)]
+    pub groups: BTreeMap<String, u32>,
+    /// New slot by variable name, or by old `"group:binding"`.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Slot>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub(crate) struct Slot {
+    pub group: u32,
+    pub binding: u32,
+}
+
+/// One variable moved by a remap.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BindingRemap {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub from_group: u32,
+    #[wasm_bindgen(readonly)]
+    pub from_binding: u32,
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+}
+
+#[wasm_bindgen]
+impl BindingRemap {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RemappedShader {
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+    /// The `@group` and `@binding` argument edits, against the original.
+    #[wasm_bindgen(readonly)]
+    pub edits: Vec<TextEdit>,
+    /// Variables that moved, in declaration order.
+    #[wasm_bindgen(readonly)]
+    pub remapped: Vec<BindingRemap>,
+    /// Of the remapped shader.
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl RemappedShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Binding Remap Implementation
+// ============================================================================
+//
+// The new slots are worked out on the validated module, where every
+// `@group` and `@binding` is already evaluated, and checked before anything
+// is rewritten: two variables may only end up sharing a slot if they shared
+// it before, as an author can mean when entry points never use both. Source
+// rewrites then replace just the attribute arguments, so comments and
+// formatting survive, and the result is validated again.
+
+pub(crate) fn binding_map(map: JsValue) -> Result<BindingMap, JsValue> {
+    let map: Option<BindingMap> = serde_wasm_bindgen::from_value(map)
+        .map_err(|e| JsValue::from_str(&format!("Invalid binding map: {e}")))?;
+    Ok(map.unwrap_or_default())
+}
+
+fn slot_name(binding: &ResourceBinding) -> String {
+    format!("@group({}) @binding({})", binding.group, binding.binding)
+}
+
+/// Each bound global `map` moves, with its old and new binding, in
+/// declaration order.
+pub(crate) fn plan(
+    module: &naga::Module,
+    map: &BindingMap,
+) -> Result<Vec<(Handle<GlobalVariable>, ResourceBinding, ResourceBinding)>, Diagnostic> {
+    let mut groups = HashMap::new();
+    for (from, to) in &map.groups {
+        let from: u32 = from
+            .parse()
+            .map_err(|_| Diagnostic::error(format!("Group '{from}' is not a number")))?;
+        groups.insert(from, *to);
+    }
+    let bound: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter_map(|(handle, var)| Some((handle, var, var.binding?)))
+        .collect();
+    let key = |var: &GlobalVariable, binding: &ResourceBinding| {
+        let name = var.name.clone().unwrap_or_default();
+        let slot = format!("{}:{}", binding.group, binding.binding);
+        (map.bindings.get(&name), map.bindings.get(&slot))
+    };
+    for name in map.bindings.keys() {
+        let matched = bound.iter().any(|(_, var, binding)| {
+            var.name.as_ref() == Some(name)
+                || *name == format!("{}:{}", binding.group, binding.binding)
+        });
+        if !matched {
+            return Err(Diagnostic::error(format!(
+                "Binding map entry '{name}' names no bound variable"
+            )));
+        }
+    }
+
+    let mut moved = Vec::new();
+    let mut slots: HashMap<ResourceBinding, Vec<(Handle<GlobalVariable>, ResourceBinding)>> =
+        HashMap::new();
+    for &(handle, var, old) in &bound {
+        let new = match key(var, &old) {
+            (Some(slot), _) | (None, Some(slot)) => ResourceBinding {
+                group: slot.group,
+                binding: slot.binding,
+            },
+            (None, None) => ResourceBinding {
+                group: match groups.get(&old.group) {
+                    Some(&group) => group,
+                    None => old.group.checked_add(map.group_offset).ok_or_else(|| {
+                        Diagnostic::error(format!("Group {} plus the offset overflows", old.group))
+                    })?,
+                },
+                binding: old.binding,
+            },
+        };
+        slots.entry(new).or_default().push((handle, old));
+        if new != old {
+            moved.push((handle, old, new));
+        }
+    }
+    for (handle, old, new) in &moved {
+        let sharing = &slots[new];
+        if let Some((other, _)) = sharing.iter().find(|(h, o)| h != handle && o != old) {
+            let name = |h: &Handle<GlobalVariable>| {
+                module.global_variables[*h].name.clone().unwrap_or_default()
+            };
+            return Err(Diagnostic::error(format!(
+                "Remapping puts '{}' and '{}' both at {}",
+                name(handle),
+                name(other),
+                slot_name(new)
+            )));
+        }
+    }
+    Ok(moved)
+}
+
+/// `source` with the `@group` and `@binding` arguments of each variable in
+/// `moved` replaced, and the edits made.
+fn rewrite(
+    source: &str,
+    module: &naga::Module,
+    moved: &[(Handle<GlobalVariable>, ResourceBinding, ResourceBinding)],
+) -> Result<(String, Vec<TextEdit>), Diagnostic> {
+    let tokens = tokenize(source);
+    let declared: HashMap<_, _> = module_declarations(&tokens).into_iter().collect();
+    let mut replacements = Vec::new();
+    for (handle, old, new) in moved {
+        let name = module.global_variables[*handle]
+            .name
+            .as_deref()
+            .unwrap_or_default();
+        let keyword = declared
+            .get(name)
+            .and_then(|&at| (0..at).rev().find(|&i| tokens[i].is_ident("var")));
+        let attributes = keyword
+            .map(|at| attributes_before(&tokens, at))
+            .unwrap_or_default();
+        for (attribute, from, to) in [
+            ("group", old.group, new.group),
+            ("binding", old.binding, new.binding),
+        ] {
+            if from == to {
+                continue;
+            }
+            let (open, close) = attributes
+                .iter()
+                .find(|(a, _)| *a == attribute)
+                .and_then(|(_, args)| *args)
+                .ok_or_else(|| {
+                    Diagnostic::error(format!(
+                        "Cannot find the @{attribute} of '{name}' in the source"
+                    ))
+                })?;
+            replacements.push((tokens[open].end(), tokens[close].start, to.to_string()));
+        }
+    }
+    replacements.sort();
+    let edits = replacements
+        .iter()
+        .map(|(start, end, text)| text_edit(source, *start, *end, text))
+        .collect();
+    let mut out = source.to_string();
+    for (start, end, text) in replacements.iter().rev() {
+        out.replace_range(start..end, text);
+    }
+    Ok((out, edits))
+}
+
+pub(crate) fn remap(wgsl: &str, map: &BindingMap) -> Result<RemappedShader, Diagnostic> {
+    let (module, _) = crate::parse_and_validate(wgsl)?;
+    let moved = plan(&module, map)?;
+    let (remapped_wgsl, edits) = rewrite(wgsl, &module, &moved)?;
+    let (remapped_module, info) = crate::parse_and_validate(&remapped_wgsl)
+        .map_err(|e| Diagnostic::error(format!("Remapped shader is invalid: {}", e.message)))?;
+    let remapped = moved
+        .iter()
+        .map(|(handle, old, new)| BindingRemap {
+            name: module.global_variables[*handle]
+                .name
+                .clone()
+                .unwrap_or_default(),
+            from_group: old.group,
+            from_binding: old.binding,
+            group: new.group,
+            binding: new.binding,
+        })
+        .collect();
+    Ok(RemappedShader {
+        reflection: crate::reflect_module(&remapped_module, &info),
+        wgsl: remapped_wgsl,
+        edits,
+        remapped,
+    })
+}
+
+/// Moves resource bindings before the shader is compiled, e.g. to free
+/// group 0 for per-frame data: `map` is `{ groupOffset?: number, groups?:
+/// { [oldGroup]: newGroup }, bindings?: { [nameOrGroupColonBinding]: {
+/// group, binding } } }`, most specific first. Throws if two variables
+/// would end up sharing a slot they did not share before, or an entry
+/// matches nothing. Returns the rewritten WGSL, the edits, what moved and
+/// the new reflection; `ShaderModule.remapBindings` does the same to a
+/// parsed module.
+#[wasm_bindgen(js_name = remapBindings)]
+pub fn remap_bindings(wgsl: &str, map: JsValue) -> Result<RemappedShader, JsValue> {
+    let map = binding_map(map)?;
+    remap(wgsl, &map).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+@group(0) @binding(0) var<uniform> tint: vec4<f32>;
+@group(0)
+@binding(1) // the sampler
+var smp: sampler;
+@group(1) @binding(0) var tex: texture_2d<f32>;
+
+@fragment
+fn fs() -> @location(0) vec4<f32> {
+    return textureSample(tex, smp, vec2<f32>()) * tint;
+}
+"#;
+
+    fn slots(shader: &RemappedShader) -> Vec<(&str, u32, u32)> {
+        shader.reflection.entry_points[0]
+            .bindings
+            .iter()
+            .map(|b| (b.name.as_str(), b.group, b.binding))
+            .collect()
+    }
+
+    #[test]
+    fn shifts_groups_and_moves_named_bindings() {
+        let map = BindingMap {
+            group_offset: 1,
+            groups: [("1".to_string(), 3)].into(),
+            bindings: [(
+                "tint".to_string(),
+                Slot {
+                    group: 1,
+                    binding: 4,
+                },
+            )]
+            .into(),
+        };
+        let shader = remap(SHADER, &map).unwrap();
+        assert!(
+            shader
+                .wgsl
+                .contains("@group(1)\n@binding(1) // the sampler")
+        );
+        assert!(
+            shader
+                .wgsl
+                .contains("@group(1) @binding(4) var<uniform> tint")
+        );
+        assert!(shader.wgsl.contains("@group(3) @binding(0) var tex"));
+        let mut slots = slots(&shader);
+        slots.sort();
+        assert_eq!(slots, [("smp", 1, 1), ("tex", 3, 0), ("tint", 1, 4)]);
+        let moved: Vec<_> = shader.remapped.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(moved, ["tint", "smp", "tex"]);
+        assert_eq!(shader.edits.len(), 4);
+        assert_eq!(shader.edits[0].new_text, "1");
+
+        let unchanged = remap(SHADER, &BindingMap::default()).unwrap();
+        assert_eq!(unchanged.wgsl, SHADER);
+        assert!(unchanged.remapped.is_empty());
+    }
+
+    #[test]
+    fn conflicts_and_stray_entries_are_errors() {
+        let onto_tex = BindingMap {
+            bindings: [(
+                "0:0".to_string(),
+                Slot {
+                    group: 1,
+                    binding: 0,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let error = remap(SHADER, &onto_tex).err().unwrap().message;
+        assert_eq!(
+            error,
+            "Remapping puts 'tint' and 'tex' both at @group(1) @binding(0)"
+        );
+        let stray = BindingMap {
+            bindings: [(
+                "missing".to_string(),
+                Slot {
+                    group: 0,
+                    binding: 0,
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        assert!(
+            remap(SHADER, &stray)
+                .err()
+                .unwrap()
+                .message
+                .contains("'missing'")
+        );
+        let bad_group = BindingMap {
+            groups: [("first".to_string(), 1)].into(),
+            ..Default::default()
+        };
+        assert!(remap(SHADER, &bad_group).is_err());
+    }
+}
@@ -0,0 +1,460 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::directory::parse_file;
+use crate::lexer::{Token, TokenKind, module_declarations, skip_template, tokenize};
+
+// ============================================================================
+// Rename Types
+// ============================================================================
+
+/// The replacements a rename made, one entry per changed file.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RenameResult {
+    /// Sorted by path. The declaring file comes first only if it sorts first.
+    #[wasm_bindgen(readonly)]
+    pub files: Vec<FileEdit>,
+}
+
+#[wasm_bindgen]
+impl RenameResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct FileEdit {
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    /// In source order, offsets into the text before the rename.
+    #[wasm_bindgen(readonly)]
+    pub edits: Vec<TextEdit>,
+    /// The text after the rename, as now registered in the project.
+    #[wasm_bindgen(readonly)]
+    pub source: String,
+}
+
+#[wasm_bindgen]
+impl FileEdit {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// One replaced identifier. Offsets and columns count UTF-16 code units so
+/// they can be used with JS string methods directly.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct TextEdit {
+    #[wasm_bindgen(readonly)]
+    pub start: u32,
+    #[wasm_bindgen(readonly)]
+    pub end: u32,
+    /// 1-based.
+    #[wasm_bindgen(readonly)]
+    pub line: u32,
+    /// 1-based.
+    #[wasm_bindgen(readonly)]
+    pub column: u32,
+    #[wasm_bindgen(readonly)]
+    pub new_text: String,
+}
+
+#[wasm_bindgen]
+impl TextEdit {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Rename Implementation
+// ============================================================================
+//
+// Works on tokens rather than naga's IR so comments, formatting and
+// `#include` lines survive. The symbol must be declared at module scope in
+// exactly one registered file; that file and everything including it are
+// rewritten. Member accesses, struct members, attribute arguments and locals
+// or parameters shadowing the symbol are left alone.
+
+const KEYWORDS: &[&str] = &[
+    "alias",
+    "break",
+    "case",
+    "const",
+    "const_assert",
+    "continue",
+    "continuing",
+    "default",
+    "diagnostic",
+    "discard",
+    "else",
+    "enable",
+    "false",
+    "fn",
+    "for",
+    "if",
+    "let",
+    "loop",
+    "override",
+    "requires",
+    "return",
+    "struct",
+    "switch",
+    "true",
+    "var",
+    "while",
+];
+
+/// Attributes whose arguments are enumerants rather than expressions.
+const ENUMERANT_ATTRIBUTES: &[&str] = &["builtin", "interpolate", "diagnostic"];
+
+/// Rename `symbol` to `new_name` in its declaring file and every dependent,
+/// returning the changed files. `files` is left untouched; the caller applies
+/// the returned sources.
+pub(crate) fn rename_across(
+    files: &BTreeMap<String, String>,
+    dependents_of: impl Fn(&str) -> Vec<String>,
+    symbol: &str,
+    new_name: &str,
+) -> Result<RenameResult, Diagnostic> {
+    if !is_identifier(new_name) {
+        return Err(Diagnostic::error(format!(
+            "'{}' is not a valid identifier",
+            new_name
+        )));
+    }
+
+    let declaring: Vec<_> = files
+        .iter()
+        .filter(|(path, source)| {
+            path.ends_with(".wgsl")
+                && module_declarations(&tokenize(source))
+                    .iter()
+                    .any(|(name, _)| *name == symbol)
+        })
+        .map(|(path, _)| path.as_str())
+        .collect();
+    let header = match declaring.as_slice() {
+        [] => {
+            return Err(Diagnostic::error(format!(
+                "No module-scope declaration of '{}' in the project",
+                symbol
+            )));
+        }
+        [path] => *path,
+        paths => {
+            return Err(Diagnostic::error(format!(
+                "'{}' is declared in several files: {}",
+                symbol,
+                paths.join(", ")
+            )));
+        }
+    };
+
+    let affected = dependents_of(header);
+    let mut renamed = files.clone();
+    let mut result = Vec::new();
+    for path in &affected {
+        let source = &files[path];
+        let tokens = tokenize(source);
+        // Conservative: any existing use of the new name could capture or
+        // collide with a renamed reference.
+        if tokens.iter().any(|t| t.is_ident(new_name)) {
+            return Err(Diagnostic::error(format!(
+                "'{}' is already used in {}",
+                new_name, path
+            )));
+        }
+        let spans = references(&tokens, symbol);
+        if spans.is_empty() {
+            continue;
+        }
+        let new_source = splice(source, &spans, new_name);
+        let edits = spans
+            .iter()
+            .map(|&(start, end)| text_edit(source, start, end, new_name))
+            .collect();
+        renamed.insert(path.clone(), new_source.clone());
+        result.push(FileEdit {
+            path: path.clone(),
+            edits,
+            source: new_source,
+        });
+    }
+
+    // The token walk is heuristic; never hand back a rename that breaks a
+    // file which built before.
+    for path in affected.iter().filter(|p| p.ends_with(".wgsl")) {
+        if parse_file(path, files).is_ok()
+            && let Err(diagnostic) = parse_file(path, &renamed)
+        {
+            return Err(Diagnostic::error(format!(
+                "Renaming '{}' would break {}: {}",
+                symbol, path, diagnostic.message
+            )));
+        }
+    }
+
+    Ok(RenameResult { files: result })
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let tokens = tokenize(name);
+    matches!(tokens.as_slice(), [t] if t.kind == TokenKind::Ident && t.text == name)
+        && !name.starts_with("__")
+        && !KEYWORDS.contains(&name)
+}
+
+/// Byte ranges of every token referring to the module-scope `symbol`.
+fn references(tokens: &[Token], symbol: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    // One entry per open brace: whether `symbol` is shadowed in that scope.
+    let mut scopes = vec![false];
+    // A local declaration of `symbol` takes effect after its initializer.
+    let mut shadow_at_semicolon = None;
+    // A parameter named `symbol` shadows it in the function body.
+    let mut shadow_next_scope = false;
+    let mut paren_depth = 0usize;
+    let mut params_depth = None;
+    // Between `case` and the `:` or `{` ending its selectors.
+    let mut case_selectors = false;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let prev = i.checked_sub(1).map(|p| tokens[p]);
+        let next = tokens.get(i + 1);
+        match token.kind {
+            TokenKind::Punct('{') => {
+                case_selectors = false;
+                let inherited = *scopes.last().unwrap_or(&false);
+                scopes.push(inherited || std::mem::take(&mut shadow_next_scope));
+            }
+            TokenKind::Punct('}') if scopes.len() > 1 => {
+                scopes.pop();
+            }
+            TokenKind::Punct(';') if shadow_at_semicolon == Some(scopes.len()) => {
+                shadow_at_semicolon = None;
+                if let Some(top) = scopes.last_mut() {
+                    *top = true;
+                }
+            }
+            TokenKind::Punct('(') => {
+                paren_depth += 1;
+                if prev.is_some_and(|p| p.kind == TokenKind::Ident)
+                    && i >= 2
+                    && tokens[i - 2].is_ident("fn")
+                {
+                    params_depth = Some(paren_depth);
+                }
+            }
+            TokenKind::Punct(')') => {
+                if params_depth == Some(paren_depth) {
+                    params_depth = None;
+                }
+                paren_depth = paren_depth.saturating_sub(1);
+            }
+            TokenKind::Punct(':') => case_selectors = false,
+            TokenKind::Ident if token.text == "case" => case_selectors = true,
+            TokenKind::Punct('@') => {
+                // Skip the attribute name, and enumerant arguments entirely.
+                if let Some(name) = next
+                    && ENUMERANT_ATTRIBUTES.contains(&name.text)
+                    && tokens.get(i + 2).is_some_and(|t| t.is_punct('('))
+                {
+                    i = skip_parens(tokens, i + 2);
+                    continue;
+                }
+                i += 2;
+                continue;
+            }
+            TokenKind::Ident if token.text == symbol => {
+                let member = prev.is_some_and(|p| p.is_punct('.'));
+                let followed_by_colon = next.is_some_and(|n| n.is_punct(':'));
+                let local = scopes.len() > 1
+                    && prev.is_some_and(|p| {
+                        p.is_ident("let")
+                            || p.is_ident("var")
+                            || p.is_ident("const")
+                            || p.is_punct('>') && declares_var(tokens, i)
+                    });
+                if local {
+                    shadow_at_semicolon = Some(scopes.len());
+                } else if params_depth.is_some() && followed_by_colon {
+                    shadow_next_scope = true;
+                } else if member || (scopes.len() > 1 && followed_by_colon && !case_selectors) {
+                    // Struct members are only ever seen through `.`.
+                } else if !scopes.last().copied().unwrap_or(false) {
+                    spans.push((token.start, token.end()));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    spans
+}
+
+/// Whether the `>` before `tokens[at]` closes the template of a `var`.
+fn declares_var(tokens: &[Token], at: usize) -> bool {
+    tokens[..at]
+        .iter()
+        .rposition(|t| t.is_ident("var"))
+        .is_some_and(|var| skip_template(tokens, var + 1) == at)
+}
+
+/// Index past the balanced parentheses opening at `at`.
+fn skip_parens(tokens: &[Token], at: usize) -> usize {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(at) {
+        if token.is_punct('(') {
+            depth += 1;
+        } else if token.is_punct(')') {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+    }
+    tokens.len()
+}
+
+fn splice(source: &str, spans: &[(usize, usize)], new_name: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    for &(start, end) in spans {
+        out.push_str(&source[copied..start]);
+        out.push_str(new_name);
+        copied = end;
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+pub(crate) fn text_edit(source: &str, start: usize, end: usize, new_name: &str) -> TextEdit {
+    let before = &source[..start];
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    let utf16 = |s: &str| s.encode_utf16().count() as u32;
+    TextEdit {
+        start: utf16(before),
+        end: utf16(&source[..end]),
+        line: before.matches('\n').count() as u32 + 1,
+        column: utf16(&before[line_start..]) + 1,
+        new_text: new_name.to_string(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+
+    fn project(entries: &[(&str, &str)]) -> Project {
+        let mut project = Project::new();
+        for (path, source) in entries {
+            project.set_source(path.to_string(), source.to_string());
+        }
+        project
+    }
+
+    const LIGHT: &str = "// Light helpers\nstruct Light { color: vec3<f32>, Light: f32 }\nfn shade(l: Light) -> vec3<f32> { return l.color * l.Light; }\n";
+
+    #[test]
+    fn renames_declaration_and_dependents() {
+        let mut p = project(&[
+            ("lib/light.wgsl", LIGHT),
+            (
+                "main.wgsl",
+                "#include \"lib/light.wgsl\"\n@fragment fn fs() -> @location(0) vec4<f32> {\n    let l = Light(vec3<f32>(1.0), 2.0); // Light\n    return vec4<f32>(shade(l), 1.0);\n}\n",
+            ),
+            ("other.wgsl", "struct Light { x: f32 }"),
+        ]);
+        // `other.wgsl` declares its own `Light` too.
+        assert!(p.rename_files("Light", "PointLight").is_err());
+        p.remove_source("other.wgsl");
+
+        let result = p.rename_files("Light", "PointLight").unwrap();
+        let paths: Vec<_> = result.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["lib/light.wgsl", "main.wgsl"]);
+
+        let header = &result.files[0].source;
+        assert!(
+            header.starts_with(
+                "// Light helpers\nstruct PointLight { color: vec3<f32>, Light: f32 }"
+            )
+        );
+        assert!(header.contains("fn shade(l: PointLight)"));
+        assert!(header.contains("l.Light"));
+        let main = &result.files[1];
+        assert!(
+            main.source
+                .contains("let l = PointLight(vec3<f32>(1.0), 2.0); // Light")
+        );
+        assert_eq!(
+            main.edits,
+            vec![TextEdit {
+                start: 84,
+                end: 89,
+                line: 3,
+                column: 13,
+                new_text: "PointLight".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn shadowing_locals_and_parameters_are_kept() {
+        let source = "fn scale(x: f32) -> f32 { return x * 2.0; }\nfn a(scale: f32) -> f32 { return scale; }\nfn b() -> f32 { let y = scale(1.0); let scale = 3.0; return scale + y; }\n";
+        let tokens = tokenize(source);
+        let renamed = splice(source, &references(&tokens, "scale"), "grow");
+        assert_eq!(
+            renamed,
+            "fn grow(x: f32) -> f32 { return x * 2.0; }\nfn a(scale: f32) -> f32 { return scale; }\nfn b() -> f32 { let y = grow(1.0); let scale = 3.0; return scale + y; }\n"
+        );
+    }
+
+    #[test]
+    fn case_selectors_are_references() {
+        let source = "const MODE_A = 0u;\nconst MODE_B = 1u;\nfn f(m: u32) {\n    switch m {\n        case MODE_A: { }\n        case 2u, MODE_B: { }\n        case MODE_B + 1u { }\n        default { }\n    }\n}\n";
+        let tokens = tokenize(source);
+        let renamed = splice(source, &references(&tokens, "MODE_A"), "FIRST");
+        assert!(renamed.contains("case FIRST: { }"));
+        let renamed = splice(source, &references(&tokens, "MODE_B"), "SECOND");
+        assert!(renamed.contains("case 2u, SECOND: { }"));
+        assert!(renamed.contains("case SECOND + 1u { }"));
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        let mut p = project(&[
+            (
+                "a.wgsl",
+                "const SIZE = 4u;\nfn count() -> u32 { return SIZE; }",
+            ),
+            ("b.wgsl", "struct Unrelated { y: f32 }"),
+        ]);
+        assert!(p.rename_files("SIZE", "fn").is_err());
+        assert!(p.rename_files("SIZE", "2x").is_err());
+        assert!(p.rename_files("SIZE", "count").is_err());
+        assert!(p.rename_files("MISSING", "OTHER").is_err());
+        // Names in files that do not include the header are free to reuse.
+        assert!(p.rename_files("SIZE", "Unrelated").is_ok());
+    }
+}
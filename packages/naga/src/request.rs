@@ -0,0 +1,84 @@
+use serde::Deserialize;
+
+use crate::Diagnostic;
+use crate::{glsl, hlsl, msl, preset, spv};
+
+// ============================================================================
+// Compile Request Types
+// ============================================================================
+
+/// One compile for the native bindings, which take a target name and its
+/// options as data rather than one function per backend.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompileRequest {
+    /// `spirv`, `msl`, `hlsl` or `glsl`.
+    pub target: String,
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Applies to SPIR-V; the others take theirs in `options`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// GLSL's stage.
+    #[serde(default)]
+    pub stage: Option<String>,
+    /// The target's options object, as the JS API takes it.
+    #[serde(default)]
+    pub options: serde_json::Value,
+}
+
+pub(crate) enum RequestError {
+    /// The request itself is malformed.
+    Invalid(Diagnostic),
+    /// The shader failed to compile.
+    Failed(Diagnostic),
+}
+
+// ============================================================================
+// Compile Request Implementation
+// ============================================================================
+
+fn options<T: serde::de::DeserializeOwned + Default>(
+    value: serde_json::Value,
+) -> Result<T, RequestError> {
+    match value {
+        serde_json::Value::Null => Ok(T::default()),
+        value => serde_json::from_value(value).map_err(|e| {
+            RequestError::Invalid(Diagnostic::error(format!("Invalid compile options: {e}")))
+        }),
+    }
+}
+
+/// SPIR-V as bytes, the other targets as UTF-8 text.
+pub(crate) fn compile(wgsl: &str, request: CompileRequest) -> Result<Vec<u8>, RequestError> {
+    let entry_point = request.entry_point.as_deref();
+    let output = match request.target.as_str() {
+        "spirv" => {
+            let preset =
+                preset::resolve(request.preset.as_deref()).map_err(RequestError::Invalid)?;
+            let options: spv::SpirvOptions = options(request.options)?;
+            crate::compile_spirv_with(wgsl, entry_point, preset, &options)
+        }
+        "msl" => {
+            let options: msl::MslOptions = options(request.options)?;
+            msl::compile_msl_mapped(wgsl, entry_point, &options).map(|out| out.source.into_bytes())
+        }
+        "hlsl" => {
+            let options: hlsl::HlslOptions = options(request.options)?;
+            hlsl::compile_hlsl(wgsl, entry_point, &options).map(String::into_bytes)
+        }
+        "glsl" => {
+            let options: glsl::GlslOptions = options(request.options)?;
+            let stage = request.stage.ok_or_else(|| {
+                RequestError::Invalid(Diagnostic::error("A GLSL compile request needs a stage"))
+            })?;
+            glsl::compile_glsl(wgsl, entry_point, &stage, &options).map(String::into_bytes)
+        }
+        other => {
+            return Err(RequestError::Invalid(Diagnostic::error(format!(
+                "Unknown target '{other}'; expected spirv, msl, hlsl or glsl"
+            ))));
+        }
+    };
+    output.map_err(RequestError::Failed)
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+use crate::root_signature::RootSignature;
+use crate::usage::BindingUsageReport;
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Resource Names
+// ============================================================================
+//
+// A render graph names its resources itself, `FrameConstants` or
+// `GBuffer.albedo`, and a shader's variable names are its author's choice.
+// The annotation map bridges the two, keyed like the `remapBindings` map:
+// by variable name, or by `"group:binding"` for a slot whatever the
+// variable there is called, the name winning if both match. Output gains a
+// `resourceName` wherever a binding appears; the variable names stay, for
+// mapping back to the WGSL.
+
+/// Logical resource name by variable name or `"group:binding"`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(transparent)]
+pub(crate) struct ResourceNames(BTreeMap<String, String>);
+
+pub(crate) fn resource_names(value: JsValue) -> Result<ResourceNames, JsValue> {
+    parse_resource_names(value).map_err(|e| JsValue::from_str(&e.message))
+}
+
+/// `resource_names`, failing with a diagnostic for the `try*` variants.
+pub(crate) fn parse_resource_names(value: JsValue) -> Result<ResourceNames, Diagnostic> {
+    let names: Option<ResourceNames> = serde_wasm_bindgen::from_value(value)
+        .map_err(|e| Diagnostic::error(format!("Invalid resource names: {e}")))?;
+    Ok(names.unwrap_or_default())
+}
+
+impl ResourceNames {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The resource bound as `name` at `group`, `binding`.
+    pub(crate) fn get(&self, name: &str, group: u32, binding: u32) -> Option<String> {
+        self.0
+            .get(name)
+            .or_else(|| self.0.get(&format!("{group}:{binding}")))
+            .cloned()
+    }
+
+    /// Keys that name none of `bindings`, given as variable name, group and
+    /// binding.
+    fn unmatched(&self, bindings: &[(&str, u32, u32)]) -> Vec<&str> {
+        let keys: Vec<String> = bindings
+            .iter()
+            .flat_map(|(name, group, binding)| [name.to_string(), format!("{group}:{binding}")])
+            .collect();
+        self.0
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Fails on the first key that names no bound variable of `module`.
+    pub(crate) fn check(&self, module: &naga::Module) -> Result<(), Diagnostic> {
+        let bindings: Vec<_> = module
+            .global_variables
+            .iter()
+            .filter_map(|(_, var)| {
+                let binding = var.binding.as_ref()?;
+                Some((
+                    var.name.as_deref().unwrap_or_default(),
+                    binding.group,
+                    binding.binding,
+                ))
+            })
+            .collect();
+        match self.unmatched(&bindings).first() {
+            Some(key) => Err(Diagnostic::error(format!(
+                "Resource name entry '{key}' names no bound variable"
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn annotate_reflection(&self, reflection: &mut ReflectionData) {
+        for binding in reflection
+            .entry_points
+            .iter_mut()
+            .flat_map(|ep| &mut ep.bindings)
+        {
+            binding.resource_name = self.get(&binding.name, binding.group, binding.binding);
+        }
+    }
+
+    /// Names the ranges of WGSL variables; naga's sampler heaps and index
+    /// buffers have none.
+    pub(crate) fn annotate_root_signature(&self, signature: &mut RootSignature) {
+        for range in signature.parameters.iter_mut().flat_map(|p| &mut p.ranges) {
+            if let Some(name) = &range.name {
+                range.resource_name = self.get(name, range.space, range.base_register);
+            }
+        }
+    }
+
+    /// Annotates every use, and reports keys no file uses as error
+    /// diagnostics, since they may be meant for files that failed to build.
+    pub(crate) fn annotate_usage(&self, report: &mut BindingUsageReport) {
+        let mut bindings = Vec::new();
+        for slot in &mut report.slots {
+            for used in &mut slot.uses {
+                used.resource_name = self.get(&used.name, slot.group, slot.binding);
+                bindings.push((used.name.as_str(), slot.group, slot.binding));
+            }
+        }
+        let unmatched: Vec<_> = self
+            .unmatched(&bindings)
+            .into_iter()
+            .map(|key| {
+                Diagnostic::error(format!(
+                    "Resource name entry '{key}' names no binding in the project"
+                ))
+            })
+            .collect();
+        report.diagnostics.extend(unmatched);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<uniform> frame: vec4<f32>;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+        @group(1) @binding(1) var albedo_sampler: sampler;
+
+        @fragment
+        fn fs() -> @location(0) vec4<f32> {
+            return textureSample(albedo, albedo_sampler, frame.xy);
+        }
+    "#;
+
+    fn names(entries: &[(&str, &str)]) -> ResourceNames {
+        ResourceNames(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn names_and_slots_annotate_reflection_and_layouts() {
+        let names = names(&[
+            ("frame", "FrameConstants"),
+            ("1:0", "GBuffer.albedo"),
+            ("albedo", "GBuffer.color"),
+        ]);
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        names.check(&module).unwrap();
+
+        let mut reflection = crate::reflect_module(&module, &info);
+        names.annotate_reflection(&mut reflection);
+        let annotated: Vec<_> = reflection.entry_points[0]
+            .bindings
+            .iter()
+            .map(|b| (b.name.as_str(), b.resource_name.as_deref()))
+            .collect();
+        assert_eq!(
+            annotated,
+            [
+                ("frame", Some("FrameConstants")),
+                ("albedo", Some("GBuffer.color")),
+                ("albedo_sampler", None),
+            ]
+        );
+
+        let mut signature = crate::root_signature::root_signature(&module, &info, None).unwrap();
+        names.annotate_root_signature(&mut signature);
+        let ranges: Vec<_> = signature
+            .parameters
+            .iter()
+            .flat_map(|p| &p.ranges)
+            .filter_map(|r| r.resource_name.as_deref())
+            .collect();
+        assert_eq!(ranges, ["FrameConstants", "GBuffer.color"]);
+    }
+
+    #[test]
+    fn stray_entries_are_reported() {
+        let (module, _) = crate::parse_and_validate(SHADER).unwrap();
+        let error = names(&[("2:0", "Shadows")]).check(&module).err().unwrap();
+        assert_eq!(
+            error.message,
+            "Resource name entry '2:0' names no bound variable"
+        );
+
+        let files = [("a.wgsl".to_string(), SHADER.to_string())].into();
+        let mut report = crate::usage::binding_usage(&files);
+        names(&[("frame", "FrameConstants"), ("lights", "Lights")]).annotate_usage(&mut report);
+        assert_eq!(
+            report.slots[0].uses[0].resource_name.as_deref(),
+            Some("FrameConstants")
+        );
+        assert_eq!(report.diagnostics.len(), 1);
+        assert!(report.diagnostics[0].message.contains("'lights'"));
+    }
+}
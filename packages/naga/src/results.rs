@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Result Types
+// ============================================================================
+//
+// The `try*` functions mirror the throwing API one-to-one, but never throw:
+// failures come back as `{ ok: false, diagnostics }` so batch pipelines can
+// collect every failure instead of unwinding on the first one.
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ValidationResult {
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl ValidationResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BinaryResult {
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub value: Option<Vec<u8>>,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl BinaryResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct TextResult {
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub value: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl TextResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct ReflectionResult {
+    #[wasm_bindgen(readonly)]
+    pub ok: bool,
+    #[wasm_bindgen(readonly)]
+    pub value: Option<ReflectionData>,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl ReflectionResult {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Split a `Result` into the `(ok, value, diagnostics)` triple shared by every result type.
+fn split<T>(result: Result<T, Diagnostic>) -> (bool, Option<T>, Vec<Diagnostic>) {
+    match result {
+        Ok(value) => (true, Some(value), Vec::new()),
+        Err(diagnostic) => (false, None, vec![diagnostic]),
+    }
+}
+
+// ============================================================================
+// Result-Object API
+// ============================================================================
+
+/// Like `validateWgsl`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = tryValidateWgsl)]
+pub fn try_validate_wgsl(wgsl: &str, profile: Option<String>) -> ValidationResult {
+    let (ok, _, diagnostics) = split(crate::parse_and_validate_as(wgsl, profile.as_deref()));
+    ValidationResult { ok, diagnostics }
+}
+
+/// Like `wgslToSpirvBin`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = tryWgslToSpirvBin)]
+pub fn try_wgsl_to_spirv_bin(
+    wgsl: &str,
+    entry_point: Option<String>,
+    preset: Option<String>,
+    options: JsValue,
+) -> BinaryResult {
+    let options = crate::parse_spirv_options(options);
+    spirv_result(wgsl, entry_point.as_deref(), preset.as_deref(), options)
+}
+
+fn spirv_result(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    preset: Option<&str>,
+    options: Result<crate::spv::SpirvOptions, Diagnostic>,
+) -> BinaryResult {
+    let compiled = options.and_then(|options| {
+        let preset = crate::preset::resolve(preset)?;
+        crate::compile_spirv_with(wgsl, entry_point, preset, &options)
+    });
+    let (ok, value, diagnostics) = split(compiled);
+    BinaryResult {
+        ok,
+        value,
+        diagnostics,
+    }
+}
+
+/// Like `wgslToMsl`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = tryWgslToMsl)]
+pub fn try_wgsl_to_msl(
+    wgsl: &str,
+    entry_point: Option<String>,
+    preset: Option<String>,
+) -> TextResult {
+    let (ok, value, diagnostics) = split(
+        crate::preset::resolve(preset.as_deref())
+            .and_then(|preset| crate::compile_msl_with(wgsl, entry_point.as_deref(), preset)),
+    );
+    TextResult {
+        ok,
+        value,
+        diagnostics,
+    }
+}
+
+/// Like `spirvBinToText`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = trySpirvBinToText)]
+pub fn try_spirv_bin_to_text(spirv_bytes: &[u8]) -> TextResult {
+    let (ok, value, diagnostics) = split(crate::spirv_to_text(spirv_bytes));
+    TextResult {
+        ok,
+        value,
+        diagnostics,
+    }
+}
+
+/// Like `spirvToWgsl`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = trySpirvToWgsl)]
+pub fn try_spirv_to_wgsl(spirv_bytes: &[u8]) -> TextResult {
+    let (ok, value, diagnostics) = split(crate::spirv_to_wgsl(spirv_bytes));
+    TextResult {
+        ok,
+        value,
+        diagnostics,
+    }
+}
+
+/// Like `reflectWgsl`, but reports failures in the result instead of throwing.
+#[wasm_bindgen(js_name = tryReflectWgsl)]
+pub fn try_reflect_wgsl(wgsl: &str, resource_names: JsValue) -> ReflectionResult {
+    reflection_result(wgsl, crate::resources::parse_resource_names(resource_names))
+}
+
+fn reflection_result(
+    wgsl: &str,
+    names: Result<crate::resources::ResourceNames, Diagnostic>,
+) -> ReflectionResult {
+    let reflected = names.and_then(|names| crate::reflect_named(wgsl, &names));
+    let (ok, value, diagnostics) = split(reflected);
+    ReflectionResult {
+        ok,
+        value,
+        diagnostics,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = r#"
+        @vertex
+        fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+            return vec4f(f32(i), 0.0, 0.0, 1.0);
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4f {
+            return vec4f(1.0);
+        }
+    "#;
+
+    #[test]
+    fn valid_shader_has_no_diagnostics() {
+        let result = try_validate_wgsl(TRIANGLE, None);
+        assert!(result.ok);
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn parse_error_is_reported_not_thrown() {
+        let result = spirv_result("fn broken( {", None, None, Ok(Default::default()));
+        assert!(!result.ok);
+        assert!(result.value.is_none());
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].severity, "error");
+    }
+
+    #[test]
+    fn missing_entry_point_is_reported() {
+        let result = try_wgsl_to_msl(TRIANGLE, Some("nope".to_string()), None);
+        assert!(!result.ok);
+        assert!(result.diagnostics[0].message.contains("'nope' not found"));
+    }
+
+    #[test]
+    fn successful_compile_carries_value() {
+        let result = spirv_result(TRIANGLE, Some("fs_main"), None, Ok(Default::default()));
+        assert!(result.ok);
+        let bytes = result.value.unwrap();
+        // SPIR-V magic number, little-endian
+        assert_eq!(&bytes[..4], &[0x03, 0x02, 0x23, 0x07]);
+    }
+
+    #[test]
+    fn options_mirror_the_throwing_calls() {
+        let options = crate::spv::SpirvOptions {
+            version: Some("2.0".to_string()),
+            ..Default::default()
+        };
+        let result = spirv_result(TRIANGLE, Some("fs_main"), None, Ok(options));
+        assert!(!result.ok);
+        assert!(
+            result.diagnostics[0]
+                .message
+                .contains("Unknown SPIR-V version '2.0'")
+        );
+
+        let names: crate::resources::ResourceNames =
+            serde_json::from_str(r#"{ "missing": "FrameConstants" }"#).unwrap();
+        let result = reflection_result(TRIANGLE, Ok(names));
+        assert!(!result.ok);
+        assert!(reflection_result(TRIANGLE, Ok(Default::default())).ok);
+    }
+}
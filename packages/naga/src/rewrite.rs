@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use naga::{
+    Arena, AtomicFunction, Block, Expression, GatherMode, Handle, ImageQuery, Range,
+    RayQueryFunction, SampleLevel, Statement,
+};
+
+// ============================================================================
+// IR Rewriting Utilities
+// ============================================================================
+//
+// Shared by the passes that rebuild function bodies. naga has no public way
+// to remap handles, so these enumerate every handle an expression or a
+// statement holds.
+
+type Expr = Handle<Expression>;
+
+/// Call `f` on every expression handle `expr` reads.
+pub(crate) fn visit_operands(expr: &mut Expression, f: &mut impl FnMut(&mut Expr)) {
+    let opt = |h: &mut Option<Expr>, f: &mut dyn FnMut(&mut Expr)| {
+        if let Some(h) = h {
+            f(h);
+        }
+    };
+    match expr {
+        Expression::Literal(_)
+        | Expression::Constant(_)
+        | Expression::Override(_)
+        | Expression::ZeroValue(_)
+        | Expression::FunctionArgument(_)
+        | Expression::GlobalVariable(_)
+        | Expression::LocalVariable(_)
+        | Expression::CallResult(_)
+        | Expression::AtomicResult { .. }
+        | Expression::WorkGroupUniformLoadResult { .. }
+        | Expression::RayQueryProceedResult
+        | Expression::SubgroupBallotResult
+        | Expression::SubgroupOperationResult { .. } => {}
+        Expression::Compose { components, .. } => components.iter_mut().for_each(f),
+        Expression::Access { base, index } => {
+            f(base);
+            f(index);
+        }
+        Expression::AccessIndex { base, .. } => f(base),
+        Expression::Splat { value, .. } => f(value),
+        Expression::Swizzle { vector, .. } => f(vector),
+        Expression::Load { pointer } => f(pointer),
+        Expression::ImageSample {
+            image,
+            sampler,
+            coordinate,
+            array_index,
+            offset,
+            level,
+            depth_ref,
+            ..
+        } => {
+            f(image);
+            f(sampler);
+            f(coordinate);
+            opt(array_index, f);
+            opt(offset, f);
+            match level {
+                SampleLevel::Auto | SampleLevel::Zero => {}
+                SampleLevel::Exact(h) | SampleLevel::Bias(h) => f(h),
+                SampleLevel::Gradient { x, y } => {
+                    f(x);
+                    f(y);
+                }
+            }
+            opt(depth_ref, f);
+        }
+        Expression::ImageLoad {
+            image,
+            coordinate,
+            array_index,
+            sample,
+            level,
+        } => {
+            f(image);
+            f(coordinate);
+            opt(array_index, f);
+            opt(sample, f);
+            opt(level, f);
+        }
+        Expression::ImageQuery { image, query } => {
+            f(image);
+            if let ImageQuery::Size { level } = query {
+                opt(level, f);
+            }
+        }
+        Expression::Unary { expr, .. } => f(expr),
+        Expression::Binary { left, right, .. } => {
+            f(left);
+            f(right);
+        }
+        Expression::Select {
+            condition,
+            accept,
+            reject,
+        } => {
+            f(condition);
+            f(accept);
+            f(reject);
+        }
+        Expression::Derivative { expr, .. } => f(expr),
+        Expression::Relational { argument, .. } => f(argument),
+        Expression::Math {
+            arg,
+            arg1,
+            arg2,
+            arg3,
+            ..
+        } => {
+            f(arg);
+            opt(arg1, f);
+            opt(arg2, f);
+            opt(arg3, f);
+        }
+        Expression::As { expr, .. } => f(expr),
+        Expression::ArrayLength(h) => f(h),
+        Expression::RayQueryVertexPositions { query, .. }
+        | Expression::RayQueryGetIntersection { query, .. } => f(query),
+    }
+}
+
+/// Expression handles `expr` reads, in order.
+pub(crate) fn operands(expr: &Expression) -> Vec<Expr> {
+    let mut out = Vec::new();
+    visit_operands(&mut expr.clone(), &mut |h| out.push(*h));
+    out
+}
+
+/// Call `f` on every expression handle a statement holds itself, results
+/// included. Nested blocks and `Emit` ranges are not visited.
+pub(crate) fn visit_statement_operands(statement: &mut Statement, f: &mut impl FnMut(&mut Expr)) {
+    let opt = |h: &mut Option<Expr>, f: &mut dyn FnMut(&mut Expr)| {
+        if let Some(h) = h {
+            f(h);
+        }
+    };
+    match statement {
+        Statement::Emit(_)
+        | Statement::Block(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Kill
+        | Statement::ControlBarrier(_)
+        | Statement::MemoryBarrier(_) => {}
+        Statement::If { condition, .. } => f(condition),
+        Statement::Switch { selector, .. } => f(selector),
+        Statement::Loop { break_if, .. } => opt(break_if, f),
+        Statement::Return { value } => opt(value, f),
+        Statement::Store { pointer, value } => {
+            f(pointer);
+            f(value);
+        }
+        Statement::ImageStore {
+            image,
+            coordinate,
+            array_index,
+            value,
+        } => {
+            f(image);
+            f(coordinate);
+            opt(array_index, f);
+            f(value);
+        }
+        Statement::Atomic {
+            pointer,
+            fun,
+            value,
+            result,
+        } => {
+            f(pointer);
+            if let AtomicFunction::Exchange { compare } = fun {
+                opt(compare, f);
+            }
+            f(value);
+            opt(result, f);
+        }
+        Statement::ImageAtomic {
+            image,
+            coordinate,
+            array_index,
+            fun,
+            value,
+        } => {
+            f(image);
+            f(coordinate);
+            opt(array_index, f);
+            if let AtomicFunction::Exchange { compare } = fun {
+                opt(compare, f);
+            }
+            f(value);
+        }
+        Statement::WorkGroupUniformLoad { pointer, result } => {
+            f(pointer);
+            f(result);
+        }
+        Statement::Call {
+            arguments, result, ..
+        } => {
+            arguments.iter_mut().for_each(&mut *f);
+            opt(result, f);
+        }
+        Statement::RayQuery { query, fun } => {
+            f(query);
+            match fun {
+                RayQueryFunction::Initialize {
+                    acceleration_structure,
+                    descriptor,
+                } => {
+                    f(acceleration_structure);
+                    f(descriptor);
+                }
+                RayQueryFunction::Proceed { result } => f(result),
+                RayQueryFunction::GenerateIntersection { hit_t } => f(hit_t),
+                RayQueryFunction::ConfirmIntersection | RayQueryFunction::Terminate => {}
+            }
+        }
+        Statement::SubgroupBallot { result, predicate } => {
+            f(result);
+            opt(predicate, f);
+        }
+        Statement::SubgroupGather {
+            mode,
+            argument,
+            result,
+        } => {
+            match mode {
+                GatherMode::BroadcastFirst | GatherMode::QuadSwap(_) => {}
+                GatherMode::Broadcast(h)
+                | GatherMode::Shuffle(h)
+                | GatherMode::ShuffleDown(h)
+                | GatherMode::ShuffleUp(h)
+                | GatherMode::ShuffleXor(h)
+                | GatherMode::QuadBroadcast(h) => f(h),
+            }
+            f(argument);
+            f(result);
+        }
+        Statement::SubgroupCollectiveOperation {
+            argument, result, ..
+        } => {
+            f(argument);
+            f(result);
+        }
+    }
+}
+
+/// The blocks nested directly in `statement`.
+pub(crate) fn child_blocks(statement: &mut Statement) -> Vec<&mut Block> {
+    match statement {
+        Statement::Block(block) => vec![block],
+        Statement::If { accept, reject, .. } => vec![accept, reject],
+        Statement::Switch { cases, .. } => cases.iter_mut().map(|case| &mut case.body).collect(),
+        Statement::Loop {
+            body, continuing, ..
+        } => vec![body, continuing],
+        _ => Vec::new(),
+    }
+}
+
+/// Call `f` on every statement in `block`, nested ones included, parents
+/// before their children.
+pub(crate) fn for_each_statement(block: &mut Block, f: &mut impl FnMut(&mut Statement)) {
+    for statement in block.iter_mut() {
+        f(statement);
+        for child in child_blocks(statement) {
+            for_each_statement(child, f);
+        }
+    }
+}
+
+/// Every function body in the module, entry points last.
+pub(crate) fn functions_mut(
+    module: &mut naga::Module,
+) -> impl Iterator<Item = &mut naga::Function> {
+    module
+        .functions
+        .iter_mut()
+        .map(|(_, function)| function)
+        .chain(module.entry_points.iter_mut().map(|ep| &mut ep.function))
+}
+
+/// Drop the functions `keep` rejects, none of which may still be called,
+/// and renumber calls to the rest.
+pub(crate) fn retain_functions(
+    module: &mut naga::Module,
+    mut keep: impl FnMut(Handle<naga::Function>, &naga::Function) -> bool,
+) {
+    let mut arena = naga::Arena::new();
+    let mut renumbered = Vec::with_capacity(module.functions.len());
+    for (handle, function) in module.functions.iter() {
+        renumbered.push(
+            keep(handle, function)
+                .then(|| arena.append(function.clone(), module.functions.get_span(handle))),
+        );
+    }
+    module.functions = arena;
+
+    let map = |h: Handle<naga::Function>| {
+        renumbered[h.index()].expect("removed function is still called")
+    };
+    for function in functions_mut(module) {
+        for (_, expr) in function.expressions.iter_mut() {
+            if let Expression::CallResult(callee) = expr {
+                *callee = map(*callee);
+            }
+        }
+        for_each_statement(&mut function.body, &mut |statement| {
+            if let Statement::Call { function, .. } = statement {
+                *function = map(*function);
+            }
+        });
+    }
+}
+
+/// Split `Emit`s around expressions naga considers in scope from the start,
+/// such as literals a rewrite introduced, which must not be emitted.
+pub(crate) fn split_emits(block: &mut Block, expressions: &Arena<Expression>) {
+    let statements = std::mem::replace(block, Block::new());
+    for (mut statement, span) in statements.span_into_iter() {
+        if let Statement::Emit(ref range) = statement {
+            let mut run: Option<(Expr, Expr)> = None;
+            for h in range.clone() {
+                if expressions[h].needs_pre_emit() {
+                    if let Some((first, last)) = run.take() {
+                        block.push(Statement::Emit(Range::new_from_bounds(first, last)), span);
+                    }
+                } else {
+                    run = Some((run.map_or(h, |(first, _)| first), h));
+                }
+            }
+            if let Some((first, last)) = run {
+                block.push(Statement::Emit(Range::new_from_bounds(first, last)), span);
+            }
+            continue;
+        }
+        for child in child_blocks(&mut statement) {
+            split_emits(child, expressions);
+        }
+        block.push(statement, span);
+    }
+}
+
+/// Rebuild `function`'s expression arena in order. `f` gets each expression
+/// with its operands renumbered and returns its replacement, which may read
+/// new expressions `f` appends through `push`. These are emitted along with
+/// the expression they precede.
+pub(crate) fn rebuild_expressions(
+    function: &mut naga::Function,
+    mut f: impl FnMut(Expr, Expression, &mut dyn FnMut(Expression) -> Expr) -> Expression,
+) {
+    let old = std::mem::take(&mut function.expressions);
+    let mut arena = Arena::new();
+    // New handle of each old expression, and of the first one `f` added for it.
+    let mut map = Vec::with_capacity(old.len());
+    let mut first = Vec::with_capacity(old.len());
+    for (h, expr) in old.iter() {
+        let span = old.get_span(h);
+        let mut expr = expr.clone();
+        visit_operands(&mut expr, &mut |operand| *operand = map[operand.index()]);
+        let mut added = None;
+        let expr = f(h, expr, &mut |new| {
+            let new = arena.append(new, span);
+            added.get_or_insert(new);
+            new
+        });
+        let new = arena.append(expr, span);
+        map.push(new);
+        first.push(added.unwrap_or(new));
+    }
+
+    for_each_statement(&mut function.body, &mut |statement| {
+        if let Statement::Emit(range) = statement {
+            if let Some((start, end)) = range.first_and_last() {
+                *range = Range::new_from_bounds(first[start.index()], map[end.index()]);
+            }
+        } else {
+            visit_statement_operands(statement, &mut |h| *h = map[h.index()]);
+        }
+    });
+    for (_, local) in function.local_variables.iter_mut() {
+        if let Some(init) = &mut local.init {
+            *init = map[init.index()];
+        }
+    }
+    function.named_expressions = std::mem::take(&mut function.named_expressions)
+        .into_iter()
+        .map(|(h, name)| (map[h.index()], name))
+        .collect();
+    function.expressions = arena;
+    split_emits(&mut function.body, &function.expressions);
+}
+
+/// Calls to insert: for each expression to be a call's result, the callee
+/// and its arguments.
+pub(crate) type Calls = HashMap<Expr, (Handle<naga::Function>, Vec<Expr>)>;
+
+/// Split `Emit`s around the expressions in `calls`, which must already be
+/// `CallResult`s, calling each callee where its result used to be evaluated.
+pub(crate) fn insert_calls(block: &mut Block, calls: &Calls) {
+    let statements = std::mem::replace(block, Block::new());
+    for (mut statement, span) in statements.span_into_iter() {
+        if let Statement::Emit(ref range) = statement {
+            let mut run: Option<(Expr, Expr)> = None;
+            for h in range.clone() {
+                let Some((function, arguments)) = calls.get(&h) else {
+                    run = Some((run.map_or(h, |(first, _)| first), h));
+                    continue;
+                };
+                if let Some((first, last)) = run.take() {
+                    block.push(Statement::Emit(Range::new_from_bounds(first, last)), span);
+                }
+                let call = Statement::Call {
+                    function: *function,
+                    arguments: arguments.clone(),
+                    result: Some(h),
+                };
+                block.push(call, span);
+            }
+            if let Some((first, last)) = run {
+                block.push(Statement::Emit(Range::new_from_bounds(first, last)), span);
+            }
+            continue;
+        }
+        for child in child_blocks(&mut statement) {
+            insert_calls(child, calls);
+        }
+        block.push(statement, span);
+    }
+}
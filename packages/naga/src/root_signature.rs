@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+
+use naga::{AddressSpace, ImageClass, ShaderStage, TypeInner};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+/// Descriptors in each of naga's sampler heaps.
+const SAMPLER_HEAP_SIZE: u32 = 2048;
+/// Register space of the per-group sampler index buffers.
+const SAMPLER_INDEX_SPACE: u32 = 255;
+
+// ============================================================================
+// Root Signature Types
+// ============================================================================
+
+/// D3D12 root signature matching naga's default HLSL binding mapping:
+/// `@group(g) @binding(b)` becomes register `b`, `t` or `u` number `b` in
+/// `space g`, and samplers go through naga's sampler heaps.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RootSignature {
+    /// Root signature flags, e.g. `ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT`.
+    #[wasm_bindgen(readonly)]
+    pub flags: Vec<String>,
+    /// One descriptor table per parameter, in root parameter order.
+    #[wasm_bindgen(readonly)]
+    pub parameters: Vec<RootParameter>,
+    /// The same signature in HLSL root signature language, for a
+    /// `[RootSignature("...")]` attribute or `#define`.
+    #[wasm_bindgen(readonly)]
+    pub hlsl: String,
+}
+
+#[wasm_bindgen]
+impl RootSignature {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Pretty-printed JSON, for writing next to the HLSL.
+    #[wasm_bindgen(js_name = toJsonString)]
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(self).expect("root signature is always serializable")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RootParameter {
+    /// Bind group the table covers, unset for the sampler heaps.
+    #[wasm_bindgen(readonly)]
+    pub group: Option<u32>,
+    /// `all`, `vertex`, `pixel` or `mesh`, after D3D12 shader visibility.
+    #[wasm_bindgen(readonly)]
+    pub visibility: String,
+    #[wasm_bindgen(readonly)]
+    pub ranges: Vec<DescriptorRange>,
+}
+
+#[wasm_bindgen]
+impl RootParameter {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DescriptorRange {
+    /// `CBV`, `SRV`, `UAV` or `Sampler`.
+    #[wasm_bindgen(readonly)]
+    pub range_type: String,
+    #[wasm_bindgen(readonly)]
+    pub base_register: u32,
+    #[wasm_bindgen(readonly)]
+    pub space: u32,
+    /// Unset for unbounded binding arrays.
+    #[wasm_bindgen(readonly)]
+    pub count: Option<u32>,
+    /// WGSL variable the range is for, unset for naga's own resources.
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+    /// Render graph resource the variable is annotated with, if any.
+    #[wasm_bindgen(readonly)]
+    pub resource_name: Option<String>,
+}
+
+#[wasm_bindgen]
+impl DescriptorRange {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Root Signature Derivation
+// ============================================================================
+
+/// Stage bits, to combine the stages that see a group.
+fn stage_bit(stage: ShaderStage) -> u8 {
+    match stage {
+        ShaderStage::Vertex => 1,
+        ShaderStage::Fragment => 2,
+        ShaderStage::Compute => 4,
+        ShaderStage::Task => 8,
+        ShaderStage::Mesh => 16,
+    }
+}
+
+fn visibility(stages: u8) -> &'static str {
+    match stages {
+        1 => "vertex",
+        2 => "pixel",
+        16 => "mesh",
+        _ => "all",
+    }
+}
+
+/// Build the root signature for `entry_point`, or for every entry point.
+pub(crate) fn root_signature(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+) -> Result<RootSignature, Diagnostic> {
+    let entry_points: Vec<usize> = match entry_point.filter(|name| !name.is_empty()) {
+        Some(name) => vec![
+            module
+                .entry_points
+                .iter()
+                .position(|ep| ep.name == name)
+                .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", name)))?,
+        ],
+        None => (0..module.entry_points.len()).collect(),
+    };
+
+    // Per group: stage bits and ranges.
+    let mut groups = BTreeMap::<u32, (u8, Vec<DescriptorRange>)>::new();
+    let (mut samplers, mut comparison_samplers) = (false, false);
+    for (handle, var) in module.global_variables.iter() {
+        let Some(binding) = &var.binding else {
+            continue;
+        };
+        let stages = entry_points
+            .iter()
+            .filter(|i| !info.get_entry_point(**i)[handle].is_empty())
+            .fold(0, |bits, i| bits | stage_bit(module.entry_points[*i].stage));
+        if stages == 0 {
+            continue;
+        }
+
+        let (inner, count) = match module.types[var.ty].inner {
+            TypeInner::BindingArray { base, size } => (
+                &module.types[base].inner,
+                match size {
+                    naga::ArraySize::Constant(n) => Some(n.get()),
+                    _ => None,
+                },
+            ),
+            ref inner => (inner, Some(1)),
+        };
+        let range_type = match (var.space, inner) {
+            (AddressSpace::Uniform, _) => "CBV",
+            (AddressSpace::Storage { access }, _)
+                if access.contains(naga::StorageAccess::STORE) =>
+            {
+                "UAV"
+            }
+            (AddressSpace::Storage { .. }, _) => "SRV",
+            (_, TypeInner::Sampler { comparison }) => {
+                if *comparison {
+                    comparison_samplers = true;
+                } else {
+                    samplers = true;
+                }
+                // The sampler itself is an index into the group's index buffer.
+                let (group_stages, ranges) = groups.entry(binding.group).or_default();
+                *group_stages |= stages;
+                let index_buffer = DescriptorRange {
+                    range_type: "SRV".to_string(),
+                    base_register: binding.group,
+                    space: SAMPLER_INDEX_SPACE,
+                    count: Some(1),
+                    name: None,
+                    resource_name: None,
+                };
+                if !ranges.contains(&index_buffer) {
+                    ranges.push(index_buffer);
+                }
+                continue;
+            }
+            (
+                _,
+                TypeInner::Image {
+                    class: ImageClass::Storage { .. },
+                    ..
+                },
+            ) => "UAV",
+            _ => "SRV",
+        };
+
+        let (group_stages, ranges) = groups.entry(binding.group).or_default();
+        *group_stages |= stages;
+        ranges.push(DescriptorRange {
+            range_type: range_type.to_string(),
+            base_register: binding.binding,
+            space: binding.group,
+            count,
+            name: var.name.clone(),
+            resource_name: None,
+        });
+    }
+
+    let mut parameters: Vec<RootParameter> = groups
+        .into_iter()
+        .map(|(group, (stages, mut ranges))| {
+            ranges.sort_by_key(|r| (r.space, r.base_register));
+            RootParameter {
+                group: Some(group),
+                visibility: visibility(stages).to_string(),
+                ranges,
+            }
+        })
+        .collect();
+    for (used, space) in [(samplers, 0), (comparison_samplers, 1)] {
+        if used {
+            parameters.push(RootParameter {
+                group: None,
+                visibility: "all".to_string(),
+                ranges: vec![DescriptorRange {
+                    range_type: "Sampler".to_string(),
+                    base_register: 0,
+                    space,
+                    count: Some(SAMPLER_HEAP_SIZE),
+                    name: None,
+                    resource_name: None,
+                }],
+            });
+        }
+    }
+
+    let mut flags = Vec::new();
+    if entry_points
+        .iter()
+        .any(|i| module.entry_points[*i].stage == ShaderStage::Vertex)
+    {
+        flags.push("ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT".to_string());
+    }
+
+    let hlsl = hlsl_root_signature(&flags, &parameters);
+    Ok(RootSignature {
+        flags,
+        parameters,
+        hlsl,
+    })
+}
+
+/// Write the signature in HLSL root signature language.
+fn hlsl_root_signature(flags: &[String], parameters: &[RootParameter]) -> String {
+    let mut parts = Vec::new();
+    if !flags.is_empty() {
+        parts.push(format!("RootFlags({})", flags.join(" | ")));
+    }
+    for parameter in parameters {
+        let mut ranges: Vec<String> = parameter
+            .ranges
+            .iter()
+            .map(|r| {
+                let register = match r.range_type.as_str() {
+                    "CBV" => 'b',
+                    "UAV" => 'u',
+                    "Sampler" => 's',
+                    _ => 't',
+                };
+                let count = match r.count {
+                    Some(1) => String::new(),
+                    Some(n) => format!(", numDescriptors = {n}"),
+                    None => ", numDescriptors = unbounded".to_string(),
+                };
+                format!(
+                    "{}({}{}, space = {}{})",
+                    r.range_type, register, r.base_register, r.space, count
+                )
+            })
+            .collect();
+        if parameter.visibility != "all" {
+            ranges.push(format!(
+                "visibility = SHADER_VISIBILITY_{}",
+                parameter.visibility.to_uppercase()
+            ));
+        }
+        parts.push(format!("DescriptorTable({})", ranges.join(", ")));
+    }
+    parts.join(", ")
+}
+
+/// Derives a D3D12 root signature from a shader's bindings, consistent with
+/// the register assignment of naga's HLSL backend. Pass an entry point to
+/// cover only the bindings it uses. `hlsl` holds the root signature string;
+/// `toJsonString()` gives the serialized description. `resourceNames`, as
+/// for `reflectWgsl`, sets each range's `resourceName`.
+#[wasm_bindgen(js_name = wgslToRootSignature)]
+pub fn wgsl_to_root_signature(
+    wgsl: &str,
+    entry_point: Option<String>,
+    resource_names: JsValue,
+) -> Result<RootSignature, JsValue> {
+    let names = crate::resources::resource_names(resource_names)?;
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    names.check(&module).map_err(throw)?;
+    let mut signature = root_signature(&module, &info, entry_point.as_deref()).map_err(throw)?;
+    names.annotate_root_signature(&mut signature);
+    Ok(signature)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Camera { view_proj: mat4x4<f32> }
+
+        @group(0) @binding(0) var<uniform> camera: Camera;
+        @group(1) @binding(0) var albedo: texture_2d<f32>;
+        @group(1) @binding(1) var albedo_sampler: sampler;
+        @group(2) @binding(0) var<storage, read_write> counters: array<atomic<u32>>;
+
+        @vertex
+        fn vs(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+            return camera.view_proj * vec4<f32>(position, 1.0);
+        }
+
+        @fragment
+        fn fs() -> @location(0) vec4<f32> {
+            atomicAdd(&counters[0], 1u);
+            return textureSample(albedo, albedo_sampler, vec2<f32>(0.5));
+        }
+    "#;
+
+    fn signature(entry_point: Option<&str>) -> RootSignature {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        root_signature(&module, &info, entry_point).unwrap()
+    }
+
+    #[test]
+    fn tables_follow_groups() {
+        let signature = signature(None);
+        let tables: Vec<_> = signature
+            .parameters
+            .iter()
+            .map(|p| (p.group, p.visibility.as_str(), p.ranges.len()))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![
+                (Some(0), "vertex", 1),
+                (Some(1), "pixel", 2),
+                (Some(2), "pixel", 1),
+                (None, "all", 1),
+            ]
+        );
+        assert_eq!(signature.parameters[2].ranges[0].range_type, "UAV");
+        assert_eq!(signature.flags, vec!["ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT"]);
+    }
+
+    #[test]
+    fn hlsl_string_matches_the_tables() {
+        let hlsl = signature(None).hlsl;
+        assert!(hlsl.starts_with("RootFlags(ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT), "));
+        assert!(hlsl.contains(
+            "DescriptorTable(CBV(b0, space = 0), visibility = SHADER_VISIBILITY_VERTEX)"
+        ));
+        assert!(hlsl.contains("SRV(t1, space = 255)"));
+        assert!(hlsl.contains("DescriptorTable(Sampler(s0, space = 0, numDescriptors = 2048))"));
+    }
+
+    #[test]
+    fn entry_point_limits_the_tables() {
+        let signature = signature(Some("vs"));
+        assert_eq!(signature.parameters.len(), 1);
+        assert_eq!(
+            signature.parameters[0].ranges[0].name.as_deref(),
+            Some("camera")
+        );
+    }
+}
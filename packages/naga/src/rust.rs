@@ -0,0 +1,349 @@
+use std::fmt::Write;
+
+use naga::proc::Layouter;
+use naga::{ArraySize, Handle, Scalar, ScalarKind, Type, TypeInner, VectorSize};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::layout::{PaddedField, layouter, padded_struct};
+use crate::typescript::{buffer_structs, struct_name};
+
+// ============================================================================
+// Rust Codegen Types
+// ============================================================================
+
+/// How generated structs get their layout.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RustStyle {
+    /// `#[repr(C)]` with explicit padding fields, deriving `bytemuck::Pod`.
+    #[default]
+    Bytemuck,
+    /// `#[derive(encase::ShaderType)]` over glam types; encase pads them.
+    Encase,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RustOptions {
+    #[serde(default)]
+    pub style: RustStyle,
+}
+
+/// Rust mirrors of a shader's buffer structs, from `generateRust`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct RustBindings {
+    /// A Rust module: one struct per buffer struct, in declaration order.
+    #[wasm_bindgen(readonly)]
+    pub code: String,
+    #[wasm_bindgen(readonly)]
+    pub structs: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl RustBindings {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Rust Codegen Implementation
+// ============================================================================
+//
+// The structs covered are the ones `generateTypeScript` covers. In the
+// bytemuck style, vectors and matrix columns are arrays, with vec3 columns
+// and vec3 array elements widened to four components as the GPU strides
+// them; gaps before members and at the end become `_padN: [u8; N]` fields,
+// and const asserts pin every offset and size to naga's layouter, so a
+// drifting copy fails to compile rather than misrenders. Rust has no stable
+// f16, so f16 values are their `u16` bits. A runtime-sized array cannot be
+// a field: the struct stops at its offset, for `bytemuck::bytes_of` of the
+// head followed by `cast_slice` of the elements. The encase style leaves
+// layout to encase and only needs types it understands.
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+fn field_name(name: Option<&str>) -> String {
+    match name.unwrap_or("_") {
+        name if KEYWORDS.contains(&name) => format!("r#{name}"),
+        name => name.to_string(),
+    }
+}
+
+fn scalar(scalar: Scalar) -> &'static str {
+    match (scalar.kind, scalar.width) {
+        (ScalarKind::Float, 2) => "u16",
+        (ScalarKind::Float, 8) => "f64",
+        (ScalarKind::Float, _) => "f32",
+        (ScalarKind::Sint, 8) => "i64",
+        (ScalarKind::Sint, _) => "i32",
+        (ScalarKind::Uint, 8) => "u64",
+        (_, _) => "u32",
+    }
+}
+
+/// The bytemuck-style type of `ty`; `element` when it is an array element,
+/// which widens vec3s to their stride.
+fn pod_type(module: &naga::Module, ty: Handle<Type>, element: bool) -> String {
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) => scalar(s).to_string(),
+        TypeInner::Vector { size, scalar: s } => {
+            let size = match (size, element) {
+                (VectorSize::Tri, true) => 4,
+                (size, _) => size as u32,
+            };
+            format!("[{}; {size}]", scalar(s))
+        }
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar: s,
+        } => {
+            let rows = match rows {
+                VectorSize::Tri => 4,
+                rows => rows as u32,
+            };
+            format!("[[{}; {rows}]; {}]", scalar(s), columns as u32)
+        }
+        TypeInner::Array { base, size, .. } => {
+            let base = pod_type(module, base, true);
+            match size {
+                ArraySize::Constant(count) => format!("[{base}; {count}]"),
+                _ => base,
+            }
+        }
+        _ => struct_name(module, ty),
+    }
+}
+
+/// The encase-style type of `ty`, or why encase cannot hold it.
+fn encase_type(module: &naga::Module, ty: Handle<Type>) -> Result<String, Diagnostic> {
+    let unsupported = || {
+        let name = crate::get_type_name(module, ty).unwrap_or_default();
+        Diagnostic::error(format!(
+            "encase has no type for {name}; use the bytemuck style"
+        ))
+    };
+    let prefix = |s: Scalar| match (s.kind, s.width) {
+        (ScalarKind::Float, 4) => Some(""),
+        (ScalarKind::Sint, 4) => Some("I"),
+        (ScalarKind::Uint, 4) => Some("U"),
+        _ => None,
+    };
+    Ok(match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) => {
+            prefix(s).ok_or_else(unsupported)?;
+            scalar(s).to_string()
+        }
+        TypeInner::Vector { size, scalar: s } => {
+            let prefix = prefix(s).ok_or_else(unsupported)?;
+            format!("glam::{prefix}Vec{}", size as u32)
+        }
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar: s,
+        } if columns == rows && s.width == 4 => format!("glam::Mat{}", columns as u32),
+        TypeInner::Array { base, size, .. } => {
+            let base = encase_type(module, base)?;
+            match size {
+                ArraySize::Constant(count) => format!("[{base}; {count}]"),
+                _ => format!("Vec<{base}>"),
+            }
+        }
+        TypeInner::Struct { .. } => struct_name(module, ty),
+        _ => return Err(unsupported()),
+    })
+}
+
+fn pod_struct(
+    out: &mut String,
+    module: &naga::Module,
+    layouter: &Layouter,
+    ty: Handle<Type>,
+) -> Result<(), Diagnostic> {
+    let name = struct_name(module, ty);
+    let padded = padded_struct(module, layouter, ty);
+    let mut fields = String::new();
+    let mut asserts = String::new();
+    let mut padding = 0;
+    for field in &padded.fields {
+        let member = match *field {
+            PaddedField::Padding(bytes) => {
+                let _ = writeln!(fields, "    pub _pad{padding}: [u8; {bytes}],");
+                padding += 1;
+                continue;
+            }
+            PaddedField::Member(member) => member,
+        };
+        let field = field_name(member.name.as_deref());
+        let ty = pod_type(module, member.ty, false);
+        if let TypeInner::Scalar(s) | TypeInner::Vector { scalar: s, .. } =
+            module.types[member.ty].inner
+            && s.kind == ScalarKind::Float
+            && s.width == 2
+        {
+            let _ = writeln!(fields, "    /// f16 bits.");
+        }
+        let _ = writeln!(fields, "    pub {field}: {ty},");
+        let _ = writeln!(
+            asserts,
+            "const _: () = assert!(core::mem::offset_of!({name}, {field}) == {});",
+            member.offset
+        );
+    }
+
+    let _ = writeln!(out, "/// `{name}` as a uniform or storage buffer holds it.");
+    if let Some(member) = padded.tail
+        && let TypeInner::Array { base, stride, .. } = module.types[member.ty].inner
+    {
+        let _ = writeln!(out, "///");
+        let _ = writeln!(
+            out,
+            "/// The runtime-sized `{}` follows, one `{}` every {stride} bytes.",
+            field_name(member.name.as_deref()),
+            pod_type(module, base, true)
+        );
+    }
+    let _ = writeln!(out, "#[repr(C)]");
+    let _ = writeln!(
+        out,
+        "#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]"
+    );
+    let _ = writeln!(out, "pub struct {name} {{");
+    out.push_str(&fields);
+    let _ = writeln!(out, "}}");
+    let _ = writeln!(
+        out,
+        "const _: () = assert!(core::mem::size_of::<{name}>() == {});",
+        padded.size
+    );
+    out.push_str(&asserts);
+    Ok(())
+}
+
+fn encase_struct(
+    out: &mut String,
+    module: &naga::Module,
+    ty: Handle<Type>,
+) -> Result<(), Diagnostic> {
+    let TypeInner::Struct { ref members, .. } = module.types[ty].inner else {
+        return Ok(());
+    };
+    let name = struct_name(module, ty);
+    let _ = writeln!(out, "/// `{name}`, laid out by encase.");
+    let _ = writeln!(out, "#[derive(Clone, Debug, encase::ShaderType)]");
+    let _ = writeln!(out, "pub struct {name} {{");
+    for member in members {
+        if let TypeInner::Array {
+            size: ArraySize::Dynamic,
+            ..
+        } = module.types[member.ty].inner
+        {
+            let _ = writeln!(out, "    #[size(runtime)]");
+        }
+        let field = field_name(member.name.as_deref());
+        let _ = writeln!(out, "    pub {field}: {},", encase_type(module, member.ty)?);
+    }
+    let _ = writeln!(out, "}}");
+    Ok(())
+}
+
+pub(crate) fn generate(
+    module: &naga::Module,
+    options: &RustOptions,
+) -> Result<RustBindings, Diagnostic> {
+    let layouter = layouter(module)?;
+    let structs = buffer_structs(module);
+    let mut code = String::new();
+    for (index, &ty) in structs.iter().enumerate() {
+        if index > 0 {
+            code.push('\n');
+        }
+        match options.style {
+            RustStyle::Bytemuck => pod_struct(&mut code, module, &layouter, ty)?,
+            RustStyle::Encase => encase_struct(&mut code, module, ty)?,
+        }
+    }
+    Ok(RustBindings {
+        code,
+        structs: structs.iter().map(|&ty| struct_name(module, ty)).collect(),
+    })
+}
+
+/// Rust mirrors of every buffer-storable struct in `wgsl`, for the native
+/// side of an engine. `options` is `{ style?: "bytemuck" | "encase" }`:
+/// `"bytemuck"`, the default, emits `#[repr(C)]` structs with explicit
+/// padding fields and const asserts of every offset; `"encase"` emits
+/// `encase::ShaderType` derives over glam types.
+#[wasm_bindgen(js_name = generateRust)]
+pub fn generate_rust(wgsl: &str, options: JsValue) -> Result<RustBindings, JsValue> {
+    let options: Option<RustOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid Rust options: {e}")))?;
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    generate(&module, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Uniforms {
+            view: mat4x4<f32>,
+            normal: mat3x3<f32>,
+            light: Light,
+            frame: u32,
+        }
+        struct Particles { count: atomic<u32>, positions: array<vec3<f32>> }
+        @group(0) @binding(0) var<uniform> uniforms: Uniforms;
+        @group(0) @binding(1) var<storage, read_write> particles: Particles;
+    "#;
+
+    #[test]
+    fn pod_structs_pad_to_wgsl_offsets() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let code = generate(&module, &RustOptions::default()).unwrap().code;
+        assert!(code.contains("    pub normal: [[f32; 4]; 3],\n"));
+        assert!(code.contains("    pub frame: u32,\n    pub _pad0: [u8; 12],\n}"));
+        assert!(code.contains("assert!(core::mem::size_of::<Uniforms>() == 144);"));
+        assert!(code.contains("assert!(core::mem::offset_of!(Uniforms, light) == 112);"));
+        // The head stops at the runtime-sized array, padded to its offset.
+        assert!(code.contains("    pub count: u32,\n    pub _pad0: [u8; 12],\n}"));
+        assert!(code.contains("one `[f32; 4]` every 16 bytes"));
+        assert!(code.contains("assert!(core::mem::size_of::<Particles>() == 16);"));
+    }
+
+    #[test]
+    fn encase_structs_use_glam_types() {
+        let module = crate::parse_wgsl(SHADER).unwrap();
+        let options = RustOptions {
+            style: RustStyle::Encase,
+        };
+        let code = generate(&module, &options).unwrap().code;
+        assert!(code.contains("    pub color: glam::Vec3,\n"));
+        assert!(code.contains("    pub normal: glam::Mat3,\n"));
+        assert!(code.contains("    #[size(runtime)]\n    pub positions: Vec<glam::Vec3>,\n"));
+
+        let module = crate::parse_wgsl("struct S { m: mat2x3<f32> }").unwrap();
+        let error = generate(&module, &options).err().unwrap();
+        assert!(error.message.contains("mat2x3f"));
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+
+use naga::proc::{BoundsCheckPolicies, BoundsCheckPolicy};
+use naga::{AddressSpace, Barrier, BinaryOperator, Block, Expression, Handle, Literal, Statement};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::bounds::{self, InjectedCheck};
+use crate::diagnostics::{SourceSpan, throw};
+use crate::rewrite;
+
+// ============================================================================
+// Safety Audit Types
+// ============================================================================
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SafetyAudit {
+    /// Accesses that are not provably in bounds, so only bounds-check
+    /// policies keep them in range.
+    #[wasm_bindgen(readonly)]
+    pub out_of_bounds: Vec<AuditFinding>,
+    /// Loops without a counter running to a constant bound.
+    #[wasm_bindgen(readonly)]
+    pub unbounded_loops: Vec<AuditFinding>,
+    /// Atomics on storage or workgroup memory, which other invocations see.
+    #[wasm_bindgen(readonly)]
+    pub shared_atomics: Vec<AuditFinding>,
+    /// Barriers every invocation may not reach, or with nothing to order.
+    #[wasm_bindgen(readonly)]
+    pub barriers: Vec<AuditFinding>,
+    /// Estimated worst-case cost per entry point.
+    #[wasm_bindgen(readonly)]
+    pub costs: Vec<InvocationCost>,
+}
+
+#[wasm_bindgen]
+impl SafetyAudit {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct AuditFinding {
+    #[wasm_bindgen(readonly)]
+    pub function: String,
+    /// The function's `id` in reflection.
+    #[wasm_bindgen(readonly)]
+    pub function_id: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// Variable involved, if any.
+    #[wasm_bindgen(readonly)]
+    pub resource: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub span: Option<SourceSpan>,
+}
+
+#[wasm_bindgen]
+impl AuditFinding {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct InvocationCost {
+    /// The entry point's `id` in reflection.
+    #[wasm_bindgen(readonly)]
+    pub id: String,
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    #[wasm_bindgen(readonly)]
+    pub stage: String,
+    /// Operations one invocation executes on its most expensive path, with
+    /// texture samples weighing more than arithmetic; saturates at
+    /// `u32::MAX`. `None` if an unbounded loop is reachable.
+    #[wasm_bindgen(readonly)]
+    pub cost: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl InvocationCost {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Safety Audit Implementation
+// ============================================================================
+//
+// Every check is static and conservative: a finding means the audit could
+// not prove the code harmless, not that it misbehaves. A loop counts as
+// bounded only in the shape `for` loops lower to: a guard comparing a local
+// counter with a constant, the counter starting at a constant and stepped by
+// a constant in the continuing block. Costs walk each function's statements,
+// taking the dearer branch of every `if` and `switch`, multiplying loop
+// bodies by their trip counts and adding callees' costs at calls; callees
+// precede callers in the arena, so each is costed before use.
+
+/// Rough cost of evaluating `expr`, in arithmetic operations.
+fn expression_cost(expr: &Expression) -> u64 {
+    match *expr {
+        Expression::ImageSample { .. } => 8,
+        Expression::ImageLoad { .. } | Expression::ImageQuery { .. } => 4,
+        Expression::Derivative { .. } => 2,
+        _ => 1,
+    }
+}
+
+fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    Some(a?.saturating_add(b?))
+}
+
+/// Value of an integer constant expression written as a literal or a
+/// `const`.
+fn constant_value(
+    module: &naga::Module,
+    function: &naga::Function,
+    expr: Handle<Expression>,
+) -> Option<i64> {
+    let literal = match function.expressions[expr] {
+        Expression::Literal(literal) => literal,
+        Expression::Constant(c) => match module.global_expressions[module.constants[c].init] {
+            Expression::Literal(literal) => literal,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match literal {
+        Literal::I32(v) => Some(v.into()),
+        Literal::U32(v) => Some(v.into()),
+        Literal::I64(v) | Literal::AbstractInt(v) => Some(v),
+        Literal::U64(v) => i64::try_from(v).ok(),
+        _ => None,
+    }
+}
+
+/// The local variable `expr` loads, if it is a load of one.
+fn loaded_local(
+    function: &naga::Function,
+    expr: Handle<Expression>,
+) -> Option<Handle<naga::LocalVariable>> {
+    let Expression::Load { pointer } = function.expressions[expr] else {
+        return None;
+    };
+    match function.expressions[pointer] {
+        Expression::LocalVariable(v) => Some(v),
+        _ => None,
+    }
+}
+
+/// How many times a loop runs, or why that is not known.
+fn trip_count(
+    module: &naga::Module,
+    function: &naga::Function,
+    body: &Block,
+    continuing: &Block,
+    break_if: Option<Handle<Expression>>,
+) -> Result<u64, &'static str> {
+    let guard = body.iter().find(|s| !matches!(s, Statement::Emit(_)));
+    let condition = match guard {
+        Some(Statement::If {
+            condition,
+            accept,
+            reject,
+        }) if break_if.is_none()
+            && accept.is_empty()
+            && reject.len() == 1
+            && matches!(reject.iter().next(), Some(Statement::Break)) =>
+        {
+            *condition
+        }
+        _ => return Err("it only exits through a break or return"),
+    };
+    let Expression::Binary { op, left, right } = function.expressions[condition] else {
+        return Err("its condition is not a comparison");
+    };
+    let (Some(counter), Some(bound)) = (
+        loaded_local(function, left),
+        constant_value(module, function, right),
+    ) else {
+        return Err("its condition does not compare a counter with a constant");
+    };
+    let start = function.local_variables[counter]
+        .init
+        .and_then(|init| constant_value(module, function, init))
+        .ok_or("its counter does not start at a constant")?;
+
+    let mut step = None;
+    for statement in continuing.iter() {
+        let Statement::Store { pointer, value } = *statement else {
+            continue;
+        };
+        if !matches!(function.expressions[pointer], Expression::LocalVariable(v) if v == counter) {
+            continue;
+        }
+        step = match function.expressions[value] {
+            Expression::Binary {
+                op: op @ (BinaryOperator::Add | BinaryOperator::Subtract),
+                left,
+                right,
+            } if loaded_local(function, left) == Some(counter) => {
+                constant_value(module, function, right)
+                    .map(|n| if op == BinaryOperator::Add { n } else { -n })
+            }
+            _ => None,
+        };
+    }
+    let step = step
+        .filter(|&n| n != 0)
+        .ok_or("its counter is not stepped by a constant")?;
+    let mut assigned = false;
+    rewrite::for_each_statement(&mut body.clone(), &mut |statement| {
+        if let Statement::Store { pointer, .. } = *statement
+            && matches!(function.expressions[pointer], Expression::LocalVariable(v) if v == counter)
+        {
+            assigned = true;
+        }
+    });
+    if assigned {
+        return Err("its counter is also assigned in the body");
+    }
+
+    // Iterations of `counter` from `start` by `step` before the guard fails.
+    let iterations = |distance: i64| -> Result<u64, &'static str> {
+        if distance <= 0 {
+            return Ok(0);
+        }
+        if (step > 0) != (bound > start) && bound != start {
+            return Err("its counter moves away from the bound");
+        }
+        Ok((distance as u64).div_ceil(step.unsigned_abs()))
+    };
+    match op {
+        BinaryOperator::Less => iterations(bound - start),
+        BinaryOperator::LessEqual => iterations(bound - start + 1),
+        BinaryOperator::Greater => iterations(start - bound),
+        BinaryOperator::GreaterEqual => iterations(start - bound + 1),
+        BinaryOperator::NotEqual if (bound - start) % step == 0 && (bound - start) / step >= 0 => {
+            Ok(((bound - start) / step) as u64)
+        }
+        BinaryOperator::NotEqual => Err("its counter can step past the bound"),
+        _ => Err("its condition is not an ordering comparison"),
+    }
+}
+
+fn barrier_name(barrier: Barrier) -> &'static str {
+    if barrier.contains(Barrier::WORK_GROUP) {
+        "workgroupBarrier"
+    } else if barrier.contains(Barrier::STORAGE) {
+        "storageBarrier"
+    } else if barrier.contains(Barrier::TEXTURE) {
+        "textureBarrier"
+    } else {
+        "subgroupBarrier"
+    }
+}
+
+struct Auditor<'a> {
+    source: &'a str,
+    module: &'a naga::Module,
+    function: &'a naga::Function,
+    name: &'a str,
+    /// Costs of the functions audited so far.
+    costs: &'a HashMap<Handle<naga::Function>, Option<u64>>,
+    audit: &'a mut SafetyAudit,
+}
+
+impl Auditor<'_> {
+    fn finding(&self, message: String, resource: Option<String>, span: naga::Span) -> AuditFinding {
+        AuditFinding {
+            function: self.name.to_string(),
+            function_id: crate::function_id(self.module, self.name),
+            message,
+            resource,
+            span: SourceSpan::new(self.source, span),
+        }
+    }
+
+    /// The global `pointer` points into, following accesses.
+    fn root_global(&self, mut pointer: Handle<Expression>) -> Option<&naga::GlobalVariable> {
+        loop {
+            match self.function.expressions[pointer] {
+                Expression::Access { base, .. } | Expression::AccessIndex { base, .. } => {
+                    pointer = base
+                }
+                Expression::GlobalVariable(h) => return Some(&self.module.global_variables[h]),
+                _ => return None,
+            }
+        }
+    }
+
+    fn atomic(&mut self, pointer: Handle<Expression>, span: naga::Span) {
+        let Some(global) = self.root_global(pointer) else {
+            return;
+        };
+        let visibility = match global.space {
+            AddressSpace::Storage { .. } => "every invocation of the dispatch",
+            AddressSpace::WorkGroup => "the whole workgroup",
+            _ => return,
+        };
+        let message = format!("Atomic operation visible to {}", visibility);
+        let finding = self.finding(message, global.name.clone(), span);
+        self.audit.shared_atomics.push(finding);
+    }
+
+    fn barrier(&mut self, barrier: Barrier, nested: bool, span: naga::Span) {
+        let name = barrier_name(barrier);
+        let globals = || self.module.global_variables.iter().map(|(_, g)| g);
+        if nested {
+            let message = format!(
+                "{} in conditional control flow; every invocation must reach it",
+                name
+            );
+            self.audit.barriers.push(self.finding(message, None, span));
+        }
+        let unordered = if barrier.contains(Barrier::WORK_GROUP) {
+            !globals().any(|g| g.space == AddressSpace::WorkGroup)
+        } else if barrier.contains(Barrier::STORAGE) {
+            !globals().any(|g| matches!(g.space, AddressSpace::Storage { .. }))
+        } else {
+            false
+        };
+        if unordered {
+            let message = format!("{} has no shared memory to order", name);
+            self.audit.barriers.push(self.finding(message, None, span));
+        }
+    }
+
+    fn emit(&self, range: &naga::Range<Expression>) -> Option<u64> {
+        Some(
+            range
+                .clone()
+                .map(|h| expression_cost(&self.function.expressions[h]))
+                .sum(),
+        )
+    }
+
+    /// Audit `block`, returning its worst-case cost. `nested` is whether it
+    /// runs conditionally.
+    fn block(&mut self, block: &Block, nested: bool) -> Option<u64> {
+        let mut cost = Some(0);
+        for (statement, span) in block.span_iter() {
+            let span = *span;
+            let statement_cost = match *statement {
+                Statement::Emit(ref range) => self.emit(range),
+                Statement::Block(ref inner) => self.block(inner, nested),
+                Statement::If {
+                    ref accept,
+                    ref reject,
+                    ..
+                } => {
+                    let accept = self.block(accept, true);
+                    let reject = self.block(reject, true);
+                    add(Some(1), accept.zip(reject).map(|(a, b)| a.max(b)))
+                }
+                Statement::Switch { ref cases, .. } => {
+                    let mut worst = Some(0);
+                    for case in cases {
+                        let cost = self.block(&case.body, true);
+                        worst = worst.zip(cost).map(|(a, b)| a.max(b));
+                    }
+                    add(Some(1), worst)
+                }
+                Statement::Loop {
+                    ref body,
+                    ref continuing,
+                    break_if,
+                } => {
+                    let iteration = add(self.block(body, true), self.block(continuing, true));
+                    match trip_count(self.module, self.function, body, continuing, break_if) {
+                        Ok(trips) => iteration.map(|c| c.saturating_mul(trips)),
+                        Err(reason) => {
+                            let message = format!("Loop may not terminate: {}", reason);
+                            let finding = self.finding(message, None, span);
+                            self.audit.unbounded_loops.push(finding);
+                            None
+                        }
+                    }
+                }
+                Statement::Call { function, .. } => {
+                    add(Some(1), self.costs.get(&function).copied().flatten())
+                }
+                Statement::Atomic { pointer, .. } => {
+                    self.atomic(pointer, span);
+                    Some(4)
+                }
+                Statement::ImageAtomic { image, .. } => {
+                    self.atomic(image, span);
+                    Some(4)
+                }
+                Statement::ImageStore { .. } => Some(4),
+                Statement::ControlBarrier(barrier) | Statement::MemoryBarrier(barrier) => {
+                    self.barrier(barrier, nested, span);
+                    Some(1)
+                }
+                _ => Some(1),
+            };
+            cost = add(cost, statement_cost);
+        }
+        cost
+    }
+}
+
+fn out_of_bounds(check: InjectedCheck) -> AuditFinding {
+    let target = match check.resource {
+        Some(ref name) => format!("'{}'", name),
+        None => "a value".to_string(),
+    };
+    let message = match (check.kind.as_str(), check.length) {
+        ("image_load", _) => format!("Texel load from {} may be out of bounds", target),
+        (_, Some(length)) => format!(
+            "Index into {} may be out of bounds (length {})",
+            target, length
+        ),
+        (_, None) => format!("Index into runtime-sized {} may be out of bounds", target),
+    };
+    AuditFinding {
+        function: check.function,
+        function_id: check.function_id,
+        message,
+        resource: check.resource,
+        span: check.span,
+    }
+}
+
+fn audit(wgsl: &str) -> Result<SafetyAudit, Diagnostic> {
+    let (module, info) = crate::parse_and_validate(wgsl)?;
+    let mut audit = SafetyAudit::default();
+
+    let policies = BoundsCheckPolicies {
+        index: BoundsCheckPolicy::Restrict,
+        buffer: BoundsCheckPolicy::Restrict,
+        image_load: BoundsCheckPolicy::Restrict,
+        binding_array: BoundsCheckPolicy::Restrict,
+    };
+    audit.out_of_bounds = bounds::module_checks(wgsl, &module, &info, &policies)
+        .into_iter()
+        .map(out_of_bounds)
+        .collect();
+
+    let mut costs = HashMap::new();
+    for (handle, function) in module.functions.iter() {
+        let mut auditor = Auditor {
+            source: wgsl,
+            module: &module,
+            function,
+            name: function.name.as_deref().unwrap_or("?"),
+            costs: &costs,
+            audit: &mut audit,
+        };
+        let cost = auditor.block(&function.body, false);
+        costs.insert(handle, cost);
+    }
+    for ep in module.entry_points.iter() {
+        let mut auditor = Auditor {
+            source: wgsl,
+            module: &module,
+            function: &ep.function,
+            name: &ep.name,
+            costs: &costs,
+            audit: &mut audit,
+        };
+        let cost = auditor.block(&ep.function.body, false);
+        audit.costs.push(InvocationCost {
+            id: crate::function_id(&module, &ep.name),
+            entry_point: ep.name.clone(),
+            stage: crate::stage_name(ep.stage).to_string(),
+            cost: cost.map(|c| c.min(u32::MAX.into()) as u32),
+        });
+    }
+    Ok(audit)
+}
+
+/// Static safety report for running an untrusted shader: accesses that only
+/// bounds checks keep in range, loops that may not terminate, atomics on
+/// memory other invocations see, barriers that are conditional or order
+/// nothing, and an estimated worst-case cost per invocation of each entry
+/// point. Findings are conservative: code the audit cannot prove harmless is
+/// reported.
+#[wasm_bindgen(js_name = safetyAudit)]
+pub fn safety_audit(wgsl: &str) -> Result<SafetyAudit, JsValue> {
+    audit(wgsl).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDED: &str = r#"
+        const TAPS = 4u;
+        @group(0) @binding(0) var<storage, read_write> histogram: array<atomic<u32>, 16>;
+        var<workgroup> tile: array<f32, 64>;
+
+        fn weight(i: u32) -> f32 {
+            return f32(i) * 0.25;
+        }
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(local_invocation_index) li: u32) {
+            var sum = 0.0;
+            for (var i = 0u; i < TAPS; i++) {
+                sum += weight(i);
+            }
+            tile[li] = sum;
+            workgroupBarrier();
+            atomicAdd(&histogram[li % 16u], 1u);
+        }
+    "#;
+
+    #[test]
+    fn bounded_shaders_have_a_cost() {
+        let audit = audit(BOUNDED).unwrap();
+        assert!(audit.unbounded_loops.is_empty());
+        assert!(audit.barriers.is_empty());
+        let resources: Vec<_> = audit
+            .out_of_bounds
+            .iter()
+            .map(|f| f.resource.as_deref())
+            .collect();
+        assert_eq!(resources, [Some("tile"), Some("histogram")]);
+        assert_eq!(audit.shared_atomics.len(), 1);
+        assert_eq!(
+            audit.shared_atomics[0].resource.as_deref(),
+            Some("histogram")
+        );
+
+        // IDs match reflection's, so reports correlate with it.
+        let reflection = crate::reflect(BOUNDED).unwrap();
+        assert_eq!(audit.costs[0].id, reflection.entry_points[0].id);
+        assert_eq!(
+            audit.shared_atomics[0].function_id,
+            reflection.entry_points[0].id
+        );
+
+        let cost = audit.costs[0].cost.unwrap();
+        let once = audit_cost("for (var i = 0u; i < 1u; i++) { sum += weight(i); }");
+        let none = audit_cost("");
+        assert!(cost > once && once > none);
+    }
+
+    fn audit_cost(body: &str) -> u32 {
+        let source = format!(
+            "fn weight(i: u32) -> f32 {{ return f32(i) * 0.25; }}\n\
+             @compute @workgroup_size(1) fn main() {{ var sum = 0.0; {} }}",
+            body
+        );
+        audit(&source).unwrap().costs[0].cost.unwrap()
+    }
+
+    #[test]
+    fn unbounded_loops_and_stray_barriers_are_reported() {
+        let source = r#"
+            @group(0) @binding(0) var<uniform> count: u32;
+
+            @compute @workgroup_size(8)
+            fn main() {
+                var n = 0u;
+                for (var i = 0u; i < count; i++) {
+                    n += 1u;
+                }
+                loop {
+                    n -= 1u;
+                    if n == 0u { break; }
+                }
+                if count > 4u {
+                    workgroupBarrier();
+                }
+            }
+        "#;
+        let audit = audit(source).unwrap();
+        let messages: Vec<_> = audit
+            .unbounded_loops
+            .iter()
+            .map(|f| f.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Loop may not terminate: its condition does not compare a counter with a constant",
+                "Loop may not terminate: it only exits through a break or return",
+            ]
+        );
+        assert_eq!(audit.barriers.len(), 2);
+        assert!(audit.barriers[1].message.contains("no shared memory"));
+        assert_eq!(audit.costs[0].cost, None);
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::hash::sha256_hex;
+use crate::shader_module::ShaderModule;
+use crate::{Diagnostic, ReflectionData};
+
+// ============================================================================
+// Compiler Session Types
+// ============================================================================
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SessionStats {
+    /// Lookups answered from the cache.
+    #[wasm_bindgen(readonly)]
+    pub hits: u32,
+    /// Lookups that had to parse and validate.
+    #[wasm_bindgen(readonly)]
+    pub misses: u32,
+    /// Cached results, failures included.
+    #[wasm_bindgen(readonly)]
+    pub entries: u32,
+    /// Estimated memory the cached modules hold.
+    #[wasm_bindgen(readonly)]
+    pub bytes_held: u32,
+}
+
+#[wasm_bindgen]
+impl SessionStats {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Compiler Session
+// ============================================================================
+//
+// A session keeps every parsed and validated module it has seen, keyed by
+// the SHA-256 of the preset name and the source, so a hot-reload loop
+// recompiling files that did not change skips the front end entirely.
+// Failures are kept too: an unchanged broken file throws the same error
+// without being parsed again. Nothing is evicted; a long session watches
+// `stats().bytesHeld` and calls `clear()`.
+
+/// Parse and validate results memoized across compiles.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CompilerSession {
+    entries: HashMap<String, Entry>,
+    hits: u32,
+    misses: u32,
+}
+
+struct Entry {
+    result: Result<ShaderModule, Diagnostic>,
+    bytes: usize,
+}
+
+#[wasm_bindgen]
+impl CompilerSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CompilerSession {
+        CompilerSession::default()
+    }
+
+    /// Same as `validateWgsl`, validating against `preset` if given.
+    pub fn validate(&mut self, wgsl: &str, preset: Option<String>) -> Result<(), JsValue> {
+        self.module(wgsl, preset.as_deref())
+            .map(|_| ())
+            .map_err(throw)
+    }
+
+    /// Same as `ShaderModule.reflect`.
+    pub fn reflect(
+        &mut self,
+        wgsl: &str,
+        preset: Option<String>,
+    ) -> Result<ReflectionData, JsValue> {
+        let module = self.module(wgsl, preset.as_deref()).map_err(throw)?;
+        Ok(module.reflect())
+    }
+
+    /// Same as `ShaderModule.toSpirv`.
+    #[wasm_bindgen(js_name = toSpirv)]
+    pub fn spirv(
+        &mut self,
+        wgsl: &str,
+        entry_point: Option<String>,
+        preset: Option<String>,
+        options: JsValue,
+    ) -> Result<Box<[u8]>, JsValue> {
+        let module = self.module(wgsl, preset.as_deref()).map_err(throw)?;
+        module.to_spirv(entry_point, options)
+    }
+
+    /// Same as `ShaderModule.toMsl`.
+    #[wasm_bindgen(js_name = toMsl)]
+    pub fn msl(
+        &mut self,
+        wgsl: &str,
+        entry_point: Option<String>,
+        preset: Option<String>,
+    ) -> Result<String, JsValue> {
+        let module = self.module(wgsl, preset.as_deref()).map_err(throw)?;
+        module.to_msl(entry_point)
+    }
+
+    /// Drop every cached module and reset the counters.
+    pub fn clear(&mut self) {
+        *self = CompilerSession::default();
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len() as u32,
+            bytes_held: self.entries.values().map(|e| e.bytes).sum::<usize>() as u32,
+        }
+    }
+}
+
+/// Rough size of what `module` and its validation info keep alive: each
+/// arena's elements, not what they point to.
+fn module_bytes(module: &naga::Module) -> usize {
+    let function = |f: &naga::Function| {
+        f.arguments.len() * size_of::<naga::FunctionArgument>()
+            + f.local_variables.len() * size_of::<naga::LocalVariable>()
+            + f.expressions.len()
+                * (size_of::<naga::Expression>() + size_of::<naga::valid::ExpressionInfo>())
+            + f.body.len() * size_of::<naga::Statement>()
+    };
+    size_of::<naga::Module>()
+        + module.types.len() * size_of::<naga::Type>()
+        + module.constants.len() * size_of::<naga::Constant>()
+        + module.overrides.len() * size_of::<naga::Override>()
+        + module.global_variables.len() * size_of::<naga::GlobalVariable>()
+        + module.global_expressions.len() * size_of::<naga::Expression>()
+        + module
+            .functions
+            .iter()
+            .map(|(_, f)| function(f))
+            .sum::<usize>()
+        + module
+            .entry_points
+            .iter()
+            .map(|ep| size_of::<naga::EntryPoint>() + function(&ep.function))
+            .sum::<usize>()
+}
+
+impl CompilerSession {
+    /// The cached result for `wgsl` under `preset`, parsing and validating
+    /// on a miss.
+    pub(crate) fn module(
+        &mut self,
+        wgsl: &str,
+        preset: Option<&str>,
+    ) -> Result<&ShaderModule, Diagnostic> {
+        let key = sha256_hex(format!("{}\0{wgsl}", preset.unwrap_or_default()).as_bytes());
+        if self.entries.contains_key(&key) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        let entry = self.entries.entry(key).or_insert_with_key(|key| {
+            let result = ShaderModule::new(wgsl, preset);
+            let bytes = key.len()
+                + match &result {
+                    Ok(module) => module_bytes(module.naga_module()),
+                    Err(error) => error.message.len(),
+                };
+            Entry { result, bytes }
+        });
+        entry.result.as_ref().map_err(Clone::clone)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "@compute @workgroup_size(1) fn main() {}";
+
+    #[test]
+    fn unchanged_sources_hit_the_cache() {
+        let mut session = CompilerSession::new();
+        for _ in 0..3 {
+            let module = session.module(SHADER, None).unwrap();
+            assert_eq!(module.entry_points(), ["main"]);
+        }
+        session.module(SHADER, Some("apple-m1")).unwrap();
+        session.module(&format!("{SHADER}\n"), None).unwrap();
+        let stats = session.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 3, 3));
+        assert!(stats.bytes_held > 3 * 64);
+
+        session.clear();
+        assert_eq!(session.stats().entries, 0);
+        session.module(SHADER, None).unwrap();
+        assert_eq!(session.stats().misses, 1);
+    }
+
+    #[test]
+    fn failures_are_cached_too() {
+        let mut session = CompilerSession::new();
+        let first = session.module("fn broken(", None).err().unwrap();
+        let again = session.module("fn broken(", None).err().unwrap();
+        assert_eq!(first.message, again.message);
+        assert_eq!((session.stats().hits, session.stats().misses), (1, 1));
+        assert!(session.module(SHADER, Some("no-such-preset")).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use naga::Module;
+use naga::valid::ModuleInfo;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::throw;
+use crate::preset::{self, Preset};
+use crate::{Diagnostic, ReflectionData, glsl, hlsl, remap, spv};
+
+// ============================================================================
+// Compile All Types
+// ============================================================================
+
+/// Options object accepted by `compileAll`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompileAllOptions {
+    /// Entry point every target is written for; all of them if unset, except
+    /// for GLSL, which needs one.
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Device preset name; see `listPresets`.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Same as the `wgslToSpirvBin` options.
+    #[serde(default)]
+    pub spirv: spv::SpirvOptions,
+    /// Shader model to also write HLSL for, e.g. `"6_0"`.
+    #[serde(default)]
+    pub hlsl: Option<String>,
+    /// Stage and version to also write GLSL for.
+    #[serde(default)]
+    pub glsl: Option<GlslRequest>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GlslRequest {
+    pub stage: String,
+    /// Defaults to GLSL ES 3.00.
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct CompiledShader {
+    #[wasm_bindgen(readonly)]
+    pub spirv: Vec<u8>,
+    #[wasm_bindgen(readonly)]
+    pub msl: String,
+    /// Set if `options.hlsl` was.
+    #[wasm_bindgen(readonly)]
+    pub hlsl: Option<String>,
+    /// Set if `options.glsl` was.
+    #[wasm_bindgen(readonly)]
+    pub glsl: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub reflection: ReflectionData,
+}
+
+#[wasm_bindgen]
+impl CompiledShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// ShaderModule
+// ============================================================================
+
+/// A parsed and validated WGSL module. Parsing and validation happen once, in
+/// `parse`; every backend and reflection call after that reuses the result,
+/// so a hot-reload loop emitting several targets pays for the front end once.
+#[wasm_bindgen]
+pub struct ShaderModule {
+    module: Module,
+    info: ModuleInfo,
+    preset: Option<&'static Preset>,
+}
+
+#[wasm_bindgen]
+impl ShaderModule {
+    /// Parse and validate `wgsl`. `preset` names a device preset (see
+    /// `listPresets`) whose capabilities the module is validated against and
+    /// whose versions and bounds checks every backend then uses. With
+    /// `assumeValid`, validation is skipped, for WGSL written from a module
+    /// validated before, e.g. by `toWgsl`; invalid input then fails in the
+    /// backends, or yields broken output.
+    pub fn parse(
+        wgsl: &str,
+        preset: Option<String>,
+        assume_valid: Option<bool>,
+    ) -> Result<ShaderModule, JsValue> {
+        ShaderModule::load(wgsl, preset.as_deref(), assume_valid.unwrap_or(false)).map_err(throw)
+    }
+
+    /// Names of the module's entry points, in declaration order.
+    #[wasm_bindgen(js_name = entryPoints)]
+    pub fn entry_points(&self) -> Vec<String> {
+        self.module
+            .entry_points
+            .iter()
+            .map(|ep| ep.name.clone())
+            .collect()
+    }
+
+    /// Same as `reflectWgsl`.
+    pub fn reflect(&self) -> ReflectionData {
+        crate::reflect_module(&self.module, &self.info)
+    }
+
+    /// Same as `wgslToSpirvBin`, with the module's preset.
+    #[wasm_bindgen(js_name = toSpirv)]
+    pub fn to_spirv(
+        &self,
+        entry_point: Option<String>,
+        options: JsValue,
+    ) -> Result<Box<[u8]>, JsValue> {
+        let options = crate::spirv_options(options)?;
+        crate::write_spirv_configured(
+            &self.module,
+            &self.info,
+            entry_point.as_deref(),
+            self.preset,
+            &options,
+        )
+        .map(Vec::into_boxed_slice)
+        .map_err(throw)
+    }
+
+    /// Same as `wgslToMsl`, with the module's preset.
+    #[wasm_bindgen(js_name = toMsl)]
+    pub fn to_msl(&self, entry_point: Option<String>) -> Result<String, JsValue> {
+        crate::write_msl_with(
+            &self.module,
+            &self.info,
+            entry_point.as_deref(),
+            self.preset,
+        )
+        .map_err(throw)
+    }
+
+    /// Same as `wgslToHlsl`. `shaderModel` is `"5_1"`, `"6_0"`, ...; it
+    /// defaults to 5.1.
+    #[wasm_bindgen(js_name = toHlsl)]
+    pub fn to_hlsl(
+        &self,
+        entry_point: Option<String>,
+        shader_model: Option<String>,
+    ) -> Result<String, JsValue> {
+        self.hlsl(entry_point.as_deref(), shader_model.as_deref())
+            .map_err(throw)
+    }
+
+    /// Same as `wgslToGlsl`. `version` is `"330"`, `"300es"`, ...; it
+    /// defaults to GLSL ES 3.00.
+    #[wasm_bindgen(js_name = toGlsl)]
+    pub fn to_glsl(
+        &self,
+        entry_point: Option<String>,
+        stage: &str,
+        version: Option<String>,
+    ) -> Result<String, JsValue> {
+        self.glsl(entry_point.as_deref(), stage, version.as_deref())
+            .map_err(throw)
+    }
+
+    /// The module written back out as WGSL.
+    #[wasm_bindgen(js_name = toWgsl)]
+    pub fn to_wgsl(&self) -> Result<String, JsValue> {
+        naga::back::wgsl::write_string(
+            &self.module,
+            &self.info,
+            naga::back::wgsl::WriterFlags::empty(),
+        )
+        .map_err(|e| throw(Diagnostic::error(format!("WGSL write error: {e:?}"))))
+    }
+
+    /// A copy with its bindings moved by `map`, as for `remapBindings`;
+    /// every backend and `reflect` of the copy use the new slots.
+    #[wasm_bindgen(js_name = remapBindings)]
+    pub fn remap_bindings(&self, map: JsValue) -> Result<ShaderModule, JsValue> {
+        let map = remap::binding_map(map)?;
+        self.remapped(&map).map_err(throw)
+    }
+}
+
+impl ShaderModule {
+    pub(crate) fn new(wgsl: &str, preset: Option<&str>) -> Result<ShaderModule, Diagnostic> {
+        ShaderModule::load(wgsl, preset, false)
+    }
+
+    pub(crate) fn load(
+        wgsl: &str,
+        preset: Option<&str>,
+        assume_valid: bool,
+    ) -> Result<ShaderModule, Diagnostic> {
+        let preset = preset::resolve(preset)?;
+        let module = crate::parse_wgsl(wgsl)?;
+        let info = crate::validate_or_trust(&module, preset, assume_valid)?;
+        Ok(ShaderModule {
+            module,
+            info,
+            preset,
+        })
+    }
+
+    pub(crate) fn naga_module(&self) -> &Module {
+        &self.module
+    }
+
+    fn remapped(&self, map: &remap::BindingMap) -> Result<ShaderModule, Diagnostic> {
+        let mut module = self.module.clone();
+        for (handle, _, binding) in remap::plan(&module, map)? {
+            module.global_variables[handle].binding = Some(binding);
+        }
+        let info = crate::validate_for(&module, self.preset)?;
+        Ok(ShaderModule {
+            module,
+            info,
+            preset: self.preset,
+        })
+    }
+
+    fn hlsl(
+        &self,
+        entry_point: Option<&str>,
+        shader_model: Option<&str>,
+    ) -> Result<String, Diagnostic> {
+        let shader_model = match shader_model {
+            Some(name) => hlsl::parse_shader_model(name)?,
+            None => naga::back::hlsl::Options::default().shader_model,
+        };
+        hlsl::write_hlsl(&self.module, &self.info, entry_point, shader_model)
+    }
+
+    fn glsl(
+        &self,
+        entry_point: Option<&str>,
+        stage: &str,
+        version: Option<&str>,
+    ) -> Result<String, Diagnostic> {
+        let stage = glsl::parse_stage(stage)?;
+        let version = match version {
+            Some(name) => glsl::parse_version(name)?,
+            None => naga::back::glsl::Version::new_gles(300),
+        };
+        let entry_point = glsl::select_entry_point(&self.module, entry_point, stage)?;
+        glsl::write_glsl(
+            &self.module,
+            &self.info,
+            entry_point,
+            stage,
+            version,
+            self.preset,
+        )
+    }
+
+    fn compile_all(&self, options: &CompileAllOptions) -> Result<CompiledShader, Diagnostic> {
+        let entry_point = options.entry_point.as_deref();
+        let spirv = crate::write_spirv_configured(
+            &self.module,
+            &self.info,
+            entry_point,
+            self.preset,
+            &options.spirv,
+        )?;
+        let msl = crate::write_msl_with(&self.module, &self.info, entry_point, self.preset)?;
+        let hlsl = options
+            .hlsl
+            .as_deref()
+            .map(|model| self.hlsl(entry_point, Some(model)))
+            .transpose()?;
+        let glsl = options
+            .glsl
+            .as_ref()
+            .map(|glsl| self.glsl(entry_point, &glsl.stage, glsl.version.as_deref()))
+            .transpose()?;
+        Ok(CompiledShader {
+            spirv,
+            msl,
+            hlsl,
+            glsl,
+            reflection: self.reflect(),
+        })
+    }
+}
+
+/// SPIR-V, MSL and reflection for `wgsl`, plus HLSL and GLSL when `options`
+/// asks for them, from a single parse and validation. `options` is
+/// `{ entryPoint?, preset?, spirv?: SpirvOptions, hlsl?: shaderModel,
+/// glsl?: { stage, version? } }`.
+#[wasm_bindgen(js_name = compileAll)]
+pub fn compile_all(wgsl: &str, options: JsValue) -> Result<CompiledShader, JsValue> {
+    let options: Option<CompileAllOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid compileAll options: {e}")))?;
+    let options = options.unwrap_or_default();
+    ShaderModule::new(wgsl, options.preset.as_deref())
+        .and_then(|shader| shader.compile_all(&options))
+        .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<uniform> tint: vec4<f32>;
+
+        @vertex
+        fn vs(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+            return vec4<f32>(position, 1.0);
+        }
+
+        @fragment
+        fn fs() -> @location(0) vec4<f32> {
+            return tint;
+        }
+    "#;
+
+    #[test]
+    fn one_parse_matches_the_free_functions() {
+        let shader = ShaderModule::new(SHADER, None).unwrap();
+        assert_eq!(shader.entry_points(), ["vs", "fs"]);
+        assert_eq!(shader.reflect().entry_points.len(), 2);
+
+        let spirv = crate::write_spirv(&shader.module, &shader.info, Some("fs")).unwrap();
+        assert_eq!(spirv, crate::compile_spirv(SHADER, Some("fs")).unwrap());
+        let msl = crate::write_msl_with(&shader.module, &shader.info, None, None).unwrap();
+        assert_eq!(msl, crate::compile_msl_with(SHADER, None, None).unwrap());
+
+        assert!(shader.hlsl(Some("fs"), Some("6_0")).unwrap().contains("fs"));
+        let glsl = shader.glsl(None, "vertex", Some("330")).unwrap();
+        assert!(glsl.starts_with("#version 330"));
+        assert!(shader.glsl(None, "compute", None).is_err());
+    }
+
+    #[test]
+    fn remapped_copies_reflect_and_emit_new_slots() {
+        let shader = ShaderModule::new(SHADER, None).unwrap();
+        let map = remap::BindingMap {
+            group_offset: 2,
+            ..Default::default()
+        };
+        let moved = shader.remapped(&map).unwrap();
+        let binding = &moved.reflect().entry_points[1].bindings[0];
+        assert_eq!((binding.group, binding.binding), (2, 0));
+        assert!(moved.to_wgsl().unwrap().contains("@group(2) @binding(0)"));
+        assert_eq!(shader.reflect().entry_points[1].bindings[0].group, 0);
+    }
+
+    #[test]
+    fn invalid_sources_and_presets_fail_to_parse() {
+        assert!(ShaderModule::new("fn main( {", None).is_err());
+        assert!(ShaderModule::new(SHADER, Some("no-such-preset")).is_err());
+    }
+
+    #[test]
+    fn written_wgsl_can_skip_validation() {
+        let shader = ShaderModule::new(SHADER, None).unwrap();
+        let written = shader.to_wgsl().unwrap();
+        let trusted = ShaderModule::load(&written, None, true).unwrap();
+        let spirv =
+            |shader: &ShaderModule| crate::write_spirv(&shader.module, &shader.info, None).unwrap();
+        let checked = ShaderModule::new(&written, None).unwrap();
+        assert_eq!(spirv(&trusted), spirv(&checked));
+
+        // Only validation is skipped: parse errors still fail.
+        assert!(ShaderModule::load("fn main( {", None, true).is_err());
+        let unchecked = "@fragment fn fs() -> @location(0) f32 { return 1u; }";
+        assert!(ShaderModule::new(unchecked, None).is_err());
+        assert!(ShaderModule::load(unchecked, None, true).is_ok());
+    }
+
+    #[test]
+    fn compile_all_writes_requested_targets() {
+        let shader = ShaderModule::new(SHADER, None).unwrap();
+        let all = shader.compile_all(&CompileAllOptions::default()).unwrap();
+        assert_eq!(all.spirv, crate::compile_spirv(SHADER, None).unwrap());
+        assert!(all.msl.contains("fs"));
+        assert!(all.hlsl.is_none() && all.glsl.is_none());
+        assert_eq!(all.reflection.entry_points.len(), 2);
+
+        let options = CompileAllOptions {
+            entry_point: Some("vs".to_string()),
+            hlsl: Some("6_0".to_string()),
+            glsl: Some(GlslRequest {
+                stage: "vertex".to_string(),
+                version: Some("330".to_string()),
+            }),
+            ..Default::default()
+        };
+        let all = shader.compile_all(&options).unwrap();
+        assert!(all.hlsl.unwrap().contains("vs"));
+        assert!(all.glsl.unwrap().starts_with("#version 330"));
+    }
+}
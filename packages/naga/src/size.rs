@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::spv;
+
+// ============================================================================
+// Size Report Types
+// ============================================================================
+
+/// Code-size metrics of one emitted artifact.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SizeReport {
+    #[wasm_bindgen(readonly)]
+    pub bytes: u32,
+    /// Line count of textual artifacts (MSL).
+    #[wasm_bindgen(readonly)]
+    pub lines: Option<u32>,
+    /// Word count of SPIR-V, header included.
+    #[wasm_bindgen(readonly)]
+    pub words: Option<u32>,
+    /// Instruction count of SPIR-V.
+    #[wasm_bindgen(readonly)]
+    pub instructions: Option<u32>,
+    /// SPIR-V instruction counts per opcode class, sorted by class.
+    #[wasm_bindgen(readonly)]
+    pub opcode_classes: Vec<OpcodeClassCount>,
+}
+
+#[wasm_bindgen]
+impl SizeReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct OpcodeClassCount {
+    /// Class name from the SPIR-V specification, e.g. `arithmetic` or `memory`.
+    #[wasm_bindgen(readonly)]
+    pub class: String,
+    #[wasm_bindgen(readonly)]
+    pub count: u32,
+}
+
+// ============================================================================
+// Size Report Implementation
+// ============================================================================
+
+/// Opcode class as grouped in the SPIR-V specification, coarsened a little.
+fn opcode_class(op: Op) -> &'static str {
+    let name = format!("{op:?}");
+    match op as u32 {
+        109..=124 => return "conversion",
+        126..=152 => return "arithmetic",
+        154..=191 => return "relational-logical",
+        194..=205 => return "bit",
+        207..=215 => return "derivative",
+        _ => {}
+    }
+    match op {
+        Op::Capability
+        | Op::Extension
+        | Op::ExtInstImport
+        | Op::MemoryModel
+        | Op::EntryPoint
+        | Op::ExecutionMode
+        | Op::ExecutionModeId => "mode-setting",
+        Op::SourceContinued
+        | Op::Source
+        | Op::SourceExtension
+        | Op::Name
+        | Op::MemberName
+        | Op::String
+        | Op::Line
+        | Op::NoLine
+        | Op::ModuleProcessed => "debug",
+        Op::Decorate
+        | Op::MemberDecorate
+        | Op::DecorationGroup
+        | Op::GroupDecorate
+        | Op::GroupMemberDecorate
+        | Op::DecorateId
+        | Op::DecorateString
+        | Op::MemberDecorateString => "annotation",
+        Op::Variable
+        | Op::Load
+        | Op::Store
+        | Op::CopyMemory
+        | Op::CopyMemorySized
+        | Op::AccessChain
+        | Op::InBoundsAccessChain
+        | Op::PtrAccessChain
+        | Op::ArrayLength
+        | Op::ImageTexelPointer => "memory",
+        Op::Function | Op::FunctionParameter | Op::FunctionEnd | Op::FunctionCall => "function",
+        Op::Phi
+        | Op::LoopMerge
+        | Op::SelectionMerge
+        | Op::Label
+        | Op::Branch
+        | Op::BranchConditional
+        | Op::Switch
+        | Op::Kill
+        | Op::Return
+        | Op::ReturnValue
+        | Op::Unreachable
+        | Op::TerminateInvocation => "control-flow",
+        Op::VectorExtractDynamic
+        | Op::VectorInsertDynamic
+        | Op::VectorShuffle
+        | Op::CompositeConstruct
+        | Op::CompositeExtract
+        | Op::CompositeInsert
+        | Op::CopyObject
+        | Op::Transpose => "composite",
+        Op::ExtInst => "extension",
+        Op::ControlBarrier | Op::MemoryBarrier => "barrier",
+        _ if name.starts_with("Type") => "type-declaration",
+        _ if name.starts_with("Constant") || name.starts_with("SpecConstant") => {
+            "constant-creation"
+        }
+        _ if name.starts_with("Image") || name == "SampledImage" => "image",
+        _ if name.starts_with("Atomic") => "atomic",
+        _ if name.starts_with("Group") => "group",
+        _ => "other",
+    }
+}
+
+/// Size metrics of a SPIR-V word stream.
+pub(crate) fn spirv_report(words: &[u32]) -> Result<SizeReport, Diagnostic> {
+    let insts = spv::instructions(words)?;
+    let mut classes = BTreeMap::<&str, u32>::new();
+    for inst in &insts {
+        let class = inst.op().map_or("unknown", opcode_class);
+        *classes.entry(class).or_default() += 1;
+    }
+    Ok(SizeReport {
+        bytes: (words.len() * 4) as u32,
+        lines: None,
+        words: Some(words.len() as u32),
+        instructions: Some(insts.len() as u32),
+        opcode_classes: classes
+            .into_iter()
+            .map(|(class, count)| OpcodeClassCount {
+                class: class.to_string(),
+                count,
+            })
+            .collect(),
+    })
+}
+
+/// Size metrics of a textual artifact.
+pub(crate) fn text_report(text: &str) -> SizeReport {
+    SizeReport {
+        bytes: text.len() as u32,
+        lines: Some(text.lines().count() as u32),
+        words: None,
+        instructions: None,
+        opcode_classes: Vec::new(),
+    }
+}
+
+/// Size metrics of a SPIR-V binary: bytes, words, and instruction counts by
+/// opcode class.
+#[wasm_bindgen(js_name = spirvSizeReport)]
+pub fn spirv_size_report(spirv_bytes: &[u8]) -> Result<SizeReport, JsValue> {
+    let words = spv::words_from_bytes(spirv_bytes).map_err(throw)?;
+    spirv_report(&words).map_err(throw)
+}
+
+/// Size metrics of a textual artifact (MSL): bytes and lines.
+#[wasm_bindgen(js_name = textSizeReport)]
+pub fn text_size_report(text: &str) -> SizeReport {
+    text_report(text)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+        @compute @workgroup_size(64)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            data[id.x] = data[id.x] * 2.0 + 1.0;
+        }
+    "#;
+
+    #[test]
+    fn spirv_classes_add_up() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let words = spv::words_from_bytes(&bytes).unwrap();
+        let report = spirv_report(&words).unwrap();
+
+        assert_eq!(report.bytes as usize, bytes.len());
+        assert_eq!(report.words.unwrap() as usize, words.len());
+        let total: u32 = report.opcode_classes.iter().map(|c| c.count).sum();
+        assert_eq!(Some(total), report.instructions);
+
+        let classes: Vec<_> = report
+            .opcode_classes
+            .iter()
+            .map(|c| c.class.as_str())
+            .collect();
+        for class in ["arithmetic", "memory", "type-declaration", "mode-setting"] {
+            assert!(classes.contains(&class), "missing {class}");
+        }
+        assert!(!classes.contains(&"unknown"));
+    }
+
+    #[test]
+    fn opcode_ranges_classify() {
+        assert_eq!(opcode_class(Op::FMul), "arithmetic");
+        assert_eq!(opcode_class(Op::ConvertFToU), "conversion");
+        assert_eq!(opcode_class(Op::Select), "relational-logical");
+        assert_eq!(opcode_class(Op::TypeRuntimeArray), "type-declaration");
+        assert_eq!(opcode_class(Op::ConstantComposite), "constant-creation");
+        assert_eq!(opcode_class(Op::ImageSampleImplicitLod), "image");
+    }
+
+    #[test]
+    fn text_counts_lines() {
+        let report = text_report("a\nbc\n");
+        assert_eq!(report.bytes, 5);
+        assert_eq!(report.lines, Some(2));
+        assert!(report.opcode_classes.is_empty());
+    }
+}
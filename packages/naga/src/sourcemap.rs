@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::{SourceSpan, throw};
+use crate::lexer::{module_declarations, tokenize};
+use crate::spv::{self, decode_string};
+
+// ============================================================================
+// Source Map Types
+// ============================================================================
+
+/// Where generated code came from in the WGSL.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SourceMapping {
+    /// For SPIR-V, the word offset of the first instruction an `OpLine`
+    /// covers; for MSL, the 1-based line of a function's signature.
+    #[wasm_bindgen(readonly)]
+    pub generated: u32,
+    /// 1-based WGSL line.
+    #[wasm_bindgen(readonly)]
+    pub line: u32,
+    /// 1-based WGSL column.
+    #[wasm_bindgen(readonly)]
+    pub column: u32,
+    /// The WGSL function the code belongs to, if known.
+    #[wasm_bindgen(readonly)]
+    pub function: Option<String>,
+}
+
+#[wasm_bindgen]
+impl SourceMapping {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A mapping table from generated code back to WGSL, in generated order.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SourceMap {
+    /// The name the WGSL was given.
+    #[wasm_bindgen(readonly)]
+    pub file: String,
+    /// `"spirv"` or `"msl"`.
+    #[wasm_bindgen(readonly)]
+    pub target: String,
+    #[wasm_bindgen(readonly)]
+    pub mappings: Vec<SourceMapping>,
+}
+
+#[wasm_bindgen]
+impl SourceMap {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MappedSpirv {
+    #[wasm_bindgen(readonly)]
+    pub bytes: Vec<u8>,
+    #[wasm_bindgen(readonly)]
+    pub source_map: SourceMap,
+}
+
+#[wasm_bindgen]
+impl MappedSpirv {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Source Map Implementation
+// ============================================================================
+//
+// naga does the SPIR-V side itself: given the source it emits an `OpString`
+// naming the file, an `OpSource` holding the WGSL and an `OpLine` ahead of
+// each statement, and the table is read back out of the binary. Its MSL
+// writer tracks no spans, so MSL maps at function granularity: functions
+// are found in the output in the order naga writes them, module functions
+// then entry points, by their possibly `_`-suffixed names, and each gets a
+// `#line` naming its WGSL declaration. Lines after a signature count on from
+// it, so they point near, not exactly at, their WGSL.
+
+const DEFAULT_FILE_NAME: &str = "shader.wgsl";
+
+pub(crate) fn file_name(name: Option<&str>) -> &str {
+    name.filter(|n| !n.is_empty()).unwrap_or(DEFAULT_FILE_NAME)
+}
+
+/// Every `OpLine` in `words`, with the function it is in.
+pub(crate) fn spirv_source_map(words: &[u32], file: &str) -> Result<SourceMap, Diagnostic> {
+    let insts = spv::instructions(words)?;
+    let mut names = HashMap::new();
+    for inst in &insts {
+        if inst.op() == Some(Op::Name) && inst.words.len() > 2 {
+            names.insert(inst.words[1], decode_string(&inst.words[2..]).0);
+        }
+    }
+    let mut function = None;
+    let mut mappings = Vec::new();
+    for inst in &insts {
+        match inst.op() {
+            Some(Op::Function) if inst.words.len() > 2 => {
+                function = names.get(&inst.words[2]).cloned();
+            }
+            Some(Op::FunctionEnd) => function = None,
+            Some(Op::Line) if inst.words.len() > 3 => mappings.push(SourceMapping {
+                generated: (inst.offset + inst.words.len()) as u32,
+                line: inst.words[2],
+                column: inst.words[3],
+                function: function.clone(),
+            }),
+            _ => {}
+        }
+    }
+    Ok(SourceMap {
+        file: file.to_string(),
+        target: "spirv".to_string(),
+        mappings,
+    })
+}
+
+/// Whether `name` is how naga's namer may spell `wgsl_name`.
+fn is_spelling(name: &str, wgsl_name: &str) -> bool {
+    name.strip_prefix(wgsl_name)
+        .is_some_and(|rest| rest.chars().all(|c| c == '_' || c.is_ascii_digit()))
+}
+
+/// The function a top-level `line` of MSL defines, e.g. `twice` for
+/// `float twice(`.
+fn defined_function(line: &str) -> Option<&str> {
+    if line.starts_with(char::is_whitespace) || line.trim_end().ends_with(';') {
+        return None;
+    }
+    let before = line[..line.find('(')?].trim_end();
+    let start = before
+        .rfind(|c: char| !(c == '_' || c.is_alphanumeric()))
+        .map_or(0, |i| i + 1);
+    Some(&before[start..]).filter(|name| !name.is_empty())
+}
+
+/// `msl`, written from `emitted`, with a `#line` before each function
+/// naming where `module`, parsed from `wgsl`, declares it. `emitted` is
+/// `module` or a copy with an entry point renamed.
+pub(crate) fn annotate_msl(
+    msl: &str,
+    wgsl: &str,
+    module: &naga::Module,
+    emitted: &naga::Module,
+    file: &str,
+) -> (String, SourceMap) {
+    let tokens = tokenize(wgsl);
+    let declared: HashMap<_, _> = module_declarations(&tokens)
+        .into_iter()
+        .map(|(name, at)| (name, tokens[at].start))
+        .collect();
+    let functions = module
+        .functions
+        .iter()
+        .filter_map(|(_, f)| f.name.as_deref())
+        .map(|name| (name, name));
+    let entry_points = module
+        .entry_points
+        .iter()
+        .zip(&emitted.entry_points)
+        .map(|(ep, out)| (ep.name.as_str(), out.name.as_str()));
+
+    let lines: Vec<&str> = msl.lines().collect();
+    let mut directives = Vec::new();
+    let mut cursor = 0;
+    for (name, out_name) in functions.chain(entry_points) {
+        let Some(&offset) = declared.get(name) else {
+            continue;
+        };
+        let Some(found) = (cursor..lines.len())
+            .find(|&i| defined_function(lines[i]).is_some_and(|f| is_spelling(f, out_name)))
+        else {
+            // Compacted away, or not emitted for this entry point.
+            continue;
+        };
+        cursor = found + 1;
+        let at = naga::Span::new(offset as u32, offset as u32 + 1);
+        let Some(span) = SourceSpan::new(wgsl, at) else {
+            continue;
+        };
+        directives.push((found, name, span.line, span.column));
+    }
+
+    let escaped = file.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut out = String::with_capacity(msl.len() + directives.len() * 32);
+    let mut mappings = Vec::new();
+    let mut next = directives.iter().peekable();
+    for (i, text) in lines.iter().enumerate() {
+        if let Some(&(_, name, line, column)) = next.next_if(|d| d.0 == i) {
+            out.push_str(&format!("#line {line} \"{escaped}\"\n"));
+            mappings.push(SourceMapping {
+                generated: (i + mappings.len() + 2) as u32,
+                line,
+                column,
+                function: Some(name.to_string()),
+            });
+        }
+        out.push_str(text);
+        out.push('\n');
+    }
+    let map = SourceMap {
+        file: file.to_string(),
+        target: "msl".to_string(),
+        mappings,
+    };
+    (out, map)
+}
+
+/// `wgslToSpirvBin` with the `sourceMap` option forced on, returning the
+/// bytes and a table of every `OpLine` in them: the word offset it applies
+/// from and the WGSL line, column and function it names.
+#[wasm_bindgen(js_name = wgslToSpirvWithSourceMap)]
+pub fn wgsl_to_spirv_with_source_map(
+    wgsl: &str,
+    entry_point: Option<String>,
+    preset: Option<String>,
+    options: JsValue,
+) -> Result<MappedSpirv, JsValue> {
+    let mut options = crate::spirv_options(options)?;
+    options.source_map = true;
+    let preset = crate::preset::resolve(preset.as_deref()).map_err(throw)?;
+    mapped_spirv(wgsl, entry_point.as_deref(), preset, &options).map_err(throw)
+}
+
+fn mapped_spirv(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    preset: Option<&crate::preset::Preset>,
+    options: &spv::SpirvOptions,
+) -> Result<MappedSpirv, Diagnostic> {
+    let bytes = crate::compile_spirv_with(wgsl, entry_point, preset, options)?;
+    let words = spv::words_from_bytes(&bytes)?;
+    let file = file_name(options.file_name.as_deref());
+    Ok(MappedSpirv {
+        source_map: spirv_source_map(&words, file)?,
+        bytes,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = "\
+fn twice(x: f32) -> f32 {
+    let y = x * 2.0;
+    return y;
+}
+
+@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+@compute @workgroup_size(1)
+fn main() {
+    data[0] = twice(data[1]);
+}
+";
+
+    #[test]
+    fn spirv_lines_point_into_the_wgsl() {
+        let options = spv::SpirvOptions {
+            source_map: true,
+            file_name: Some("kernels/double.wgsl".to_string()),
+            ..Default::default()
+        };
+        let mapped = mapped_spirv(SHADER, None, None, &options).unwrap();
+        let map = &mapped.source_map;
+        assert_eq!(map.file, "kernels/double.wgsl");
+        let lines: Vec<_> = map
+            .mappings
+            .iter()
+            .map(|m| (m.function.as_deref(), m.line))
+            .collect();
+        assert!(lines.contains(&(Some("twice"), 2)));
+        assert!(lines.contains(&(Some("main"), 10)));
+
+        let words = spv::words_from_bytes(&mapped.bytes).unwrap();
+        let text = spv::disassemble(&words).unwrap();
+        assert!(text.contains("OpString \"kernels/double.wgsl\""));
+        assert!(text.contains("OpSource"));
+        let without = crate::compile_spirv_with(SHADER, None, None, &Default::default()).unwrap();
+        let words = spv::words_from_bytes(&without).unwrap();
+        assert!(spirv_source_map(&words, "x").unwrap().mappings.is_empty());
+    }
+
+    #[test]
+    fn msl_functions_get_line_directives() {
+        let options = crate::msl::MslOptions {
+            source_map: true,
+            ..Default::default()
+        };
+        let output = crate::msl::compile_msl_mapped(SHADER, None, &options).unwrap();
+        let map = output.source_map.unwrap();
+        let functions: Vec<_> = map
+            .mappings
+            .iter()
+            .map(|m| (m.function.as_deref().unwrap(), m.line, m.column))
+            .collect();
+        assert_eq!(functions, [("twice", 1, 4), ("main", 9, 4)]);
+        let lines: Vec<_> = output.source.lines().collect();
+        for mapping in &map.mappings {
+            let at = mapping.generated as usize - 1;
+            assert_eq!(
+                lines[at - 1],
+                format!("#line {} \"shader.wgsl\"", mapping.line)
+            );
+            assert!(lines[at].contains(mapping.function.as_deref().unwrap()));
+        }
+
+        let plain = crate::msl::compile_msl_mapped(SHADER, None, &Default::default()).unwrap();
+        assert!(plain.source_map.is_none());
+        assert!(!plain.source.contains("#line"));
+    }
+}
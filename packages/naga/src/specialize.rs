@@ -0,0 +1,943 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use naga::{Expression, Literal, ScalarKind, TypeInner};
+use serde::{Deserialize, Serialize};
+use spirv::{Decoration, Op};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::spv;
+
+// ============================================================================
+// Specialization Types
+// ============================================================================
+
+/// A WGSL `override` emitted as a SPIR-V specialization constant or an MSL
+/// function constant.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpecConstant {
+    #[wasm_bindgen(readonly)]
+    pub name: Option<String>,
+    /// SPIR-V `SpecId` or MSL `[[function_constant]]` index: the `@id` of the
+    /// override, or the lowest index not claimed by an explicit `@id`, in
+    /// declaration order.
+    #[wasm_bindgen(readonly)]
+    pub id: u32,
+    /// `u32`, `i32` or `f32`.
+    #[wasm_bindgen(readonly)]
+    pub scalar: String,
+    /// The WGSL default, also the spec constant's default. Unset if the
+    /// override has none; the binary then defaults to zero.
+    #[wasm_bindgen(readonly)]
+    pub default_value: Option<f64>,
+}
+
+#[wasm_bindgen]
+impl SpecConstant {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpecializableSpirv {
+    #[wasm_bindgen(readonly)]
+    pub bytes: Vec<u8>,
+    /// Sorted by id.
+    #[wasm_bindgen(readonly)]
+    pub spec_constants: Vec<SpecConstant>,
+    /// Overrides that could not become spec constants and were baked to
+    /// their defaults or left out as unused.
+    #[wasm_bindgen(readonly)]
+    pub baked: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl SpecializableSpirv {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpecializableMsl {
+    #[wasm_bindgen(readonly)]
+    pub source: String,
+    /// Sorted by index.
+    #[wasm_bindgen(readonly)]
+    pub function_constants: Vec<SpecConstant>,
+    /// Overrides that could not become function constants, as for SPIR-V.
+    #[wasm_bindgen(readonly)]
+    pub baked: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl SpecializableMsl {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Override Location
+// ============================================================================
+//
+// naga's backends only accept modules whose overrides have been replaced by
+// values. To recover specializable constants, every candidate override is
+// given a unique sentinel value and the module is compiled once more per
+// candidate with just that sentinel changed. In SPIR-V, an override whose
+// change touches exactly one `OpConstant` value word maps to that constant,
+// which is then turned into an `OpSpecConstant`; in MSL, the sentinel literal
+// must be the only text that changes, and is replaced by a function constant.
+// Anything else means the value was folded
+// into other code (workgroup sizes, array lengths, constant arithmetic), so
+// the override is baked instead.
+
+/// An override that may become a spec constant.
+struct Candidate {
+    handle: naga::Handle<naga::Override>,
+    key: String,
+    label: String,
+    scalar: ScalarKind,
+    default_bits: Option<u32>,
+    default_value: Option<f64>,
+}
+
+/// Value for the `variant`th sentinel of the `slot`th candidate, and its bits.
+fn sentinel(scalar: ScalarKind, slot: usize, variant: u32) -> (f64, u32) {
+    let step = 2 * slot as u32 + variant;
+    match scalar {
+        ScalarKind::Float => {
+            let bits = 0x4B5E_C000 + step;
+            (f32::from_bits(bits) as f64, bits)
+        }
+        _ => {
+            let bits = 0x5EC0_0000 + step;
+            (bits as f64, bits)
+        }
+    }
+}
+
+fn scalar_name(scalar: ScalarKind) -> &'static str {
+    match scalar {
+        ScalarKind::Uint => "u32",
+        ScalarKind::Sint => "i32",
+        _ => "f32",
+    }
+}
+
+/// Identifier naga's pipeline constants use for an override.
+pub(crate) fn override_key(o: &naga::Override) -> String {
+    match (o.id, &o.name) {
+        (Some(id), _) => id.to_string(),
+        (None, Some(name)) => name.clone(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Name to report an override by.
+fn override_label(o: &naga::Override) -> String {
+    o.name.clone().unwrap_or_else(|| override_key(o))
+}
+
+fn candidates(module: &naga::Module) -> (Vec<Candidate>, Vec<String>) {
+    // Overrides that global expressions (other overrides' defaults, workgroup
+    // sizes) or array lengths depend on are always folded.
+    let mut folded = HashSet::new();
+    for (_, expr) in module.global_expressions.iter() {
+        if let Expression::Override(h) = expr {
+            folded.insert(*h);
+        }
+    }
+    for (_, ty) in module.types.iter() {
+        if let TypeInner::Array {
+            size: naga::ArraySize::Pending(h),
+            ..
+        } = ty.inner
+        {
+            folded.insert(h);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    let mut baked = Vec::new();
+    for (handle, o) in module.overrides.iter() {
+        let scalar = match module.types[o.ty].inner {
+            TypeInner::Scalar(s) if s.width == 4 && s.kind != ScalarKind::Bool => Some(s.kind),
+            _ => None,
+        };
+        let default = match o.init.map(|init| &module.global_expressions[init]) {
+            None => Some((None, None)),
+            Some(Expression::Literal(Literal::U32(v))) => Some((Some(*v), Some(*v as f64))),
+            Some(Expression::Literal(Literal::I32(v))) => Some((Some(*v as u32), Some(*v as f64))),
+            Some(Expression::Literal(Literal::F32(v))) => {
+                Some((Some(v.to_bits()), Some(*v as f64)))
+            }
+            Some(_) => None,
+        };
+        match (scalar, default) {
+            (Some(scalar), Some((default_bits, default_value))) if !folded.contains(&handle) => {
+                candidates.push(Candidate {
+                    handle,
+                    key: override_key(o),
+                    label: override_label(o),
+                    scalar,
+                    default_bits,
+                    default_value,
+                });
+            }
+            _ => baked.push(override_label(o)),
+        }
+    }
+    (candidates, baked)
+}
+
+/// Index of each emitted override: its `@id`, or else the lowest index not
+/// claimed by an explicit `@id`, in declaration order. Baked overrides take
+/// none.
+fn override_ids<L>(
+    module: &naga::Module,
+    located: &[(Candidate, L)],
+) -> HashMap<naga::Handle<naga::Override>, u32> {
+    let mut taken: HashSet<u32> = module
+        .overrides
+        .iter()
+        .filter_map(|(_, o)| o.id.map(u32::from))
+        .collect();
+    let mut next = 0;
+    located
+        .iter()
+        .map(|(candidate, _)| {
+            let id = match module.overrides[candidate.handle].id {
+                Some(id) => id as u32,
+                None => {
+                    while !taken.insert(next) {
+                        next += 1;
+                    }
+                    next
+                }
+            };
+            (candidate.handle, id)
+        })
+        .collect()
+}
+
+/// Compile with sentinel values until every remaining candidate can be
+/// `locate`d in the output, baking the ones that cannot. Returns the output
+/// with sentinels in place, and each surviving candidate with its location.
+fn locate_overrides<T, L>(
+    baked: &mut Vec<String>,
+    mut candidates: Vec<Candidate>,
+    compile: impl Fn(&[(String, f64)]) -> Result<T, Diagnostic>,
+    locate: impl Fn(&T, &T, &Candidate, usize) -> Option<L>,
+) -> Result<(T, Vec<(Candidate, L)>), Diagnostic> {
+    let values = |candidates: &[Candidate], changed: Option<usize>| -> Vec<(String, f64)> {
+        candidates
+            .iter()
+            .enumerate()
+            .map(|(slot, c)| {
+                let variant = (changed == Some(slot)) as u32;
+                (c.key.clone(), sentinel(c.scalar, slot, variant).0)
+            })
+            .collect()
+    };
+
+    loop {
+        let base = match compile(&values(&candidates, None)) {
+            Ok(base) => base,
+            // A sentinel broke constant evaluation; bake everything.
+            Err(_) if !candidates.is_empty() => {
+                baked.extend(candidates.drain(..).map(|c| c.label));
+                continue;
+            }
+            Err(diagnostic) => return Err(diagnostic),
+        };
+
+        let mut located = Vec::new();
+        let mut folded = Vec::new();
+        for (slot, candidate) in candidates.iter().enumerate() {
+            let variant = compile(&values(&candidates, Some(slot)));
+            match variant
+                .ok()
+                .and_then(|v| locate(&base, &v, candidate, slot))
+            {
+                Some(location) => located.push(location),
+                None => folded.push(slot),
+            }
+        }
+        if folded.is_empty() {
+            return Ok((base, candidates.into_iter().zip(located).collect()));
+        }
+        for slot in folded.into_iter().rev() {
+            baked.push(candidates.remove(slot).label);
+        }
+    }
+}
+
+/// Entry point stage and name for `process_overrides`.
+fn pipeline_entry_point<'a>(
+    module: &naga::Module,
+    entry_point: Option<&'a str>,
+) -> Result<Option<(naga::ShaderStage, &'a str)>, Diagnostic> {
+    match entry_point {
+        Some(name) => Ok(Some((crate::find_entry_point(module, name)?.stage, name))),
+        None => Ok(None),
+    }
+}
+
+/// Bake `values`, keyed by override name or `@id`, into `module`; overrides
+/// without a value take their defaults. Modules without overrides are
+/// returned as they are.
+pub(crate) fn bake<'a>(
+    module: &'a naga::Module,
+    info: &'a naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    values: &BTreeMap<String, f64>,
+) -> Result<(Cow<'a, naga::Module>, Cow<'a, naga::valid::ModuleInfo>), Diagnostic> {
+    let mut keyed = Vec::with_capacity(values.len());
+    for (name, value) in values {
+        let o = module
+            .overrides
+            .iter()
+            .map(|(_, o)| o)
+            .find(|o| {
+                o.name.as_ref() == Some(name) || o.id.is_some_and(|id| id.to_string() == *name)
+            })
+            .ok_or_else(|| Diagnostic::error(format!("Unknown override '{name}'")))?;
+        keyed.push((override_key(o), *value));
+    }
+    if module.overrides.is_empty() {
+        return Ok((Cow::Borrowed(module), Cow::Borrowed(info)));
+    }
+    process(
+        module,
+        info,
+        entry_point.filter(|ep| !ep.is_empty()),
+        &keyed,
+    )
+}
+
+/// Replace overrides by the given values.
+pub(crate) fn process<'a>(
+    module: &'a naga::Module,
+    info: &'a naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    values: &[(String, f64)],
+) -> Result<(Cow<'a, naga::Module>, Cow<'a, naga::valid::ModuleInfo>), Diagnostic> {
+    let mut constants = naga::back::PipelineConstants::default();
+    constants.extend(values.iter().cloned());
+    naga::back::pipeline_constants::process_overrides(
+        module,
+        info,
+        pipeline_entry_point(module, entry_point)?,
+        &constants,
+    )
+    .map_err(|e| Diagnostic::error(format!("Override error: {e}")))
+}
+
+// ============================================================================
+// SPIR-V Specialization Constants
+// ============================================================================
+
+/// Word offset of the `OpConstant` whose value word is the only difference
+/// between `a` and `b`.
+fn changed_constant(a: &[u32], b: &[u32], bits: u32) -> Option<usize> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let mut diffs = (0..a.len()).filter(|i| a[*i] != b[*i]);
+    let at = diffs.next()?;
+    if diffs.next().is_some() || a[at] != bits || at < spv::HEADER_WORDS + 3 {
+        return None;
+    }
+    let start = at - 3;
+    let is_constant = a[start] == (4 << 16) | Op::Constant as u32;
+    is_constant.then_some(start)
+}
+
+pub(crate) struct Specializable<T> {
+    pub output: T,
+    pub spec_constants: Vec<SpecConstant>,
+    pub baked: Vec<String>,
+}
+
+/// Emit SPIR-V with overrides as specialization constants where possible.
+pub(crate) fn write_spirv_specializable(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+) -> Result<Specializable<Vec<u32>>, Diagnostic> {
+    let entry_point = entry_point.filter(|name| !name.is_empty());
+    let (candidates, mut baked) = candidates(module);
+    let (mut words, located) = locate_overrides(
+        &mut baked,
+        candidates,
+        |values| {
+            let (module, info) = process(module, info, entry_point, values)?;
+            spv::words_from_bytes(&crate::write_spirv(&module, &info, entry_point)?)
+        },
+        |base, variant, candidate, slot| {
+            changed_constant(base, variant, sentinel(candidate.scalar, slot, 0).1)
+        },
+    )?;
+
+    let ids = override_ids(module, &located);
+    let mut spec_constants = Vec::new();
+    let mut decorations = Vec::new();
+    let mut spec_ids = HashSet::new();
+    for (candidate, offset) in located {
+        let id = ids[&candidate.handle];
+        words[offset] = (4 << 16) | Op::SpecConstant as u32;
+        words[offset + 3] = candidate.default_bits.unwrap_or(0);
+        let result = words[offset + 2];
+        spec_ids.insert(result);
+        decorations.extend(spv::encode(
+            Op::Decorate,
+            &[result, Decoration::SpecId as u32, id],
+        ));
+        spec_constants.push(spec_constant(module, &candidate, id));
+    }
+
+    promote_composites(&mut words, &mut spec_ids)?;
+    let after = [
+        spv::PREAMBLE,
+        spv::DEBUG_SOURCE,
+        spv::DEBUG_NAMES,
+        spv::ANNOTATIONS,
+    ]
+    .concat();
+    let at = spv::insertion_point(&words, &after)?;
+    words.splice(at..at, decorations);
+
+    spec_constants.sort_by_key(|c| c.id);
+    baked.sort();
+    Ok(Specializable {
+        output: words,
+        spec_constants,
+        baked,
+    })
+}
+
+fn spec_constant(module: &naga::Module, candidate: &Candidate, id: u32) -> SpecConstant {
+    SpecConstant {
+        name: module.overrides[candidate.handle].name.clone(),
+        id,
+        scalar: scalar_name(candidate.scalar).to_string(),
+        default_value: candidate.default_value,
+    }
+}
+
+/// Composites built from spec constants must be spec constants themselves.
+fn promote_composites(words: &mut [u32], spec_ids: &mut HashSet<u32>) -> Result<(), Diagnostic> {
+    let composites: Vec<(usize, u32, Vec<u32>)> = spv::instructions(words)?
+        .iter()
+        .filter(|i| i.op() == Some(Op::ConstantComposite))
+        .map(|i| (i.offset, i.operands()[1], i.operands()[2..].to_vec()))
+        .collect();
+    // Constants are declared before use, so one pass in order suffices.
+    for (offset, result, constituents) in composites {
+        if constituents.iter().any(|c| spec_ids.contains(c)) {
+            words[offset] = (words[offset] & 0xFFFF_0000) | Op::SpecConstantComposite as u32;
+            spec_ids.insert(result);
+        }
+    }
+    Ok(())
+}
+
+/// WGSL -> SPIR-V with `override`s emitted as specialization constants
+/// decorated with their `@id`s, for native Vulkan pipelines that specialize
+/// at creation time. Overrides whose values get folded into other code
+/// (workgroup sizes, array lengths, other overrides' defaults, constant
+/// arithmetic) are baked to their defaults and listed in `baked`.
+#[wasm_bindgen(js_name = wgslToSpirvSpecializable)]
+pub fn wgsl_to_spirv_specializable(
+    wgsl: &str,
+    entry_point: Option<String>,
+) -> Result<SpecializableSpirv, JsValue> {
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    let result =
+        write_spirv_specializable(&module, &info, entry_point.as_deref()).map_err(throw)?;
+    Ok(SpecializableSpirv {
+        bytes: spv::bytes_from_words(&result.output),
+        spec_constants: result.spec_constants,
+        baked: result.baked,
+    })
+}
+
+// ============================================================================
+// MSL Function Constants
+// ============================================================================
+
+/// A scalar as naga's MSL backend prints it.
+fn msl_value(scalar: ScalarKind, bits: u32) -> String {
+    match scalar {
+        ScalarKind::Uint => format!("{bits}u"),
+        ScalarKind::Sint if bits as i32 == i32::MIN => "(-2147483647 - 1)".to_string(),
+        ScalarKind::Sint => format!("{}", bits as i32),
+        _ => {
+            let value = f32::from_bits(bits);
+            let suffix = if value.fract() == 0.0 { ".0" } else { "" };
+            format!("{value}{suffix}")
+        }
+    }
+}
+
+fn msl_type(scalar: ScalarKind) -> &'static str {
+    match scalar {
+        ScalarKind::Uint => "uint",
+        ScalarKind::Sint => "int",
+        _ => "float",
+    }
+}
+
+/// Emit MSL with overrides as function constants where possible.
+pub(crate) fn write_msl_specializable(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+) -> Result<Specializable<String>, Diagnostic> {
+    let entry_point = entry_point.filter(|name| !name.is_empty());
+    let (candidates, mut baked) = candidates(module);
+    let (mut source, located) = locate_overrides(
+        &mut baked,
+        candidates,
+        |values| {
+            let (module, info) = process(module, info, entry_point, values)?;
+            crate::write_msl(&module, &info, entry_point)
+        },
+        |base: &String, variant: &String, candidate, slot| {
+            // Usable if the sentinel is printed as is, and nothing else changes.
+            let a = msl_value(candidate.scalar, sentinel(candidate.scalar, slot, 0).1);
+            let b = msl_value(candidate.scalar, sentinel(candidate.scalar, slot, 1).1);
+            (base.contains(&a) && base.replace(&a, "\0") == variant.replace(&b, "\0")).then_some(a)
+        },
+    )?;
+
+    let ids = override_ids(module, &located);
+    let mut spec_constants = Vec::new();
+    let mut declarations = String::new();
+    for (candidate, literal) in located {
+        let id = ids[&candidate.handle];
+        let ty = msl_type(candidate.scalar);
+        let name = format!("override_{id}");
+        source = source.replace(&literal, &name);
+        match candidate.default_bits {
+            Some(bits) => {
+                let default = msl_value(candidate.scalar, bits);
+                declarations.push_str(&format!(
+                    "constant {ty} {name}_value [[function_constant({id})]];\n\
+                     constant {ty} {name} = is_function_constant_defined({name}_value) ? {name}_value : {default};\n"
+                ));
+            }
+            None => {
+                declarations.push_str(&format!(
+                    "constant {ty} {name} [[function_constant({id})]];\n"
+                ));
+            }
+        }
+        spec_constants.push(spec_constant(module, &candidate, id));
+    }
+
+    if !declarations.is_empty() {
+        let at = header_end(&source);
+        source.insert_str(at, &format!("\n{declarations}"));
+    }
+
+    spec_constants.sort_by_key(|c| c.id);
+    baked.sort();
+    Ok(Specializable {
+        output: source,
+        spec_constants,
+        baked,
+    })
+}
+
+/// Byte offset just past the leading comment, `#include` and `using` lines.
+pub(crate) fn header_end(source: &str) -> usize {
+    let mut end = 0;
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#include") || trimmed.starts_with("using ") {
+            end = offset + line.len();
+        } else if !(trimmed.is_empty() || trimmed.starts_with("//")) {
+            break;
+        }
+        offset += line.len();
+    }
+    end
+}
+
+/// WGSL -> MSL with `override`s emitted as function constants, indexed by
+/// their `@id`s, so one library can be specialized through
+/// `MTLFunctionConstantValues` instead of recompiling per configuration.
+/// Overrides with a default fall back to it when no value is supplied.
+/// Overrides that cannot become function constants are baked to their
+/// defaults and listed in `baked`, as with `wgslToSpirvSpecializable`.
+#[wasm_bindgen(js_name = wgslToMslSpecializable)]
+pub fn wgsl_to_msl_specializable(
+    wgsl: &str,
+    entry_point: Option<String>,
+) -> Result<SpecializableMsl, JsValue> {
+    let (module, info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    let result = write_msl_specializable(&module, &info, entry_point.as_deref()).map_err(throw)?;
+    Ok(SpecializableMsl {
+        source: result.output,
+        function_constants: result.spec_constants,
+        baked: result.baked,
+    })
+}
+
+// ============================================================================
+// Override Reflection
+// ============================================================================
+//
+// An entry point depends on an override its function or any callee reads,
+// on one its workgroup size or the length of a used workgroup array names,
+// and on every override those defaults are computed from. naga orders
+// functions after their callees, so one pass over the arena suffices.
+
+type Overrides = HashSet<naga::Handle<naga::Override>>;
+
+/// Add the overrides global expression `expr` reads, and those their
+/// defaults read, to `out`.
+fn global_overrides(module: &naga::Module, expr: naga::Handle<Expression>, out: &mut Overrides) {
+    let expression = &module.global_expressions[expr];
+    if let Expression::Override(h) = *expression
+        && out.insert(h)
+        && let Some(init) = module.overrides[h].init
+    {
+        global_overrides(module, init, out);
+    }
+    for operand in crate::rewrite::operands(expression) {
+        global_overrides(module, operand, out);
+    }
+}
+
+fn function_overrides(
+    module: &naga::Module,
+    function: &naga::Function,
+    callees: &HashMap<naga::Handle<naga::Function>, Overrides>,
+) -> Overrides {
+    let mut used = Overrides::new();
+    for (_, expr) in function.expressions.iter() {
+        if let Expression::Override(h) = *expr
+            && used.insert(h)
+            && let Some(init) = module.overrides[h].init
+        {
+            global_overrides(module, init, &mut used);
+        }
+    }
+    crate::rewrite::for_each_statement(&mut function.body.clone(), &mut |statement| {
+        if let naga::Statement::Call { function, .. } = statement
+            && let Some(overrides) = callees.get(function)
+        {
+            used.extend(overrides);
+        }
+    });
+    used
+}
+
+/// The default of override `o` as a pipeline constant value.
+fn default_value(module: &naga::Module, o: &naga::Override) -> Option<f64> {
+    match module.global_expressions[o.init?] {
+        Expression::Literal(Literal::Bool(v)) => Some(v as u32 as f64),
+        Expression::Literal(Literal::U32(v)) => Some(v as f64),
+        Expression::Literal(Literal::I32(v)) => Some(v as f64),
+        Expression::Literal(Literal::F32(v)) => Some(v as f64),
+        Expression::Literal(Literal::F64(v)) => Some(v),
+        Expression::Literal(Literal::F16(v)) => Some(v.to_f64()),
+        _ => None,
+    }
+}
+
+/// Every override of a validated module, with the entry points it affects.
+pub(crate) fn reflect_overrides(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+) -> Vec<crate::OverrideInfo> {
+    let mut functions = HashMap::new();
+    for (handle, function) in module.functions.iter() {
+        let used = function_overrides(module, function, &functions);
+        functions.insert(handle, used);
+    }
+
+    let entry_points: Vec<(&str, Overrides)> = module
+        .entry_points
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let mut used = function_overrides(module, &entry.function, &functions);
+            for size in entry.workgroup_size_overrides.iter().flatten().flatten() {
+                global_overrides(module, *size, &mut used);
+            }
+            let usage = info.get_entry_point(index);
+            for (handle, var) in module.global_variables.iter() {
+                if let TypeInner::Array {
+                    size: naga::ArraySize::Pending(h),
+                    ..
+                } = module.types[var.ty].inner
+                    && !usage[handle].is_empty()
+                    && used.insert(h)
+                    && let Some(init) = module.overrides[h].init
+                {
+                    global_overrides(module, init, &mut used);
+                }
+            }
+            (entry.name.as_str(), used)
+        })
+        .collect();
+
+    module
+        .overrides
+        .iter()
+        .map(|(handle, o)| crate::OverrideInfo {
+            name: override_label(o),
+            id: o.id.map(u32::from),
+            type_name: crate::get_type_name(module, o.ty).unwrap_or_else(|| "unknown".to_string()),
+            default_value: default_value(module, o),
+            has_default: o.init.is_some(),
+            entry_points: entry_points
+                .iter()
+                .filter(|(_, used)| used.contains(&handle))
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @id(7) override scale: f32 = 2.0;
+        override count: u32 = 3u;
+        override size: u32 = 4u;
+        override offset: i32;
+
+        @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+
+        @compute @workgroup_size(size)
+        fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+            if id.x < count {
+                data[id.x] = data[id.x] * scale + f32(offset);
+            }
+        }
+    "#;
+
+    fn specializable() -> Specializable<Vec<u32>> {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        write_spirv_specializable(&module, &info, None).unwrap()
+    }
+
+    #[test]
+    fn overrides_become_spec_constants() {
+        let result = specializable();
+        let ids: Vec<_> = result
+            .spec_constants
+            .iter()
+            .map(|c| (c.id, c.name.as_deref().unwrap(), c.scalar.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (0, "count", "u32"),
+                (1, "offset", "i32"),
+                (7, "scale", "f32")
+            ]
+        );
+        assert_eq!(result.spec_constants[2].default_value, Some(2.0));
+        assert_eq!(result.spec_constants[1].default_value, None);
+
+        let insts = spv::instructions(&result.output).unwrap();
+        let spec = insts
+            .iter()
+            .filter(|i| i.op() == Some(Op::SpecConstant))
+            .count();
+        assert_eq!(spec, 3);
+        let decorated = insts
+            .iter()
+            .filter(|i| {
+                i.op() == Some(Op::Decorate) && i.operands()[1] == Decoration::SpecId as u32
+            })
+            .count();
+        assert_eq!(decorated, 3);
+    }
+
+    #[test]
+    fn workgroup_size_overrides_are_baked() {
+        let result = specializable();
+        assert_eq!(result.baked, vec!["size"]);
+
+        let module = naga::front::spv::parse_u8_slice(
+            &spv::bytes_from_words(&result.output),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(module.entry_points[0].workgroup_size, [4, 1, 1]);
+        assert_eq!(module.overrides.len(), 3);
+    }
+
+    #[test]
+    fn folded_arithmetic_is_baked() {
+        let source = r#"
+            override half: f32 = 0.5;
+            @fragment fn main() -> @location(0) vec4<f32> {
+                return vec4<f32>(half * 2.0);
+            }
+        "#;
+        let (module, info) = crate::parse_and_validate(source).unwrap();
+        let result = write_spirv_specializable(&module, &info, None).unwrap();
+        assert!(result.spec_constants.is_empty());
+        assert_eq!(result.baked, vec!["half"]);
+    }
+
+    #[test]
+    fn overrides_become_function_constants() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let result = write_msl_specializable(&module, &info, None).unwrap();
+        let msl = &result.output;
+
+        assert!(msl.contains("constant float override_7_value [[function_constant(7)]];"));
+        assert!(
+            msl.contains(
+                "is_function_constant_defined(override_7_value) ? override_7_value : 2.0;"
+            )
+        );
+        assert!(msl.contains("constant int override_1 [[function_constant(1)]];"));
+        assert!(msl.contains("override_0"));
+        assert!(!msl.contains("1589641216"));
+        assert!(msl.find("using metal::uint;").unwrap() < msl.find("override_0").unwrap());
+
+        let ids: Vec<_> = result.spec_constants.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![0, 1, 7]);
+        assert_eq!(result.baked, vec!["size"]);
+    }
+
+    #[test]
+    fn msl_values_match_the_backend() {
+        assert_eq!(msl_value(ScalarKind::Uint, 3), "3u");
+        assert_eq!(msl_value(ScalarKind::Sint, -4i32 as u32), "-4");
+        assert_eq!(msl_value(ScalarKind::Float, 2.5f32.to_bits()), "2.5");
+        assert_eq!(msl_value(ScalarKind::Float, 2.0f32.to_bits()), "2.0");
+    }
+
+    #[test]
+    fn reflection_lists_overrides_and_their_entry_points() {
+        let source = r#"
+            @id(3) override radius: u32 = 2u;
+            override width: u32 = radius * 2u + 1u;
+            override enabled: bool = true;
+            override gain: f32;
+
+            var<workgroup> tile: array<f32, width>;
+
+            fn weight() -> f32 { return gain; }
+
+            @compute @workgroup_size(64)
+            fn blur(@builtin(local_invocation_index) i: u32) {
+                tile[i % width] = weight();
+            }
+
+            @compute @workgroup_size(1)
+            fn other() {
+                if enabled { return; }
+            }
+        "#;
+        let reflection = crate::reflect(source).unwrap();
+        let overrides: Vec<_> = reflection
+            .overrides
+            .iter()
+            .map(|o| {
+                (
+                    o.name.as_str(),
+                    o.id,
+                    o.type_name.as_str(),
+                    o.default_value,
+                    o.has_default,
+                    o.entry_points.join(","),
+                )
+            })
+            .collect();
+        assert_eq!(
+            overrides,
+            [
+                (
+                    "radius",
+                    Some(3),
+                    "u32",
+                    Some(2.0),
+                    true,
+                    "blur".to_string()
+                ),
+                ("width", None, "u32", None, true, "blur".to_string()),
+                (
+                    "enabled",
+                    None,
+                    "bool",
+                    Some(1.0),
+                    true,
+                    "other".to_string()
+                ),
+                ("gain", None, "f32", None, false, "blur".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn baked_overrides_size_workgroups_and_arrays() {
+        let source = r#"
+            @id(1) override size: u32 = 4u;
+            override scale: f32;
+            var<workgroup> tile: array<f32, size * 2u>;
+
+            @compute @workgroup_size(size)
+            fn main(@builtin(local_invocation_index) i: u32) {
+                tile[i] = scale;
+            }
+        "#;
+        let (module, info) = crate::parse_and_validate(source).unwrap();
+        let values = BTreeMap::from([("size".to_string(), 8.0), ("scale".to_string(), 0.5)]);
+        let (baked, _) = bake(&module, &info, None, &values).unwrap();
+        assert_eq!(baked.entry_points[0].workgroup_size, [8, 1, 1]);
+        let tile = baked.global_variables.iter().next().unwrap().1;
+        let TypeInner::Array { size, .. } = baked.types[tile.ty].inner else {
+            panic!("tile is not an array");
+        };
+        assert!(matches!(
+            size.resolve(baked.to_ctx()),
+            Ok(naga::proc::IndexableLength::Known(16))
+        ));
+
+        let options = |overrides: &[(&str, f64)]| crate::spv::SpirvOptions {
+            overrides: overrides.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+            ..Default::default()
+        };
+        let spirv = |options| crate::write_spirv_configured(&module, &info, None, None, &options);
+        assert!(spirv(options(&[("1", 2.0), ("scale", 1.0)])).is_ok());
+        let missing = spirv(options(&[])).unwrap_err();
+        assert!(
+            missing.message.contains("Missing value"),
+            "{}",
+            missing.message
+        );
+        let unknown = spirv(options(&[("nope", 1.0)])).unwrap_err();
+        assert_eq!(unknown.message, "Unknown override 'nope'");
+    }
+}
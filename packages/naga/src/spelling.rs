@@ -0,0 +1,279 @@
+use naga::Span;
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::SourceSpan;
+use crate::lexer::{TokenKind, line_comments, tokenize};
+use crate::lint::{LintWarning, RULES};
+
+// ============================================================================
+// Spellchecking
+// ============================================================================
+//
+// Some misspellings cost nothing at compile time: WGSL ignores diagnostic
+// rules it does not know, and a mistyped `// metis-ignore` comment is just a
+// comment. Others fail with an error naming the typo but not the fix, like
+// `@grup`. This pass works on tokens and comments alone, so it also runs on
+// sources naga rejects, and reports only words close to a known one, taking
+// a suggestion as evidence of a typo rather than of something new.
+
+/// Attributes WGSL and naga accept.
+const ATTRIBUTES: &[&str] = &[
+    "align",
+    "binding",
+    "blend_src",
+    "builtin",
+    "compute",
+    "const",
+    "diagnostic",
+    "early_depth_test",
+    "fragment",
+    "group",
+    "id",
+    "interpolate",
+    "invariant",
+    "location",
+    "must_use",
+    "size",
+    "vertex",
+    "workgroup_size",
+];
+
+const SEVERITIES: &[&str] = &["error", "warning", "info", "off"];
+
+/// Unqualified rule names; `ns.rule` names belong to other tools.
+const DIAGNOSTIC_RULES: &[&str] = &["derivative_uniformity", "subgroup_uniformity"];
+
+/// Comment directives: lint suppression, and the fingerprints our textual
+/// backends write.
+pub(crate) const IGNORE_DIRECTIVE: &str = "metis-ignore";
+const DIRECTIVES: &[&str] = &[IGNORE_DIRECTIVE, "metis-source", "metis-provenance"];
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The known word `word` is most likely a typo of: at most two edits away,
+/// and fewer than half its length.
+pub(crate) fn suggestion<'a>(word: &str, known: &[&'a str]) -> Option<&'a str> {
+    let limit = (word.chars().count().saturating_sub(1) / 2).min(2);
+    known
+        .iter()
+        .map(|k| (edit_distance(word, k), *k))
+        .filter(|(distance, _)| (1..=limit).contains(distance))
+        .min()
+        .map(|(_, k)| k)
+}
+
+struct Checker<'a> {
+    source: &'a str,
+    warnings: Vec<LintWarning>,
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, rule: &str, message: String, start: usize, len: usize) {
+        self.warnings.push(LintWarning {
+            rule: rule.to_string(),
+            message,
+            span: SourceSpan::new(self.source, Span::new(start as u32, (start + len) as u32)),
+        });
+    }
+
+    fn tokens(&mut self) {
+        let tokens = tokenize(self.source);
+        for (i, token) in tokens.iter().enumerate() {
+            if token.is_punct('@')
+                && let Some(name) = tokens.get(i + 1).filter(|t| t.kind == TokenKind::Ident)
+                && !ATTRIBUTES.contains(&name.text)
+                && let Some(known) = suggestion(name.text, ATTRIBUTES)
+            {
+                let message = format!(
+                    "Unknown attribute '@{}'; did you mean '@{known}'?",
+                    name.text
+                );
+                self.warn("misspelled-attribute", message, name.start, name.text.len());
+            }
+            // `diagnostic(severity, rule)`, as a directive or an attribute.
+            if !token.is_ident("diagnostic") || !tokens.get(i + 1).is_some_and(|t| t.is_punct('('))
+            {
+                continue;
+            }
+            if let Some(severity) = tokens.get(i + 2).filter(|t| t.kind == TokenKind::Ident)
+                && !SEVERITIES.contains(&severity.text)
+                && let Some(known) = suggestion(severity.text, SEVERITIES)
+            {
+                let message = format!(
+                    "Unknown diagnostic severity '{}'; did you mean '{known}'?",
+                    severity.text
+                );
+                self.warn(
+                    "misspelled-diagnostic",
+                    message,
+                    severity.start,
+                    severity.text.len(),
+                );
+            }
+            if let Some(rule) = tokens.get(i + 4).filter(|t| t.kind == TokenKind::Ident)
+                && tokens.get(i + 3).is_some_and(|t| t.is_punct(','))
+                && !tokens.get(i + 5).is_some_and(|t| t.is_punct('.'))
+                && !DIAGNOSTIC_RULES.contains(&rule.text)
+                && let Some(known) = suggestion(rule.text, DIAGNOSTIC_RULES)
+            {
+                let message = format!(
+                    "Unknown diagnostic rule '{}' is ignored; did you mean '{known}'?",
+                    rule.text
+                );
+                self.warn(
+                    "misspelled-diagnostic",
+                    message,
+                    rule.start,
+                    rule.text.len(),
+                );
+            }
+        }
+    }
+
+    fn comments(&mut self) {
+        for (at, text) in line_comments(self.source) {
+            let start = at + (text.len() - text.trim_start().len());
+            let text = text.trim_start();
+            let word = &text[..text
+                .find(|c: char| c == ':' || c.is_whitespace())
+                .unwrap_or(text.len())];
+            if word == IGNORE_DIRECTIVE {
+                let Some(list) = text[word.len()..].trim_start().strip_prefix(':') else {
+                    continue;
+                };
+                let list_start = start + text.len() - list.len();
+                for rule in list.split(',') {
+                    let name = rule.trim();
+                    if name.is_empty() || RULES.contains(&name) {
+                        continue;
+                    }
+                    let offset = list_start
+                        + (rule.as_ptr() as usize - list.as_ptr() as usize)
+                        + (rule.len() - rule.trim_start().len());
+                    let message = match suggestion(name, RULES) {
+                        Some(known) => format!(
+                            "Unknown lint rule '{name}' in '{IGNORE_DIRECTIVE}'; did you mean '{known}'?"
+                        ),
+                        None => format!("Unknown lint rule '{name}' in '{IGNORE_DIRECTIVE}'"),
+                    };
+                    self.warn("misspelled-directive", message, offset, name.len());
+                }
+            } else if !DIRECTIVES.contains(&word)
+                && let Some(known) = suggestion(word, DIRECTIVES)
+            {
+                let message = format!(
+                    "'{word}' is not a directive, so the comment does nothing; did you mean '{known}'?"
+                );
+                self.warn("misspelled-directive", message, start, word.len());
+            }
+        }
+    }
+}
+
+/// Likely typos in `source`, in source order.
+pub(crate) fn spellcheck(source: &str) -> Vec<LintWarning> {
+    let mut checker = Checker {
+        source,
+        warnings: Vec::new(),
+    };
+    checker.tokens();
+    checker.comments();
+    let mut warnings = checker.warnings;
+    warnings.sort_by_key(|w| w.span.as_ref().map_or(u32::MAX, |s| s.start));
+    warnings
+}
+
+/// Flags likely-misspelled attribute names (`misspelled-attribute`),
+/// `diagnostic` severities and rules (`misspelled-diagnostic`; WGSL ignores
+/// rules it does not know) and comment directives such as `metis-ignore`
+/// and its rule list (`misspelled-directive`), each with a suggestion. Works
+/// on sources that do not parse; `lintWgsl` runs the same checks with its
+/// `spellcheck` option.
+#[wasm_bindgen(js_name = spellcheckWgsl)]
+pub fn spellcheck_wgsl(wgsl: &str) -> Vec<LintWarning> {
+    spellcheck(wgsl)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos_get_suggestions() {
+        let source = "\
+diagnostic(warnin, derivative_uniformty);
+@grup(0) @binding(0) var<uniform> tint: vec4<f32>;
+// metis-ignor: unused-global
+// metis-ignore: unused-globl, not-a-rule
+@fragment @diagnostic(warning, my_tool.rule)
+fn fs() -> @location(0) vec4<f32> { return tint; } // metis-source: sha256=00
+";
+        let warnings: Vec<_> = spellcheck(source)
+            .into_iter()
+            .map(|w| (w.rule, w.span.unwrap().line, w.message))
+            .collect();
+        let expected = [
+            (
+                "misspelled-diagnostic",
+                1,
+                "severity 'warnin'; did you mean 'warning'?",
+            ),
+            (
+                "misspelled-diagnostic",
+                1,
+                "'derivative_uniformty' is ignored; did you mean 'derivative_uniformity'?",
+            ),
+            ("misspelled-attribute", 2, "'@grup'; did you mean '@group'?"),
+            (
+                "misspelled-directive",
+                3,
+                "'metis-ignor' is not a directive",
+            ),
+            (
+                "misspelled-directive",
+                4,
+                "'unused-globl' in 'metis-ignore'; did you mean 'unused-global'?",
+            ),
+            ("misspelled-directive", 4, "'not-a-rule' in 'metis-ignore'"),
+        ];
+        assert_eq!(warnings.len(), expected.len(), "{warnings:?}");
+        for ((rule, line, message), (want_rule, want_line, want)) in warnings.iter().zip(expected) {
+            assert_eq!((rule.as_str(), *line), (want_rule, want_line));
+            assert!(message.contains(want), "{message}");
+        }
+        let rule = &spellcheck(source)[4];
+        let span = rule.span.as_ref().unwrap();
+        assert_eq!(
+            &source[span.start as usize..span.end as usize],
+            "unused-globl"
+        );
+    }
+
+    #[test]
+    fn distant_words_are_not_typos() {
+        assert_eq!(suggestion("grup", ATTRIBUTES), Some("group"));
+        assert_eq!(suggestion("idd", ATTRIBUTES), Some("id"));
+        // Short words need to be near-exact; unrelated ones are left alone.
+        assert_eq!(suggestion("ix", ATTRIBUTES), None);
+        assert_eq!(suggestion("metis", DIRECTIVES), None);
+        let clean = "@compute @workgroup_size(1) fn main() {} // TODO: tidy";
+        assert!(spellcheck(clean).is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use naga::valid::{Capabilities, ValidationError, ValidationFlags};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::catalog::localize;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// SPIR-V Validation Types
+// ============================================================================
+
+/// What `validateSpirv` found in a binary.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpirvValidation {
+    #[wasm_bindgen(readonly)]
+    pub valid: bool,
+    /// Empty when `valid`.
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<SpirvDiagnostic>,
+    /// Set once the binary parsed, even if it then failed validation.
+    #[wasm_bindgen(readonly)]
+    pub entry_points: Vec<SpirvEntryPoint>,
+}
+
+#[wasm_bindgen]
+impl SpirvValidation {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpirvDiagnostic {
+    #[wasm_bindgen(readonly)]
+    pub severity: String,
+    /// `"parse"` if naga could not read the binary, `"validate"` if it read
+    /// it but the module is invalid.
+    #[wasm_bindgen(readonly)]
+    pub phase: String,
+    #[wasm_bindgen(readonly)]
+    pub message: String,
+    /// The function the error is in, for errors inside one.
+    #[wasm_bindgen(readonly)]
+    pub function: Option<String>,
+    /// The entry point the error is in, for errors inside one.
+    #[wasm_bindgen(readonly)]
+    pub entry_point: Option<String>,
+    /// Underlying causes, outermost first.
+    #[wasm_bindgen(readonly)]
+    pub notes: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl SpirvDiagnostic {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SpirvEntryPoint {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// `"vertex"`, `"fragment"`, `"compute"`, ...
+    #[wasm_bindgen(readonly)]
+    pub stage: String,
+}
+
+#[wasm_bindgen]
+impl SpirvEntryPoint {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// SPIR-V Validation Implementation
+// ============================================================================
+//
+// The binary goes through naga's SPIR-V frontend and then its validator,
+// with every check on and only the capabilities asked for. The frontend
+// keeps the binary's coordinate space, since nothing is emitted, and
+// accepts `OpCapability`s it does not support, naga's own `f16` output
+// among them: the validator decides what the module actually uses, and
+// instructions the frontend cannot read still fail. A binary naga cannot
+// read is not necessarily invalid SPIR-V, only one naga would not accept;
+// binaries from other toolchains that pass here will also load through
+// naga-based pipelines. SPIR-V carries no source, so diagnostics name the
+// function or entry point instead of a span.
+
+/// The capabilities named in `names`, naga's validator flag names such as
+/// `SHADER_FLOAT16`; all of them if `names` is `None`.
+pub(crate) fn capabilities(names: Option<&[String]>) -> Result<Capabilities, Diagnostic> {
+    let Some(names) = names else {
+        return Ok(Capabilities::all());
+    };
+    names.iter().try_fold(Capabilities::empty(), |all, name| {
+        Capabilities::from_name(name)
+            .map(|c| all | c)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown capability '{name}'")))
+    })
+}
+
+fn diagnostic(phase: &str, error: &dyn std::error::Error) -> SpirvDiagnostic {
+    let mut notes = Vec::new();
+    let mut cause = error.source();
+    while let Some(next) = cause {
+        notes.push(localize(next.to_string()));
+        cause = next.source();
+    }
+    SpirvDiagnostic {
+        severity: "error".to_string(),
+        phase: phase.to_string(),
+        message: localize(error.to_string()),
+        function: None,
+        entry_point: None,
+        notes,
+    }
+}
+
+pub(crate) fn validate_spirv_bytes(bytes: &[u8], capabilities: Capabilities) -> SpirvValidation {
+    let options = naga::front::spv::Options {
+        adjust_coordinate_space: false,
+        strict_capabilities: false,
+        ..Default::default()
+    };
+    let module = match naga::front::spv::parse_u8_slice(bytes, &options) {
+        Ok(module) => module,
+        Err(error) => {
+            return SpirvValidation {
+                valid: false,
+                diagnostics: vec![diagnostic("parse", &error)],
+                entry_points: Vec::new(),
+            };
+        }
+    };
+    let entry_points = module
+        .entry_points
+        .iter()
+        .map(|ep| SpirvEntryPoint {
+            name: ep.name.clone(),
+            stage: crate::stage_name(ep.stage).to_string(),
+        })
+        .collect();
+    let diagnostics = match crate::run_validator(&module, ValidationFlags::all(), capabilities) {
+        Ok(_) => Vec::new(),
+        Err(error) => {
+            let mut found = diagnostic("validate", error.as_inner());
+            match error.as_inner() {
+                ValidationError::Function { name, .. } => found.function = Some(name.clone()),
+                ValidationError::EntryPoint { name, .. } => found.entry_point = Some(name.clone()),
+                _ => {}
+            }
+            vec![found]
+        }
+    };
+    SpirvValidation {
+        valid: diagnostics.is_empty(),
+        diagnostics,
+        entry_points,
+    }
+}
+
+/// Checks a SPIR-V binary from another toolchain: parses it with naga's
+/// SPIR-V frontend and runs the validator over the result. `capabilities`
+/// lists the naga capability names the target supports, e.g.
+/// `["SHADER_FLOAT16"]` (see `getPreset`); all of them by default. Never
+/// throws for a bad binary, whose errors come back in `diagnostics`; throws
+/// only for unknown capability names.
+#[wasm_bindgen(js_name = validateSpirv)]
+pub fn validate_spirv(
+    bytes: &[u8],
+    capabilities: Option<Vec<String>>,
+) -> Result<SpirvValidation, JsValue> {
+    let capabilities = self::capabilities(capabilities.as_deref()).map_err(throw)?;
+    Ok(validate_spirv_bytes(bytes, capabilities))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiled_binaries_are_valid() {
+        let shader = r#"
+            @vertex fn vs() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }
+            @compute @workgroup_size(1) fn cs() {}
+        "#;
+        let bytes = crate::compile_spirv(shader, None).unwrap();
+        let result = validate_spirv_bytes(&bytes, Capabilities::all());
+        assert!(result.valid, "{:?}", result.diagnostics);
+        let stages: Vec<_> = result
+            .entry_points
+            .iter()
+            .map(|ep| (ep.name.as_str(), ep.stage.as_str()))
+            .collect();
+        assert_eq!(stages, [("vs", "vertex"), ("cs", "compute")]);
+
+        let error = capabilities(Some(&["SHADER_FLOAT17".to_string()]))
+            .err()
+            .unwrap();
+        assert_eq!(error.message, "Unknown capability 'SHADER_FLOAT17'");
+    }
+
+    #[test]
+    fn errors_carry_phase_and_location() {
+        let result = validate_spirv_bytes(&[0x03, 0x02, 0x23], Capabilities::all());
+        assert!(!result.valid);
+        assert_eq!(result.diagnostics[0].phase, "parse");
+        assert!(result.entry_points.is_empty());
+
+        let shader = r#"
+            enable f16;
+            @group(0) @binding(0) var<uniform> tint: vec4<f16>;
+            @fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(tint); }
+        "#;
+        let bytes = crate::compile_spirv(shader, None).unwrap();
+        let f16 = capabilities(Some(&["SHADER_FLOAT16".to_string()])).unwrap();
+        assert!(validate_spirv_bytes(&bytes, f16).valid);
+
+        let result = validate_spirv_bytes(&bytes, Capabilities::empty());
+        let diagnostic = &result.diagnostics[0];
+        assert_eq!(diagnostic.phase, "validate");
+        assert!(diagnostic.notes[0].contains("FLOAT16"), "{diagnostic:?}");
+        assert_eq!(result.entry_points[0].name, "fs");
+
+        let shader = r#"
+            @fragment fn fs(@builtin(sample_index) i: u32) -> @location(0) vec4<f32> {
+                return vec4<f32>(f32(i));
+            }
+        "#;
+        let bytes = crate::compile_spirv(shader, None).unwrap();
+        let result = validate_spirv_bytes(&bytes, Capabilities::empty());
+        assert_eq!(result.diagnostics[0].entry_point.as_deref(), Some("fs"));
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+
+use naga::back::spv::WriterFlags;
+use serde::Deserialize;
+use spirv::Op;
+
+use crate::Diagnostic;
+
+// ============================================================================
+// SPIR-V Word Utilities
+// ============================================================================
+//
+// Small helpers for post-processing the binaries naga writes (or binaries we
+// are handed). Everything works on the raw word stream; the module layout is
+// never rebuilt, only spliced.
+
+pub(crate) const MAGIC: u32 = 0x0723_0203;
+/// Magic, version, generator, bound, schema.
+pub(crate) const HEADER_WORDS: usize = 5;
+
+/// One instruction in a word stream.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Instruction<'a> {
+    pub opcode: u16,
+    /// Word offset of the instruction in the module.
+    pub offset: usize,
+    /// All words of the instruction, including the opcode/length word.
+    pub words: &'a [u32],
+}
+
+impl Instruction<'_> {
+    pub(crate) fn op(&self) -> Option<Op> {
+        Op::from_u32(self.opcode as u32)
+    }
+
+    /// Operand words, without the opcode/length word.
+    pub(crate) fn operands(&self) -> &[u32] {
+        &self.words[1..]
+    }
+
+    /// Operand word `index`, or an error if the instruction is too short.
+    pub(crate) fn operand(&self, index: usize) -> Result<u32, Diagnostic> {
+        self.operands().get(index).copied().ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Malformed SPIR-V instruction at word {}: missing operand {}",
+                self.offset, index
+            ))
+        })
+    }
+}
+
+/// Little-endian bytes -> words, checking alignment and the magic number.
+pub(crate) fn words_from_bytes(bytes: &[u8]) -> Result<Vec<u32>, Diagnostic> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(Diagnostic::error(
+            "SPIR-V binary length must be multiple of 4",
+        ));
+    }
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    if words.len() < HEADER_WORDS || words[0] != MAGIC {
+        return Err(Diagnostic::error("Not a SPIR-V binary (bad magic number)"));
+    }
+    Ok(words)
+}
+
+/// Words -> little-endian bytes.
+pub(crate) fn bytes_from_words(words: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(words.len() * 4);
+    for w in words {
+        bytes.extend_from_slice(&w.to_le_bytes());
+    }
+    bytes
+}
+
+/// (major, minor) from the header.
+pub(crate) fn version(words: &[u32]) -> (u8, u8) {
+    let v = words[1];
+    ((v >> 16) as u8, (v >> 8) as u8)
+}
+
+/// Split the module body into instructions.
+pub(crate) fn instructions(words: &[u32]) -> Result<Vec<Instruction<'_>>, Diagnostic> {
+    let mut out = Vec::new();
+    let mut offset = HEADER_WORDS;
+    while offset < words.len() {
+        let first = words[offset];
+        let count = (first >> 16) as usize;
+        if count == 0 || offset + count > words.len() {
+            return Err(Diagnostic::error(format!(
+                "Malformed SPIR-V instruction at word {}",
+                offset
+            )));
+        }
+        out.push(Instruction {
+            opcode: first as u16,
+            offset,
+            words: &words[offset..offset + count],
+        });
+        offset += count;
+    }
+    Ok(out)
+}
+
+/// Encode an instruction from its opcode and operand words.
+pub(crate) fn encode(op: Op, operands: &[u32]) -> Vec<u32> {
+    let mut words = Vec::with_capacity(operands.len() + 1);
+    words.push(((operands.len() as u32 + 1) << 16) | op as u32);
+    words.extend_from_slice(operands);
+    words
+}
+
+/// Literal string operand: UTF-8, nul-terminated, padded to a word boundary.
+pub(crate) fn encode_string(s: &str) -> Vec<u32> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Decode a literal string operand, returning it and the number of words it used.
+pub(crate) fn decode_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, w) in words.iter().enumerate() {
+        for b in w.to_le_bytes() {
+            if b == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(b);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+/// Word offset at which an instruction that must follow every instruction in
+/// `after` can be inserted: right before the first instruction not in it.
+pub(crate) fn insertion_point(words: &[u32], after: &[Op]) -> Result<usize, Diagnostic> {
+    Ok(instructions(words)?
+        .iter()
+        .find(|inst| !inst.op().is_some_and(|op| after.contains(&op)))
+        .map_or(words.len(), |inst| inst.offset))
+}
+
+/// Logical layout sections that precede debug instructions.
+pub(crate) const PREAMBLE: &[Op] = &[
+    Op::Capability,
+    Op::Extension,
+    Op::ExtInstImport,
+    Op::MemoryModel,
+    Op::EntryPoint,
+    Op::ExecutionMode,
+    Op::ExecutionModeId,
+];
+
+/// Debug section 7a: source information.
+pub(crate) const DEBUG_SOURCE: &[Op] = &[
+    Op::String,
+    Op::SourceExtension,
+    Op::Source,
+    Op::SourceContinued,
+];
+
+/// Debug sections 7b and 7c: names and processing records.
+pub(crate) const DEBUG_NAMES: &[Op] = &[Op::Name, Op::MemberName, Op::ModuleProcessed];
+
+/// Section 8: annotations.
+pub(crate) const ANNOTATIONS: &[Op] = &[
+    Op::Decorate,
+    Op::MemberDecorate,
+    Op::DecorationGroup,
+    Op::GroupDecorate,
+    Op::GroupMemberDecorate,
+    Op::DecorateId,
+    Op::DecorateString,
+    Op::MemberDecorateString,
+];
+
+/// spirv-dis style assembly of a whole module: header comments, then one
+/// instruction per line with `%id` results. Works on any well-formed
+/// binary, including ones naga cannot load.
+pub(crate) fn disassemble(words: &[u32]) -> Result<String, Diagnostic> {
+    use rspirv::binary::Disassemble;
+
+    let module = rspirv::dr::load_words(words)
+        .map_err(|e| Diagnostic::error(format!("SPIR-V parse error: {e}")))?;
+    Ok(module.disassemble())
+}
+
+// ============================================================================
+// SPIR-V Backend Options
+// ============================================================================
+
+/// Options object accepted by `wgslToSpirvBin`. Anything left out keeps
+/// naga's default, or the preset's SPIR-V version.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SpirvOptions {
+    /// `"1.0"` through `"1.6"`.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Emit `OpName`s for everything. naga only does by default in debug
+    /// builds; varying labels are the separate `labelVaryings` flag.
+    #[serde(default)]
+    pub debug_names: Option<bool>,
+    /// Writer flags to set, replacing naga's defaults.
+    #[serde(default)]
+    pub flags: Option<Vec<String>>,
+    /// Pipeline constant values by override name or `@id`, baked in before
+    /// emitting.
+    #[serde(default)]
+    pub overrides: BTreeMap<String, f64>,
+    /// Strip functions, globals, types and constants the compiled entry
+    /// points never use before emitting.
+    #[serde(default)]
+    pub compact: bool,
+    /// Embed the WGSL in `OpSource` and put an `OpLine` before each
+    /// statement, so debuggers and crash reports can show WGSL lines. Implies
+    /// `debugNames`.
+    #[serde(default)]
+    pub source_map: bool,
+    /// The file name `OpSource` and source maps give the WGSL.
+    #[serde(default)]
+    pub file_name: Option<String>,
+    /// Skip validation, for WGSL already validated or written from a
+    /// validated module, e.g. by `ShaderModule.toWgsl`. Invalid input then
+    /// fails in the backend, or yields broken SPIR-V.
+    #[serde(default)]
+    pub assume_valid: bool,
+    /// Name the entry point is given in the output, e.g. `main`; it is
+    /// still picked by its WGSL name.
+    #[serde(default)]
+    pub entry_point_name: Option<String>,
+}
+
+/// `flags` names, camel-cased from naga's.
+const WRITER_FLAGS: &[(&str, WriterFlags)] = &[
+    ("debug", WriterFlags::DEBUG),
+    (
+        "adjustCoordinateSpace",
+        WriterFlags::ADJUST_COORDINATE_SPACE,
+    ),
+    ("labelVaryings", WriterFlags::LABEL_VARYINGS),
+    ("forcePointSize", WriterFlags::FORCE_POINT_SIZE),
+    ("clampFragDepth", WriterFlags::CLAMP_FRAG_DEPTH),
+];
+
+impl SpirvOptions {
+    pub(crate) fn apply(&self, options: &mut naga::back::spv::Options) -> Result<(), Diagnostic> {
+        if let Some(version) = &self.version {
+            options.lang_version = match version.as_str() {
+                "1.0" => (1, 0),
+                "1.1" => (1, 1),
+                "1.2" => (1, 2),
+                "1.3" => (1, 3),
+                "1.4" => (1, 4),
+                "1.5" => (1, 5),
+                "1.6" => (1, 6),
+                _ => {
+                    return Err(Diagnostic::error(format!(
+                        "Unknown SPIR-V version '{}'; expected 1.0 through 1.6",
+                        version
+                    )));
+                }
+            };
+        }
+        if let Some(names) = &self.flags {
+            let mut flags = WriterFlags::empty();
+            for name in names {
+                let (_, flag) = WRITER_FLAGS
+                    .iter()
+                    .find(|(known, _)| known == name)
+                    .ok_or_else(|| {
+                        let known: Vec<_> = WRITER_FLAGS.iter().map(|(n, _)| *n).collect();
+                        Diagnostic::error(format!(
+                            "Unknown SPIR-V writer flag '{}'; expected one of {}",
+                            name,
+                            known.join(", ")
+                        ))
+                    })?;
+                flags |= *flag;
+            }
+            options.flags = flags;
+        }
+        if let Some(debug) = self.debug_names {
+            options.flags.set(WriterFlags::DEBUG, debug);
+        }
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_round_trip() {
+        for s in ["", "abc", "abcd", "metis"] {
+            let words = encode_string(s);
+            assert_eq!(words.len(), s.len() / 4 + 1);
+            assert_eq!(decode_string(&words), (s.to_string(), words.len()));
+        }
+    }
+
+    #[test]
+    fn rejects_non_spirv() {
+        assert!(words_from_bytes(&[1, 2, 3]).is_err());
+        assert!(words_from_bytes(&[0; 20]).is_err());
+    }
+
+    #[test]
+    fn naga_output_splits_into_instructions() {
+        let bytes = crate::compile_spirv("@compute @workgroup_size(1) fn main() {}", None).unwrap();
+        let words = words_from_bytes(&bytes).unwrap();
+        let insts = instructions(&words).unwrap();
+        assert_eq!(insts[0].op(), Some(Op::Capability));
+        assert!(insts.iter().any(|i| i.op() == Some(Op::EntryPoint)));
+        assert_eq!(
+            insts.iter().map(|i| i.words.len()).sum::<usize>(),
+            words.len() - HEADER_WORDS
+        );
+    }
+
+    #[test]
+    fn disassembly_shows_spirv_details() {
+        let source = r#"
+            @group(0) @binding(1) var<storage, read_write> data: array<u32>;
+            @compute @workgroup_size(8) fn main() { data[0] = 1u; }
+        "#;
+        let bytes = crate::compile_spirv(source, None).unwrap();
+        let mut words = words_from_bytes(&bytes).unwrap();
+        // A debug record a WGSL round trip would lose.
+        let at = insertion_point(&words, PREAMBLE).unwrap();
+        words.splice(
+            at..at,
+            encode(Op::SourceExtension, &encode_string("metis-test")),
+        );
+
+        let text = disassemble(&words).unwrap();
+        assert!(text.starts_with("; SPIR-V"));
+        assert!(text.contains("OpCapability Shader"));
+        assert!(text.contains("OpEntryPoint GLCompute"));
+        assert!(text.contains("LocalSize 8 1 1"));
+        assert!(text.contains("Binding 1"));
+        assert!(text.contains("OpSourceExtension \"metis-test\""));
+
+        assert!(disassemble(&words[..words.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn backend_options_set_version_and_names() {
+        let source = "@compute @workgroup_size(1) fn main() { var counter: u32; counter = 1u; }";
+        let compile = |options: SpirvOptions| {
+            let module = crate::parse_wgsl(source).unwrap();
+            let info = crate::validate_module(&module).unwrap();
+            let bytes = crate::write_spirv_configured(&module, &info, None, None, &options);
+            words_from_bytes(&bytes.unwrap()).unwrap()
+        };
+        let has_name = |words: &[u32]| {
+            instructions(words).unwrap().iter().any(|i| {
+                i.op() == Some(Op::Name) && decode_string(&i.operands()[1..]).0 == "counter"
+            })
+        };
+
+        let words = compile(SpirvOptions {
+            version: Some("1.3".to_string()),
+            debug_names: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(version(&words), (1, 3));
+        assert!(has_name(&words));
+
+        let words = compile(SpirvOptions {
+            flags: Some(vec!["debug".to_string(), "forcePointSize".to_string()]),
+            debug_names: Some(false),
+            ..Default::default()
+        });
+        assert_eq!(version(&words), (1, 0));
+        assert!(!has_name(&words));
+
+        let mut options = naga::back::spv::Options::default();
+        let bad_version = SpirvOptions {
+            version: Some("2.0".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_version.apply(&mut options).is_err());
+        let bad_flag = SpirvOptions {
+            flags: Some(vec!["DEBUG".to_string()]),
+            ..Default::default()
+        };
+        assert!(bad_flag.apply(&mut options).is_err());
+    }
+}
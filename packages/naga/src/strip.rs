@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use spirv::Op;
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::spv;
+
+/// Debug instructions that never affect execution.
+const DEBUG_OPS: &[Op] = &[
+    Op::SourceContinued,
+    Op::Source,
+    Op::SourceExtension,
+    Op::Name,
+    Op::MemberName,
+    Op::String,
+    Op::Line,
+    Op::NoLine,
+    Op::ModuleProcessed,
+];
+
+// ============================================================================
+// Metadata Stripping
+// ============================================================================
+
+/// Remove debug and non-semantic instructions: names, source text, line info,
+/// processing records (including our provenance records), and every
+/// `NonSemantic.*` extended instruction set with the instructions using it.
+/// Result ids are left allocated; the bound does not shrink.
+pub(crate) fn strip_words(words: &[u32]) -> Result<Vec<u32>, Diagnostic> {
+    let insts = spv::instructions(words)?;
+
+    // Result ids of non-semantic instruction set imports.
+    let mut non_semantic_sets = HashSet::new();
+    for inst in insts.iter().filter(|i| i.op() == Some(Op::ExtInstImport)) {
+        let set = inst.operand(0)?;
+        let (name, _) = spv::decode_string(&inst.operands()[1..]);
+        if name.starts_with("NonSemantic.") {
+            non_semantic_sets.insert(set);
+        }
+    }
+
+    let mut out = words[..spv::HEADER_WORDS].to_vec();
+    for inst in &insts {
+        let drop = match inst.op() {
+            Some(op) if DEBUG_OPS.contains(&op) => true,
+            Some(Op::ExtInstImport) => non_semantic_sets.contains(&inst.operand(0)?),
+            // OpExtInst <result type> <result> <set> <instruction> ...
+            Some(Op::ExtInst) => non_semantic_sets.contains(&inst.operand(2)?),
+            Some(Op::Extension) => {
+                spv::decode_string(inst.operands()).0 == "SPV_KHR_non_semantic_info"
+            }
+            _ => false,
+        };
+        if !drop {
+            out.extend_from_slice(inst.words);
+        }
+    }
+    Ok(out)
+}
+
+/// Returns a copy of a SPIR-V binary with all debug and non-semantic
+/// instructions removed, for minimal shipping builds. Works on any SPIR-V,
+/// not just binaries produced by this package.
+#[wasm_bindgen(js_name = stripMetadata)]
+pub fn strip_metadata(spirv_bytes: &[u8]) -> Result<Box<[u8]>, JsValue> {
+    let words = spv::words_from_bytes(spirv_bytes).map_err(throw)?;
+    let stripped = strip_words(&words).map_err(throw)?;
+    Ok(spv::bytes_from_words(&stripped).into_boxed_slice())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::{Provenance, embed_spirv, read_spirv};
+
+    const SHADER: &str = r#"
+        struct Params { scale: f32 }
+        @group(0) @binding(0) var<uniform> params: Params;
+
+        @compute @workgroup_size(1)
+        fn main() {
+            let s = params.scale;
+        }
+    "#;
+
+    fn ops(words: &[u32]) -> Vec<Op> {
+        spv::instructions(words)
+            .unwrap()
+            .iter()
+            .filter_map(|i| i.op())
+            .collect()
+    }
+
+    #[test]
+    fn removes_names_and_provenance() {
+        let bytes = crate::compile_spirv(SHADER, None).unwrap();
+        let mut words = spv::words_from_bytes(&bytes).unwrap();
+        embed_spirv(&mut words, &Provenance::new(SHADER, &"spirv")).unwrap();
+
+        let stripped = strip_words(&words).unwrap();
+        assert!(read_spirv(&stripped).unwrap().is_none());
+        assert!(ops(&stripped).iter().all(|op| !DEBUG_OPS.contains(op)));
+        assert!(stripped.len() < words.len());
+
+        // Still loadable, with the same entry points.
+        let module = naga::front::spv::parse_u8_slice(
+            &spv::bytes_from_words(&stripped),
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!(module.entry_points[0].name, "main");
+    }
+
+    #[test]
+    fn removes_non_semantic_sets() {
+        let mut words =
+            spv::words_from_bytes(&crate::compile_spirv(SHADER, None).unwrap()).unwrap();
+        let set_id = words[3];
+        words[3] += 1;
+        let mut import = vec![set_id];
+        import.extend(spv::encode_string("NonSemantic.Metis"));
+        let at = spv::insertion_point(&words, &[Op::Capability, Op::Extension]).unwrap();
+        words.splice(at..at, spv::encode(Op::ExtInstImport, &import));
+
+        let imports = |w: &[u32]| ops(w).iter().filter(|op| **op == Op::ExtInstImport).count();
+        let stripped = strip_words(&words).unwrap();
+        assert_eq!(imports(&stripped), imports(&words) - 1);
+    }
+
+    #[test]
+    fn truncated_instructions_are_errors() {
+        let mut words =
+            spv::words_from_bytes(&crate::compile_spirv(SHADER, None).unwrap()).unwrap();
+        let at = spv::insertion_point(&words, &[Op::Capability, Op::Extension]).unwrap();
+        words.splice(at..at, spv::encode(Op::ExtInstImport, &[]));
+        let error = strip_words(&words).unwrap_err();
+        assert!(error.message.contains("missing operand 0"));
+    }
+
+    #[test]
+    fn untouched_without_metadata() {
+        let words = spv::words_from_bytes(&crate::compile_spirv(SHADER, None).unwrap()).unwrap();
+        let once = strip_words(&words).unwrap();
+        assert_eq!(strip_words(&once).unwrap(), once);
+    }
+}
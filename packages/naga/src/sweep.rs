@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::batch::{Artifact, Target};
+use crate::diagnostics::throw;
+use crate::hash::sha256_hex;
+use crate::specialize::{override_key, process};
+
+// ============================================================================
+// Override Sweep Types
+// ============================================================================
+
+/// Every combination of a grid of override values, compiled from one parse.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct OverrideSweep {
+    /// One row per combination, in grid order: the last override listed
+    /// varies fastest, overrides being listed by name.
+    #[wasm_bindgen(readonly)]
+    pub variants: Vec<SweepVariant>,
+    /// Each distinct output once, in order of first appearance.
+    #[wasm_bindgen(readonly)]
+    pub artifacts: Vec<SweepArtifact>,
+}
+
+#[wasm_bindgen]
+impl OverrideSweep {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SweepVariant {
+    #[wasm_bindgen(readonly)]
+    pub values: Vec<OverrideValue>,
+    /// SHA-256 of the artifact, or `None` if this combination failed.
+    #[wasm_bindgen(readonly)]
+    pub hash: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl SweepVariant {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct OverrideValue {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub value: f64,
+}
+
+#[wasm_bindgen]
+impl OverrideValue {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SweepArtifact {
+    #[wasm_bindgen(readonly)]
+    pub hash: String,
+    /// SPIR-V output.
+    #[wasm_bindgen(readonly)]
+    pub bytes: Option<Vec<u8>>,
+    /// MSL output.
+    #[wasm_bindgen(readonly)]
+    pub text: Option<String>,
+}
+
+#[wasm_bindgen]
+impl SweepArtifact {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Override Sweep Implementation
+// ============================================================================
+
+/// Upper bound on combinations, so a typo in a grid cannot hang the page.
+const MAX_VARIANTS: usize = 4096;
+
+/// Compile `module` once per combination of `grid`, keyed by override name.
+pub(crate) fn sweep(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    target: Target,
+    grid: &BTreeMap<String, Vec<f64>>,
+) -> Result<OverrideSweep, Diagnostic> {
+    let entry_point = entry_point.filter(|name| !name.is_empty());
+    if let Some(name) = entry_point {
+        crate::find_entry_point(module, name)?;
+    }
+
+    // Grids name overrides; pipeline constants key `@id`d ones by id.
+    let mut axes = Vec::new();
+    for (name, values) in grid {
+        let o = module
+            .overrides
+            .iter()
+            .map(|(_, o)| o)
+            .find(|o| o.name.as_deref() == Some(name) || override_key(o) == *name)
+            .ok_or_else(|| Diagnostic::error(format!("Unknown override '{}'", name)))?;
+        if values.is_empty() {
+            return Err(Diagnostic::error(format!(
+                "Override '{}' has no values to sweep",
+                name
+            )));
+        }
+        axes.push((name.as_str(), override_key(o), values.as_slice()));
+    }
+    let total = axes
+        .iter()
+        .try_fold(1usize, |n, (_, _, values)| n.checked_mul(values.len()))
+        .filter(|n| *n <= MAX_VARIANTS)
+        .ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Override grid exceeds {} combinations",
+                MAX_VARIANTS
+            ))
+        })?;
+
+    let mut variants = Vec::with_capacity(total);
+    let mut artifacts = Vec::new();
+    let mut seen = HashSet::new();
+    for index in 0..total {
+        // Mixed-radix digits of `index`, last axis fastest.
+        let mut rest = index;
+        let mut picked = vec![0.0; axes.len()];
+        for (slot, (_, _, values)) in axes.iter().enumerate().rev() {
+            picked[slot] = values[rest % values.len()];
+            rest /= values.len();
+        }
+        let constants: Vec<_> = axes
+            .iter()
+            .zip(&picked)
+            .map(|((_, key, _), value)| (key.clone(), *value))
+            .collect();
+        let values = axes
+            .iter()
+            .zip(&picked)
+            .map(|((name, _, _), value)| OverrideValue {
+                name: name.to_string(),
+                value: *value,
+            })
+            .collect();
+
+        let (hash, diagnostics) = match emit(module, info, entry_point, target, &constants) {
+            Ok(artifact) => {
+                let hash = sha256_hex(artifact.as_bytes());
+                if seen.insert(hash.clone()) {
+                    let (bytes, text) = match artifact {
+                        Artifact::Binary(bytes) => (Some(bytes), None),
+                        Artifact::Text(text) => (None, Some(text)),
+                    };
+                    artifacts.push(SweepArtifact {
+                        hash: hash.clone(),
+                        bytes,
+                        text,
+                    });
+                }
+                (Some(hash), Vec::new())
+            }
+            Err(diagnostic) => (None, vec![diagnostic]),
+        };
+        variants.push(SweepVariant {
+            values,
+            hash,
+            diagnostics,
+        });
+    }
+
+    Ok(OverrideSweep {
+        variants,
+        artifacts,
+    })
+}
+
+fn emit(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    entry_point: Option<&str>,
+    target: Target,
+    constants: &[(String, f64)],
+) -> Result<Artifact, Diagnostic> {
+    let (module, info) = process(module, info, entry_point, constants)?;
+    Ok(match target {
+        Target::Spirv => Artifact::Binary(crate::write_spirv(&module, &info, entry_point)?),
+        Target::Msl => Artifact::Text(crate::write_msl(&module, &info, entry_point)?),
+    })
+}
+
+/// Compiles `source` once per combination of `overrideGrid`, an object
+/// mapping override names to the values to try, e.g.
+/// `{ TILE: [8, 16, 32], QUALITY: [0, 1] }`. The source is parsed and
+/// validated once. Combinations with identical output share one entry in
+/// `artifacts`; `variants` maps each combination to its artifact's hash.
+/// A combination naga rejects gets diagnostics instead of a hash.
+#[wasm_bindgen(js_name = compileOverrideSweep)]
+pub fn compile_override_sweep(
+    source: &str,
+    entry_point: Option<String>,
+    target: &str,
+    override_grid: JsValue,
+) -> Result<OverrideSweep, JsValue> {
+    let target = Target::parse(target)
+        .ok_or_else(|| throw(Diagnostic::error(format!("Unknown target '{}'", target))))?;
+    let grid: BTreeMap<String, Vec<f64>> = serde_wasm_bindgen::from_value(override_grid)
+        .map_err(|e| JsValue::from_str(&format!("Invalid override grid: {e}")))?;
+    let (module, info) = crate::parse_and_validate(source).map_err(throw)?;
+    sweep(&module, &info, entry_point.as_deref(), target, &grid).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        override TILE: u32 = 8u;
+        @id(3) override QUALITY: f32 = 1.0;
+        @group(0) @binding(0) var<storage, read_write> out: array<f32>;
+
+        @compute @workgroup_size(TILE)
+        fn main() {
+            out[0] = select(0.0, 2.0, QUALITY > 0.5);
+        }
+    "#;
+
+    fn grid(entries: &[(&str, &[f64])]) -> BTreeMap<String, Vec<f64>> {
+        entries
+            .iter()
+            .map(|(name, values)| (name.to_string(), values.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn sweeps_the_cartesian_product_and_dedupes() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let grid = grid(&[("QUALITY", &[0.0, 1.0]), ("TILE", &[8.0, 16.0, 8.0])]);
+        let result = sweep(&module, &info, Some("main"), Target::Spirv, &grid).unwrap();
+
+        assert_eq!(result.variants.len(), 6);
+        assert_eq!(
+            result.variants[1].values,
+            vec![
+                OverrideValue {
+                    name: "QUALITY".to_string(),
+                    value: 0.0,
+                },
+                OverrideValue {
+                    name: "TILE".to_string(),
+                    value: 16.0,
+                },
+            ]
+        );
+        // Repeating TILE = 8 yields the same binary.
+        assert_eq!(result.variants[0].hash, result.variants[2].hash);
+        assert_ne!(result.variants[0].hash, result.variants[1].hash);
+        assert_eq!(result.artifacts.len(), 4);
+        for variant in &result.variants {
+            let hash = variant.hash.as_ref().unwrap();
+            assert!(result.artifacts.iter().any(|a| &a.hash == hash));
+        }
+    }
+
+    #[test]
+    fn empty_grid_compiles_defaults_once() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let result = sweep(&module, &info, None, Target::Msl, &BTreeMap::new()).unwrap();
+        assert_eq!(result.variants.len(), 1);
+        assert!(result.variants[0].values.is_empty());
+        assert!(result.artifacts[0].text.is_some());
+    }
+
+    #[test]
+    fn rejected_values_and_bad_grids() {
+        let (module, info) = crate::parse_and_validate(SHADER).unwrap();
+        let failing = grid(&[("TILE", &[0.0, 4.0])]);
+        let result = sweep(&module, &info, Some("main"), Target::Spirv, &failing).unwrap();
+        assert!(result.variants[0].hash.is_none());
+        assert!(!result.variants[0].diagnostics.is_empty());
+        assert!(result.variants[1].hash.is_some());
+
+        for bad in [grid(&[("NOPE", &[1.0])]), grid(&[("TILE", &[])])] {
+            assert!(sweep(&module, &info, None, Target::Spirv, &bad).is_err());
+        }
+        let values: Vec<f64> = (0..65).map(f64::from).collect();
+        let huge = grid(&[("TILE", &values), ("3", &values)]);
+        assert!(sweep(&module, &info, None, Target::Spirv, &huge).is_err());
+    }
+}
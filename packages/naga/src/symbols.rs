@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::SourceSpan;
+use crate::lexer::{attributes_before, module_declarations, tokenize};
+
+// ============================================================================
+// Symbol Index Types
+// ============================================================================
+
+/// A module-scope declaration matching a `searchSymbols` query.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SymbolMatch {
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// `function`, `entry-point`, `struct`, `alias`, `constant`, `override`,
+    /// `binding` (a `var` with `@group`/`@binding`) or `global`.
+    #[wasm_bindgen(readonly)]
+    pub kind: String,
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    /// The name in the declaration.
+    #[wasm_bindgen(readonly)]
+    pub span: SourceSpan,
+    /// The declaration's line, trimmed, e.g. `fn shade(n: vec3<f32>) -> f32 {`.
+    #[wasm_bindgen(readonly)]
+    pub detail: String,
+    /// Higher is better: exact names first, then prefixes, word prefixes,
+    /// substrings and finally scattered letters.
+    #[wasm_bindgen(readonly)]
+    pub score: u32,
+}
+
+#[wasm_bindgen]
+impl SymbolMatch {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Symbol Index Implementation
+// ============================================================================
+//
+// Declarations come from the lexer rather than naga, so a file that does not
+// parse on its own, say one relying on an `#include`, or one mid-edit, is
+// still indexed. The inverted index maps lowercase terms to the symbols they
+// lead to: each name, and each word of it split at `_` and at camel-case
+// humps, so `light` finds `PointLight` and `light_count`. Queries are
+// matched against the terms, never the sources, and a file's entries are
+// replaced whenever the file is.
+
+struct Symbol {
+    name: String,
+    kind: &'static str,
+    span: SourceSpan,
+    detail: String,
+}
+
+/// Symbols per file and the terms leading to them.
+#[derive(Default)]
+pub(crate) struct SymbolIndex {
+    files: BTreeMap<String, Vec<Symbol>>,
+    terms: BTreeMap<String, BTreeSet<(String, usize)>>,
+}
+
+/// `PointLight` -> `point`, `light`; `MAX_LIGHTS` -> `max`, `lights`.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if (c == '_' || (c.is_uppercase() && previous_lower)) && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        if c != '_' {
+            current.extend(c.to_lowercase());
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn declarations(source: &str) -> Vec<Symbol> {
+    let tokens = tokenize(source);
+    module_declarations(&tokens)
+        .into_iter()
+        .filter_map(|(name, at)| {
+            // The keyword is right before the name, or before its template.
+            let keyword = (0..at).rev().find(|&i| {
+                matches!(
+                    tokens[i].text,
+                    "fn" | "struct" | "alias" | "const" | "override" | "var"
+                )
+            })?;
+            let attributes: Vec<_> = attributes_before(&tokens, keyword)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            let kind = match tokens[keyword].text {
+                "fn" if attributes
+                    .iter()
+                    .any(|a| matches!(*a, "vertex" | "fragment" | "compute")) =>
+                {
+                    "entry-point"
+                }
+                "fn" => "function",
+                "struct" => "struct",
+                "alias" => "alias",
+                "const" => "constant",
+                "override" => "override",
+                _ if attributes.contains(&"binding") => "binding",
+                _ => "global",
+            };
+            let token = tokens[at];
+            let span = naga::Span::new(token.start as u32, token.end() as u32);
+            let line_start = source[..token.start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = source[token.start..]
+                .find('\n')
+                .map_or(source.len(), |i| token.start + i);
+            Some(Symbol {
+                name: name.to_string(),
+                kind,
+                span: SourceSpan::new(source, span)?,
+                detail: source[line_start..line_end].trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn is_subsequence(query: &str, term: &str) -> bool {
+    let mut chars = term.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+impl SymbolIndex {
+    /// Index `source` as `path`, replacing what was indexed for it.
+    pub(crate) fn set(&mut self, path: &str, source: &str) {
+        self.remove(path);
+        let symbols = declarations(source);
+        for (index, symbol) in symbols.iter().enumerate() {
+            let mut terms = words(&symbol.name);
+            terms.push(symbol.name.to_lowercase());
+            for term in terms {
+                self.terms
+                    .entry(term)
+                    .or_default()
+                    .insert((path.to_string(), index));
+            }
+        }
+        self.files.insert(path.to_string(), symbols);
+    }
+
+    pub(crate) fn remove(&mut self, path: &str) {
+        if self.files.remove(path).is_none() {
+            return;
+        }
+        self.terms.retain(|_, refs| {
+            refs.retain(|(file, _)| file != path);
+            !refs.is_empty()
+        });
+    }
+
+    /// The best `limit` matches for `query`, best first.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<SymbolMatch> {
+        let lower = query.trim().to_lowercase();
+        if lower.is_empty() {
+            return Vec::new();
+        }
+        let mut scores: HashMap<(&str, usize), u32> = HashMap::new();
+        for (term, refs) in &self.terms {
+            for (path, index) in refs {
+                let symbol = &self.files[path][*index];
+                let whole = symbol.name.to_lowercase() == *term;
+                let score = match () {
+                    _ if whole && symbol.name == query.trim() => 100,
+                    _ if whole && *term == lower => 95,
+                    _ if whole && term.starts_with(&lower) => 80,
+                    _ if *term == lower => 70,
+                    _ if term.starts_with(&lower) => 60,
+                    _ if whole && term.contains(&lower) => 40,
+                    _ if whole && is_subsequence(&lower, term) => 20,
+                    _ => continue,
+                };
+                let best = scores.entry((path.as_str(), *index)).or_default();
+                *best = (*best).max(score);
+            }
+        }
+        let mut matches: Vec<_> = scores
+            .into_iter()
+            .map(|((path, index), score)| {
+                let symbol = &self.files[path][index];
+                SymbolMatch {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind.to_string(),
+                    path: path.to_string(),
+                    span: symbol.span.clone(),
+                    detail: symbol.detail.clone(),
+                    score,
+                }
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.path.cmp(&b.path))
+                .then(a.span.start.cmp(&b.span.start))
+        });
+        matches.truncate(limit);
+        matches
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHTING: &str = r#"
+struct PointLight { position: vec3<f32>, range: f32 }
+const MAX_LIGHTS: u32 = 16u;
+@group(0) @binding(1) var<storage, read> lights: array<PointLight>;
+var<private> light_count: u32;
+fn light_attenuation(light: PointLight, d: f32) -> f32 { return 1.0 - d / light.range; }
+@fragment fn fs_light() -> @location(0) vec4<f32> { return vec4<f32>(); }
+"#;
+
+    fn names(matches: &[SymbolMatch]) -> Vec<(&str, &str)> {
+        matches
+            .iter()
+            .map(|m| (m.name.as_str(), m.kind.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn matches_are_ranked_with_spans() {
+        let mut index = SymbolIndex::default();
+        index.set("lighting.wgsl", LIGHTING);
+        let matches = index.search("lights", 10);
+        assert_eq!(
+            names(&matches),
+            [("lights", "binding"), ("MAX_LIGHTS", "constant")]
+        );
+        let binding = &matches[0];
+        assert_eq!((binding.span.line, binding.span.column), (4, 42));
+        assert!(
+            binding
+                .detail
+                .starts_with("@group(0) @binding(1) var<storage")
+        );
+
+        assert_eq!(
+            names(&index.search("light", 10)),
+            [
+                ("lights", "binding"),
+                ("light_count", "global"),
+                ("light_attenuation", "function"),
+                ("fs_light", "entry-point"),
+                ("PointLight", "struct"),
+                ("MAX_LIGHTS", "constant"),
+            ]
+        );
+        // Scattered letters match last.
+        assert_eq!(
+            names(&index.search("ltatt", 10)),
+            [("light_attenuation", "function")]
+        );
+        assert!(index.search("  ", 10).is_empty());
+    }
+
+    #[test]
+    fn files_are_reindexed_when_replaced() {
+        let mut index = SymbolIndex::default();
+        index.set("a.wgsl", "fn shade() {}");
+        index.set("b.wgsl", "fn shade_fast() {}\nfn broken( {");
+        assert_eq!(index.search("shade", 10).len(), 2);
+        index.set("a.wgsl", "fn tint() {}");
+        assert_eq!(
+            names(&index.search("shade", 10)),
+            [("shade_fast", "function")]
+        );
+        index.remove("b.wgsl");
+        assert!(index.search("shade", 10).is_empty());
+        assert_eq!(index.search("tint", 1)[0].path, "a.wgsl");
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+
+use naga::{
+    BinaryOperator, Expression, ImageClass, ImageDimension, ImageQuery, Literal, MathFunction,
+    SampleLevel, ScalarKind, TypeInner, VectorSize,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::rewrite;
+
+// ============================================================================
+// Texel Fetch Types
+// ============================================================================
+
+/// Options object accepted by `rewriteTexelFetches`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TexelFetchOptions {
+    /// Sampler variables the host always creates with nearest filtering,
+    /// clamp-to-edge addressing and a `lodMaxClamp` of 0.
+    #[serde(default)]
+    pub samplers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct TexelFetchReport {
+    /// Samples replaced by `textureLoad`.
+    #[wasm_bindgen(readonly)]
+    pub converted: Vec<TexelFetch>,
+    /// Samples through one of the samplers left in place, with why.
+    #[wasm_bindgen(readonly)]
+    pub skipped: Vec<TexelFetch>,
+    #[wasm_bindgen(readonly)]
+    pub wgsl: String,
+}
+
+#[wasm_bindgen]
+impl TexelFetchReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// Samples of one texture through one sampler in one function, grouped.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct TexelFetch {
+    #[wasm_bindgen(readonly)]
+    pub function: String,
+    #[wasm_bindgen(readonly)]
+    pub texture: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub sampler: String,
+    /// Number of samples.
+    #[wasm_bindgen(readonly)]
+    pub count: u32,
+    /// Why the samples were skipped; `None` for converted ones.
+    #[wasm_bindgen(readonly)]
+    pub reason: Option<String>,
+}
+
+#[wasm_bindgen]
+impl TexelFetch {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Texel Fetch Implementation
+// ============================================================================
+//
+// With nearest filtering, clamp-to-edge addressing and only mip level 0
+// reachable, sampling a 2D texture at `uv` returns the texel at
+// `clamp(floor(uv * size), 0, size - 1)` of level 0, which is what each
+// sample through one of the given samplers becomes. Nothing in the shader
+// says how a sampler is configured, so which ones qualify is up to the
+// caller. Samples that could still differ, with an offset, a bias, explicit
+// gradients or a nonzero level, are left alone, as are comparisons, gathers
+// and textures other than plain `texture_2d<f32>` and `texture_2d_array<f32>`.
+
+/// Why the sample `expr` must stay a sample, if it must.
+fn unsafe_reason(
+    module: &naga::Module,
+    function: &naga::Function,
+    expr: &Expression,
+) -> Option<String> {
+    let Expression::ImageSample {
+        image,
+        gather,
+        offset,
+        level,
+        depth_ref,
+        ..
+    } = *expr
+    else {
+        return None;
+    };
+    if gather.is_some() {
+        return Some("gathers read four texels".to_string());
+    }
+    if depth_ref.is_some() {
+        return Some("depth comparisons need the sampler".to_string());
+    }
+    if offset.is_some() {
+        return Some("offsets are not supported".to_string());
+    }
+    let level_zero = match level {
+        SampleLevel::Auto | SampleLevel::Zero => true,
+        SampleLevel::Exact(h) => matches!(
+            function.expressions[h],
+            Expression::Literal(Literal::F32(0.0) | Literal::AbstractFloat(0.0))
+        ),
+        SampleLevel::Bias(_) | SampleLevel::Gradient { .. } => false,
+    };
+    if !level_zero {
+        return Some("the level may not be 0".to_string());
+    }
+    let Expression::GlobalVariable(texture) = function.expressions[image] else {
+        return Some("the texture is not a global".to_string());
+    };
+    match module.types[module.global_variables[texture].ty].inner {
+        TypeInner::Image {
+            dim: ImageDimension::D2,
+            class:
+                ImageClass::Sampled {
+                    kind: ScalarKind::Float,
+                    multi: false,
+                },
+            ..
+        } => None,
+        _ => Some("only texture_2d<f32> and texture_2d_array<f32> are supported".to_string()),
+    }
+}
+
+/// `image`, `sampler` and the variable names behind them, for a sample
+/// through one of `samplers`.
+fn sample_names(
+    module: &naga::Module,
+    function: &naga::Function,
+    expr: &Expression,
+    samplers: &[String],
+) -> Option<(Option<String>, String)> {
+    let Expression::ImageSample { image, sampler, .. } = *expr else {
+        return None;
+    };
+    let name = |h| match function.expressions[h] {
+        Expression::GlobalVariable(g) => module.global_variables[g].name.clone(),
+        _ => None,
+    };
+    let sampler = name(sampler).filter(|s| samplers.contains(s))?;
+    Some((name(image), sampler))
+}
+
+/// The `textureLoad` equivalent of the sample `expr`, whose operands are
+/// already renumbered, appending the coordinate computation through `push`.
+fn texel_load(
+    expr: Expression,
+    push: &mut dyn FnMut(Expression) -> naga::Handle<Expression>,
+) -> Expression {
+    let Expression::ImageSample {
+        image,
+        coordinate,
+        array_index,
+        ..
+    } = expr
+    else {
+        unreachable!("only samples are converted");
+    };
+    let size = push(Expression::ImageQuery {
+        image,
+        query: ImageQuery::Size { level: None },
+    });
+    let size_f = push(Expression::As {
+        expr: size,
+        kind: ScalarKind::Float,
+        convert: Some(4),
+    });
+    let scaled = push(Expression::Binary {
+        op: BinaryOperator::Multiply,
+        left: coordinate,
+        right: size_f,
+    });
+    let floored = push(Expression::Math {
+        fun: MathFunction::Floor,
+        arg: scaled,
+        arg1: None,
+        arg2: None,
+        arg3: None,
+    });
+    let texel = push(Expression::As {
+        expr: floored,
+        kind: ScalarKind::Sint,
+        convert: Some(4),
+    });
+    let one = push(Expression::Literal(Literal::U32(1)));
+    let ones = push(Expression::Splat {
+        size: VectorSize::Bi,
+        value: one,
+    });
+    let last = push(Expression::Binary {
+        op: BinaryOperator::Subtract,
+        left: size,
+        right: ones,
+    });
+    let last = push(Expression::As {
+        expr: last,
+        kind: ScalarKind::Sint,
+        convert: Some(4),
+    });
+    let zero = push(Expression::Literal(Literal::I32(0)));
+    let zeros = push(Expression::Splat {
+        size: VectorSize::Bi,
+        value: zero,
+    });
+    let clamped = push(Expression::Math {
+        fun: MathFunction::Clamp,
+        arg: texel,
+        arg1: Some(zeros),
+        arg2: Some(last),
+        arg3: None,
+    });
+    let level = push(Expression::Literal(Literal::I32(0)));
+    Expression::ImageLoad {
+        image,
+        coordinate: clamped,
+        array_index,
+        sample: None,
+        level: Some(level),
+    }
+}
+
+type Groups = BTreeMap<(String, Option<String>, String, Option<String>), u32>;
+
+/// Convert the eligible samples in `function`, counting every candidate in
+/// `groups` by outcome.
+fn convert_function(
+    module: &naga::Module,
+    name: &str,
+    function: &mut naga::Function,
+    samplers: &[String],
+    groups: &mut Groups,
+) {
+    let mut convert = Vec::new();
+    for (h, expr) in function.expressions.iter() {
+        let Some((texture, sampler)) = sample_names(module, function, expr, samplers) else {
+            continue;
+        };
+        let reason = unsafe_reason(module, function, expr);
+        if reason.is_none() {
+            convert.push(h);
+        }
+        *groups
+            .entry((name.to_string(), texture, sampler, reason))
+            .or_default() += 1;
+    }
+    if convert.is_empty() {
+        return;
+    }
+    rewrite::rebuild_expressions(function, |h, expr, push| {
+        if convert.contains(&h) {
+            texel_load(expr, push)
+        } else {
+            expr
+        }
+    });
+}
+
+/// Convert samples through `options.samplers` throughout `module`.
+pub(crate) fn rewrite_texel_fetches(
+    module: &mut naga::Module,
+    options: &TexelFetchOptions,
+) -> Result<(Vec<TexelFetch>, Vec<TexelFetch>), Diagnostic> {
+    for sampler in &options.samplers {
+        let declared = module.global_variables.iter().any(|(_, var)| {
+            var.name.as_deref() == Some(sampler.as_str())
+                && matches!(module.types[var.ty].inner, TypeInner::Sampler { .. })
+        });
+        if !declared {
+            return Err(Diagnostic::error(format!("Unknown sampler '{}'", sampler)));
+        }
+    }
+
+    let mut groups = Groups::new();
+    let handles: Vec<_> = module.functions.iter().map(|(h, _)| h).collect();
+    for h in handles {
+        let mut function = std::mem::take(&mut module.functions[h]);
+        let name = function.name.clone().unwrap_or_default();
+        convert_function(module, &name, &mut function, &options.samplers, &mut groups);
+        module.functions[h] = function;
+    }
+    for index in 0..module.entry_points.len() {
+        let mut function = std::mem::take(&mut module.entry_points[index].function);
+        let name = module.entry_points[index].name.clone();
+        convert_function(module, &name, &mut function, &options.samplers, &mut groups);
+        module.entry_points[index].function = function;
+    }
+
+    let (mut converted, mut skipped) = (Vec::new(), Vec::new());
+    for ((function, texture, sampler, reason), count) in groups {
+        let list = if reason.is_some() {
+            &mut skipped
+        } else {
+            &mut converted
+        };
+        list.push(TexelFetch {
+            function,
+            texture,
+            sampler,
+            count,
+            reason,
+        });
+    }
+    Ok((converted, skipped))
+}
+
+fn texel_fetch_report(
+    wgsl: &str,
+    options: &TexelFetchOptions,
+) -> Result<TexelFetchReport, Diagnostic> {
+    let (mut module, _info) = crate::parse_and_validate(wgsl)?;
+    let (converted, skipped) = rewrite_texel_fetches(&mut module, options)?;
+    let info = crate::validate_module(&module)?;
+    let wgsl =
+        naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+            .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+    Ok(TexelFetchReport {
+        converted,
+        skipped,
+        wgsl,
+    })
+}
+
+/// Replaces `textureSample` and friends with `textureLoad` for targets
+/// without samplers, such as old GLES drivers. Only samples through the
+/// samplers named in `options.samplers` are considered: the host must create
+/// those with nearest filtering, clamp-to-edge addressing and a `lodMaxClamp`
+/// of 0, since the shader cannot tell. Of those, samples that a texel fetch
+/// reproduces exactly are converted; the report lists the rest with why.
+/// `options` is `{ samplers: string[] }`.
+#[wasm_bindgen(js_name = rewriteTexelFetches)]
+pub fn rewrite_texel_fetches_wgsl(
+    wgsl: &str,
+    options: JsValue,
+) -> Result<TexelFetchReport, JsValue> {
+    let options: Option<TexelFetchOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid texel fetch options: {e}")))?;
+    texel_fetch_report(wgsl, &options.unwrap_or_default()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        @group(0) @binding(0) var atlas: texture_2d<f32>;
+        @group(0) @binding(1) var layers: texture_2d_array<f32>;
+        @group(0) @binding(2) var point: sampler;
+        @group(0) @binding(3) var bilinear: sampler;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            let a = textureSample(atlas, point, uv);
+            let b = textureSampleLevel(layers, point, uv, 2, 0.0);
+            let c = textureSampleBias(atlas, point, uv, 1.0);
+            let d = textureSample(atlas, point, uv, vec2<i32>(1, 0));
+            let e = textureSample(atlas, bilinear, uv);
+            return a + b + c + d + e;
+        }
+    "#;
+
+    fn options(samplers: &[&str]) -> TexelFetchOptions {
+        TexelFetchOptions {
+            samplers: samplers.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn safe_samples_become_texel_loads() {
+        let report = texel_fetch_report(SHADER, &options(&["point"])).unwrap();
+        let converted: Vec<_> = report
+            .converted
+            .iter()
+            .map(|c| (c.texture.as_deref(), c.count))
+            .collect();
+        assert_eq!(converted, [(Some("atlas"), 1), (Some("layers"), 1)]);
+        let reasons: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|s| (s.count, s.reason.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                (1, "offsets are not supported"),
+                (1, "the level may not be 0")
+            ]
+        );
+        assert_eq!(report.wgsl.matches("textureLoad").count(), 2);
+        assert!(report.wgsl.contains("textureSample(atlas, bilinear"));
+    }
+
+    #[test]
+    fn unknown_samplers_are_errors() {
+        assert!(texel_fetch_report(SHADER, &options(&["nope"])).is_err());
+        assert!(texel_fetch_report(SHADER, &options(&["atlas"])).is_err());
+        let untouched = texel_fetch_report(SHADER, &options(&[])).unwrap();
+        assert!(untouched.converted.is_empty() && untouched.skipped.is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+// ============================================================================
+// Thread Pool
+// ============================================================================
+//
+// Batch jobs and project builds spread over rayon's pool when the
+// `parallel` feature is on (see `batch::in_order`). Native builds always
+// have the pool. On the web, threads are Web Workers sharing the module's
+// memory, which needs a cross-origin-isolated page and a module built with
+// `wasm-threads`; the pool only exists once JS has awaited
+// `initThreadPool`, and until then everything runs on the calling thread.
+// rayon blocks the thread that hands it work, so that thread must itself be
+// a worker: browsers do not let the main thread wait.
+
+/// Threads in the pool started by `initThreadPool`; 0 before then.
+static POOL_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether `in_order` should hand work to rayon.
+#[cfg(feature = "parallel")]
+pub(crate) fn enabled() -> bool {
+    cfg!(not(target_arch = "wasm32")) || POOL_THREADS.load(Ordering::Acquire) > 0
+}
+
+/// Start a pool of `threads` Web Workers for batch jobs and project builds.
+/// Only built with the `wasm-threads` feature, and only usable on a
+/// cross-origin-isolated page; await the promise before compiling, from a
+/// worker rather than the main thread. `navigator.hardwareConcurrency` is
+/// a reasonable size.
+#[cfg(all(feature = "wasm-threads", target_arch = "wasm32"))]
+#[wasm_bindgen(js_name = initThreadPool)]
+pub fn init_thread_pool(threads: usize) -> js_sys::Promise {
+    POOL_THREADS.store(threads.max(1), Ordering::Release);
+    wasm_bindgen_rayon::init_thread_pool(threads.max(1))
+}
+
+/// Threads batch jobs and project builds run on: the pool's size, or 1
+/// when the module has none, either because it was built without threads
+/// or because `initThreadPool` has not been called.
+#[wasm_bindgen(js_name = threadPoolSize)]
+pub fn thread_pool_size() -> u32 {
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    {
+        rayon::current_num_threads() as u32
+    }
+    #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+    {
+        POOL_THREADS.load(Ordering::Acquire).max(1) as u32
+    }
+}
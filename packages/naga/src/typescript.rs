@@ -0,0 +1,601 @@
+use std::fmt::Write;
+
+use naga::proc::Layouter;
+use naga::{ArraySize, Handle, Scalar, ScalarKind, Type, TypeInner, VectorSize};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::layout::layouter;
+
+// ============================================================================
+// TypeScript Codegen Types
+// ============================================================================
+
+/// TypeScript mirrors of a shader's buffer structs, from `generateTypeScript`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct TypeScriptBindings {
+    /// One interface per struct, as a `.d.ts` file would declare them.
+    #[wasm_bindgen(readonly)]
+    pub declarations: String,
+    /// A self-contained `.ts` module: the interfaces again, plus a
+    /// `<Struct>Layout` class per struct that writes and reads it.
+    #[wasm_bindgen(readonly)]
+    pub code: String,
+    /// The structs covered, in declaration order.
+    #[wasm_bindgen(readonly)]
+    pub structs: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl TypeScriptBindings {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// TypeScript Codegen Implementation
+// ============================================================================
+//
+// Every struct that could sit in a uniform or storage buffer gets an
+// interface and a layout class; structs with bool or resource members, and
+// stage IO structs, cannot and are left out. Scalars map to `number`
+// (`bigint` for 64-bit integers), vectors to tuples and matrices to flat
+// column-major `number[]`s. Offsets come from naga's layouter, so padding,
+// vec3 alignment and matrix column strides match what the GPU reads.
+// Writers leave padding untouched; `pack` starts from a zeroed buffer.
+
+/// Whether `ty` can be stored in a buffer and so mirrored.
+pub(crate) fn host_shareable(module: &naga::Module, ty: Handle<Type>) -> bool {
+    let scalar = |s: Scalar| {
+        matches!(
+            s.kind,
+            ScalarKind::Float | ScalarKind::Sint | ScalarKind::Uint
+        )
+    };
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) | TypeInner::Vector { scalar: s, .. } => {
+            scalar(s)
+        }
+        TypeInner::Matrix { scalar: s, .. } => s.kind == ScalarKind::Float,
+        TypeInner::Array { base, .. } => host_shareable(module, base),
+        TypeInner::Struct { ref members, .. } => members
+            .iter()
+            .all(|m| m.binding.is_none() && host_shareable(module, m.ty)),
+        _ => false,
+    }
+}
+
+fn scalar_type(scalar: Scalar) -> &'static str {
+    match (scalar.kind, scalar.width) {
+        (ScalarKind::Sint | ScalarKind::Uint, 8) => "bigint",
+        _ => "number",
+    }
+}
+
+/// `DataView` accessor suffix for `scalar`; f16 goes through `Uint16`.
+fn accessor(scalar: Scalar) -> &'static str {
+    match (scalar.kind, scalar.width) {
+        (ScalarKind::Float, 2) => "Uint16",
+        (ScalarKind::Float, 8) => "Float64",
+        (ScalarKind::Float, _) => "Float32",
+        (ScalarKind::Sint, 8) => "BigInt64",
+        (ScalarKind::Sint, _) => "Int32",
+        (ScalarKind::Uint, 8) => "BigUint64",
+        (_, _) => "Uint32",
+    }
+}
+
+fn is_f16(scalar: Scalar) -> bool {
+    scalar.kind == ScalarKind::Float && scalar.width == 2
+}
+
+pub(crate) fn struct_name(module: &naga::Module, ty: Handle<Type>) -> String {
+    module.types[ty]
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("Struct{}", ty.index()))
+}
+
+fn ts_type(module: &naga::Module, ty: Handle<Type>) -> String {
+    match module.types[ty].inner {
+        TypeInner::Scalar(s) | TypeInner::Atomic(s) => scalar_type(s).to_string(),
+        TypeInner::Vector { size, scalar } => {
+            let component = scalar_type(scalar);
+            format!("[{}]", vec![component; size as usize].join(", "))
+        }
+        TypeInner::Matrix { .. } => "number[]".to_string(),
+        TypeInner::Array { base, .. } => match module.types[base].inner {
+            TypeInner::Scalar(_) | TypeInner::Atomic(_) | TypeInner::Struct { .. } => {
+                format!("{}[]", ts_type(module, base))
+            }
+            _ => format!("Array<{}>", ts_type(module, base)),
+        },
+        TypeInner::Struct { .. } => struct_name(module, ty),
+        _ => "never".to_string(),
+    }
+}
+
+/// A byte offset in generated code: an expression plus a constant, kept
+/// apart so constants fold as members and components nest.
+#[derive(Clone)]
+struct Offset {
+    base: String,
+    bytes: u32,
+}
+
+impl Offset {
+    /// `bytes` into the struct being generated, whose start is `offset`.
+    fn member(bytes: u32) -> Offset {
+        Offset {
+            base: "offset".to_string(),
+            bytes,
+        }
+    }
+
+    fn plus(&self, bytes: u32) -> Offset {
+        Offset {
+            base: self.base.clone(),
+            bytes: self.bytes + bytes,
+        }
+    }
+
+    /// Element `index` of an array with `stride`-byte elements here.
+    fn element(&self, stride: u32, index: &str) -> Offset {
+        Offset {
+            base: format!("{} + {stride} * {index}", self.base),
+            bytes: self.bytes,
+        }
+    }
+}
+
+impl std::fmt::Display for Offset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bytes {
+            0 => f.write_str(&self.base),
+            bytes => write!(f, "{} + {bytes}", self.base),
+        }
+    }
+}
+
+/// Bytes between matrix columns: two-row columns pack, wider ones align
+/// like a vec4.
+fn column_stride(rows: VectorSize, scalar: Scalar) -> u32 {
+    match rows {
+        VectorSize::Bi => 2 * scalar.width as u32,
+        _ => 4 * scalar.width as u32,
+    }
+}
+
+fn set_scalar(out: &mut String, indent: &str, scalar: Scalar, offset: &Offset, value: &str) {
+    let value = match is_f16(scalar) {
+        true => format!("f16Bits({value})"),
+        false => value.to_string(),
+    };
+    let _ = writeln!(
+        out,
+        "{indent}view.set{}({offset}, {value}, true);",
+        accessor(scalar)
+    );
+}
+
+fn get_scalar(scalar: Scalar, offset: &Offset) -> String {
+    let get = format!("view.get{}({offset}, true)", accessor(scalar));
+    match is_f16(scalar) {
+        true => format!("f16Value({get})"),
+        false => get,
+    }
+}
+
+struct Codegen<'a> {
+    module: &'a naga::Module,
+    layouter: &'a Layouter,
+    uses_f16: bool,
+}
+
+impl Codegen<'_> {
+    /// Statements writing `value`, of type `ty`, at byte `offset`.
+    fn write(
+        &mut self,
+        out: &mut String,
+        indent: &str,
+        ty: Handle<Type>,
+        offset: &Offset,
+        value: &str,
+        depth: usize,
+    ) {
+        match self.module.types[ty].inner {
+            TypeInner::Scalar(s) | TypeInner::Atomic(s) => {
+                self.uses_f16 |= is_f16(s);
+                set_scalar(out, indent, s, offset, value);
+            }
+            TypeInner::Vector { size, scalar } => {
+                self.uses_f16 |= is_f16(scalar);
+                for i in 0..size as u32 {
+                    let component = format!("{value}[{i}]");
+                    set_scalar(
+                        out,
+                        indent,
+                        scalar,
+                        &offset.plus(i * scalar.width as u32),
+                        &component,
+                    );
+                }
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                scalar,
+            } => {
+                self.uses_f16 |= is_f16(scalar);
+                let stride = column_stride(rows, scalar);
+                for c in 0..columns as u32 {
+                    for r in 0..rows as u32 {
+                        let component = format!("{value}[{}]", c * rows as u32 + r);
+                        let bytes = c * stride + r * scalar.width as u32;
+                        set_scalar(out, indent, scalar, &offset.plus(bytes), &component);
+                    }
+                }
+            }
+            TypeInner::Array { base, size, stride } => {
+                let i = format!("i{depth}");
+                let count = match size {
+                    ArraySize::Constant(count) => count.to_string(),
+                    _ => format!("{value}.length"),
+                };
+                let _ = writeln!(out, "{indent}for (let {i} = 0; {i} < {count}; {i}++) {{");
+                let inner = format!("{indent}  ");
+                let element = format!("{value}[{i}]");
+                let offset = offset.element(stride, &i);
+                self.write(out, &inner, base, &offset, &element, depth + 1);
+                let _ = writeln!(out, "{indent}}}");
+            }
+            TypeInner::Struct { .. } => {
+                let name = struct_name(self.module, ty);
+                let _ = writeln!(out, "{indent}{name}Layout.write(view, {offset}, {value});");
+            }
+            _ => {}
+        }
+    }
+
+    /// An expression reading a value of type `ty` at byte `offset`.
+    fn read(&mut self, ty: Handle<Type>, offset: &Offset, depth: usize) -> String {
+        match self.module.types[ty].inner {
+            TypeInner::Scalar(s) | TypeInner::Atomic(s) => {
+                self.uses_f16 |= is_f16(s);
+                get_scalar(s, offset)
+            }
+            TypeInner::Vector { size, scalar } => {
+                self.uses_f16 |= is_f16(scalar);
+                let components: Vec<_> = (0..size as u32)
+                    .map(|i| get_scalar(scalar, &offset.plus(i * scalar.width as u32)))
+                    .collect();
+                format!("[{}]", components.join(", "))
+            }
+            TypeInner::Matrix {
+                columns,
+                rows,
+                scalar,
+            } => {
+                self.uses_f16 |= is_f16(scalar);
+                let stride = column_stride(rows, scalar);
+                let rows = rows as u32;
+                let element = Offset {
+                    base: format!(
+                        "{} + {stride} * Math.floor(k / {rows}) + {} * (k % {rows})",
+                        offset.base, scalar.width
+                    ),
+                    bytes: offset.bytes,
+                };
+                format!(
+                    "Array.from({{ length: {} }}, (_, k) => {})",
+                    columns as u32 * rows,
+                    get_scalar(scalar, &element)
+                )
+            }
+            TypeInner::Array { base, size, stride } => {
+                let i = format!("i{depth}");
+                let count = match size {
+                    ArraySize::Constant(count) => count.to_string(),
+                    _ => format!("Math.floor((view.byteLength - ({offset})) / {stride})"),
+                };
+                let element = self.read(base, &offset.element(stride, &i), depth + 1);
+                format!("Array.from({{ length: {count} }}, (_, {i}) => {element})")
+            }
+            TypeInner::Struct { .. } => {
+                let name = struct_name(self.module, ty);
+                format!("{name}Layout.read(view, {offset})")
+            }
+            _ => "undefined".to_string(),
+        }
+    }
+
+    fn layout_class(&mut self, out: &mut String, ty: Handle<Type>) {
+        let TypeInner::Struct { ref members, .. } = self.module.types[ty].inner else {
+            return;
+        };
+        let name = struct_name(self.module, ty);
+        let size = self.layouter[ty].size;
+        let alignment = self.layouter[ty].alignment.round_up(1);
+
+        let _ = writeln!(
+            out,
+            "/** Writes and reads `{name}` as a uniform or storage buffer holds it. */"
+        );
+        let _ = writeln!(out, "export class {name}Layout {{");
+        let _ = writeln!(
+            out,
+            "  /** Bytes, with one element of a runtime-sized array. */"
+        );
+        let _ = writeln!(out, "  static readonly size = {size};");
+        let _ = writeln!(out, "  static readonly alignment = {alignment};");
+        let _ = writeln!(out);
+
+        // Only a trailing member can be runtime-sized.
+        let runtime = members
+            .last()
+            .and_then(|m| match self.module.types[m.ty].inner {
+                TypeInner::Array {
+                    size: ArraySize::Dynamic,
+                    stride,
+                    ..
+                } => Some((m, stride)),
+                _ => None,
+            });
+        let _ = writeln!(
+            out,
+            "  /** Bytes `value` takes, trailing padding included. */"
+        );
+        let param = if runtime.is_some() { "value" } else { "_value" };
+        let _ = writeln!(out, "  static byteLength({param}: {name}): number {{");
+        match runtime {
+            Some((member, stride)) => {
+                let field = member.name.as_deref().unwrap_or("_");
+                let _ = writeln!(
+                    out,
+                    "    const end = {} + {stride} * value.{field}.length;",
+                    member.offset
+                );
+                let _ = writeln!(
+                    out,
+                    "    return Math.ceil(end / {alignment}) * {alignment};"
+                );
+            }
+            None => {
+                let _ = writeln!(out, "    return {size};");
+            }
+        }
+        let _ = writeln!(out, "  }}");
+        let _ = writeln!(out);
+
+        let _ = writeln!(
+            out,
+            "  static write(view: DataView, offset: number, value: {name}): void {{"
+        );
+        for member in members {
+            let field = member.name.as_deref().unwrap_or("_");
+            let offset = Offset::member(member.offset);
+            self.write(
+                out,
+                "    ",
+                member.ty,
+                &offset,
+                &format!("value.{field}"),
+                0,
+            );
+        }
+        let _ = writeln!(out, "  }}");
+        let _ = writeln!(out);
+
+        let _ = writeln!(out, "  static read(view: DataView, offset = 0): {name} {{");
+        let _ = writeln!(out, "    return {{");
+        for member in members {
+            let field = member.name.as_deref().unwrap_or("_");
+            let offset = Offset::member(member.offset);
+            let read = self.read(member.ty, &offset, 0);
+            let _ = writeln!(out, "      {field}: {read},");
+        }
+        let _ = writeln!(out, "    }};");
+        let _ = writeln!(out, "  }}");
+        let _ = writeln!(out);
+
+        let _ = writeln!(out, "  /** `value` in a new, zeroed buffer of its own. */");
+        let _ = writeln!(out, "  static pack(value: {name}): ArrayBuffer {{");
+        let _ = writeln!(
+            out,
+            "    const buffer = new ArrayBuffer({name}Layout.byteLength(value));"
+        );
+        let _ = writeln!(
+            out,
+            "    {name}Layout.write(new DataView(buffer), 0, value);"
+        );
+        let _ = writeln!(out, "    return buffer;");
+        let _ = writeln!(out, "  }}");
+        let _ = writeln!(out, "}}");
+    }
+}
+
+fn interface(module: &naga::Module, ty: Handle<Type>) -> String {
+    let mut out = String::new();
+    let TypeInner::Struct { ref members, .. } = module.types[ty].inner else {
+        return out;
+    };
+    let _ = writeln!(out, "export interface {} {{", struct_name(module, ty));
+    for member in members {
+        if let TypeInner::Matrix { columns, rows, .. } = module.types[member.ty].inner {
+            let _ = writeln!(
+                out,
+                "  /** mat{}x{}, {} values, column-major. */",
+                columns as u32,
+                rows as u32,
+                columns as u32 * rows as u32
+            );
+        }
+        let field = member.name.as_deref().unwrap_or("_");
+        let _ = writeln!(out, "  {field}: {};", ts_type(module, member.ty));
+    }
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// `f16Bits`, shared with the packing plan's writer, then its inverse.
+fn f16_helpers() -> String {
+    format!(
+        "/** f16 bits of `x`, rounding toward zero and flushing subnormals. */\n\
+         function f16Bits(x: number): number {{\n{}}}\n\n{}",
+        crate::packing::F16_BITS_BODY,
+        F16_VALUE
+    )
+}
+
+const F16_VALUE: &str = "\
+/** The number the f16 `bits` encode. */
+function f16Value(bits: number): number {
+  const exponent = (bits >>> 10) & 0x1f;
+  const mantissa = bits & 0x3ff;
+  const sign = bits & 0x8000 ? -1 : 1;
+  if (exponent === 0) return sign * mantissa * 2 ** -24;
+  if (exponent === 31) return mantissa ? NaN : sign * Infinity;
+  return sign * (1 + mantissa / 1024) * 2 ** (exponent - 15);
+}
+";
+
+/// The structs in `module` that can be stored in a buffer, in declaration
+/// order, which puts every struct after the ones it contains.
+pub(crate) fn buffer_structs(module: &naga::Module) -> Vec<Handle<Type>> {
+    module
+        .types
+        .iter()
+        .filter(|(h, ty)| {
+            matches!(ty.inner, TypeInner::Struct { .. }) && host_shareable(module, *h)
+        })
+        .map(|(h, _)| h)
+        .collect()
+}
+
+pub(crate) fn generate(module: &naga::Module) -> Result<TypeScriptBindings, Diagnostic> {
+    let layouter = layouter(module)?;
+    let structs = buffer_structs(module);
+
+    let interfaces: Vec<String> = structs.iter().map(|&ty| interface(module, ty)).collect();
+    let declarations = interfaces.join("\n");
+
+    let mut codegen = Codegen {
+        module,
+        layouter: &layouter,
+        uses_f16: false,
+    };
+    let mut classes = String::new();
+    for &ty in &structs {
+        classes.push('\n');
+        codegen.layout_class(&mut classes, ty);
+    }
+    let mut code = declarations.clone();
+    if codegen.uses_f16 {
+        code.push('\n');
+        code.push_str(&f16_helpers());
+    }
+    code.push_str(&classes);
+
+    Ok(TypeScriptBindings {
+        declarations,
+        code,
+        structs: structs.iter().map(|&ty| struct_name(module, ty)).collect(),
+    })
+}
+
+/// TypeScript mirrors of every buffer-storable struct in `wgsl`:
+/// `declarations` holds an interface per struct, and `code` a module with
+/// those interfaces and a `<Struct>Layout` class per struct whose `write`,
+/// `read` and `pack` convert between objects and `DataView`s at the offsets
+/// the GPU uses, padding included.
+#[wasm_bindgen(js_name = generateTypeScript)]
+pub fn generate_type_script(wgsl: &str) -> Result<TypeScriptBindings, JsValue> {
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    generate(&module).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Light { color: vec3<f32>, intensity: f32 }
+        struct Scene {
+            view: mat4x4<f32>,
+            light: Light,
+            weights: array<vec2<f32>, 3>,
+            frame: u32,
+        }
+        struct Particles { count: atomic<u32>, positions: array<vec4<f32>> }
+        struct VertexOut { @builtin(position) position: vec4<f32> }
+        // Storage, so the vec2 array may keep its 8-byte stride.
+        @group(0) @binding(0) var<storage, read> scene: Scene;
+        @group(0) @binding(1) var<storage, read_write> particles: Particles;
+        @vertex fn vs() -> VertexOut { return VertexOut(scene.view[0]); }
+    "#;
+
+    #[test]
+    fn interfaces_cover_buffer_structs_only() {
+        let (module, _) = crate::parse_and_validate(SHADER).unwrap();
+        let bindings = generate(&module).unwrap();
+        assert_eq!(bindings.structs, vec!["Light", "Scene", "Particles"]);
+        assert!(
+            bindings
+                .declarations
+                .contains("  color: [number, number, number];")
+        );
+        assert!(bindings.declarations.contains("  view: number[];"));
+        assert!(
+            bindings
+                .declarations
+                .contains("  weights: Array<[number, number]>;")
+        );
+        assert!(bindings.declarations.contains("  light: Light;"));
+        assert!(!bindings.code.contains("f16Bits"));
+    }
+
+    #[test]
+    fn layout_classes_use_gpu_offsets() {
+        let (module, _) = crate::parse_and_validate(SHADER).unwrap();
+        let code = generate(&module).unwrap().code;
+        assert!(code.contains("static readonly size = 112;"));
+        // `light` follows the 64-byte matrix; `intensity` fills vec3 padding.
+        assert!(code.contains("LightLayout.write(view, offset + 64, value.light);"));
+        assert!(code.contains("view.setFloat32(offset + 12, value.intensity, true);"));
+        assert!(
+            code.contains("view.setFloat32(offset + 8 * i0 + 84, value.weights[i0][1], true);")
+        );
+        assert!(code.contains("view.setUint32(offset + 104, value.frame, true);"));
+        // Runtime-sized arrays size the buffer from the value.
+        assert!(code.contains("const end = 16 + 16 * value.positions.length;"));
+        assert!(code.contains("Math.floor((view.byteLength - (offset + 16)) / 16)"));
+    }
+
+    #[test]
+    fn f16_members_share_the_packing_conversion() {
+        let source = r#"
+            enable f16;
+            struct Half { value: f16, pair: vec2<f16> }
+            @group(0) @binding(0) var<storage, read_write> half: Half;
+            @compute @workgroup_size(1) fn main() { half.value = 1.0h; }
+        "#;
+        let (module, _) = crate::parse_and_validate(source).unwrap();
+        let code = generate(&module).unwrap().code;
+        assert!(code.contains(&format!(
+            "function f16Bits(x: number): number {{\n{}}}",
+            crate::packing::F16_BITS_BODY
+        )));
+        assert!(code.contains("function f16Value(bits: number): number {"));
+        assert!(code.contains(", f16Bits(value.value), true);"));
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::directory::parse_file;
+
+// ============================================================================
+// Binding Usage Types
+// ============================================================================
+
+/// Which shaders use which binding slots, across a whole project.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BindingUsageReport {
+    /// Every used slot, sorted by group then binding.
+    #[wasm_bindgen(readonly)]
+    pub slots: Vec<SlotUsage>,
+    /// Files that failed to build and are missing from the report.
+    #[wasm_bindgen(readonly)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[wasm_bindgen]
+impl BindingUsageReport {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Slots whose users disagree on what is bound there.
+    pub fn conflicts(&self) -> Vec<SlotUsage> {
+        self.slots.iter().filter(|s| s.conflict).cloned().collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct SlotUsage {
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+    /// Stages using the slot anywhere in the project, sorted.
+    #[wasm_bindgen(readonly)]
+    pub stages: Vec<String>,
+    /// One use per entry point, sorted by path then entry point.
+    #[wasm_bindgen(readonly)]
+    pub uses: Vec<BindingUse>,
+    /// True if users declare different resource types or type names here.
+    #[wasm_bindgen(readonly)]
+    pub conflict: bool,
+}
+
+#[wasm_bindgen]
+impl SlotUsage {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct BindingUse {
+    #[wasm_bindgen(readonly)]
+    pub path: String,
+    #[wasm_bindgen(readonly)]
+    pub entry_point: String,
+    #[wasm_bindgen(readonly)]
+    pub stage: String,
+    /// Variable name in that shader.
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    #[wasm_bindgen(readonly)]
+    pub resource_type: String,
+    #[wasm_bindgen(readonly)]
+    pub type_name: Option<String>,
+    /// Render graph resource the variable is annotated with, if any.
+    #[wasm_bindgen(readonly)]
+    pub resource_name: Option<String>,
+}
+
+#[wasm_bindgen]
+impl BindingUse {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Binding Usage Implementation
+// ============================================================================
+
+/// Aggregate binding usage over every `.wgsl` file with entry points, the
+/// same set of files `build_files` compiles.
+pub(crate) fn binding_usage(files: &BTreeMap<String, String>) -> BindingUsageReport {
+    let mut slots = BTreeMap::<(u32, u32), Vec<BindingUse>>::new();
+    let mut diagnostics = Vec::new();
+
+    for path in files.keys().filter(|path| path.ends_with(".wgsl")) {
+        let file = match parse_file(path, files) {
+            Ok(file) => file,
+            Err(diagnostic) => {
+                diagnostics.push(Diagnostic::error(format!(
+                    "{}: {}",
+                    path, diagnostic.message
+                )));
+                continue;
+            }
+        };
+        let module = &file.module;
+        for (index, ep) in module.entry_points.iter().enumerate() {
+            let ep_info = file.info.get_entry_point(index);
+            for (handle, var) in module.global_variables.iter() {
+                let Some(binding) = &var.binding else {
+                    continue;
+                };
+                if ep_info[handle].is_empty() {
+                    continue;
+                }
+                let (resource_type, type_name, _) = crate::classify_binding(module, var);
+                slots
+                    .entry((binding.group, binding.binding))
+                    .or_default()
+                    .push(BindingUse {
+                        path: path.clone(),
+                        entry_point: ep.name.clone(),
+                        stage: crate::stage_name(ep.stage).to_string(),
+                        name: var.name.clone().unwrap_or_default(),
+                        resource_type,
+                        type_name,
+                        resource_name: None,
+                    });
+            }
+        }
+    }
+
+    let slots = slots
+        .into_iter()
+        .map(|((group, binding), uses)| {
+            let stages: BTreeSet<_> = uses.iter().map(|u| u.stage.clone()).collect();
+            let kinds: BTreeSet<_> = uses
+                .iter()
+                .map(|u| (&u.resource_type, &u.type_name))
+                .collect();
+            let conflict = kinds.len() > 1;
+            SlotUsage {
+                group,
+                binding,
+                stages: stages.into_iter().collect(),
+                uses,
+                conflict,
+            }
+        })
+        .collect();
+
+    BindingUsageReport { slots, diagnostics }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(p, s)| (p.to_string(), s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn slots_aggregate_across_files() {
+        let files = files(&[
+            (
+                "common/camera.wgsl",
+                "struct Camera { view: mat4x4<f32> }\n@group(0) @binding(0) var<uniform> camera: Camera;",
+            ),
+            (
+                "mesh.wgsl",
+                "#include \"common/camera.wgsl\"\n@vertex fn vs() -> @builtin(position) vec4<f32> { return camera.view[0]; }",
+            ),
+            (
+                "blur.wgsl",
+                "@group(0) @binding(0) var<storage, read_write> pixels: array<u32>;\n@compute @workgroup_size(1) fn main() { pixels[0] = 0u; }",
+            ),
+            ("broken.wgsl", "fn"),
+        ]);
+        let report = binding_usage(&files);
+
+        assert_eq!(report.slots.len(), 1);
+        let slot = &report.slots[0];
+        assert_eq!((slot.group, slot.binding), (0, 0));
+        assert_eq!(slot.stages, vec!["compute", "vertex"]);
+        let users: Vec<_> = slot.uses.iter().map(|u| u.path.as_str()).collect();
+        assert_eq!(users, vec!["blur.wgsl", "mesh.wgsl"]);
+        assert!(slot.conflict);
+        assert_eq!(report.conflicts().len(), 1);
+
+        assert_eq!(report.diagnostics.len(), 1);
+        assert!(report.diagnostics[0].message.starts_with("broken.wgsl: "));
+    }
+
+    #[test]
+    fn agreeing_users_do_not_conflict() {
+        let shader = "@group(1) @binding(2) var<uniform> scale: f32;\n@fragment fn fs() -> @location(0) vec4<f32> { return vec4<f32>(scale); }";
+        let report = binding_usage(&files(&[("a.wgsl", shader), ("b.wgsl", shader)]));
+        assert_eq!(report.slots[0].uses.len(), 2);
+        assert!(report.conflicts().is_empty());
+    }
+}
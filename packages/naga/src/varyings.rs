@@ -0,0 +1,797 @@
+use naga::{
+    Binding, Expression, Handle, Interpolation, Sampling, Scalar, ShaderStage, Span, Statement,
+    StructMember, SwizzleComponent, Type, TypeInner, VectorSize,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Varying Packing Types
+// ============================================================================
+
+/// A packing of vertex -> fragment varyings into fewer locations.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct VaryingPacking {
+    /// Locations the vertex stage writes as declared.
+    #[wasm_bindgen(readonly)]
+    pub locations_before: u32,
+    /// Locations it writes once packed.
+    #[wasm_bindgen(readonly)]
+    pub locations_after: u32,
+    /// One per vertex output varying, in original location order.
+    #[wasm_bindgen(readonly)]
+    pub assignments: Vec<VaryingAssignment>,
+    /// The rewritten module, when the packing was applied.
+    #[wasm_bindgen(readonly)]
+    pub wgsl: Option<String>,
+}
+
+#[wasm_bindgen]
+impl VaryingPacking {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct VaryingAssignment {
+    /// Output member (or result) name in the vertex stage.
+    #[wasm_bindgen(readonly)]
+    pub vertex_name: String,
+    /// Input name in the fragment stage; `None` if it does not read it.
+    #[wasm_bindgen(readonly)]
+    pub fragment_name: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub type_name: String,
+    #[wasm_bindgen(readonly)]
+    pub location: u32,
+    #[wasm_bindgen(readonly)]
+    pub packed_location: u32,
+    /// First component it occupies in the packed location (0 = x).
+    #[wasm_bindgen(readonly)]
+    pub component: u32,
+    #[wasm_bindgen(readonly)]
+    pub components: u32,
+}
+
+#[wasm_bindgen]
+impl VaryingAssignment {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Varying Packing Implementation
+// ============================================================================
+//
+// 32-bit scalars and vectors sharing a scalar type, interpolation and
+// sampling are packed first-fit, largest first, into vec4 slots. Anything
+// else (f16 varyings, dual-source outputs) keeps a location of its own.
+//
+// Applying the packing turns each of the two entry points into a plain
+// function, called by a new entry point of the same name that packs (vertex)
+// or unpacks (fragment) the slots around it, so no other code changes.
+
+/// One entry point input or output: an argument or result, or a member of
+/// one that is a struct.
+#[derive(Clone)]
+pub(crate) struct Leaf {
+    pub name: String,
+    pub ty: Handle<Type>,
+    pub binding: Binding,
+    /// Argument index (inputs only).
+    pub argument: usize,
+    /// Member index, if the argument or result is a struct.
+    pub member: Option<u32>,
+}
+
+/// Flatten an entry point's arguments (`result: false`) or its result.
+pub(crate) fn leaves(module: &naga::Module, function: &naga::Function, result: bool) -> Vec<Leaf> {
+    let mut out = Vec::new();
+    let mut push = |argument: usize, name: &str, ty: Handle<Type>, binding: &Option<Binding>| match (
+        binding,
+        &module.types[ty].inner,
+    ) {
+        (Some(binding), _) => out.push(Leaf {
+            name: name.to_string(),
+            ty,
+            binding: binding.clone(),
+            argument,
+            member: None,
+        }),
+        (None, TypeInner::Struct { members, .. }) => {
+            for (index, member) in members.iter().enumerate() {
+                if let Some(binding) = &member.binding {
+                    out.push(Leaf {
+                        name: member
+                            .name
+                            .clone()
+                            .unwrap_or_else(|| format!("{name}.{index}")),
+                        ty: member.ty,
+                        binding: binding.clone(),
+                        argument,
+                        member: Some(index as u32),
+                    });
+                }
+            }
+        }
+        (None, _) => {}
+    };
+    if result {
+        if let Some(result) = &function.result {
+            push(0, "result", result.ty, &result.binding);
+        }
+    } else {
+        for (index, arg) in function.arguments.iter().enumerate() {
+            let name = arg.name.clone().unwrap_or_else(|| format!("arg{index}"));
+            push(index, &name, arg.ty, &arg.binding);
+        }
+    }
+    out
+}
+
+type SlotKey = (Scalar, Option<Interpolation>, Option<Sampling>);
+
+/// One packed location.
+struct Slot {
+    /// Vertex output leaves in component order, with their first component.
+    members: Vec<(usize, u32)>,
+    used: u32,
+    /// `None` for a leaf that is not packable and keeps its own type.
+    key: Option<SlotKey>,
+    binding: Binding,
+}
+
+fn packable(module: &naga::Module, leaf: &Leaf) -> Option<(SlotKey, u32)> {
+    let Binding::Location {
+        interpolation,
+        sampling,
+        blend_src: None,
+        ..
+    } = leaf.binding
+    else {
+        return None;
+    };
+    let (scalar, components) = match module.types[leaf.ty].inner {
+        TypeInner::Scalar(scalar) => (scalar, 1),
+        TypeInner::Vector { size, scalar } => (scalar, size as u32),
+        _ => return None,
+    };
+    (scalar.width == 4).then_some(((scalar, interpolation, sampling), components))
+}
+
+fn components_of(module: &naga::Module, ty: Handle<Type>) -> u32 {
+    match module.types[ty].inner {
+        TypeInner::Vector { size, .. } => size as u32,
+        _ => 1,
+    }
+}
+
+fn vector_size(components: u32) -> Option<VectorSize> {
+    match components {
+        2 => Some(VectorSize::Bi),
+        3 => Some(VectorSize::Tri),
+        4 => Some(VectorSize::Quad),
+        _ => None,
+    }
+}
+
+struct Plan {
+    outputs: Vec<Leaf>,
+    slots: Vec<Slot>,
+    /// Slot and first component per output leaf with a location.
+    placement: Vec<Option<(usize, u32)>>,
+}
+
+fn plan(module: &naga::Module, vertex: &naga::Function) -> Plan {
+    let outputs = leaves(module, vertex, true);
+    let mut order: Vec<usize> = (0..outputs.len())
+        .filter(|i| matches!(outputs[*i].binding, Binding::Location { .. }))
+        .collect();
+    let location = |i: &usize| match outputs[*i].binding {
+        Binding::Location { location, .. } => location,
+        Binding::BuiltIn(_) => u32::MAX,
+    };
+    order.sort_by_key(|i| {
+        (
+            std::cmp::Reverse(components_of(module, outputs[*i].ty)),
+            location(i),
+        )
+    });
+
+    let mut slots: Vec<Slot> = Vec::new();
+    let mut placement = vec![None; outputs.len()];
+    let mut alone = Vec::new();
+    for index in order {
+        let Some((key, components)) = packable(module, &outputs[index]) else {
+            alone.push(index);
+            continue;
+        };
+        let slot = match slots
+            .iter()
+            .position(|s| s.key == Some(key) && s.used + components <= 4)
+        {
+            Some(slot) => slot,
+            None => {
+                slots.push(Slot {
+                    members: Vec::new(),
+                    used: 0,
+                    key: Some(key),
+                    binding: outputs[index].binding.clone(),
+                });
+                slots.len() - 1
+            }
+        };
+        let component = slots[slot].used;
+        slots[slot].members.push((index, component));
+        slots[slot].used += components;
+        placement[index] = Some((slot, component));
+    }
+    for index in alone {
+        placement[index] = Some((slots.len(), 0));
+        slots.push(Slot {
+            members: vec![(index, 0)],
+            used: components_of(module, outputs[index].ty),
+            key: None,
+            binding: outputs[index].binding.clone(),
+        });
+    }
+    for (location, slot) in slots.iter_mut().enumerate() {
+        if let Binding::Location { location: l, .. } = &mut slot.binding {
+            *l = location as u32;
+        }
+    }
+    Plan {
+        outputs,
+        slots,
+        placement,
+    }
+}
+
+impl Plan {
+    fn slot_type(&self, module: &mut naga::Module, slot: &Slot) -> Handle<Type> {
+        let inner = match (slot.key, vector_size(slot.used)) {
+            (None, _) => return self.outputs[slot.members[0].0].ty,
+            (Some((scalar, _, _)), None) => TypeInner::Scalar(scalar),
+            (Some((scalar, _, _)), Some(size)) => TypeInner::Vector { size, scalar },
+        };
+        module
+            .types
+            .insert(Type { name: None, inner }, Span::UNDEFINED)
+    }
+
+    /// The output leaf with `location`, if any.
+    fn output_at(&self, location: u32) -> Option<usize> {
+        self.outputs.iter().position(
+            |leaf| matches!(leaf.binding, Binding::Location { location: l, .. } if l == location),
+        )
+    }
+}
+
+/// Appends expressions to a function body, emitting the ones that need it.
+pub(crate) struct Body<'a> {
+    pub function: &'a mut naga::Function,
+}
+
+impl Body<'_> {
+    pub(crate) fn add(&mut self, expr: Expression) -> Handle<Expression> {
+        let needs_emit = !matches!(
+            expr,
+            Expression::FunctionArgument(_)
+                | Expression::GlobalVariable(_)
+                | Expression::CallResult(_)
+        );
+        let handle = self.function.expressions.append(expr, Span::UNDEFINED);
+        if needs_emit {
+            self.function.body.push(
+                Statement::Emit(naga::Range::new_from_bounds(handle, handle)),
+                Span::UNDEFINED,
+            );
+        }
+        handle
+    }
+
+    pub(crate) fn call(
+        &mut self,
+        helper: Handle<naga::Function>,
+        arguments: Vec<Handle<Expression>>,
+        returns: bool,
+    ) -> Option<Handle<Expression>> {
+        let result = returns.then(|| self.add(Expression::CallResult(helper)));
+        self.function.body.push(
+            Statement::Call {
+                function: helper,
+                arguments,
+                result,
+            },
+            Span::UNDEFINED,
+        );
+        result
+    }
+}
+
+/// Move an entry point's function into `module.functions` as `name`, minus
+/// its interface bindings, and return it with the emptied entry point
+/// function.
+pub(crate) fn demote(
+    module: &mut naga::Module,
+    index: usize,
+    name: String,
+) -> (Handle<naga::Function>, naga::Function) {
+    let ep = &mut module.entry_points[index];
+    let original = std::mem::take(&mut ep.function);
+    let mut helper = original.clone();
+    helper.name = Some(name);
+    for arg in &mut helper.arguments {
+        arg.binding = None;
+    }
+    if let Some(result) = &mut helper.result {
+        result.binding = None;
+    }
+    let handle = module.functions.append(helper, Span::UNDEFINED);
+    (handle, original)
+}
+
+fn struct_type(
+    module: &mut naga::Module,
+    name: String,
+    members: Vec<(String, Handle<Type>, Binding)>,
+) -> Result<Handle<Type>, Diagnostic> {
+    let layouter = crate::layout::layouter(module)?;
+    let mut offset = 0;
+    let mut alignment = naga::proc::Alignment::ONE;
+    let members = members
+        .into_iter()
+        .map(|(name, ty, binding)| {
+            let layout = layouter[ty];
+            offset = layout.alignment.round_up(offset);
+            alignment = alignment.max(layout.alignment);
+            let member = StructMember {
+                name: Some(name),
+                ty,
+                binding: Some(binding),
+                offset,
+            };
+            offset += layout.size;
+            member
+        })
+        .collect();
+    let inner = TypeInner::Struct {
+        members,
+        span: alignment.round_up(offset),
+    };
+    Ok(module.types.insert(
+        Type {
+            name: Some(name),
+            inner,
+        },
+        Span::UNDEFINED,
+    ))
+}
+
+fn rewrite_vertex(module: &mut naga::Module, index: usize, plan: &Plan) -> Result<(), Diagnostic> {
+    let name = format!("{}_unpacked", module.entry_points[index].name);
+    let (helper, original) = demote(module, index, name);
+    let slot_types: Vec<_> = plan
+        .slots
+        .iter()
+        .map(|s| plan.slot_type(module, s))
+        .collect();
+
+    let mut members = Vec::new();
+    for leaf in plan
+        .outputs
+        .iter()
+        .filter(|l| matches!(l.binding, Binding::BuiltIn(_)))
+    {
+        members.push((leaf.name.clone(), leaf.ty, leaf.binding.clone()));
+    }
+    let builtins = members.len();
+    for (location, (slot, ty)) in plan.slots.iter().zip(&slot_types).enumerate() {
+        members.push((format!("packed{location}"), *ty, slot.binding.clone()));
+    }
+    let name = match original
+        .result
+        .as_ref()
+        .and_then(|r| module.types[r.ty].name.as_ref())
+    {
+        Some(name) => format!("{name}Packed"),
+        None => format!("{}_packed", module.entry_points[index].name),
+    };
+    let packed = struct_type(module, name, members)?;
+
+    let mut function = naga::Function {
+        name: original.name.clone(),
+        arguments: original.arguments.clone(),
+        result: Some(naga::FunctionResult {
+            ty: packed,
+            binding: None,
+        }),
+        ..Default::default()
+    };
+    let mut body = Body {
+        function: &mut function,
+    };
+    let arguments = (0..original.arguments.len() as u32)
+        .map(|i| body.add(Expression::FunctionArgument(i)))
+        .collect();
+    let result = body
+        .call(helper, arguments, true)
+        .expect("vertex entry points return a value");
+    let values: Vec<_> = plan
+        .outputs
+        .iter()
+        .map(|leaf| match leaf.member {
+            Some(index) => body.add(Expression::AccessIndex {
+                base: result,
+                index,
+            }),
+            None => result,
+        })
+        .collect();
+
+    let mut components: Vec<_> = plan
+        .outputs
+        .iter()
+        .zip(&values)
+        .filter(|(l, _)| matches!(l.binding, Binding::BuiltIn(_)))
+        .map(|(_, value)| *value)
+        .collect();
+    debug_assert_eq!(components.len(), builtins);
+    for (slot, ty) in plan.slots.iter().zip(&slot_types) {
+        let parts: Vec<_> = slot.members.iter().map(|(leaf, _)| values[*leaf]).collect();
+        components.push(if parts.len() == 1 {
+            parts[0]
+        } else {
+            body.add(Expression::Compose {
+                ty: *ty,
+                components: parts,
+            })
+        });
+    }
+    let value = body.add(Expression::Compose {
+        ty: packed,
+        components,
+    });
+    function
+        .body
+        .push(Statement::Return { value: Some(value) }, Span::UNDEFINED);
+    module.entry_points[index].function = function;
+    Ok(())
+}
+
+fn rewrite_fragment(
+    module: &mut naga::Module,
+    index: usize,
+    plan: &Plan,
+    inputs: &[Leaf],
+) -> Result<(), Diagnostic> {
+    let name = format!("{}_unpacked", module.entry_points[index].name);
+    let (helper, original) = demote(module, index, name);
+
+    // Builtins stay arguments of their own; varyings come from their slots.
+    let mut arguments = Vec::new();
+    let mut source = Vec::with_capacity(inputs.len());
+    let mut slot_argument = vec![None; plan.slots.len()];
+    for leaf in inputs {
+        match leaf.binding {
+            Binding::BuiltIn(_) => {
+                source.push((arguments.len(), None));
+                arguments.push(naga::FunctionArgument {
+                    name: Some(leaf.name.clone()),
+                    ty: leaf.ty,
+                    binding: Some(leaf.binding.clone()),
+                });
+            }
+            Binding::Location { location, .. } => {
+                let output = plan.output_at(location).expect("checked by the caller");
+                let (slot, component) = plan.placement[output].expect("varyings are placed");
+                let argument = match slot_argument[slot] {
+                    Some(argument) => argument,
+                    None => {
+                        let ty = plan.slot_type(module, &plan.slots[slot]);
+                        arguments.push(naga::FunctionArgument {
+                            name: Some(format!("packed{slot}")),
+                            ty,
+                            binding: Some(plan.slots[slot].binding.clone()),
+                        });
+                        slot_argument[slot] = Some(arguments.len() - 1);
+                        arguments.len() - 1
+                    }
+                };
+                source.push((argument, Some((slot, component))));
+            }
+        }
+    }
+
+    let mut function = naga::Function {
+        name: original.name.clone(),
+        arguments,
+        result: original.result.clone(),
+        ..Default::default()
+    };
+    let mut body = Body {
+        function: &mut function,
+    };
+    let mut values = Vec::with_capacity(inputs.len());
+    for (leaf, (argument, placed)) in inputs.iter().zip(source) {
+        let argument = body.add(Expression::FunctionArgument(argument as u32));
+        let value = match placed {
+            Some((slot, component)) if plan.slots[slot].used > 1 => {
+                let components = components_of(module, leaf.ty);
+                match vector_size(components) {
+                    _ if components == plan.slots[slot].used => argument,
+                    None => body.add(Expression::AccessIndex {
+                        base: argument,
+                        index: component,
+                    }),
+                    Some(size) => {
+                        let mut pattern = [SwizzleComponent::X; 4];
+                        for (i, p) in pattern.iter_mut().take(components as usize).enumerate() {
+                            *p = SwizzleComponent::from_index(component + i as u32);
+                        }
+                        body.add(Expression::Swizzle {
+                            size,
+                            vector: argument,
+                            pattern,
+                        })
+                    }
+                }
+            }
+            _ => argument,
+        };
+        values.push(value);
+    }
+
+    let mut call_arguments = Vec::with_capacity(original.arguments.len());
+    for (index, arg) in original.arguments.iter().enumerate() {
+        let parts: Vec<_> = inputs
+            .iter()
+            .zip(&values)
+            .filter(|(leaf, _)| leaf.argument == index)
+            .map(|(leaf, value)| (leaf.member, *value))
+            .collect();
+        call_arguments.push(match parts.as_slice() {
+            [(None, value)] => *value,
+            _ => body.add(Expression::Compose {
+                ty: arg.ty,
+                components: parts.iter().map(|(_, value)| *value).collect(),
+            }),
+        });
+    }
+    let result = body.call(helper, call_arguments, original.result.is_some());
+    function
+        .body
+        .push(Statement::Return { value: result }, Span::UNDEFINED);
+    module.entry_points[index].function = function;
+    Ok(())
+}
+
+pub(crate) fn find(
+    module: &naga::Module,
+    name: &str,
+    stage: ShaderStage,
+) -> Result<usize, Diagnostic> {
+    let index = module
+        .entry_points
+        .iter()
+        .position(|ep| ep.name == name)
+        .ok_or_else(|| Diagnostic::error(format!("Entry point '{}' not found", name)))?;
+    if module.entry_points[index].stage != stage {
+        return Err(Diagnostic::error(format!(
+            "Entry point '{}' is not a {} shader",
+            name,
+            crate::stage_name(stage)
+        )));
+    }
+    Ok(index)
+}
+
+/// Plan a packing for `vertex`'s outputs as read by `fragment`, and rewrite
+/// both entry points to use it if `apply`.
+pub(crate) fn pack_varyings(
+    module: &mut naga::Module,
+    vertex: &str,
+    fragment: &str,
+    apply: bool,
+) -> Result<VaryingPacking, Diagnostic> {
+    let vs = find(module, vertex, ShaderStage::Vertex)?;
+    let fs = find(module, fragment, ShaderStage::Fragment)?;
+    let plan = plan(module, &module.entry_points[vs].function);
+    let inputs = leaves(module, &module.entry_points[fs].function, false);
+    for leaf in &inputs {
+        let Binding::Location { location, .. } = leaf.binding else {
+            continue;
+        };
+        let output = plan.output_at(location).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Fragment input '{}' reads @location({}), which '{}' does not write",
+                leaf.name, location, vertex
+            ))
+        })?;
+        if module.types[plan.outputs[output].ty].inner != module.types[leaf.ty].inner {
+            return Err(Diagnostic::error(format!(
+                "Fragment input '{}' does not match the type of vertex output '{}'",
+                leaf.name, plan.outputs[output].name
+            )));
+        }
+    }
+
+    let mut assignments = Vec::new();
+    for (index, leaf) in plan.outputs.iter().enumerate() {
+        let (Binding::Location { location, .. }, Some((slot, component))) =
+            (&leaf.binding, plan.placement[index])
+        else {
+            continue;
+        };
+        let fragment_name = inputs
+            .iter()
+            .find(|input| matches!(input.binding, Binding::Location { location: l, .. } if l == *location))
+            .map(|input| input.name.clone());
+        assignments.push(VaryingAssignment {
+            vertex_name: leaf.name.clone(),
+            fragment_name,
+            type_name: crate::get_type_name(module, leaf.ty).unwrap_or_default(),
+            location: *location,
+            packed_location: slot as u32,
+            component,
+            components: components_of(module, leaf.ty),
+        });
+    }
+    assignments.sort_by_key(|a| a.location);
+
+    let wgsl = if apply {
+        rewrite_vertex(module, vs, &plan)?;
+        rewrite_fragment(module, fs, &plan, &inputs)?;
+        let info = crate::validate_module(module)?;
+        let text =
+            naga::back::wgsl::write_string(module, &info, naga::back::wgsl::WriterFlags::empty())
+                .map_err(|e| Diagnostic::error(format!("WGSL write error: {e:?}")))?;
+        Some(text)
+    } else {
+        None
+    };
+
+    Ok(VaryingPacking {
+        locations_before: assignments.len() as u32,
+        locations_after: plan.slots.len() as u32,
+        assignments,
+        wgsl,
+    })
+}
+
+/// Packs the varyings `vertexEntry` passes to `fragmentEntry` into as few
+/// vec4 locations as possible, for GPUs limited in varying count. Returns
+/// the location usage before and after and where each varying moved. With
+/// `apply: true`, `wgsl` holds the module with both entry points rewritten
+/// to the packed interface; other fragment shaders paired with the vertex
+/// stage are left as they were.
+#[wasm_bindgen(js_name = packVaryings)]
+pub fn pack_varyings_wgsl(
+    wgsl: &str,
+    vertex_entry: &str,
+    fragment_entry: &str,
+    apply: Option<bool>,
+) -> Result<VaryingPacking, JsValue> {
+    let (mut module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    pack_varyings(
+        &mut module,
+        vertex_entry,
+        fragment_entry,
+        apply.unwrap_or(false),
+    )
+    .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct VsOut {
+            @builtin(position) position: vec4<f32>,
+            @location(0) uv: vec2<f32>,
+            @location(1) normal: vec3<f32>,
+            @location(2) fade: f32,
+            @location(3) tint: vec2<f32>,
+            @location(4) @interpolate(flat) material: u32,
+        }
+
+        @vertex
+        fn vs(@location(0) p: vec3<f32>) -> VsOut {
+            var out: VsOut;
+            out.position = vec4<f32>(p, 1.0);
+            out.uv = p.xy;
+            out.normal = p;
+            out.fade = p.z;
+            out.tint = p.yx;
+            out.material = 7u;
+            return out;
+        }
+
+        @fragment
+        fn fs(in: VsOut) -> @location(0) vec4<f32> {
+            return vec4<f32>(in.normal * in.fade, f32(in.material)) + in.uv.xyxy;
+        }
+
+        @fragment
+        fn fs_flat(@location(4) @interpolate(flat) material: u32,
+                   @location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            return vec4<f32>(uv, f32(material), 1.0);
+        }
+    "#;
+
+    #[test]
+    fn plans_vec4_slots_per_interpolation() {
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        let packing = pack_varyings(&mut module, "vs", "fs_flat", false).unwrap();
+        assert_eq!(packing.locations_before, 5);
+        // normal + fade, uv + tint, material.
+        assert_eq!(packing.locations_after, 3);
+        let at = |name: &str| {
+            let a = packing
+                .assignments
+                .iter()
+                .find(|a| a.vertex_name == name)
+                .unwrap();
+            (a.packed_location, a.component)
+        };
+        assert_eq!(at("normal"), (0, 0));
+        assert_eq!(at("fade"), (0, 3));
+        assert_eq!(at("uv"), (1, 0));
+        assert_eq!(at("tint"), (1, 2));
+        assert_eq!(at("material"), (2, 0));
+        let uv = &packing.assignments[0];
+        assert_eq!(uv.fragment_name.as_deref(), Some("uv"));
+        assert_eq!(packing.assignments[1].fragment_name, None);
+        assert!(packing.wgsl.is_none());
+    }
+
+    #[test]
+    fn applied_packing_compiles_for_both_styles() {
+        for fragment in ["fs", "fs_flat"] {
+            let mut module = crate::parse_wgsl(SHADER).unwrap();
+            let packing = pack_varyings(&mut module, "vs", fragment, true).unwrap();
+            let wgsl = packing.wgsl.unwrap();
+            assert!(wgsl.contains("@location(0) packed0_: vec4<f32>"), "{wgsl}");
+            assert!(
+                wgsl.contains("@location(2) @interpolate(flat) packed2_: u32"),
+                "{wgsl}"
+            );
+            let signature = wgsl
+                .lines()
+                .find(|line| line.starts_with(&format!("fn {fragment}(")))
+                .unwrap();
+            assert!(!signature.contains("@location(4)"), "{signature}");
+            let (module, info) = crate::parse_and_validate(&wgsl).unwrap();
+            crate::write_msl(&module, &info, Some(fragment)).unwrap();
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_stages() {
+        let mut module = crate::parse_wgsl(SHADER).unwrap();
+        assert!(pack_varyings(&mut module, "fs", "vs", false).is_err());
+        let mismatched = SHADER.replace(
+            "@location(0) uv: vec2<f32>) -> @location(0)",
+            "@location(0) uv: vec3<f32>) -> @location(0)",
+        );
+        let mismatched = mismatched.replace("vec4<f32>(uv, f32", "vec4<f32>(uv.xy, f32");
+        let mut module = crate::parse_wgsl(&mismatched).unwrap();
+        assert!(pack_varyings(&mut module, "vs", "fs_flat", false).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use naga::{Binding, Handle, Scalar, ScalarKind, Type, TypeInner, VectorSize};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+
+// ============================================================================
+// Vertex Buffer Layout Types
+// ============================================================================
+
+/// A `GPUVertexBufferLayout`; `toJSON` is ready for pipeline creation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct VertexBufferLayout {
+    #[wasm_bindgen(readonly)]
+    pub array_stride: u32,
+    /// Always `"vertex"`.
+    #[wasm_bindgen(readonly)]
+    pub step_mode: String,
+    /// By shader location.
+    #[wasm_bindgen(readonly)]
+    pub attributes: Vec<VertexAttribute>,
+}
+
+#[wasm_bindgen]
+impl VertexBufferLayout {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A `GPUVertexAttribute`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct VertexAttribute {
+    /// A `GPUVertexFormat`, e.g. `"float32x3"`.
+    #[wasm_bindgen(readonly)]
+    pub format: String,
+    #[wasm_bindgen(readonly)]
+    pub offset: u32,
+    #[wasm_bindgen(readonly)]
+    pub shader_location: u32,
+}
+
+#[wasm_bindgen]
+impl VertexAttribute {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// Vertex Buffer Layout Implementation
+// ============================================================================
+//
+// Each input gets the format its type reads without conversion, 32-bit
+// types as `float32`/`uint32`/`sint32` and `f16` as `float16`. There is no
+// three-component `f16` format, so `vec3<f16>` reads `float16x4`. Attributes
+// are interleaved in location order, each at the next offset WebGPU allows
+// for its format (a multiple of the smaller of 4 and its size), and the
+// stride rounds up to 4.
+
+/// The format for a vertex input of type `inner`, and its size in bytes.
+fn vertex_format(inner: &TypeInner) -> Option<(String, u32)> {
+    let (scalar, count) = match *inner {
+        TypeInner::Scalar(scalar) => (scalar, 1),
+        TypeInner::Vector { size, scalar } => (scalar, size as u32),
+        _ => return None,
+    };
+    let base = match scalar {
+        Scalar {
+            kind: ScalarKind::Float,
+            width: 4,
+        } => "float32",
+        Scalar {
+            kind: ScalarKind::Float,
+            width: 2,
+        } => "float16",
+        Scalar {
+            kind: ScalarKind::Uint,
+            width: 4,
+        } => "uint32",
+        Scalar {
+            kind: ScalarKind::Sint,
+            width: 4,
+        } => "sint32",
+        _ => return None,
+    };
+    let count = if scalar.width == 2 && count == VectorSize::Tri as u32 {
+        4
+    } else {
+        count
+    };
+    let format = match count {
+        1 => base.to_string(),
+        n => format!("{base}x{n}"),
+    };
+    Some((format, scalar.width as u32 * count))
+}
+
+/// `(location, type)` of every `@location` input, struct members included.
+fn location_inputs(module: &naga::Module, function: &naga::Function) -> Vec<(u32, Handle<Type>)> {
+    let mut inputs = Vec::new();
+    for arg in &function.arguments {
+        match (&arg.binding, &module.types[arg.ty].inner) {
+            (Some(Binding::Location { location, .. }), _) => inputs.push((*location, arg.ty)),
+            (None, TypeInner::Struct { members, .. }) => {
+                for member in members {
+                    if let Some(Binding::Location { location, .. }) = member.binding {
+                        inputs.push((location, member.ty));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    inputs.sort_by_key(|&(location, _)| location);
+    inputs
+}
+
+pub(crate) fn vertex_buffer_layout(
+    module: &naga::Module,
+    entry_point: Option<&str>,
+) -> Result<VertexBufferLayout, Diagnostic> {
+    let entry = match entry_point {
+        Some(name) => crate::find_entry_point(module, name)?,
+        None => {
+            let mut vertex = module
+                .entry_points
+                .iter()
+                .filter(|ep| ep.stage == naga::ShaderStage::Vertex);
+            match (vertex.next(), vertex.next()) {
+                (Some(entry), None) => entry,
+                (None, _) => return Err(Diagnostic::error("No vertex entry point")),
+                (Some(_), Some(_)) => {
+                    return Err(Diagnostic::error("Several vertex entry points; name one"));
+                }
+            }
+        }
+    };
+    if entry.stage != naga::ShaderStage::Vertex {
+        return Err(Diagnostic::error(format!(
+            "Entry point '{}' is not a vertex shader",
+            entry.name
+        )));
+    }
+
+    let mut attributes = Vec::new();
+    let mut offset = 0u32;
+    for (location, ty) in location_inputs(module, &entry.function) {
+        let (format, size) = vertex_format(&module.types[ty].inner).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "No vertex format for the input at location {} ({})",
+                location,
+                crate::get_type_name(module, ty).unwrap_or_default()
+            ))
+        })?;
+        offset = offset.next_multiple_of(size.min(4));
+        attributes.push(VertexAttribute {
+            format,
+            offset,
+            shader_location: location,
+        });
+        offset += size;
+    }
+    Ok(VertexBufferLayout {
+        array_stride: offset.next_multiple_of(4),
+        step_mode: "vertex".to_string(),
+        attributes,
+    })
+}
+
+/// An interleaved `GPUVertexBufferLayout` for the vertex entry point
+/// `entryPoint` (or the only one): a format per `@location` input, matching
+/// its type, with offsets and `arrayStride` packed as tightly as WebGPU
+/// allows.
+#[wasm_bindgen(js_name = generateVertexBufferLayout)]
+pub fn generate_vertex_buffer_layout(
+    wgsl: &str,
+    entry_point: Option<String>,
+) -> Result<VertexBufferLayout, JsValue> {
+    let (module, _info) = crate::parse_and_validate(wgsl).map_err(throw)?;
+    vertex_buffer_layout(&module, entry_point.as_deref()).map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(source: &str, entry_point: Option<&str>) -> Result<VertexBufferLayout, Diagnostic> {
+        let module = crate::parse_wgsl(source).unwrap();
+        vertex_buffer_layout(&module, entry_point)
+    }
+
+    #[test]
+    fn inputs_interleave_in_location_order() {
+        let source = r#"
+            enable f16;
+            struct Vertex {
+                @location(2) uv: vec2<f32>,
+                @location(0) position: vec3<f32>,
+            }
+            @vertex
+            fn vs(v: Vertex, @location(1) normal: vec3<f16>, @location(3) id: u32)
+                -> @builtin(position) vec4<f32> {
+                return vec4<f32>(v.position, f32(id));
+            }
+        "#;
+        let layout = layout(source, None).unwrap();
+        let attributes: Vec<_> = layout
+            .attributes
+            .iter()
+            .map(|a| (a.shader_location, a.format.as_str(), a.offset))
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                (0, "float32x3", 0),
+                (1, "float16x4", 12),
+                (2, "float32x2", 20),
+                (3, "uint32", 28),
+            ]
+        );
+        assert_eq!(layout.array_stride, 32);
+        assert_eq!(layout.step_mode, "vertex");
+    }
+
+    #[test]
+    fn entry_point_must_be_a_vertex_shader() {
+        let source = "@vertex fn a() -> @builtin(position) vec4<f32> { return vec4<f32>(); }\n\
+                      @vertex fn b() -> @builtin(position) vec4<f32> { return vec4<f32>(); }\n\
+                      @fragment fn fs() {}";
+        assert!(layout(source, None).is_err());
+        assert!(layout(source, Some("fs")).is_err());
+        let empty = layout(source, Some("a")).unwrap();
+        assert!(empty.attributes.is_empty());
+        assert_eq!(empty.array_stride, 0);
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::{BTreeMap, HashSet};
+
+use naga::back::glsl::{self, Features, Version, WriterFlags};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::Diagnostic;
+use crate::diagnostics::throw;
+use crate::glsl::{parse_stage, select_entry_point, write_with};
+use crate::lexer::{TokenKind, tokenize};
+use crate::preset;
+use crate::provenance::embed_fingerprint;
+
+// ============================================================================
+// WebGL2 Types
+// ============================================================================
+
+/// How combined samplers are named in the output.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum SamplerNaming {
+    /// The WGSL texture's name, `albedo`.
+    #[default]
+    Texture,
+    /// The texture's and the sampler's names joined, `albedo_linear`.
+    TextureSampler,
+    /// naga's binding-derived name, `_group_0_binding_1_fs`.
+    Binding,
+}
+
+/// A slot chosen for the resource at `group`/`binding`.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub(crate) struct SlotOverride {
+    pub group: u32,
+    pub binding: u32,
+    pub slot: u32,
+}
+
+/// Options object accepted by `wgslToWebGl2`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebGlOptions {
+    #[serde(default)]
+    pub sampler_naming: SamplerNaming,
+    /// Uniform block slots and texture units to use instead of the
+    /// assigned ones, by the WGSL resource's group and binding.
+    #[serde(default)]
+    pub slots: Vec<SlotOverride>,
+    /// Flip `y` and remap depth from WebGPU's clip space to GL's.
+    #[serde(default = "default_adjust_coordinate_space")]
+    pub adjust_coordinate_space: bool,
+    /// Write `gl_PointSize = 1.0` in vertex shaders, for drawing points.
+    #[serde(default)]
+    pub force_point_size: bool,
+    /// Keep globals and functions the entry point does not use.
+    #[serde(default)]
+    pub include_unused_items: bool,
+    /// Device preset whose bounds checks apply.
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Lead the output with a source hash and entry point comment.
+    #[serde(default)]
+    pub fingerprint: bool,
+}
+
+fn default_adjust_coordinate_space() -> bool {
+    true
+}
+
+impl Default for WebGlOptions {
+    fn default() -> Self {
+        Self {
+            sampler_naming: SamplerNaming::default(),
+            slots: Vec::new(),
+            adjust_coordinate_space: default_adjust_coordinate_space(),
+            force_point_size: false,
+            include_unused_items: false,
+            preset: None,
+            fingerprint: false,
+        }
+    }
+}
+
+/// GLSL ES 3.00 for one entry point, with what WebGL2 needs to bind it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct WebGlShader {
+    /// `None` if the entry point uses something in `unsupported`.
+    #[wasm_bindgen(readonly)]
+    pub glsl: Option<String>,
+    /// In slot order.
+    #[wasm_bindgen(readonly)]
+    pub uniform_blocks: Vec<WebGlUniformBlock>,
+    /// In unit order.
+    #[wasm_bindgen(readonly)]
+    pub samplers: Vec<WebGlSampler>,
+    /// WGSL features GLSL ES 3.00 cannot express; empty when `glsl` is set.
+    #[wasm_bindgen(readonly)]
+    pub unsupported: Vec<UnsupportedFeature>,
+}
+
+#[wasm_bindgen]
+impl WebGlShader {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A uniform buffer, for `gl.uniformBlockBinding(program,
+/// gl.getUniformBlockIndex(program, name), slot)`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct WebGlUniformBlock {
+    /// The block name in the GLSL.
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// The WGSL variable.
+    #[wasm_bindgen(readonly)]
+    pub variable: String,
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+    #[wasm_bindgen(readonly)]
+    pub slot: u32,
+}
+
+#[wasm_bindgen]
+impl WebGlUniformBlock {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+/// A texture and the sampler GLSL combines it with, for
+/// `gl.uniform1i(gl.getUniformLocation(program, name), unit)`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct WebGlSampler {
+    /// The uniform name in the GLSL.
+    #[wasm_bindgen(readonly)]
+    pub name: String,
+    /// The WGSL texture variable.
+    #[wasm_bindgen(readonly)]
+    pub texture: String,
+    /// The WGSL sampler variable; `None` for storage textures.
+    #[wasm_bindgen(readonly)]
+    pub sampler: Option<String>,
+    /// The texture's group and binding.
+    #[wasm_bindgen(readonly)]
+    pub group: u32,
+    #[wasm_bindgen(readonly)]
+    pub binding: u32,
+    #[wasm_bindgen(readonly)]
+    pub unit: u32,
+}
+
+#[wasm_bindgen]
+impl WebGlSampler {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[wasm_bindgen(getter_with_clone)]
+pub struct UnsupportedFeature {
+    /// naga's name for it, e.g. `BUFFER_STORAGE`.
+    #[wasm_bindgen(readonly)]
+    pub feature: String,
+    /// What it is in WGSL terms.
+    #[wasm_bindgen(readonly)]
+    pub description: String,
+}
+
+#[wasm_bindgen]
+impl UnsupportedFeature {
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        serde_wasm_bindgen::to_value(self).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
+
+// ============================================================================
+// WebGL2 Implementation
+// ============================================================================
+//
+// WebGL2 is GLSL ES 3.00, which has no `layout(binding = ...)`: uniform
+// blocks get their slot from `gl.uniformBlockBinding` and samplers their
+// unit from `gl.uniform1i`, by name, after linking. So the output comes with
+// a table of both. Slots and units count from 0 in group and binding order,
+// separately, skipping any the caller fixed with `slots`. GLSL has no
+// separate samplers either; naga folds each sampler into the texture it
+// samples and names the result after the texture's binding, which is
+// renamed here after the WGSL names unless `samplerNaming` is `"binding"`.
+// A name already used in the output or reserved by GLSL gets `_` appended.
+//
+// When the entry point needs something ES 3.00 lacks, such as storage
+// buffers or compute shaders, naga's writer fails listing every missing
+// feature; those come back in `unsupported` rather than as an error.
+
+/// Words GLSL ES 3.00 reserves that are valid WGSL identifiers.
+const GLSL_RESERVED: &[&str] = &[
+    "active",
+    "asm",
+    "attribute",
+    "buffer",
+    "cast",
+    "centroid",
+    "class",
+    "common",
+    "double",
+    "enum",
+    "extern",
+    "external",
+    "filter",
+    "fixed",
+    "flat",
+    "float",
+    "goto",
+    "half",
+    "highp",
+    "in",
+    "inline",
+    "input",
+    "int",
+    "interface",
+    "invariant",
+    "layout",
+    "long",
+    "lowp",
+    "main",
+    "mediump",
+    "namespace",
+    "noinline",
+    "out",
+    "output",
+    "packed",
+    "partition",
+    "precision",
+    "public",
+    "sample",
+    "sampler",
+    "shared",
+    "short",
+    "sizeof",
+    "smooth",
+    "static",
+    "superp",
+    "template",
+    "texture",
+    "this",
+    "typedef",
+    "uniform",
+    "union",
+    "unsigned",
+    "using",
+    "varying",
+    "volatile",
+];
+
+/// What `feature` is in WGSL terms.
+fn describe(feature: Features) -> &'static str {
+    const DESCRIPTIONS: &[(Features, &str)] = &[
+        (Features::BUFFER_STORAGE, "storage buffers (`var<storage>`)"),
+        (Features::ARRAY_OF_ARRAYS, "arrays of arrays"),
+        (Features::DOUBLE_TYPE, "`f64`"),
+        (Features::FULL_IMAGE_FORMATS, "storage texture formats"),
+        (Features::MULTISAMPLED_TEXTURES, "multisampled textures"),
+        (
+            Features::MULTISAMPLED_TEXTURE_ARRAYS,
+            "multisampled texture arrays",
+        ),
+        (Features::CUBE_TEXTURES_ARRAY, "`texture_cube_array`"),
+        (Features::COMPUTE_SHADER, "compute shaders"),
+        (Features::IMAGE_LOAD_STORE, "storage textures"),
+        (Features::CONSERVATIVE_DEPTH, "`@early_depth_test`"),
+        (Features::NOPERSPECTIVE_QUALIFIER, "`@interpolate(linear)`"),
+        (Features::SAMPLE_QUALIFIER, "`@interpolate(..., sample)`"),
+        (Features::CLIP_DISTANCE, "`@builtin(clip_distances)`"),
+        (Features::CULL_DISTANCE, "cull distances"),
+        (
+            Features::SAMPLE_VARIABLES,
+            "`@builtin(sample_index)` and `@builtin(sample_mask)`",
+        ),
+        (Features::DYNAMIC_ARRAY_SIZE, "runtime-sized arrays"),
+        (Features::MULTI_VIEW, "multiview"),
+        (Features::TEXTURE_SAMPLES, "`textureNumSamples`"),
+        (Features::TEXTURE_LEVELS, "`textureNumLevels`"),
+        (
+            Features::IMAGE_SIZE,
+            "`textureDimensions` of storage textures",
+        ),
+        (Features::DUAL_SOURCE_BLENDING, "`@blend_src`"),
+        (Features::INSTANCE_INDEX, "`@builtin(instance_index)`"),
+        (
+            Features::TEXTURE_SHADOW_LOD,
+            "explicit LOD on shadow textures",
+        ),
+        (Features::SUBGROUP_OPERATIONS, "subgroup operations"),
+        (Features::TEXTURE_ATOMICS, "texture atomics"),
+    ];
+    DESCRIPTIONS
+        .iter()
+        .find(|(flag, _)| *flag == feature)
+        .map_or("a feature this GLSL version lacks", |(_, text)| text)
+}
+
+/// The features of a failed write, or the error if it failed otherwise.
+fn unsupported(error: glsl::Error) -> Result<Vec<UnsupportedFeature>, Diagnostic> {
+    let single = |feature: &str, description: &str| {
+        Ok(vec![UnsupportedFeature {
+            feature: feature.to_string(),
+            description: description.to_string(),
+        }])
+    };
+    match error {
+        glsl::Error::MissingFeatures(features) => Ok(features
+            .iter_names()
+            .map(|(name, feature)| UnsupportedFeature {
+                feature: name.to_string(),
+                description: describe(feature).to_string(),
+            })
+            .collect()),
+        glsl::Error::ImageMultipleSamplers => single(
+            "IMAGE_MULTIPLE_SAMPLERS",
+            "a texture sampled with more than one sampler",
+        ),
+        glsl::Error::FirstSamplingNotSupported => {
+            single("FIRST_SAMPLING", "`@interpolate(flat, first)`")
+        }
+        error => Err(Diagnostic::error(format!("GLSL error: {error:?}"))),
+    }
+}
+
+/// Slot for each key in order, skipping the `fixed` ones, which keep theirs.
+fn assign_slots(keys: &[(u32, u32)], fixed: &[SlotOverride]) -> Vec<u32> {
+    let taken: HashSet<u32> = fixed
+        .iter()
+        .filter(|o| keys.contains(&(o.group, o.binding)))
+        .map(|o| o.slot)
+        .collect();
+    let mut free = (0..).filter(|slot| !taken.contains(slot));
+    keys.iter()
+        .map(|&(group, binding)| {
+            fixed
+                .iter()
+                .find(|o| o.group == group && o.binding == binding)
+                .map(|o| o.slot)
+                .unwrap_or_else(|| free.next().unwrap())
+        })
+        .collect()
+}
+
+/// `wanted`, or with `_` appended until it is free.
+fn free_name(wanted: String, taken: &HashSet<String>) -> String {
+    let mut name = wanted;
+    while taken.contains(&name) || GLSL_RESERVED.contains(&name.as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+/// `source` with each identifier `from` in `renames` replaced by `to`.
+fn rename_identifiers(source: &str, renames: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    for token in tokenize(source) {
+        if token.kind == TokenKind::Ident
+            && let Some(to) = renames.get(token.text)
+        {
+            out.push_str(&source[copied..token.start]);
+            out.push_str(to);
+            copied = token.end();
+        }
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+fn global_name(module: &naga::Module, handle: naga::Handle<naga::GlobalVariable>) -> String {
+    module.global_variables[handle]
+        .name
+        .clone()
+        .unwrap_or_default()
+}
+
+pub(crate) fn compile_webgl(
+    wgsl: &str,
+    entry_point: Option<&str>,
+    stage: &str,
+    options: &WebGlOptions,
+) -> Result<WebGlShader, Diagnostic> {
+    let stage = parse_stage(stage)?;
+    let preset = preset::resolve(options.preset.as_deref())?;
+    let module = crate::parse_wgsl(wgsl)?;
+    let info = crate::validate_for(&module, preset)?;
+    let entry_point = select_entry_point(&module, entry_point, stage)?;
+
+    let mut writer_flags = WriterFlags::empty();
+    writer_flags.set(
+        WriterFlags::ADJUST_COORDINATE_SPACE,
+        options.adjust_coordinate_space,
+    );
+    writer_flags.set(WriterFlags::FORCE_POINT_SIZE, options.force_point_size);
+    writer_flags.set(
+        WriterFlags::INCLUDE_UNUSED_ITEMS,
+        options.include_unused_items,
+    );
+    let glsl_options = glsl::Options {
+        version: Version::new_gles(300),
+        writer_flags,
+        ..Default::default()
+    };
+    let (source, reflection) = match write_with(
+        &module,
+        &info,
+        &glsl_options,
+        entry_point.clone(),
+        stage,
+        preset,
+    ) {
+        Ok(written) => written,
+        Err(error) => {
+            return Ok(WebGlShader {
+                glsl: None,
+                uniform_blocks: Vec::new(),
+                samplers: Vec::new(),
+                unsupported: unsupported(error)?,
+            });
+        }
+    };
+    let binding = |handle: naga::Handle<naga::GlobalVariable>| {
+        let binding = module.global_variables[handle].binding.as_ref().unwrap();
+        (binding.group, binding.binding)
+    };
+
+    let mut blocks: Vec<_> = reflection.uniforms.into_iter().collect();
+    blocks.sort_by_key(|&(handle, _)| binding(handle));
+    let keys: Vec<_> = blocks.iter().map(|&(handle, _)| binding(handle)).collect();
+    let mut uniform_blocks: Vec<_> = blocks
+        .into_iter()
+        .zip(assign_slots(&keys, &options.slots))
+        .map(|((handle, name), slot)| {
+            let (group, binding) = binding(handle);
+            WebGlUniformBlock {
+                name,
+                variable: global_name(&module, handle),
+                group,
+                binding,
+                slot,
+            }
+        })
+        .collect();
+
+    let mut textures: Vec<_> = reflection.texture_mapping.into_iter().collect();
+    textures.sort_by_key(|(_, mapping)| binding(mapping.texture));
+    let keys: Vec<_> = textures.iter().map(|(_, m)| binding(m.texture)).collect();
+    let mut taken: HashSet<String> = tokenize(&source)
+        .iter()
+        .filter(|token| token.kind == TokenKind::Ident)
+        .map(|token| token.text.to_string())
+        .collect();
+    let mut renames = BTreeMap::new();
+    let mut samplers: Vec<_> = textures
+        .into_iter()
+        .zip(assign_slots(&keys, &options.slots))
+        .map(|((name, mapping), unit)| {
+            let texture = global_name(&module, mapping.texture);
+            let sampler = mapping.sampler.map(|handle| global_name(&module, handle));
+            let wanted = match (options.sampler_naming, &sampler) {
+                (SamplerNaming::Binding, _) => None,
+                (SamplerNaming::TextureSampler, Some(sampler)) => {
+                    Some(format!("{texture}_{sampler}"))
+                }
+                _ => Some(texture.clone()),
+            };
+            let name = match wanted.filter(|n| !n.starts_with("gl_") && !n.contains("__")) {
+                Some(wanted) => {
+                    let new = free_name(wanted, &taken);
+                    taken.insert(new.clone());
+                    renames.insert(name, new.clone());
+                    new
+                }
+                None => name,
+            };
+            let (group, binding) = binding(mapping.texture);
+            WebGlSampler {
+                name,
+                texture,
+                sampler,
+                group,
+                binding,
+                unit,
+            }
+        })
+        .collect();
+    uniform_blocks.sort_by_key(|block| block.slot);
+    samplers.sort_by_key(|sampler| sampler.unit);
+
+    let mut source = rename_identifiers(&source, &renames);
+    if options.fingerprint {
+        embed_fingerprint(&mut source, wgsl, Some(&entry_point));
+    }
+    Ok(WebGlShader {
+        glsl: Some(source),
+        uniform_blocks,
+        samplers,
+        unsupported: Vec::new(),
+    })
+}
+
+/// WGSL -> GLSL ES 3.00 for WebGL2, with the slot of every uniform block
+/// and the texture unit of every sampler, which WebGL2 sets by name from
+/// JavaScript. `stage` and `entryPoint` work as in `wgslToGlsl`. `options`
+/// is `{ samplerNaming?: "texture" | "textureSampler" | "binding", slots?:
+/// [{ group, binding, slot }], adjustCoordinateSpace?, forcePointSize?,
+/// includeUnusedItems?, preset?, fingerprint? }`. Features ES 3.00 lacks,
+/// such as storage buffers, come back in `unsupported` rather than thrown.
+#[wasm_bindgen(js_name = wgslToWebGl2)]
+pub fn wgsl_to_webgl2(
+    wgsl: &str,
+    entry_point: Option<String>,
+    stage: &str,
+    options: JsValue,
+) -> Result<WebGlShader, JsValue> {
+    let options: Option<WebGlOptions> = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid WebGL options: {e}")))?;
+    compile_webgl(
+        wgsl,
+        entry_point.as_deref(),
+        stage,
+        &options.unwrap_or_default(),
+    )
+    .map_err(throw)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+        struct Params { tint: vec4<f32> }
+        @group(0) @binding(0) var<uniform> params: Params;
+        @group(0) @binding(1) var albedo: texture_2d<f32>;
+        @group(0) @binding(2) var linear: sampler;
+        @group(1) @binding(0) var<uniform> scale: f32;
+        @group(1) @binding(1) var texture: texture_2d<f32>;
+
+        @fragment
+        fn fs(@location(0) uv: vec2<f32>) -> @location(0) vec4<f32> {
+            let base = textureSample(albedo, linear, uv) * params.tint * scale;
+            return base + textureSample(texture, linear, uv);
+        }
+
+        @group(2) @binding(0) var<storage, read_write> data: array<f32>;
+
+        @fragment
+        fn store() -> @location(0) vec4<f32> {
+            data[0] = 1.0;
+            return vec4<f32>(data[0]);
+        }
+    "#;
+
+    #[test]
+    fn bindings_are_reported_and_samplers_renamed() {
+        let options = WebGlOptions {
+            slots: vec![SlotOverride {
+                group: 0,
+                binding: 0,
+                slot: 1,
+            }],
+            ..Default::default()
+        };
+        let shader = compile_webgl(SHADER, Some("fs"), "fragment", &options).unwrap();
+        let glsl = shader.glsl.unwrap();
+        assert!(glsl.starts_with("#version 300 es"));
+        assert!(glsl.contains("uniform highp sampler2D albedo;"), "{glsl}");
+        // `texture` is GLSL's sampling function.
+        assert!(glsl.contains("uniform highp sampler2D texture_;"), "{glsl}");
+        assert!(!glsl.contains("_group_0_binding_1_fs"));
+
+        let blocks: Vec<_> = shader
+            .uniform_blocks
+            .iter()
+            .map(|b| (b.variable.as_str(), b.slot))
+            .collect();
+        assert_eq!(blocks, [("scale", 0), ("params", 1)]);
+        assert!(glsl.contains(&format!("uniform {} {{", shader.uniform_blocks[1].name)));
+        let samplers: Vec<_> = shader
+            .samplers
+            .iter()
+            .map(|s| (s.name.as_str(), s.sampler.as_deref(), s.unit))
+            .collect();
+        assert_eq!(
+            samplers,
+            [
+                ("albedo", Some("linear"), 0),
+                ("texture_", Some("linear"), 1)
+            ]
+        );
+
+        let options = WebGlOptions {
+            sampler_naming: SamplerNaming::TextureSampler,
+            ..Default::default()
+        };
+        let shader = compile_webgl(SHADER, Some("fs"), "fragment", &options).unwrap();
+        assert_eq!(shader.samplers[0].name, "albedo_linear");
+        let options = WebGlOptions {
+            sampler_naming: SamplerNaming::Binding,
+            ..Default::default()
+        };
+        let shader = compile_webgl(SHADER, Some("fs"), "fragment", &options).unwrap();
+        assert_eq!(shader.samplers[0].name, "_group_0_binding_1_fs");
+    }
+
+    #[test]
+    fn features_es_300_lacks_are_reported() {
+        let shader =
+            compile_webgl(SHADER, Some("store"), "fragment", &WebGlOptions::default()).unwrap();
+        assert!(shader.glsl.is_none());
+        let features: Vec<_> = shader
+            .unsupported
+            .iter()
+            .map(|f| f.feature.as_str())
+            .collect();
+        assert_eq!(features, ["BUFFER_STORAGE", "DYNAMIC_ARRAY_SIZE"]);
+        assert_eq!(
+            shader.unsupported[0].description,
+            "storage buffers (`var<storage>`)"
+        );
+
+        let compute = "@compute @workgroup_size(1) fn cs() {}";
+        let shader = compile_webgl(compute, None, "compute", &WebGlOptions::default()).unwrap();
+        assert_eq!(shader.unsupported[0].feature, "COMPUTE_SHADER");
+    }
+}